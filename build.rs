@@ -0,0 +1,22 @@
+fn main() {
+    #[cfg(feature = "capi")]
+    generate_c_header();
+}
+
+/// Write the C API's header to `$OUT_DIR/funscriptvideo.h`. The source tree is left alone; the copy in `include/` is checked
+/// against it by the capi tests.
+#[cfg(feature = "capi")]
+fn generate_c_header() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is set by cargo");
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).expect("cbindgen.toml is valid");
+    // Only the C API module is parsed, so public items elsewhere in the crate don't leak into the header
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(format!("{}/src/capi.rs", crate_dir))
+        .generate()
+        .expect("C header generation failed")
+        .write_to_file(format!("{}/funscriptvideo.h", out_dir));
+}
@@ -0,0 +1,105 @@
+#ifndef FUNSCRIPTVIDEO_H
+#define FUNSCRIPTVIDEO_H
+
+/* Generated by cbindgen from src/capi.rs when building with the `capi` feature. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * An open FSV archive.
+ */
+typedef struct FsvArchive FsvArchive;
+
+/**
+ * Receives the bytes of an entry streamed by `fsv_entry_read`, in order. A nonzero return value stops the stream.
+ */
+typedef int (*FsvWriteCallback)(void *user_data, const uint8_t *data, size_t len);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Message for the last failed call on this thread, or NULL if there was none. Valid until the next failing call on this thread.
+ */
+const char *fsv_last_error(void);
+
+/**
+ * Open the FSV at `path` (UTF-8). Returns NULL on failure, including when an entry name contains a NUL byte. Close it with `fsv_close`.
+ *
+ * # Safety
+ * `path` must be a valid NUL-terminated string.
+ */
+struct FsvArchive *fsv_open(const char *path);
+
+/**
+ * Close an archive opened with `fsv_open`. NULL is ignored.
+ *
+ * # Safety
+ * `archive` must be NULL or returned by `fsv_open`, and not used afterwards.
+ */
+void fsv_close(struct FsvArchive *archive);
+
+/**
+ * Number of entries in the archive, including `metadata.json`.
+ *
+ * # Safety
+ * `archive` must be returned by `fsv_open`.
+ */
+size_t fsv_entry_count(const struct FsvArchive *archive);
+
+/**
+ * Name of the entry at `index`, or NULL if out of range. Owned by the archive.
+ *
+ * # Safety
+ * `archive` must be returned by `fsv_open`.
+ */
+const char *fsv_entry_name(const struct FsvArchive *archive, size_t index);
+
+/**
+ * Store the uncompressed size of the entry `name` in `size`.
+ *
+ * # Safety
+ * `archive` must be returned by `fsv_open`, `name` must be a valid NUL-terminated string and `size` a valid pointer.
+ */
+int fsv_entry_size(struct FsvArchive *archive,
+                   const char *name,
+                   uint64_t *size);
+
+/**
+ * Metadata of the archive as a JSON string. Free it with `fsv_string_free`.
+ *
+ * # Safety
+ * `archive` must be returned by `fsv_open`.
+ */
+char *fsv_metadata_json(struct FsvArchive *archive);
+
+/**
+ * Free a string returned by this library. NULL is ignored.
+ *
+ * # Safety
+ * `s` must be NULL or returned by `fsv_metadata_json`, and not used afterwards.
+ */
+void fsv_string_free(char *s);
+
+/**
+ * Stream the uncompressed bytes of the entry `name` to `callback` in chunks, without buffering the entry in memory.
+ *
+ * # Safety
+ * `archive` must be returned by `fsv_open` and `name` must be a valid NUL-terminated string.
+ * `user_data` is passed to `callback` as-is.
+ */
+int fsv_entry_read(struct FsvArchive *archive,
+                   const char *name,
+                   FsvWriteCallback callback,
+                   void *user_data);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FUNSCRIPTVIDEO_H */
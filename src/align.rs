@@ -0,0 +1,243 @@
+use std::{io::{BufRead, BufReader, Read}, path::Path, process::{Command, Stdio}};
+
+use clap::ValueEnum;
+use tracing::info;
+
+use crate::{funscript::Funscript, transcode::TranscodeError};
+
+/// Signals are compared in bins of this many milliseconds, which is also the resolution of the estimate.
+pub const ALIGN_BIN_MS: u64 = 100;
+pub const DEFAULT_MAX_OFFSET_MS: u64 = 60_000;
+/// Correlation below which an estimate is more likely noise than a match
+pub const WEAK_MATCH_SCORE: f64 = 0.2;
+/// ffmpeg scene score above which a frame counts as a cut
+const SCENE_CUT_THRESHOLD: f64 = 0.3;
+/// Audio is decoded as mono at this rate; enough for loudness, cheap to stream
+const AUDIO_SAMPLE_RATE: u64 = 4000;
+
+/// What the script is matched against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum AlignSignal {
+    /// Audio loudness against script intensity
+    #[default]
+    Audio,
+    /// Scene cuts against changes in script intensity
+    SceneCuts,
+}
+
+impl AlignSignal {
+    pub fn get_name(&self) -> &str {
+        match self {
+            AlignSignal::Audio => "audio",
+            AlignSignal::SceneCuts => "scene-cuts",
+        }
+    }
+}
+
+/// Estimated `start_offset` (script time minus video time) and the correlation it was found with (-1.0..=1.0).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlignEstimate {
+    pub offset_ms: i64,
+    pub score: f64,
+}
+
+/// Distance travelled per bin, spreading each move over the bins it spans.
+pub fn script_intensity(funscript: &Funscript, bins: usize) -> Vec<f64> {
+    let mut intensity = vec![0.0; bins];
+    let mut actions: Vec<_> = funscript.actions.iter().collect();
+    actions.sort_by_key(|action| action.at);
+    for pair in actions.windows(2) {
+        let (start, end) = (pair[0].at, pair[1].at);
+        if end <= start {
+            continue;
+        }
+
+        let speed = pair[1].pos.abs_diff(pair[0].pos) as f64 / (end - start) as f64;
+        let mut time = start;
+        while time < end {
+            let bin = (time / ALIGN_BIN_MS) as usize;
+            let bin_end = ((bin as u64 + 1) * ALIGN_BIN_MS).min(end);
+            if let Some(value) = intensity.get_mut(bin) {
+                *value += speed * (bin_end - time) as f64;
+            }
+            time = bin_end;
+        }
+    }
+
+    intensity
+}
+
+/// Rises in a signal: where activity picks up, which is where scene cuts tend to land.
+pub fn onsets(signal: &[f64]) -> Vec<f64> {
+    let mut onsets = vec![0.0; signal.len()];
+    for i in 1..signal.len() {
+        onsets[i] = (signal[i] - signal[i - 1]).max(0.0);
+    }
+
+    onsets
+}
+
+/// Cut times (ms) as impulses, one per bin.
+pub fn cut_signal(cuts: &[u64], bins: usize) -> Vec<f64> {
+    let mut signal = vec![0.0; bins];
+    for cut in cuts {
+        if let Some(value) = signal.get_mut((cut / ALIGN_BIN_MS) as usize) {
+            *value = 1.0;
+        }
+    }
+
+    signal
+}
+
+fn standardize(signal: &[f64]) -> Option<Vec<f64>> {
+    if signal.is_empty() {
+        return None;
+    }
+
+    let mean = signal.iter().sum::<f64>() / signal.len() as f64;
+    let variance = signal.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / signal.len() as f64;
+    if variance <= f64::EPSILON {
+        return None;
+    }
+
+    let deviation = variance.sqrt();
+    Some(signal.iter().map(|value| (value - mean) / deviation).collect())
+}
+
+/// The lag (in bins, script relative to video) within `max_lag` that correlates best. Lags that leave less than half of the
+/// shorter signal overlapping are not considered. `None` if either signal is flat.
+pub fn best_offset(video: &[f64], script: &[f64], max_lag: usize) -> Option<AlignEstimate> {
+    let video = standardize(video)?;
+    let script = standardize(script)?;
+    let min_overlap = video.len().min(script.len()).div_ceil(2);
+    let max_lag = max_lag as i64;
+
+    let mut best: Option<AlignEstimate> = None;
+    for lag in -max_lag..=max_lag {
+        let first = 0.max(-lag) as usize;
+        let last = (video.len() as i64).min(script.len() as i64 - lag);
+        if last - (first as i64) < min_overlap as i64 {
+            continue;
+        }
+
+        let overlap = (first..last as usize).map(|i| video[i] * script[(i as i64 + lag) as usize]);
+        let score = overlap.sum::<f64>() / (last as usize - first) as f64;
+        if best.is_none_or(|best| score > best.score) {
+            best = Some(AlignEstimate { offset_ms: lag * ALIGN_BIN_MS as i64, score });
+        }
+    }
+
+    best
+}
+
+/// Loudness (RMS) per bin of the video's first audio stream, decoded by `ffmpeg`.
+pub fn audio_energy(video: &Path) -> Result<Vec<f64>, TranscodeError> {
+    let mut child = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-i"])
+        .arg(video)
+        .args(["-vn", "-ac", "1", "-ar", &AUDIO_SAMPLE_RATE.to_string(), "-f", "s16le", "-"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let samples_per_bin = (AUDIO_SAMPLE_RATE * ALIGN_BIN_MS / 1000) as usize;
+    let mut energy = Vec::new();
+    let (mut sum, mut count) = (0.0, 0);
+    let mut reader = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let mut sample = [0u8; 2];
+    while reader.read_exact(&mut sample).is_ok() {
+        let value = i16::from_le_bytes(sample) as f64 / i16::MAX as f64;
+        sum += value * value;
+        count += 1;
+        if count == samples_per_bin {
+            energy.push((sum / count as f64).sqrt());
+            (sum, count) = (0.0, 0);
+        }
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(TranscodeError::Ffmpeg(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+
+    Ok(energy)
+}
+
+/// Timestamps (ms) from ffmpeg `showinfo` output lines, e.g. `[Parsed_showinfo_1 @ 0x..] n:3 pts:... pts_time:12.345 ...`
+fn parse_showinfo_times(stderr: impl BufRead) -> Vec<u64> {
+    stderr.lines()
+        .map_while(Result::ok)
+        .filter(|line| line.contains("showinfo"))
+        .filter_map(|line| {
+            let time = line.split_whitespace().find_map(|field| field.strip_prefix("pts_time:"))?;
+            time.parse::<f64>().ok().map(|seconds| (seconds * 1000.0).round() as u64)
+        })
+        .collect()
+}
+
+/// Scene cut timestamps (ms) detected by `ffmpeg`'s scene score.
+pub fn scene_cuts(video: &Path) -> Result<Vec<u64>, TranscodeError> {
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i"])
+        .arg(video)
+        .args(["-an", "-vf", &format!("select='gt(scene,{})',showinfo", SCENE_CUT_THRESHOLD), "-f", "null", "-"])
+        .output()?;
+    if !output.status.success() {
+        return Err(TranscodeError::Ffmpeg(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+
+    Ok(parse_showinfo_times(output.stderr.as_slice()))
+}
+
+/// Estimate the `start_offset` of `funscript` against the video at `video` by cross-correlation, searching up to `max_offset_ms` either way.
+/// Requires ffmpeg to be installed and on PATH. `None` if a signal carries no information (silent audio, no cuts, no movement).
+pub fn estimate_offset(video: &Path, funscript: &Funscript, signal: AlignSignal, max_offset_ms: u64) -> Result<Option<AlignEstimate>, TranscodeError> {
+    info!(operation = "align", item = %video.display(), signal = signal.get_name(), outcome = "started", "Measuring video signal");
+    let script_bins = funscript.actions.iter().map(|action| action.at).max().map_or(0, |last| (last / ALIGN_BIN_MS) as usize + 1);
+    let video_signal = match signal {
+        AlignSignal::Audio => audio_energy(video)?,
+        AlignSignal::SceneCuts => {
+            // The cut list ends at the last cut, not the end of the video; assume the video runs at least as long as the script
+            let cuts = scene_cuts(video)?;
+            let bins = cuts.iter().max().map_or(0, |last| (last / ALIGN_BIN_MS) as usize + 1);
+            cut_signal(&cuts, bins.max(script_bins))
+        },
+    };
+
+    let intensity = script_intensity(funscript, script_bins.max(video_signal.len()));
+    let script_signal = match signal {
+        AlignSignal::Audio => intensity,
+        AlignSignal::SceneCuts => onsets(&intensity),
+    };
+
+    Ok(best_offset(&video_signal, &script_signal, (max_offset_ms / ALIGN_BIN_MS) as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_signals() {
+        // Pseudo-random activity, with the script running 1.2 s (12 bins) ahead of the video
+        let mut state = 7u64;
+        let video: Vec<f64> = (0..600).map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) as f64 / (1u64 << 31) as f64
+        }).collect();
+        let mut script = vec![0.5; 12];
+        script.extend_from_slice(&video[..588]);
+
+        let estimate = best_offset(&video, &script, 50).unwrap();
+        assert_eq!(estimate.offset_ms, 1200);
+        assert!(estimate.score > 0.9);
+        assert!(best_offset(&video, &[1.0; 600], 50).is_none());
+
+        let showinfo = "[Parsed_showinfo_1 @ 0x1] n:   0 pts:  12800 pts_time:1.28 duration:512\nframe=1\n[Parsed_showinfo_1 @ 0x1] n:   1 pts: 64000 pts_time:6.4\n";
+        assert_eq!(parse_showinfo_times(showinfo.as_bytes()), [1280, 6400]);
+
+        let funscript: Funscript = serde_json::from_str(r#"{ "actions": [{ "at": 50, "pos": 0 }, { "at": 250, "pos": 100 }], "inverted": false, "range": 100, "version": "1.0" }"#).unwrap();
+        assert_eq!(script_intensity(&funscript, 4), [25.0, 50.0, 25.0, 0.0]);
+        assert_eq!(onsets(&[0.0, 2.0, 1.0, 3.0]), [0.0, 2.0, 0.0, 2.0]);
+    }
+}
@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{db_client::{DbClient, DbClientError}, fsv::FsvError, journal::{self, DbWrite, Journal, JournalError, JournalOperation}, library};
+
+#[derive(Debug, Error)]
+pub enum ArchiveTxError {
+    #[error("Journal error: {0}")]
+    Journal(#[from] JournalError),
+    #[error("Database client error: {0}")]
+    DbClient(#[from] DbClientError),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+}
+
+/// Keeps a change to an archive and the database writes that go with it together. The writes are journaled next to the
+/// archive before it is touched, and applied in one database transaction only once it has been replaced, so a failed
+/// archive change writes nothing. If the database fails after the archive was replaced, the writes stay pending in the
+/// journal until `recover` applies them with `apply_pending`.
+#[derive(Debug)]
+pub struct ArchiveTransaction {
+    journal: Journal,
+}
+
+impl ArchiveTransaction {
+    /// Record `writes` for the change about to be made to the archive at `target`. Fails with `JournalError::Pending`
+    /// while writes of an earlier change to it are still pending.
+    pub fn begin(target: &Path, writes: Vec<DbWrite>) -> Result<ArchiveTransaction, JournalError> {
+        let journal = Journal::begin(target, JournalOperation::DbWrites { writes, replaced: false })?;
+        Ok(ArchiveTransaction { journal })
+    }
+
+    /// The archive change failed; nothing is written.
+    pub fn abort(self) -> Result<(), JournalError> {
+        self.journal.abort()?;
+        Ok(())
+    }
+
+    /// The archive has been replaced (or didn't need to be); apply the writes.
+    pub async fn commit(mut self, db_client: &DbClient) -> Result<(), ArchiveTxError> {
+        self.journal.update(|operation| if let JournalOperation::DbWrites { replaced, .. } = operation {
+            *replaced = true;
+        })?;
+        complete(self.journal, db_client).await
+    }
+}
+
+/// Apply the database writes left pending by an interrupted or failed change to the archive at `target`, as reported by
+/// `journal::recover_dir`. Returns false if there are none, or their operation is still running.
+pub async fn apply_pending(target: &Path, db_client: &DbClient) -> Result<bool, ArchiveTxError> {
+    let path = journal::journal_path(target, "db");
+    match Journal::reopen(&path)? {
+        Some(journal) => complete(journal, db_client).await.map(|()| true),
+        None => Ok(false),
+    }
+}
+
+/// Apply the writes of `journal` in one transaction and remove it. If that fails the journal stays, pending.
+async fn complete(journal: Journal, db_client: &DbClient) -> Result<(), ArchiveTxError> {
+    let record = journal.record();
+    let JournalOperation::DbWrites { writes, .. } = &record.operation else {
+        return Ok(journal.finish()?);
+    };
+
+    if let Err(err) = apply(&record.target, writes, db_client).await {
+        warn!(operation = "db", archive = %record.target.display(), error = %err, "Database changes are pending until `recover` applies them");
+        return Err(err);
+    }
+
+    Ok(journal.finish()?)
+}
+
+async fn apply(target: &Path, writes: &[DbWrite], db_client: &DbClient) -> Result<(), ArchiveTxError> {
+    // The archive is read first, the transaction holds a connection. One that is gone since has nothing left to index
+    let index = writes.contains(&DbWrite::IndexWork) && target.exists();
+    let work = match index {
+        true => Some(library::library_work(target)?),
+        false => None,
+    };
+
+    let mut tx = db_client.begin().await?;
+    for write in writes {
+        if let DbWrite::SaveCreator { key, name, socials } = write {
+            tx.save_creator_info(key, name, socials).await?;
+        }
+    }
+    if let Some(work) = &work {
+        tx.upsert_library_work(work).await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_archive_transaction() {
+        let dir = std::env::temp_dir().join(format!("fsv-archive-tx-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("scene.fsv");
+        let db_client = DbClient::in_memory().await.unwrap();
+        let save = |name: &str| DbWrite::SaveCreator { key: "creator".to_string(), name: name.to_string(), socials: vec!["https://twitter.com/Creator".to_string()] };
+
+        // Nothing is written for a change that failed
+        ArchiveTransaction::begin(&target, vec![save("Aborted")]).unwrap().abort().unwrap();
+        assert!(db_client.get_creator_info_by_key("creator").await.unwrap().is_none());
+
+        // The writes of a replaced archive are applied together; saving again replaces the record
+        ArchiveTransaction::begin(&target, vec![save("Creator")]).unwrap().commit(&db_client).await.unwrap();
+        ArchiveTransaction::begin(&target, vec![save("Renamed")]).unwrap().commit(&db_client).await.unwrap();
+        let creator = db_client.get_creator_info_by_key("creator").await.unwrap().unwrap();
+        assert_eq!((creator.name.as_str(), creator.socials.as_slice()), ("Renamed", ["https://x.com/creator".to_string()].as_slice()));
+
+        // Writes that fail after the archive was replaced stay pending, blocking further changes until applied
+        let broken = DbClient::in_memory().await.unwrap();
+        broken.pool.close().await;
+        assert!(ArchiveTransaction::begin(&target, vec![save("Pending")]).unwrap().commit(&broken).await.is_err());
+        let recoveries = journal::recover_dir(&dir).unwrap();
+        assert_eq!(recoveries.iter().map(|recovery| recovery.outcome).collect::<Vec<_>>(), [journal::RecoveryOutcome::Pending]);
+        assert!(matches!(ArchiveTransaction::begin(&target, vec![]), Err(JournalError::Pending(_))));
+        assert!(apply_pending(&target, &db_client).await.unwrap());
+        assert_eq!(db_client.get_creator_info_by_key("creator").await.unwrap().unwrap().name, "Pending");
+        assert!(!apply_pending(&target, &db_client).await.unwrap());
+        assert!(journal::recover_dir(&dir).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
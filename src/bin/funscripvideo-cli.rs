@@ -1,14 +1,15 @@
-use std::{path::PathBuf, process::ExitCode, result};
+use std::{cell::OnceCell, io::Write, path::{Path, PathBuf}, process::ExitCode, time::Duration};
 
-use clap::{ArgAction, Parser, Subcommand, ValueEnum};
-use tracing::{error, info, level_filters::LevelFilter, warn};
+use clap::{ArgAction, Args as ClapArgs, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use tracing::{Span, debug, error, info, info_span, level_filters::LevelFilter, warn};
 use tracing_appender::{non_blocking::WorkerGuard, rolling};
-use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{EnvFilter, Layer, Registry, fmt::MakeWriter, layer::SubscriberExt, util::SubscriberInitExt};
 
-use FunScriptVideo::{db_client::DbClient, fsv::{self, AddArgs, EntryType, ItemType}};
+use FunScriptVideo::{align::AlignSignal, checksum::HashAlgorithm, duration::DurationMs, config::{Config, CONFIG_FILE_NAME}, convert::ScriptFormat, error_context::{ErrorContext, IoContext}, funscript::transform::TransformOptions, hash_cache::EntryHashCache, hooks::{HookContext, HookOperation, HooksConfig}, jobs::{JobScheduler, RetryPolicy}, notify::Notifier, journal::RecoveryOutcome, library::{ScanOptions, VerifyStatus}, open::PlayerConfig, path_safety::PathSafety, snapshot::SnapshotStatus, package::PackageOptions, policy::ContentPolicy, transcode::TranscodeProfile, db_client::{CreatorRecord, DbClient, DbClientError, LibraryFilter, StatsCount, StatsSize, UsageGrouping, UsageRecord}, exit_code::{FsvExitCode, ToExitCode}, fsv::{compression_ratio, AddArgs, AddConflict, AlignOptions, ArchiveCompression, CreateArgs, CreatorSyncDirection, EntryType, FsvEditError, ExtractOnly, ExtractOptions, FsvError, FsvInfo, FsvValidationError, InfoOptions, IssueSeverity, ItemType, NameMatching, ParseMode, PreviewSelection, RebuildOptions, ValidateOptions, ValidationReport, DEFAULT_DURATION_TOLERANCE_MS}, notes::DEFAULT_NOTES_NAME, preview::DEFAULT_PREVIEW_NAME, progress::{EventBroadcaster, ProgressListener, ProgressLog}, simplify::SimplifyOptions, watch::WatchArgs};
 
 #[derive(Parser, Debug)]
-#[command(version = "v1.0.0", about = "FunscriptVideo CLI Utility", long_about = None, group(
+#[command(name = "funscripvideo-cli", version = "v1.0.0", about = "FunscriptVideo CLI Utility", long_about = None, group(
     clap::ArgGroup::new("logging")
         .args(&["verbosity", "quiet", "silent"])
         .multiple(false)
@@ -17,6 +18,8 @@ use FunScriptVideo::{db_client::DbClient, fsv::{self, AddArgs, EntryType, ItemTy
 struct Args {
     #[arg(short, long, global = true, default_value = "stdout", help = "Logging mode: none, stdout, file, both")]
     log_mode: LogMode,
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text, help = "Log format: text, or json for one JSON object per line (for log aggregators)")]
+    log_format: LogFormat,
     #[arg(
         short = 'v',
         long = "verbose",
@@ -43,6 +46,13 @@ struct Args {
     /// Run in non-interactive mode (disable all user prompts)
     #[arg(long, global = true, help = "Disable interactive prompts (for scripting or CI)")]
     non_interactive: bool,
+    #[arg(
+        long,
+        global = true,
+        value_name = "URL",
+        help = "Database to use instead of funscripvideo.db next to the executable: a sqlite: URL, or a postgres:// URL with the postgres feature"
+    )]
+    database: Option<String>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -51,8 +61,51 @@ struct Args {
 enum Commands {
     /// Validate a FunscriptVideo file
     Validate {
-        #[arg(help = "Path to the FunscriptVideo file to validate")]
+        #[arg(help = "Path to the FunscriptVideo file to validate (or its http(s):// or s3:// URL with the http or s3 feature)")]
         path: PathBuf,
+        #[arg(long, help = "Print the full list of errors and warnings instead of logging them")]
+        report: bool,
+        #[arg(long, value_enum, default_value_t = NameMatching::Strict, help = "How metadata file names are matched against archive entries (normalized ignores case and path separators)")]
+        name_matching: NameMatching,
+        #[arg(long, value_enum, default_value_t = ParseMode::Lenient, help = "How metadata fields the format doesn't define are reported (strict makes them errors, to catch typos like 'video_fromats')")]
+        parse_mode: ParseMode,
+        #[arg(long, help = "Also check each script against the checksum and duration in the metadata, and each video's duration with ffprobe (reads every entry in full)")]
+        deep: bool,
+        #[arg(long, value_name = "MS", default_value_t = DEFAULT_DURATION_TOLERANCE_MS, requires = "deep", help = "How far a duration measured by --deep may drift from the metadata's before it is reported")]
+        duration_tolerance: u64,
+    },
+    /// Validate every FunscriptVideo file in a directory and its subdirectories in parallel, and write a summary report
+    VerifyLibrary {
+        #[arg(help = "Directory to verify")]
+        dir: PathBuf,
+        #[arg(long, value_name = "PATH", help = "Write the results (valid / incomplete / invalid with reasons) to this file")]
+        report: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = ReportFormat::Json, help = "Format of the report")]
+        format: ReportFormat,
+        #[command(flatten)]
+        jobs: JobArgs,
+        #[arg(long, value_enum, default_value_t = NameMatching::Strict, help = "How metadata file names are matched against archive entries (normalized ignores case and path separators)")]
+        name_matching: NameMatching,
+    },
+    /// Record the hash of every FunscriptVideo file in a directory and of each of its entries, to detect bit-rot or tampering later
+    Snapshot {
+        #[arg(help = "Directory to snapshot")]
+        dir: PathBuf,
+        #[arg(long = "hash-algo", value_enum, default_value_t = HashAlgorithm::Sha256, help = "Hash algorithm (xxh3 is fast but not cryptographic, so it can't reveal tampering)")]
+        hash_algo: HashAlgorithm,
+        #[arg(long, help = "Drop the records of files that are gone")]
+        prune: bool,
+        #[command(flatten)]
+        jobs: JobArgs,
+    },
+    /// Compare the FunscriptVideo files in a directory with their snapshot, reporting changed, corrupted, missing and new files
+    VerifySnapshot {
+        #[arg(help = "Directory to verify")]
+        dir: PathBuf,
+        #[arg(long, value_name = "PATH", help = "Write the results as JSON to this file")]
+        report: Option<PathBuf>,
+        #[command(flatten)]
+        jobs: JobArgs,
     },
     /// Create a new FunscriptVideo file
     Create {
@@ -66,29 +119,63 @@ enum Commands {
         video: Option<PathBuf>,
         #[arg(long, help = "Optional video creator key")]
         video_creator_key: Option<String>,
+        #[arg(long, requires = "video", help = "Description of the video format")]
+        video_description: Option<String>,
         #[arg(long, help = "Optional script file to include")]
         script: Option<PathBuf>,
         #[arg(long, help = "Optional script creator key")]
         script_creator_key: Option<String>,
+        #[arg(long, requires = "script", help = "Description of the script variant")]
+        script_description: Option<String>,
+        #[arg(long = "performer", value_name = "NAME", help = "Performer appearing in the video (repeatable)")]
+        performers: Vec<String>,
+        #[arg(long, help = "Studio that produced the video")]
+        studio: Option<String>,
+        #[arg(long, requires = "script", help = "Fill in title, tags, performers and script creator from the script's embedded metadata (new creators are saved to the database)")]
+        from_script_metadata: bool,
+        #[arg(long, help = "Produce byte-identical output for identical inputs (fixed timestamps, canonical entry and key order)")]
+        reproducible: bool,
+        #[arg(long, value_enum, help = "Generate an additional video format with an ffmpeg profile (repeatable, requires ffmpeg)")]
+        transcode: Vec<TranscodeProfile>,
+        #[arg(long = "hash-algo", value_enum, help = "Checksum algorithm for added files, sha256 by default (xxh3 is fast but not cryptographic)")]
+        hash_algo: Option<HashAlgorithm>,
+        #[arg(long, value_enum, help = "Compression for the archive entries, bzip2 by default")]
+        compression: Option<ArchiveCompression>,
+        #[arg(long, value_name = "NAME", help = "Start from a creation template in funscripvideo.json (next to the database); other options override it and tags are added to its tags. With a naming pattern, PATH may be a directory")]
+        template: Option<String>,
     },
     /// Add an entry to a FunscriptVideo file
     #[command(subcommand)]
     Add(AddCommands),
+    /// Show a document stored in a FunscriptVideo file
+    #[command(subcommand)]
+    Show(ShowCommands),
     /// Remove an entry from a FunscriptVideo file
     Remove {
         #[arg(help = "Path to the FunscriptVideo file to modify")]
         path: PathBuf,
         #[arg(help = "Type of entry to remove")]
         entry_type: EntryType,
-        #[arg(help = "Identifier of the entry to remove (key for creator_info, filename for video/script/subtitle)")]
+        #[arg(help = "Identifier of the entry to remove (key for creator_info, filename for video/script/subtitle). Removing a script also removes its axis scripts")]
         entry_id: String,
         // TODO: Figure out how to cleanly add this option to the cli
         // #[arg()]
         // db: bool,
     },
+    /// Restore entries removed from a FunscriptVideo file, newest removal first (the last 10 removals are kept in a .trash file next to it)
+    Undo {
+        #[arg(help = "Path to the FunscriptVideo file to restore entries to")]
+        path: PathBuf,
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..), help = "Number of removals to undo")]
+        steps: u32,
+        #[arg(long, help = "Undo even if the metadata changed since the removal (those changes are lost)")]
+        force: bool,
+        #[arg(long, conflicts_with_all = ["steps", "force"], help = "List the removals that can be undone instead, newest first")]
+        list: bool,
+    },
     /// Extract contents from a FunscriptVideo file
     Extract {
-        #[arg(help = "Path to the FunscriptVideo file to extract from")]
+        #[arg(help = "Path to the FunscriptVideo file to extract from (or its s3:// URL with the s3 feature)")]
         path: PathBuf,
         #[arg(
             short,
@@ -97,19 +184,610 @@ enum Commands {
             help = "Destination directory for extracted files. The extractor will create a new subdirectory named after the FSV file stem (e.g., 'foo.fsv' -> '<output_dir>/foo/')."
         )]
         output_dir: PathBuf,
+        #[arg(long, value_enum, default_value_t = NameMatching::Strict, help = "How metadata file names are matched against archive entries (normalized ignores case and path separators)")]
+        name_matching: NameMatching,
+        #[arg(long, value_enum, help = "Extract only this kind of entry instead of video/script pairs")]
+        only: Option<ExtractOnly>,
+        #[arg(long = "subtitle-lang", value_delimiter = ',', help = "Comma separated languages of the subtitle tracks to write next to each pair (e.g. 'en,ja'), instead of every track")]
+        subtitle_languages: Vec<String>,
+        #[arg(long, value_enum, default_value_t = PathSafety::Sanitize, help = "What to do with names Windows can't store (reserved device names, trailing dots or spaces, paths over 260 characters)")]
+        path_safety: PathSafety,
+        #[arg(long, conflicts_with_all = ["only", "entry"], help = "Shift each extracted script by its variant's start_offset (or the pair's offset), and each subtitle by its offset against the video, so players that ignore the metadata stay in sync")]
+        apply_offset: bool,
+        #[arg(long, requires = "stdout", conflicts_with = "only", help = "Extract only this entry, matched against archive entries as --name-matching says")]
+        entry: Option<String>,
+        #[arg(long, requires = "entry", help = "Write the entry's bytes to stdout instead of a file, e.g. to pipe a video into mpv or a script into jq (logs go to stderr)")]
+        stdout: bool,
     },
     /// Display information about a FunscriptVideo file
     Info {
-        #[arg(help = "Path to the FunscriptVideo file to display info for")]
+        #[arg(help = "Path to the FunscriptVideo file to display info for (or its http(s):// or s3:// URL with the http or s3 feature)")]
         path: PathBuf,
+        #[arg(long, value_enum, default_value_t = NameMatching::Strict, help = "How metadata file names are matched against archive entries (normalized ignores case and path separators)")]
+        name_matching: NameMatching,
+        #[arg(long, help = "Include format version, tags, creators, durations, sizes, and checksum status (reads every entry)")]
+        full: bool,
+        #[arg(long, help = "Include the compressed and uncompressed size of every entry and the archive totals")]
+        sizes: bool,
+        #[arg(long, help = "Print the info as JSON")]
+        json: bool,
     },
     /// Rebuild a FunscriptVideo file
     Rebuild {
         #[arg(help = "Path to the FunscriptVideo file to rebuild")]
         path: PathBuf,
+        #[arg(long, help = "Also collapse duplicate video, script, and subtitle entries in the metadata (the first one is kept)")]
+        fix_duplicates: bool,
+        #[arg(long, help = "Refuse to rebuild if it would discard local entries missing from the central directory or shadowed by a duplicate")]
+        strict: bool,
+        #[arg(long, requires = "strict", help = "With --strict, confirm that the unknown data can be discarded")]
+        discard_unknown: bool,
+    },
+    /// Complete or roll back operations (rebuilds, imports) that were interrupted by a crash, using the journals they left behind
+    Recover {
+        #[arg(help = "Directory to search for journals, including its subdirectories")]
+        dir: PathBuf,
+    },
+    /// Print BLAKE3 content hashes of a FunscriptVideo file's entries and their root hash
+    Hash {
+        #[arg(help = "Path to the FunscriptVideo file to hash")]
+        path: PathBuf,
+        #[arg(long, conflicts_with = "verify", help = "Store the hashes in the metadata (fsv.content-hashes extension)")]
+        write: bool,
+        #[arg(long, help = "Verify the hashes stored in the metadata against the archive contents")]
+        verify: bool,
+    },
+    /// Show the change history of a FunscriptVideo file (fsv.history extension), oldest change first
+    History {
+        #[arg(help = "Path to the FunscriptVideo file")]
+        path: PathBuf,
+        #[arg(long, help = "Start recording changes to the file in its metadata")]
+        enable: bool,
+    },
+    /// Rewrite a FunscriptVideo file's metadata.json in canonical form (sorted keys, stable ordering)
+    NormalizeMetadata {
+        #[arg(help = "Path to the FunscriptVideo file to normalize")]
+        path: PathBuf,
+    },
+    /// Write the raw metadata.json of a FunscriptVideo file to stdout
+    CatMetadata {
+        #[arg(help = "Path to the FunscriptVideo file to read the metadata of")]
+        path: PathBuf,
+    },
+    /// Replace the metadata.json of a FunscriptVideo file, if the archive still validates with the new one
+    PutMetadata {
+        #[arg(help = "Path to the FunscriptVideo file to replace the metadata of")]
+        path: PathBuf,
+        #[arg(help = "JSON file holding the new metadata ('-' reads it from stdin)")]
+        metadata: PathBuf,
+        #[arg(long, help = "Accept metadata that only leaves referenced content missing")]
+        allow_content_incomplete: bool,
+    },
+    /// Print the JSON Schema of metadata.json
+    MetadataSchema,
+    /// Check a standalone metadata.json document against the metadata JSON Schema, warning about unknown fields
+    ValidateMetadata {
+        #[arg(help = "Path to the metadata JSON document to check")]
+        path: PathBuf,
+        #[arg(long, help = "Print the errors and warnings as JSON")]
+        json: bool,
+    },
+    /// Compare the creator info embedded in a FunscriptVideo file with the database records it is linked to by key, and sync it
+    SyncCreators {
+        #[arg(help = "Path to the FunscriptVideo file to sync")]
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t = CreatorSyncDirection::Pull, help = "pull refreshes the file from the database, push stores the file's creators in the database")]
+        direction: CreatorSyncDirection,
+        #[arg(long, help = "Only report differences, change neither the file nor the database")]
+        dry_run: bool,
+    },
+    /// Cut a preview clip (a single segment or a montage) from a video in a FunscriptVideo file and store it in the archive (requires ffmpeg)
+    Preview {
+        #[arg(help = "Path to the FunscriptVideo file to modify")]
+        path: PathBuf,
+        #[arg(long, conflicts_with = "montage", help = "Start of the preview segment, in seconds [default: 0]")]
+        start: Option<f64>,
+        #[arg(long, help = "Length of the preview segment (or of each montage segment), in seconds [default: 10, or 3 with --montage]")]
+        duration: Option<f64>,
+        #[arg(long, value_name = "COUNT", help = "Build a montage of COUNT segments spread over the whole video")]
+        montage: Option<u32>,
+        #[arg(long, help = "Video format to cut from (defaults to the first video present in the archive)")]
+        source: Option<String>,
+        #[arg(long, default_value = DEFAULT_PREVIEW_NAME, help = "Archive entry name of the preview")]
+        name: String,
+    },
+    /// Estimate a script's start_offset against a video by cross-correlating script intensity with audio loudness or scene cuts (requires ffmpeg)
+    Align {
+        #[arg(help = "Path to the FunscriptVideo file to modify")]
+        path: PathBuf,
+        #[arg(long, help = "Script variant to align (defaults to the first one listed)")]
+        script: Option<String>,
+        #[arg(long, help = "Video format to align against (defaults to the first video present in the archive)")]
+        video: Option<String>,
+        #[arg(long, value_enum, default_value_t = AlignSignal::Audio, help = "Video signal the script is matched against")]
+        signal: AlignSignal,
+        #[arg(long, value_name = "SECONDS", default_value_t = 60.0, help = "Largest offset to consider, either way")]
+        max_offset: f64,
+        #[arg(long, help = "Only print the estimate, don't write it to the script variant")]
+        dry_run: bool,
+    },
+    /// Watch a drop folder and automatically import video+script pairs into FunscriptVideo files
+    Watch {
+        #[arg(help = "Folder to watch for new video and script files")]
+        drop_dir: PathBuf,
+        #[arg(short, long, default_value = ".", help = "Folder to write created FunscriptVideo files to")]
+        output_dir: PathBuf,
+        #[arg(short, long, help = "Folder to move imported originals to (default: <drop_dir>/imported)")]
+        archive_dir: Option<PathBuf>,
+        #[arg(long, default_value_t = 10, help = "Polling interval in seconds")]
+        interval: u64,
+        #[arg(long, help = "Process the folder once and exit instead of watching")]
+        once: bool,
+        #[cfg(feature = "serve")]
+        #[arg(long, value_name = "ADDR", help = "Publish import events as a WebSocket feed on ws://ADDR/api/events")]
+        events: Option<String>,
+        #[cfg(feature = "serve")]
+        #[arg(long, requires = "events", help = "Token event feed clients must send (default: $FSV_API_TOKEN, or a generated one printed to stderr)")]
+        token: Option<String>,
+    },
+    /// Maintain the library index of FunscriptVideo files kept in the local database
+    #[command(subcommand)]
+    Library(LibraryCommands),
+    /// List indexed FunscriptVideo files, optionally filtered
+    List {
+        #[arg(long, help = "Only works with this tag or a tag implying it (aliases are resolved)")]
+        tag: Option<String>,
+        #[arg(long, help = "Only works featuring this performer")]
+        performer: Option<String>,
+        #[arg(long, help = "Only works whose title (in any language), studio, or path matches (substring, or a SQL LIKE pattern if it contains %)")]
+        search: Option<String>,
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=5), help = "Only works rated at least this (1-5)")]
+        min_rating: Option<u8>,
+        #[arg(long, help = "Only works marked as favorite")]
+        favorites: bool,
+        #[arg(long, help = "Only works that were never played")]
+        unwatched: bool,
+    },
+    /// Rate a FunscriptVideo file 1-5 in the library index (the file itself is not modified)
+    Rate {
+        #[arg(help = "Path to the FunscriptVideo file to rate")]
+        path: PathBuf,
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=5), required_unless_present = "clear", help = "Rating (1-5)")]
+        rating: Option<u8>,
+        #[arg(long, conflicts_with = "rating", help = "Remove the rating")]
+        clear: bool,
+    },
+    /// Mark a FunscriptVideo file as a favorite in the library index (the file itself is not modified)
+    Favorite {
+        #[arg(help = "Path to the FunscriptVideo file to mark")]
+        path: PathBuf,
+        #[arg(long, help = "Unmark the file instead")]
+        off: bool,
+    },
+    /// Show statistics over the library index: archives, video hours, scripts per axis, top creators and tags, size per codec and resolution
+    Stats {
+        #[arg(help = "Only count works in this directory and its subdirectories (default: the whole library)")]
+        dir: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = StatsFormat::Table, help = "Output format")]
+        format: StatsFormat,
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..), help = "Number of top creators and tags to show")]
+        top: u32,
+    },
+    /// Edit the metadata of a FunscriptVideo file
+    #[command(subcommand)]
+    Edit(EditCommands),
+    /// Manage records stored in the local database
+    #[command(subcommand)]
+    Db(DbCommands),
+    /// Work with standalone script files
+    #[command(subcommand)]
+    Script(ScriptCommands),
+    /// Interactively browse a FunscriptVideo file or a directory of them
+    #[cfg(feature = "tui")]
+    Browse {
+        #[arg(help = "Path to a FunscriptVideo file or a directory containing FunscriptVideo files")]
+        path: PathBuf,
+        #[arg(short, long, default_value = ".", help = "Destination directory for extractions triggered from the browser")]
+        output_dir: PathBuf,
+    },
+    /// Extract a video and script from a FunscriptVideo file and play them with the configured player
+    Open {
+        #[arg(help = "Path to the FunscriptVideo file to open")]
+        path: PathBuf,
+        #[arg(long, help = "Video format to play (defaults to the first one present)")]
+        video: Option<String>,
+        #[arg(long, help = "Script variant to play (defaults to the first one present)")]
+        script: Option<String>,
+    },
+    /// Create a .torrent and a release manifest (title, tags, creators, hashes, sizes) for publishing a FunscriptVideo file
+    Package {
+        #[arg(help = "Path to the FunscriptVideo file to package")]
+        path: PathBuf,
+        #[arg(long = "tracker", value_name = "URL", help = "Tracker announce URL (can be repeated, each becomes its own tier)")]
+        trackers: Vec<String>,
+        #[arg(long, value_name = "KIB", help = "Piece size in KiB, a power of two of at least 16 (picked from the file size by default)")]
+        piece_size: Option<u64>,
+        #[arg(long, help = "Mark the torrent private, so peers are only found through the trackers")]
+        private: bool,
+        #[arg(short, long, help = "Directory for <name>.torrent and <name>.release.json (defaults to the directory of the file)")]
+        output_dir: Option<PathBuf>,
+    },
+    /// Associate the .fsv extension with this CLI for the current user, so file managers open FSVs with it and offer its info view
+    Register {
+        #[arg(long, help = "Only list the files that would be written and the commands that would be run")]
+        dry_run: bool,
+    },
+    /// Download the external videos of a stub FunscriptVideo file, verify them and store them in the archive
+    #[cfg(feature = "http")]
+    Fetch {
+        #[arg(help = "Path to the FunscriptVideo file to complete")]
+        path: PathBuf,
+        #[arg(long, help = "Only fetch this video format (defaults to every external one)")]
+        video: Option<String>,
+    },
+    /// Stream a script from a FunscriptVideo file to a Buttplug device through Intiface, or to an OSR serial port
+    #[cfg(feature = "play")]
+    Play {
+        #[arg(help = "Path to the FunscriptVideo file to play")]
+        path: PathBuf,
+        #[arg(long, help = "Script variant to play (defaults to the first one listed)")]
+        script: Option<String>,
+        #[arg(long, default_value = FunScriptVideo::play::DEFAULT_SERVER_URL, help = "Intiface/Buttplug server WebSocket URL")]
+        server: String,
+        #[arg(long, value_name = "PORT", conflicts_with = "server", help = "Drive an OSR-style stroker over this serial port with TCode instead of using Intiface")]
+        serial: Option<String>,
+        #[arg(long, default_value_t = FunScriptVideo::play::DEFAULT_BAUD_RATE, requires = "serial", help = "Serial port baud rate")]
+        baud: u32,
+        #[arg(long, value_name = "URL", conflicts_with_all = ["start_at", "mpv_socket"], help = "WebSocket sending the player's position (ms, or {\"position_ms\", \"playing\"}) to follow")]
+        timecode: Option<String>,
+        #[arg(long, value_name = "PATH", conflicts_with = "start_at", help = "Follow mpv through its JSON IPC socket (or named pipe), launching mpv if nothing is listening there")]
+        mpv_socket: Option<PathBuf>,
+        #[arg(long, help = "Video format the script is timed against, using its pair offset, and that mpv opens unless --media is given (defaults to the first one)")]
+        video: Option<String>,
+        #[arg(long, value_name = "PATH|URL", requires = "mpv_socket", help = "Open this file or URL in mpv instead of extracting the video and subtitles from the FSV")]
+        media: Option<String>,
+        #[arg(long, default_value_t = 0.0, help = "Start playing immediately from this video time, in seconds")]
+        start_at: f64,
+        #[arg(long, conflicts_with_all = ["start_at", "timecode", "mpv_socket"], help = "Start playing immediately from where the file was last stopped (see the library history)")]
+        resume: bool,
+    },
+    /// Serve the library index and FSV operations as a JSON HTTP API with bearer token authentication
+    #[cfg(feature = "serve")]
+    Serve {
+        #[arg(help = "Library directory to serve; request paths are relative to it")]
+        root: PathBuf,
+        #[arg(long, default_value = FunScriptVideo::serve::DEFAULT_BIND_ADDRESS, help = "Address to listen on")]
+        bind: String,
+        #[arg(long, help = "API token clients must send as 'Authorization: Bearer <token>' (default: $FSV_API_TOKEN, or a generated one printed to stderr)")]
+        token: Option<String>,
+    },
+    /// Print a shell completion script to stdout
+    Completions {
+        #[arg(help = "Shell to generate completions for")]
+        shell: Shell,
+    },
+    /// Generate manpages for the CLI and all of its subcommands
+    Manpages {
+        #[arg(short, long, default_value = ".", help = "Directory to write the manpages to")]
+        output_dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum EditCommands {
+    /// Set the title of a FunscriptVideo file, or its title in another language
+    Title {
+        #[arg(help = "Path to the FunscriptVideo file to modify")]
+        path: PathBuf,
+        #[arg(help = "New title (with --lang, an empty string removes the title in that language)")]
+        title: String,
+        #[arg(long, value_name = "LANG", help = "Set the title in this language (a BCP-47 tag, e.g. 'ja') instead of the primary title, stored with the fsv.localized-titles extension")]
+        lang: Option<String>,
+    },
+    /// Set the studio of a FunscriptVideo file (an empty string clears it)
+    Studio {
+        #[arg(help = "Path to the FunscriptVideo file to modify")]
+        path: PathBuf,
+        #[arg(help = "New studio")]
+        studio: String,
+    },
+    /// Set the description of a video format, script variant or subtitle track (an empty string clears it)
+    Description {
+        #[arg(help = "Path to the FunscriptVideo file to modify")]
+        path: PathBuf,
+        #[arg(help = "Type of the item to describe")]
+        item_type: ItemType,
+        #[arg(help = "Name of the video format, script variant or subtitle track")]
+        name: String,
+        #[arg(help = "New description")]
+        description: String,
+    },
+    /// Add or remove performers on a FunscriptVideo file
+    Performers {
+        #[arg(help = "Path to the FunscriptVideo file to modify")]
+        path: PathBuf,
+        #[arg(long, num_args = 1.., help = "Performers to add")]
+        add: Vec<String>,
+        #[arg(long, num_args = 1.., help = "Performers to remove")]
+        remove: Vec<String>,
+    },
+    /// Add or remove tags on a FunscriptVideo file (added tags are normalized against the tag vocabulary)
+    Tags {
+        #[arg(help = "Path to the FunscriptVideo file to modify")]
+        path: PathBuf,
+        #[arg(long, num_args = 1.., help = "Tags to add")]
+        add: Vec<String>,
+        #[arg(long, num_args = 1.., help = "Tags to remove")]
+        remove: Vec<String>,
+        #[arg(long, help = "Also add the tags implied by the resulting tags in the tag vocabulary (see 'db tag imply')")]
+        apply_implied: bool,
+    },
+    /// Remove creator records crediting a video, script or subtitle that is not in the metadata
+    PruneOrphanCreators {
+        #[arg(help = "Path to the FunscriptVideo file to modify")]
+        path: PathBuf,
+    },
+    /// Set or clear the start offset of a script against one video format, overriding the script's own start_offset for that pair
+    Offset {
+        #[arg(help = "Path to the FunscriptVideo file to modify")]
+        path: PathBuf,
+        #[arg(long, help = "Script variant of the pair")]
+        script: String,
+        #[arg(long, help = "Video format of the pair")]
+        video: String,
+        #[arg(allow_negative_numbers = true, required_unless_present = "clear", help = "Offset in ms (script time minus video time)")]
+        offset_ms: Option<i64>,
+        #[arg(long, conflicts_with = "offset_ms", help = "Remove the pair's offset, so the script's own start_offset applies again")]
+        clear: bool,
+    },
+    /// Set or clear the offset of a subtitle track against one video format, applied by extract --apply-offset
+    SubtitleOffset {
+        #[arg(help = "Path to the FunscriptVideo file to modify")]
+        path: PathBuf,
+        #[arg(long, help = "Subtitle track of the pair")]
+        subtitle: String,
+        #[arg(long, help = "Video format of the pair")]
+        video: String,
+        #[arg(allow_negative_numbers = true, required_unless_present = "clear", help = "Offset in ms (subtitle time minus video time)")]
+        offset_ms: Option<i64>,
+        #[arg(long, conflicts_with = "offset_ms", help = "Remove the pair's offset")]
+        clear: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum LibraryCommands {
+    /// Index the FunscriptVideo files in a directory and its subdirectories (unchanged files are skipped, vanished ones dropped)
+    Scan {
+        #[arg(help = "Directory to scan (or an s3://<bucket>/<prefix> URL with the s3 feature)")]
+        dir: PathBuf,
+        #[arg(long, help = "Continue an interrupted scan of the directory where it stopped")]
+        resume: bool,
+        #[command(flatten)]
+        jobs: JobArgs,
+    },
+    /// Show the disk usage of indexed works (file, compressed and uncompressed size), largest first
+    Du {
+        #[arg(help = "Only count works in this directory and its subdirectories (default: the whole library)")]
+        dir: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = UsageGrouping::Work, help = "Add sizes up per work, per tag or per creator (a work counts towards each of its tags and creators)")]
+        by: UsageGrouping,
+    },
+    /// Add and remove tags on every matching work, e.g. when a community renames a tag (only archives whose tags change are rewritten)
+    Retag {
+        #[arg(help = "Only retag works in this directory and its subdirectories, scanning it first (default: the whole library)")]
+        dir: Option<PathBuf>,
+        #[arg(long, num_args = 1.., required_unless_present = "remove", help = "Tags to add")]
+        add: Vec<String>,
+        #[arg(long, num_args = 1.., help = "Tags to remove")]
+        remove: Vec<String>,
+        #[arg(long, value_name = "KEY=VALUE", value_parser = parse_retag_filter, help = "Only works matching this: tag=<tag>, performer=<name> or search=<text>, as for 'list' (repeatable)")]
+        filter: Vec<RetagFilter>,
+    },
+    /// Rewrite the stored paths of archives after moving a collection, keeping their ratings, history, snapshots and cached hashes
+    Relocate {
+        #[arg(long, help = "Directory the archives were in, as recorded in the index (it doesn't have to exist anymore)")]
+        from: PathBuf,
+        #[arg(long, help = "Directory the archives are in now")]
+        to: PathBuf,
+        #[arg(long, help = "Only list the paths that would be rewritten")]
+        dry_run: bool,
+    },
+}
+
+/// A `--filter` condition of `library retag`.
+#[derive(Clone, Debug)]
+enum RetagFilter {
+    Tag(String),
+    Performer(String),
+    Search(String),
+}
+
+fn parse_retag_filter(filter: &str) -> Result<RetagFilter, String> {
+    let (key, value) = filter.split_once('=').ok_or_else(|| format!("expected KEY=VALUE, got '{}'", filter))?;
+    let value = value.trim().to_string();
+    match key.trim() {
+        "tag" => Ok(RetagFilter::Tag(value)),
+        "performer" => Ok(RetagFilter::Performer(value)),
+        "search" => Ok(RetagFilter::Search(value)),
+        key => Err(format!("unknown filter '{}', expected tag, performer or search", key)),
+    }
+}
+
+/// Worker count and retries for operations over many files.
+#[derive(ClapArgs, Debug)]
+struct JobArgs {
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), help = "Number of files processed at once (defaults to the number of CPUs)")]
+    jobs: Option<u32>,
+    #[arg(long, default_value_t = 0, help = "Times a file that failed is retried (e.g. while it is still being written)")]
+    retries: u32,
+    #[arg(long, value_name = "SECONDS", default_value_t = 1.0, help = "Wait between retries")]
+    retry_delay: f64,
+    #[arg(long, value_name = "FILES_PER_SECOND", help = "Start at most this many files a second, to keep the load on network shares down")]
+    rate_limit: Option<f64>,
+}
+
+impl JobArgs {
+    fn scheduler(&self) -> JobScheduler {
+        let scheduler = match self.jobs {
+            Some(jobs) => JobScheduler::new(jobs as usize),
+            None => JobScheduler::default(),
+        };
+        scheduler.retry(RetryPolicy::retries(self.retries, Duration::from_millis(seconds_to_ms(self.retry_delay)))).rate_limit(self.rate_limit.unwrap_or_default())
     }
 }
 
+#[derive(Subcommand, Debug)]
+enum ScriptCommands {
+    /// Convert a script between funscript, raw CSV, RealTouch CSV, Vorze CSV and legacy Launch JSON
+    Convert {
+        #[arg(help = "Path to the script to convert")]
+        input: PathBuf,
+        #[arg(help = "Path to write the converted script to")]
+        output: PathBuf,
+        #[arg(long, value_enum, help = "Format of the input (detected from the extension and content if omitted; RealTouch must be named explicitly)")]
+        from: Option<ScriptFormat>,
+        #[arg(long, value_enum, help = "Format of the output (detected from the extension if omitted)")]
+        to: Option<ScriptFormat>,
+    },
+    /// Store a thinned out copy of a script variant for devices with low command rates, as a new script variant
+    #[command(group(clap::ArgGroup::new("method").args(["epsilon", "min_interval"]).multiple(true).required(true)))]
+    Simplify {
+        #[arg(help = "Path to the FunscriptVideo file to modify")]
+        path: PathBuf,
+        #[arg(long, help = "Script variant to simplify (defaults to the first one listed)")]
+        script: Option<String>,
+        #[arg(long, help = "Drop actions within this many positions (0-100) of a straight move (Ramer-Douglas-Peucker)")]
+        epsilon: Option<f64>,
+        #[arg(long, value_name = "MS", help = "Minimum time between actions, in milliseconds")]
+        min_interval: Option<u64>,
+        #[arg(long, help = "Name of the new script variant (default: <stem>.simplified.funscript)")]
+        name: Option<String>,
+    },
+    /// Store an inverted, range limited and/or speed scaled copy of a script variant as a new script variant
+    #[command(group(clap::ArgGroup::new("transform").args(["invert", "min", "max", "speed"]).multiple(true).required(true)))]
+    Transform {
+        #[arg(help = "Path to the FunscriptVideo file to modify")]
+        path: PathBuf,
+        #[arg(long, help = "Script variant to transform (defaults to the first one listed)")]
+        script: Option<String>,
+        #[arg(long, help = "Invert all positions")]
+        invert: bool,
+        #[arg(long, value_parser = clap::value_parser!(u64).range(0..=100), help = "Lowest allowed position (0-100)")]
+        min: Option<u64>,
+        #[arg(long, value_parser = clap::value_parser!(u64).range(0..=100), help = "Highest allowed position (0-100)")]
+        max: Option<u64>,
+        #[arg(long, help = "Stretch the script's own position range onto --min/--max instead of clamping to them")]
+        rescale: bool,
+        #[arg(long, value_name = "FACTOR", help = "Scale every move's distance from the midpoint, and so its speed (e.g. 0.5 for a soft version)")]
+        speed: Option<f64>,
+        #[arg(long, help = "Name of the new script variant (default: <stem>.transformed.funscript)")]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCommands {
+    /// Manage creator_info records
+    #[command(subcommand)]
+    Creator(DbCreatorCommands),
+    /// Manage the tag vocabulary
+    #[command(subcommand)]
+    Tag(DbTagCommands),
+    /// Remove all cached file and archive entry digests
+    ClearHashCache,
+}
+
+#[derive(Subcommand, Debug)]
+enum DbTagCommands {
+    /// Define a canonical tag with optional aliases
+    Add {
+        #[arg(help = "Canonical tag name")]
+        name: String,
+        #[arg(num_args = 0.., help = "Aliases that should be normalized to this tag")]
+        aliases: Vec<String>,
+    },
+    /// Add an alias to an existing canonical tag
+    Alias {
+        #[arg(help = "Canonical tag name")]
+        name: String,
+        #[arg(help = "Alias to add")]
+        alias: String,
+    },
+    /// Remove an alias
+    Unalias {
+        #[arg(help = "Alias to remove")]
+        alias: String,
+    },
+    /// Remove a canonical tag and all of its aliases
+    Remove {
+        #[arg(help = "Canonical tag name")]
+        name: String,
+    },
+    /// Make a tag imply another (e.g. 'vr' implies 'video'): filtering the library by the implied tag also finds works with the
+    /// implying one, and 'edit tags --apply-implied' adds it
+    Imply {
+        #[arg(help = "Canonical tag name")]
+        name: String,
+        #[arg(help = "Canonical name of the tag it implies")]
+        implied: String,
+    },
+    /// Remove an implication added with 'db tag imply'
+    Unimply {
+        #[arg(help = "Canonical tag name")]
+        name: String,
+        #[arg(help = "Canonical name of the tag it implies")]
+        implied: String,
+    },
+    /// List the tag vocabulary
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCreatorCommands {
+    /// Update an existing creator_info record (rename, change key, or replace socials)
+    Update {
+        #[arg(help = "Key of the creator to update, or a name only one creator has")]
+        key_name: String,
+        #[arg(long, help = "New name for the creator")]
+        name: Option<String>,
+        #[arg(long, help = "New unique key/identifier for the creator")]
+        key: Option<String>,
+        #[arg(long, num_args = 0.., help = "Replace the social URLs with this list (pass the flag with no values to clear them)")]
+        socials: Option<Vec<String>>,
+    },
+    /// List creator_info records
+    List {
+        #[arg(long, help = "Maximum number of records to show")]
+        limit: Option<u32>,
+        #[arg(long, default_value_t = 0, help = "Number of records to skip")]
+        offset: u32,
+    },
+    /// Merge a creator entered twice into one record, moving the loser's socials and indexed works to the winner
+    Merge {
+        #[arg(help = "Key or name of the creator to keep")]
+        winner: String,
+        #[arg(help = "Key or name of the creator to merge into the winner and delete")]
+        loser: String,
+        #[arg(long = "fsv", value_name = "PATH", help = "Also link the loser's credits in this FunscriptVideo file to the winner and refresh its embedded creator info (repeatable)")]
+        fsv_paths: Vec<PathBuf>,
+    },
+    /// Find the creator a funscript's embedded metadata credits (its `creator` and `script_url`) in the database, offering to create it if missing
+    ImportFromScript {
+        #[arg(help = "Path to the funscript")]
+        script_path: PathBuf,
+    },
+    /// Search creator_info records by name, key, or social URL
+    Search {
+        #[arg(help = "Search pattern (substring, or a SQL LIKE pattern if it contains %)")]
+        pattern: String,
+        #[arg(long, help = "Maximum number of records to show")]
+        limit: Option<u32>,
+        #[arg(long, default_value_t = 0, help = "Number of records to skip")]
+        offset: u32,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 enum AddCommands {
     /// Add a creator_info record to the database or FSV, depending on arguments
@@ -123,8 +801,19 @@ enum AddCommands {
         video_path: PathBuf,
         #[arg(long, help = "Optional creator key (must exist in DB)")]
         creator_key: Option<String>,
+        #[arg(long, value_enum, help = "Generate an additional video format with an ffmpeg profile (repeatable, requires ffmpeg)")]
+        transcode: Vec<TranscodeProfile>,
+        #[arg(long = "hash-algo", value_enum, default_value_t = HashAlgorithm::Sha256, help = "Checksum algorithm for added files (xxh3 is fast but not cryptographic)")]
+        hash_algo: HashAlgorithm,
+        #[arg(long, value_enum, help = "What to do if the FSV already has an entry with this name (asked interactively if omitted, otherwise skip)")]
+        on_conflict: Option<AddConflict>,
+        #[arg(long, value_name = "URL|PATH", conflicts_with = "transcode", help = "Don't store the video, reference it at this URL or path relative to the FSV (fsv.external-content); the local file is only read for its checksum, duration and size")]
+        external: Option<String>,
+        #[arg(long, help = "Description of the video format (replaces the existing one when overwriting)")]
+        description: Option<String>,
     },
-    /// Add a script file (with optional creator info) to an existing FSV container
+    /// Add a script file (with optional creator info) to an existing FSV container. Axis scripts (e.g. scene.roll.funscript) join their main script's variant.
+    /// CSV, Vorze and Launch scripts are converted to funscript first
     Script {
         #[arg(help = "Path to the FSV file to modify")]
         fsv_path: PathBuf,
@@ -132,6 +821,18 @@ enum AddCommands {
         script_path: PathBuf,
         #[arg(long, help = "Optional creator key (must exist in DB)")]
         creator_key: Option<String>,
+        #[arg(long, value_enum, help = "Format of the script (detected from the extension and content if omitted)")]
+        format: Option<ScriptFormat>,
+        #[arg(long, help = "Fill in title, tags, performers and script creator from the script's embedded metadata (new creators are saved to the database)")]
+        from_script_metadata: bool,
+        #[arg(long, conflicts_with = "creator_key", help = "Credit the creator named in the script's embedded metadata, matched in the database by name or script URL (offers to create it if missing)")]
+        import_creator: bool,
+        #[arg(long = "hash-algo", value_enum, default_value_t = HashAlgorithm::Sha256, help = "Checksum algorithm for added files (xxh3 is fast but not cryptographic)")]
+        hash_algo: HashAlgorithm,
+        #[arg(long, value_enum, help = "What to do if the FSV already has an entry with this name (asked interactively if omitted, otherwise skip)")]
+        on_conflict: Option<AddConflict>,
+        #[arg(long, help = "Description of the script variant (replaces the existing one when overwriting)")]
+        description: Option<String>,
     },
     /// Add a subtitle file (with optional creator info) to an existing FSV container
     Subtitle {
@@ -141,6 +842,30 @@ enum AddCommands {
         subtitle_path: PathBuf,
         #[arg(long, help = "Optional creator key (must exist in DB)")]
         creator_key: Option<String>,
+        #[arg(long = "hash-algo", value_enum, default_value_t = HashAlgorithm::Sha256, help = "Checksum algorithm for added files (xxh3 is fast but not cryptographic)")]
+        hash_algo: HashAlgorithm,
+        #[arg(long, value_enum, help = "What to do if the FSV already has an entry with this name (asked interactively if omitted, otherwise skip)")]
+        on_conflict: Option<AddConflict>,
+        #[arg(long, help = "Description of the subtitle track (replaces the existing one when overwriting)")]
+        description: Option<String>,
+    },
+    /// Attach a notes/readme document (Markdown or plain text) to an existing FSV container, replacing its current notes (fsv.notes)
+    Notes {
+        #[arg(help = "Path to the FSV file to modify")]
+        fsv_path: PathBuf,
+        #[arg(help = "Path to the notes file to add")]
+        notes_path: PathBuf,
+        #[arg(long, default_value = DEFAULT_NOTES_NAME, help = "Name of the notes entry in the archive")]
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ShowCommands {
+    /// Print the notes/readme document of a FunscriptVideo file (fsv.notes)
+    Notes {
+        #[arg(help = "Path to the FunscriptVideo file")]
+        path: PathBuf,
     },
 }
 
@@ -168,6 +893,19 @@ enum CreatorLocation {
     }
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum StatsFormat {
+    Table,
+    Json,
+    Markdown,
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 enum LogMode {
     None,
@@ -176,6 +914,13 @@ enum LogMode {
     Both,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum LogFormat {
+    Text,
+    /// JSON lines with the event's fields (operation, archive, item, outcome, ...) and the spans it happened in
+    Json,
+}
+
 #[derive(Debug, Clone, Copy)]
 enum LogLevel {
     Off,
@@ -216,7 +961,23 @@ fn quiet_to_level(count: u8) -> LogLevel {
 }
 
 
-fn configure_logging(app_name: &str, mode: LogMode, level: LogLevel) -> WorkerGuard {
+/// Log layer writing to `writer` in `format`.
+fn log_layer<W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi)
+        .with_target(false);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().flatten_event(true).with_current_span(false).with_span_list(true).boxed(),
+    }
+}
+
+/// `console_to_stderr` sends what would go to stdout to stderr instead, for commands whose output is stdout.
+fn configure_logging(app_name: &str, mode: LogMode, format: LogFormat, level: LogLevel, console_to_stderr: bool) -> WorkerGuard {
     let file_appender = rolling::daily("logs", format!("{}.log", app_name));
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
 
@@ -225,52 +986,118 @@ fn configure_logging(app_name: &str, mode: LogMode, level: LogLevel) -> WorkerGu
         .with_default_directive(level_filter.into())
         .from_env_lossy();
 
-    match mode {
-        LogMode::None => {}
-        LogMode::Stdout => {
-            let stdout_layer = tracing_subscriber::fmt::layer()
-                .with_writer(std::io::stdout)
-                .with_target(false);
+    let stdout_ansi = matches!(format, LogFormat::Text);
+    let console_layer = || match console_to_stderr {
+        true => log_layer(format, std::io::stderr, stdout_ansi),
+        false => log_layer(format, std::io::stdout, stdout_ansi),
+    };
+    let layers = match mode {
+        LogMode::None => return _guard,
+        LogMode::Stdout => vec![console_layer()],
+        // no color codes in log file
+        LogMode::File => vec![log_layer(format, non_blocking, false)],
+        LogMode::Both => vec![log_layer(format, non_blocking, false), console_layer()],
+    };
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(env_filter)
+        .init();
+
+    _guard
+}
+
+/// Log why a command failed. When the error says which file or archive entry it concerned, JSON events carry
+/// them as the `archive` and `item` fields, and the step that failed as `operation`.
+fn log_error(message: &str, err: &(dyn std::error::Error + 'static)) {
+    match ErrorContext::find(err) {
+        Some(context) => {
+            let archive = context.path.as_ref().map(|path| path.display().to_string());
+            error!(operation = context.operation, archive = archive.as_deref(), item = context.entry.as_deref(), "{}: {}", message, err);
+        },
+        None => error!("{}: {}", message, err),
+    }
+}
+
+/// The creator and library database, opened by the first command that uses it. Commands that don't touch it (e.g.
+/// `validate` or `extract`) work even when it can't be opened, such as a missing database in a read-only directory.
+struct Database<'a> {
+    rt: &'a tokio::runtime::Runtime,
+    url: Option<String>,
+    path: PathBuf,
+    client: OnceCell<Result<DbClient, DbClientError>>,
+}
+
+impl<'a> Database<'a> {
+    fn new(rt: &'a tokio::runtime::Runtime, url: Option<String>, path: PathBuf) -> Self {
+        Database { rt, url, path, client: OnceCell::new() }
+    }
 
-            tracing_subscriber::registry()
-                .with(env_filter)
-                .with(stdout_layer)
-                .init();
+    fn open(&self) -> Result<DbClient, DbClientError> {
+        if let Some(url) = &self.url {
+            return self.rt.block_on(DbClient::connect(url));
         }
-        LogMode::File => {
-            let file_layer = tracing_subscriber::fmt::layer()
-                .with_writer(non_blocking)
-                .with_ansi(false) // no color codes in log file
-                .with_target(false);
 
-            tracing_subscriber::registry()
-                .with(env_filter)
-                .with(file_layer)
-                .init();
+        let err = match self.rt.block_on(DbClient::new(&self.path)) {
+            Ok(db_client) => return Ok(db_client),
+            Err(err) => err,
+        };
+        // A database that can't be written (or migrated) may still be read
+        if !self.path.exists() {
+            return Err(err);
         }
-        LogMode::Both => {
-            let file_layer = tracing_subscriber::fmt::layer()
-                .with_writer(non_blocking)
-                .with_ansi(false) // no color codes in log file
-                .with_target(false);
+        match self.rt.block_on(DbClient::open_read_only(&self.path)) {
+            Ok(db_client) => {
+                warn!("Opened database '{}' read-only ({}); changes to it will fail", self.path.display(), err);
+                Ok(db_client)
+            },
+            Err(_) => Err(err),
+        }
+    }
 
-            let stdout_layer = tracing_subscriber::fmt::layer()
-                .with_writer(std::io::stdout)
-                .with_target(false);
+    fn client(&self) -> Result<&DbClient, &DbClientError> {
+        self.client.get_or_init(|| self.open()).as_ref()
+    }
 
-            tracing_subscriber::registry()
-                .with(env_filter)
-                .with(file_layer)
-                .with(stdout_layer)
-                .init();
+    /// Run a command that needs the database, failing it if the database can't be opened.
+    fn with(&self, command: impl FnOnce(&DbClient) -> FsvExitCode) -> FsvExitCode {
+        match self.client() {
+            Ok(db_client) => command(db_client),
+            Err(err) => {
+                log_error("Failed to initialize database client", err);
+                err.exit_code()
+            },
         }
     }
 
-    _guard
+    /// Run a command that only looks things up in the database or caches in it. If the database can't be opened it
+    /// runs against an empty in-memory one instead: creator keys aren't found and nothing is kept.
+    fn or_in_memory(&self, command: impl FnOnce(&DbClient) -> FsvExitCode) -> FsvExitCode {
+        let err = match self.client() {
+            Ok(db_client) => return command(db_client),
+            Err(err) => err,
+        };
+        warn!("Database unavailable, continuing without it (creator keys can't be looked up and nothing is saved): {}", err);
+        match self.rt.block_on(DbClient::in_memory()) {
+            Ok(db_client) => command(&db_client),
+            Err(err) => {
+                log_error("Failed to initialize in-memory database", &err);
+                err.exit_code()
+            },
+        }
+    }
 }
 
 fn main() -> ExitCode {
-    let args = Args::parse();
+    let matches = Args::command().try_get_matches();
+    let (args, matches) = match matches.and_then(|matches| Ok((Args::from_arg_matches(&matches)?, matches))) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            let _ = err.print();
+            // --help and --version are reported through clap errors as well
+            return if err.use_stderr() { FsvExitCode::Usage.into() } else { FsvExitCode::Success.into() };
+        }
+    };
     let level = if args.silent {
         LogLevel::Off
     }
@@ -284,13 +1111,27 @@ fn main() -> ExitCode {
         LogLevel::Info
     };
 
-    let _guard = configure_logging("funscripvideo-cli", args.log_mode, level);
+    let writes_stdout = matches!(args.command, Commands::Extract { stdout: true, .. } | Commands::CatMetadata { .. } | Commands::Show(_) | Commands::MetadataSchema);
+    let _guard = configure_logging("funscripvideo-cli", args.log_mode, args.log_format, level, writes_stdout);
+    // Every JSON event of the run carries the subcommand as its operation. Text logs leave it out, it would only prefix every line
+    let command_span = match args.log_format {
+        LogFormat::Json => info_span!("command", operation = matches.subcommand_name().unwrap_or_default()),
+        LogFormat::Text => Span::none(),
+    };
+    let _command_span = command_span.enter();
+    // These only describe the CLI itself and don't need the runtime or database
+    match &args.command {
+        Commands::Completions { shell } => return completions(*shell).into(),
+        Commands::Manpages { output_dir } => return manpages(output_dir).into(),
+        _ => (),
+    }
+
     let result = tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build();
     if result.is_err() {
         error!("Failed to create Tokio runtime: {}", result.err().unwrap());
-        return ExitCode::FAILURE;
+        return FsvExitCode::Failure.into();
     }
 
     let executable_dir = std::env::current_exe()
@@ -298,150 +1139,719 @@ fn main() -> ExitCode {
         .and_then(|p| p.parent().map(|p| p.to_path_buf()));
     if executable_dir.is_none() {
         error!("Failed to determine executable directory.");
-        return ExitCode::FAILURE;
+        return FsvExitCode::Io.into();
     }
 
     let executable_dir = executable_dir.unwrap();
     let database_path = executable_dir.join("funscripvideo.db");
+    let config_path = executable_dir.join(CONFIG_FILE_NAME);
     let rt = result.unwrap();
-    let result = rt.block_on(DbClient::new(&database_path));
-    if result.is_err() {
-        error!("Failed to initialize database client: {}", result.err().unwrap());
-        return ExitCode::FAILURE;
-    }
+    let database = Database::new(&rt, args.database, database_path);
 
-    let db_client = result.unwrap();
     let interactive = !args.non_interactive;
-    match args.command {
-        Commands::Validate { path } => validate(&path),
-        Commands::Create { path, title, tags, video, script, video_creator_key, script_creator_key } => rt.block_on(create(path, title, tags, video, script, video_creator_key, script_creator_key, &db_client, interactive)),
-        Commands::Add(add_cmd) => rt.block_on(add(add_cmd, &db_client, interactive)),
-        Commands::Remove { path, entry_type, entry_id } => remove(&path, entry_type, entry_id),
-        Commands::Extract { path, output_dir } => extract(&path, &output_dir),
-        Commands::Info { path } => info(&path),
-        Commands::Rebuild { path } => rebuild(path),
-    }
-
-    ExitCode::SUCCESS
-}
-
-fn validate(path: &PathBuf) {
-    let result = FunScriptVideo::fsv::validate_fsv(&path);
-    match result {
-        Ok(state) => match state {
-            FunScriptVideo::fsv::FsvState::Valid => {
-                info!("FSV file is valid.");
-            }
-            FunScriptVideo::fsv::FsvState::ContentIncomplete(reason) => match reason {
-                FunScriptVideo::fsv::ContentIncompleteReason::UnableToReadItem(item_type) => warn!("Unable to read {} file", item_type.get_name_lower()),
-                FunScriptVideo::fsv::ContentIncompleteReason::MissingItemFile(item_type) => warn!("Missing {} file in archive", item_type.get_name_lower()),
-                FunScriptVideo::fsv::ContentIncompleteReason::ItemPasswordProtected(item_type) => warn!("{} file is password protected", item_type.get_name()),
-                FunScriptVideo::fsv::ContentIncompleteReason::DuplicateItemEntry(item_type) => warn!("Duplicate {} entry in metadata", item_type.get_name_lower()),
-            },
-            FunScriptVideo::fsv::FsvState::MetadataInvalid(reason) => match reason {
-                FunScriptVideo::fsv::MetadataInvalidReason::InvalidFormatVersion => {
-                    error!("Invalid format version in metadata.");
-                }
-                FunScriptVideo::fsv::MetadataInvalidReason::MalformedJson(json) => {
-                    error!("Malformed JSON in metadata: {}", json);
-                }
-                FunScriptVideo::fsv::MetadataInvalidReason::UnsupportedFormatVersion(version) => {
-                    error!("Unsupported format version in metadata: {}", version);
-                }
-                FunScriptVideo::fsv::MetadataInvalidReason::MissingVideoFormat => {
-                    error!("Missing video format in metadata.");
-                }
-                FunScriptVideo::fsv::MetadataInvalidReason::MissingScriptVariant => {
-                    error!("Missing script variant in metadata.");
-                }
-            },
+    let exit_code = match args.command {
+        Commands::Validate { path, name_matching, parse_mode, deep, duration_tolerance, report } => {
+            let options = ValidateOptions { name_matching, parse_mode, deep, duration_tolerance: DurationMs::from_millis(duration_tolerance) };
+            validate(&path, &options, report)
         },
-        Err(err) => {
-            error!("Error validating FSV file: {}", err);
-        }
-    }
-}
+        Commands::VerifyLibrary { dir, report, format, jobs, name_matching } => {
+            verify_library(&dir, report.as_deref(), format, &jobs.scheduler(), name_matching)
+        },
+        Commands::Snapshot { dir, hash_algo, prune, jobs } => database.with(|db_client| rt.block_on(snapshot(&dir, hash_algo, prune, &jobs.scheduler(), db_client))),
+        Commands::VerifySnapshot { dir, report, jobs } => database.with(|db_client| rt.block_on(verify_snapshot(&dir, report.as_deref(), &jobs.scheduler(), db_client))),
+        Commands::Create { path, title, tags, video, script, video_creator_key, video_description, script_creator_key, script_description, performers, studio, from_script_metadata, reproducible, transcode, hash_algo, compression, template } => {
+            let config = match load_config(&config_path) {
+                Ok(config) => config,
+                Err(code) => return code.into(),
+            };
+            let content_policy = match load_content_policy(&config, &config_path) {
+                Ok(content_policy) => content_policy,
+                Err(code) => return code.into(),
+            };
+            let mut create_args = CreateArgs::new(path, title, tags, video, script, video_creator_key, script_creator_key)
+                .reproducible(reproducible)
+                .transcode(transcode)
+                .from_script_metadata(from_script_metadata)
+                .performers(performers)
+                .studio(studio.unwrap_or_default())
+                .video_description(video_description.unwrap_or_default())
+                .script_description(script_description.unwrap_or_default())
+                .naming_policy(config.naming_policy.clone())
+                .content_policy(content_policy);
+            if let Some(name) = template {
+                let result = config.template(&config_path, &name).and_then(|template| {
+                    let password = template.encryption.as_ref().map(|encryption| encryption.password(&name, interactive)).transpose()?;
+                    Ok(template.apply(create_args)?.encryption_password(password))
+                });
+                match result {
+                    Ok(args) => create_args = args,
+                    Err(err) => {
+                        error!("Error applying template '{}': {}", name, err);
+                        return err.exit_code().into();
+                    },
+                }
+            }
 
-async fn create(path: PathBuf, title: String, tags: Vec<String>, video: Option<PathBuf>, script: Option<PathBuf>, video_creator_key: Option<String>, script_creator_key: Option<String>, db_client: &DbClient, interactive: bool) {
-    let args = FunScriptVideo::fsv::CreateArgs::new(path, title, tags, video, script, video_creator_key, script_creator_key);
-    let result = FunScriptVideo::fsv::create_fsv(args, db_client, interactive).await;
-    match result {
-        Ok(_) => info!("FSV file created successfully."),
-        Err(err) => error!("Error creating FSV file: {}", err),
-    }
-}
+            if let Some(hash_algo) = hash_algo {
+                create_args = create_args.hash_algorithm(hash_algo);
+            }
 
-async fn add(cmd: AddCommands, db_client: &DbClient, interactive: bool) {
-    match cmd {
-        AddCommands::Creator(creator_location) => {
-            match creator_location {
-                CreatorLocation::Database { name, key, socials } => {
-                    let creator_info = FunScriptVideo::metadata::CreatorInfo::new(name, socials);
-                    let result = db_client.insert_creator_info(&key, &creator_info).await;
-                    match result {
-                        Ok(_) => info!("Creator info added to database successfully."),
-                        Err(err) => error!("Error adding creator info to database: {}", err),
-                    }
-                },
-                CreatorLocation::Fsv { fsv_path, work_type, creator_key, work_name, source_url } => {
-                    let result = FunScriptVideo::fsv::add_creator_to_fsv(&fsv_path, work_type, &creator_key, &work_name, &source_url, db_client).await;
-                    match result {
-                        Ok(_) => info!("Creator info added to FSV file successfully."),
-                        Err(err) => error!("Error adding creator info to FSV file: {}", err),
-                    }
-                },
+            if let Some(compression) = compression {
+                create_args = create_args.compression(compression);
             }
+
+            database.or_in_memory(|db_client| rt.block_on(create(create_args, &config.hooks, db_client, interactive)))
         },
-        AddCommands::Video { fsv_path, video_path, creator_key } => add_item_to_fsv(fsv_path, ItemType::Video, video_path, creator_key, db_client, interactive).await,
-        AddCommands::Script { fsv_path, script_path, creator_key } => add_item_to_fsv(fsv_path, ItemType::Script, script_path, creator_key, db_client, interactive).await,
-        AddCommands::Subtitle { fsv_path, subtitle_path, creator_key } => add_item_to_fsv(fsv_path, ItemType::Subtitle, subtitle_path, creator_key, db_client, interactive).await,
-    }
+        Commands::Add(add_cmd) => database.or_in_memory(|db_client| rt.block_on(add(add_cmd, &config_path, db_client, interactive))),
+        Commands::Show(ShowCommands::Notes { path }) => show_notes(&path),
+        Commands::Remove { path, entry_type, entry_id } => remove(&path, entry_type, entry_id, &config_path),
+        Commands::Undo { path, steps, force, list } => undo(&path, steps, force, list),
+        Commands::Extract { entry: Some(entry), path, name_matching, .. } => extract_entry(&path, &entry, name_matching),
+        Commands::Extract { path, output_dir, name_matching, only, subtitle_languages, path_safety, apply_offset, .. } => extract(&path, &output_dir, ExtractOptions { name_matching, only, subtitle_languages, path_safety, apply_offset, ..Default::default() }),
+        Commands::Info { path, name_matching, full, sizes, json } => info(&path, InfoOptions { name_matching, full, sizes }, json),
+        Commands::Rebuild { path, fix_duplicates, strict, discard_unknown } => rebuild(path, RebuildOptions { fix_duplicates, strict, discard_unknown }),
+        Commands::Recover { dir } => recover(&dir, &database),
+        Commands::Hash { path, write, verify } => database.or_in_memory(|db_client| rt.block_on(hash(&path, write, verify, db_client))),
+        Commands::History { path, enable } => history(&path, enable),
+        Commands::NormalizeMetadata { path } => normalize_metadata(&path),
+        Commands::CatMetadata { path } => cat_metadata(&path),
+        Commands::MetadataSchema => metadata_schema(),
+        Commands::ValidateMetadata { path, json } => validate_metadata(&path, json),
+        Commands::PutMetadata { path, metadata, allow_content_incomplete } => put_metadata(&path, &metadata, allow_content_incomplete),
+        Commands::SyncCreators { path, direction, dry_run } => database.with(|db_client| rt.block_on(sync_creators(&path, direction, dry_run, db_client))),
+        Commands::Preview { path, start, duration, montage, source, name } => {
+            let selection = match montage {
+                Some(count) => PreviewSelection::Montage { count, segment_ms: seconds_to_ms(duration.unwrap_or(3.0)) },
+                None => PreviewSelection::Segment { start_ms: seconds_to_ms(start.unwrap_or(0.0)), duration_ms: seconds_to_ms(duration.unwrap_or(10.0)) },
+            };
+            preview(&path, selection, source.as_deref(), &name)
+        },
+        Commands::Align { path, script, video, signal, max_offset, dry_run } => {
+            let options = AlignOptions { script, video, signal, max_offset_ms: seconds_to_ms(max_offset), dry_run };
+            align(&path, &options)
+        },
+        Commands::Watch { drop_dir, output_dir, archive_dir, interval, once, #[cfg(feature = "serve")] events, #[cfg(feature = "serve")] token } => {
+            let (config, content_policy) = match load_config(&config_path).and_then(|config| Ok((load_content_policy(&config, &config_path)?, config))) {
+                Ok((content_policy, config)) => (config, content_policy),
+                Err(code) => return code.into(),
+            };
+            let archive_dir = archive_dir.unwrap_or_else(|| drop_dir.join("imported"));
+            let watch_args = WatchArgs::new(drop_dir, output_dir, archive_dir, Duration::from_secs(interval), once).content_policy(content_policy).hooks(config.hooks);
+            let broadcaster = std::sync::Arc::new(EventBroadcaster::new());
+            let notifier = Notifier::spawn(config.notifications, &broadcaster);
+            #[cfg(feature = "serve")]
+            if let Some(bind) = events && let Err(err) = api_token(token).and_then(|token| FunScriptVideo::serve::spawn_event_server(&bind, token, broadcaster.clone())) {
+                log_error("Error starting event feed", &err);
+                return err.exit_code().into();
+            }
+            let exit_code = database.with(|db_client| rt.block_on(watch(watch_args, db_client, &*broadcaster)));
+            if let Some(notifier) = notifier {
+                notifier.finish();
+            }
+            exit_code
+        },
+        Commands::Library(library_cmd) => database.with(|db_client| rt.block_on(library(library_cmd, db_client))),
+        Commands::List { tag, performer, search, min_rating, favorites, unwatched } => {
+            let filter = LibraryFilter { tag, performer, search, min_rating, favorites, unwatched };
+            database.with(|db_client| rt.block_on(list(filter, db_client)))
+        },
+        Commands::Rate { path, rating, clear: _ } => database.with(|db_client| rt.block_on(rate(&path, rating, db_client))),
+        Commands::Favorite { path, off } => database.with(|db_client| rt.block_on(favorite(&path, !off, db_client))),
+        Commands::Stats { dir, format, top } => database.with(|db_client| rt.block_on(stats(dir.as_deref(), format, top, db_client))),
+        Commands::Edit(edit_cmd) => database.with(|db_client| rt.block_on(edit(edit_cmd, db_client))),
+        Commands::Db(db_cmd) => database.with(|db_client| rt.block_on(db(db_cmd, db_client, interactive))),
+        Commands::Script(script_cmd) => script(script_cmd),
+        #[cfg(feature = "tui")]
+        Commands::Browse { path, output_dir } => browse(&path, &output_dir),
+        Commands::Open { path, video, script } => {
+            let config = match load_config(&config_path) {
+                Ok(config) => config,
+                Err(code) => return code.into(),
+            };
+            open(&path, video.as_deref(), script.as_deref(), &config.player)
+        },
+        Commands::Package { path, trackers, piece_size, private, output_dir } => {
+            package(&path, &PackageOptions { trackers, piece_size: piece_size.map(|kib| kib.saturating_mul(1024)), private, output_dir })
+        },
+        Commands::Register { dry_run } => register(dry_run),
+        #[cfg(feature = "http")]
+        Commands::Fetch { path, video } => fetch(&path, video.as_deref()),
+        #[cfg(feature = "play")]
+        Commands::Play { path, script, server, serial, baud, timecode, mpv_socket, video, media, start_at, resume } => database.or_in_memory(|db_client| {
+            let start_at = match resume {
+                true => rt.block_on(resume_position(&path, db_client)),
+                false => seconds_to_ms(start_at),
+            };
+            let timecode = match (timecode, mpv_socket) {
+                (Some(url), _) => FunScriptVideo::play::TimecodeSource::WebSocket(url),
+                (None, Some(socket)) => FunScriptVideo::play::TimecodeSource::Mpv { socket, media },
+                (None, None) => FunScriptVideo::play::TimecodeSource::Manual { start_at },
+            };
+            let output = match serial {
+                Some(port) => FunScriptVideo::play::PlayOutput::Serial { port, baud_rate: baud },
+                None => FunScriptVideo::play::PlayOutput::Buttplug { server_url: server },
+            };
+            let play_args = FunScriptVideo::play::PlayArgs { path, script, video, output, timecode };
+            rt.block_on(play(play_args, db_client))
+        }),
+        #[cfg(feature = "serve")]
+        Commands::Serve { root, bind, token } => {
+            let config = match load_config(&config_path) {
+                Ok(config) => config,
+                Err(code) => return code.into(),
+            };
+            let token = match api_token(token) {
+                Ok(token) => token,
+                Err(err) => {
+                    log_error("Error generating API token", &err);
+                    return err.exit_code().into();
+                },
+            };
+            let serve_args = FunScriptVideo::serve::ServeArgs { bind, root, token, events: Default::default() };
+            // Runs as long as the server does
+            let _notifier = Notifier::spawn(config.notifications, &serve_args.events);
+            database.with(|db_client| serve(serve_args, db_client, &rt))
+        },
+        Commands::Completions { .. } | Commands::Manpages { .. } => unreachable!("handled before the runtime is built"),
+    };
+
+    exit_code.into()
 }
 
-async fn add_item_to_fsv(fsv_path: PathBuf, item_type: ItemType, item_path: PathBuf, creator_key: Option<String>, db_client: &DbClient, interactive: bool) {
-    let args = AddArgs::new(fsv_path, item_type, item_path, creator_key);
-    let result = FunScriptVideo::fsv::add_to_fsv(args, db_client, interactive).await;
-    match result {
-        Ok(_) => info!("{} added to FSV file successfully.", item_type.get_name()),
-        Err(err) => error!("Error adding {} to FSV file: {}", item_type.get_name(), err),
-    }
+/// The bucket and key an `s3://<bucket>/<key>` path stands for, with the connection settings from the environment.
+#[cfg(feature = "s3")]
+fn s3_location(path: &Path) -> Option<std::io::Result<(FunScriptVideo::s3::S3Storage, String)>> {
+    let (bucket, key) = FunScriptVideo::s3::parse_s3_url(path)?;
+    Some(FunScriptVideo::s3::S3Config::from_env().map(|config| (FunScriptVideo::s3::S3Storage::new(config, &bucket, ""), key)))
 }
 
-fn remove(path: &PathBuf, entry_type: EntryType, entry_id: String) {
-    let result = FunScriptVideo::fsv::remove_from_fsv(&path, entry_type, &entry_id);
-    match result {
-        Ok(_) => info!("Entry removed from FSV file successfully."),
-        Err(err) => error!("Error removing entry from FSV file: {}", err),
+/// Validation report of a local FSV, or of one at an `http(s)://` or `s3://` URL with the `http` or `s3` feature.
+fn validate_report(path: &Path, options: &ValidateOptions) -> Result<ValidationReport, FsvValidationError> {
+    #[cfg(feature = "s3")]
+    if let Some(location) = s3_location(path) {
+        let (storage, key) = location?;
+        return FunScriptVideo::storage::validate_stored(&storage, &key, options);
     }
-}
 
-fn extract(path: &PathBuf, output_dir: &PathBuf) {
-    let result = FunScriptVideo::fsv::extract_fsv(&path, &output_dir, false);
-    match result {
-        Ok(_) => info!("FSV file extracted successfully."),
-        Err(err) => error!("Error extracting FSV file: {}", err),
+    #[cfg(feature = "http")]
+    if let Some(url) = FunScriptVideo::remote::as_url(path) {
+        return FunScriptVideo::remote::validate_url_report(url, options);
     }
+
+    FunScriptVideo::fsv::validate_fsv_report_with(path, options)
 }
 
-fn info(path: &PathBuf) {
-    let result = FunScriptVideo::fsv::get_fsv_info(&path);
-    let fsv_info = match result {
+fn validate(path: &Path, options: &ValidateOptions, print_report: bool) -> FsvExitCode {
+    let result = validate_report(path, options);
+    let report = match result {
+        Ok(report) => report,
+        Err(err) => {
+            log_error("Error validating FSV file", &err);
+            return err.exit_code();
+        }
+    };
+
+    if print_report {
+        println!("Validation report for {}", path.display());
+        let state = match &report.state {
+            FunScriptVideo::fsv::FsvState::Valid => "Valid",
+            FunScriptVideo::fsv::FsvState::ContentIncomplete(_) => "Content Incomplete",
+            FunScriptVideo::fsv::FsvState::MetadataInvalid(_) => "Metadata Invalid",
+        };
+        println!("State: {}", state);
+        if !report.external.is_empty() {
+            println!("External, not verified ({}):", report.external.len());
+            for name in &report.external {
+                println!("  - {}", name);
+            }
+        }
+
+        let errors: Vec<_> = report.errors().collect();
+        if !errors.is_empty() {
+            println!("Errors ({}):", errors.len());
+            for issue in errors {
+                println!("  - {}", issue);
+            }
+        }
+
+        let warnings: Vec<_> = report.warnings().collect();
+        if !warnings.is_empty() {
+            println!("Warnings ({}):", warnings.len());
+            for issue in warnings {
+                println!("  - {}", issue);
+            }
+        }
+    }
+    else {
+        for issue in &report.issues {
+            match issue.severity {
+                IssueSeverity::Error => error!("{}", issue),
+                IssueSeverity::Warning => warn!("{}", issue),
+            }
+        }
+
+        match &report.state {
+            FunScriptVideo::fsv::FsvState::Valid if !report.external.is_empty() => info!("FSV file is valid, {} external video(s) not verified.", report.external.len()),
+            FunScriptVideo::fsv::FsvState::Valid => info!("FSV file is valid."),
+            FunScriptVideo::fsv::FsvState::ContentIncomplete(_) => warn!("FSV file is content incomplete."),
+            FunScriptVideo::fsv::FsvState::MetadataInvalid(_) => error!("FSV metadata is invalid."),
+        }
+    }
+
+    FsvExitCode::from(&report.state)
+}
+
+async fn create(args: CreateArgs, hooks: &HooksConfig, db_client: &DbClient, interactive: bool) -> FsvExitCode {
+    let details = serde_json::json!({ "title": args.title, "video": args.video, "script": args.script });
+    let context = HookContext::new(HookOperation::Create, &args.path, details);
+    if let Err(code) = run_pre_hooks(hooks, &context) {
+        return code;
+    }
+
+    let result = FunScriptVideo::fsv::create_fsv(args, db_client, interactive).await;
+    hooks.run_post(&context, result.as_ref().err().map(ToString::to_string).as_deref());
+    match result {
+        Ok(_) => {
+            info!("FSV file created successfully.");
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error creating FSV file", &err);
+            err.exit_code()
+        },
+    }
+}
+
+/// Read the config next to the database. A missing config is an empty one.
+fn load_config(config_path: &Path) -> Result<Config, FsvExitCode> {
+    Config::load(config_path).map_err(|err| {
+        log_error("Error reading config", &err);
+        err.exit_code()
+    })
+}
+
+/// Run the `pre` hooks of an operation; one refusing it stops the command.
+fn run_pre_hooks(hooks: &HooksConfig, context: &HookContext) -> Result<(), FsvExitCode> {
+    hooks.run_pre(context).map_err(|err| {
+        log_error("Error running hook", &err);
+        err.exit_code()
+    })
+}
+
+fn load_content_policy(config: &Config, config_path: &Path) -> Result<Option<ContentPolicy>, FsvExitCode> {
+    config.content_policy(config_path).map_err(|err| {
+        log_error("Error reading content policy", &err);
+        err.exit_code()
+    })
+}
+
+fn verify_library(dir: &Path, report_path: Option<&Path>, format: ReportFormat, scheduler: &JobScheduler, name_matching: NameMatching) -> FsvExitCode {
+    let result = FunScriptVideo::library::verify_library(dir, name_matching, scheduler, &ProgressLog::new());
+    let report = match result {
+        Ok(report) => report,
+        Err(err) => {
+            log_error("Error verifying library", &err);
+            return err.exit_code();
+        },
+    };
+
+    for result in report.results.iter().filter(|result| result.status != VerifyStatus::Valid) {
+        warn!("{} is {}: {}", result.path.display(), result.status.get_name(), result.reasons.join("; "));
+    }
+
+    if let Some(report_path) = report_path {
+        let contents = match format {
+            ReportFormat::Json => serde_json::to_string_pretty(&report).map_err(|err| err.to_string()),
+            ReportFormat::Csv => Ok(report.to_csv()),
+        };
+        if let Err(err) = contents.and_then(|contents| std::fs::write(report_path, contents).map_err(|err| err.to_string())) {
+            error!("Error writing report to '{}': {}", report_path.display(), err);
+            return FsvExitCode::Io;
+        }
+    }
+
+    info!("Library verified: {} valid, {} incomplete, {} invalid.", report.valid, report.incomplete, report.invalid);
+    match report.worst_status() {
+        Some(VerifyStatus::Invalid) => FsvExitCode::ValidationFailed,
+        Some(VerifyStatus::Incomplete) => FsvExitCode::ContentIncomplete,
+        Some(VerifyStatus::Valid) | None => FsvExitCode::Success,
+    }
+}
+
+async fn snapshot(dir: &Path, algorithm: HashAlgorithm, prune: bool, scheduler: &JobScheduler, db_client: &DbClient) -> FsvExitCode {
+    let result = FunScriptVideo::snapshot::take_snapshot(db_client, dir, algorithm, prune, scheduler, &ProgressLog::new()).await;
+    match result {
+        Ok(summary) => {
+            info!("Snapshot taken: {} recorded, {} unchanged, {} failed, {} pruned.", summary.recorded, summary.unchanged, summary.failed, summary.pruned);
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error taking snapshot", &err);
+            err.exit_code()
+        },
+    }
+}
+
+async fn verify_snapshot(dir: &Path, report_path: Option<&Path>, scheduler: &JobScheduler, db_client: &DbClient) -> FsvExitCode {
+    let result = FunScriptVideo::snapshot::verify_snapshot(db_client, dir, scheduler, &ProgressLog::new()).await;
+    let report = match result {
+        Ok(report) => report,
+        Err(err) => {
+            log_error("Error verifying snapshot", &err);
+            return err.exit_code();
+        },
+    };
+
+    for result in report.results.iter().filter(|result| !matches!(result.status, SnapshotStatus::Intact | SnapshotStatus::New)) {
+        match result.reasons.is_empty() {
+            true => warn!("{} is {}", result.path.display(), result.status.get_name()),
+            false => warn!("{} is {}: {}", result.path.display(), result.status.get_name(), result.reasons.join("; ")),
+        }
+    }
+
+    if let Some(report_path) = report_path {
+        let contents = serde_json::to_string_pretty(&report).map_err(|err| err.to_string());
+        if let Err(err) = contents.and_then(|contents| std::fs::write(report_path, contents).map_err(|err| err.to_string())) {
+            error!("Error writing report to '{}': {}", report_path.display(), err);
+            return FsvExitCode::Io;
+        }
+    }
+
+    info!("Snapshot verified: {} intact, {} modified, {} corrupted, {} missing, {} new.", report.intact, report.modified, report.corrupted, report.missing, report.new);
+    match report.has_changes() {
+        true => FsvExitCode::ValidationFailed,
+        false => FsvExitCode::Success,
+    }
+}
+
+async fn add(cmd: AddCommands, config_path: &Path, db_client: &DbClient, interactive: bool) -> FsvExitCode {
+    match cmd {
+        AddCommands::Creator(creator_location) => {
+            match creator_location {
+                CreatorLocation::Database { name, key, socials } => {
+                    let creator_info = FunScriptVideo::metadata::CreatorInfo::new(name, socials);
+                    let result = db_client.insert_creator_info(&key, &creator_info).await;
+                    match result {
+                        Ok(_) => {
+                            info!("Creator info added to database successfully.");
+                            FsvExitCode::Success
+                        },
+                        Err(err) => {
+                            log_error("Error adding creator info to database", &err);
+                            err.exit_code()
+                        },
+                    }
+                },
+                CreatorLocation::Fsv { fsv_path, work_type, creator_key, work_name, source_url } => {
+                    let result = FunScriptVideo::fsv::add_creator_to_fsv(&fsv_path, work_type, &creator_key, &work_name, &source_url, db_client).await;
+                    match result {
+                        Ok(_) => {
+                            info!("Creator info added to FSV file successfully.");
+                            FsvExitCode::Success
+                        },
+                        Err(err) => {
+                            log_error("Error adding creator info to FSV file", &err);
+                            err.exit_code()
+                        },
+                    }
+                },
+            }
+        },
+        AddCommands::Video { fsv_path, video_path, creator_key, transcode, hash_algo, on_conflict, external, description } => {
+            let context = add_hook_context(&fsv_path, ItemType::Video, &video_path);
+            let args = AddArgs::new(fsv_path, ItemType::Video, video_path, creator_key).hash_algorithm(hash_algo).transcode(transcode).on_conflict(on_conflict).external(external).description(description);
+            add_item_to_fsv(args, ItemType::Video, context, config_path, db_client, interactive).await
+        },
+        AddCommands::Script { fsv_path, script_path, creator_key, format, from_script_metadata, import_creator, hash_algo, on_conflict, description } => {
+            let context = add_hook_context(&fsv_path, ItemType::Script, &script_path);
+            let args = AddArgs::new(fsv_path, ItemType::Script, script_path, creator_key)
+                .hash_algorithm(hash_algo)
+                .script_format(format)
+                .from_script_metadata(from_script_metadata)
+                .import_creator(import_creator)
+                .on_conflict(on_conflict)
+                .description(description);
+            add_item_to_fsv(args, ItemType::Script, context, config_path, db_client, interactive).await
+        },
+        AddCommands::Subtitle { fsv_path, subtitle_path, creator_key, hash_algo, on_conflict, description } => {
+            let context = add_hook_context(&fsv_path, ItemType::Subtitle, &subtitle_path);
+            let args = AddArgs::new(fsv_path, ItemType::Subtitle, subtitle_path, creator_key).hash_algorithm(hash_algo).on_conflict(on_conflict).description(description);
+            add_item_to_fsv(args, ItemType::Subtitle, context, config_path, db_client, interactive).await
+        },
+        AddCommands::Notes { fsv_path, notes_path, name } => {
+            let context = HookContext::new(HookOperation::Add, &fsv_path, serde_json::json!({ "item_type": "notes", "item_path": notes_path }));
+            add_notes(&fsv_path, &notes_path, &name, context, config_path)
+        },
+    }
+}
+
+fn add_notes(fsv_path: &Path, notes_path: &Path, name: &str, context: HookContext, config_path: &Path) -> FsvExitCode {
+    let config = match load_config(config_path) {
+        Ok(config) => config,
+        Err(code) => return code,
+    };
+    if let Err(code) = run_pre_hooks(&config.hooks, &context) {
+        return code;
+    }
+
+    let result = FunScriptVideo::fsv::add_fsv_notes(fsv_path, notes_path, name);
+    config.hooks.run_post(&context, result.as_ref().err().map(ToString::to_string).as_deref());
+    match result {
+        Ok(()) => {
+            info!("Notes '{}' added to FSV file successfully.", name);
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error adding notes to FSV file", &err);
+            err.exit_code()
+        },
+    }
+}
+
+fn show_notes(path: &Path) -> FsvExitCode {
+    match FunScriptVideo::fsv::read_fsv_notes(path) {
+        Ok(Some(notes)) => {
+            print!("{}", notes);
+            if !notes.ends_with('\n') {
+                println!();
+            }
+            FsvExitCode::Success
+        },
+        Ok(None) => {
+            warn!("FSV file has no notes.");
+            FsvExitCode::NotFound
+        },
+        Err(err) => {
+            log_error("Error reading FSV notes", &err);
+            err.exit_code()
+        },
+    }
+}
+
+fn add_hook_context(fsv_path: &Path, item_type: ItemType, item_path: &Path) -> HookContext {
+    HookContext::new(HookOperation::Add, fsv_path, serde_json::json!({ "item_type": item_type, "item_path": item_path }))
+}
+
+async fn add_item_to_fsv(args: AddArgs, item_type: ItemType, context: HookContext, config_path: &Path, db_client: &DbClient, interactive: bool) -> FsvExitCode {
+    let config = match load_config(config_path) {
+        Ok(config) => config,
+        Err(code) => return code,
+    };
+    if let Err(code) = run_pre_hooks(&config.hooks, &context) {
+        return code;
+    }
+
+    let result = FunScriptVideo::fsv::add_to_fsv(args.naming_policy(config.naming_policy), db_client, interactive).await;
+    config.hooks.run_post(&context, result.as_ref().err().map(ToString::to_string).as_deref());
+    match result {
+        Ok(_) => {
+            info!("{} added to FSV file successfully.", item_type.get_name());
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            error!("Error adding {} to FSV file: {}", item_type.get_name(), err);
+            err.exit_code()
+        },
+    }
+}
+
+fn remove(path: &Path, entry_type: EntryType, entry_id: String, config_path: &Path) -> FsvExitCode {
+    let hooks = match load_config(config_path) {
+        Ok(config) => config.hooks,
+        Err(code) => return code,
+    };
+    let context = HookContext::new(HookOperation::Remove, path, serde_json::json!({ "entry_type": entry_type.get_name().to_lowercase(), "entry_id": entry_id }));
+    if let Err(code) = run_pre_hooks(&hooks, &context) {
+        return code;
+    }
+
+    let result = FunScriptVideo::fsv::remove_from_fsv(path, entry_type, &entry_id);
+    hooks.run_post(&context, result.as_ref().err().map(ToString::to_string).as_deref());
+    match result {
+        Ok(_) => {
+            info!("Entry removed from FSV file successfully.");
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error removing entry from FSV file", &err);
+            err.exit_code()
+        },
+    }
+}
+
+fn undo(path: &Path, steps: u32, force: bool, list: bool) -> FsvExitCode {
+    if list {
+        return match FunScriptVideo::trash::list_snapshots(path) {
+            Ok(snapshots) => {
+                for snapshot in snapshots.iter().rev() {
+                    println!("{}", snapshot.description);
+                }
+                FsvExitCode::Success
+            },
+            Err(err) => {
+                log_error("Error reading trash", &err);
+                err.exit_code()
+            },
+        };
+    }
+
+    for _ in 0..steps {
+        let result = FunScriptVideo::fsv::undo_remove(path, force);
+        match result {
+            Ok(snapshot) => info!("Restored removed {}.", snapshot.description),
+            Err(err) => {
+                log_error("Error undoing removal", &err);
+                return err.exit_code();
+            },
+        }
+    }
+
+    FsvExitCode::Success
+}
+
+fn extract(path: &Path, output_dir: &Path, options: ExtractOptions) -> FsvExitCode {
+    #[cfg(feature = "s3")]
+    let result = match s3_location(path) {
+        Some(Ok((storage, key))) => FunScriptVideo::storage::extract_stored(&storage, &key, output_dir, &options),
+        Some(Err(err)) => Err(err.into()),
+        None => FunScriptVideo::fsv::extract_fsv(path, output_dir, &options),
+    };
+    #[cfg(not(feature = "s3"))]
+    let result = FunScriptVideo::fsv::extract_fsv(path, output_dir, &options);
+    match result {
+        Ok(_) => {
+            info!("FSV file extracted successfully.");
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error extracting FSV file", &err);
+            err.exit_code()
+        },
+    }
+}
+
+/// Stream one entry of the archive to stdout, buffered as the entry is decompressed rather than held in memory.
+fn extract_entry(path: &Path, entry: &str, name_matching: NameMatching) -> FsvExitCode {
+    let mut stdout = std::io::BufWriter::new(std::io::stdout().lock());
+    #[cfg(feature = "s3")]
+    let result = match s3_location(path) {
+        Some(Ok((storage, key))) => FunScriptVideo::storage::copy_stored_entry(&storage, &key, entry, name_matching, &mut stdout),
+        Some(Err(err)) => Err(err.into()),
+        None => FunScriptVideo::fsv::copy_fsv_entry(path, entry, name_matching, &mut stdout),
+    };
+    #[cfg(not(feature = "s3"))]
+    let result = FunScriptVideo::fsv::copy_fsv_entry(path, entry, name_matching, &mut stdout);
+    let result = result.and_then(|copied| Ok(stdout.flush().map(|_| copied)?));
+    match result {
+        Ok(copied) => {
+            info!("Wrote {} bytes of '{}' to stdout.", copied, entry);
+            FsvExitCode::Success
+        },
+        // The reading end stopping early (e.g. `| head`) is not a failure of the extraction
+        Err(FsvError::Io(err)) if err.kind() == std::io::ErrorKind::BrokenPipe => FsvExitCode::Success,
+        Err(err) => {
+            log_error("Error extracting FSV entry", &err);
+            err.exit_code()
+        },
+    }
+}
+
+/// Info of a local FSV, or of one at an `http(s)://` or `s3://` URL with the `http` or `s3` feature.
+fn fsv_info(path: &Path, options: &InfoOptions) -> Result<FsvInfo, FsvError> {
+    #[cfg(feature = "s3")]
+    if let Some(location) = s3_location(path) {
+        let (storage, key) = location?;
+        return FunScriptVideo::storage::stored_info(&storage, &key, options);
+    }
+
+    #[cfg(feature = "http")]
+    if let Some(url) = FunScriptVideo::remote::as_url(path) {
+        return FunScriptVideo::remote::get_url_info(url, options);
+    }
+
+    FunScriptVideo::fsv::get_fsv_info_with_options(path, options)
+}
+
+fn info(path: &Path, options: InfoOptions, json: bool) -> FsvExitCode {
+    let result = fsv_info(path, &options);
+    let fsv_info = match result {
         Ok(info) => info,
         Err(err) => {
-            error!("Error getting FSV file info: {}", err);
-            return;
+            log_error("Error getting FSV file info", &err);
+            return err.exit_code();
         }
     };
 
+    if json {
+        match serde_json::to_string_pretty(&fsv_info) {
+            Ok(json) => println!("{}", json),
+            Err(err) => {
+                log_error("Error serializing FSV file info", &err);
+                return FsvExitCode::Failure;
+            }
+        }
+
+        return FsvExitCode::Success;
+    }
+
     println!("FSV File Info:");
     println!("Title: {}", fsv_info.title);
+    for (language, title) in &fsv_info.localized_titles {
+        println!("Title ({}): {}", language, title);
+    }
+    if !fsv_info.studio.is_empty() {
+        println!("Studio: {}", fsv_info.studio);
+    }
+    if !fsv_info.performers.is_empty() {
+        println!("Performers: {}", fsv_info.performers.join(", "));
+    }
+    if !fsv_info.creators.is_empty() {
+        println!("Creators:");
+        for creator in &fsv_info.creators {
+            println!("  {}", creator.name);
+            for social in &creator.socials {
+                match social.platform {
+                    Some(platform) => println!("    {}: {}", platform.label(), social.url),
+                    None => println!("    {}", social.url),
+                }
+            }
+        }
+    }
+    if let Some(details) = &fsv_info.details {
+        println!("Format Version: {}", details.format_version);
+        println!("Tags: {}", if details.tags.is_empty() { "(none)".to_string() } else { details.tags.join(", ") });
+        println!("Entries ({}):", details.entries.len());
+        for entry in &details.entries {
+            let kind = entry.item_type.map(|t| t.get_name().to_string()).unwrap_or_else(|| "Extra".to_string());
+            println!("  {} [{}]", entry.name, kind);
+            if !entry.present {
+                println!("    Missing from archive");
+            }
+            if let (Some(size), Some(compressed)) = (entry.uncompressed_size, entry.compressed_size) {
+                println!("    Size: {} bytes ({} compressed)", size, compressed);
+            }
+            if let Some(duration) = entry.duration {
+                println!("    Duration: {} ms", duration.as_millis());
+            }
+            if !entry.creators.is_empty() {
+                println!("    Creators: {}", entry.creators.join(", "));
+            }
+            println!("    Checksum: {}", entry.checksum.get_name());
+        }
+    }
+
     let mut missing_video_file = false;
     if !fsv_info.videos.is_empty() {
         println!("Videos ({}):", fsv_info.videos.len());
         for (video_name, is_present) in &fsv_info.videos {
-            println!("  {}: {}", video_name, if *is_present { "Present" } else { "Missing" });
-            if !*is_present {
-                missing_video_file = true;
+            let external = fsv_info.external_content.iter().find(|reference| &reference.name == video_name);
+            match (is_present, external) {
+                (true, _) => println!("  {}: Present", video_name),
+                (false, Some(reference)) => println!("  {}: External ({})", video_name, reference.source),
+                (false, None) => {
+                    println!("  {}: Missing", video_name);
+                    missing_video_file = true;
+                },
             }
         }
     }
@@ -468,6 +1878,10 @@ fn info(path: &PathBuf) {
         }
     }
 
+    if let Some((notes_name, is_present)) = &fsv_info.notes {
+        println!("Notes: {}: {}", notes_name, if *is_present { "Present" } else { "Missing" });
+    }
+
     if !fsv_info.extra_files.is_empty() {
         println!("WARNING: Extra files found in FSV archive ({}):", fsv_info.extra_files.len());
         for extra_file in &fsv_info.extra_files {
@@ -475,6 +1889,37 @@ fn info(path: &PathBuf) {
         }
     }
 
+    if let Some(sizes) = &fsv_info.sizes {
+        println!("Sizes ({} entries):", sizes.entries.len());
+        for entry in &sizes.entries {
+            println!("  {}: {} ({} compressed, {:.0}%)", entry.name, format_size(entry.uncompressed_size), format_size(entry.compressed_size), compression_ratio(entry.compressed_size, entry.uncompressed_size));
+        }
+        println!("  Total: {} ({} compressed, {:.0}%)", format_size(sizes.uncompressed_size), format_size(sizes.compressed_size), compression_ratio(sizes.compressed_size, sizes.uncompressed_size));
+    }
+
+    if !fsv_info.extensions.is_empty() {
+        println!("Extensions ({}):", fsv_info.extensions.len());
+        for extension in &fsv_info.extensions {
+            let status = match (extension.known, extension.required) {
+                (true, true) => "known, required",
+                (true, false) => "known",
+                (false, true) => "unknown, required",
+                (false, false) => "unknown",
+            };
+            println!("  {} ({})", extension.id, status);
+            for issue in &extension.issues {
+                println!("    WARNING: {}", issue);
+            }
+        }
+    }
+
+    if !fsv_info.name_mismatches.is_empty() {
+        println!("WARNING: Entry names differ from metadata only in case or path separators ({}):", fsv_info.name_mismatches.len());
+        for (expected, found) in &fsv_info.name_mismatches {
+            println!("  {} -> {}", expected, found);
+        }
+    }
+
     if missing_video_file {
         println!("WARNING: Some video files are missing from the FSV archive.");
     }
@@ -496,12 +1941,1297 @@ fn info(path: &PathBuf) {
     else {
         println!("Container State: Content Complete");
     }
+
+    FsvExitCode::Success
 }
 
-fn rebuild(path: PathBuf) {
-    let result = FunScriptVideo::fsv::rebuild_fsv(&path);
+fn rebuild(path: PathBuf, options: RebuildOptions) -> FsvExitCode {
+    let result = FunScriptVideo::fsv::rebuild_fsv(&path, &options);
     match result {
-        Ok(_) => info!("FSV file rebuilt successfully."),
-        Err(err) => error!("Error rebuilding FSV file: {}", err),
+        Ok(removed) => {
+            for name in &removed {
+                info!("Removed duplicate metadata entry: {}", name);
+            }
+            info!("FSV file rebuilt successfully.");
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error rebuilding FSV file", &err);
+            err.exit_code()
+        },
+    }
+}
+
+fn recover(dir: &Path, database: &Database) -> FsvExitCode {
+    let result = FunScriptVideo::journal::recover_dir(dir);
+    let recoveries = match result {
+        Ok(recoveries) => recoveries,
+        Err(err) => {
+            log_error("Error recovering interrupted operations", &err);
+            return err.exit_code();
+        },
+    };
+
+    let mut pending = Vec::new();
+    for recovery in &recoveries {
+        let outcome = match recovery.outcome {
+            RecoveryOutcome::RolledBack => "Rolled back",
+            RecoveryOutcome::Completed => "Completed",
+            RecoveryOutcome::Pending => {
+                pending.push(recovery.record.target.as_path());
+                continue;
+            },
+        };
+        info!("{} interrupted {} of '{}'", outcome, recovery.record.operation.get_name(), recovery.record.target.display());
+    }
+    info!("Recovered {} interrupted operation(s).", recoveries.len() - pending.len());
+    if pending.is_empty() {
+        return FsvExitCode::Success;
+    }
+
+    // Only database changes that go with an archive change need the database
+    database.with(|db_client| {
+        let mut exit_code = FsvExitCode::Success;
+        for target in pending {
+            match database.rt.block_on(FunScriptVideo::archive_tx::apply_pending(target, db_client)) {
+                Ok(true) => info!("Applied pending database changes for '{}'", target.display()),
+                Ok(false) => (),
+                Err(err) => {
+                    log_error(&format!("Error applying pending database changes for '{}'", target.display()), &err);
+                    exit_code = err.exit_code();
+                },
+            }
+        }
+
+        exit_code
+    })
+}
+
+async fn hash(path: &Path, write: bool, verify: bool, db_client: &DbClient) -> FsvExitCode {
+    let mut cache = EntryHashCache::load(db_client, path, HashAlgorithm::Blake3).await;
+    if verify {
+        let exit_code = verify_hashes(path, &mut cache);
+        cache.save(db_client).await;
+        return exit_code;
+    }
+
+    let result = match write {
+        true => FunScriptVideo::fsv::store_content_hashes(path, Some(&mut cache)).map_err(|err| (err.to_string(), err.exit_code())),
+        false => FunScriptVideo::fsv::hash_fsv(path, Some(&mut cache)).map_err(|err| (err.to_string(), err.exit_code())),
+    };
+    debug!("{} entry digests served from the hash cache", cache.hits());
+    cache.save(db_client).await;
+    let hashes = match result {
+        Ok(hashes) => hashes,
+        Err((err, exit_code)) => {
+            error!("Error hashing FSV file: {}", err);
+            return exit_code;
+        }
+    };
+
+    for (name, hash) in &hashes.entries {
+        println!("{}  {}", hash, name);
+    }
+    println!("{}  (root)", hashes.root);
+    if write {
+        info!("Content hashes stored in FSV metadata.");
+    }
+
+    FsvExitCode::Success
+}
+
+fn verify_hashes(path: &Path, cache: &mut EntryHashCache) -> FsvExitCode {
+    let verification = match FunScriptVideo::fsv::verify_content_hashes(path, Some(cache)) {
+        Ok(Some(verification)) => verification,
+        Ok(None) => {
+            error!("FSV file has no stored content hashes. Use `hash --write` to add them.");
+            return FsvExitCode::NotFound;
+        },
+        Err(err) => {
+            log_error("Error verifying FSV content hashes", &err);
+            return err.exit_code();
+        }
+    };
+
+    for name in &verification.mismatched {
+        error!("Hash mismatch: {}", name);
+    }
+    for name in &verification.missing {
+        error!("Hashed entry missing from archive: {}", name);
+    }
+    for name in &verification.unlisted {
+        warn!("Entry not covered by stored hashes: {}", name);
+    }
+
+    if verification.is_ok() {
+        info!("All content hashes match.");
+        FsvExitCode::Success
+    }
+    else {
+        if !verification.root_matches {
+            error!("Root hash does not match.");
+        }
+        FsvExitCode::ValidationFailed
+    }
+}
+
+fn history(path: &Path, enable: bool) -> FsvExitCode {
+    if enable {
+        return match FunScriptVideo::fsv::enable_fsv_history(path) {
+            Ok(true) => {
+                info!("Change history enabled.");
+                FsvExitCode::Success
+            },
+            Ok(false) => {
+                info!("Change history is already enabled.");
+                FsvExitCode::Success
+            },
+            Err(err) => {
+                log_error("Error enabling change history", &err);
+                err.exit_code()
+            },
+        };
+    }
+
+    let metadata = match FunScriptVideo::fsv::read_fsv_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            log_error("Error reading FSV metadata", &err);
+            return err.exit_code();
+        },
+    };
+
+    match FunScriptVideo::history::history_from_metadata(&metadata) {
+        Ok(entries) => {
+            if !FunScriptVideo::history::is_enabled(&metadata) {
+                info!("Change history is not enabled for this file (enable it with --enable).");
+            }
+            for entry in entries {
+                println!("{}  {}  ({})", entry.timestamp, entry.operation, entry.tool);
+            }
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Malformed change history", &err);
+            FsvExitCode::Metadata
+        },
+    }
+}
+
+fn normalize_metadata(path: &Path) -> FsvExitCode {
+    let result = FunScriptVideo::fsv::normalize_fsv_metadata(path);
+    match result {
+        Ok(true) => {
+            info!("FSV metadata normalized successfully.");
+            FsvExitCode::Success
+        },
+        Ok(false) => {
+            info!("FSV metadata is already in canonical form.");
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error normalizing FSV metadata", &err);
+            err.exit_code()
+        },
+    }
+}
+
+fn cat_metadata(path: &Path) -> FsvExitCode {
+    let mut stdout = std::io::stdout().lock();
+    let result = FunScriptVideo::fsv::copy_fsv_entry(path, "metadata.json", NameMatching::Strict, &mut stdout);
+    match result.and_then(|_| Ok(stdout.flush()?)) {
+        Ok(()) => FsvExitCode::Success,
+        Err(err) => {
+            log_error("Error reading FSV metadata", &err);
+            err.exit_code()
+        },
+    }
+}
+
+fn metadata_schema() -> FsvExitCode {
+    match serde_json::to_string_pretty(&FunScriptVideo::schema::metadata_schema()) {
+        Ok(schema) => {
+            println!("{}", schema);
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error generating metadata schema", &err);
+            FsvExitCode::Failure
+        },
+    }
+}
+
+fn validate_metadata(path: &Path, json: bool) -> FsvExitCode {
+    let document = std::fs::read(path).path_context("reading", path).map_err(FsvError::from)
+        .and_then(|data| Ok(serde_json::from_slice::<serde_json::Value>(&data)?));
+    let document = match document {
+        Ok(document) => document,
+        Err(err) => {
+            log_error("Error reading metadata document", &err);
+            return err.exit_code();
+        },
+    };
+
+    let report = FunScriptVideo::schema::validate_metadata_document(&document);
+    if json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(report) => println!("{}", report),
+            Err(err) => {
+                log_error("Error serializing report", &err);
+                return FsvExitCode::Failure;
+            },
+        }
+    }
+    else {
+        for warning in &report.warnings {
+            warn!("{}", warning);
+        }
+        for error in &report.errors {
+            error!("{}", error);
+        }
+    }
+
+    match report.is_valid() {
+        true => {
+            info!("Metadata document conforms to the schema.");
+            FsvExitCode::Success
+        },
+        false => FsvExitCode::ValidationFailed,
+    }
+}
+
+fn put_metadata(path: &Path, metadata_path: &Path, allow_content_incomplete: bool) -> FsvExitCode {
+    let metadata_json = match metadata_path.to_str() {
+        Some("-") => std::io::read_to_string(std::io::stdin()),
+        _ => std::fs::read_to_string(metadata_path).path_context("reading", metadata_path),
+    };
+    let metadata_json = match metadata_json.map_err(FsvEditError::from) {
+        Ok(metadata_json) => metadata_json,
+        Err(err) => {
+            log_error("Error reading new metadata", &err);
+            return err.exit_code();
+        },
+    };
+
+    let result = FunScriptVideo::fsv::put_fsv_metadata(path, &metadata_json, allow_content_incomplete);
+    match result {
+        Ok(Some(report)) => {
+            for issue in report.issues {
+                warn!("{}", issue);
+            }
+            info!("FSV metadata replaced successfully.");
+            FsvExitCode::Success
+        },
+        Ok(None) => {
+            info!("FSV metadata is unchanged.");
+            FsvExitCode::Success
+        },
+        Err(FsvEditError::MetadataRejected(report)) => {
+            for issue in report.errors() {
+                error!("{}", issue);
+            }
+            error!("FSV metadata not replaced: the FSV would not validate with it");
+            (&report.state).into()
+        },
+        Err(err) => {
+            log_error("Error replacing FSV metadata", &err);
+            err.exit_code()
+        },
+    }
+}
+
+async fn sync_creators(path: &Path, direction: CreatorSyncDirection, dry_run: bool, db_client: &DbClient) -> FsvExitCode {
+    let result = FunScriptVideo::fsv::sync_creators(path, direction, dry_run, db_client).await;
+    match result {
+        Ok(report) => {
+            for conflict in &report.conflicts {
+                warn!("{}", conflict);
+            }
+            for key in &report.missing {
+                warn!("Creator '{}' is not in the database{}", key, if direction == CreatorSyncDirection::Push && !dry_run { ", added it" } else { "" });
+            }
+            for work_name in &report.unlinked {
+                info!("Creator of '{}' has no creator key and was not synced", work_name);
+            }
+
+            let outcome = match (dry_run, direction) {
+                (true, _) => "found",
+                (false, CreatorSyncDirection::Pull) => "refreshed from the database",
+                (false, CreatorSyncDirection::Push) => "stored in the database",
+            };
+            info!("{} creator(s) up to date, {} differing {}.", report.unchanged, report.conflicts.len(), outcome);
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error syncing creators", &err);
+            err.exit_code()
+        },
+    }
+}
+
+async fn merge_creators(winner: &str, loser: &str, fsv_paths: &[PathBuf], db_client: &DbClient) -> FsvExitCode {
+    let merge = match db_client.merge_creators(winner, loser).await {
+        Ok(Some(merge)) => merge,
+        Ok(None) => {
+            error!("Creator '{}' or '{}' not found in database.", winner, loser);
+            return FsvExitCode::NotFound;
+        },
+        Err(err) => {
+            log_error("Error merging creators", &err);
+            return err.exit_code();
+        },
+    };
+    info!("Creator '{}' merged into '{}': {} social(s) moved, {} indexed work(s) now credit '{}'.", merge.loser_key, merge.winner_key, merge.socials_moved, merge.works_repointed, merge.winner_key);
+
+    // The database is merged either way, so every file is tried and the last failure decides the exit code
+    let mut exit_code = FsvExitCode::Success;
+    for path in fsv_paths {
+        match FunScriptVideo::fsv::relink_merged_creator(path, &merge.loser_key, &merge.winner_key, db_client).await {
+            Ok(report) => info!("'{}': {} creator(s) relinked, {} refreshed from the database.", path.display(), report.relinked.len(), report.conflicts.len()),
+            Err(err) => {
+                log_error(&format!("Error updating creators of '{}'", path.display()), &err);
+                exit_code = err.exit_code();
+            },
+        }
+    }
+
+    exit_code
+}
+
+fn seconds_to_ms(seconds: f64) -> u64 {
+    (seconds.max(0.0) * 1000.0).round() as u64
+}
+
+fn preview(path: &Path, selection: PreviewSelection, source: Option<&str>, name: &str) -> FsvExitCode {
+    let result = FunScriptVideo::fsv::create_preview(path, selection, source, name);
+    match result {
+        Ok(preview) => {
+            info!("Preview '{}' ({} segment(s) from '{}') added to FSV file.", preview.name, preview.segments.len(), preview.source);
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error creating preview", &err);
+            err.exit_code()
+        },
+    }
+}
+
+fn align(path: &Path, options: &AlignOptions) -> FsvExitCode {
+    let result = FunScriptVideo::fsv::align_script_variant(path, options);
+    match result {
+        Ok(report) => {
+            let offset = report.estimate.offset_ms;
+            info!("Estimated start_offset of '{}' against '{}': {} ms (score {:.2}, was {} ms).", report.script, report.video, offset, report.estimate.score, report.previous_offset);
+            if !options.dry_run && offset != report.previous_offset {
+                info!("start_offset of '{}' updated.", report.script);
+            }
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error aligning script", &err);
+            err.exit_code()
+        },
+    }
+}
+
+async fn watch(args: WatchArgs, db_client: &DbClient, progress: &dyn ProgressListener) -> FsvExitCode {
+    let result = FunScriptVideo::watch::watch_folder_with_progress(args, db_client, progress).await;
+    match result {
+        Ok(_) => FsvExitCode::Success,
+        Err(err) => {
+            log_error("Error watching folder", &err);
+            err.exit_code()
+        }
+    }
+}
+
+async fn library(cmd: LibraryCommands, db_client: &DbClient) -> FsvExitCode {
+    match cmd {
+        LibraryCommands::Scan { dir, resume, jobs } => {
+            let options = ScanOptions { resume };
+            #[cfg(feature = "s3")]
+            let result = match FunScriptVideo::s3::parse_s3_url(&dir) {
+                Some((bucket, prefix)) => match FunScriptVideo::s3::S3Config::from_env() {
+                    Ok(config) => FunScriptVideo::library::scan_storage(db_client, &FunScriptVideo::s3::S3Storage::new(config, &bucket, &prefix)).await,
+                    Err(err) => Err(err.into()),
+                },
+                None => FunScriptVideo::library::scan_library_with_options(db_client, &dir, &options, &jobs.scheduler(), &ProgressLog::new()).await,
+            };
+            #[cfg(not(feature = "s3"))]
+            let result = FunScriptVideo::library::scan_library_with_options(db_client, &dir, &options, &jobs.scheduler(), &ProgressLog::new()).await;
+            match result {
+                Ok(summary) => {
+                    if summary.resumed > 0 {
+                        info!("Resumed after {} files scanned before the interruption.", summary.resumed);
+                    }
+                    if summary.moved > 0 {
+                        info!("{} moved archives were recognized and kept their ratings and history.", summary.moved);
+                    }
+                    info!("Library scan finished: {} indexed, {} unchanged, {} removed, {} failed, {} skipped.", summary.indexed, summary.unchanged, summary.removed, summary.failed, summary.skipped);
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error scanning library", &err);
+                    err.exit_code()
+                },
+            }
+        },
+        LibraryCommands::Du { dir, by } => {
+            let result = FunScriptVideo::library::library_usage(db_client, by, dir.as_deref()).await;
+            match result {
+                Ok(records) => {
+                    print_usage_records(&records, by);
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error reading library usage", &err);
+                    err.exit_code()
+                },
+            }
+        },
+        LibraryCommands::Retag { dir, add, remove, filter: filters } => {
+            let mut filter = LibraryFilter::default();
+            for condition in filters {
+                match condition {
+                    RetagFilter::Tag(tag) => filter.tag = Some(tag),
+                    RetagFilter::Performer(performer) => filter.performer = Some(performer),
+                    RetagFilter::Search(search) => filter.search = Some(search),
+                }
+            }
+
+            let result = FunScriptVideo::library::retag_library(db_client, dir.as_deref(), filter, &add, &remove).await;
+            match result {
+                Ok(summary) => {
+                    info!("Library retag finished: {} changed, {} unchanged, {} failed.", summary.changed, summary.unchanged, summary.failed);
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error retagging library", &err);
+                    err.exit_code()
+                },
+            }
+        },
+        LibraryCommands::Relocate { from, to, dry_run } => {
+            let result = FunScriptVideo::library::relocate_library(db_client, &from, &to, dry_run).await;
+            match result {
+                Ok(summary) => {
+                    for (old_path, new_path) in &summary.moves {
+                        match dry_run {
+                            true => println!("{} -> {}", old_path, new_path),
+                            false => debug!("Moved '{}' to '{}'", old_path, new_path),
+                        }
+                    }
+                    for path in &summary.missing {
+                        warn!("{} does not exist", path);
+                    }
+                    match dry_run {
+                        true => info!("{} paths would be rewritten, {} of them to files that don't exist.", summary.moves.len(), summary.missing.len()),
+                        false => info!("Library relocated: {} paths rewritten, {} of them to files that don't exist.", summary.moves.len(), summary.missing.len()),
+                    }
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error relocating library", &err);
+                    err.exit_code()
+                },
+            }
+        },
+    }
+}
+
+fn print_usage_records(records: &[UsageRecord], by: UsageGrouping) {
+    if records.is_empty() {
+        println!("No works found.");
+        return;
+    }
+
+    for record in records {
+        let ratio = compression_ratio(record.compressed_size, record.uncompressed_size);
+        match by {
+            UsageGrouping::Work => println!("{:>10}  {:>10}  {:>10}  {:>4.0}%  {}", format_size(record.file_size), format_size(record.compressed_size), format_size(record.uncompressed_size), ratio, record.name),
+            UsageGrouping::Tag | UsageGrouping::Creator => println!("{:>10}  {:>10}  {:>10}  {:>4.0}%  {} ({} works)", format_size(record.file_size), format_size(record.compressed_size), format_size(record.uncompressed_size), ratio, record.name, record.works),
+        }
+    }
+
+    let total = |size: fn(&UsageRecord) -> u64| records.iter().map(size).sum::<u64>();
+    if by == UsageGrouping::Work {
+        let (compressed, uncompressed) = (total(|record| record.compressed_size), total(|record| record.uncompressed_size));
+        println!("{:>10}  {:>10}  {:>10}  {:>4.0}%  total", format_size(total(|record| record.file_size)), format_size(compressed), format_size(uncompressed), compression_ratio(compressed, uncompressed));
+    }
+}
+
+/// `bytes` with a binary unit, e.g. `1.5 GiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
+
+async fn stats(dir: Option<&Path>, format: StatsFormat, top: u32, db_client: &DbClient) -> FsvExitCode {
+    let stats = match FunScriptVideo::library::library_stats(db_client, dir, top).await {
+        Ok(stats) => stats,
+        Err(err) => {
+            log_error("Error reading library statistics", &err);
+            return err.exit_code();
+        },
+    };
+
+    if let StatsFormat::Json = format {
+        match serde_json::to_string_pretty(&stats) {
+            Ok(json) => println!("{}", json),
+            Err(err) => {
+                log_error("Error serializing library statistics", &err);
+                return FsvExitCode::Failure;
+            },
+        }
+
+        return FsvExitCode::Success;
+    }
+
+    let hours = |ms: u64| format!("{:.1} h", ms as f64 / 3_600_000.0);
+    let counts = |counts: &[StatsCount]| counts.iter().map(|count| vec![count.name.clone(), count.count.to_string()]).collect::<Vec<_>>();
+    let sizes = |sizes: &[StatsSize]| sizes.iter().map(|size| vec![size.name.clone(), size.videos.to_string(), format_size(size.size)]).collect::<Vec<_>>();
+    let sections = [
+        ("Library", vec!["Statistic", "Value"], vec![
+            vec!["Archives".to_string(), stats.works.to_string()],
+            vec!["Size".to_string(), format_size(stats.size)],
+            vec!["Video formats".to_string(), stats.video_formats.to_string()],
+            vec!["Total video".to_string(), hours(stats.total_video_ms)],
+            vec!["Unique video".to_string(), hours(stats.unique_video_ms)],
+        ]),
+        ("Scripts per axis", vec!["Axis", "Scripts"], counts(&stats.scripts_by_axis)),
+        ("Top creators", vec!["Creator", "Works"], counts(&stats.top_creators)),
+        ("Top tags", vec!["Tag", "Works"], counts(&stats.top_tags)),
+        ("Size per codec", vec!["Codec", "Videos", "Size"], sizes(&stats.size_by_codec)),
+        ("Size per resolution", vec!["Resolution", "Videos", "Size"], sizes(&stats.size_by_resolution)),
+    ];
+
+    for (i, (title, headers, rows)) in sections.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        match format {
+            StatsFormat::Markdown => {
+                println!("## {}\n", title);
+                println!("| {} |", headers.join(" | "));
+                println!("|{}", "---|".repeat(headers.len()));
+                for row in rows {
+                    println!("| {} |", row.iter().map(|cell| cell.replace('|', "\\|")).collect::<Vec<_>>().join(" | "));
+                }
+            },
+            _ => {
+                println!("{}:", title);
+                if rows.is_empty() {
+                    println!("  None");
+                }
+                let width = rows.iter().map(|row| row[0].chars().count()).max().unwrap_or(0);
+                for row in rows {
+                    println!("  {:<width$}  {}", row[0], row[1..].join("  "));
+                }
+            },
+        }
+    }
+
+    FsvExitCode::Success
+}
+
+async fn list(filter: LibraryFilter, db_client: &DbClient) -> FsvExitCode {
+    let result = FunScriptVideo::library::list_works(db_client, filter).await;
+    let entries = match result {
+        Ok(entries) => entries,
+        Err(err) => {
+            log_error("Error listing library", &err);
+            return err.exit_code();
+        },
+    };
+
+    if entries.is_empty() {
+        println!("No works found.");
+        return FsvExitCode::Success;
+    }
+
+    for entry in entries {
+        let mut flags = Vec::new();
+        if let Some(rating) = entry.rating {
+            flags.push(format!("{}/5", rating));
+        }
+
+        if entry.favorite {
+            flags.push("favorite".to_string());
+        }
+
+        match flags.is_empty() {
+            true => println!("{}", entry.work.title),
+            false => println!("{} [{}]", entry.work.title, flags.join(", ")),
+        }
+        println!("  {}", entry.work.path);
+        if !entry.work.studio.is_empty() {
+            println!("  Studio: {}", entry.work.studio);
+        }
+
+        if !entry.work.performers.is_empty() {
+            println!("  Performers: {}", entry.work.performers.join(", "));
+        }
+
+        if !entry.work.tags.is_empty() {
+            println!("  Tags: {}", entry.work.tags.join(", "));
+        }
+
+        if let Some(history) = entry.history {
+            println!("  Last played: {} UTC ({} plays, resume at {} ms)", history.last_played, history.play_count, history.position_ms);
+        }
+    }
+
+    FsvExitCode::Success
+}
+
+async fn rate(path: &Path, rating: Option<u8>, db_client: &DbClient) -> FsvExitCode {
+    let result = FunScriptVideo::library::rate_work(db_client, path, rating).await;
+    match result {
+        Ok(()) => {
+            match rating {
+                Some(rating) => info!("Rated '{}' {}/5.", path.display(), rating),
+                None => info!("Rating of '{}' cleared.", path.display()),
+            }
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error rating work", &err);
+            err.exit_code()
+        },
+    }
+}
+
+async fn favorite(path: &Path, favorite: bool, db_client: &DbClient) -> FsvExitCode {
+    let result = FunScriptVideo::library::set_favorite(db_client, path, favorite).await;
+    match result {
+        Ok(()) => {
+            match favorite {
+                true => info!("Marked '{}' as favorite.", path.display()),
+                false => info!("Unmarked '{}' as favorite.", path.display()),
+            }
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error updating favorite", &err);
+            err.exit_code()
+        },
+    }
+}
+
+async fn edit(cmd: EditCommands, db_client: &DbClient) -> FsvExitCode {
+    match cmd {
+        EditCommands::Title { path, title, lang: Some(lang) } => {
+            let result = FunScriptVideo::fsv::edit_fsv_localized_title(&path, &lang, &title);
+            match result {
+                Ok(language) => {
+                    info!("Title ({}) updated successfully.", language);
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error updating title", &err);
+                    err.exit_code()
+                },
+            }
+        },
+        EditCommands::Title { path, title, lang: None } => {
+            let result = FunScriptVideo::fsv::edit_fsv_title(&path, &title);
+            match result {
+                Ok(_) => {
+                    info!("Title updated successfully.");
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error updating title", &err);
+                    err.exit_code()
+                },
+            }
+        },
+        EditCommands::Tags { path, add, remove, apply_implied } => {
+            let result = FunScriptVideo::fsv::edit_fsv_tags(&path, add, remove, apply_implied, db_client).await;
+            match result {
+                Ok(implied) if !implied.is_empty() => {
+                    info!("Tags updated successfully, adding implied tags: {}.", implied.join(", "));
+                    FsvExitCode::Success
+                },
+                Ok(_) => {
+                    info!("Tags updated successfully.");
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error updating tags", &err);
+                    err.exit_code()
+                },
+            }
+        },
+        EditCommands::Studio { path, studio } => {
+            let result = FunScriptVideo::fsv::edit_fsv_studio(&path, &studio);
+            match result {
+                Ok(_) => {
+                    info!("Studio updated successfully.");
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error updating studio", &err);
+                    err.exit_code()
+                },
+            }
+        },
+        EditCommands::Description { path, item_type, name, description } => {
+            let result = FunScriptVideo::fsv::edit_fsv_description(&path, item_type, &name, &description);
+            match result {
+                Ok(_) => {
+                    info!("Description of {} '{}' updated successfully.", item_type.get_name_lower(), name);
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error updating description", &err);
+                    err.exit_code()
+                },
+            }
+        },
+        EditCommands::Performers { path, add, remove } => {
+            let result = FunScriptVideo::fsv::edit_fsv_performers(&path, add, remove);
+            match result {
+                Ok(_) => {
+                    info!("Performers updated successfully.");
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error updating performers", &err);
+                    err.exit_code()
+                },
+            }
+        },
+        EditCommands::PruneOrphanCreators { path } => {
+            let result = FunScriptVideo::fsv::prune_orphan_creators(&path);
+            match result {
+                Ok(pruned) if pruned.is_empty() => {
+                    info!("No orphaned creator records found.");
+                    FsvExitCode::Success
+                },
+                Ok(pruned) => {
+                    info!("Removed {} orphaned creator record(s): {}", pruned.len(), pruned.join(", "));
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error pruning creator records", &err);
+                    err.exit_code()
+                },
+            }
+        },
+        EditCommands::Offset { path, script, video, offset_ms, clear: _ } => {
+            let result = FunScriptVideo::fsv::set_pair_offset(&path, &script, &video, offset_ms);
+            match result {
+                Ok(previous) => {
+                    let describe = |offset: Option<i64>| offset.map_or_else(|| "unset".to_string(), |offset| format!("{} ms", offset));
+                    info!("Offset of '{}' against '{}': {} (was {}).", script, video, describe(offset_ms), describe(previous));
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error updating offset", &err);
+                    err.exit_code()
+                },
+            }
+        },
+        EditCommands::SubtitleOffset { path, subtitle, video, offset_ms, clear: _ } => {
+            let result = FunScriptVideo::fsv::set_subtitle_offset(&path, &subtitle, &video, offset_ms);
+            match result {
+                Ok(previous) => {
+                    let describe = |offset: Option<i64>| offset.map_or_else(|| "unset".to_string(), |offset| format!("{} ms", offset));
+                    info!("Offset of '{}' against '{}': {} (was {}).", subtitle, video, describe(offset_ms), describe(previous));
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error updating subtitle offset", &err);
+                    err.exit_code()
+                },
+            }
+        },
+    }
+}
+
+fn script(cmd: ScriptCommands) -> FsvExitCode {
+    match cmd {
+        ScriptCommands::Convert { input, output, from, to } => {
+            let result = FunScriptVideo::convert::convert_file(&input, &output, from, to);
+            match result {
+                Ok((from, to)) => {
+                    info!("Converted '{}' ({}) to '{}' ({}).", input.display(), from, output.display(), to);
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error converting script", &err);
+                    err.exit_code()
+                },
+            }
+        },
+        ScriptCommands::Simplify { path, script, epsilon, min_interval, name } => {
+            let options = SimplifyOptions { epsilon, min_interval_ms: min_interval };
+            let result = FunScriptVideo::fsv::simplify_script_variant(&path, script.as_deref(), &options, name.as_deref());
+            match result {
+                Ok(name) => {
+                    info!("Script variant '{}' added to FSV file successfully.", name);
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error simplifying script", &err);
+                    err.exit_code()
+                },
+            }
+        },
+        ScriptCommands::Transform { path, script, invert, min, max, rescale, speed, name } => {
+            let range = match (min, max) {
+                (None, None) => None,
+                (min, max) => Some((min.unwrap_or(0), max.unwrap_or(100))),
+            };
+            let options = TransformOptions { invert, range, rescale, speed };
+            let result = FunScriptVideo::fsv::transform_script_variant(&path, script.as_deref(), &options, name.as_deref());
+            match result {
+                Ok(name) => {
+                    info!("Script variant '{}' added to FSV file successfully.", name);
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error transforming script", &err);
+                    err.exit_code()
+                },
+            }
+        },
+    }
+}
+
+async fn db(cmd: DbCommands, db_client: &DbClient, interactive: bool) -> FsvExitCode {
+    match cmd {
+        DbCommands::Creator(creator_cmd) => match creator_cmd {
+            DbCreatorCommands::Update { key_name, name, key, socials } => {
+                if name.is_none() && key.is_none() && socials.is_none() {
+                    error!("Nothing to update for creator '{}'.", key_name);
+                    return FsvExitCode::Usage;
+                }
+
+                let result = db_client.update_creator_info(&key_name, name.as_deref(), key.as_deref(), socials.as_deref()).await;
+                match result {
+                    Ok(true) => {
+                        info!("Creator info updated successfully.");
+                        FsvExitCode::Success
+                    },
+                    Ok(false) => {
+                        error!("Creator '{}' not found in database.", key_name);
+                        FsvExitCode::NotFound
+                    },
+                    Err(err) => {
+                        log_error("Error updating creator info", &err);
+                        err.exit_code()
+                    },
+                }
+            },
+            DbCreatorCommands::List { limit, offset } => {
+                let result = db_client.list_creators(limit, offset).await;
+                match result {
+                    Ok(records) => {
+                        print_creator_records(&records);
+                        FsvExitCode::Success
+                    },
+                    Err(err) => {
+                        log_error("Error listing creators", &err);
+                        err.exit_code()
+                    },
+                }
+            },
+            DbCreatorCommands::Merge { winner, loser, fsv_paths } => merge_creators(&winner, &loser, &fsv_paths, db_client).await,
+            DbCreatorCommands::ImportFromScript { script_path } => {
+                let result = FunScriptVideo::fsv::import_creator_from_script(&script_path, db_client, interactive).await;
+                match result {
+                    Ok(Some(imported)) => {
+                        let key = imported.key.as_deref().unwrap_or_default();
+                        match imported.created {
+                            true => info!("Creator '{}' created with key '{}'.", imported.creator_info.name, key),
+                            false => info!("Script creator is '{}' ({}).", key, imported.creator_info.name),
+                        }
+                        FsvExitCode::Success
+                    },
+                    Ok(None) => {
+                        info!("No creator imported from '{}'.", script_path.display());
+                        FsvExitCode::Success
+                    },
+                    Err(err) => {
+                        log_error("Error importing creator from script", &err);
+                        err.exit_code()
+                    },
+                }
+            },
+            DbCreatorCommands::Search { pattern, limit, offset } => {
+                let result = db_client.search_creators(&pattern, limit, offset).await;
+                match result {
+                    Ok(records) => {
+                        print_creator_records(&records);
+                        FsvExitCode::Success
+                    },
+                    Err(err) => {
+                        log_error("Error searching creators", &err);
+                        err.exit_code()
+                    },
+                }
+            },
+        },
+        DbCommands::Tag(tag_cmd) => match tag_cmd {
+            DbTagCommands::Add { name, aliases } => {
+                let result = db_client.insert_tag(&name, &aliases).await;
+                match result {
+                    Ok(_) => {
+                        info!("Tag '{}' added to vocabulary.", name);
+                        FsvExitCode::Success
+                    },
+                    Err(err) => {
+                        log_error("Error adding tag", &err);
+                        err.exit_code()
+                    },
+                }
+            },
+            DbTagCommands::Alias { name, alias } => {
+                let result = db_client.add_tag_alias(&name, &alias).await;
+                match result {
+                    Ok(true) => {
+                        info!("Alias '{}' added to tag '{}'.", alias, name);
+                        FsvExitCode::Success
+                    },
+                    Ok(false) => {
+                        error!("Tag '{}' not found in vocabulary.", name);
+                        FsvExitCode::NotFound
+                    },
+                    Err(err) => {
+                        log_error("Error adding tag alias", &err);
+                        err.exit_code()
+                    },
+                }
+            },
+            DbTagCommands::Unalias { alias } => {
+                let result = db_client.remove_tag_alias(&alias).await;
+                match result {
+                    Ok(true) => {
+                        info!("Alias '{}' removed.", alias);
+                        FsvExitCode::Success
+                    },
+                    Ok(false) => {
+                        error!("Alias '{}' not found in vocabulary.", alias);
+                        FsvExitCode::NotFound
+                    },
+                    Err(err) => {
+                        log_error("Error removing tag alias", &err);
+                        err.exit_code()
+                    },
+                }
+            },
+            DbTagCommands::Remove { name } => {
+                let result = db_client.delete_tag(&name).await;
+                match result {
+                    Ok(true) => {
+                        info!("Tag '{}' removed from vocabulary.", name);
+                        FsvExitCode::Success
+                    },
+                    Ok(false) => {
+                        error!("Tag '{}' not found in vocabulary.", name);
+                        FsvExitCode::NotFound
+                    },
+                    Err(err) => {
+                        log_error("Error removing tag", &err);
+                        err.exit_code()
+                    },
+                }
+            },
+            DbTagCommands::Imply { name, implied } => {
+                let result = db_client.add_tag_implication(&name, &implied).await;
+                match result {
+                    Ok(true) => {
+                        info!("Tag '{}' now implies '{}'.", name, implied);
+                        FsvExitCode::Success
+                    },
+                    Ok(false) => {
+                        error!("Tag '{}' or '{}' not found in vocabulary.", name, implied);
+                        FsvExitCode::NotFound
+                    },
+                    Err(err) => {
+                        log_error("Error adding tag implication", &err);
+                        err.exit_code()
+                    },
+                }
+            },
+            DbTagCommands::Unimply { name, implied } => {
+                let result = db_client.remove_tag_implication(&name, &implied).await;
+                match result {
+                    Ok(true) => {
+                        info!("Tag '{}' no longer implies '{}'.", name, implied);
+                        FsvExitCode::Success
+                    },
+                    Ok(false) => {
+                        error!("Tag '{}' does not imply '{}'.", name, implied);
+                        FsvExitCode::NotFound
+                    },
+                    Err(err) => {
+                        log_error("Error removing tag implication", &err);
+                        err.exit_code()
+                    },
+                }
+            },
+            DbTagCommands::List => {
+                let result = db_client.list_tags().await;
+                match result {
+                    Ok(tags) if tags.is_empty() => {
+                        println!("No tags defined.");
+                        FsvExitCode::Success
+                    },
+                    Ok(tags) => {
+                        for tag in tags {
+                            let mut details = Vec::new();
+                            if !tag.aliases.is_empty() {
+                                details.push(format!("aliases: {}", tag.aliases.join(", ")));
+                            }
+                            if !tag.implies.is_empty() {
+                                details.push(format!("implies: {}", tag.implies.join(", ")));
+                            }
+
+                            match details.is_empty() {
+                                true => println!("{}", tag.name),
+                                false => println!("{} ({})", tag.name, details.join("; ")),
+                            }
+                        }
+
+                        FsvExitCode::Success
+                    },
+                    Err(err) => {
+                        log_error("Error listing tags", &err);
+                        err.exit_code()
+                    },
+                }
+            },
+        },
+        DbCommands::ClearHashCache => {
+            let result = db_client.clear_hash_cache().await;
+            match result {
+                Ok(count) => {
+                    info!("Removed {} cached digests.", count);
+                    FsvExitCode::Success
+                },
+                Err(err) => {
+                    log_error("Error clearing hash cache", &err);
+                    err.exit_code()
+                },
+            }
+        },
+    }
+}
+
+#[cfg(feature = "tui")]
+fn browse(path: &Path, output_dir: &Path) -> FsvExitCode {
+    let result = FunScriptVideo::tui::browse(path, output_dir);
+    match result {
+        Ok(_) => FsvExitCode::Success,
+        Err(err) => {
+            log_error("Error browsing FSV files", &err);
+            err.exit_code()
+        },
+    }
+}
+
+fn open(path: &Path, video: Option<&str>, script: Option<&str>, player: &PlayerConfig) -> FsvExitCode {
+    let result = FunScriptVideo::open::open_fsv(path, video, script, player);
+    match result {
+        Ok(_) => FsvExitCode::Success,
+        Err(err) => {
+            log_error("Error opening FSV file", &err);
+            err.exit_code()
+        },
+    }
+}
+
+fn package(path: &Path, options: &PackageOptions) -> FsvExitCode {
+    let result = FunScriptVideo::package::package_fsv(path, options);
+    match result {
+        Ok(output) => {
+            info!("Wrote '{}' and '{}'", output.torrent_path.display(), output.manifest_path.display());
+            println!("Info hash: {}", output.manifest.info_hash);
+            println!("Piece size: {}", format_size(output.manifest.piece_size));
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error packaging FSV file", &err);
+            err.exit_code()
+        },
+    }
+}
+
+fn register(dry_run: bool) -> FsvExitCode {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(err) => {
+            log_error("Error finding the CLI executable", &err);
+            return FsvExitCode::Io;
+        },
+    };
+
+    let result = match dry_run {
+        true => FunScriptVideo::register::registration_steps(&exe),
+        false => FunScriptVideo::register::register(&exe),
+    };
+    match result {
+        Ok(steps) => {
+            for step in &steps {
+                println!("{}", step);
+            }
+            if !dry_run {
+                info!("Associated .fsv files ({}) with '{}'", FunScriptVideo::magic::MIME_TYPE, exe.display());
+            }
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error registering the .fsv file type", &err);
+            err.exit_code()
+        },
+    }
+}
+
+#[cfg(feature = "http")]
+fn fetch(path: &Path, video: Option<&str>) -> FsvExitCode {
+    let result = FunScriptVideo::fetch::fetch_external_with_progress(path, video, &ProgressLog::new());
+    match result {
+        Ok(names) if names.is_empty() => {
+            info!("'{}' has no external videos", path.display());
+            FsvExitCode::Success
+        },
+        Ok(names) => {
+            info!("Embedded {} into '{}'", names.join(", "), path.display());
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error fetching external videos", &err);
+            err.exit_code()
+        },
+    }
+}
+
+#[cfg(feature = "play")]
+async fn resume_position(path: &Path, db_client: &DbClient) -> u64 {
+    match FunScriptVideo::library::get_history(db_client, path).await {
+        Ok(Some(history)) => {
+            info!("Resuming '{}' at {} ms", path.display(), history.position_ms);
+            history.position_ms
+        },
+        Ok(None) => 0,
+        Err(err) => {
+            warn!("Unable to read watch history: {}", err);
+            0
+        },
+    }
+}
+
+#[cfg(feature = "play")]
+async fn play(args: FunScriptVideo::play::PlayArgs, db_client: &DbClient) -> FsvExitCode {
+    let path = args.path.clone();
+    let result = FunScriptVideo::play::play(args).await;
+    match result {
+        Ok(summary) => {
+            info!("Finished playing '{}'", path.display());
+            // A finished script starts over next time
+            let position_ms = if summary.finished { 0 } else { summary.position_ms };
+            if let Err(err) = FunScriptVideo::library::record_progress(db_client, &path, position_ms, true).await {
+                warn!("Unable to update watch history: {}", err);
+            }
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error playing FSV file", &err);
+            err.exit_code()
+        },
+    }
+}
+
+/// The API token from `--token`, `$FSV_API_TOKEN`, or a freshly generated one. A generated token is printed to stderr once so it
+/// can be handed to clients, and never logged.
+#[cfg(feature = "serve")]
+fn api_token(token: Option<String>) -> Result<String, FunScriptVideo::serve::ServeError> {
+    if let Some(token) = token.or_else(|| std::env::var("FSV_API_TOKEN").ok()) {
+        return Ok(token);
+    }
+    let token = FunScriptVideo::serve::generate_token()?;
+    eprintln!("API token: {}", token);
+    Ok(token)
+}
+
+#[cfg(feature = "serve")]
+fn serve(args: FunScriptVideo::serve::ServeArgs, db_client: &DbClient, rt: &tokio::runtime::Runtime) -> FsvExitCode {
+    let result = FunScriptVideo::serve::serve(args, db_client, rt);
+    match result {
+        Ok(()) => FsvExitCode::Success,
+        Err(err) => {
+            log_error("Error serving API", &err);
+            err.exit_code()
+        },
+    }
+}
+
+fn completions(shell: Shell) -> FsvExitCode {
+    let mut cmd = Args::command();
+    let bin_name = cmd.get_name().to_string();
+    // Generate into a buffer so a closed pipe (e.g. `| head`) is reported instead of panicking
+    let mut buffer = Vec::new();
+    clap_complete::generate(shell, &mut cmd, bin_name, &mut buffer);
+    match std::io::stdout().write_all(&buffer) {
+        Ok(_) => FsvExitCode::Success,
+        Err(err) => {
+            log_error("Error writing completions", &err);
+            FsvExitCode::Io
+        },
+    }
+}
+
+fn manpages(output_dir: &Path) -> FsvExitCode {
+    if let Err(err) = std::fs::create_dir_all(output_dir) {
+        log_error("Error creating manpage directory", &err);
+        return FsvExitCode::Io;
+    }
+
+    // The implicit `help` subcommands would otherwise get a manpage each
+    let mut cmd = disable_help_subcommands(Args::command());
+    cmd.build();
+    match clap_mangen::generate_to(cmd, output_dir) {
+        Ok(_) => {
+            info!("Manpages written to '{}'.", output_dir.display());
+            FsvExitCode::Success
+        },
+        Err(err) => {
+            log_error("Error generating manpages", &err);
+            FsvExitCode::Io
+        },
+    }
+}
+
+fn disable_help_subcommands(cmd: clap::Command) -> clap::Command {
+    cmd.disable_help_subcommand(true).mut_subcommands(disable_help_subcommands)
+}
+
+fn print_creator_records(records: &[CreatorRecord]) {
+    if records.is_empty() {
+        println!("No creators found.");
+        return;
+    }
+
+    for record in records {
+        println!("{} ({})", record.key, record.creator_info.name);
+        for (social, platform) in record.creator_info.socials.iter().zip(&record.social_platforms) {
+            match platform.is_empty() {
+                true => println!("  {}", social),
+                false => println!("  {}: {}", platform, social),
+            }
+        }
     }
 }
\ No newline at end of file
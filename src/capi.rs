@@ -0,0 +1,281 @@
+use std::{cell::RefCell, ffi::{CStr, CString, c_char, c_int, c_void}, fs::File, io::Write, path::Path, ptr};
+
+use crate::{exit_code::{FsvExitCode, ToExitCode}, fsv::{FsvContainer, FsvError}};
+
+/// An open FSV archive.
+pub struct FsvArchive {
+    container: FsvContainer<File>,
+    entry_names: Vec<CString>,
+}
+
+/// Receives the bytes of an entry streamed by `fsv_entry_read`, in order. A nonzero return value stops the stream.
+pub type FsvWriteCallback = extern "C" fn(user_data: *mut c_void, data: *const u8, len: usize) -> c_int;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl ToString) {
+    let message = CString::new(message.to_string().replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+fn fail<E: ToString + ToExitCode>(err: E) -> c_int {
+    set_last_error(err.to_string());
+    err.exit_code().code() as c_int
+}
+
+/// # Safety
+/// `s` must be NULL or a valid NUL-terminated string.
+unsafe fn str_arg<'a>(s: *const c_char, name: &str) -> Option<&'a str> {
+    if s.is_null() {
+        set_last_error(format!("{} is NULL", name));
+        return None;
+    }
+
+    match unsafe { CStr::from_ptr(s) }.to_str() {
+        Ok(s) => Some(s),
+        Err(_) => {
+            set_last_error(format!("{} is not valid UTF-8", name));
+            None
+        },
+    }
+}
+
+/// # Safety
+/// `archive` must be NULL or returned by `fsv_open`.
+unsafe fn archive_arg<'a>(archive: *mut FsvArchive) -> Option<&'a mut FsvArchive> {
+    let archive = unsafe { archive.as_mut() };
+    if archive.is_none() {
+        set_last_error("archive is NULL");
+    }
+    archive
+}
+
+/// Message for the last failed call on this thread, or NULL if there was none. Valid until the next failing call on this thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn fsv_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Open the FSV at `path` (UTF-8). Returns NULL on failure, including when an entry name contains a NUL byte. Close it with `fsv_close`.
+///
+/// # Safety
+/// `path` must be a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fsv_open(path: *const c_char) -> *mut FsvArchive {
+    let Some(path) = (unsafe { str_arg(path, "path") }) else {
+        return ptr::null_mut();
+    };
+
+    let container = match File::open(Path::new(path)).map_err(FsvError::from).and_then(|file| FsvContainer::from_reader(file).map_err(FsvError::from)) {
+        Ok(container) => container,
+        Err(err) => {
+            fail(err);
+            return ptr::null_mut();
+        },
+    };
+    // Names are kept as C strings so `fsv_entry_name` can hand out pointers that live as long as the archive
+    let entry_names = match container.entry_names().map(|name| CString::new(name).map_err(|_| name)).collect() {
+        Ok(entry_names) => entry_names,
+        Err(name) => {
+            set_last_error(format!("Entry name '{}' contains a NUL byte", name.replace('\0', "\\0")));
+            return ptr::null_mut();
+        },
+    };
+
+    Box::into_raw(Box::new(FsvArchive { container, entry_names }))
+}
+
+/// Close an archive opened with `fsv_open`. NULL is ignored.
+///
+/// # Safety
+/// `archive` must be NULL or returned by `fsv_open`, and not used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fsv_close(archive: *mut FsvArchive) {
+    if !archive.is_null() {
+        drop(unsafe { Box::from_raw(archive) });
+    }
+}
+
+/// Number of entries in the archive, including `metadata.json`.
+///
+/// # Safety
+/// `archive` must be returned by `fsv_open`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fsv_entry_count(archive: *const FsvArchive) -> usize {
+    unsafe { archive.as_ref() }.map_or(0, |archive| archive.entry_names.len())
+}
+
+/// Name of the entry at `index`, or NULL if out of range. Owned by the archive.
+///
+/// # Safety
+/// `archive` must be returned by `fsv_open`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fsv_entry_name(archive: *const FsvArchive, index: usize) -> *const c_char {
+    match unsafe { archive.as_ref() }.and_then(|archive| archive.entry_names.get(index)) {
+        Some(name) => name.as_ptr(),
+        None => {
+            set_last_error(format!("No entry at index {}", index));
+            ptr::null()
+        },
+    }
+}
+
+/// Store the uncompressed size of the entry `name` in `size`.
+///
+/// # Safety
+/// `archive` must be returned by `fsv_open`, `name` must be a valid NUL-terminated string and `size` a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fsv_entry_size(archive: *mut FsvArchive, name: *const c_char, size: *mut u64) -> c_int {
+    let (Some(archive), Some(name)) = (unsafe { archive_arg(archive) }, unsafe { str_arg(name, "name") }) else {
+        return FsvExitCode::Usage.code() as c_int;
+    };
+    if size.is_null() {
+        set_last_error("size is NULL");
+        return FsvExitCode::Usage.code() as c_int;
+    }
+
+    match archive.container.entry_size(name) {
+        Ok(entry_size) => {
+            unsafe { *size = entry_size };
+            FsvExitCode::Success.code() as c_int
+        },
+        Err(err) => fail(err),
+    }
+}
+
+/// Metadata of the archive as a JSON string. Free it with `fsv_string_free`.
+///
+/// # Safety
+/// `archive` must be returned by `fsv_open`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fsv_metadata_json(archive: *mut FsvArchive) -> *mut c_char {
+    let Some(archive) = (unsafe { archive_arg(archive) }) else {
+        return ptr::null_mut();
+    };
+
+    let json = archive.container.metadata().and_then(|metadata| Ok(serde_json::to_string(&metadata)?));
+    match json.map(CString::new) {
+        Ok(Ok(json)) => json.into_raw(),
+        Ok(Err(err)) => {
+            set_last_error(err);
+            ptr::null_mut()
+        },
+        Err(err) => {
+            fail(err);
+            ptr::null_mut()
+        },
+    }
+}
+
+/// Free a string returned by this library. NULL is ignored.
+///
+/// # Safety
+/// `s` must be NULL or returned by `fsv_metadata_json`, and not used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fsv_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+struct CallbackWriter {
+    callback: FsvWriteCallback,
+    user_data: *mut c_void,
+}
+
+impl Write for CallbackWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match (self.callback)(self.user_data, buf.as_ptr(), buf.len()) {
+            0 => Ok(buf.len()),
+            code => Err(std::io::Error::other(format!("write callback returned {}", code))),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Stream the uncompressed bytes of the entry `name` to `callback` in chunks, without buffering the entry in memory.
+///
+/// # Safety
+/// `archive` must be returned by `fsv_open` and `name` must be a valid NUL-terminated string.
+/// `user_data` is passed to `callback` as-is.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fsv_entry_read(archive: *mut FsvArchive, name: *const c_char, callback: FsvWriteCallback, user_data: *mut c_void) -> c_int {
+    let (Some(archive), Some(name)) = (unsafe { archive_arg(archive) }, unsafe { str_arg(name, "name") }) else {
+        return FsvExitCode::Usage.code() as c_int;
+    };
+
+    match archive.container.copy_entry(name, &mut CallbackWriter { callback, user_data }) {
+        Ok(_) => FsvExitCode::Success.code() as c_int,
+        Err(err) => fail(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsv::FsvBuilder;
+
+    extern "C" fn collect(user_data: *mut c_void, data: *const u8, len: usize) -> c_int {
+        let buffer = unsafe { &mut *(user_data as *mut Vec<u8>) };
+        buffer.extend_from_slice(unsafe { std::slice::from_raw_parts(data, len) });
+        0
+    }
+
+    #[test]
+    fn test_capi() {
+        let path = std::env::temp_dir().join(format!("fsv-capi-test-{}.fsv", std::process::id()));
+        let data = FsvBuilder::new("scene").script("video.funscript", b"{\"actions\":[]}", 1000).to_bytes().unwrap();
+        std::fs::write(&path, data).unwrap();
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+
+        unsafe {
+            let archive = fsv_open(c_path.as_ptr());
+            assert!(!archive.is_null());
+            let names: Vec<_> = (0..fsv_entry_count(archive)).map(|index| CStr::from_ptr(fsv_entry_name(archive, index)).to_str().unwrap()).collect();
+            assert!(names.contains(&"video.funscript"));
+
+            let mut size = 0;
+            let name = c"video.funscript";
+            assert_eq!(fsv_entry_size(archive, name.as_ptr(), &mut size), 0);
+            let mut buffer: Vec<u8> = Vec::new();
+            assert_eq!(fsv_entry_read(archive, name.as_ptr(), collect, &mut buffer as *mut Vec<u8> as *mut c_void), 0);
+            assert_eq!((buffer.as_slice(), size), (&b"{\"actions\":[]}"[..], 14));
+
+            let json = fsv_metadata_json(archive);
+            assert!(CStr::from_ptr(json).to_str().unwrap().contains("\"title\":\"scene\""));
+            fsv_string_free(json);
+
+            assert_eq!(fsv_entry_read(archive, c"missing.mp4".as_ptr(), collect, ptr::null_mut()), FsvExitCode::NotFound.code() as c_int);
+            assert!(!fsv_last_error().is_null());
+
+            let usage = FsvExitCode::Usage.code() as c_int;
+            let last_error = || CStr::from_ptr(fsv_last_error()).to_str().unwrap().to_string();
+            assert_eq!(fsv_entry_size(archive, name.as_ptr(), ptr::null_mut()), usage);
+            assert_eq!(last_error(), "size is NULL");
+            assert_eq!(fsv_entry_size(ptr::null_mut(), name.as_ptr(), &mut size), usage);
+            assert_eq!(last_error(), "archive is NULL");
+            assert_eq!(fsv_entry_read(archive, c"missing.mp4".as_ptr(), collect, ptr::null_mut()), FsvExitCode::NotFound.code() as c_int);
+            assert_eq!(fsv_entry_read(ptr::null_mut(), name.as_ptr(), collect, ptr::null_mut()), usage);
+            assert_eq!(last_error(), "archive is NULL");
+            fsv_close(archive);
+
+            // An entry name a C string can't hold fails the open rather than hiding the entry
+            let data = FsvBuilder::new("scene").script("video.funscript", b"{\"actions\":[]}", 1000).entry("bad\0name.txt", &b""[..]).to_bytes().unwrap();
+            std::fs::write(&path, data).unwrap();
+            assert!(fsv_open(c_path.as_ptr()).is_null());
+            assert_eq!(last_error(), "Entry name 'bad\\0name.txt' contains a NUL byte");
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_header_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/funscriptvideo.h"));
+        assert!(generated == include_str!("../include/funscriptvideo.h"), "include/funscriptvideo.h is outdated, copy it from {}", concat!(env!("OUT_DIR"), "/funscriptvideo.h"));
+    }
+}
@@ -0,0 +1,235 @@
+use std::{fmt::Display, io::Read, str::FromStr};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// Hash algorithms understood in `algo:hex` checksum strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256,
+    /// Deprecated by the spec. Recognized so existing checksums can be reported, never offered for new content.
+    #[value(skip)]
+    #[serde(skip)]
+    Sha1,
+    Blake3,
+    /// Non-cryptographic, only suitable for fast integrity checks
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    pub fn get_name(&self) -> &str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::Xxh3 => "xxh3",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sha256" => Some(HashAlgorithm::Sha256),
+            "sha1" => Some(HashAlgorithm::Sha1),
+            "blake3" => Some(HashAlgorithm::Blake3),
+            "xxh3" => Some(HashAlgorithm::Xxh3),
+            _ => None,
+        }
+    }
+
+    /// Length of the hex encoded digest
+    pub fn digest_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 | HashAlgorithm::Blake3 => 64,
+            HashAlgorithm::Sha1 => 40,
+            HashAlgorithm::Xxh3 => 16,
+        }
+    }
+
+    /// Compromised algorithms that readers must ignore (spec 5.2.3)
+    pub fn is_deprecated(&self) -> bool {
+        matches!(self, HashAlgorithm::Sha1)
+    }
+
+    /// Hex encoded digest of `data`
+    pub fn digest(&self, data: &[u8]) -> String {
+        match self {
+            HashAlgorithm::Sha256 => format!("{:x}", Sha256::digest(data)),
+            HashAlgorithm::Sha1 => format!("{:x}", Sha1::digest(data)),
+            HashAlgorithm::Blake3 => blake3::hash(data).to_hex().to_string(),
+            HashAlgorithm::Xxh3 => format!("{:016x}", xxhash_rust::xxh3::xxh3_64(data)),
+        }
+    }
+
+    /// Hex encoded digest of everything read from `reader`, without buffering it in memory
+    pub fn digest_reader(&self, reader: &mut impl Read) -> std::io::Result<String> {
+        let mut reader = HashingReader::new(reader, *self);
+        std::io::copy(&mut reader, &mut std::io::sink())?;
+        Ok(reader.finish().digest)
+    }
+}
+
+enum HasherState {
+    Sha256(Sha256),
+    Sha1(Sha1),
+    Blake3(Box<blake3::Hasher>),
+    Xxh3(Box<xxhash_rust::xxh3::Xxh3>),
+}
+
+/// Hashes data fed to it in pieces with any `HashAlgorithm`.
+pub struct Hasher {
+    algorithm: HashAlgorithm,
+    state: HasherState,
+}
+
+impl Hasher {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        let state = match algorithm {
+            HashAlgorithm::Sha256 => HasherState::Sha256(Sha256::new()),
+            HashAlgorithm::Sha1 => HasherState::Sha1(Sha1::new()),
+            HashAlgorithm::Blake3 => HasherState::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgorithm::Xxh3 => HasherState::Xxh3(Box::new(xxhash_rust::xxh3::Xxh3::new())),
+        };
+
+        Hasher { algorithm, state }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match &mut self.state {
+            HasherState::Sha256(hasher) => hasher.update(data),
+            HasherState::Sha1(hasher) => hasher.update(data),
+            HasherState::Blake3(hasher) => {
+                hasher.update(data);
+            },
+            HasherState::Xxh3(hasher) => hasher.update(data),
+        }
+    }
+
+    pub fn finish(self) -> Checksum {
+        let digest = match self.state {
+            HasherState::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            HasherState::Sha1(hasher) => format!("{:x}", hasher.finalize()),
+            HasherState::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            HasherState::Xxh3(hasher) => format!("{:016x}", hasher.digest()),
+        };
+
+        Checksum { algorithm: self.algorithm, digest }
+    }
+}
+
+/// Hashes everything read through it, so data can be hashed while it is copied somewhere else instead of being read twice.
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Hasher,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R, algorithm: HashAlgorithm) -> Self {
+        HashingReader { inner, hasher: Hasher::new(algorithm) }
+    }
+
+    /// Checksum of everything read so far.
+    pub fn finish(self) -> Checksum {
+        self.hasher.finish()
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+impl Display for HashAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.get_name())
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ParseChecksumError {
+    #[error("Checksum '{0}' is not in algorithm:digest form")]
+    MissingSeparator(String),
+    #[error("Unsupported checksum algorithm '{0}'")]
+    UnsupportedAlgorithm(String),
+    #[error("Digest is not lowercase hex")]
+    InvalidDigest,
+    #[error("Expected a {expected} character digest for {algorithm}, found {found}")]
+    InvalidDigestLength { algorithm: HashAlgorithm, expected: usize, found: usize },
+}
+
+/// A checksum in the spec's `<algorithm_name>:<hex_encoded_digest>` form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    pub algorithm: HashAlgorithm,
+    pub digest: String,
+}
+
+impl Checksum {
+    pub fn compute(algorithm: HashAlgorithm, data: &[u8]) -> Self {
+        Checksum { algorithm, digest: algorithm.digest(data) }
+    }
+
+    pub fn matches(&self, data: &[u8]) -> bool {
+        self.algorithm.digest(data) == self.digest
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = ParseChecksumError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (algorithm, digest) = s.trim().split_once(':').ok_or_else(|| ParseChecksumError::MissingSeparator(s.to_string()))?;
+        let algorithm = HashAlgorithm::from_name(algorithm).ok_or_else(|| ParseChecksumError::UnsupportedAlgorithm(algorithm.to_string()))?;
+        if digest.is_empty() || !digest.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
+            return Err(ParseChecksumError::InvalidDigest);
+        }
+
+        if digest.len() != algorithm.digest_len() {
+            return Err(ParseChecksumError::InvalidDigestLength { algorithm, expected: algorithm.digest_len(), found: digest.len() });
+        }
+
+        Ok(Checksum { algorithm, digest: digest.to_string() })
+    }
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_round_trip() {
+        for algorithm in [HashAlgorithm::Sha256, HashAlgorithm::Sha1, HashAlgorithm::Blake3, HashAlgorithm::Xxh3] {
+            let checksum = Checksum::compute(algorithm, b"data");
+            assert_eq!(checksum.digest.len(), algorithm.digest_len());
+            assert_eq!(checksum.to_string().parse::<Checksum>(), Ok(checksum.clone()));
+            assert!(checksum.matches(b"data"));
+            assert_eq!(algorithm.digest_reader(&mut &b"data"[..]).unwrap(), checksum.digest);
+            let mut hasher = Hasher::new(algorithm);
+            hasher.update(b"da");
+            hasher.update(b"ta");
+            assert_eq!(hasher.finish(), checksum);
+            assert!(!checksum.matches(b"other"));
+        }
+    }
+
+    #[test]
+    fn test_checksum_parse_errors() {
+        assert_eq!("abcdef".parse::<Checksum>(), Err(ParseChecksumError::MissingSeparator("abcdef".to_string())));
+        assert_eq!("md5:abcdef".parse::<Checksum>(), Err(ParseChecksumError::UnsupportedAlgorithm("md5".to_string())));
+        assert_eq!("xxh3:ABCDEF0123456789".parse::<Checksum>(), Err(ParseChecksumError::InvalidDigest));
+        assert!(matches!("sha256:abcdef".parse::<Checksum>(), Err(ParseChecksumError::InvalidDigestLength { .. })));
+    }
+}
@@ -0,0 +1,49 @@
+use std::{collections::BTreeMap, path::{Path, PathBuf}};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{hooks::HooksConfig, naming::NamingPolicy, notify::NotificationsConfig, open::PlayerConfig, template::CreateTemplate};
+
+/// Name of the config file, looked up next to the database.
+pub const CONFIG_FILE_NAME: &str = "funscripvideo.json";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid config '{0}': {1}")]
+    Invalid(PathBuf, serde_json::Error),
+}
+
+/// Contents of the config file. Unknown keys are rejected, so a typo doesn't silently produce archives that differ from what
+/// the config promises.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Named defaults for `create --template`
+    pub templates: BTreeMap<String, CreateTemplate>,
+    /// Entry names enforced on `add` and `create`
+    pub naming_policy: Option<NamingPolicy>,
+    /// Allowlist/blocklist file screening the metadata of archives made by `create`, `import` and `watch`
+    pub content_policy: Option<PathBuf>,
+    /// Player launched by `open`
+    pub player: PlayerConfig,
+    /// External commands run before and after `create`, `add`, `remove` and `import`
+    pub hooks: HooksConfig,
+    /// Webhook, Discord and ntfy notifications sent by `watch` and `serve`
+    pub notifications: NotificationsConfig,
+}
+
+impl Config {
+    /// Read the config at `path`. A missing file is an empty config.
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => return Err(err.into()),
+        };
+
+        serde_json::from_str(&json).map_err(|err| ConfigError::Invalid(path.to_path_buf(), err))
+    }
+}
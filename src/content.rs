@@ -0,0 +1,147 @@
+use crate::funscript::Funscript;
+
+/// Number of leading bytes needed by `detect_video_container`.
+pub const VIDEO_SIGNATURE_LEN: usize = 377;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    Ass,
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub fn get_name(&self) -> &str {
+        match self {
+            SubtitleFormat::Srt => "SRT",
+            SubtitleFormat::Ass => "ASS/SSA",
+            SubtitleFormat::Vtt => "WebVTT",
+        }
+    }
+}
+
+/// Identify a video container from its leading bytes. Returns the container name if the signature is recognized.
+pub fn detect_video_container(header: &[u8]) -> Option<&'static str> {
+    const EBML: [u8; 4] = [0x1A, 0x45, 0xDF, 0xA3];
+    const ASF: [u8; 8] = [0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11];
+    const MPEG_PS: [u8; 4] = [0x00, 0x00, 0x01, 0xBA];
+    const TS_PACKET_LEN: usize = 188;
+
+    if header.len() >= 8 && &header[4..8] == b"ftyp" {
+        return Some("MP4/QuickTime");
+    }
+
+    if header.starts_with(&EBML) {
+        return Some("Matroska/WebM");
+    }
+
+    if header.len() >= 12 && header.starts_with(b"RIFF") && &header[8..12] == b"AVI " {
+        return Some("AVI");
+    }
+
+    if header.starts_with(&ASF) {
+        return Some("ASF/WMV");
+    }
+
+    if header.starts_with(b"FLV") {
+        return Some("FLV");
+    }
+
+    if header.starts_with(b"OggS") {
+        return Some("Ogg");
+    }
+
+    if header.starts_with(&MPEG_PS) {
+        return Some("MPEG-PS");
+    }
+
+    // Transport streams have a sync byte at the start of every 188 byte packet
+    if header.len() > 2 * TS_PACKET_LEN && header[0] == 0x47 && header[TS_PACKET_LEN] == 0x47 && header[2 * TS_PACKET_LEN] == 0x47 {
+        return Some("MPEG-TS");
+    }
+
+    None
+}
+
+/// Check that `data` deserializes as a funscript with at least one action.
+pub fn validate_funscript(data: &[u8]) -> Result<Funscript, String> {
+    let funscript = serde_json::from_slice::<Funscript>(data).map_err(|err| format!("Not a valid funscript: {}", err))?;
+    if funscript.actions.is_empty() {
+        return Err("Funscript has no actions".to_string());
+    }
+
+    Ok(funscript)
+}
+
+/// Check that `data` is a parsable SRT, ASS/SSA or WebVTT subtitle file and return its format.
+pub fn validate_subtitle(data: &[u8]) -> Result<SubtitleFormat, String> {
+    let text = std::str::from_utf8(data).map_err(|_| "Subtitle is not valid UTF-8".to_string())?;
+    let text = text.trim_start_matches('\u{feff}');
+
+    if text.starts_with("WEBVTT") {
+        return match text.lines().any(|line| is_timing_line(line, '.')) {
+            true => Ok(SubtitleFormat::Vtt),
+            false => Err("WebVTT file has no cues".to_string()),
+        };
+    }
+
+    if text.lines().any(|line| line.trim().eq_ignore_ascii_case("[script info]")) {
+        let has_events = text.lines().any(|line| line.trim().eq_ignore_ascii_case("[events]"));
+        let has_dialogue = text.lines().any(|line| line.trim_start().starts_with("Dialogue:"));
+        return match (has_events, has_dialogue) {
+            (true, true) => Ok(SubtitleFormat::Ass),
+            (false, _) => Err("ASS/SSA file has no [Events] section".to_string()),
+            (true, false) => Err("ASS/SSA file has no dialogue lines".to_string()),
+        };
+    }
+
+    if text.lines().any(|line| is_timing_line(line, ',')) {
+        return Ok(SubtitleFormat::Srt);
+    }
+
+    Err("Not a recognized subtitle format (SRT, ASS/SSA or WebVTT)".to_string())
+}
+
+/// Matches `00:00:01,000 --> 00:00:02,000` (SRT, `,`) or `00:01.000 --> 00:02.000` (WebVTT, `.`), ignoring cue settings.
+fn is_timing_line(line: &str, fraction_separator: char) -> bool {
+    let Some((start, end)) = line.split_once("-->") else {
+        return false;
+    };
+
+    let end = end.split_whitespace().next().unwrap_or("");
+    is_timestamp(start.trim(), fraction_separator) && is_timestamp(end, fraction_separator)
+}
+
+fn is_timestamp(value: &str, fraction_separator: char) -> bool {
+    let Some((clock, fraction)) = value.rsplit_once(fraction_separator) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = clock.split(':').collect();
+    (2..=3).contains(&parts.len())
+        && parts.iter().all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        && fraction.len() == 3
+        && fraction.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_video_container() {
+        assert_eq!(detect_video_container(b"\0\0\0\x18ftypisom"), Some("MP4/QuickTime"));
+        assert_eq!(detect_video_container(&[0x1A, 0x45, 0xDF, 0xA3, 0x01]), Some("Matroska/WebM"));
+        assert_eq!(detect_video_container(b"RIFF\0\0\0\0AVI LIST"), Some("AVI"));
+        assert_eq!(detect_video_container(b"not a video"), None);
+    }
+
+    #[test]
+    fn test_validate_subtitle() {
+        assert_eq!(validate_subtitle(b"1\n00:00:01,000 --> 00:00:02,500\nHello\n"), Ok(SubtitleFormat::Srt));
+        assert_eq!(validate_subtitle(b"WEBVTT\n\n00:01.000 --> 00:02.000 align:start\nHello\n"), Ok(SubtitleFormat::Vtt));
+        assert_eq!(validate_subtitle(b"[Script Info]\nTitle: x\n\n[Events]\nDialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,Hello\n"), Ok(SubtitleFormat::Ass));
+        assert!(validate_subtitle(b"WEBVTT\n").is_err());
+        assert!(validate_subtitle(b"just some text").is_err());
+    }
+}
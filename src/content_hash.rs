@@ -0,0 +1,130 @@
+use std::{collections::BTreeMap, io::Read};
+
+use serde::{Deserialize, Serialize};
+
+use crate::metadata::FsvMetadata;
+
+/// Metadata field holding the `fsv.content-hashes` extension data.
+pub const CONTENT_HASHES_FIELD: &str = "content_hashes";
+pub const CONTENT_HASH_ALGORITHM: &str = "blake3";
+
+/// BLAKE3 hashes of every archive entry except metadata.json, plus a root hash over all of them.
+/// The root lets distribution sites identify an FSV by a single value that does not depend on ZIP layout or compression.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentHashes {
+    pub algorithm: String,
+    pub entries: BTreeMap<String, String>,
+    pub root: String,
+}
+
+impl ContentHashes {
+    pub fn new(entries: BTreeMap<String, String>) -> Self {
+        let root = root_hash(&entries);
+        ContentHashes { algorithm: CONTENT_HASH_ALGORITHM.to_string(), entries, root }
+    }
+
+    /// Read the stored hashes from metadata, if the field is present.
+    pub fn from_metadata(metadata: &FsvMetadata) -> Option<Result<Self, serde_json::Error>> {
+        metadata.extra.get(CONTENT_HASHES_FIELD).map(|value| serde_json::from_value(value.clone()))
+    }
+}
+
+/// Differences between stored and freshly computed content hashes.
+#[derive(Debug, Clone, Default)]
+pub struct HashVerification {
+    pub mismatched: Vec<String>,
+    /// Listed in the stored hashes but not in the archive
+    pub missing: Vec<String>,
+    /// Present in the archive but not listed in the stored hashes
+    pub unlisted: Vec<String>,
+    pub root_matches: bool,
+}
+
+impl HashVerification {
+    pub fn compare(stored: &ContentHashes, computed: &ContentHashes) -> Self {
+        let mut verification = HashVerification { root_matches: stored.root.eq_ignore_ascii_case(&computed.root), ..Default::default() };
+        for (name, hash) in &stored.entries {
+            match computed.entries.get(name) {
+                Some(actual) if actual.eq_ignore_ascii_case(hash) => (),
+                Some(_) => verification.mismatched.push(name.clone()),
+                None => verification.missing.push(name.clone()),
+            }
+        }
+
+        verification.unlisted = computed.entries.keys().filter(|name| !stored.entries.contains_key(*name)).cloned().collect();
+        verification
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.root_matches && self.mismatched.is_empty() && self.missing.is_empty() && self.unlisted.is_empty()
+    }
+}
+
+pub fn hash_reader(reader: &mut impl Read) -> Result<String, std::io::Error> {
+    let mut hasher = blake3::Hasher::new();
+    std::io::copy(reader, &mut hasher)?;
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Hash of the `name\0hash\n` lines of all entries in name order.
+pub fn root_hash(entries: &BTreeMap<String, String>) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for (name, hash) in entries {
+        hasher.update(name.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(hash.to_ascii_lowercase().as_bytes());
+        hasher.update(b"\n");
+    }
+
+    hasher.finalize().to_hex().to_string()
+}
+
+/// Validation hook for the extension registry.
+pub fn validate_content_hashes(metadata: &FsvMetadata, entry_names: &[&str]) -> Vec<String> {
+    let hashes = match ContentHashes::from_metadata(metadata) {
+        None => return vec![format!("Missing '{}' field", CONTENT_HASHES_FIELD)],
+        Some(Err(err)) => return vec![format!("Malformed '{}' field: {}", CONTENT_HASHES_FIELD, err)],
+        Some(Ok(hashes)) => hashes,
+    };
+
+    let mut issues = Vec::new();
+    if hashes.algorithm != CONTENT_HASH_ALGORITHM {
+        issues.push(format!("Unsupported content hash algorithm '{}'", hashes.algorithm));
+    }
+
+    for (name, hash) in &hashes.entries {
+        if !entry_names.contains(&name.as_str()) {
+            issues.push(format!("Hashed entry '{}' not found in archive", name));
+        }
+
+        if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            issues.push(format!("Hash for '{}' is not a BLAKE3 hex digest", name));
+        }
+    }
+
+    if hashes.root != root_hash(&hashes.entries) {
+        issues.push("Root hash does not match the entry hashes".to_string());
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_verification() {
+        let hash = |data: &[u8]| hash_reader(&mut &data[..]).unwrap();
+        let stored = ContentHashes::new(BTreeMap::from([("a.mp4".to_string(), hash(b"a")), ("b.funscript".to_string(), hash(b"b"))]));
+        assert!(HashVerification::compare(&stored, &stored.clone()).is_ok());
+
+        let computed = ContentHashes::new(BTreeMap::from([("a.mp4".to_string(), hash(b"changed")), ("c.srt".to_string(), hash(b"c"))]));
+        let verification = HashVerification::compare(&stored, &computed);
+        assert!(!verification.root_matches);
+        assert_eq!(verification.mismatched, ["a.mp4"]);
+        assert_eq!(verification.missing, ["b.funscript"]);
+        assert_eq!(verification.unlisted, ["c.srt"]);
+    }
+}
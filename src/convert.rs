@@ -0,0 +1,264 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::funscript::{Funscript, FunscriptAction};
+
+#[derive(Debug, Error)]
+pub enum ConvertError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serde JSON error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("Line {line}: {message}")]
+    Parse { line: usize, message: String },
+    #[error("Script has no actions")]
+    NoActions,
+    #[error("Unable to determine script format of: {0}")]
+    UnknownFormat(String),
+}
+
+/// Script formats that can be converted to and from funscript.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ScriptFormat {
+    /// Funscript JSON
+    Funscript,
+    /// Raw `at,pos` CSV: milliseconds and position 0-100
+    Csv,
+    /// RealTouch-style CSV: seconds (with fraction) and position 0-255
+    Realtouch,
+    /// Vorze CSV: deciseconds, direction (0/1) and speed 0-100. Positions above 50 map to direction 1, below to direction 0, 50 is stopped
+    Vorze,
+    /// Legacy Launch JSON: `[{ "at", "pos", "speed" }]` with position and speed 0-99
+    Launch,
+}
+
+impl ScriptFormat {
+    pub fn get_name(&self) -> &str {
+        match self {
+            ScriptFormat::Funscript => "funscript",
+            ScriptFormat::Csv => "csv",
+            ScriptFormat::Realtouch => "realtouch",
+            ScriptFormat::Vorze => "vorze",
+            ScriptFormat::Launch => "launch",
+        }
+    }
+
+    pub fn extension(&self) -> &str {
+        match self {
+            ScriptFormat::Funscript => "funscript",
+            ScriptFormat::Csv | ScriptFormat::Realtouch | ScriptFormat::Vorze => "csv",
+            ScriptFormat::Launch => "launch",
+        }
+    }
+
+    /// Format implied by a file name, e.g. for the output of a conversion. `.csv` means raw CSV.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "funscript" => Some(ScriptFormat::Funscript),
+            "csv" => Some(ScriptFormat::Csv),
+            "launch" => Some(ScriptFormat::Launch),
+            _ => None,
+        }
+    }
+
+    /// Format of an existing script file: by extension, and for CSV by column count (three columns are Vorze).
+    /// RealTouch CSV can't be told apart from raw CSV and has to be named explicitly.
+    pub fn detect(path: &Path, data: &[u8]) -> Option<Self> {
+        match ScriptFormat::from_path(path)? {
+            ScriptFormat::Csv => {
+                let text = String::from_utf8_lossy(data);
+                let columns = csv_rows(&text).next().map(|(_, fields)| fields.len());
+                match columns {
+                    Some(3) => Some(ScriptFormat::Vorze),
+                    _ => Some(ScriptFormat::Csv),
+                }
+            },
+            format => Some(format),
+        }
+    }
+}
+
+impl std::fmt::Display for ScriptFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.get_name())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LaunchCommand {
+    at: u64,
+    pos: u64,
+    #[serde(default)]
+    speed: u64,
+}
+
+/// Non-empty, non-comment rows with their 1-based line numbers. A header row (first field not numeric) is skipped.
+fn csv_rows(text: &str) -> impl Iterator<Item = (usize, Vec<&str>)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line, row)| (line, row.split([',', ';']).map(str::trim).collect::<Vec<_>>()))
+        .filter(|(_, fields)| fields[0].parse::<f64>().is_ok())
+}
+
+fn parse_field<T: std::str::FromStr>(fields: &[&str], index: usize, line: usize) -> Result<T, ConvertError> {
+    let field = fields.get(index).ok_or_else(|| ConvertError::Parse { line, message: format!("Expected at least {} fields", index + 1) })?;
+    field.parse().map_err(|_| ConvertError::Parse { line, message: format!("Invalid number '{}'", field) })
+}
+
+fn new_funscript(mut actions: Vec<FunscriptAction>) -> Result<Funscript, ConvertError> {
+    if actions.is_empty() {
+        return Err(ConvertError::NoActions);
+    }
+
+    actions.sort_by_key(|action| action.at);
+    Ok(Funscript { actions, inverted: false, metadata: None, range: 100, version: "1.0".to_string() })
+}
+
+/// Read a script in `format` as a funscript.
+pub fn read_script(data: &[u8], format: ScriptFormat) -> Result<Funscript, ConvertError> {
+    let text = String::from_utf8_lossy(data);
+    let actions = match format {
+        ScriptFormat::Funscript => return Ok(serde_json::from_slice::<Funscript>(data)?),
+        ScriptFormat::Csv => csv_rows(&text)
+            .map(|(line, fields)| Ok(FunscriptAction { at: parse_field(&fields, 0, line)?, pos: parse_field::<u64>(&fields, 1, line)?.min(100) }))
+            .collect::<Result<Vec<_>, ConvertError>>()?,
+        ScriptFormat::Realtouch => csv_rows(&text)
+            .map(|(line, fields)| {
+                let seconds: f64 = parse_field(&fields, 0, line)?;
+                let position: u64 = parse_field(&fields, 1, line)?;
+                Ok(FunscriptAction { at: (seconds.max(0.0) * 1000.0).round() as u64, pos: (position.min(255) * 100 + 127) / 255 })
+            })
+            .collect::<Result<Vec<_>, ConvertError>>()?,
+        ScriptFormat::Vorze => csv_rows(&text)
+            .map(|(line, fields)| {
+                let deciseconds: u64 = parse_field(&fields, 0, line)?;
+                let direction: u64 = parse_field(&fields, 1, line)?;
+                let speed = parse_field::<u64>(&fields, 2, line)?.min(100);
+                let pos = match direction {
+                    0 => 50 - speed / 2,
+                    _ => 50 + speed / 2,
+                };
+                Ok(FunscriptAction { at: deciseconds * 100, pos })
+            })
+            .collect::<Result<Vec<_>, ConvertError>>()?,
+        ScriptFormat::Launch => serde_json::from_slice::<Vec<LaunchCommand>>(data)?
+            .into_iter()
+            .map(|command| FunscriptAction { at: command.at, pos: (command.pos.min(99) * 100 + 49) / 99 })
+            .collect(),
+    };
+
+    new_funscript(actions)
+}
+
+/// Funscript positions as 0-100 with `range` and `inverted` applied, sorted by time.
+fn normalized_actions(funscript: &Funscript) -> Vec<(u64, u64)> {
+    let range = funscript.range.max(1);
+    let mut actions: Vec<(u64, u64)> = funscript.actions.iter()
+        .map(|action| {
+            let pos = (action.pos * 100 / range).min(100);
+            (action.at, if funscript.inverted { 100 - pos } else { pos })
+        })
+        .collect();
+    actions.sort_by_key(|(at, _)| *at);
+    actions
+}
+
+/// Launch speed (0-99) needed to cover `distance` positions in `duration_ms`, using the commonly used Launch speed curve.
+fn launch_speed(distance: u64, duration_ms: u64) -> u64 {
+    if distance == 0 || duration_ms == 0 {
+        return 20;
+    }
+
+    let speed = 25000.0 * (duration_ms as f64 * 90.0 / distance as f64).powf(-1.05);
+    (speed.round() as u64).clamp(20, 80)
+}
+
+/// Write a funscript in `format`.
+pub fn write_script(funscript: &Funscript, format: ScriptFormat) -> Result<Vec<u8>, ConvertError> {
+    let actions = normalized_actions(funscript);
+    let mut output = String::new();
+    match format {
+        ScriptFormat::Funscript => return Ok(serde_json::to_vec(funscript)?),
+        ScriptFormat::Csv => {
+            for (at, pos) in actions {
+                output.push_str(&format!("{},{}\n", at, pos));
+            }
+        },
+        ScriptFormat::Realtouch => {
+            for (at, pos) in actions {
+                output.push_str(&format!("{}.{:03},{}\n", at / 1000, at % 1000, (pos * 255 + 50) / 100));
+            }
+        },
+        ScriptFormat::Vorze => {
+            let mut last_time = None;
+            for (at, pos) in actions {
+                // Vorze only has decisecond resolution; keep the last action per slot
+                let deciseconds = (at + 50) / 100;
+                if last_time == Some(deciseconds) {
+                    let end = output[..output.len() - 1].rfind('\n').map_or(0, |i| i + 1);
+                    output.truncate(end);
+                }
+
+                let (direction, speed) = match pos {
+                    pos if pos >= 50 => (1, (pos - 50) * 2),
+                    pos => (0, (50 - pos) * 2),
+                };
+                output.push_str(&format!("{},{},{}\n", deciseconds, direction, speed.min(100)));
+                last_time = Some(deciseconds);
+            }
+        },
+        ScriptFormat::Launch => {
+            let mut previous = (0, 0);
+            let commands: Vec<LaunchCommand> = actions.iter()
+                .map(|&(at, pos)| {
+                    let speed = launch_speed(pos.abs_diff(previous.1), at.saturating_sub(previous.0));
+                    previous = (at, pos);
+                    LaunchCommand { at, pos: (pos * 99 + 50) / 100, speed }
+                })
+                .collect();
+            return Ok(serde_json::to_vec(&commands)?);
+        },
+    }
+
+    Ok(output.into_bytes())
+}
+
+/// Convert the script at `input` to `output`. Formats default to detection from the file names (and the input's content).
+pub fn convert_file(input: &Path, output: &Path, from: Option<ScriptFormat>, to: Option<ScriptFormat>) -> Result<(ScriptFormat, ScriptFormat), ConvertError> {
+    let data = std::fs::read(input)?;
+    let from = from.or_else(|| ScriptFormat::detect(input, &data)).ok_or_else(|| ConvertError::UnknownFormat(input.display().to_string()))?;
+    let to = to.or_else(|| ScriptFormat::from_path(output)).ok_or_else(|| ConvertError::UnknownFormat(output.display().to_string()))?;
+    let funscript = read_script(&data, from)?;
+    std::fs::write(output, write_script(&funscript, to)?)?;
+
+    Ok((from, to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_format_round_trips() {
+        let funscript = read_script(b"at,pos\n0,0\n1000,100\n# comment\n1500,50\n", ScriptFormat::Csv).unwrap();
+        assert_eq!(funscript.actions.iter().map(|a| (a.at, a.pos)).collect::<Vec<_>>(), [(0, 0), (1000, 100), (1500, 50)]);
+
+        for format in [ScriptFormat::Csv, ScriptFormat::Realtouch, ScriptFormat::Launch, ScriptFormat::Funscript] {
+            // Launch positions only go up to 99, so they may be off by one
+            let converted = normalized_actions(&read_script(&write_script(&funscript, format).unwrap(), format).unwrap());
+            let close = converted.iter().zip(normalized_actions(&funscript)).all(|(a, b)| a.0 == b.0 && a.1.abs_diff(b.1) <= 1);
+            assert!(close && converted.len() == 3, "{}: {:?}", format, converted);
+        }
+
+        let vorze = write_script(&funscript, ScriptFormat::Vorze).unwrap();
+        assert_eq!(String::from_utf8(vorze.clone()).unwrap(), "0,0,100\n10,1,100\n15,1,0\n");
+        assert_eq!(ScriptFormat::detect(Path::new("a.csv"), &vorze), Some(ScriptFormat::Vorze));
+        assert!(matches!(read_script(b"0,abc\n", ScriptFormat::Csv), Err(ConvertError::Parse { line: 1, .. })));
+    }
+}
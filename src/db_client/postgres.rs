@@ -0,0 +1,166 @@
+use super::{Migration, MigrationStep, Queries};
+
+pub(super) const QUERIES: Queries = Queries {
+    migrations: &[
+        Migration { version: 1, description: "initial schema", step: MigrationStep::Sql(SCHEMA_V1) },
+        Migration { version: 2, description: "social platform labels", step: MigrationStep::LabelSocials },
+        Migration {
+            version: 3,
+            description: "scan checkpoints",
+            step: MigrationStep::Sql("CREATE TABLE IF NOT EXISTS scan_checkpoints (root TEXT PRIMARY KEY, last_path TEXT NOT NULL, updated_at BIGINT NOT NULL)"),
+        },
+    ],
+    snapshot_entries: r#"
+        SELECT path, entry, digest FROM snapshot_entries
+        WHERE $1 IS NULL OR substr(path, 1, length($1)) = $1
+        ORDER BY path, seq
+        "#,
+    work_videos: r#"
+        SELECT name, duration_ms, checksum, codec, resolution, size FROM library_work_videos WHERE work_id = $1 ORDER BY seq
+        "#,
+    work_scripts: r#"
+        SELECT name, axis FROM library_work_scripts WHERE work_id = $1 ORDER BY seq
+        "#,
+    video_durations: r#"
+        SELECT v.work_id, v.duration_ms, v.checksum FROM library_work_videos v JOIN library_works w ON w.id = v.work_id
+        WHERE $1 IS NULL OR substr(w.path, 1, length($1)) = $1
+        ORDER BY w.path, v.seq
+        "#,
+    social_platform_column: r#"
+        SELECT 1 FROM information_schema.columns
+        WHERE table_schema = current_schema() AND table_name = 'creator_info_socials' AND column_name = 'platform'
+        "#,
+};
+
+/// The SQLite schema in PostgreSQL's types. Tables keeping entries in archive or metadata order number them with `seq`,
+/// as PostgreSQL has no `rowid`, and the `nocase` collation stands in for SQLite's `COLLATE NOCASE` (it needs a UTF-8
+/// database with ICU support).
+pub(super) const SCHEMA_V1: &str = r#"
+    CREATE COLLATION IF NOT EXISTS nocase (provider = icu, locale = 'und-u-ks-level2', deterministic = false);
+    CREATE TABLE IF NOT EXISTS creator_info (
+        id BIGSERIAL PRIMARY KEY,
+        name TEXT NOT NULL,
+        key TEXT NOT NULL UNIQUE
+    );
+    CREATE TABLE IF NOT EXISTS creator_info_socials (
+        id BIGSERIAL PRIMARY KEY,
+        creator_info_id BIGINT NOT NULL,
+        social_url TEXT NOT NULL,
+        FOREIGN KEY (creator_info_id) REFERENCES creator_info(id) ON DELETE CASCADE,
+        UNIQUE (creator_info_id, social_url)
+    );
+    CREATE TABLE IF NOT EXISTS tags (
+        id BIGSERIAL PRIMARY KEY,
+        name TEXT COLLATE nocase NOT NULL UNIQUE
+    );
+    CREATE TABLE IF NOT EXISTS tag_aliases (
+        id BIGSERIAL PRIMARY KEY,
+        tag_id BIGINT NOT NULL,
+        alias TEXT COLLATE nocase NOT NULL UNIQUE,
+        FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+    );
+    CREATE TABLE IF NOT EXISTS tag_implications (
+        tag_id BIGINT NOT NULL,
+        implied_tag_id BIGINT NOT NULL,
+        FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE,
+        FOREIGN KEY (implied_tag_id) REFERENCES tags(id) ON DELETE CASCADE,
+        PRIMARY KEY (tag_id, implied_tag_id)
+    );
+    CREATE TABLE IF NOT EXISTS hash_cache (
+        source TEXT NOT NULL,
+        entry TEXT NOT NULL,
+        algorithm TEXT NOT NULL,
+        size BIGINT NOT NULL,
+        stamp BIGINT NOT NULL,
+        digest TEXT NOT NULL,
+        PRIMARY KEY (source, entry, algorithm)
+    );
+    CREATE TABLE IF NOT EXISTS snapshots (
+        path TEXT PRIMARY KEY,
+        algorithm TEXT NOT NULL,
+        size BIGINT NOT NULL,
+        stamp BIGINT NOT NULL,
+        digest TEXT NOT NULL,
+        taken_at BIGINT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS snapshot_entries (
+        seq BIGSERIAL,
+        path TEXT NOT NULL,
+        entry TEXT NOT NULL,
+        digest TEXT NOT NULL,
+        FOREIGN KEY (path) REFERENCES snapshots(path) ON DELETE CASCADE,
+        PRIMARY KEY (path, entry)
+    );
+    CREATE TABLE IF NOT EXISTS library_works (
+        id BIGSERIAL PRIMARY KEY,
+        path TEXT NOT NULL UNIQUE,
+        title TEXT NOT NULL,
+        studio TEXT NOT NULL DEFAULT '',
+        size BIGINT NOT NULL,
+        stamp BIGINT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS library_work_tags (
+        work_id BIGINT NOT NULL,
+        tag TEXT COLLATE nocase NOT NULL,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE,
+        PRIMARY KEY (work_id, tag)
+    );
+    CREATE TABLE IF NOT EXISTS library_work_performers (
+        work_id BIGINT NOT NULL,
+        performer TEXT COLLATE nocase NOT NULL,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE,
+        PRIMARY KEY (work_id, performer)
+    );
+    CREATE TABLE IF NOT EXISTS library_work_titles (
+        work_id BIGINT NOT NULL,
+        language TEXT NOT NULL,
+        title TEXT NOT NULL,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE,
+        PRIMARY KEY (work_id, language)
+    );
+    CREATE TABLE IF NOT EXISTS library_work_creators (
+        work_id BIGINT NOT NULL,
+        creator TEXT COLLATE nocase NOT NULL,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE,
+        PRIMARY KEY (work_id, creator)
+    );
+    CREATE TABLE IF NOT EXISTS library_work_sizes (
+        work_id BIGINT PRIMARY KEY,
+        compressed_size BIGINT NOT NULL,
+        uncompressed_size BIGINT NOT NULL,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE
+    );
+    CREATE TABLE IF NOT EXISTS library_work_videos (
+        seq BIGSERIAL,
+        work_id BIGINT NOT NULL,
+        name TEXT NOT NULL,
+        duration_ms BIGINT NOT NULL,
+        checksum TEXT NOT NULL DEFAULT '',
+        codec TEXT NOT NULL DEFAULT '',
+        resolution TEXT NOT NULL DEFAULT '',
+        size BIGINT NOT NULL DEFAULT 0,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE,
+        PRIMARY KEY (work_id, name)
+    );
+    CREATE TABLE IF NOT EXISTS library_work_scripts (
+        seq BIGSERIAL,
+        work_id BIGINT NOT NULL,
+        name TEXT NOT NULL,
+        axis TEXT NOT NULL,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE,
+        PRIMARY KEY (work_id, name)
+    );
+    CREATE TABLE IF NOT EXISTS work_ratings (
+        work_id BIGINT PRIMARY KEY,
+        rating INTEGER,
+        favorite INTEGER NOT NULL DEFAULT 0,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE
+    );
+    CREATE TABLE IF NOT EXISTS history (
+        work_id BIGINT PRIMARY KEY,
+        last_played BIGINT NOT NULL,
+        position_ms BIGINT NOT NULL DEFAULT 0,
+        play_count INTEGER NOT NULL DEFAULT 1,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE
+    );
+    "#;
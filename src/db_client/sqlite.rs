@@ -0,0 +1,177 @@
+use std::path::Path;
+
+use super::{Migration, MigrationStep, Queries};
+
+pub(super) const QUERIES: Queries = Queries {
+    migrations: &[
+        // Databases from before migrations are at version 0 and may lack tables added since, so it only creates what is missing
+        Migration { version: 1, description: "initial schema", step: MigrationStep::Sql(SCHEMA_V1) },
+        Migration { version: 2, description: "social platform labels", step: MigrationStep::LabelSocials },
+        Migration {
+            version: 3,
+            description: "scan checkpoints",
+            step: MigrationStep::Sql("CREATE TABLE IF NOT EXISTS scan_checkpoints (root TEXT PRIMARY KEY, last_path TEXT NOT NULL, updated_at INTEGER NOT NULL)"),
+        },
+    ],
+    snapshot_entries: r#"
+        SELECT path, entry, digest FROM snapshot_entries
+        WHERE $1 IS NULL OR substr(path, 1, length($1)) = $1
+        ORDER BY path, rowid
+        "#,
+    work_videos: r#"
+        SELECT name, duration_ms, checksum, codec, resolution, size FROM library_work_videos WHERE work_id = $1 ORDER BY rowid
+        "#,
+    work_scripts: r#"
+        SELECT name, axis FROM library_work_scripts WHERE work_id = $1 ORDER BY rowid
+        "#,
+    video_durations: r#"
+        SELECT v.work_id, v.duration_ms, v.checksum FROM library_work_videos v JOIN library_works w ON w.id = v.work_id
+        WHERE $1 IS NULL OR substr(w.path, 1, length($1)) = $1
+        ORDER BY w.path, v.rowid
+        "#,
+    social_platform_column: r#"
+        SELECT 1 FROM pragma_table_info('creator_info_socials') WHERE name = 'platform'
+        "#,
+};
+
+pub(super) const SCHEMA_V1: &str = r#"
+    CREATE TABLE IF NOT EXISTS creator_info (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL,
+        key TEXT NOT NULL UNIQUE
+    );
+    CREATE TABLE IF NOT EXISTS creator_info_socials (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        creator_info_id INTEGER NOT NULL,
+        social_url TEXT NOT NULL,
+        FOREIGN KEY (creator_info_id) REFERENCES creator_info(id) ON DELETE CASCADE,
+        UNIQUE (creator_info_id, social_url)
+    );
+    CREATE TABLE IF NOT EXISTS tags (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE COLLATE NOCASE
+    );
+    CREATE TABLE IF NOT EXISTS tag_aliases (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        tag_id INTEGER NOT NULL,
+        alias TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+    );
+    CREATE TABLE IF NOT EXISTS tag_implications (
+        tag_id INTEGER NOT NULL,
+        implied_tag_id INTEGER NOT NULL,
+        FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE,
+        FOREIGN KEY (implied_tag_id) REFERENCES tags(id) ON DELETE CASCADE,
+        PRIMARY KEY (tag_id, implied_tag_id)
+    );
+    CREATE TABLE IF NOT EXISTS hash_cache (
+        source TEXT NOT NULL,
+        entry TEXT NOT NULL,
+        algorithm TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        stamp INTEGER NOT NULL,
+        digest TEXT NOT NULL,
+        PRIMARY KEY (source, entry, algorithm)
+    );
+    CREATE TABLE IF NOT EXISTS snapshots (
+        path TEXT PRIMARY KEY,
+        algorithm TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        stamp INTEGER NOT NULL,
+        digest TEXT NOT NULL,
+        taken_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS snapshot_entries (
+        path TEXT NOT NULL,
+        entry TEXT NOT NULL,
+        digest TEXT NOT NULL,
+        FOREIGN KEY (path) REFERENCES snapshots(path) ON DELETE CASCADE,
+        PRIMARY KEY (path, entry)
+    );
+    CREATE TABLE IF NOT EXISTS library_works (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        path TEXT NOT NULL UNIQUE,
+        title TEXT NOT NULL,
+        studio TEXT NOT NULL DEFAULT '',
+        size INTEGER NOT NULL,
+        stamp INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS library_work_tags (
+        work_id INTEGER NOT NULL,
+        tag TEXT NOT NULL COLLATE NOCASE,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE,
+        PRIMARY KEY (work_id, tag)
+    );
+    CREATE TABLE IF NOT EXISTS library_work_performers (
+        work_id INTEGER NOT NULL,
+        performer TEXT NOT NULL COLLATE NOCASE,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE,
+        PRIMARY KEY (work_id, performer)
+    );
+    CREATE TABLE IF NOT EXISTS library_work_titles (
+        work_id INTEGER NOT NULL,
+        language TEXT NOT NULL,
+        title TEXT NOT NULL,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE,
+        PRIMARY KEY (work_id, language)
+    );
+    CREATE TABLE IF NOT EXISTS library_work_creators (
+        work_id INTEGER NOT NULL,
+        creator TEXT NOT NULL COLLATE NOCASE,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE,
+        PRIMARY KEY (work_id, creator)
+    );
+    CREATE TABLE IF NOT EXISTS library_work_sizes (
+        work_id INTEGER PRIMARY KEY,
+        compressed_size INTEGER NOT NULL,
+        uncompressed_size INTEGER NOT NULL,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE
+    );
+    CREATE TABLE IF NOT EXISTS library_work_videos (
+        work_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        duration_ms INTEGER NOT NULL,
+        checksum TEXT NOT NULL DEFAULT '',
+        codec TEXT NOT NULL DEFAULT '',
+        resolution TEXT NOT NULL DEFAULT '',
+        size INTEGER NOT NULL DEFAULT 0,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE,
+        PRIMARY KEY (work_id, name)
+    );
+    CREATE TABLE IF NOT EXISTS library_work_scripts (
+        work_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        axis TEXT NOT NULL,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE,
+        PRIMARY KEY (work_id, name)
+    );
+    CREATE TABLE IF NOT EXISTS work_ratings (
+        work_id INTEGER PRIMARY KEY,
+        rating INTEGER,
+        favorite INTEGER NOT NULL DEFAULT 0,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE
+    );
+    CREATE TABLE IF NOT EXISTS history (
+        work_id INTEGER PRIMARY KEY,
+        last_played INTEGER NOT NULL,
+        position_ms INTEGER NOT NULL DEFAULT 0,
+        play_count INTEGER NOT NULL DEFAULT 1,
+        FOREIGN KEY (work_id) REFERENCES library_works(id) ON DELETE CASCADE
+    );
+    "#;
+
+/// URL of the database file at `path`, opened in `mode` (`rwc` creates it if missing, `ro` only reads it). `?` and `#`
+/// in the path are escaped, as they would start the URL's query or fragment; the path itself stays as it is, which keeps
+/// Windows drive letters and relative paths intact.
+pub(super) fn file_url(path: &Path, mode: &str) -> String {
+    let mut url = String::from("sqlite:");
+    for c in path.to_string_lossy().chars() {
+        match c {
+            '%' | '?' | '#' => url.push_str(&format!("%{:02X}", c as u32)),
+            c => url.push(c),
+        }
+    }
+    url.push_str(&format!("?mode={}", mode));
+
+    url
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A length of time in whole milliseconds, the unit of every duration in FSV metadata and of funscript action times.
+/// Serialized as the bare number of milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(transparent)]
+pub struct DurationMs(u64);
+
+impl DurationMs {
+    pub const ZERO: DurationMs = DurationMs(0);
+
+    pub const fn from_millis(millis: u64) -> Self {
+        DurationMs(millis)
+    }
+
+    pub const fn from_secs(secs: u64) -> Self {
+        DurationMs(secs.saturating_mul(1000))
+    }
+
+    /// Rounded to the nearest millisecond, as ffprobe reports fractional seconds. Negative and NaN seconds are zero.
+    pub fn from_secs_f64(secs: f64) -> Self {
+        DurationMs((secs * 1000.0).round() as u64)
+    }
+
+    pub const fn as_millis(self) -> u64 {
+        self.0
+    }
+
+    pub fn as_secs_f64(self) -> f64 {
+        self.0 as f64 / 1000.0
+    }
+
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// How far apart `self` and `other` are, whichever is longer.
+    pub const fn abs_diff(self, other: DurationMs) -> DurationMs {
+        DurationMs(self.0.abs_diff(other.0))
+    }
+
+    pub const fn saturating_sub(self, other: DurationMs) -> DurationMs {
+        DurationMs(self.0.saturating_sub(other.0))
+    }
+}
+
+impl From<DurationMs> for Duration {
+    fn from(duration: DurationMs) -> Self {
+        Duration::from_millis(duration.0)
+    }
+}
+
+/// Truncated to whole milliseconds.
+impl From<Duration> for DurationMs {
+    fn from(duration: Duration) -> Self {
+        DurationMs(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duration_ms() {
+        assert_eq!(DurationMs::from_secs(3), DurationMs::from_millis(3000));
+        assert_eq!(DurationMs::from_secs_f64(1.2345), DurationMs::from_millis(1235));
+        assert_eq!(DurationMs::from_secs_f64(-1.0), DurationMs::ZERO);
+        assert_eq!(DurationMs::from_millis(1500).as_secs_f64(), 1.5);
+        assert_eq!(DurationMs::from_millis(1000).abs_diff(DurationMs::from_millis(4000)), DurationMs::from_millis(3000));
+        assert_eq!(Duration::from(DurationMs::from_millis(250)), Duration::from_millis(250));
+        assert_eq!(DurationMs::from(Duration::from_micros(2999)), DurationMs::from_millis(2));
+
+        assert_eq!(serde_json::to_string(&DurationMs::from_millis(1000)).unwrap(), "1000");
+        assert_eq!(serde_json::from_str::<DurationMs>("42").unwrap(), DurationMs::from_millis(42));
+        assert!(serde_json::from_str::<DurationMs>("-1").is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use std::{borrow::Cow, ffi::OsStr};
+
+use tracing::warn;
+
+/// Characters of code page 437 for the bytes 0x80-0xFF. Entry names stored without the UTF-8 flag are read in it.
+const CP437_HIGH: [char; 128] = [
+    '\u{C7}', '\u{FC}', '\u{E9}', '\u{E2}', '\u{E4}', '\u{E0}', '\u{E5}', '\u{E7}', '\u{EA}', '\u{EB}', '\u{E8}', '\u{EF}', '\u{EE}', '\u{EC}', '\u{C4}', '\u{C5}',
+    '\u{C9}', '\u{E6}', '\u{C6}', '\u{F4}', '\u{F6}', '\u{F2}', '\u{FB}', '\u{F9}', '\u{FF}', '\u{D6}', '\u{DC}', '\u{A2}', '\u{A3}', '\u{A5}', '\u{20A7}', '\u{192}',
+    '\u{E1}', '\u{ED}', '\u{F3}', '\u{FA}', '\u{F1}', '\u{D1}', '\u{AA}', '\u{BA}', '\u{BF}', '\u{2310}', '\u{AC}', '\u{BD}', '\u{BC}', '\u{A1}', '\u{AB}', '\u{BB}',
+    '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{2561}', '\u{2562}', '\u{2556}', '\u{2555}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255D}', '\u{255C}', '\u{255B}', '\u{2510}',
+    '\u{2514}', '\u{2534}', '\u{252C}', '\u{251C}', '\u{2500}', '\u{253C}', '\u{255E}', '\u{255F}', '\u{255A}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256C}', '\u{2567}',
+    '\u{2568}', '\u{2564}', '\u{2565}', '\u{2559}', '\u{2558}', '\u{2552}', '\u{2553}', '\u{256B}', '\u{256A}', '\u{2518}', '\u{250C}', '\u{2588}', '\u{2584}', '\u{258C}', '\u{2590}', '\u{2580}',
+    '\u{3B1}', '\u{DF}', '\u{393}', '\u{3C0}', '\u{3A3}', '\u{3C3}', '\u{B5}', '\u{3C4}', '\u{3A6}', '\u{398}', '\u{3A9}', '\u{3B4}', '\u{221E}', '\u{3C6}', '\u{3B5}', '\u{2229}',
+    '\u{2261}', '\u{B1}', '\u{2265}', '\u{2264}', '\u{2320}', '\u{2321}', '\u{F7}', '\u{2248}', '\u{B0}', '\u{2219}', '\u{B7}', '\u{221A}', '\u{207F}', '\u{B2}', '\u{25A0}', '\u{A0}',
+];
+
+fn cp437_byte(c: char) -> Option<u8> {
+    match c {
+        c if c.is_ascii() => Some(c as u8),
+        c => CP437_HIGH.iter().position(|&high| high == c).map(|i| 0x80 + i as u8),
+    }
+}
+
+/// The name of an archive entry as it was meant to be read. The ZIP library reads names stored without the UTF-8 flag
+/// as code page 437, as the format prescribes, but many tools store UTF-8 names without the flag: a name whose code
+/// page 437 bytes are valid UTF-8 is such a name (`σïòτö╗.mp4` is `動画.mp4`), other names are returned as they are.
+pub fn decode_entry_name(name: &str) -> Cow<'_, str> {
+    if name.is_ascii() {
+        return Cow::Borrowed(name);
+    }
+
+    // Names read as UTF-8 in the first place hold characters code page 437 doesn't have
+    let Some(bytes) = name.chars().map(cp437_byte).collect::<Option<Vec<u8>>>() else {
+        return Cow::Borrowed(name);
+    };
+
+    match String::from_utf8(bytes) {
+        Ok(decoded) => Cow::Owned(decoded),
+        Err(_) => Cow::Borrowed(name),
+    }
+}
+
+/// A file name as an entry name. Names that aren't valid Unicode (e.g. legacy encoded names on Unix) can't be stored
+/// in the metadata as they are, so their invalid parts are replaced, with a warning.
+pub fn os_str_entry_name(name: &OsStr) -> String {
+    match name.to_str() {
+        Some(name) => name.to_string(),
+        None => {
+            let lossy = name.to_string_lossy().to_string();
+            warn!(operation = "add", item = ?name, outcome = "renamed", "File name is not valid Unicode, storing it as '{}'", lossy);
+            lossy
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_entry_name() {
+        // UTF-8 bytes read as code page 437
+        let misread: String = "動画.mp4".bytes().map(|byte| match byte {
+            byte if byte.is_ascii() => byte as char,
+            byte => CP437_HIGH[byte as usize - 0x80],
+        }).collect();
+        assert_eq!(decode_entry_name(&misread), "動画.mp4");
+        assert_eq!(decode_entry_name("動画.mp4"), "動画.mp4");
+        assert_eq!(decode_entry_name("video.mp4"), "video.mp4");
+        // Genuine code page 437 names that aren't UTF-8 stay as they are
+        assert_eq!(decode_entry_name("Café.mp4"), "Café.mp4");
+        assert_eq!(os_str_entry_name(OsStr::new("動画.funscript")), "動画.funscript");
+    }
+}
@@ -0,0 +1,157 @@
+use std::{error::Error, fmt, path::{Path, PathBuf}};
+
+use zip::result::{ZipError, ZipResult};
+
+/// What was being done when an error happened: the operation, and the file or archive and the entry in it that it concerned.
+/// I/O errors carry it inside (keeping their kind, so exit codes don't change), see `IoContext` and `ZipContext`.
+#[derive(Debug)]
+pub struct ErrorContext {
+    /// What failed, e.g. `reading` or `opening database`
+    pub operation: &'static str,
+    /// File, archive or database path
+    pub path: Option<PathBuf>,
+    /// Name of the archive entry
+    pub entry: Option<String>,
+    source: Box<dyn Error + Send + Sync>,
+}
+
+impl ErrorContext {
+    pub fn new(operation: &'static str, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        ErrorContext { operation, path: None, entry: None, source: source.into() }
+    }
+
+    pub fn with_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn with_entry(mut self, entry: impl Into<String>) -> Self {
+        self.entry = Some(entry.into());
+        self
+    }
+
+    /// The context of `err` or the first error it wraps that has one, looking inside I/O errors too.
+    pub fn find<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a ErrorContext> {
+        let mut current = Some(err);
+        while let Some(err) = current {
+            if let Some(context) = err.downcast_ref::<ErrorContext>() {
+                return Some(context);
+            }
+            // An I/O error's source skips the error it wraps, so that is looked at directly
+            if let Some(context) = err.downcast_ref::<std::io::Error>().and_then(|err| err.get_ref()).and_then(|inner| inner.downcast_ref::<ErrorContext>()) {
+                return Some(context);
+            }
+            current = err.source();
+        }
+
+        None
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.operation)?;
+        match (&self.entry, &self.path) {
+            (Some(entry), Some(path)) => write!(f, " '{}' in '{}'", entry, path.display())?,
+            (Some(entry), None) => write!(f, " '{}'", entry)?,
+            (None, Some(path)) => write!(f, " '{}'", path.display())?,
+            (None, None) => (),
+        }
+        write!(f, ": {}", self.source)
+    }
+}
+
+impl Error for ErrorContext {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+fn has_context(err: &std::io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<ErrorContext>())
+}
+
+fn set_archive(mut err: std::io::Error, path: &Path) -> std::io::Error {
+    if let Some(context) = err.get_mut().and_then(|inner| inner.downcast_mut::<ErrorContext>()) && context.path.is_none() {
+        context.path = Some(path.to_path_buf());
+    }
+    err
+}
+
+/// Add the operation and path or entry to I/O errors. Errors that already carry a context keep it, it is the more specific one.
+pub trait IoContext<T> {
+    fn path_context(self, operation: &'static str, path: impl AsRef<Path>) -> std::io::Result<T>;
+    fn entry_context(self, operation: &'static str, entry: &str) -> std::io::Result<T>;
+    /// Name the archive an entry context belongs to, for code that only learns it after the entry failed.
+    fn in_archive(self, path: impl AsRef<Path>) -> std::io::Result<T>;
+}
+
+impl<T> IoContext<T> for std::io::Result<T> {
+    fn path_context(self, operation: &'static str, path: impl AsRef<Path>) -> std::io::Result<T> {
+        self.map_err(|err| if has_context(&err) { err } else { std::io::Error::new(err.kind(), ErrorContext::new(operation, err).with_path(path)) })
+    }
+
+    fn entry_context(self, operation: &'static str, entry: &str) -> std::io::Result<T> {
+        self.map_err(|err| if has_context(&err) { err } else { std::io::Error::new(err.kind(), ErrorContext::new(operation, err).with_entry(entry)) })
+    }
+
+    fn in_archive(self, path: impl AsRef<Path>) -> std::io::Result<T> {
+        self.map_err(|err| set_archive(err, path.as_ref()))
+    }
+}
+
+/// `IoContext` for ZIP results. The ZIP error type shows nothing of the I/O errors it wraps, so all errors become I/O errors
+/// carrying the context: a missing entry a not-found one, and format errors invalid data wrapping the ZIP error.
+pub trait ZipContext<T> {
+    fn path_context(self, operation: &'static str, path: impl AsRef<Path>) -> std::io::Result<T>;
+    fn entry_context(self, operation: &'static str, entry: &str) -> std::io::Result<T>;
+}
+
+fn zip_to_io(err: ZipError) -> std::io::Error {
+    match err {
+        ZipError::Io(err) => err,
+        ZipError::FileNotFound => std::io::Error::new(std::io::ErrorKind::NotFound, "not found in archive"),
+        err => std::io::Error::new(std::io::ErrorKind::InvalidData, err),
+    }
+}
+
+impl<T> ZipContext<T> for ZipResult<T> {
+    fn path_context(self, operation: &'static str, path: impl AsRef<Path>) -> std::io::Result<T> {
+        self.map_err(zip_to_io).path_context(operation, path)
+    }
+
+    fn entry_context(self, operation: &'static str, entry: &str) -> std::io::Result<T> {
+        self.map_err(zip_to_io).entry_context(operation, entry)
+    }
+}
+
+/// The ZIP error an I/O error from `ZipContext` stands for, if it was not an I/O error to begin with.
+pub fn zip_error(err: &std::io::Error) -> Option<&ZipError> {
+    let context = err.get_ref()?.downcast_ref::<ErrorContext>()?;
+    context.source.downcast_ref::<std::io::Error>()?.get_ref()?.downcast_ref::<ZipError>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_context() {
+        let err = Err::<(), _>(std::io::Error::from(std::io::ErrorKind::NotFound)).path_context("opening", "scene.fsv").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(err.to_string(), "opening 'scene.fsv': entity not found");
+        // The innermost context is kept
+        let err = Err::<(), _>(err).path_context("extracting", "other.fsv").unwrap_err();
+        assert_eq!(ErrorContext::find(&err).unwrap().path.as_deref(), Some(Path::new("scene.fsv")));
+
+        let err = Err::<(), _>(ZipError::FileNotFound).entry_context("reading", "video.mp4").in_archive("scene.fsv").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(err.to_string(), "reading 'video.mp4' in 'scene.fsv': not found in archive");
+        let context = ErrorContext::find(&err).unwrap();
+        assert_eq!((context.operation, context.entry.as_deref()), ("reading", Some("video.mp4")));
+        assert!(zip_error(&err).is_none());
+
+        let err = Err::<(), _>(ZipError::InvalidArchive("bad".into())).path_context("reading", "scene.fsv").unwrap_err();
+        assert!(matches!(zip_error(&err), Some(ZipError::InvalidArchive(_))));
+    }
+}
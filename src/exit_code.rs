@@ -0,0 +1,571 @@
+use crate::{error_context, file_util::GetDurationError, fsv::{FsvAddError, FsvCreateError, FsvEditError, FsvError, FsvExtractError, FsvNotesError, FsvRebuildError, FsvRemoveError, FsvDeriveError, FsvUndoError, FsvState, FsvValidationError}, import::ImportError, journal::JournalError, convert::ConvertError, policy::PolicyError, playback::PlaybackError, transcode::TranscodeError, trash::TrashError};
+#[cfg(feature = "native")]
+use crate::{archive_tx::ArchiveTxError, config::ConfigError, db_client::DbClientError, fsv::{FsvAlignError, FsvPreviewError}, hooks::HookError, library::LibraryError, naming::NamingError, open::OpenError, snapshot::SnapshotError, template::TemplateError, watch::WatchError};
+
+/// Process exit codes used by the CLI. The numeric values are part of the CLI's public interface and must not be reordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FsvExitCode {
+    /// The command completed successfully.
+    Success = 0,
+    /// The FSV metadata is invalid (or the archive failed validation).
+    ValidationFailed = 1,
+    /// The FSV metadata is valid but referenced content is missing or unreadable.
+    ContentIncomplete = 2,
+    /// A filesystem I/O error occurred.
+    Io = 3,
+    /// The ZIP archive could not be read or written.
+    Archive = 4,
+    /// metadata.json (or another JSON document) is missing or could not be parsed.
+    Metadata = 5,
+    /// The database could not be opened or queried.
+    Database = 6,
+    /// A requested entry, creator, or record does not exist.
+    NotFound = 7,
+    /// The target of a create operation already exists.
+    AlreadyExists = 8,
+    /// An external tool (e.g. ffprobe) failed or produced unusable output.
+    ExternalTool = 9,
+    /// The command line could not be parsed.
+    Usage = 10,
+    /// Any other failure.
+    Failure = 11,
+    /// The archive is being modified by another process; the command can be retried once it is done.
+    Locked = 12,
+}
+
+impl FsvExitCode {
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            FsvExitCode::Success => "success",
+            FsvExitCode::ValidationFailed => "validation failed",
+            FsvExitCode::ContentIncomplete => "content incomplete",
+            FsvExitCode::Io => "I/O error",
+            FsvExitCode::Archive => "archive error",
+            FsvExitCode::Metadata => "metadata error",
+            FsvExitCode::Database => "database error",
+            FsvExitCode::NotFound => "not found",
+            FsvExitCode::AlreadyExists => "already exists",
+            FsvExitCode::ExternalTool => "external tool error",
+            FsvExitCode::Usage => "usage error",
+            FsvExitCode::Failure => "failure",
+            FsvExitCode::Locked => "archive locked",
+        }
+    }
+
+    pub fn is_success(self) -> bool {
+        self == FsvExitCode::Success
+    }
+}
+
+impl From<FsvExitCode> for std::process::ExitCode {
+    fn from(code: FsvExitCode) -> Self {
+        std::process::ExitCode::from(code.code())
+    }
+}
+
+impl From<&FsvState> for FsvExitCode {
+    fn from(state: &FsvState) -> Self {
+        match state {
+            FsvState::Valid => FsvExitCode::Success,
+            FsvState::ContentIncomplete(_) => FsvExitCode::ContentIncomplete,
+            FsvState::MetadataInvalid(_) => FsvExitCode::ValidationFailed,
+        }
+    }
+}
+
+/// Maps an error to the exit code the CLI reports for it.
+pub trait ToExitCode {
+    fn exit_code(&self) -> FsvExitCode;
+}
+
+fn io_exit_code(err: &std::io::Error) -> FsvExitCode {
+    // ZIP format errors given a context travel as I/O errors
+    if let Some(zip_err) = error_context::zip_error(err) {
+        return zip_exit_code(zip_err);
+    }
+
+    match err.kind() {
+        std::io::ErrorKind::NotFound => FsvExitCode::NotFound,
+        std::io::ErrorKind::AlreadyExists => FsvExitCode::AlreadyExists,
+        _ => FsvExitCode::Io,
+    }
+}
+
+fn zip_exit_code(err: &zip::result::ZipError) -> FsvExitCode {
+    match err {
+        zip::result::ZipError::Io(io_err) => io_exit_code(io_err),
+        zip::result::ZipError::FileNotFound => FsvExitCode::NotFound,
+        _ => FsvExitCode::Archive,
+    }
+}
+
+#[cfg(feature = "native")]
+impl ToExitCode for DbClientError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            DbClientError::TagCycle(..) | DbClientError::SelfMerge(..) | DbClientError::UnsupportedDatabase(_) | DbClientError::AmbiguousCreator(..) => FsvExitCode::Usage,
+            _ => FsvExitCode::Database,
+        }
+    }
+}
+
+impl ToExitCode for JournalError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            JournalError::Io(err) => io_exit_code(err),
+            JournalError::SerdeJson(_) => FsvExitCode::Metadata,
+            JournalError::Busy(_) => FsvExitCode::Locked,
+            JournalError::Pending(_) => FsvExitCode::Database,
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl ToExitCode for ArchiveTxError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            ArchiveTxError::Journal(err) => err.exit_code(),
+            ArchiveTxError::DbClient(err) => err.exit_code(),
+            ArchiveTxError::Fsv(err) => err.exit_code(),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl ToExitCode for LibraryError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            LibraryError::Io(err) => io_exit_code(err),
+            #[cfg(feature = "native")]
+            LibraryError::DbClient(err) => err.exit_code(),
+            LibraryError::Fsv(err) => err.exit_code(),
+            LibraryError::ArchiveTx(err) => err.exit_code(),
+            LibraryError::InvalidRating(_) => FsvExitCode::Usage,
+        }
+    }
+}
+
+impl ToExitCode for GetDurationError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            GetDurationError::Io(err) => io_exit_code(err),
+            GetDurationError::ParseFloat(_) | GetDurationError::Ffprobe(_) => FsvExitCode::ExternalTool,
+            GetDurationError::SerdeJson(_) | GetDurationError::FunscriptMissingActions => FsvExitCode::Metadata,
+        }
+    }
+}
+
+impl ToExitCode for TranscodeError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            TranscodeError::Io(err) => io_exit_code(err),
+            TranscodeError::GetDuration(err) => err.exit_code(),
+            TranscodeError::SerdeJson(_) | TranscodeError::Ffmpeg(_) | TranscodeError::Ffprobe(_) | TranscodeError::NoVideoStream(_) => FsvExitCode::ExternalTool,
+        }
+    }
+}
+
+impl ToExitCode for FsvError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            FsvError::Io(err) => io_exit_code(err),
+            FsvError::Zip(err) => zip_exit_code(err),
+            FsvError::SerdeJson(_) | FsvError::MetadataFileNotFound => FsvExitCode::Metadata,
+            #[cfg(feature = "native")]
+            FsvError::DbClient(err) => err.exit_code(),
+            FsvError::CreatorInfoNotFound(_) => FsvExitCode::NotFound,
+            FsvError::NoCreatorDatabase(_) => FsvExitCode::Database,
+            FsvError::Locked(_) => FsvExitCode::Locked,
+            FsvError::Journal(err) => err.exit_code(),
+        }
+    }
+}
+
+impl ToExitCode for FsvValidationError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            FsvValidationError::Io(err) => io_exit_code(err),
+            FsvValidationError::Zip(err) => zip_exit_code(err),
+            FsvValidationError::SerdeJson(_) | FsvValidationError::MetadataNotFound => FsvExitCode::ValidationFailed,
+        }
+    }
+}
+
+impl ToExitCode for FsvExtractError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            FsvExtractError::Io(err) => io_exit_code(err),
+            FsvExtractError::Zip(err) => zip_exit_code(err),
+            FsvExtractError::SerdeJson(_) | FsvExtractError::MetadataNotFound => FsvExitCode::Metadata,
+            FsvExtractError::Validation(err) => err.exit_code(),
+            FsvExtractError::InvalidState(state) => state.into(),
+            FsvExtractError::UnsafePath(_) => FsvExitCode::Failure,
+        }
+    }
+}
+
+impl ToExitCode for FsvCreateError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            FsvCreateError::Io(err) => io_exit_code(err),
+            FsvCreateError::Zip(err) => zip_exit_code(err),
+            FsvCreateError::SerdeJson(_) | FsvCreateError::FromUtf8(_) => FsvExitCode::Metadata,
+            #[cfg(feature = "native")]
+            FsvCreateError::DbClient(err) => err.exit_code(),
+            FsvCreateError::Fsv(err) => err.exit_code(),
+            FsvCreateError::GetDurationError(err) => err.exit_code(),
+            FsvCreateError::FsvAlreadyExists(_) => FsvExitCode::AlreadyExists,
+            FsvCreateError::CreatorInfoNotFound(_, _) => FsvExitCode::NotFound,
+            FsvCreateError::Transcode(err) => err.exit_code(),
+            #[cfg(feature = "native")]
+            FsvCreateError::Naming(err) => err.exit_code(),
+            FsvCreateError::Policy(err) => err.exit_code(),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl ToExitCode for HookError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            HookError::Launch(_, _) => FsvExitCode::ExternalTool,
+            HookError::Rejected(_, _, _) => FsvExitCode::ValidationFailed,
+        }
+    }
+}
+
+impl ToExitCode for PolicyError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            PolicyError::Io(err) => io_exit_code(err),
+            PolicyError::Invalid(_, _) => FsvExitCode::Metadata,
+            PolicyError::Rejected(_) => FsvExitCode::ValidationFailed,
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl ToExitCode for ConfigError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            ConfigError::Io(err) => io_exit_code(err),
+            ConfigError::Invalid(_, _) => FsvExitCode::Metadata,
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl ToExitCode for NamingError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            NamingError::InvalidPattern(_, _) => FsvExitCode::Usage,
+            NamingError::Violation(_, _) => FsvExitCode::ValidationFailed,
+            NamingError::Transcode(err) => err.exit_code(),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl ToExitCode for TemplateError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            TemplateError::Config(err) => err.exit_code(),
+            TemplateError::NotFound(_, _) => FsvExitCode::NotFound,
+            TemplateError::Naming(err) => err.exit_code(),
+            TemplateError::Io(err) => io_exit_code(err),
+            TemplateError::MissingPassword(_, _) => FsvExitCode::Usage,
+        }
+    }
+}
+
+impl ToExitCode for FsvAddError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            FsvAddError::Io(err) => io_exit_code(err),
+            FsvAddError::Zip(err) => zip_exit_code(err),
+            FsvAddError::SerdeJson(_) => FsvExitCode::Metadata,
+            #[cfg(feature = "native")]
+            FsvAddError::DbClient(err) => err.exit_code(),
+            FsvAddError::Fsv(err) => err.exit_code(),
+            FsvAddError::GetVideoDuration(err) => err.exit_code(),
+            FsvAddError::UnableToGetFileName(_) => FsvExitCode::Usage,
+            FsvAddError::CreatorInfoNotFound(_) => FsvExitCode::NotFound,
+            FsvAddError::Transcode(err) => err.exit_code(),
+            FsvAddError::Convert(err) => err.exit_code(),
+            #[cfg(feature = "native")]
+            FsvAddError::Naming(err) => err.exit_code(),
+        }
+    }
+}
+
+impl ToExitCode for FsvDeriveError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            FsvDeriveError::Io(err) => io_exit_code(err),
+            FsvDeriveError::Zip(err) => zip_exit_code(err),
+            FsvDeriveError::SerdeJson(_) | FsvDeriveError::NoActions(_) => FsvExitCode::Metadata,
+            FsvDeriveError::Fsv(err) => err.exit_code(),
+            FsvDeriveError::ScriptNotFound(_) | FsvDeriveError::NoScripts => FsvExitCode::NotFound,
+            FsvDeriveError::NothingToDo | FsvDeriveError::InvalidOptions(_) | FsvDeriveError::UnsafeName(_) => FsvExitCode::Usage,
+            FsvDeriveError::NameInUse(_) => FsvExitCode::AlreadyExists,
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl ToExitCode for FsvAlignError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            FsvAlignError::Io(err) => io_exit_code(err),
+            FsvAlignError::Zip(err) => zip_exit_code(err),
+            FsvAlignError::SerdeJson(_) => FsvExitCode::Metadata,
+            FsvAlignError::Fsv(err) => err.exit_code(),
+            FsvAlignError::Transcode(err) => err.exit_code(),
+            FsvAlignError::ScriptNotFound(_) | FsvAlignError::NoScripts | FsvAlignError::VideoNotFound(_) | FsvAlignError::NoVideo => FsvExitCode::NotFound,
+            FsvAlignError::NoMatch { .. } => FsvExitCode::Failure,
+        }
+    }
+}
+
+impl ToExitCode for ConvertError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            ConvertError::Io(err) => io_exit_code(err),
+            ConvertError::SerdeJson(_) | ConvertError::Parse { .. } | ConvertError::NoActions => FsvExitCode::Metadata,
+            ConvertError::UnknownFormat(_) => FsvExitCode::Usage,
+        }
+    }
+}
+
+impl ToExitCode for FsvRemoveError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            FsvRemoveError::Io(err) => io_exit_code(err),
+            FsvRemoveError::Zip(err) => zip_exit_code(err),
+            FsvRemoveError::SerdeJson(_) => FsvExitCode::Metadata,
+            #[cfg(feature = "native")]
+            FsvRemoveError::DbClient(err) => err.exit_code(),
+            FsvRemoveError::Fsv(err) => err.exit_code(),
+            FsvRemoveError::Trash(err) => err.exit_code(),
+            FsvRemoveError::EntryNotFound(_) => FsvExitCode::NotFound,
+        }
+    }
+}
+
+impl ToExitCode for FsvUndoError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            FsvUndoError::Io(err) => io_exit_code(err),
+            FsvUndoError::Zip(err) => zip_exit_code(err),
+            FsvUndoError::SerdeJson(_) => FsvExitCode::Metadata,
+            FsvUndoError::Fsv(err) => err.exit_code(),
+            FsvUndoError::Trash(err) => err.exit_code(),
+            FsvUndoError::NothingToUndo(_) => FsvExitCode::NotFound,
+            FsvUndoError::Modified(_) => FsvExitCode::Failure,
+        }
+    }
+}
+
+impl ToExitCode for TrashError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            TrashError::Io(err) => io_exit_code(err),
+            TrashError::Zip(err) => zip_exit_code(err),
+            TrashError::SerdeJson(_) => FsvExitCode::Metadata,
+        }
+    }
+}
+
+impl ToExitCode for FsvEditError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            FsvEditError::Io(err) => io_exit_code(err),
+            FsvEditError::Zip(err) => zip_exit_code(err),
+            FsvEditError::SerdeJson(_) => FsvExitCode::Metadata,
+            #[cfg(feature = "native")]
+            FsvEditError::DbClient(err) => err.exit_code(),
+            #[cfg(feature = "native")]
+            FsvEditError::ArchiveTx(err) => err.exit_code(),
+            FsvEditError::Fsv(err) => err.exit_code(),
+            FsvEditError::ItemNotFound(..) => FsvExitCode::NotFound,
+            FsvEditError::InvalidLanguage(_) => FsvExitCode::Usage,
+            FsvEditError::Validation(err) => err.exit_code(),
+            FsvEditError::MetadataRejected(report) => (&report.state).into(),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl ToExitCode for FsvPreviewError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            FsvPreviewError::Io(err) => io_exit_code(err),
+            FsvPreviewError::Zip(err) => zip_exit_code(err),
+            FsvPreviewError::SerdeJson(_) => FsvExitCode::Metadata,
+            FsvPreviewError::Fsv(err) => err.exit_code(),
+            FsvPreviewError::Transcode(err) => err.exit_code(),
+            FsvPreviewError::GetVideoDuration(err) => err.exit_code(),
+            FsvPreviewError::NoVideo => FsvExitCode::NotFound,
+            FsvPreviewError::NameInUse(_) => FsvExitCode::AlreadyExists,
+        }
+    }
+}
+
+impl ToExitCode for FsvNotesError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            FsvNotesError::Io(err) => io_exit_code(err),
+            FsvNotesError::Zip(err) => zip_exit_code(err),
+            FsvNotesError::SerdeJson(_) => FsvExitCode::Metadata,
+            FsvNotesError::Fsv(err) => err.exit_code(),
+            FsvNotesError::NotText(_) => FsvExitCode::Usage,
+            FsvNotesError::NameInUse(_) => FsvExitCode::AlreadyExists,
+        }
+    }
+}
+
+impl ToExitCode for FsvRebuildError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            FsvRebuildError::Io(err) => io_exit_code(err),
+            FsvRebuildError::Zip(err) => zip_exit_code(err),
+            FsvRebuildError::SerdeJson(_) => FsvExitCode::Metadata,
+            #[cfg(feature = "native")]
+            FsvRebuildError::DbClient(err) => err.exit_code(),
+            FsvRebuildError::Fsv(err) => err.exit_code(),
+            FsvRebuildError::UnknownData(_) => FsvExitCode::ValidationFailed,
+        }
+    }
+}
+
+impl ToExitCode for ImportError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            ImportError::Io(err) => io_exit_code(err),
+            ImportError::Create(err) => err.exit_code(),
+            ImportError::Add(err) => err.exit_code(),
+            ImportError::Journal(err) => err.exit_code(),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl ToExitCode for SnapshotError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            SnapshotError::Io(err) => io_exit_code(err),
+            SnapshotError::DbClient(err) => err.exit_code(),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl ToExitCode for WatchError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            WatchError::Io(err) => io_exit_code(err),
+            WatchError::Journal(err) => err.exit_code(),
+            WatchError::DropDirNotFound(_) => FsvExitCode::NotFound,
+        }
+    }
+}
+
+impl ToExitCode for PlaybackError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            PlaybackError::Io(err) => io_exit_code(err),
+            PlaybackError::Zip(err) => zip_exit_code(err),
+            PlaybackError::SerdeJson(_) => FsvExitCode::Metadata,
+            PlaybackError::Fsv(err) => err.exit_code(),
+            PlaybackError::ScriptNotFound(_) | PlaybackError::NoScripts | PlaybackError::VideoNotFound(_) | PlaybackError::NoVideo => FsvExitCode::NotFound,
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl ToExitCode for OpenError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            OpenError::Io(err) => io_exit_code(err),
+            OpenError::Zip(err) => zip_exit_code(err),
+            OpenError::Fsv(err) => err.exit_code(),
+            OpenError::Playback(err) => err.exit_code(),
+            OpenError::Launch(_, _) | OpenError::PlayerFailed(_, _) => FsvExitCode::ExternalTool,
+        }
+    }
+}
+
+#[cfg(feature = "play")]
+impl ToExitCode for crate::play::PlayError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            crate::play::PlayError::Playback(err) => err.exit_code(),
+            crate::play::PlayError::SerdeJson(_) | crate::play::PlayError::WebSocket(_) | crate::play::PlayError::Server(_) | crate::play::PlayError::Protocol(_) => FsvExitCode::ExternalTool,
+            crate::play::PlayError::NoDevice => FsvExitCode::NotFound,
+            crate::play::PlayError::Io(err) => io_exit_code(err),
+            crate::play::PlayError::Serial(_) | crate::play::PlayError::Mpv(_) => FsvExitCode::ExternalTool,
+        }
+    }
+}
+
+impl ToExitCode for crate::package::PackageError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            crate::package::PackageError::Io(err) => io_exit_code(err),
+            crate::package::PackageError::Fsv(err) => err.exit_code(),
+            crate::package::PackageError::SerdeJson(_) => FsvExitCode::Failure,
+            crate::package::PackageError::InvalidPieceSize(_) => FsvExitCode::Usage,
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl ToExitCode for crate::register::RegisterError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            crate::register::RegisterError::Io(err) => io_exit_code(err),
+            crate::register::RegisterError::NoDataDir | crate::register::RegisterError::Unsupported => FsvExitCode::Failure,
+            crate::register::RegisterError::ToolFailed(_, _) => FsvExitCode::ExternalTool,
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+impl ToExitCode for crate::fetch::FetchError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            crate::fetch::FetchError::Io(err) => io_exit_code(err),
+            crate::fetch::FetchError::Http(_) => FsvExitCode::Io,
+            crate::fetch::FetchError::SerdeJson(_) | crate::fetch::FetchError::UnverifiableChecksum(..) => FsvExitCode::Metadata,
+            crate::fetch::FetchError::Fsv(err) => err.exit_code(),
+            crate::fetch::FetchError::Edit(err) => err.exit_code(),
+            crate::fetch::FetchError::NotExternal(_) => FsvExitCode::NotFound,
+            crate::fetch::FetchError::ChecksumMismatch(_) => FsvExitCode::ContentIncomplete,
+        }
+    }
+}
+
+#[cfg(feature = "serve")]
+impl ToExitCode for crate::serve::ServeError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            crate::serve::ServeError::Io(err) => io_exit_code(err),
+            crate::serve::ServeError::Bind(_) => FsvExitCode::Io,
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+impl ToExitCode for crate::tui::TuiError {
+    fn exit_code(&self) -> FsvExitCode {
+        match self {
+            crate::tui::TuiError::Io(err) => io_exit_code(err),
+            crate::tui::TuiError::NoFsvFiles(_) => FsvExitCode::NotFound,
+        }
+    }
+}
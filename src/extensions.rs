@@ -0,0 +1,261 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{content_hash, external, history, metadata::FsvMetadata, notes, offsets, preview, subtitle_offsets, titles};
+
+/// Metadata field listing extensions a reader must understand to interpret the container correctly.
+/// Unknown fields are ignored by readers, so this stays compatible with the spec.
+pub const REQUIRED_EXTENSIONS_FIELD: &str = "required_extensions";
+
+pub const CHAPTERS_EXTENSION: &str = "fsv.chapters";
+pub const COVER_EXTENSION: &str = "fsv.cover";
+pub const SIGNATURES_EXTENSION: &str = "fsv.signatures";
+pub const CONTENT_HASHES_EXTENSION: &str = "fsv.content-hashes";
+pub const PREVIEWS_EXTENSION: &str = "fsv.previews";
+pub const HISTORY_EXTENSION: &str = "fsv.history";
+pub const EXTERNAL_CONTENT_EXTENSION: &str = "fsv.external-content";
+pub const PAIR_OFFSETS_EXTENSION: &str = "fsv.pair-offsets";
+pub const LOCALIZED_TITLES_EXTENSION: &str = "fsv.localized-titles";
+pub const SUBTITLE_OFFSETS_EXTENSION: &str = "fsv.subtitle-offsets";
+pub const NOTES_EXTENSION: &str = "fsv.notes";
+
+const COVER_IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+/// A known extension: its identifier, the top-level metadata field holding its data, and a validation hook.
+/// Hooks get the metadata and the archive entry names and return human-readable issues.
+pub struct ExtensionSpec {
+    pub id: &'static str,
+    pub field: &'static str,
+    pub description: &'static str,
+    validate: fn(&FsvMetadata, &[&str]) -> Vec<String>,
+}
+
+impl ExtensionSpec {
+    pub fn validate(&self, metadata: &FsvMetadata, entry_names: &[&str]) -> Vec<String> {
+        (self.validate)(metadata, entry_names)
+    }
+}
+
+pub const KNOWN_EXTENSIONS: [ExtensionSpec; 11] = [
+    ExtensionSpec {
+        id: CHAPTERS_EXTENSION,
+        field: "chapters",
+        description: "Named chapter markers (start in ms)",
+        validate: validate_chapters,
+    },
+    ExtensionSpec {
+        id: COVER_EXTENSION,
+        field: "cover",
+        description: "Cover image stored in the archive",
+        validate: validate_cover,
+    },
+    ExtensionSpec {
+        id: SIGNATURES_EXTENSION,
+        field: "signatures",
+        description: "Signatures over the canonical metadata",
+        validate: validate_signatures,
+    },
+    ExtensionSpec {
+        id: CONTENT_HASHES_EXTENSION,
+        field: content_hash::CONTENT_HASHES_FIELD,
+        description: "BLAKE3 hash per entry and a root hash over all entries",
+        validate: content_hash::validate_content_hashes,
+    },
+    ExtensionSpec {
+        id: PREVIEWS_EXTENSION,
+        field: preview::PREVIEWS_FIELD,
+        description: "Short preview clips cut from a video in the archive",
+        validate: preview::validate_previews,
+    },
+    ExtensionSpec {
+        id: HISTORY_EXTENSION,
+        field: history::HISTORY_FIELD,
+        description: "Changelog of edits made to the archive",
+        validate: history::validate_history,
+    },
+    ExtensionSpec {
+        id: EXTERNAL_CONTENT_EXTENSION,
+        field: external::EXTERNAL_CONTENT_FIELD,
+        description: "Video formats fetched from a URL or relative path instead of stored in the archive",
+        validate: external::validate_external_content,
+    },
+    ExtensionSpec {
+        id: PAIR_OFFSETS_EXTENSION,
+        field: offsets::PAIR_OFFSETS_FIELD,
+        description: "Start offset of a script variant against a particular video format",
+        validate: offsets::validate_pair_offsets,
+    },
+    ExtensionSpec {
+        id: LOCALIZED_TITLES_EXTENSION,
+        field: titles::LOCALIZED_TITLES_FIELD,
+        description: "Titles in other languages, keyed by BCP-47 language tag",
+        validate: titles::validate_localized_titles,
+    },
+    ExtensionSpec {
+        id: SUBTITLE_OFFSETS_EXTENSION,
+        field: subtitle_offsets::SUBTITLE_OFFSETS_FIELD,
+        description: "Offset of a subtitle track against a particular video format",
+        validate: subtitle_offsets::validate_subtitle_offsets,
+    },
+    ExtensionSpec {
+        id: NOTES_EXTENSION,
+        field: notes::NOTES_FIELD,
+        description: "Free-form notes or readme document stored in the archive",
+        validate: notes::validate_notes,
+    },
+];
+
+pub fn find_extension(id: &str) -> Option<&'static ExtensionSpec> {
+    KNOWN_EXTENSIONS.iter().find(|spec| spec.id == id)
+}
+
+/// The result of checking one extension declared (or required) by a container.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionReport {
+    pub id: String,
+    pub known: bool,
+    pub required: bool,
+    pub issues: Vec<String>,
+}
+
+pub fn required_extensions(metadata: &FsvMetadata) -> Vec<String> {
+    match metadata.extra.get(REQUIRED_EXTENSIONS_FIELD) {
+        Some(Value::Array(items)) => items.iter().filter_map(|item| item.as_str().map(str::to_string)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Check every declared or required extension. Per the spec, issues never invalidate the container;
+/// callers should report them as warnings.
+pub fn check_extensions(metadata: &FsvMetadata, entry_names: &[&str]) -> Vec<ExtensionReport> {
+    let required = required_extensions(metadata);
+    let mut ids: Vec<&str> = metadata.extensions.iter().map(String::as_str).collect();
+    for id in &required {
+        if !ids.contains(&id.as_str()) {
+            ids.push(id);
+        }
+    }
+
+    ids.into_iter()
+        .map(|id| {
+            let is_required = required.iter().any(|r| r == id);
+            let mut issues = Vec::new();
+            if id.trim().is_empty() {
+                issues.push("Empty extension identifier".to_string());
+            }
+            else if !id.contains('.') {
+                issues.push("Identifier is not namespaced (e.g. com.example.extension)".to_string());
+            }
+
+            if is_required && !metadata.extensions.iter().any(|e| e == id) {
+                issues.push("Required but not listed in extensions".to_string());
+            }
+
+            let spec = find_extension(id);
+            match spec {
+                Some(spec) => issues.extend(spec.validate(metadata, entry_names)),
+                None if is_required => issues.push("Unknown required extension, container may not be interpreted correctly".to_string()),
+                None => (),
+            }
+
+            ExtensionReport { id: id.to_string(), known: spec.is_some(), required: is_required, issues }
+        })
+        .collect()
+}
+
+fn validate_chapters(metadata: &FsvMetadata, _entry_names: &[&str]) -> Vec<String> {
+    let Some(value) = metadata.extra.get("chapters") else {
+        return vec!["Missing 'chapters' field".to_string()];
+    };
+
+    let Value::Array(chapters) = value else {
+        return vec!["'chapters' must be an array".to_string()];
+    };
+
+    let mut issues = Vec::new();
+    let mut last_start = None;
+    for (i, chapter) in chapters.iter().enumerate() {
+        let start = chapter.get("start").and_then(Value::as_u64);
+        if start.is_none() {
+            issues.push(format!("Chapter {} has no valid 'start'", i));
+        }
+
+        if chapter.get("title").and_then(Value::as_str).is_none() {
+            issues.push(format!("Chapter {} has no 'title'", i));
+        }
+
+        if let (Some(start), Some(last)) = (start, last_start) && start < last {
+            issues.push(format!("Chapter {} starts before the previous chapter", i));
+        }
+
+        last_start = start.or(last_start);
+    }
+
+    issues
+}
+
+fn validate_cover(metadata: &FsvMetadata, entry_names: &[&str]) -> Vec<String> {
+    let Some(value) = metadata.extra.get("cover") else {
+        return vec!["Missing 'cover' field".to_string()];
+    };
+
+    let Some(cover) = value.as_str() else {
+        return vec!["'cover' must be the name of an archive entry".to_string()];
+    };
+
+    let mut issues = Vec::new();
+    if !entry_names.contains(&cover) {
+        issues.push(format!("Cover image '{}' not found in archive", cover));
+    }
+
+    let is_image = cover.rsplit_once('.')
+        .is_some_and(|(_, ext)| COVER_IMAGE_EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)));
+    if !is_image {
+        issues.push(format!("Cover '{}' is not a supported image type ({})", cover, COVER_IMAGE_EXTENSIONS.join(", ")));
+    }
+
+    issues
+}
+
+fn validate_signatures(metadata: &FsvMetadata, _entry_names: &[&str]) -> Vec<String> {
+    let Some(value) = metadata.extra.get("signatures") else {
+        return vec!["Missing 'signatures' field".to_string()];
+    };
+
+    let Value::Array(signatures) = value else {
+        return vec!["'signatures' must be an array".to_string()];
+    };
+
+    let mut issues = Vec::new();
+    for (i, signature) in signatures.iter().enumerate() {
+        for field in ["algorithm", "public_key", "signature"] {
+            if signature.get(field).and_then(Value::as_str).is_none() {
+                issues.push(format!("Signature {} has no '{}'", i, field));
+            }
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semver::Version;
+
+    #[test]
+    fn test_check_extensions() {
+        let mut metadata = FsvMetadata::new(Version::new(1, 0, 0));
+        metadata.extensions = vec![CHAPTERS_EXTENSION.to_string(), COVER_EXTENSION.to_string()];
+        metadata.extra.insert("chapters".to_string(), serde_json::json!([{ "start": 1000, "title": "Intro" }, { "start": 500, "title": "Oops" }]));
+        metadata.extra.insert("cover".to_string(), serde_json::json!("cover.png"));
+        metadata.extra.insert(REQUIRED_EXTENSIONS_FIELD.to_string(), serde_json::json!(["com.example.unknown"]));
+
+        let reports = check_extensions(&metadata, &["metadata.json", "cover.png"]);
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[0].issues, ["Chapter 1 starts before the previous chapter"]);
+        assert!(reports[1].issues.is_empty());
+        assert!(reports[2].required && !reports[2].known);
+        assert_eq!(reports[2].issues.len(), 2);
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{extensions, metadata::FsvMetadata};
+
+/// Metadata field holding the `fsv.external-content` extension data.
+pub const EXTERNAL_CONTENT_FIELD: &str = "external_content";
+
+/// A video format whose file is not stored in the archive but fetched from `source`, an `http(s)` URL or a path relative
+/// to the directory holding the FSV. The format's `checksum` verifies the fetched file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalContent {
+    /// Name of the video format
+    pub name: String,
+    pub source: String,
+    /// Size of the file in bytes, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Fields of other tools, kept when the reference is rewritten
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl ExternalContent {
+    pub fn new(name: String, source: String, size: Option<u64>) -> Self {
+        ExternalContent { name, source, size, extra: HashMap::new() }
+    }
+
+    pub fn is_url(&self) -> bool {
+        let source = self.source.to_ascii_lowercase();
+        source.starts_with("http://") || source.starts_with("https://")
+    }
+}
+
+/// Read the external content references declared in metadata. A missing field means everything is embedded.
+pub fn external_content_from_metadata(metadata: &FsvMetadata) -> Result<Vec<ExternalContent>, serde_json::Error> {
+    metadata.extension_data(EXTERNAL_CONTENT_FIELD)
+}
+
+/// Store `references`, declaring the extension while there are any and dropping both field and declaration once there are none.
+pub fn set_external_content(metadata: &mut FsvMetadata, references: &[ExternalContent]) -> serde_json::Result<()> {
+    metadata.set_extension_data(extensions::EXTERNAL_CONTENT_EXTENSION, EXTERNAL_CONTENT_FIELD, (!references.is_empty()).then_some(references))
+}
+
+pub fn validate_external_content(metadata: &FsvMetadata, entry_names: &[&str]) -> Vec<String> {
+    let Some(value) = metadata.extra.get(EXTERNAL_CONTENT_FIELD) else {
+        return vec![format!("Missing '{}' field", EXTERNAL_CONTENT_FIELD)];
+    };
+
+    let Value::Array(items) = value else {
+        return vec![format!("'{}' must be an array", EXTERNAL_CONTENT_FIELD)];
+    };
+
+    let mut issues = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        let reference = match serde_json::from_value::<ExternalContent>(item.clone()) {
+            Ok(reference) => reference,
+            Err(err) => {
+                issues.push(format!("External reference {} is malformed: {}", i, err));
+                continue;
+            },
+        };
+
+        match metadata.video_formats.iter().find(|format| format.name == reference.name) {
+            Some(format) if format.checksum.trim().is_empty() => issues.push(format!("External video '{}' has no checksum to verify it with", reference.name)),
+            Some(_) => (),
+            None => issues.push(format!("External reference '{}' is not a video format", reference.name)),
+        }
+
+        if reference.source.trim().is_empty() {
+            issues.push(format!("External video '{}' has an empty source", reference.name));
+        }
+
+        if entry_names.contains(&reference.name.as_str()) {
+            issues.push(format!("External video '{}' is also stored in the archive", reference.name));
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{duration::DurationMs, metadata::VideoFormat, semver::Version};
+
+    #[test]
+    fn test_external_content() {
+        let mut metadata = FsvMetadata::new(Version::new(1, 0, 0));
+        metadata.add_video_format(VideoFormat::new("scene.mp4".to_string(), String::new(), DurationMs::from_millis(1000), "sha256:00".to_string()));
+        let reference = ExternalContent::new("scene.mp4".to_string(), "https://example.com/scene.mp4".to_string(), Some(10));
+        assert!(reference.is_url());
+        set_external_content(&mut metadata, std::slice::from_ref(&reference)).unwrap();
+        assert_eq!(metadata.extensions, [extensions::EXTERNAL_CONTENT_EXTENSION]);
+        assert_eq!(external_content_from_metadata(&metadata).unwrap(), [reference]);
+        assert!(validate_external_content(&metadata, &["metadata.json"]).is_empty());
+        assert_eq!(validate_external_content(&metadata, &["scene.mp4"]).len(), 1);
+
+        set_external_content(&mut metadata, &[]).unwrap();
+        assert!(metadata.extensions.is_empty() && !metadata.extra.contains_key(EXTERNAL_CONTENT_FIELD));
+    }
+}
@@ -0,0 +1,187 @@
+use std::{fs::{File, OpenOptions}, io::{BufReader, Read, Write}, path::{Path, PathBuf}};
+
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::{checksum::{Checksum, ParseChecksumError}, external::{self, ExternalContent}, fsv::{self, FsvEditError, FsvError}, progress::{NoProgress, ProgressEvent, ProgressListener}};
+
+/// Bytes between two `fetch` progress events
+const PROGRESS_STEP: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("HTTP error: {0}")]
+    Http(#[from] ureq::Error),
+    #[error("Serde json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+    #[error("FSV edit error: {0}")]
+    Edit(#[from] FsvEditError),
+    #[error("No external video named '{0}'")]
+    NotExternal(String),
+    #[error("External video '{0}' can't be verified: {1}")]
+    UnverifiableChecksum(String, ParseChecksumError),
+    #[error("Checksum mismatch for external video '{0}' (the download was discarded)")]
+    ChecksumMismatch(String),
+}
+
+/// Download the external videos of the FSV at `path` (only `video` if given), verify them against their checksums and store
+/// them in the archive, turning a stub into a complete FSV. Interrupted downloads are kept next to the FSV as `.part` files
+/// and resumed with HTTP range requests. Returns the names of the embedded videos.
+pub fn fetch_external(path: &Path, video: Option<&str>) -> Result<Vec<String>, FetchError> {
+    fetch_external_with_progress(path, video, &NoProgress)
+}
+
+/// Same as `fetch_external`, reporting `fetch` progress events (in bytes) per video.
+pub fn fetch_external_with_progress(path: &Path, video: Option<&str>, progress: &dyn ProgressListener) -> Result<Vec<String>, FetchError> {
+    let metadata = fsv::read_fsv_metadata(path)?;
+    let mut references = external::external_content_from_metadata(&metadata)?;
+    if let Some(video) = video {
+        references.retain(|reference| reference.name == video);
+        if references.is_empty() {
+            return Err(FetchError::NotExternal(video.to_string()));
+        }
+    }
+
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let mut files = Vec::new();
+    let mut downloads = Vec::new();
+    for reference in &references {
+        let checksum = metadata.video_formats.iter().find(|format| format.name == reference.name).map(|format| format.checksum.as_str()).unwrap_or_default();
+        let checksum: Checksum = checksum.parse().map_err(|err| FetchError::UnverifiableChecksum(reference.name.clone(), err))?;
+        let file = match reference.is_url() {
+            true => {
+                let partial = partial_path(path, reference);
+                download(reference, &partial, progress)?;
+                downloads.push(partial.clone());
+                partial
+            },
+            false => base_dir.join(&reference.source),
+        };
+
+        let digest = checksum.algorithm.digest_reader(&mut BufReader::new(File::open(&file)?))?;
+        if digest != checksum.digest {
+            if reference.is_url() {
+                // A corrupt download would otherwise be resumed forever
+                std::fs::remove_file(&file)?;
+            }
+            return Err(FetchError::ChecksumMismatch(reference.name.clone()));
+        }
+
+        info!(operation = "fetch", archive = %path.display(), item = %reference.name, outcome = "verified", "Verified external video");
+        files.push((reference.name.clone(), file));
+    }
+
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    fsv::embed_external_content(path, &files)?;
+    for download in downloads {
+        if let Err(err) = std::fs::remove_file(&download) {
+            warn!(operation = "fetch", archive = %path.display(), item = %download.display(), outcome = "cleanup_failed", error = %err, "Unable to remove download");
+        }
+    }
+
+    Ok(files.into_iter().map(|(name, _)| name).collect())
+}
+
+/// Where the download of `reference` is kept until it is embedded: `<fsv file name>.<video name>.part` next to the FSV.
+fn partial_path(path: &Path, reference: &ExternalContent) -> PathBuf {
+    let archive_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let video_name = fsv::sanitize_path_component(&reference.name).unwrap_or_else(|| "video".to_string());
+    path.with_file_name(format!("{}.{}.part", archive_name, video_name))
+}
+
+/// Download `reference.source` into `partial`, continuing after the bytes already there if the server supports range requests.
+fn download(reference: &ExternalContent, partial: &Path, progress: &dyn ProgressListener) -> Result<(), FetchError> {
+    let offset = std::fs::metadata(partial).map(|metadata| metadata.len()).unwrap_or(0);
+    if reference.size.is_some_and(|size| offset >= size) {
+        return Ok(());
+    }
+
+    let mut request = ureq::get(&reference.source);
+    if offset > 0 {
+        request = request.header("Range", format!("bytes={}-", offset));
+    }
+
+    let mut response = match request.call() {
+        Ok(response) => response,
+        // The partial file already holds everything
+        Err(ureq::Error::StatusCode(416)) if offset > 0 => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+
+    // Servers ignoring the range send the whole file again
+    let resumed = response.status() == 206;
+    let mut file = OpenOptions::new().create(true).write(true).append(resumed).truncate(!resumed).open(partial)?;
+    let mut done = if resumed { offset } else { 0 };
+    if resumed {
+        info!(operation = "fetch", item = %reference.name, outcome = "resumed", "Resuming download at {} bytes", offset);
+    }
+
+    let total = response.body().content_length().map(|length| length + done).or(reference.size).unwrap_or(0);
+    progress.on_event(ProgressEvent::started("fetch", &reference.name));
+    let mut reader = response.body_mut().as_reader();
+    let mut buffer = vec![0; 64 * 1024];
+    let mut reported = done;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+
+        file.write_all(&buffer[..read])?;
+        done += read as u64;
+        if done - reported >= PROGRESS_STEP {
+            progress.on_event(ProgressEvent::progress("fetch", &reference.name, done as usize, total.max(done) as usize));
+            reported = done;
+        }
+    }
+
+    file.flush()?;
+    progress.on_event(ProgressEvent::completed("fetch", &reference.name));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{duration::DurationMs, fsv::{get_file_hash, read_fsv_entry, FsvBuilder}, metadata::VideoFormat};
+
+    const VIDEO: &[u8] = b"\0\0\0\x18ftypisom";
+
+    #[test]
+    fn test_fetch_local_source() {
+        let dir = std::env::temp_dir().join(format!("fsv-fetch-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fsv_path = dir.join("scene.fsv");
+        let write_stub = |checksum: String| {
+            let mut builder = FsvBuilder::new("scene");
+            builder.metadata_mut().add_video_format(VideoFormat::new("video.mp4".to_string(), String::new(), DurationMs::from_millis(1000), checksum));
+            let reference = ExternalContent::new("video.mp4".to_string(), "media/video.mp4".to_string(), None);
+            external::set_external_content(builder.metadata_mut(), &[reference]).unwrap();
+            std::fs::write(&fsv_path, builder.to_bytes().unwrap()).unwrap();
+        };
+        std::fs::create_dir_all(dir.join("media")).unwrap();
+        std::fs::write(dir.join("media/video.mp4"), VIDEO).unwrap();
+
+        write_stub(get_file_hash(b"other"));
+        assert!(matches!(fetch_external(&fsv_path, None), Err(FetchError::ChecksumMismatch(_))));
+        assert!(matches!(fetch_external(&fsv_path, Some("missing.mp4")), Err(FetchError::NotExternal(_))));
+
+        write_stub(get_file_hash(VIDEO));
+        assert_eq!(fetch_external(&fsv_path, None).unwrap(), ["video.mp4"]);
+        assert_eq!(read_fsv_entry(&fsv_path, "video.mp4").unwrap(), VIDEO);
+        let metadata = fsv::read_fsv_metadata(&fsv_path).unwrap();
+        assert!(external::external_content_from_metadata(&metadata).unwrap().is_empty());
+        assert!(metadata.extensions.is_empty());
+        assert!(fetch_external(&fsv_path, None).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,9 +1,12 @@
-use std::{path::Path, process::Command, str::FromStr};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "native")]
+use std::{io::Read, process::{Command, Output, Stdio}, str::FromStr};
 
 use sha2::{Digest, Sha256};
 use thiserror::Error;
+use tracing::warn;
 
-use crate::funscript::Funscript;
+use crate::{duration::DurationMs, error_context::IoContext, funscript::Funscript};
 
 //const VIDEO_SIG: Map<u64, &'static str> 
 
@@ -26,19 +29,56 @@ pub enum GetDurationError {
     FunscriptMissingActions,
 }
 
-/// Get video duration (in seconds) using `ffprobe`.
+/// Get video duration using `ffprobe`.
 /// Requires ffprobe to be installed and on PATH.
-pub fn get_video_duration<P: AsRef<Path>>(path: P) -> Result<u64, GetDurationError> {
+#[cfg(feature = "native")]
+pub fn get_video_duration<P: AsRef<Path>>(path: P) -> Result<DurationMs, GetDurationError> {
     let output = Command::new("ffprobe")
-        .args([
-            "-v", "error",
-            "-select_streams", "v:0",
-            "-show_entries", "format=duration",
-            "-of", "default=noprint_wrappers=1:nokey=1",
-            path.as_ref().to_str().unwrap(),
-        ])
-        .output()?;
+        .args(FFPROBE_DURATION_ARGS)
+        .arg(path.as_ref())
+        .output()
+        .path_context("running ffprobe on", &path)?;
 
+    parse_ffprobe_duration(&output)
+}
+
+/// Get the duration of the video read from `reader`, piping it into `ffprobe` instead of writing it to disk.
+/// ffprobe stops reading once it knows the duration, which for MP4s with the index at the end is only after the whole video.
+/// Requires ffprobe to be installed and on PATH.
+#[cfg(feature = "native")]
+pub fn get_video_duration_from_reader(reader: &mut impl Read) -> Result<DurationMs, GetDurationError> {
+    let mut child = Command::new("ffprobe")
+        .args(FFPROBE_DURATION_ARGS)
+        .arg("pipe:0")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().expect("ffprobe stdin is piped");
+    match std::io::copy(reader, &mut stdin) {
+        // ffprobe closes its input once it has what it needs
+        Err(err) if err.kind() != std::io::ErrorKind::BrokenPipe => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(err.into());
+        },
+        _ => drop(stdin),
+    }
+
+    parse_ffprobe_duration(&child.wait_with_output()?)
+}
+
+#[cfg(feature = "native")]
+const FFPROBE_DURATION_ARGS: [&str; 8] = [
+    "-v", "error",
+    "-select_streams", "v:0",
+    "-show_entries", "format=duration",
+    "-of", "default=noprint_wrappers=1:nokey=1",
+];
+
+#[cfg(feature = "native")]
+fn parse_ffprobe_duration(output: &Output) -> Result<DurationMs, GetDurationError> {
     if !output.status.success() {
         return Err(GetDurationError::Ffprobe(format!(
             "{}",
@@ -49,15 +89,15 @@ pub fn get_video_duration<P: AsRef<Path>>(path: P) -> Result<u64, GetDurationErr
     let stdout = String::from_utf8_lossy(&output.stdout);
     let trimmed = stdout.trim();
 
-    // Parse seconds (float) -> milliseconds (u64)
+    // ffprobe reports seconds
     let seconds = f64::from_str(trimmed)?;
-    let ms = (seconds * 1000.0).round() as u64;
 
-    Ok(ms)
+    Ok(DurationMs::from_secs_f64(seconds))
 }
 
-pub fn get_funscript_duration(funscript: &Funscript) -> Result<u64, GetDurationError> {
-    funscript.actions.iter().map(|a| a.at).max().ok_or(GetDurationError::FunscriptMissingActions)
+/// Time of the last action. The `duration` of the script's embedded metadata isn't used, editors store it in seconds.
+pub fn get_funscript_duration(funscript: &Funscript) -> Result<DurationMs, GetDurationError> {
+    funscript.actions.iter().map(|a| DurationMs::from_millis(a.at)).max().ok_or(GetDurationError::FunscriptMissingActions)
     // Metadata appears to store duration in seconds
     // if let Some(metadata) = funscript.metadata {
     //     Ok(metadata.duration)
@@ -66,3 +106,70 @@ pub fn get_funscript_duration(funscript: &Funscript) -> Result<u64, GetDurationE
     //     funscript.actions.iter().map(|a| a.at).max().ok_or(GetDurationError::FunscriptMissingActions)
     // }
 }
+
+/// Files in `dir` and its subdirectories for which `matches` holds, sorted. Unreadable subdirectories are skipped with a warning.
+pub fn find_files(dir: &Path, matches: impl Fn(&Path) -> bool) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = match std::fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(err) if current != dir => {
+                warn!(operation = "scan", item = %current.display(), outcome = "skipped", error = %err, "Skipping unreadable directory");
+                continue;
+            },
+            Err(err) => return Err(err).path_context("listing", dir),
+        };
+
+        for entry in entries {
+            let path = entry.path_context("listing", &current)?.path();
+            if path.is_dir() {
+                pending.push(path);
+            }
+            else if path.is_file() && matches(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+/// Move `file` into `dir`, keeping its name.
+pub fn move_into_dir(file: &Path, dir: &Path) -> std::io::Result<()> {
+    let file_name = file.file_name().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let destination = dir.join(file_name);
+    // rename fails across filesystems, fall back to copy + remove
+    if std::fs::rename(file, &destination).is_err() {
+        std::fs::copy(file, &destination).path_context("copying", file)?;
+        std::fs::remove_file(file).path_context("removing", file)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_unicode_file_names() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let dir = std::env::temp_dir().join(format!("fsv-file-util-test-{}", std::process::id()));
+        let archive_dir = dir.join("archive");
+        std::fs::create_dir_all(&archive_dir).unwrap();
+        let video = dir.join(OsStr::from_bytes(b"sc\xe8ne.mp4"));
+        std::fs::write(&video, b"not a video").unwrap();
+
+        // Fails without ffprobe or on the bogus video, but never panics on the name
+        assert!(get_video_duration(&video).is_err());
+        move_into_dir(&video, &archive_dir).unwrap();
+        assert!(archive_dir.join(OsStr::from_bytes(b"sc\xe8ne.mp4")).is_file());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,15 +1,218 @@
-use std::{collections::HashSet, fs::File, io::{Read, Write}, path::{Path, PathBuf}};
+use std::{borrow::Cow, collections::{BTreeMap, HashMap, HashSet}, fs::File, io::{Read, Seek, Write}, path::{Path, PathBuf}};
 
 use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{error, info, warn};
 use zip::write::SimpleFileOptions;
 
-use crate::{db_client::{self, DbClient}, file_util, funscript::Funscript, metadata::{CreatorInfo, FsvMetadata, ScriptVariant, SubtitleTrack, VideoFormat, WorkCreatorsMetadata, WorkItem}, semver::Version};
+use crate::{checksum::{Checksum, HashAlgorithm, HashingReader, ParseChecksumError}, content, content_hash::{self, ContentHashes, HashVerification}, convert::ConvertError, duration::DurationMs, entry_name, extensions::{self, ExtensionReport}, external::{self, ExternalContent}, file_util, history, funscript::{Funscript, transform::{self, TransformOptions}}, hash_cache::EntryHashCache, import, error_context::{IoContext, ZipContext}, journal::{Journal, JournalError, JournalOperation}, lock::ArchiveLock, magic, metadata::{self, CreatorInfo, FsvMetadata, ScriptVariant, SubtitleTrack, VideoFormat, WorkCreatorsMetadata, WorkItem}, notes, offsets::{self, PairOffset}, path_safety::{self, PathSafety, PathSafetyError}, policy::PolicyError, preview, schema, socials::{self, Social}, subtitle_offsets::{self, SubtitleOffset}, titles, progress::{NoProgress, ProgressEvent, ProgressListener}, semver::Version, simplify::SimplifyOptions, transcode::{TranscodeError, TranscodeWorkDir}, trash::{self, TrashError, TrashSnapshot}};
+#[cfg(feature = "native")]
+use crate::{align::{self, AlignEstimate, AlignSignal}, archive_tx::{ArchiveTransaction, ArchiveTxError}, convert::{self, ScriptFormat}, db_client::{self, CreatorRecord, DbClient}, funscript::FunscriptMetadata, hash_cache, journal::DbWrite, library, naming::{NamingError, NamingPolicy}, policy::ContentPolicy, preview::{Preview, PreviewSegment}, transcode::{self, TranscodeProfile, TranscodedVideo}};
 
 const LATEST_FSV_FORMAT_VERSION: Version = Version::new(1, 0, 0);
 const MINIMUM_FSV_FORMAT_VERSION: Version = Version::new(1, 0, 0);
-const AXES: [&str; 11] = ["pitch", "roll", "suckManual", "surge", "sway", "twist", "valve", "vib", "lube", "suck", "max"]; // TODO: Check if there are more axes in use
+pub const AXES: [&str; 11] = ["pitch", "roll", "suckManual", "surge", "sway", "twist", "valve", "vib", "lube", "suck", "max"]; // TODO: Check if there are more axes in use
+/// How far an axis script's duration may drift from its script variant's before validation warns about it
+const AXIS_DURATION_TOLERANCE: DurationMs = DurationMs::from_millis(1000);
+/// How far an entry's duration may drift from the one in the metadata before deep validation warns about it, by default
+pub const DEFAULT_DURATION_TOLERANCE_MS: u64 = 1000;
+
+/// A script variant and its axis scripts form one bundle: `scene.funscript` with `additional_axes: ["roll"]` owns `scene.roll.funscript`.
+/// Returns (axis, entry name) for each listed axis. Variants not named `*.funscript` have no axis scripts.
+pub fn axis_script_names(variant: &ScriptVariant) -> Vec<(String, String)> {
+    let Some((stem, None)) = import::split_script_name(&variant.name) else {
+        return Vec::new();
+    };
+
+    variant.additional_axes.iter().map(|axis| (axis.clone(), format!("{}.{}.{}", stem, axis, import::SCRIPT_EXTENSION))).collect()
+}
+
+/// How metadata names are matched against archive entry names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum NameMatching {
+    /// Names must match exactly, as the spec requires. Entries that only match after normalization are reported as mismatched.
+    #[default]
+    Strict,
+    /// Names also match case-insensitively and with `\` treated as `/`, for archives produced by other tools.
+    Normalized,
+}
+
+/// How validation treats metadata fields the format doesn't define. They are kept on parse either way, so metadata written by
+/// newer or other tools round-trips, but that also hides typos like `video_fromats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ParseMode {
+    /// Keep unknown fields without reporting them
+    #[default]
+    Lenient,
+    /// Report unknown fields as warnings
+    Warn,
+    /// Report unknown fields as errors that make the metadata invalid
+    Strict,
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidateOptions {
+    pub name_matching: NameMatching,
+    pub parse_mode: ParseMode,
+    /// Also check what the metadata records about each entry against its content: script checksums and durations, and video
+    /// durations (probed with ffprobe, reading every video in full; skipped without the `native` feature).
+    pub deep: bool,
+    /// How far a duration measured by deep validation may drift from the metadata's.
+    pub duration_tolerance: DurationMs,
+}
+
+impl Default for ValidateOptions {
+    fn default() -> Self {
+        ValidateOptions {
+            name_matching: NameMatching::default(),
+            parse_mode: ParseMode::default(),
+            deep: false,
+            duration_tolerance: DurationMs::from_millis(DEFAULT_DURATION_TOLERANCE_MS),
+        }
+    }
+}
+
+/// Normalize an entry name for lenient comparison: backslashes become slashes, leading `./` and `/` are dropped, and case is folded.
+pub fn normalize_entry_name(name: &str) -> String {
+    let name = name.trim().replace('\\', "/");
+    let mut name = name.as_str();
+    loop {
+        if let Some(stripped) = name.strip_prefix("./") {
+            name = stripped;
+        }
+        else if let Some(stripped) = name.strip_prefix('/') {
+            name = stripped;
+        }
+        else {
+            break;
+        }
+    }
+
+    name.to_lowercase()
+}
+
+/// Result of looking up a metadata name among the archive entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryLookup {
+    Exact,
+    /// Matched once the entry name is decoded as UTF-8, as it was stored without the UTF-8 flag (see `entry_name::decode_entry_name`).
+    /// Holds the entry name as the ZIP library reads it, which is what opens the entry.
+    Decoded(String),
+    /// Only matched after normalization. Holds the actual entry name in the archive.
+    Normalized(String),
+    Missing,
+}
+
+/// Archive entry names indexed for exact and normalized lookups.
+#[derive(Debug)]
+pub struct EntryIndex {
+    exact: HashSet<String>,
+    decoded: HashMap<String, String>,
+    normalized: HashMap<String, String>,
+}
+
+impl EntryIndex {
+    pub fn new<'a>(names: impl Iterator<Item = &'a str>) -> Self {
+        let mut exact = HashSet::new();
+        let mut decoded = HashMap::new();
+        let mut normalized = HashMap::new();
+        for name in names {
+            exact.insert(name.to_string());
+            let decoded_name = entry_name::decode_entry_name(name);
+            normalized.entry(normalize_entry_name(&decoded_name)).or_insert_with(|| name.to_string());
+            if decoded_name != name {
+                decoded.insert(decoded_name.into_owned(), name.to_string());
+            }
+        }
+
+        EntryIndex { exact, decoded, normalized }
+    }
+
+    pub fn lookup(&self, name: &str) -> EntryLookup {
+        if self.exact.contains(name) {
+            return EntryLookup::Exact;
+        }
+
+        if let Some(actual) = self.decoded.get(name) {
+            return EntryLookup::Decoded(actual.clone());
+        }
+
+        match self.normalized.get(&normalize_entry_name(name)) {
+            Some(actual) => EntryLookup::Normalized(actual.clone()),
+            None => EntryLookup::Missing,
+        }
+    }
+
+    /// The archive entry to open for `name` under `matching`, if there is one.
+    pub fn resolve(&self, name: &str, matching: NameMatching) -> Option<String> {
+        match (self.lookup(name), matching) {
+            (EntryLookup::Exact, _) => Some(name.to_string()),
+            (EntryLookup::Decoded(actual), _) | (EntryLookup::Normalized(actual), NameMatching::Normalized) => Some(actual),
+            _ => None,
+        }
+    }
+}
+
+/// Whether an archive entry name is safe to extract: no `..` components, no absolute path and no drive prefix.
+pub fn is_safe_entry_name(name: &str) -> bool {
+    if name.starts_with('/') || name.starts_with('\\') || name.contains('\0') {
+        return false;
+    }
+
+    let bytes = name.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        return false;
+    }
+
+    !name.split(['/', '\\']).any(|component| component == "..")
+}
+
+/// Make a metadata-derived string (e.g. the title) usable as a single file or folder name.
+/// Path separators, reserved and control characters are replaced with `_`. Returns `None` if nothing usable is left.
+pub fn sanitize_path_component(name: &str) -> Option<String> {
+    let sanitized: String = name.chars()
+        .map(|c| match c {
+            '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // Windows does not allow trailing dots or spaces
+    let sanitized = sanitized.trim().trim_end_matches(['.', ' ']);
+    if sanitized.is_empty() || sanitized == "." || sanitized == ".." {
+        return None;
+    }
+
+    Some(sanitized.to_string())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    /// Extract even if some referenced content files are missing.
+    pub allow_content_incomplete: bool,
+    pub name_matching: NameMatching,
+    /// Extract only this kind of entry instead of video/script pairs.
+    pub only: Option<ExtractOnly>,
+    /// Language ranges (e.g. `en`, `ja`, `pt-BR`) of the subtitle tracks written next to every pair, all of them if empty.
+    pub subtitle_languages: Vec<String>,
+    /// What to do with names Windows can't store.
+    pub path_safety: PathSafety,
+    /// Shift every extracted script by its start offset against the pair's video (its pair offset, or else the variant's
+    /// `start_offset`), not just by pair offsets, and every subtitle track by its offset against the pair's video,
+    /// for players that ignore the metadata.
+    pub apply_offset: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExtractOnly {
+    /// Preview clips declared by the `fsv.previews` extension
+    Previews,
+    /// The notes document declared by the `fsv.notes` extension
+    Notes,
+    /// metadata.json alone, without validating the archive first
+    Metadata,
+}
 
 #[derive(Debug, Error)]
 pub enum FsvExtractError {
@@ -25,88 +228,92 @@ pub enum FsvExtractError {
     MetadataNotFound,
     #[error("Invalid state for extraction")]
     InvalidState(FsvState),
+    #[error("Unsafe extraction path: {0}")]
+    UnsafePath(#[from] PathSafetyError),
 }
 
-pub fn extract_fsv(path: &Path, output_dir: &Path, allow_content_incomplete_extract: bool) -> Result<(), FsvExtractError> {
-    let fsv_state = validate_fsv(path)?;
-    match &fsv_state {
-        FsvState::Valid => (),
-        FsvState::ContentIncomplete(_) => {
-            if !allow_content_incomplete_extract {
-                return Err(FsvExtractError::InvalidState(fsv_state));
-            }
-        },
-        FsvState::MetadataInvalid(_) => return Err(FsvExtractError::InvalidState(fsv_state)),
-    }
-
-    let file = std::fs::File::open(path)?;
-    let mut archive = zip::ZipArchive::new(file)?;
-    let metadata_json = {
-        let result = archive.by_name("metadata.json");
-        let mut metadata_file = match result {
-            Ok(file) => file,
-            Err(zip_err) => {
-                match zip_err {
-                    zip::result::ZipError::FileNotFound => {
-                        return Err(FsvExtractError::MetadataNotFound);
-                    }
-                    _ => {
-                        return Err(FsvExtractError::Zip(zip_err));
-                    }
+pub fn extract_fsv(path: &Path, output_dir: &Path, options: &ExtractOptions) -> Result<(), FsvExtractError> {
+    let fallback_dirname = path.file_stem().map(entry_name::os_str_entry_name).unwrap_or_else(|| "extracted_fsv".to_string());
+    let mut container = FsvContainer::open(path)?;
+    container.extract(output_dir, &fallback_dirname, options)
+}
+
+fn extract_archive<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, structure: &ArchiveStructure, index: &EntryIndex, metadata_json: &str, output_dir: &Path, fallback_dirname: &str, options: &ExtractOptions) -> Result<(), FsvExtractError> {
+    // Validation reads every script and subtitle, which metadata alone doesn't need
+    if options.only != Some(ExtractOnly::Metadata) {
+        let report = validate_archive_with_json(archive, structure, index, metadata_json, &ValidateOptions { name_matching: options.name_matching, ..ValidateOptions::default() })?;
+        let fsv_state = log_validation_report(report);
+        match &fsv_state {
+            FsvState::Valid => (),
+            FsvState::ContentIncomplete(_) => {
+                // Previews and notes are useful even when the (possibly withheld) full videos are missing
+                if !options.allow_content_incomplete && !matches!(options.only, Some(ExtractOnly::Previews | ExtractOnly::Notes)) {
+                    return Err(FsvExtractError::InvalidState(fsv_state));
                 }
             },
-        };
-
-        let mut metadata_json = String::new();
-        metadata_file.read_to_string(&mut metadata_json)?;
-        
-        metadata_json
-    };
+            FsvState::MetadataInvalid(_) => return Err(FsvExtractError::InvalidState(fsv_state)),
+        }
+    }
 
-    let result = serde_json::from_str::<FsvMetadata>(&metadata_json);
+    let result = serde_json::from_str::<FsvMetadata>(metadata_json);
     let metadata = match result {
         Ok(metadata) => metadata,
         Err(err) => return Err(FsvExtractError::SerdeJson(err)), // TODO: better error handling
     };
 
-    let output_dirname = metadata.title.trim();
-    let output_dirname = if output_dirname.is_empty() {
-        path.file_stem()
-            .and_then(|os_str| os_str.to_str())
-            .unwrap_or("extracted_fsv")
-    }
-    else {
-        output_dirname
+    let fallback_dirname = sanitize_path_component(fallback_dirname).unwrap_or_else(|| "extracted_fsv".to_string());
+    let output_dirname = match metadata.title.trim() {
+        "" => path_safety::safe_dir_name(output_dir, &fallback_dirname, "extracted_fsv", options.path_safety)?,
+        title => path_safety::safe_dir_name(output_dir, title, &fallback_dirname, options.path_safety)?,
     };
 
     let extraction_path = output_dir.join(output_dirname);
-    std::fs::create_dir_all(&extraction_path)?;
+    std::fs::create_dir_all(&extraction_path).path_context("creating", &extraction_path)?;
+
+    match options.only {
+        Some(ExtractOnly::Previews) => return extract_previews(archive, &metadata, &extraction_path, options.path_safety),
+        Some(ExtractOnly::Notes) => return extract_notes(archive, index, &metadata, &extraction_path, options),
+        Some(ExtractOnly::Metadata) => {
+            let output_path = extraction_path.join("metadata.json");
+            std::fs::write(&output_path, metadata_json).path_context("writing", &output_path)?;
+            return Ok(());
+        },
+        None => (),
+    }
+
+    extract_notes(archive, index, &metadata, &extraction_path, options)?;
+    let subtitles = read_subtitles(archive, index, &metadata, options);
 
     // Create video-script pairs for each combination of video format and script variant
     for video_format in &metadata.video_formats {
         let file_name = video_format.name.trim();
         if file_name.is_empty() {
-            warn!("A video format has an empty name, skipping extraction");
+            warn!(operation = "extract", outcome = "skipped", "A video format has an empty name, skipping extraction");
             continue;
         }
 
         // Need to scope to release borrow on archive
         let video_data = {
-            let file_in_archive = archive.by_name(file_name);
+            let Some(entry_name) = index.resolve(file_name, options.name_matching) else {
+                warn!(operation = "extract", item = %file_name, outcome = "skipped", "Video file not found in archive, skipping extraction");
+                continue;
+            };
+
+            let file_in_archive = archive.by_name(&entry_name);
             let mut file_in_archive = match file_in_archive {
                 Ok(file) => file,
                 Err(err) => {
                     match err {
                         zip::result::ZipError::Io(_) => {
-                            warn!("Unable to read video file '{}', skipping extraction", file_name);
+                            warn!(operation = "extract", item = %file_name, outcome = "skipped", "Unable to read video file, skipping extraction");
                             continue;
                         },
                         zip::result::ZipError::FileNotFound => {
-                            warn!("Video file '{}' not found in archive, skipping extraction", file_name);
+                            warn!(operation = "extract", item = %file_name, outcome = "skipped", "Video file not found in archive, skipping extraction");
                             continue;
                         },
-                        zip::result::ZipError::InvalidPassword => {
-                            warn!("Video file '{}' is password protected, skipping extraction", file_name);
+                        _ if password_required(&err) => {
+                            warn!(operation = "extract", item = %file_name, outcome = "skipped", "Video file is password protected, skipping extraction");
                             continue;
                         },
                         _ => return Err(FsvExtractError::Zip(err)),
@@ -119,7 +326,7 @@ pub fn extract_fsv(path: &Path, output_dir: &Path, allow_content_incomplete_extr
             match result {
                 Ok(_) => (),
                 Err(err) => {
-                    warn!("Error reading video file '{}': {}, skipping extraction", file_name, err);
+                    warn!(operation = "extract", item = %file_name, outcome = "skipped", error = %err, "Error reading video file, skipping extraction");
                     continue;
                 },
             }
@@ -130,25 +337,35 @@ pub fn extract_fsv(path: &Path, output_dir: &Path, allow_content_incomplete_extr
         for script_variant in &metadata.script_variants {
             let script_file_name = script_variant.name.trim();
             if script_file_name.is_empty() {
-                warn!("A script variant has an empty name, skipping extraction");
+                warn!(operation = "extract", outcome = "skipped", "A script variant has an empty name, skipping extraction");
                 continue;
             }
 
-            let file_in_archive = archive.by_name(script_file_name);
+            let Some(entry_name) = index.resolve(script_file_name, options.name_matching) else {
+                warn!(operation = "extract", item = %script_file_name, outcome = "skipped", "Script file not found in archive, skipping extraction");
+                continue;
+            };
+
+            // A bundle is only useful complete, so a missing axis script skips the whole variant
+            let Some(axis_data) = read_axis_scripts(archive, index, script_variant, options.name_matching) else {
+                continue;
+            };
+
+            let file_in_archive = archive.by_name(&entry_name);
             let mut file_in_archive = match file_in_archive {
                 Ok(file) => file,
                 Err(err) => {
                     match err {
                         zip::result::ZipError::Io(_) => {
-                            warn!("Unable to read script file '{}', skipping extraction", script_file_name);
+                            warn!(operation = "extract", item = %script_file_name, outcome = "skipped", "Unable to read script file, skipping extraction");
                             continue;
                         },
                         zip::result::ZipError::FileNotFound => {
-                            warn!("Script file '{}' not found in archive, skipping extraction", script_file_name);
+                            warn!(operation = "extract", item = %script_file_name, outcome = "skipped", "Script file not found in archive, skipping extraction");
                             continue;
                         },
-                        zip::result::ZipError::InvalidPassword => {
-                            warn!("Script file '{}' is password protected, skipping extraction", script_file_name);
+                        _ if password_required(&err) => {
+                            warn!(operation = "extract", item = %script_file_name, outcome = "skipped", "Script file is password protected, skipping extraction");
                             continue;
                         },
                         _ => return Err(FsvExtractError::Zip(err)),
@@ -162,7 +379,7 @@ pub fn extract_fsv(path: &Path, output_dir: &Path, allow_content_incomplete_extr
                 match result {
                     Ok(_) => (),
                     Err(err) => {
-                        warn!("Error reading script file '{}': {}, skipping extraction", script_file_name, err);
+                        warn!(operation = "extract", item = %script_file_name, outcome = "skipped", error = %err, "Error reading script file, skipping extraction");
                         continue;
                     },
                 }
@@ -180,18 +397,201 @@ pub fn extract_fsv(path: &Path, output_dir: &Path, allow_content_incomplete_extr
             let script_stem = script_parts.next().unwrap_or(script_file_name);
             let script_ext = script_parts.next().unwrap_or(DEFAULT_SCRIPT_EXT); // Some scripts may have multiple extensions (e.g., .roll.funscript)
 
-            let output_video_filename = format!("{}_{}.{}", video_stem, script_stem, video_ext);
-            let output_script_filename = format!("{}_{}.{}", video_stem, script_stem, script_ext);
-            let output_video_path = extraction_path.join(output_video_filename);
-            let output_script_path = extraction_path.join(output_script_filename);
-            std::fs::write(&output_video_path, &video_data)?;
-            std::fs::write(&output_script_path, &script_data)?;
+            // Every file of the pair is named after the same stem, shortened alike if the longest name wouldn't fit
+            let video_suffix = format!(".{}", video_ext);
+            let script_suffix = format!(".{}", script_ext);
+            let axis_suffixes: Vec<String> = match script_suffix.strip_suffix(&format!(".{}", import::SCRIPT_EXTENSION)) {
+                Some(script_suffix) => axis_data.iter().map(|(axis, _)| format!("{}.{}.{}", script_suffix, axis, import::SCRIPT_EXTENSION)).collect(),
+                None => Vec::new(),
+            };
+            let suffixes: Vec<&str> = [video_suffix.as_str(), script_suffix.as_str()].into_iter()
+                .chain(subtitles.iter().map(|(_, suffix, _)| suffix.as_str()))
+                .chain(axis_suffixes.iter().map(String::as_str))
+                .collect();
+            let output_stem = path_safety::safe_component(&format!("{}_{}", video_stem, script_stem), "video", options.path_safety)?;
+            let output_stem = path_safety::fit_stem(&extraction_path, &output_stem, &suffixes, options.path_safety)?;
+            // Entry names may contain directories, but extracted files always go directly into the extraction folder
+            let output_video_filename = path_safety::safe_component(&format!("{}{}", output_stem, video_suffix), &format!("video.{}", DEFAULT_VIDEO_EXT), options.path_safety)?;
+            let output_script_filename = path_safety::safe_component(&format!("{}{}", output_stem, script_suffix), &format!("script.{}", DEFAULT_SCRIPT_EXT), options.path_safety)?;
+            let output_video_path = extraction_path.join(&output_video_filename);
+            let output_script_path = extraction_path.join(&output_script_filename);
+            std::fs::write(&output_video_path, &video_data).path_context("writing", &output_video_path)?;
+            // Scripts are shifted into sync with this encode when it has its own offset, players don't know about it
+            let offset = match options.apply_offset {
+                true => offsets::start_offset(&metadata, &script_variant.name, Some(&video_format.name)),
+                false => offsets::pair_offset(&metadata, &script_variant.name, &video_format.name),
+            };
+            let shift = |data: &[u8]| match offset.filter(|offset_ms| *offset_ms != 0) {
+                Some(offset_ms) => offsets::shift_funscript(data, offset_ms),
+                None => Ok(data.to_vec()),
+            };
+            std::fs::write(&output_script_path, shift(&script_data)?).path_context("writing", &output_script_path)?;
+            // Players pick up subtitles named after the video with the language before the extension
+            // Built from the file names rather than the whole paths, which needn't be valid Unicode
+            let output_video_stem = output_video_filename.strip_suffix(&format!(".{}", video_ext)).unwrap_or(&output_video_filename);
+            for (track, suffix, data) in &subtitles {
+                let output_path = extraction_path.join(format!("{}{}", output_video_stem, suffix));
+                let offset = options.apply_offset.then(|| subtitle_offsets::subtitle_offset(&metadata, track, &video_format.name)).flatten();
+                let data = match offset.filter(|offset_ms| *offset_ms != 0).map(|offset_ms| subtitle_offsets::shift_subtitle(data, offset_ms)) {
+                    Some(Ok(shifted)) => Cow::Owned(shifted),
+                    Some(Err(err)) => {
+                        warn!(operation = "extract", item = %track, outcome = "unshifted", error = %err, "Could not shift subtitle file, extracting it unshifted");
+                        Cow::Borrowed(data.as_slice())
+                    },
+                    None => Cow::Borrowed(data.as_slice()),
+                };
+                std::fs::write(&output_path, data).path_context("writing", &output_path)?;
+            }
+            let output_script_stem = output_script_filename.strip_suffix(&format!(".{}", import::SCRIPT_EXTENSION));
+            for (axis, data) in axis_data {
+                // Players pick up axis scripts named after the main script
+                let Some(output_script_stem) = &output_script_stem else {
+                    warn!(operation = "extract", item = %script_file_name, outcome = "skipped", axis = %axis, "Script file is not a .{} file, skipping extraction of its axis script", import::SCRIPT_EXTENSION);
+                    continue;
+                };
+
+                let output_path = extraction_path.join(format!("{}.{}.{}", output_script_stem, axis, import::SCRIPT_EXTENSION));
+                std::fs::write(&output_path, shift(&data)?).path_context("writing", &output_path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// What players expect after a video's stem in the file names of `tracks`, e.g. `.en.srt` for `video.en.srt`.
+/// Tracks without a usable language get just the extension, and tracks repeating an earlier one's language
+/// (or lack of one) a counter before it (`.en.2.srt`), so every track gets a distinct name.
+pub fn subtitle_suffixes<'a>(tracks: impl IntoIterator<Item = &'a SubtitleTrack>) -> Vec<(&'a SubtitleTrack, String)> {
+    const DEFAULT_SUBTITLE_EXT: &str = "srt";
+    let is_component = |value: &str| !value.is_empty() && sanitize_path_component(value).as_deref() == Some(value);
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut suffixes = Vec::new();
+    for track in tracks {
+        let language = Some(track.language_tag()).filter(|language| is_component(language));
+        if language.is_none() && !track.language.trim().is_empty() {
+            warn!(operation = "extract", item = %track.name, language = %track.language, "Subtitle file has an unusable language, naming it without one");
+        }
+
+        let ext = track.name.trim().rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .filter(|ext| is_component(ext))
+            .unwrap_or_else(|| DEFAULT_SUBTITLE_EXT.to_string());
+        let count = counts.entry(language.clone().unwrap_or_default()).or_default();
+        *count += 1;
+
+        let mut suffix = String::new();
+        if let Some(language) = &language {
+            suffix.push_str(&format!(".{}", language));
+        }
+        if *count > 1 {
+            suffix.push_str(&format!(".{}", count));
+        }
+        suffix.push_str(&format!(".{}", ext));
+        suffixes.push((track, suffix));
+    }
+
+    suffixes
+}
+
+/// Read the subtitle tracks in `options.subtitle_languages`, or every track if it is empty, as (track name, file name suffix, data).
+fn read_subtitles<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, index: &EntryIndex, metadata: &FsvMetadata, options: &ExtractOptions) -> Vec<(String, String, Vec<u8>)> {
+    let selected = metadata.subtitle_tracks.iter().filter(|track| options.subtitle_languages.is_empty() || track.matches_language(&options.subtitle_languages));
+    let mut subtitles = Vec::new();
+    for (track, suffix) in subtitle_suffixes(selected) {
+        let Some(entry_name) = index.resolve(track.name.trim(), options.name_matching) else {
+            warn!(operation = "extract", item = %track.name, outcome = "skipped", "Subtitle file not found in archive, skipping extraction");
+            continue;
+        };
+
+        let mut data = Vec::new();
+        if let Err(err) = archive.by_name(&entry_name).map_err(std::io::Error::other).and_then(|mut entry| entry.read_to_end(&mut data)) {
+            warn!(operation = "extract", item = %track.name, outcome = "skipped", error = %err, "Error reading subtitle file, skipping extraction");
+            continue;
+        }
+
+        subtitles.push((track.name.clone(), suffix, data));
+    }
+
+    for range in &options.subtitle_languages {
+        if !metadata.subtitle_tracks.iter().any(|track| track.matches_language(std::slice::from_ref(range))) {
+            warn!(operation = "extract", language = %range, outcome = "skipped", "No subtitle track in language");
+        }
+    }
+
+    subtitles
+}
+
+/// Read every axis script of a variant's bundle, or `None` (after logging why) if any of them can't be read.
+fn read_axis_scripts<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, index: &EntryIndex, variant: &ScriptVariant, name_matching: NameMatching) -> Option<Vec<(String, Vec<u8>)>> {
+    let mut scripts = Vec::new();
+    for (axis, name) in axis_script_names(variant) {
+        let Some(entry_name) = index.resolve(&name, name_matching) else {
+            warn!(operation = "extract", item = %variant.name, axis_script = %name, outcome = "skipped", "Axis script not found in archive, skipping extraction of its script variant");
+            return None;
+        };
+
+        let mut data = Vec::new();
+        if let Err(err) = archive.by_name(&entry_name).map_err(std::io::Error::other).and_then(|mut entry| entry.read_to_end(&mut data)) {
+            warn!(operation = "extract", item = %variant.name, axis_script = %name, outcome = "skipped", error = %err, "Error reading axis script, skipping extraction of its script variant");
+            return None;
         }
+
+        scripts.push((axis, data));
+    }
+
+    Some(scripts)
+}
+
+fn extract_previews<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, metadata: &FsvMetadata, extraction_path: &Path, safety: PathSafety) -> Result<(), FsvExtractError> {
+    let previews = preview::previews_from_metadata(metadata)?;
+    if previews.is_empty() {
+        warn!(operation = "extract", outcome = "skipped", "FSV has no previews to extract");
+    }
+
+    for preview in previews {
+        let mut entry = match archive.by_name(&preview.name) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::FileNotFound) => {
+                warn!(operation = "extract", item = %preview.name, outcome = "skipped", "Preview not found in archive, skipping extraction");
+                continue;
+            },
+            Err(err) => return Err(FsvExtractError::Zip(err)),
+        };
+
+        let output_filename = path_safety::safe_file_name(extraction_path, &preview.name, preview::DEFAULT_PREVIEW_NAME, safety)?;
+        let output_path = extraction_path.join(output_filename);
+        let mut output_file = File::create(&output_path).path_context("creating", &output_path)?;
+        std::io::copy(&mut entry, &mut output_file).path_context("writing", &output_path)?;
     }
 
     Ok(())
 }
 
+/// Write the notes document (`fsv.notes`) into the extraction folder under its own name.
+fn extract_notes<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, index: &EntryIndex, metadata: &FsvMetadata, extraction_path: &Path, options: &ExtractOptions) -> Result<(), FsvExtractError> {
+    // A malformed notes field is reported by validation, it shouldn't stop the rest of the extraction
+    let Some(name) = notes::notes_from_metadata(metadata).ok().flatten() else {
+        if options.only == Some(ExtractOnly::Notes) {
+            warn!(operation = "extract", outcome = "skipped", "FSV has no notes to extract");
+        }
+        return Ok(());
+    };
+
+    let Some(entry_name) = index.resolve(&name, options.name_matching) else {
+        warn!(operation = "extract", item = %name, outcome = "skipped", "Notes not found in archive, skipping extraction");
+        return Ok(());
+    };
+
+    let mut entry = archive.by_name(&entry_name)?;
+    let output_filename = path_safety::safe_file_name(extraction_path, &name, notes::DEFAULT_NOTES_NAME, options.path_safety)?;
+    let output_path = extraction_path.join(output_filename);
+    let mut output_file = File::create(&output_path).path_context("creating", &output_path)?;
+    std::io::copy(&mut entry, &mut output_file).path_context("writing", &output_path)?;
+
+    Ok(())
+}
+
 #[derive(Debug, Error)]
 pub enum FsvValidationError {
     #[error("I/O error: {0}")]
@@ -211,12 +611,40 @@ pub enum FsvState {
     MetadataInvalid(MetadataInvalidReason),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum ContentIncompleteReason {
     UnableToReadItem(ItemType),
     MissingItemFile(ItemType),
     ItemPasswordProtected(ItemType),
     DuplicateItemEntry(ItemType),
+    /// The entry is only present under a name differing in case or path separators (expected name, actual entry name).
+    MismatchedItemName(ItemType, String, String),
+    /// The entry exists but its content is not a usable file of its type.
+    MalformedItem(ItemType, String),
+    /// The archive stores more than one entry under this name. Readers only see one of them.
+    DuplicateArchiveEntry(String),
+    /// A script variant lists an axis whose script is not in the archive (variant name, axis).
+    MissingAxisScript(String, String),
+    /// The entry's content doesn't match the checksum in the metadata, found by deep validation.
+    ChecksumMismatch(ItemType),
+}
+
+impl std::fmt::Display for ContentIncompleteReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContentIncompleteReason::UnableToReadItem(item_type) => write!(f, "Unable to read {} file", item_type.get_name_lower()),
+            ContentIncompleteReason::MissingItemFile(item_type) => write!(f, "Missing {} file in archive", item_type.get_name_lower()),
+            ContentIncompleteReason::ItemPasswordProtected(item_type) => write!(f, "{} file is password protected", item_type.get_name()),
+            ContentIncompleteReason::DuplicateItemEntry(item_type) => write!(f, "Duplicate {} entry in metadata", item_type.get_name_lower()),
+            ContentIncompleteReason::MismatchedItemName(item_type, expected, found) => {
+                write!(f, "Missing {} file '{}' in archive, but found '{}' (differs only in case or path separators). Use --name-matching normalized to accept it, or rebuild the archive to fix the entry name", item_type.get_name_lower(), expected, found)
+            },
+            ContentIncompleteReason::MalformedItem(item_type, problem) => write!(f, "Invalid {} file: {}", item_type.get_name_lower(), problem),
+            ContentIncompleteReason::DuplicateArchiveEntry(name) => write!(f, "Archive contains more than one entry named '{}'", name),
+            ContentIncompleteReason::MissingAxisScript(variant, axis) => write!(f, "Missing '{}' axis script for script variant '{}' in archive", axis, variant),
+            ContentIncompleteReason::ChecksumMismatch(item_type) => write!(f, "{} file doesn't match the checksum in metadata", item_type.get_name()),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -226,67 +654,207 @@ pub enum MetadataInvalidReason {
     UnsupportedFormatVersion(Version),
     MissingVideoFormat,
     MissingScriptVariant,
+    /// An archive entry or metadata file name contains `..`, is absolute, or has a drive prefix.
+    UnsafeEntryName(String),
+    /// The archive holds more than one `metadata.json`, in the central directory or as a local entry missing from it.
+    MultipleMetadataFiles,
+    /// A field the format doesn't define, reported under `ParseMode::Strict`.
+    UnknownField(String),
+}
+
+impl std::fmt::Display for MetadataInvalidReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetadataInvalidReason::InvalidFormatVersion => write!(f, "Invalid format version in metadata"),
+            MetadataInvalidReason::MalformedJson(json) => write!(f, "Malformed JSON in metadata: {}", json),
+            MetadataInvalidReason::UnsupportedFormatVersion(version) => write!(f, "Unsupported format version in metadata: {}", version),
+            MetadataInvalidReason::MissingVideoFormat => write!(f, "Missing video format in metadata"),
+            MetadataInvalidReason::MissingScriptVariant => write!(f, "Missing script variant in metadata"),
+            MetadataInvalidReason::UnsafeEntryName(name) => write!(f, "Unsafe entry name in archive or metadata: {}", name),
+            MetadataInvalidReason::MultipleMetadataFiles => write!(f, "Archive contains more than one metadata.json"),
+            MetadataInvalidReason::UnknownField(path) => write!(f, "Unknown field in metadata: {}", path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueSeverity {
+    Error,
+    Warning,
+}
+
+/// A single problem found during validation. `item` names the entry (or extension) concerned, if any.
+#[derive(Debug, Clone)]
+pub struct ValidationIssue {
+    pub severity: IssueSeverity,
+    pub item: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.item {
+            Some(item) => write!(f, "{}: {}", item, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Every problem found in an FSV. `state` summarizes the report: the first metadata problem if there is one,
+/// otherwise the first content problem.
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+    pub state: FsvState,
+    pub issues: Vec<ValidationIssue>,
+    /// Video formats kept outside the archive (`fsv.external-content`), which are not checked
+    pub external: Vec<String>,
+}
+
+impl ValidationReport {
+    fn new() -> Self {
+        ValidationReport { state: FsvState::Valid, issues: Vec::new(), external: Vec::new() }
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.severity == IssueSeverity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|issue| issue.severity == IssueSeverity::Warning)
+    }
+
+    fn push(&mut self, severity: IssueSeverity, item: Option<&str>, message: String) {
+        self.issues.push(ValidationIssue { severity, item: item.map(str::to_string), message });
+    }
+
+    fn warning(&mut self, item: Option<&str>, message: impl Into<String>) {
+        self.push(IssueSeverity::Warning, item, message.into());
+    }
+
+    fn metadata_invalid(&mut self, reason: MetadataInvalidReason, item: Option<&str>) {
+        self.push(IssueSeverity::Error, item, reason.to_string());
+        if !matches!(self.state, FsvState::MetadataInvalid(_)) {
+            self.state = FsvState::MetadataInvalid(reason);
+        }
+    }
+
+    fn content_incomplete(&mut self, reason: ContentIncompleteReason, item: Option<&str>) {
+        self.push(IssueSeverity::Error, item, reason.to_string());
+        if matches!(self.state, FsvState::Valid) {
+            self.state = FsvState::ContentIncomplete(reason);
+        }
+    }
 }
 
 pub fn validate_fsv(path: &Path) -> Result<FsvState, FsvValidationError> {
-    let file = std::fs::File::open(path)?;
-    let mut archive = zip::ZipArchive::new(file)?;
-    // Scope needed to release borrow on archive
-    let metadata_json = {
-        let result = archive.by_name("metadata.json");
-        let mut metadata_file = match result {
-            Ok(file) => file,
-            Err(zip_err) => {
-                match zip_err {
-                    zip::result::ZipError::FileNotFound => {
-                        return Err(FsvValidationError::MetadataNotFound);
-                    }
-                    _ => {
-                        return Err(FsvValidationError::Zip(zip_err));
-                    }
-                }
-            },
-        };
+    validate_fsv_with_matching(path, NameMatching::Strict)
+}
 
-        // region Validate metadata.json
+pub fn validate_fsv_with_matching(path: &Path, name_matching: NameMatching) -> Result<FsvState, FsvValidationError> {
+    let mut container = FsvContainer::open(path)?;
+    container.validate(name_matching)
+}
 
-        let mut metadata_json = String::new();
-        metadata_file.read_to_string(&mut metadata_json)?;
+/// Validate an FSV and collect every problem instead of stopping at the first one.
+pub fn validate_fsv_report(path: &Path, name_matching: NameMatching) -> Result<ValidationReport, FsvValidationError> {
+    let mut container = FsvContainer::open(path)?;
+    container.validate_report(name_matching)
+}
 
-        metadata_json
-    };
+/// `validate_fsv_report` with unknown metadata fields reported and entries checked deeper as `options` ask.
+pub fn validate_fsv_report_with(path: &Path, options: &ValidateOptions) -> Result<ValidationReport, FsvValidationError> {
+    let mut container = FsvContainer::open(path)?;
+    container.validate_report_with(options)
+}
 
-    let result = serde_json::from_str::<FsvMetadata>(&metadata_json);
+/// Log the warnings of `report`, whose state is all that is left to act on.
+fn log_validation_report(report: ValidationReport) -> FsvState {
+    for issue in report.warnings() {
+        match &issue.item {
+            Some(item) => warn!(operation = "validate", item = %item, "{}", issue.message),
+            None => warn!(operation = "validate", "{}", issue.message),
+        }
+    }
+
+    report.state
+}
+
+/// Validate the archive as if its metadata.json held `metadata_json`, so new metadata can be checked before it is written.
+/// `structure` describes the raw ZIP records, whose irregularities `ZipArchive` hides.
+fn validate_archive_with_json<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, structure: &ArchiveStructure, index: &EntryIndex, metadata_json: &str, options: &ValidateOptions) -> Result<ValidationReport, FsvValidationError> {
+    let name_matching = options.name_matching;
+    let parse_mode = options.parse_mode;
+    let mut report = ValidationReport::new();
+    let result = serde_json::from_str::<FsvMetadata>(metadata_json);
     let metadata = match result {
         Ok(metadata) => metadata,
         Err(err) => {
+            // Nothing else can be checked without metadata
             let err_msg = err.to_string();
             if err_msg.contains("Invalid version format") || err_msg.contains("Invalid number in version") {
-                return Ok(FsvState::MetadataInvalid(MetadataInvalidReason::InvalidFormatVersion));
+                report.metadata_invalid(MetadataInvalidReason::InvalidFormatVersion, None);
             }
             else {
-                return Ok(FsvState::MetadataInvalid(MetadataInvalidReason::MalformedJson(err_msg)));
-
+                report.metadata_invalid(MetadataInvalidReason::MalformedJson(err_msg), None);
             }
+
+            return Ok(report);
         },
     };
 
+    if parse_mode != ParseMode::Lenient {
+        // Parsed as FsvMetadata above, so this can't fail
+        let document: serde_json::Value = serde_json::from_str(metadata_json)?;
+        for path in schema::unknown_fields(&document) {
+            let path = path.trim_start_matches("$.").to_string();
+            match parse_mode {
+                ParseMode::Strict => report.metadata_invalid(MetadataInvalidReason::UnknownField(path), None),
+                _ => report.warning(None, format!("Unknown field in metadata: {}", path)),
+            }
+        }
+    }
+
     if metadata.format_version > LATEST_FSV_FORMAT_VERSION || metadata.format_version < MINIMUM_FSV_FORMAT_VERSION {
-        return Ok(FsvState::MetadataInvalid(MetadataInvalidReason::UnsupportedFormatVersion(metadata.format_version)));
+        report.metadata_invalid(MetadataInvalidReason::UnsupportedFormatVersion(metadata.format_version.clone()), None);
+    }
+
+    let axis_names: Vec<String> = metadata.script_variants.iter().flat_map(axis_script_names).map(|(_, name)| name).collect();
+    let metadata_names = metadata.video_formats.iter().map(|item| item.name.as_str())
+        .chain(metadata.script_variants.iter().map(|item| item.name.as_str()))
+        .chain(metadata.subtitle_tracks.iter().map(|item| item.name.as_str()))
+        .chain(axis_names.iter().map(String::as_str));
+    let unsafe_names: HashSet<&str> = archive.file_names().chain(metadata_names).filter(|name| !is_safe_entry_name(name)).collect();
+    let mut unsafe_names: Vec<&str> = unsafe_names.into_iter().collect();
+    unsafe_names.sort();
+    for name in unsafe_names {
+        report.metadata_invalid(MetadataInvalidReason::UnsafeEntryName(name.to_string()), Some(name));
+    }
+
+    // A second metadata.json, even one only a streaming reader would find, could make readers disagree on the archive's contents
+    if structure.duplicate_entries.iter().chain(&structure.stray_entries).any(|name| name == "metadata.json") {
+        report.metadata_invalid(MetadataInvalidReason::MultipleMetadataFiles, Some("metadata.json"));
+    }
+
+    if !structure.metadata_first {
+        report.warning(Some("metadata.json"), "metadata.json is not the first entry of the archive, so streaming readers only find it at the end");
     }
 
     if metadata.title.trim().is_empty() {
-        warn!("FSV metadata title is empty");
+        report.warning(None, "FSV metadata title is empty");
     }
 
     if metadata.creators.is_empty() {
-        warn!("FSV metadata creators information is empty");
+        report.warning(None, "FSV metadata creators information is empty");
+    }
+
+    for (item_type, work) in orphan_creators(&metadata) {
+        report.warning(Some(&work.work_name), format!("{} creator '{}' credits a {} that is not in the metadata. Use `edit prune-orphan-creators` to remove it", item_type, work.creator_info.name, item_type.get_name_lower()));
     }
 
     let mut video_present = false; // at least one video format should be present
     for format in &metadata.video_formats {
         if format.name.trim().is_empty() {
-            warn!("A video format has an empty name");
+            report.warning(None, "A video format has an empty name");
         }
         else{
             video_present = true;
@@ -294,13 +862,13 @@ pub fn validate_fsv(path: &Path) -> Result<FsvState, FsvValidationError> {
     }
 
     if !video_present {
-        return Ok(FsvState::MetadataInvalid(MetadataInvalidReason::MissingVideoFormat));
+        report.metadata_invalid(MetadataInvalidReason::MissingVideoFormat, None);
     }
 
     let mut script_present = false; // at least one script variant should be present
     for variant in &metadata.script_variants {
         if variant.name.trim().is_empty() {
-            warn!("A script variant has an empty name");
+            report.warning(None, "A script variant has an empty name");
         }
         else{
             script_present = true;
@@ -308,87 +876,322 @@ pub fn validate_fsv(path: &Path) -> Result<FsvState, FsvValidationError> {
     }
 
     if !script_present {
-        return Ok(FsvState::MetadataInvalid(MetadataInvalidReason::MissingScriptVariant));
+        report.metadata_invalid(MetadataInvalidReason::MissingScriptVariant, None);
+    }
+
+    // Extension problems never invalidate the container
+    let entry_names: Vec<String> = archive.file_names().map(|name| entry_name::decode_entry_name(name).into_owned()).collect();
+    let entry_names: Vec<&str> = entry_names.iter().map(String::as_str).collect();
+    for extension in extensions::check_extensions(&metadata, &entry_names) {
+        for issue in extension.issues {
+            report.warning(Some(&extension.id), issue);
+        }
     }
 
     // endregion
 
     // region Validate content files
 
-    let state = validate_item_contents(ItemType::Video, &metadata.video_formats, &mut archive)?;
-    if !matches!(state, FsvState::Valid) {
-        return Ok(state);
+    for name in structure.duplicate_entries.iter().filter(|name| *name != "metadata.json") {
+        report.content_incomplete(ContentIncompleteReason::DuplicateArchiveEntry(name.clone()), Some(name));
     }
 
-    let state = validate_item_contents(ItemType::Script, &metadata.script_variants, &mut archive)?;
-    if !matches!(state, FsvState::Valid) {
-        return Ok(state);
+    for name in structure.stray_entries.iter().filter(|name| *name != "metadata.json") {
+        report.warning(Some(name), format!("Archive contains a local entry '{}' missing from the central directory. Readers ignore it and rebuilding discards it", name));
     }
 
-    let state = validate_item_contents(ItemType::Subtitle, &metadata.subtitle_tracks, &mut archive)?;
-    if !matches!(state, FsvState::Valid) {
-        return Ok(state);
+    // Malformed references were reported with the extension issues above
+    let external = external::external_content_from_metadata(&metadata).unwrap_or_default();
+    validate_item_contents(ItemType::Video, &metadata.video_formats, &external, archive, index, name_matching, &mut report)?;
+    validate_item_contents(ItemType::Script, &metadata.script_variants, &[], archive, index, name_matching, &mut report)?;
+    validate_item_contents(ItemType::Subtitle, &metadata.subtitle_tracks, &[], archive, index, name_matching, &mut report)?;
+    validate_axis_scripts(&metadata.script_variants, archive, index, name_matching, &mut report)?;
+    if options.deep {
+        validate_script_details(&metadata.script_variants, archive, index, options, &mut report)?;
+        #[cfg(feature = "native")]
+        validate_video_durations(&metadata.video_formats, archive, index, options, &mut report)?;
     }
 
     // endregion
 
-    Ok(FsvState::Valid)
+    Ok(report)
 }
 
-fn validate_item_contents<Item: WorkItem>(item_type: ItemType, items: &Vec<Item>, archive: &mut zip::ZipArchive<std::fs::File>) -> Result<FsvState, FsvValidationError> {
-    // TODO: Maybe add Func for specific item validations
-    // TODO: Maybe improve return value to not be confused with caller's return value (mainly since FsvState::Valid doesn't make sense when a different item type may be invalid)
+/// Items missing from the archive but listed in `external` are recorded as external instead of missing.
+fn validate_item_contents<Item: WorkItem, R: Read + Seek>(item_type: ItemType, items: &Vec<Item>, external: &[ExternalContent], archive: &mut zip::ZipArchive<R>, index: &EntryIndex, name_matching: NameMatching, report: &mut ValidationReport) -> Result<(), FsvValidationError> {
     let mut seen = HashSet::new();
     for item in items {
         let file_name = item.get_name().trim();
         if file_name.is_empty() {
-            warn!("A subtitle track has an empty file name");
+            report.warning(None, format!("A {} entry has an empty file name", item_type.get_name_lower()));
             continue;
         }
 
         if !seen.insert(file_name) {
-            warn!("Duplicate subtitle track entry found: {}", file_name);
+            report.content_incomplete(ContentIncompleteReason::DuplicateItemEntry(item_type), Some(file_name));
+            continue;
         }
 
-        let result = archive.by_name(file_name);
-        match result {
-            Ok(_) => (),
+        let entry_name = match index.lookup(file_name) {
+            EntryLookup::Exact => file_name.to_string(),
+            EntryLookup::Decoded(actual) => {
+                report.warning(Some(file_name), "Archive entry name is UTF-8 stored without the UTF-8 flag; rebuilding the archive fixes it".to_string());
+                actual
+            },
+            EntryLookup::Normalized(actual) => match name_matching {
+                NameMatching::Strict => {
+                    report.content_incomplete(ContentIncompleteReason::MismatchedItemName(item_type, file_name.to_string(), actual), Some(file_name));
+                    continue;
+                },
+                NameMatching::Normalized => {
+                    report.warning(Some(file_name), format!("Matched archive entry '{}' after name normalization", actual));
+                    actual
+                }
+            },
+            EntryLookup::Missing => {
+                match external.iter().find(|reference| reference.name == file_name) {
+                    Some(reference) => {
+                        report.warning(Some(file_name), format!("External {} file, not verified (source: {})", item_type.get_name_lower(), reference.source));
+                        report.external.push(file_name.to_string());
+                    },
+                    None => report.content_incomplete(ContentIncompleteReason::MissingItemFile(item_type), Some(file_name)),
+                }
+                continue;
+            },
+        };
+
+        let result = archive.by_name(&entry_name);
+        let entry = match result {
+            Ok(entry) => entry,
             Err(err) => {
-                match err {
-                    zip::result::ZipError::Io(_) => return Ok(FsvState::ContentIncomplete(ContentIncompleteReason::UnableToReadItem(item_type))),
-                    zip::result::ZipError::FileNotFound => return Ok(FsvState::ContentIncomplete(ContentIncompleteReason::MissingItemFile(item_type))),
-                    zip::result::ZipError::InvalidPassword => return Ok(FsvState::ContentIncomplete(ContentIncompleteReason::ItemPasswordProtected(item_type))),
+                let reason = match err {
+                    zip::result::ZipError::Io(_) => ContentIncompleteReason::UnableToReadItem(item_type),
+                    zip::result::ZipError::FileNotFound => ContentIncompleteReason::MissingItemFile(item_type),
+                    _ if password_required(&err) => ContentIncompleteReason::ItemPasswordProtected(item_type),
                     _ => return Err(FsvValidationError::Zip(err)),
-                }
+                };
+                report.content_incomplete(reason, Some(file_name));
+                continue;
             },
+        };
+
+        match check_item_content(item_type, entry) {
+            Ok(None) => (),
+            Ok(Some(problem)) => report.content_incomplete(ContentIncompleteReason::MalformedItem(item_type, problem), Some(file_name)),
+            Err(_) => report.content_incomplete(ContentIncompleteReason::UnableToReadItem(item_type), Some(file_name)),
         }
     }
 
-    Ok(FsvState::Valid)
+    Ok(())
 }
 
-#[derive(Debug, Error)]
-pub enum FsvCreateError {
-    #[error("I/O error: {0}")]
-    Io(#[from] std::io::Error),
-    #[error("ZIP archive error: {0}")]
-    Zip(#[from] zip::result::ZipError),
-    #[error("Serde json error: {0}")]
-    SerdeJson(#[from] serde_json::Error),
-    #[error("From UTF-8 error: {0}")]
-    FromUtf8(#[from] std::string::FromUtf8Error),
-    #[error("Database client error: {0}")]
-    DbClient(#[from] db_client::DbClientError),
-    #[error("FSV error: {0}")]
-    Fsv(#[from] FsvError),
-    #[error("Get duration error: {0}")]
-    GetDurationError(#[from] file_util::GetDurationError),
-    #[error("FSV already exists at path: {0}")]
+/// Whether reading an entry failed because it is encrypted. Without a password, zip reports that as an unsupported archive.
+fn password_required(err: &zip::result::ZipError) -> bool {
+    matches!(err, zip::result::ZipError::InvalidPassword | zip::result::ZipError::UnsupportedArchive(zip::result::ZipError::PASSWORD_REQUIRED))
+}
+
+/// Each axis listed in `additional_axes` needs its script in the archive, and the script should span the variant's duration.
+fn validate_axis_scripts<R: Read + Seek>(variants: &[ScriptVariant], archive: &mut zip::ZipArchive<R>, index: &EntryIndex, name_matching: NameMatching, report: &mut ValidationReport) -> Result<(), FsvValidationError> {
+    for variant in variants {
+        for (axis, name) in axis_script_names(variant) {
+            if !AXES.contains(&axis.as_str()) {
+                report.warning(Some(&variant.name), format!("Unknown axis '{}'", axis));
+            }
+
+            let Some(entry_name) = index.resolve(&name, name_matching) else {
+                report.content_incomplete(ContentIncompleteReason::MissingAxisScript(variant.name.clone(), axis), Some(&name));
+                continue;
+            };
+
+            let mut data = Vec::new();
+            let result = archive.by_name(&entry_name);
+            match result {
+                Ok(mut entry) => if entry.read_to_end(&mut data).is_err() {
+                    report.content_incomplete(ContentIncompleteReason::UnableToReadItem(ItemType::Script), Some(&name));
+                    continue;
+                },
+                Err(err) if password_required(&err) => {
+                    report.content_incomplete(ContentIncompleteReason::ItemPasswordProtected(ItemType::Script), Some(&name));
+                    continue;
+                },
+                Err(zip::result::ZipError::Io(_)) => {
+                    report.content_incomplete(ContentIncompleteReason::UnableToReadItem(ItemType::Script), Some(&name));
+                    continue;
+                },
+                Err(err) => return Err(FsvValidationError::Zip(err)),
+            }
+
+            let funscript = match content::validate_funscript(&data) {
+                Ok(funscript) => funscript,
+                Err(problem) => {
+                    report.content_incomplete(ContentIncompleteReason::MalformedItem(ItemType::Script, problem), Some(&name));
+                    continue;
+                },
+            };
+
+            let duration = file_util::get_funscript_duration(&funscript).unwrap_or_default();
+            if !variant.duration.is_zero() && duration.abs_diff(variant.duration) > AXIS_DURATION_TOLERANCE {
+                report.warning(Some(&name), format!("Axis script duration ({} ms) differs from script variant duration ({} ms)", duration.as_millis(), variant.duration.as_millis()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The checksum and duration the metadata records for each script variant must still describe its script, which they stop
+/// doing when a script is replaced without updating the metadata. Scripts that can't be found, read or parsed were reported already.
+fn validate_script_details<R: Read + Seek>(variants: &[ScriptVariant], archive: &mut zip::ZipArchive<R>, index: &EntryIndex, options: &ValidateOptions, report: &mut ValidationReport) -> Result<(), FsvValidationError> {
+    for variant in variants {
+        let Some(entry_name) = index.resolve(variant.name.trim(), options.name_matching) else {
+            continue;
+        };
+
+        let mut data = Vec::new();
+        match archive.by_name(&entry_name) {
+            Ok(mut entry) => if entry.read_to_end(&mut data).is_err() {
+                continue;
+            },
+            Err(_) => continue,
+        }
+
+        if checksum_status(&variant.checksum, &mut data.as_slice()) == ChecksumStatus::Mismatch {
+            report.content_incomplete(ContentIncompleteReason::ChecksumMismatch(ItemType::Script), Some(&variant.name));
+        }
+
+        let Ok(funscript) = content::validate_funscript(&data) else {
+            continue;
+        };
+
+        let duration = file_util::get_funscript_duration(&funscript).unwrap_or_default();
+        if !variant.duration.is_zero() && duration.abs_diff(variant.duration) > options.duration_tolerance {
+            report.warning(Some(&variant.name), format!("Script lasts {} ms, but the metadata says {} ms", duration.as_millis(), variant.duration.as_millis()));
+        }
+    }
+
+    Ok(())
+}
+
+/// The duration the metadata records for each video format must match the video's, as ffprobe measures it from the entry piped
+/// into it. Videos that can't be found or read were reported already, external ones aren't checked.
+#[cfg(feature = "native")]
+fn validate_video_durations<R: Read + Seek>(formats: &[VideoFormat], archive: &mut zip::ZipArchive<R>, index: &EntryIndex, options: &ValidateOptions, report: &mut ValidationReport) -> Result<(), FsvValidationError> {
+    for format in formats.iter().filter(|format| !format.duration.is_zero()) {
+        let Some(entry_name) = index.resolve(format.name.trim(), options.name_matching) else {
+            continue;
+        };
+
+        let Ok(mut entry) = archive.by_name(&entry_name) else {
+            continue;
+        };
+
+        let duration = match file_util::get_video_duration_from_reader(&mut entry) {
+            Ok(duration) => duration,
+            Err(file_util::GetDurationError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+                report.warning(None, "Unable to probe video durations: ffprobe is not installed or not on PATH");
+                return Ok(());
+            },
+            Err(err) => {
+                report.warning(Some(&format.name), format!("Unable to probe video duration: {}", err.to_string().trim()));
+                continue;
+            },
+        };
+
+        if duration.abs_diff(format.duration) > options.duration_tolerance {
+            report.warning(Some(&format.name), format!("Video lasts {} ms, but the metadata says {} ms", duration.as_millis(), format.duration.as_millis()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Type-specific content checks: videos need a known container signature, scripts must parse as funscripts
+/// and subtitles as SRT, ASS/SSA or WebVTT. Returns a description of the problem, if any.
+fn check_item_content(item_type: ItemType, mut reader: impl Read) -> std::io::Result<Option<String>> {
+    let problem = match item_type {
+        ItemType::Video => {
+            // Only the header is needed, don't read the whole video
+            let mut header = Vec::with_capacity(content::VIDEO_SIGNATURE_LEN);
+            reader.take(content::VIDEO_SIGNATURE_LEN as u64).read_to_end(&mut header)?;
+            match content::detect_video_container(&header) {
+                Some(_) => None,
+                None => Some("Unrecognized video container signature".to_string()),
+            }
+        },
+        ItemType::Script => {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            content::validate_funscript(&data).err()
+        },
+        ItemType::Subtitle => {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            content::validate_subtitle(&data).err()
+        },
+    };
+
+    Ok(problem)
+}
+
+#[derive(Debug, Error)]
+pub enum FsvCreateError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ZIP archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Serde json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("From UTF-8 error: {0}")]
+    FromUtf8(#[from] std::string::FromUtf8Error),
+    #[error("Database client error: {0}")]
+    #[cfg(feature = "native")]
+    DbClient(#[from] db_client::DbClientError),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+    #[error("Get duration error: {0}")]
+    GetDurationError(#[from] file_util::GetDurationError),
+    #[error("FSV already exists at path: {0}")]
     FsvAlreadyExists(PathBuf),
     #[error("Creator info for {0} not found for key: {1}")]
     CreatorInfoNotFound(ItemType, String),
+    #[error("Transcode error: {0}")]
+    Transcode(#[from] TranscodeError),
+    #[error("Naming error: {0}")]
+    #[cfg(feature = "native")]
+    Naming(#[from] NamingError),
+    #[error("Content policy error: {0}")]
+    Policy(#[from] PolicyError),
+}
+
+/// Compression used for entries written into new archives.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveCompression {
+    /// No compression, fastest to write and read (videos barely compress anyway)
+    Stored,
+    Deflate,
+    #[default]
+    Bzip2,
+    Zstd,
+}
+
+impl ArchiveCompression {
+    pub fn method(&self) -> zip::CompressionMethod {
+        match self {
+            ArchiveCompression::Stored => zip::CompressionMethod::Stored,
+            ArchiveCompression::Deflate => zip::CompressionMethod::Deflated,
+            ArchiveCompression::Bzip2 => zip::CompressionMethod::Bzip2,
+            #[cfg(feature = "native")]
+            ArchiveCompression::Zstd => zip::CompressionMethod::Zstd,
+            // The read-only core has no zstd codec, so writing such an archive fails as an unsupported method
+            #[cfg(not(feature = "native"))]
+            #[allow(deprecated)]
+            ArchiveCompression::Zstd => zip::CompressionMethod::from_u16(93),
+        }
+    }
 }
 
+#[cfg(feature = "native")]
 #[derive(Debug)]
 pub struct CreateArgs {
     pub path: PathBuf,
@@ -398,8 +1201,29 @@ pub struct CreateArgs {
     pub script: Option<PathBuf>,
     pub video_creator_key: Option<String>,
     pub script_creator_key: Option<String>,
+    /// Produce byte-identical archives for identical inputs.
+    pub reproducible: bool,
+    pub hash_algorithm: HashAlgorithm,
+    /// Profiles used to generate additional video formats from the video
+    pub transcode: Vec<TranscodeProfile>,
+    /// Fill in title, tags, performers and script creator from the script's embedded metadata block
+    pub from_script_metadata: bool,
+    pub performers: Vec<String>,
+    pub studio: String,
+    /// Description of the video format and of the script variant
+    pub video_description: String,
+    pub script_description: String,
+    pub compression: ArchiveCompression,
+    /// Entry names the video and script have to follow
+    pub naming_policy: Option<NamingPolicy>,
+    /// Screening of the finished metadata, before the archive is written
+    pub content_policy: Option<ContentPolicy>,
+    /// Encrypt every entry but metadata.json with AES-256 under this password. metadata.json stays readable, so the archive
+    /// can still be identified and validated (its content reported as password protected). Encrypted archives aren't reproducible.
+    pub encryption_password: Option<String>,
 }
 
+#[cfg(feature = "native")]
 impl CreateArgs {
     pub fn new(path: PathBuf, title: String, tags: Vec<String>, video: Option<PathBuf>, script: Option<PathBuf>, video_creator_key: Option<String>, script_creator_key: Option<String>) -> Self {
         CreateArgs {
@@ -410,12 +1234,86 @@ impl CreateArgs {
             script,
             video_creator_key,
             script_creator_key,
+            reproducible: false,
+            hash_algorithm: HashAlgorithm::default(),
+            transcode: Vec::new(),
+            from_script_metadata: false,
+            performers: Vec::new(),
+            studio: String::new(),
+            video_description: String::new(),
+            script_description: String::new(),
+            compression: ArchiveCompression::default(),
+            naming_policy: None,
+            content_policy: None,
+            encryption_password: None,
         }
     }
+
+    pub fn reproducible(mut self, reproducible: bool) -> Self {
+        self.reproducible = reproducible;
+        self
+    }
+
+    pub fn hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = hash_algorithm;
+        self
+    }
+
+    pub fn transcode(mut self, profiles: Vec<TranscodeProfile>) -> Self {
+        self.transcode = profiles;
+        self
+    }
+
+    pub fn from_script_metadata(mut self, from_script_metadata: bool) -> Self {
+        self.from_script_metadata = from_script_metadata;
+        self
+    }
+
+    pub fn performers(mut self, performers: Vec<String>) -> Self {
+        self.performers = performers;
+        self
+    }
+
+    pub fn studio(mut self, studio: String) -> Self {
+        self.studio = studio;
+        self
+    }
+
+    pub fn video_description(mut self, description: String) -> Self {
+        self.video_description = description;
+        self
+    }
+
+    pub fn script_description(mut self, description: String) -> Self {
+        self.script_description = description;
+        self
+    }
+
+    pub fn compression(mut self, compression: ArchiveCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn naming_policy(mut self, naming_policy: Option<NamingPolicy>) -> Self {
+        self.naming_policy = naming_policy;
+        self
+    }
+
+    pub fn content_policy(mut self, content_policy: Option<ContentPolicy>) -> Self {
+        self.content_policy = content_policy;
+        self
+    }
+
+    pub fn encryption_password(mut self, password: Option<String>) -> Self {
+        self.encryption_password = password;
+        self
+    }
 }
 
+#[cfg(feature = "native")]
 pub async fn create_fsv(args: CreateArgs, db_client: &DbClient, interactive: bool) -> Result<(), FsvCreateError> {
-    let CreateArgs { path, title, tags, video, script, video_creator_key, script_creator_key } = args;
+    let path = args.path.clone();
+    let lock = lock_fsv(&path)?;
     // Create file but don't overwrite if it exists
     let result = std::fs::OpenOptions::new()
         .write(true)
@@ -429,13 +1327,14 @@ pub async fn create_fsv(args: CreateArgs, db_client: &DbClient, interactive: boo
         },
     };
 
-    let result = create_inner(file, title, tags, video, script, video_creator_key, script_creator_key, db_client, interactive).await;
+    let result = create_inner(file, args, db_client, interactive).await;
+    drop(lock);
     match result {
         Ok(_) => Ok(()),
         Err(err) => {
             // Clean up by removing the created file
             if let Err(remove_err) = std::fs::remove_file(&path) {
-                error!("Error removing incomplete FSV file at '{}': {}", path.display(), remove_err);
+                error!(operation = "create", archive = %path.display(), outcome = "cleanup_failed", error = %remove_err, "Error removing incomplete FSV file");
             }
 
             Err(err)
@@ -444,33 +1343,54 @@ pub async fn create_fsv(args: CreateArgs, db_client: &DbClient, interactive: boo
 }
 
 // Providing the creator without the accompanying file path will silently skip adding the creator info (e.g., providing a video creator without a video file)
-async fn create_inner(file: File, title: String, tags: Vec<String>, video: Option<PathBuf>, script: Option<PathBuf>, video_creator_key: Option<String>, script_creator_key: Option<String>, db_client: &DbClient, interactive: bool) -> Result<(), FsvCreateError> {
+#[cfg(feature = "native")]
+async fn create_inner(file: File, args: CreateArgs, db_client: &DbClient, interactive: bool) -> Result<(), FsvCreateError> {
+    let CreateArgs { path, title, tags, video, script, video_creator_key, script_creator_key, reproducible, hash_algorithm, transcode, from_script_metadata, performers, studio, video_description, script_description, compression, naming_policy, content_policy, encryption_password } = args;
     let mut metadata = FsvMetadata::new(LATEST_FSV_FORMAT_VERSION);
     metadata.title = title;
-    metadata.tags = tags;
-
+    metadata.tags = normalize_tags(db_client, tags).await?;
+    metadata.performers = dedup_names(performers);
+    metadata.studio = studio.trim().to_string();
+
+    // Transcoder output must outlive add_files, which borrows from it
+    let work_dir = match transcode.is_empty() || video.is_none() {
+        true => None,
+        false => Some(TranscodeWorkDir::new()?),
+    };
+    let mut transcoded = Vec::new();
     let mut add_files = Vec::new();
+    let mut staged = StagedFiles::new(&path, entry_options(reproducible, compression)).password(encryption_password.clone());
     // _filename and _path variables are needed to keep the PathBuf alive while being used in AddFile, do not access them directly
     let video_filename;
     let video_path;
     let mut video_added = false;
     if let Some(video) = video {
         video_path = video;
-        let video_creator_key = get_creator_info_from_key(&db_client, video_creator_key.as_deref(), interactive).await?;
-        video_filename = video_path.file_name().and_then(|f| f.to_str()).unwrap_or("video.mp4").to_string();
+        let video_creator = get_creator_info_from_key(db_client, video_creator_key.as_deref(), interactive).await?;
+        let file_name = video_path.file_name().map(entry_name::os_str_entry_name).unwrap_or_else(|| "video.mp4".to_string());
+        video_filename = match &naming_policy {
+            Some(policy) => policy.entry_name(ItemType::Video, &file_name, &video_path, &metadata.title)?,
+            None => file_name,
+        };
         let video_duration = file_util::get_video_duration(&video_path)?;
-        let content = std::fs::read(&video_path)?;
-        let hash = get_file_hash(&content);
-        if let Some(creator_info) = video_creator_key {
-            let work_info = WorkCreatorsMetadata::new(video_filename.clone(), String::new(), creator_info);
+        let hash = checksum_for_add(Some(db_client), &video_filename, &video_path, hash_algorithm, &mut staged).await?;
+        if let Some(creator_info) = video_creator {
+            let work_info = WorkCreatorsMetadata::new(video_filename.clone(), String::new(), creator_info).with_creator_key(video_creator_key);
             metadata.add_video_creator(work_info);
         }
 
-        let video_format = VideoFormat::new(video_filename.clone(), String::new(), video_duration, hash);
+        let video_format = VideoFormat::new(video_filename.clone(), video_description.trim().to_string(), video_duration, hash);
         metadata.add_video_format(video_format);
         let add_file = AddFile::new(&video_filename, &video_path);
         video_added = true;
         add_files.push(add_file);
+
+        if let Some(work_dir) = &work_dir {
+            for (video, video_format) in transcode_video_formats(&video_path, &video_filename, work_dir, &transcode, hash_algorithm)? {
+                metadata.add_video_format(video_format);
+                transcoded.push(video);
+            }
+        }
     }
 
     let script_filename;
@@ -478,19 +1398,28 @@ async fn create_inner(file: File, title: String, tags: Vec<String>, video: Optio
     let mut script_added = false;
     if let Some(script) = script {
         script_path = script;
-        let script_creator_key = get_creator_info_from_key(&db_client, script_creator_key.as_deref(), interactive).await?;
-        script_filename = script_path.file_name().and_then(|f| f.to_str()).unwrap_or("script.funscript").to_string();
-        let content = std::fs::read(&script_path)?;
-        let hash = get_file_hash(&content);
+        let script_creator = get_creator_info_from_key(db_client, script_creator_key.as_deref(), interactive).await?;
+        let file_name = script_path.file_name().map(entry_name::os_str_entry_name).unwrap_or_else(|| "script.funscript".to_string());
+        script_filename = match &naming_policy {
+            Some(policy) => policy.entry_name(ItemType::Script, &file_name, &script_path, &metadata.title)?,
+            None => file_name,
+        };
+        let hash = checksum_for_add(Some(db_client), &script_filename, &script_path, hash_algorithm, &mut staged).await?;
+        let content = std::fs::read(&script_path).path_context("reading", &script_path)?;
         let file_content = String::from_utf8(content)?;
         let funscript = serde_json::from_str::<Funscript>(&file_content)?;
         let script_duration = file_util::get_funscript_duration(&funscript)?;
-        if let Some(creator_info) = script_creator_key {
-            let work_info = WorkCreatorsMetadata::new(script_filename.to_string(), String::new(), creator_info);
+        let has_creator = script_creator.is_some();
+        if let Some(creator_info) = script_creator {
+            let work_info = WorkCreatorsMetadata::new(script_filename.to_string(), String::new(), creator_info).with_creator_key(script_creator_key);
             metadata.add_script_creator(work_info);
         }
 
-        let script_variant = ScriptVariant::new(script_filename.to_string(), String::new(), vec![], script_duration, 0, hash);
+        if from_script_metadata {
+            apply_script_metadata(&mut metadata, &funscript, &script_filename, has_creator, db_client, interactive).await?;
+        }
+
+        let script_variant = ScriptVariant::new(script_filename.to_string(), script_description.trim().to_string(), vec![], script_duration, 0, hash);
         metadata.add_script_variant(script_variant);
         let add_file = AddFile::new(&script_filename, &script_path);
         script_added = true;
@@ -499,16 +1428,48 @@ async fn create_inner(file: File, title: String, tags: Vec<String>, video: Optio
 
     match (video_added, script_added) {
         (true, true) => (),
-        (true, false) => warn!("No script provided for FSV creation, creating incomplete FSV"),
-        (false, true) => warn!("No video provided for FSV creation, creating incomplete FSV"),
-        (false, false) => warn!("No video or script provided for FSV creation, creating incomplete FSV"),
+        (true, false) => warn!(operation = "create", archive = %path.display(), outcome = "incomplete", "No script provided for FSV creation, creating incomplete FSV"),
+        (false, true) => warn!(operation = "create", archive = %path.display(), outcome = "incomplete", "No video provided for FSV creation, creating incomplete FSV"),
+        (false, false) => warn!(operation = "create", archive = %path.display(), outcome = "incomplete", "No video or script provided for FSV creation, creating incomplete FSV"),
+    }
+
+    if !transcode.is_empty() && !video_added {
+        warn!(operation = "create", archive = %path.display(), outcome = "skipped", "Transcode profiles given without a video, skipping transcoding");
+    }
+
+    for video in &transcoded {
+        add_files.push(AddFile::new(&video.name, &video.path));
+    }
+
+    if let Some(policy) = &content_policy {
+        policy.screen(&mut metadata)?;
     }
 
-    build_archive(file, &metadata, add_files)?;
+    library::warn_similar_works(db_client, &metadata.title, &path).await;
+    build_archive(file, &metadata, add_files, &mut staged, reproducible, compression, encryption_password.as_deref())?;
     
     Ok(())
 }
 
+/// Transcode a video with each profile and build the metadata entries for the outputs, recording codec and resolution.
+#[cfg(feature = "native")]
+fn transcode_video_formats(video_path: &Path, video_name: &str, work_dir: &TranscodeWorkDir, profiles: &[TranscodeProfile], hash_algorithm: HashAlgorithm) -> Result<Vec<(TranscodedVideo, VideoFormat)>, TranscodeError> {
+    let mut videos = Vec::new();
+    for profile in profiles {
+        let video = transcode::transcode_video(video_path, video_name, work_dir.path(), *profile)?;
+        let duration = file_util::get_video_duration(&video.path)?;
+        // Outputs live in a temporary directory, so caching their digests would only bloat the cache
+        let digest = hash_algorithm.digest_reader(&mut std::io::BufReader::new(File::open(&video.path)?))?;
+        let hash = Checksum { algorithm: hash_algorithm, digest }.to_string();
+        let mut video_format = VideoFormat::new(video.name.clone(), profile.description().to_string(), duration, hash);
+        video_format.extra.insert("codec".to_string(), serde_json::Value::from(video.codec.clone()));
+        video_format.extra.insert("resolution".to_string(), serde_json::Value::from(video.resolution()));
+        videos.push((video, video_format));
+    }
+
+    Ok(videos)
+}
+
 #[derive(Debug, Error)]
 pub enum FsvAddError {
     #[error("I/O error: {0}")]
@@ -518,6 +1479,7 @@ pub enum FsvAddError {
     #[error("Serde json error: {0}")]
     SerdeJson(#[from] serde_json::Error),
     #[error("Database client error: {0}")]
+    #[cfg(feature = "native")]
     DbClient(#[from] db_client::DbClientError),
     #[error("FSV error: {0}")]
     Fsv(#[from] FsvError),
@@ -527,9 +1489,17 @@ pub enum FsvAddError {
     UnableToGetFileName(std::path::PathBuf),
     #[error("Creator info not found for key: {0}")]
     CreatorInfoNotFound(String),
+    #[error("Transcode error: {0}")]
+    Transcode(#[from] TranscodeError),
+    #[error("Script conversion error: {0}")]
+    Convert(#[from] ConvertError),
+    #[error("Naming error: {0}")]
+    #[cfg(feature = "native")]
+    Naming(#[from] NamingError),
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ItemType {
     Video,
     Script,
@@ -579,14 +1549,36 @@ impl EntryType {
     }
 }
 
+#[cfg(feature = "native")]
 #[derive(Debug)]
 pub struct AddArgs {
     path: PathBuf,
     item_type: ItemType,
     item_path: PathBuf,
     creator_key: Option<String>,
+    hash_algorithm: HashAlgorithm,
+    transcode: Vec<TranscodeProfile>,
+    script_format: Option<ScriptFormat>,
+    from_script_metadata: bool,
+    import_creator: bool,
+    naming_policy: Option<NamingPolicy>,
+    on_conflict: Option<AddConflict>,
+    external: Option<String>,
+    description: Option<String>,
+}
+
+/// How `add` handles a file whose entry name is already taken in the FSV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AddConflict {
+    /// Keep the existing entry and don't add the file
+    Skip,
+    /// Replace the existing entry's content, updating its checksum and duration
+    Overwrite,
+    /// Add the file under the first free name, e.g. `scene (2).mp4`
+    Rename,
 }
 
+#[cfg(feature = "native")]
 impl AddArgs {
     pub fn new(path: PathBuf, item_type: ItemType, item_path: PathBuf, creator_key: Option<String>) -> Self {
         AddArgs {
@@ -594,132 +1586,529 @@ impl AddArgs {
             item_type,
             item_path,
             creator_key,
+            hash_algorithm: HashAlgorithm::default(),
+            transcode: Vec::new(),
+            script_format: None,
+            from_script_metadata: false,
+            import_creator: false,
+            naming_policy: None,
+            on_conflict: None,
+            external: None,
+            description: None,
         }
     }
+
+    pub fn hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = hash_algorithm;
+        self
+    }
+
+    /// Profiles used to generate additional video formats (only applies to videos)
+    pub fn transcode(mut self, profiles: Vec<TranscodeProfile>) -> Self {
+        self.transcode = profiles;
+        self
+    }
+
+    /// Format of the script being added (only applies to scripts). Detected from the file if not set; non-funscripts are converted.
+    pub fn script_format(mut self, format: Option<ScriptFormat>) -> Self {
+        self.script_format = format;
+        self
+    }
+
+    /// Fill in title, tags, performers and script creator from the script's embedded metadata block (only applies to scripts).
+    pub fn from_script_metadata(mut self, from_script_metadata: bool) -> Self {
+        self.from_script_metadata = from_script_metadata;
+        self
+    }
+
+    /// Credit the creator the script's embedded metadata names, matched against the database by name or script URL (only
+    /// applies to scripts added without a creator key). See `import_script_creator`.
+    pub fn import_creator(mut self, import_creator: bool) -> Self {
+        self.import_creator = import_creator;
+        self
+    }
+
+    /// Entry name the added file has to follow. Depending on the policy, other names are renamed in the archive or rejected.
+    pub fn naming_policy(mut self, naming_policy: Option<NamingPolicy>) -> Self {
+        self.naming_policy = naming_policy;
+        self
+    }
+    /// What to do if the entry name is already taken. Without it, interactive runs ask and others skip the file.
+    pub fn on_conflict(mut self, on_conflict: Option<AddConflict>) -> Self {
+        self.on_conflict = on_conflict;
+        self
+    }
+
+    /// Reference the video at this URL or path relative to the FSV instead of storing it (only applies to videos, and
+    /// not together with `transcode`). The local file is still read for its checksum, duration and size.
+    pub fn external(mut self, source: Option<String>) -> Self {
+        self.external = source;
+        self
+    }
+
+    /// Description of the added video format, script variant or subtitle track. Replaced entries keep theirs unless it is set.
+    pub fn description(mut self, description: Option<String>) -> Self {
+        self.description = description.map(|description| description.trim().to_string());
+        self
+    }
+}
+
+/// Convert a script in another format to a funscript in a temporary directory, named after the original (`scene.csv` -> `scene.funscript`).
+/// Returns `None` for funscripts and files of unknown format, which are added as they are.
+#[cfg(feature = "native")]
+fn convert_script_for_add(script_path: &Path, format: Option<ScriptFormat>) -> Result<Option<(TranscodeWorkDir, PathBuf)>, FsvAddError> {
+    let data = std::fs::read(script_path).path_context("reading", script_path)?;
+    let format = match format.or_else(|| ScriptFormat::detect(script_path, &data)) {
+        None | Some(ScriptFormat::Funscript) => return Ok(None),
+        Some(format) => format,
+    };
+
+    let funscript = convert::read_script(&data, format)?;
+    let stem = script_path.file_stem().map(entry_name::os_str_entry_name).ok_or_else(|| FsvAddError::UnableToGetFileName(script_path.to_path_buf()))?;
+    let work_dir = TranscodeWorkDir::new()?;
+    let output_path = work_dir.path().join(format!("{}.{}", stem, import::SCRIPT_EXTENSION));
+    std::fs::write(&output_path, convert::write_script(&funscript, ScriptFormat::Funscript)?)?;
+    info!(operation = "convert", item = %script_path.display(), outcome = "completed", format = %format, "Converted script to funscript");
+
+    Ok(Some((work_dir, output_path)))
 }
 
+#[cfg(feature = "native")]
 pub async fn add_to_fsv(args: AddArgs, db_client: &DbClient, interactive: bool) -> Result<(), FsvAddError> {
-    let AddArgs { path, item_type, item_path, creator_key } = args;
-    let filname = item_path.file_name().and_then(|f| f.to_str()).ok_or_else(|| FsvAddError::UnableToGetFileName(item_path.to_path_buf()))?;
-    let content = std::fs::read(&item_path)?;
-    let hash = get_file_hash(&content);
-    let creator_info = get_creator_info_from_key(&db_client, creator_key.as_deref(), interactive).await?;
+    let AddArgs { path, item_type, item_path, creator_key, hash_algorithm, transcode, script_format, from_script_metadata, import_creator, naming_policy, on_conflict, external, description } = args;
+    let converted = match item_type {
+        ItemType::Script => convert_script_for_add(&item_path, script_format)?,
+        _ => None,
+    };
+    let item_path = match &converted {
+        Some((_, converted_path)) => converted_path.clone(),
+        None => item_path,
+    };
+    let filname = &item_path.file_name().map(entry_name::os_str_entry_name).ok_or_else(|| FsvAddError::UnableToGetFileName(item_path.to_path_buf()))?;
+    let creator_info = get_creator_info_from_key(db_client, creator_key.as_deref(), interactive).await?;
 
+    let _lock = lock_fsv(&path)?;
     let (archive, mut metadata) = open_fsv(&path)?;
+    let mut entry_name = match &naming_policy {
+        Some(policy) => policy.entry_name(item_type, filname, &item_path, &metadata.title)?,
+        None => filname.to_string(),
+    };
+    let mut replace = false;
+    if entry_name_taken(&archive, &metadata, &entry_name) {
+        match resolve_add_conflict(on_conflict, interactive, &entry_name)? {
+            AddConflict::Skip => {
+                warn!(operation = "add", archive = %path.display(), item = %entry_name, outcome = "skipped", "Entry already exists in FSV, skipping addition");
+                return Ok(());
+            },
+            AddConflict::Overwrite => replace = true,
+            AddConflict::Rename => {
+                let free_name = free_entry_name(&entry_name, |name| entry_name_taken(&archive, &metadata, name));
+                info!(operation = "add", archive = %path.display(), item = %entry_name, outcome = "renamed", "Entry already exists in FSV, adding as '{}'", free_name);
+                entry_name = free_name;
+            },
+        }
+    }
+
+    let filname = entry_name.as_str();
+    let mut staged = StagedFiles::new(&path, rebuild_entry_options());
+    let hash = match (item_type, &external) {
+        // External videos aren't written to the archive, so there is nothing to hash them while copying
+        (ItemType::Video, Some(_)) => hash_cache::file_checksum(db_client, &item_path, hash_algorithm).await?.to_string(),
+        // Converted scripts live in a temporary directory, so caching their digests would only bloat the cache
+        _ => checksum_for_add(converted.is_none().then_some(db_client), filname, &item_path, hash_algorithm, &mut staged).await?,
+    };
+    // Overwriting swaps the entry's content in place, so the old file has to go
+    let remove_files = match replace {
+        true => vec![filname],
+        false => vec![],
+    };
+    let action = if replace { "overwrite" } else { "add" };
+    history::record(&mut metadata, &format!("{} {} {}", action, item_type.get_name_lower(), filname));
     match item_type {
         ItemType::Video => {
-            for format in &metadata.video_formats {
-                if format.name == filname {
-                    warn!("Video format '{}' already exists in FSV, skipping addition", filname);
-                    return Ok(());
-                }
-            }
-            
             // TODO: Add validation for video format (duration, checksum, etc.)
 
             let video_duration = file_util::get_video_duration(&item_path)?;
-            if let Some(creator_info) = creator_info {
-                let work_info = WorkCreatorsMetadata::new(filname.to_string(), String::new(), creator_info);
-                metadata.add_video_creator(work_info);
+            match metadata.video_formats.iter_mut().find(|format| format.name == filname) {
+                // Replaced formats keep their description (unless a new one is given) and creators
+                Some(format) => {
+                    format.duration = video_duration;
+                    format.checksum = hash;
+                    if let Some(description) = description {
+                        format.description = description;
+                    }
+                },
+                None => {
+                    if let Some(creator_info) = creator_info {
+                        let work_info = WorkCreatorsMetadata::new(filname.to_string(), String::new(), creator_info).with_creator_key(creator_key.clone());
+                        metadata.add_video_creator(work_info);
+                    }
+
+                    let video_format = VideoFormat::new(filname.to_string(), description.unwrap_or_default(), video_duration, hash);
+                    metadata.add_video_format(video_format);
+                },
             }
 
-            let video_format = VideoFormat::new(filname.to_string(), String::new(), video_duration, hash);
-            metadata.add_video_format(video_format);
-            let add_file = AddFile::new(filname, &item_path);
-            rebuild_archive(&path, archive, &metadata, vec![add_file], vec![])?;
-        },
-        ItemType::Script => {
-            for variant in &metadata.script_variants {
-                if variant.name == filname {
-                    warn!("Script variant '{}' already exists in FSV, skipping addition", filname);
-                    return Ok(());
+            // An added video replaces any external reference of the same name, and an external one replaces the stored file
+            let mut references = external::external_content_from_metadata(&metadata)?;
+            let replaced = references.iter().position(|reference| reference.name == filname).map(|i| references.remove(i));
+            if let Some(source) = external {
+                let size = std::fs::metadata(&item_path).path_context("reading", &item_path)?.len();
+                let mut reference = ExternalContent::new(filname.to_string(), source, Some(size));
+                // A re-pointed reference keeps the fields other tools added to it
+                if let Some(replaced) = replaced {
+                    reference.extra = replaced.extra;
+                }
+                references.push(reference);
+                external::set_external_content(&mut metadata, &references)?;
+                rebuild_archive(&path, archive, &metadata, vec![], remove_files)?;
+                return Ok(());
+            }
+
+            external::set_external_content(&mut metadata, &references)?;
+            let mut add_files = vec![AddFile::new(filname, &item_path)];
+
+            let work_dir = match transcode.is_empty() {
+                true => None,
+                false => Some(TranscodeWorkDir::new()?),
+            };
+            let mut transcoded = Vec::new();
+            if let Some(work_dir) = &work_dir {
+                for (video, video_format) in transcode_video_formats(&item_path, filname, work_dir, &transcode, hash_algorithm)? {
+                    if metadata.video_formats.iter().any(|format| format.name == video.name) {
+                        warn!(operation = "add", archive = %path.display(), item = %video.name, outcome = "skipped", "Video format already exists in FSV, skipping transcoded output");
+                        continue;
+                    }
+
+                    metadata.add_video_format(video_format);
+                    transcoded.push(video);
                 }
             }
 
-            let file_content = std::fs::read_to_string(&path)?;
+            add_files.extend(transcoded.iter().map(|video| AddFile::new(&video.name, &video.path)));
+            rebuild_archive_staged(&path, archive, &metadata, add_files, remove_files, &mut staged)?;
+        },
+        ItemType::Script => {
+            let file_content = std::fs::read_to_string(&item_path).path_context("reading", &item_path)?;
             let funscript = serde_json::from_str::<Funscript>(&file_content)?; // validates funscript structure
             let script_duration = file_util::get_funscript_duration(&funscript)?;
+            if let Some(variant) = metadata.script_variants.iter_mut().find(|variant| variant.name == filname) {
+                // Replaced variants keep their description (unless a new one is given), axes, offset and creators
+                variant.duration = script_duration;
+                variant.checksum = hash;
+                if let Some(description) = description {
+                    variant.description = description;
+                }
+                rebuild_archive_staged(&path, archive, &metadata, vec![AddFile::new(filname, &item_path)], remove_files, &mut staged)?;
+                return Ok(());
+            }
+
+            let has_creator = creator_info.is_some();
             if let Some(creator_info) = creator_info {
-                let work_info = WorkCreatorsMetadata::new(filname.to_string(), String::new(), creator_info);
+                let work_info = WorkCreatorsMetadata::new(filname.to_string(), String::new(), creator_info).with_creator_key(creator_key.clone());
                 metadata.add_script_creator(work_info);
             }
 
-            let script_variant = ScriptVariant::new(filname.to_string(), String::new(), vec![], script_duration, 0, hash);
+            if from_script_metadata {
+                apply_script_metadata(&mut metadata, &funscript, filname, has_creator, db_client, interactive).await?;
+            }
+            else if import_creator && !has_creator && let Some(script_metadata) = &funscript.metadata {
+                credit_script_creator(&mut metadata, script_metadata, filname, db_client, interactive).await?;
+            }
+
+            // Axis scripts join their main script's bundle instead of becoming variants of their own
+            if let Some((stem, Some(axis))) = import::split_script_name(filname)
+                && let Some(main) = metadata.script_variants.iter_mut().find(|variant| import::split_script_name(&variant.name) == Some((stem, None)))
+            {
+                if !main.duration.is_zero() && script_duration.abs_diff(main.duration) > AXIS_DURATION_TOLERANCE {
+                    warn!(operation = "add", archive = %path.display(), item = %filname, "Axis script lasts {} ms, but script variant '{}' lasts {} ms", script_duration.as_millis(), main.name, main.duration.as_millis());
+                }
+
+                if !main.additional_axes.iter().any(|existing| existing == axis) {
+                    main.additional_axes.push(axis.to_string());
+                }
+
+                if description.is_some() {
+                    warn!(operation = "add", archive = %path.display(), item = %filname, "Axis scripts have no description of their own, ignoring the description given");
+                }
+
+                let add_file = AddFile::new(filname, &item_path);
+                rebuild_archive_staged(&path, archive, &metadata, vec![add_file], remove_files, &mut staged)?;
+                return Ok(());
+            }
+
+            let script_variant = ScriptVariant::new(filname.to_string(), description.unwrap_or_default(), vec![], script_duration, 0, hash);
             metadata.add_script_variant(script_variant);
             let add_file = AddFile::new(filname, &item_path);
-            rebuild_archive(&path, archive, &metadata, vec![add_file], vec![])?;
+            rebuild_archive_staged(&path, archive, &metadata, vec![add_file], remove_files, &mut staged)?;
         },
         ItemType::Subtitle => {
-            for track in &metadata.subtitle_tracks {
-                if track.name == filname {
-                    warn!("Subtitle track '{}' already exists in FSV, skipping addition", filname);
-                    return Ok(());
-                }
-            }
-
             // TODO: Add validation for subtitle track (checksum, etc.)
 
-            if let Some(creator_info) = creator_info {
-                let work_info = WorkCreatorsMetadata::new(filname.to_string(), String::new(), creator_info);
-                metadata.add_subtitle_creator(work_info);
+            match metadata.subtitle_tracks.iter_mut().find(|track| track.name == filname) {
+                Some(track) => {
+                    track.checksum = hash;
+                    if let Some(description) = description {
+                        track.description = description;
+                    }
+                },
+                None => {
+                    if let Some(creator_info) = creator_info {
+                        let work_info = WorkCreatorsMetadata::new(filname.to_string(), String::new(), creator_info).with_creator_key(creator_key.clone());
+                        metadata.add_subtitle_creator(work_info);
+                    }
+
+                    let subtitle_track = SubtitleTrack::new(filname.to_string(), String::new(), description.unwrap_or_default(), hash);
+                    metadata.add_subtitle_track(subtitle_track);
+                },
             }
 
-            let subtitle_track = SubtitleTrack::new(filname.to_string(), String::new(), String::new(), hash);
-            metadata.add_subtitle_track(subtitle_track);
             let add_file = AddFile::new(filname, &item_path);
-            rebuild_archive(&path, archive, &metadata, vec![add_file], vec![])?;
+            rebuild_archive_staged(&path, archive, &metadata, vec![add_file], remove_files, &mut staged)?;
         },
     }
 
     Ok(())
 }
 
+/// Whether `name` is already used in the FSV: as an archive entry, or by a video format, script variant, axis script or subtitle track.
+#[cfg(feature = "native")]
+fn entry_name_taken<R: Read + Seek>(archive: &zip::ZipArchive<R>, metadata: &FsvMetadata, name: &str) -> bool {
+    archive.index_for_name(name).is_some()
+        || metadata.video_formats.iter().any(|format| format.name == name)
+        || metadata.subtitle_tracks.iter().any(|track| track.name == name)
+        || metadata.script_variants.iter().any(|variant| variant.name == name || axis_script_names(variant).iter().any(|(_, axis_name)| axis_name == name))
+}
+
+/// `name` with the first free ` (2)`, ` (3)`, ... inserted before its extension (before `.<axis>.funscript` for axis scripts).
+#[cfg(feature = "native")]
+fn free_entry_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    let stem_len = import::split_script_name(name).map(|(stem, _)| stem.len()).or_else(|| name.rfind('.')).unwrap_or(name.len());
+    let (stem, extension) = name.split_at(stem_len);
+    (2..).map(|n| format!("{} ({}){}", stem, n, extension)).find(|candidate| !taken(candidate)).expect("some suffix is free")
+}
+
+/// What to do about an entry name that is already taken: `on_conflict` if given, otherwise the user's answer, or skipping when not interactive.
+#[cfg(feature = "native")]
+fn resolve_add_conflict(on_conflict: Option<AddConflict>, interactive: bool, entry_name: &str) -> std::io::Result<AddConflict> {
+    if let Some(on_conflict) = on_conflict {
+        return Ok(on_conflict);
+    }
+
+    if !interactive {
+        return Ok(AddConflict::Skip);
+    }
+
+    loop {
+        let answer = prompt_input(&format!("'{}' already exists in the FSV. [o]verwrite, [r]ename or [s]kip? [s]: ", entry_name))?;
+        match answer.to_lowercase().as_str() {
+            "o" | "overwrite" => return Ok(AddConflict::Overwrite),
+            "r" | "rename" => return Ok(AddConflict::Rename),
+            "" | "s" | "skip" => return Ok(AddConflict::Skip),
+            _ => println!("Please answer o, r or s."),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
 pub async fn add_creator_to_fsv(fsv_path: &Path, work_type: ItemType, creator_key: &str, work_name: &str, source_url: &str, db_client: &DbClient) -> Result<(), FsvAddError> {
+    let _lock = lock_fsv(fsv_path)?;
     let (archive, mut metadata) = open_fsv(fsv_path)?;
     let creator_info = db_client.get_creator_info_by_key(creator_key).await?;
     let creator_info = match creator_info {
         Some(info) => info,
+        None if !db_client.is_persistent() => return Err(FsvError::NoCreatorDatabase(creator_key.to_string()).into()),
         None => return Err(FsvAddError::CreatorInfoNotFound(creator_key.to_string())),
     };
 
-    let work_info = WorkCreatorsMetadata::new(work_name.to_string(), source_url.to_string(), creator_info);
+    let work_info = WorkCreatorsMetadata::new(work_name.to_string(), source_url.to_string(), creator_info).with_creator_key(Some(creator_key.to_string()));
     match work_type {
         ItemType::Video => metadata.add_video_creator(work_info),
         ItemType::Script => metadata.add_script_creator(work_info),
         ItemType::Subtitle => metadata.add_subtitle_creator(work_info),
     }
 
+    history::record(&mut metadata, &format!("add {} creator {}", work_type.get_name_lower(), creator_key));
     rebuild_archive(fsv_path, archive, &metadata, vec![], vec![])?;
     
     Ok(())
 }
 
-#[derive(Debug, Error)]
-pub enum FsvRemoveError {
-    #[error("I/O error: {0}")]
-    Io(#[from] std::io::Error),
-    #[error("ZIP archive error: {0}")]
-    Zip(#[from] zip::result::ZipError),
-    #[error("Serde json error: {0}")]
-    SerdeJson(#[from] serde_json::Error),
-    #[error("Database client error: {0}")]
-    DbClient(#[from] db_client::DbClientError),
-    #[error("FSV error: {0}")]
-    Fsv(#[from] FsvError),
-    #[error("Entry not found: {0}")]
-    EntryNotFound(String),
+/// Which way `sync_creators` copies creator info.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum CreatorSyncDirection {
+    /// Refresh the creator info embedded in the FSV from the database
+    #[default]
+    Pull,
+    /// Store the FSV's creator info in the database, adding missing records and updating diverged ones
+    Push,
 }
 
-pub fn remove_from_fsv(path: &Path, entry_type: EntryType, entry_id: &str) -> Result<(), FsvRemoveError> {
-    let (archive, mut metadata) = open_fsv(path)?;
-    match entry_type {
-        EntryType::Creator => {
-            let mut found = false;
-            metadata.creators.retain(|creator| {
-                if creator.work_name == entry_id {
-                    found = true;
-                    false
-                }
+/// A linked creator whose info embedded in an FSV differs from its database record.
+#[derive(Debug, Clone)]
+pub struct CreatorConflict {
+    pub key: String,
+    pub work_name: String,
+    pub embedded: CreatorInfo,
+    pub database: CreatorInfo,
+}
+
+impl std::fmt::Display for CreatorConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Creator '{}' of '{}' differs: FSV has '{}' [{}], database has '{}' [{}]", self.key, self.work_name,
+            self.embedded.name, self.embedded.socials.join(", "), self.database.name, self.database.socials.join(", "))
+    }
+}
+
+/// What `sync_creators` found and did.
+#[derive(Debug, Default)]
+pub struct CreatorSyncReport {
+    /// Linked creators already matching their database record
+    pub unchanged: usize,
+    /// Linked creators differing from their database record. They were synced unless it was a dry run
+    pub conflicts: Vec<CreatorConflict>,
+    /// Keys without a database record. Pushing adds them
+    pub missing: Vec<String>,
+    /// Work names of creators without a `creator_key`, which can't be synced
+    pub unlinked: Vec<String>,
+    /// Work names of creators linked to a merged-away key, now linked to the creator it was merged into
+    pub relinked: Vec<String>,
+}
+
+/// Compare the creators an FSV links to database records by `creator_key` with those records, and copy the info
+/// `direction`'s way where they differ. Names and socials are compared, the order of socials doesn't matter.
+/// A key linked more than once with different info is pushed from its first work only.
+#[cfg(feature = "native")]
+pub async fn sync_creators(path: &Path, direction: CreatorSyncDirection, dry_run: bool, db_client: &DbClient) -> Result<CreatorSyncReport, FsvEditError> {
+    sync_relinked_creators(path, direction, dry_run, db_client, None).await
+}
+
+/// Link the creators of an FSV linked to `loser_key`, a creator merged into `winner_key` by `DbClient::merge_creators`,
+/// to `winner_key` instead, and refresh every linked creator's embedded info from the database as `sync_creators` pulls it.
+#[cfg(feature = "native")]
+pub async fn relink_merged_creator(path: &Path, loser_key: &str, winner_key: &str, db_client: &DbClient) -> Result<CreatorSyncReport, FsvEditError> {
+    sync_relinked_creators(path, CreatorSyncDirection::Pull, false, db_client, Some((loser_key, winner_key))).await
+}
+
+/// `sync_creators`, linking the creators linked to the first key of `relink` to its second key first.
+#[cfg(feature = "native")]
+async fn sync_relinked_creators(path: &Path, direction: CreatorSyncDirection, dry_run: bool, db_client: &DbClient, relink: Option<(&str, &str)>) -> Result<CreatorSyncReport, FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let (archive, mut metadata) = open_fsv(path)?;
+    let same_info = |a: &CreatorInfo, b: &CreatorInfo| {
+        let socials = |info: &CreatorInfo| socials::normalize_socials(&info.socials).into_iter().collect::<HashSet<_>>();
+        a.name.trim() == b.name.trim() && socials(a) == socials(b)
+    };
+
+    let mut report = CreatorSyncReport::default();
+    // Database writes wait until the archive is rewritten; the info pushed so far stands in for the records they write
+    let mut writes = Vec::new();
+    let mut pushed: HashMap<String, CreatorInfo> = HashMap::new();
+    let mut pulled = false;
+    let creators = &mut metadata.creators;
+    for work in creators.videos.iter_mut().chain(creators.scripts.iter_mut()).chain(creators.subtitles.iter_mut()) {
+        if let Some((from, to)) = relink && work.creator_key.as_deref() == Some(from) {
+            work.creator_key = Some(to.to_string());
+            report.relinked.push(work.work_name.clone());
+        }
+
+        let Some(key) = work.creator_key.clone() else {
+            report.unlinked.push(work.work_name.clone());
+            continue;
+        };
+
+        let database = match pushed.get(&key) {
+            Some(creator_info) => Some(creator_info.clone()),
+            None => db_client.get_creator_info_by_key(&key).await?,
+        };
+        let Some(database) = database else {
+            if !report.missing.contains(&key) {
+                report.missing.push(key.clone());
+            }
+            if direction == CreatorSyncDirection::Push && !dry_run {
+                writes.push(DbWrite::SaveCreator { key: key.clone(), name: work.creator_info.name.clone(), socials: work.creator_info.socials.clone() });
+                pushed.insert(key, work.creator_info.clone());
+            }
+            continue;
+        };
+
+        if same_info(&work.creator_info, &database) {
+            report.unchanged += 1;
+            continue;
+        }
+
+        report.conflicts.push(CreatorConflict { key: key.clone(), work_name: work.work_name.clone(), embedded: work.creator_info.clone(), database: database.clone() });
+        if dry_run {
+            continue;
+        }
+
+        match direction {
+            CreatorSyncDirection::Pull => {
+                // Fields the database doesn't store are kept
+                work.creator_info.name = database.name;
+                work.creator_info.socials = database.socials;
+                pulled = true;
+            },
+            CreatorSyncDirection::Push if pushed.contains_key(&key) => warn!(operation = "sync_creators", archive = %path.display(), item = %work.work_name, creator = %key, outcome = "skipped", "Creator differs from the one already pushed for its key, keeping the database record"),
+            CreatorSyncDirection::Push => {
+                writes.push(DbWrite::SaveCreator { key: key.clone(), name: work.creator_info.name.clone(), socials: work.creator_info.socials.clone() });
+                pushed.insert(key, work.creator_info.clone());
+            },
+        }
+    }
+
+    let rebuild = pulled || !report.relinked.is_empty();
+    if writes.is_empty() && !rebuild {
+        return Ok(report);
+    }
+
+    let tx = ArchiveTransaction::begin(path, writes).map_err(FsvError::from)?;
+    if rebuild {
+        history::record(&mut metadata, match relink {
+            Some(_) => "merge creators",
+            None => "sync creators",
+        });
+        if let Err(err) = rebuild_archive(path, archive, &metadata, vec![], vec![]) {
+            tx.abort().map_err(FsvError::from)?;
+            return Err(err.into());
+        }
+    }
+    tx.commit(db_client).await?;
+
+    Ok(report)
+}
+
+#[derive(Debug, Error)]
+pub enum FsvRemoveError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ZIP archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Serde json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("Database client error: {0}")]
+    #[cfg(feature = "native")]
+    DbClient(#[from] db_client::DbClientError),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+    #[error("Trash error: {0}")]
+    Trash(#[from] TrashError),
+    #[error("Entry not found: {0}")]
+    EntryNotFound(String),
+}
+
+pub fn remove_from_fsv(path: &Path, entry_type: EntryType, entry_id: &str) -> Result<(), FsvRemoveError> {
+    let _lock = lock_fsv(path)?;
+    let (mut archive, mut metadata) = open_fsv(path)?;
+    let metadata_before = serde_json::to_value(&metadata)?;
+    let remove_files = match entry_type {
+        EntryType::Creator => {
+            let mut found = false;
+            metadata.creators.retain(|creator| {
+                if creator.work_name == entry_id {
+                    found = true;
+                    false
+                }
                 else {
                     true
                 }
@@ -729,7 +2118,7 @@ pub fn remove_from_fsv(path: &Path, entry_type: EntryType, entry_id: &str) -> Re
                 return Err(FsvRemoveError::EntryNotFound(entry_id.to_string()));
             }
 
-            rebuild_archive(path, archive, &metadata, vec![], vec![])?;
+            vec![]
         },
         EntryType::Video => {
             let mut found = false;
@@ -747,22 +2136,37 @@ pub fn remove_from_fsv(path: &Path, entry_type: EntryType, entry_id: &str) -> Re
                 return Err(FsvRemoveError::EntryNotFound(entry_id.to_string()));
             }
 
-            let remove_files = vec![entry_id];
-            rebuild_archive(path, archive, &metadata, vec![], remove_files)?;
+            // Malformed references are left for the user to fix
+            if let Ok(mut references) = external::external_content_from_metadata(&metadata) {
+                references.retain(|reference| reference.name != entry_id);
+                external::set_external_content(&mut metadata, &references)?;
+            }
+            vec![entry_id.to_string()]
         },
         EntryType::Script => {
-            let mut parts = entry_id.splitn(2, '.');
-            let stem = parts.next().unwrap_or(entry_id);
-            let ext = parts.next().unwrap_or("funscript"); // Some scripts may have multiple extensions (e.g., .roll.funscript)
-            let scripts = if ext != "funscript" { // If specific axis was provided, only remove that one
-                vec![entry_id.to_string()]
+            let mut scripts = vec![entry_id.to_string()];
+            let mut found = false;
+            match import::split_script_name(entry_id) {
+                // A single axis script: only that one is removed, and it leaves its variant's bundle
+                Some((stem, Some(axis))) => {
+                    for variant in &mut metadata.script_variants {
+                        if import::split_script_name(&variant.name) == Some((stem, None)) && let Some(i) = variant.additional_axes.iter().position(|existing| existing == axis) {
+                            variant.additional_axes.remove(i);
+                            found = true;
+                        }
+                    }
+                },
+                // A main script: the whole bundle goes, including axis scripts added as variants of their own
+                Some((stem, None)) => {
+                    if let Some(variant) = metadata.script_variants.iter().find(|variant| variant.name == entry_id) {
+                        scripts.extend(axis_script_names(variant).into_iter().map(|(_, name)| name));
+                    }
+
+                    scripts.extend(AXES.iter().map(|axis| format!("{}.{}.{}", stem, axis, import::SCRIPT_EXTENSION)));
+                },
+                None => (),
             }
-            else {  // Else remove all axis variants in addition to the base script
-                let scripts = AXES.iter().map(|axis| format!("{}.{}.{}", stem, axis, ext));
-                std::iter::once(entry_id.to_string()).chain(scripts).collect()
-            };
 
-            let mut found = false;
             metadata.script_variants.retain(|variant| {
                 if scripts.contains(&variant.name) {
                     found = true;
@@ -777,8 +2181,7 @@ pub fn remove_from_fsv(path: &Path, entry_type: EntryType, entry_id: &str) -> Re
                 return Err(FsvRemoveError::EntryNotFound(entry_id.to_string()));
             }
 
-            let remove_files = scripts.iter().map(|s| s.as_str()).collect();
-            rebuild_archive(path, archive, &metadata, vec![], remove_files)?;
+            scripts
         },
         EntryType::Subtitle => {
             let mut found = false;
@@ -796,21 +2199,73 @@ pub fn remove_from_fsv(path: &Path, entry_type: EntryType, entry_id: &str) -> Re
                 return Err(FsvRemoveError::EntryNotFound(entry_id.to_string()));
             }
 
-            let remove_files = vec![entry_id];
-            rebuild_archive(path, archive, &metadata, vec![], remove_files)?;
+            vec![entry_id.to_string()]
         },
+    };
+
+    // Keep what is removed in the trash, so `undo` can bring it back
+    let remove_files = remove_files.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    let description = format!("{} {}", entry_type.get_name().to_lowercase(), entry_id);
+    history::record(&mut metadata, &format!("remove {}", description));
+    trash::push_snapshot(path, &mut archive, &description, metadata_before, serde_json::to_value(&metadata)?, &remove_files)?;
+    if let Err(err) = rebuild_archive(path, archive, &metadata, vec![], remove_files) {
+        trash::pop_snapshot(path)?;
+        return Err(err.into());
     }
 
     Ok(())
 }
 
+#[derive(Debug, Error)]
+pub enum FsvUndoError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ZIP archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Serde json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+    #[error("Trash error: {0}")]
+    Trash(#[from] TrashError),
+    #[error("Nothing to undo for '{0}'")]
+    NothingToUndo(PathBuf),
+    #[error("Metadata of '{0}' changed since the removal; undoing it would discard those changes")]
+    Modified(PathBuf),
+}
+
+/// Restore the entries and metadata of the last removal kept in the trash of `path`, and drop it from the trash.
+/// Refuses if the metadata changed since the removal, unless `force` is set (the later changes are then lost).
+pub fn undo_remove(path: &Path, force: bool) -> Result<TrashSnapshot, FsvUndoError> {
+    let _lock = lock_fsv(path)?;
+    let (archive, metadata) = open_fsv(path)?;
+    let snapshot = trash::list_snapshots(path)?.pop().ok_or_else(|| FsvUndoError::NothingToUndo(path.to_path_buf()))?;
+    if !force && serde_json::to_value(&metadata)? != snapshot.metadata_after {
+        return Err(FsvUndoError::Modified(path.to_path_buf()));
+    }
+
+    let work_dir = TranscodeWorkDir::new()?;
+    let restored = trash::extract_snapshot_files(path, &snapshot, work_dir.path())?;
+    let add_files = restored.iter().map(|(name, file)| AddFile::new(name, file)).collect();
+    // Entries re-added since the removal are replaced rather than duplicated
+    let remove_files = snapshot.files.iter().map(|name| name.as_str()).collect();
+    let mut metadata_before = serde_json::from_value::<FsvMetadata>(snapshot.metadata_before.clone())?;
+    history::carry_over(&metadata, &mut metadata_before);
+    history::record(&mut metadata_before, &format!("undo remove {}", snapshot.description));
+    rebuild_archive(path, archive, &metadata_before, add_files, remove_files)?;
+    trash::pop_snapshot(path)?;
+
+    Ok(snapshot)
+}
+
+#[cfg(feature = "native")]
 pub async fn remove_creator_from_db(creator_key: &str, db_client: &DbClient) -> Result<(), FsvRemoveError> {
     db_client.delete_creator_info_by_key(creator_key).await?;
     Ok(())
 }
 
 #[derive(Debug, Error)]
-pub enum FsvRebuildError {
+pub enum FsvEditError {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
     #[error("ZIP archive error: {0}")]
@@ -818,195 +2273,2020 @@ pub enum FsvRebuildError {
     #[error("Serde json error: {0}")]
     SerdeJson(#[from] serde_json::Error),
     #[error("Database client error: {0}")]
+    #[cfg(feature = "native")]
     DbClient(#[from] db_client::DbClientError),
+    #[error("Archive transaction error: {0}")]
+    #[cfg(feature = "native")]
+    ArchiveTx(#[from] ArchiveTxError),
     #[error("FSV error: {0}")]
     Fsv(#[from] FsvError),
+    #[error("{0} '{1}' not found in metadata")]
+    ItemNotFound(ItemType, String),
+    #[error("'{0}' is not a language tag")]
+    InvalidLanguage(String),
+    #[error("FSV Validation error: {0}")]
+    Validation(#[from] FsvValidationError),
+    #[error("The FSV would not validate with the new metadata")]
+    MetadataRejected(Box<ValidationReport>),
 }
 
-/// Rebuild the FSV archive without any changes. This ensures that the only files present are those listed in the central directory of the ZIP archive.
-pub fn rebuild_fsv(path: &Path) -> Result<(), FsvRebuildError> {
-    let (archive, metadata) = open_fsv(path)?;
+pub fn edit_fsv_title(path: &Path, title: &str) -> Result<(), FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let (archive, mut metadata) = open_fsv(path)?;
+    metadata.title = title.to_string();
+    history::record(&mut metadata, "edit title");
     rebuild_archive(path, archive, &metadata, vec![], vec![])?;
 
     Ok(())
 }
 
-#[derive(Debug)]
-pub struct FsvInfo {
-    // Define fields to hold information about the FSV file
-    pub title: String,
-    pub videos: Vec<(String, bool)>, // (filename, is_present)
-    pub scripts: Vec<(String, bool)>, // (filename, is_present)
-    pub subtitles: Vec<(String, bool)>, // (filename, is_present)
-    pub extra_files: Vec<String>,
+/// Add and remove tags on an existing FSV. Added tags are normalized against the tag vocabulary; removals match case-insensitively.
+/// With `apply_implied`, the tags implied by the resulting ones (see `DbClient::implied_tags`) are added too, and returned.
+#[cfg(feature = "native")]
+pub async fn edit_fsv_tags(path: &Path, add_tags: Vec<String>, remove_tags: Vec<String>, apply_implied: bool, db_client: &DbClient) -> Result<Vec<String>, FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let (archive, mut metadata) = open_fsv(path)?;
+    metadata.tags = retag(db_client, &metadata.tags, &add_tags, &remove_tags).await?;
+    let implied = match apply_implied {
+        true => implied_tags(db_client, &metadata.tags).await?,
+        false => Vec::new(),
+    };
+    metadata.tags.extend(implied.iter().cloned());
+    history::record(&mut metadata, "edit tags");
+    rebuild_archive(path, archive, &metadata, vec![], vec![])?;
+
+    Ok(implied)
 }
 
-impl FsvInfo {
-    fn new(title: String, videos: Vec<(String, bool)>, scripts: Vec<(String, bool)>, subtitles: Vec<(String, bool)>, extra_files: Vec<String>) -> Self {
-        FsvInfo { title, videos, scripts, subtitles, extra_files }
+/// Set the title in `language`, stored with the `fsv.localized-titles` extension (an empty title removes it).
+/// Returns the language tag as stored, normalized (e.g. `ja-JP` for `ja_jp`).
+pub fn edit_fsv_localized_title(path: &Path, language: &str, title: &str) -> Result<String, FsvEditError> {
+    if metadata::normalize_language_tag(language).is_empty() {
+        return Err(FsvEditError::InvalidLanguage(language.to_string()));
     }
+
+    let _lock = lock_fsv(path)?;
+    let (archive, mut metadata) = open_fsv(path)?;
+    let language = titles::set_localized_title(&mut metadata, language, title)?;
+    history::record(&mut metadata, &format!("edit title {}", language));
+    rebuild_archive(path, archive, &metadata, vec![], vec![])?;
+
+    Ok(language)
 }
 
-// TODO: Add parameter for extracting other info such as creators, tags, etc.
-pub fn get_fsv_info(path: &Path) -> Result<FsvInfo, FsvError> {
-    let (mut archive, metadata) = open_fsv(path)?;
-    let title = if metadata.title.trim().is_empty() {
-        path.file_stem()
-            .and_then(|os_str| os_str.to_str())
-            .unwrap_or("unknown")
-            .to_string()
-    }
-    else{
-        metadata.title.to_string()
+pub fn edit_fsv_studio(path: &Path, studio: &str) -> Result<(), FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let (archive, mut metadata) = open_fsv(path)?;
+    metadata.studio = studio.trim().to_string();
+    history::record(&mut metadata, "edit studio");
+    rebuild_archive(path, archive, &metadata, vec![], vec![])?;
+
+    Ok(())
+}
+
+/// Set the description of the video format, script variant or subtitle track `name` (an empty string clears it).
+pub fn edit_fsv_description(path: &Path, item_type: ItemType, name: &str, description: &str) -> Result<(), FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let (archive, mut metadata) = open_fsv(path)?;
+    let target = match item_type {
+        ItemType::Video => metadata.video_formats.iter_mut().find(|format| format.name == name).map(|format| &mut format.description),
+        ItemType::Script => metadata.script_variants.iter_mut().find(|variant| variant.name == name).map(|variant| &mut variant.description),
+        ItemType::Subtitle => metadata.subtitle_tracks.iter_mut().find(|track| track.name == name).map(|track| &mut track.description),
+    };
+    let Some(target) = target else {
+        return Err(FsvEditError::ItemNotFound(item_type, name.to_string()));
     };
 
-    let mut seen_files = HashSet::new();
-    let mut videos = Vec::new();
-    for video in &metadata.video_formats {
-        let is_present = archive.by_name(&video.name).is_ok();
-        videos.push((video.name.to_string(), is_present));
-        seen_files.insert(video.name.to_string());
+    *target = description.trim().to_string();
+    history::record(&mut metadata, &format!("edit {} description {}", item_type.get_name_lower(), name));
+    rebuild_archive(path, archive, &metadata, vec![], vec![])?;
+
+    Ok(())
+}
+
+/// Add and remove performers on an existing FSV. Removals and duplicate checks match case-insensitively.
+pub fn edit_fsv_performers(path: &Path, add_performers: Vec<String>, remove_performers: Vec<String>) -> Result<(), FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let (archive, mut metadata) = open_fsv(path)?;
+    metadata.performers.retain(|performer| !remove_performers.iter().any(|r| r.trim().eq_ignore_ascii_case(performer)));
+    metadata.performers = dedup_names(metadata.performers.drain(..).chain(add_performers).collect());
+    history::record(&mut metadata, "edit performers");
+    rebuild_archive(path, archive, &metadata, vec![], vec![])?;
+
+    Ok(())
+}
+
+/// Creator records whose `work_name` names no item of their kind: video creators need a video format, script creators
+/// a script variant or one of its axis scripts, and subtitle creators a subtitle track.
+pub fn orphan_creators(metadata: &FsvMetadata) -> Vec<(ItemType, &WorkCreatorsMetadata)> {
+    let names = |item_type: ItemType| -> HashSet<String> {
+        match item_type {
+            ItemType::Video => metadata.video_formats.iter().map(|format| format.name.trim().to_string()).collect(),
+            ItemType::Script => metadata.script_variants.iter()
+                .flat_map(|variant| std::iter::once(variant.name.clone()).chain(axis_script_names(variant).into_iter().map(|(_, name)| name)))
+                .map(|name| name.trim().to_string())
+                .collect(),
+            ItemType::Subtitle => metadata.subtitle_tracks.iter().map(|track| track.name.trim().to_string()).collect(),
+        }
+    };
+
+    let creators = &metadata.creators;
+    [(ItemType::Video, &creators.videos), (ItemType::Script, &creators.scripts), (ItemType::Subtitle, &creators.subtitles)].into_iter()
+        .flat_map(|(item_type, works)| {
+            let names = names(item_type);
+            works.iter().filter(move |work| !names.contains(work.work_name.trim())).map(move |work| (item_type, work))
+        })
+        .collect()
+}
+
+/// Remove the creator records `orphan_creators` finds. Returns the work names they credited, or nothing (leaving the
+/// archive untouched) if there were none.
+pub fn prune_orphan_creators(path: &Path) -> Result<Vec<String>, FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let (archive, mut metadata) = open_fsv(path)?;
+    let orphans: Vec<(ItemType, String)> = orphan_creators(&metadata).into_iter().map(|(item_type, work)| (item_type, work.work_name.clone())).collect();
+    if orphans.is_empty() {
+        return Ok(Vec::new());
     }
 
-    let mut scripts = Vec::new();
-    for variant in &metadata.script_variants {
-        let is_present = archive.by_name(&variant.name).is_ok();
-        scripts.push((variant.name.to_string(), is_present));
-        seen_files.insert(variant.name.to_string());
+    let is_orphan = |item_type: ItemType, work: &WorkCreatorsMetadata| orphans.iter().any(|(orphan_type, name)| *orphan_type == item_type && *name == work.work_name);
+    metadata.creators.videos.retain(|work| !is_orphan(ItemType::Video, work));
+    metadata.creators.scripts.retain(|work| !is_orphan(ItemType::Script, work));
+    metadata.creators.subtitles.retain(|work| !is_orphan(ItemType::Subtitle, work));
+    history::record(&mut metadata, "prune orphan creators");
+    rebuild_archive(path, archive, &metadata, vec![], vec![])?;
+
+    Ok(orphans.into_iter().map(|(_, name)| name).collect())
+}
+
+/// Set (`Some`) or clear (`None`) the start offset of the script variant `script` against the video format `video`,
+/// stored with the `fsv.pair-offsets` extension. Returns the offset the pair had before, if any.
+pub fn set_pair_offset(path: &Path, script: &str, video: &str, offset_ms: Option<i64>) -> Result<Option<i64>, FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let (archive, mut metadata) = open_fsv(path)?;
+    if !metadata.script_variants.iter().any(|variant| variant.name == script) {
+        return Err(FsvEditError::ItemNotFound(ItemType::Script, script.to_string()));
+    }
+    if !metadata.video_formats.iter().any(|format| format.name == video) {
+        return Err(FsvEditError::ItemNotFound(ItemType::Video, video.to_string()));
     }
 
-    let mut subtitles = Vec::new();
-    for track in &metadata.subtitle_tracks {
-        let is_present = archive.by_name(&track.name).is_ok();
-        subtitles.push((track.name.to_string(), is_present));
-        seen_files.insert(track.name.to_string());
+    let mut pair_offsets = offsets::pair_offsets_from_metadata(&metadata)?;
+    let previous = pair_offsets.iter().find(|offset| offset.script == script && offset.video == video).map(|offset| offset.offset_ms);
+    if previous == offset_ms {
+        return Ok(previous);
     }
 
-    let mut extra_files = Vec::new();
-    for i in 0..archive.len() {
-        let file = archive.by_index(i)?;
-        let file_name = file.name();
-        if !seen_files.contains(file_name) {
-            extra_files.push(file_name.to_string());
-        }
+    let existing = pair_offsets.iter_mut().find(|offset| offset.script == script && offset.video == video);
+    match (existing, offset_ms) {
+        (Some(existing), Some(offset_ms)) => existing.offset_ms = offset_ms,
+        (None, Some(offset_ms)) => pair_offsets.push(PairOffset::new(script.to_string(), video.to_string(), offset_ms)),
+        (_, None) => pair_offsets.retain(|offset| offset.script != script || offset.video != video),
     }
-    
-    Ok(FsvInfo::new(title, videos, scripts, subtitles, extra_files))
+    offsets::set_pair_offsets(&mut metadata, &pair_offsets)?;
+    history::record(&mut metadata, match offset_ms {
+        Some(_) => "set pair offset",
+        None => "clear pair offset",
+    });
+    rebuild_archive(path, archive, &metadata, vec![], vec![])?;
+
+    Ok(previous)
+}
+
+/// Set (`Some`) or clear (`None`) the offset of the subtitle track `subtitle` against the video format `video`,
+/// stored with the `fsv.subtitle-offsets` extension. Returns the offset the pair had before, if any.
+pub fn set_subtitle_offset(path: &Path, subtitle: &str, video: &str, offset_ms: Option<i64>) -> Result<Option<i64>, FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let (archive, mut metadata) = open_fsv(path)?;
+    if !metadata.subtitle_tracks.iter().any(|track| track.name == subtitle) {
+        return Err(FsvEditError::ItemNotFound(ItemType::Subtitle, subtitle.to_string()));
+    }
+    if !metadata.video_formats.iter().any(|format| format.name == video) {
+        return Err(FsvEditError::ItemNotFound(ItemType::Video, video.to_string()));
+    }
+
+    let mut offsets = subtitle_offsets::subtitle_offsets_from_metadata(&metadata)?;
+    let previous = offsets.iter().find(|offset| offset.subtitle == subtitle && offset.video == video).map(|offset| offset.offset_ms);
+    if previous == offset_ms {
+        return Ok(previous);
+    }
+
+    let existing = offsets.iter_mut().find(|offset| offset.subtitle == subtitle && offset.video == video);
+    match (existing, offset_ms) {
+        (Some(existing), Some(offset_ms)) => existing.offset_ms = offset_ms,
+        (None, Some(offset_ms)) => offsets.push(SubtitleOffset::new(subtitle.to_string(), video.to_string(), offset_ms)),
+        (_, None) => offsets.retain(|offset| offset.subtitle != subtitle || offset.video != video),
+    }
+    subtitle_offsets::set_subtitle_offsets(&mut metadata, &offsets)?;
+    history::record(&mut metadata, match offset_ms {
+        Some(_) => "set subtitle offset",
+        None => "clear subtitle offset",
+    });
+    rebuild_archive(path, archive, &metadata, vec![], vec![])?;
+
+    Ok(previous)
+}
+
+/// Declare the `fsv.history` extension, so every later change to the archive is appended to its history.
+/// Returns false if it already was declared.
+pub fn enable_fsv_history(path: &Path) -> Result<bool, FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let (archive, mut metadata) = open_fsv(path)?;
+    if !history::enable(&mut metadata) {
+        return Ok(false);
+    }
+
+    history::record(&mut metadata, "enable history");
+    rebuild_archive(path, archive, &metadata, vec![], vec![])?;
+
+    Ok(true)
+}
+
+/// Store downloaded external videos (`fsv.external-content`) in the archive under their video format names and drop their
+/// external references. `files` pairs format names with local files, whose checksums the caller has verified.
+pub fn embed_external_content(path: &Path, files: &[(String, PathBuf)]) -> Result<(), FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let (archive, mut metadata) = open_fsv(path)?;
+    let mut references = external::external_content_from_metadata(&metadata)?;
+    references.retain(|reference| !files.iter().any(|(name, _)| *name == reference.name));
+    external::set_external_content(&mut metadata, &references)?;
+    for (name, _) in files {
+        history::record(&mut metadata, &format!("fetch video {}", name));
+    }
+
+    let add_files = files.iter().map(|(name, file)| AddFile::new(name, file)).collect();
+    rebuild_archive(path, archive, &metadata, add_files, vec![])?;
+
+    Ok(())
+}
+
+/// Trim names and drop empty and case-insensitively repeated ones, keeping the first spelling.
+fn dedup_names(names: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    names.into_iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty() && seen.insert(name.to_lowercase()))
+        .collect()
 }
 
 #[derive(Debug, Error)]
-pub enum FsvError {
+pub enum FsvRebuildError {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
     #[error("ZIP archive error: {0}")]
     Zip(#[from] zip::result::ZipError),
-    #[error("JSON deserialization error: {0}")]
+    #[error("Serde json error: {0}")]
     SerdeJson(#[from] serde_json::Error),
     #[error("Database client error: {0}")]
+    #[cfg(feature = "native")]
     DbClient(#[from] db_client::DbClientError),
-    #[error("Metadata file not found in FSV archive")]
-    MetadataFileNotFound,
-    #[error("Creator info not found for key: {0}")]
-    CreatorInfoNotFound(String),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+    #[error("Rebuilding would discard data missing from the central directory or shadowed by a duplicate: {}", .0.join(", "))]
+    UnknownData(Vec<String>),
 }
 
-#[derive(Debug)]
-pub struct AddFile<'a> {
-    pub name: &'a str,
-    pub path: &'a Path,
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RebuildOptions {
+    /// Also drop metadata entries repeating an earlier video, script or subtitle name
+    pub fix_duplicates: bool,
+    /// Refuse to rebuild an archive holding data the rebuild would discard (see `ArchiveStructure::unknown_data`)
+    pub strict: bool,
+    /// With `strict`, rebuild anyway, confirming the unknown data can go
+    pub discard_unknown: bool,
 }
 
-impl<'a> AddFile<'a> {
-    pub fn new(name: &'a str, path: &'a Path) -> Self {
-        AddFile { name, path }
-    }
+/// Rebuild the FSV archive without any changes. This ensures that the only files present are those listed in the central directory of the ZIP archive,
+/// and that each entry name is stored once. Returns the names of the metadata entries dropped by `fix_duplicates`.
+pub fn rebuild_fsv(path: &Path, options: &RebuildOptions) -> Result<Vec<String>, FsvRebuildError> {
+    rebuild_fsv_with_progress(path, options, &NoProgress)
 }
 
-fn build_archive(file: File, metadata: &FsvMetadata, add_files: Vec<AddFile>) -> Result<(), FsvError> {
-    let mut zip_writer = zip::ZipWriter::new(file);
-    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Bzip2);
-    // Write metadata first
-    let metadata_json = serde_json::to_string_pretty(metadata)?;
-    zip_writer.start_file("metadata.json", options)?;
-    zip_writer.write_all(metadata_json.as_bytes())?;
+/// Same as `rebuild_fsv`, reporting `rebuild` progress events to `progress`.
+pub fn rebuild_fsv_with_progress(path: &Path, options: &RebuildOptions, progress: &dyn ProgressListener) -> Result<Vec<String>, FsvRebuildError> {
+    let _lock = lock_fsv(path)?;
+    let unknown_data = FsvContainer::open(path)?.structure().unknown_data();
+    if !unknown_data.is_empty() {
+        if options.strict && !options.discard_unknown {
+            return Err(FsvRebuildError::UnknownData(unknown_data));
+        }
 
-    // Add files
-    for file_path in add_files {
-        let mut file = std::fs::File::open(file_path.path)?;
-        zip_writer.start_file(file_path.name, options)?;
-        std::io::copy(&mut file, &mut zip_writer)?;
+        warn!(operation = "rebuild", archive = %path.display(), "Discarding data missing from the central directory or shadowed by a duplicate: {}", unknown_data.join(", "));
     }
-    
-    zip_writer.finish()?.flush()?;
 
-    Ok(())
+    let (archive, mut metadata) = open_fsv(path)?;
+    let mut removed = Vec::new();
+    if options.fix_duplicates {
+        removed.extend(dedup_items(&mut metadata.video_formats));
+        removed.extend(dedup_items(&mut metadata.script_variants));
+        removed.extend(dedup_items(&mut metadata.subtitle_tracks));
+    }
+
+    history::record(&mut metadata, "rebuild");
+    let metadata_json = serde_json::to_string_pretty(&metadata)?;
+    rebuild_archive_with_json(path, archive, &metadata_json, vec![], vec![], None, progress)?;
+
+    Ok(removed)
 }
 
-/// Rebuild the FSV archive with updated metadata and added/removed files (metadata is assumed to already have added/removed the relevant entries)
-fn rebuild_archive(archive_path: &Path, mut archive: zip::ZipArchive<std::fs::File>, metadata: &FsvMetadata, add_files: Vec<AddFile>, remove_files: Vec<&str>) -> Result<(), FsvError> {
-    let temp_path = archive_path.with_extension("tmp");
-    let temp_file = std::fs::File::create(&temp_path)?;
-    let mut zip_writer = zip::ZipWriter::new(temp_file);
-    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Bzip2);
-    // Write updated metadata.json
-    let metadata_json = serde_json::to_string_pretty(metadata)?;
-    zip_writer.start_file("metadata.json", options)?;
-    zip_writer.write_all(metadata_json.as_bytes())?;
-    // Copy existing files, skipping removed files
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        let file_name = file.name();
-        if file_name == "metadata.json" || remove_files.contains(&file_name) {
-            continue; // skip metadata.json (already written) and removed files
+/// Keep the first item for each name and return the names of the removed ones.
+fn dedup_items<Item: WorkItem>(items: &mut Vec<Item>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut removed = Vec::new();
+    items.retain(|item| {
+        let name = item.get_name().trim().to_string();
+        if seen.insert(name.clone()) {
+            return true;
         }
-        zip_writer.start_file(file_name, options)?;
-        std::io::copy(&mut file, &mut zip_writer)?;
-    }
 
-    // Add new files
-    for file_path in add_files {
-        let mut file = std::fs::File::open(file_path.path)?;
-        zip_writer.start_file(file_path.name, options)?;
-        std::io::copy(&mut file, &mut zip_writer)?;
-    }
+        removed.push(name);
+        false
+    });
 
-    zip_writer.finish()?.flush()?;
-    drop(archive);
-    std::fs::rename(temp_path, archive_path)?;
+    removed
+}
 
-    Ok(())
+/// Which part of the primary video a preview is cut from.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Copy)]
+pub enum PreviewSelection {
+    Segment { start_ms: u64, duration_ms: u64 },
+    /// `count` clips of `segment_ms` spread over the whole video
+    Montage { count: u32, segment_ms: u64 },
 }
 
-fn open_fsv(path: &Path) -> Result<(zip::ZipArchive<std::fs::File>, FsvMetadata), FsvError> {
-    let file = std::fs::File::open(path)?;
-    let mut archive = zip::ZipArchive::new(file)?;
-    let metadata_json = {
-        let result = archive.by_name("metadata.json");
-        let mut metadata_file = match result {
-            Ok(file) => file,
-            Err(zip_err) => {
-                match zip_err {
-                    zip::result::ZipError::FileNotFound => {
-                        return Err(FsvError::MetadataFileNotFound);
-                    }
-                    _ => {
-                        return Err(FsvError::Zip(zip_err));
-                    }
+#[cfg(feature = "native")]
+#[derive(Debug, Error)]
+pub enum FsvPreviewError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ZIP archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Serde json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+    #[error("Transcode error: {0}")]
+    Transcode(#[from] TranscodeError),
+    #[error("Get video duration error: {0}")]
+    GetVideoDuration(#[from] file_util::GetDurationError),
+    #[error("No video found in archive to cut a preview from")]
+    NoVideo,
+    #[error("Preview name '{0}' is already used by another entry")]
+    NameInUse(String),
+}
+
+/// Cut a preview clip from a video in the archive (`source`, or the first video format present) and store it as `name`,
+/// recorded in the `previews` field of the `fsv.previews` extension. An existing preview with the same name is replaced.
+#[cfg(feature = "native")]
+pub fn create_preview(path: &Path, selection: PreviewSelection, source: Option<&str>, name: &str) -> Result<Preview, FsvPreviewError> {
+    let _lock = lock_fsv(path)?;
+    let (mut archive, mut metadata) = open_fsv(path)?;
+    let mut previews = preview::previews_from_metadata(&metadata)?;
+    let replaces_preview = previews.iter().any(|preview| preview.name == name);
+    let is_item = metadata.video_formats.iter().any(|format| format.name == name)
+        || metadata.script_variants.iter().any(|variant| variant.name == name)
+        || metadata.subtitle_tracks.iter().any(|track| track.name == name);
+    if is_item || name == "metadata.json" || (!replaces_preview && archive.index_for_name(name).is_some()) {
+        return Err(FsvPreviewError::NameInUse(name.to_string()));
+    }
+
+    let video_format = metadata.video_formats.iter()
+        .filter(|format| source.is_none_or(|source| format.name == source))
+        .find(|format| archive.index_for_name(&format.name).is_some())
+        .ok_or(FsvPreviewError::NoVideo)?;
+    let source_name = video_format.name.clone();
+    let mut video_duration = video_format.duration;
+
+    let work_dir = TranscodeWorkDir::new()?;
+    let extension = Path::new(&source_name).extension().and_then(|ext| ext.to_str()).unwrap_or("mp4");
+    let source_path = work_dir.path().join(format!("source.{}", extension));
+    {
+        let mut entry = archive.by_name(&source_name).entry_context("reading", &source_name).in_archive(path)?;
+        let mut file = File::create(&source_path).path_context("creating", &source_path)?;
+        std::io::copy(&mut entry, &mut file)?;
+    }
+
+    if video_duration.is_zero() {
+        video_duration = file_util::get_video_duration(&source_path)?;
+    }
+
+    let segments = match selection {
+        PreviewSelection::Segment { start_ms, duration_ms } => vec![PreviewSegment { start: start_ms, duration: duration_ms }],
+        PreviewSelection::Montage { count, segment_ms } => preview::montage_segments(video_duration.as_millis(), count, segment_ms),
+    };
+
+    let preview_path = work_dir.path().join("preview.mp4");
+    preview::cut_preview(&source_path, &preview_path, &segments)?;
+
+    let mut preview = Preview::new(name.to_string(), source_name, segments);
+    if let Some(i) = previews.iter().position(|existing| existing.name == name) {
+        // A recut preview keeps the fields other tools added to it
+        preview.extra = previews.remove(i).extra;
+    }
+    previews.push(preview.clone());
+    metadata.set_extension_data(extensions::PREVIEWS_EXTENSION, preview::PREVIEWS_FIELD, Some(&previews))?;
+
+    let remove_files = match replaces_preview {
+        true => vec![name],
+        false => vec![],
+    };
+    history::record(&mut metadata, &format!("add preview {}", name));
+    rebuild_archive(path, archive, &metadata, vec![AddFile::new(name, &preview_path)], remove_files)?;
+
+    Ok(preview)
+}
+
+#[derive(Debug, Error)]
+pub enum FsvNotesError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ZIP archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Serde json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+    #[error("Notes file '{0}' is not UTF-8 text")]
+    NotText(PathBuf),
+    #[error("Notes name '{0}' is already used by another entry")]
+    NameInUse(String),
+}
+
+/// Store the text file `notes_path` as the archive's notes under `name`, recorded in the `notes` field of the `fsv.notes`
+/// extension. Notes stored before, under any name, are replaced.
+pub fn add_fsv_notes(path: &Path, notes_path: &Path, name: &str) -> Result<(), FsvNotesError> {
+    let content = std::fs::read(notes_path).path_context("reading", notes_path)?;
+    if std::str::from_utf8(&content).is_err() {
+        return Err(FsvNotesError::NotText(notes_path.to_path_buf()));
+    }
+
+    let _lock = lock_fsv(path)?;
+    let (archive, mut metadata) = open_fsv(path)?;
+    let previous = notes::notes_from_metadata(&metadata)?;
+    let is_item = metadata.video_formats.iter().any(|format| format.name == name)
+        || metadata.script_variants.iter().any(|variant| variant.name == name)
+        || metadata.subtitle_tracks.iter().any(|track| track.name == name);
+    if is_item || name == "metadata.json" || (previous.as_deref() != Some(name) && archive.index_for_name(name).is_some()) {
+        return Err(FsvNotesError::NameInUse(name.to_string()));
+    }
+
+    notes::set_notes(&mut metadata, Some(name))?;
+    history::record(&mut metadata, &format!("add notes {}", name));
+    let remove_files = previous.iter().map(String::as_str).filter(|previous| archive.index_for_name(previous).is_some()).collect();
+    rebuild_archive(path, archive, &metadata, vec![AddFile::new(name, notes_path)], remove_files)?;
+
+    Ok(())
+}
+
+/// The text of the archive's notes (`fsv.notes`), `None` if it has none.
+pub fn read_fsv_notes(path: &Path) -> Result<Option<String>, FsvNotesError> {
+    let mut container = FsvContainer::open(path)?;
+    let Some(name) = notes::notes_from_metadata(container.cached_metadata()?)? else {
+        return Ok(None);
+    };
+
+    let entry_name = container.resolve_entry(&name, NameMatching::Normalized).unwrap_or(name);
+    let content = container.read_entry(&entry_name)?;
+
+    Ok(Some(String::from_utf8_lossy(&content).into_owned()))
+}
+
+#[derive(Debug, Error)]
+pub enum FsvDeriveError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ZIP archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Serde json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+    #[error("Script variant '{0}' not found in FSV")]
+    ScriptNotFound(String),
+    #[error("FSV has no script variants")]
+    NoScripts,
+    #[error("Script variant '{0}' has no actions")]
+    NoActions(String),
+    #[error("No changes requested")]
+    NothingToDo,
+    #[error("Invalid options: {0}")]
+    InvalidOptions(String),
+    #[error("Name '{0}' is already used by another entry")]
+    NameInUse(String),
+    #[error("Unsafe entry name: {0}")]
+    UnsafeName(String),
+}
+
+/// Store a copy of a script variant (`script`, or the first one listed), with its actions sorted and changed by `derive`,
+/// as a new variant named `name` (default `<stem>.<suffix>.funscript`) listed right after its source.
+/// `derive` returns the details noted in the new variant's description. Returns the name of the new variant.
+fn derive_script_variant(path: &Path, script: Option<&str>, name: Option<&str>, suffix: &str, derive: impl FnOnce(&mut Funscript) -> String) -> Result<String, FsvDeriveError> {
+    let _lock = lock_fsv(path)?;
+    let (mut archive, mut metadata) = open_fsv(path)?;
+    let source_index = match script {
+        Some(script) => metadata.script_variants.iter().position(|variant| variant.name == script).ok_or_else(|| FsvDeriveError::ScriptNotFound(script.to_string()))?,
+        None if metadata.script_variants.is_empty() => return Err(FsvDeriveError::NoScripts),
+        None => 0,
+    };
+    let source = &metadata.script_variants[source_index];
+    let (source_name, source_duration, source_start_offset) = (source.name.clone(), source.duration, source.start_offset);
+    let hash_algorithm = source.checksum.parse::<Checksum>().map(|checksum| checksum.algorithm).unwrap_or_default();
+
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => {
+            let stem = source_name.strip_suffix(&format!(".{}", import::SCRIPT_EXTENSION)).unwrap_or(&source_name);
+            format!("{}.{}.{}", stem, suffix, import::SCRIPT_EXTENSION)
+        },
+    };
+    if !is_safe_entry_name(&name) {
+        return Err(FsvDeriveError::UnsafeName(name));
+    }
+
+    let is_item = metadata.video_formats.iter().any(|format| format.name == name)
+        || metadata.script_variants.iter().any(|variant| variant.name == name)
+        || metadata.subtitle_tracks.iter().any(|track| track.name == name);
+    if is_item || name == "metadata.json" || archive.index_for_name(&name).is_some() {
+        return Err(FsvDeriveError::NameInUse(name));
+    }
+
+    let data = {
+        let mut entry = archive.by_name(&source_name).entry_context("reading", &source_name).in_archive(path)?;
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).entry_context("reading", &source_name).in_archive(path)?;
+        data
+    };
+    let mut funscript = serde_json::from_slice::<Funscript>(&data)?;
+    if funscript.actions.is_empty() {
+        return Err(FsvDeriveError::NoActions(source_name));
+    }
+
+    funscript.actions.sort_by_key(|action| action.at);
+    let details = derive(&mut funscript);
+
+    let work_dir = TranscodeWorkDir::new()?;
+    let script_path = work_dir.path().join(format!("{}.{}", suffix, import::SCRIPT_EXTENSION));
+    let script_data = serde_json::to_vec(&funscript)?;
+    std::fs::write(&script_path, &script_data)?;
+
+    let mut verb = suffix.to_string();
+    verb[..1].make_ascii_uppercase();
+    let description = format!("{} from {} ({})", verb, source_name, details);
+    let variant = ScriptVariant::new(name.clone(), description, vec![], source_duration, source_start_offset, get_file_checksum(&script_data, hash_algorithm));
+    metadata.script_variants.insert(source_index + 1, variant);
+    history::record(&mut metadata, &format!("add script {} ({} of {})", name, suffix, source_name));
+    rebuild_archive(path, archive, &metadata, vec![AddFile::new(&name, &script_path)], vec![])?;
+
+    Ok(name)
+}
+
+/// Write a thinned out copy of a script variant as a new variant (default `<stem>.simplified.funscript`), see `derive_script_variant`.
+pub fn simplify_script_variant(path: &Path, script: Option<&str>, options: &SimplifyOptions, name: Option<&str>) -> Result<String, FsvDeriveError> {
+    if options.is_empty() {
+        return Err(FsvDeriveError::NothingToDo);
+    }
+
+    derive_script_variant(path, script, name, "simplified", |funscript| {
+        let original_count = funscript.actions.len();
+        funscript.actions = options.apply(&funscript.actions);
+        info!(operation = "simplify", archive = %path.display(), outcome = "completed", "Simplified script from {} to {} actions", original_count, funscript.actions.len());
+        format!("{}: {} -> {} actions", options.describe(), original_count, funscript.actions.len())
+    })
+}
+
+/// Write an inverted, range limited and/or speed scaled copy of a script variant as a new variant
+/// (default `<stem>.transformed.funscript`), see `derive_script_variant`.
+pub fn transform_script_variant(path: &Path, script: Option<&str>, options: &TransformOptions, name: Option<&str>) -> Result<String, FsvDeriveError> {
+    if options.is_empty() {
+        return Err(FsvDeriveError::NothingToDo);
+    }
+
+    options.check().map_err(FsvDeriveError::InvalidOptions)?;
+    derive_script_variant(path, script, name, "transformed", |funscript| {
+        transform::transform(funscript, options);
+        options.describe()
+    })
+}
+
+#[cfg(feature = "native")]
+#[derive(Debug, Error)]
+pub enum FsvAlignError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ZIP archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Serde json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+    #[error("Transcode error: {0}")]
+    Transcode(#[from] TranscodeError),
+    #[error("Script variant '{0}' not found in FSV")]
+    ScriptNotFound(String),
+    #[error("FSV has no script variants")]
+    NoScripts,
+    #[error("Video format '{0}' not found in archive")]
+    VideoNotFound(String),
+    #[error("No video found in archive to align against")]
+    NoVideo,
+    #[error("Not enough signal to align '{script}' against '{video}' (silent audio, no scene cuts or no movement)")]
+    NoMatch { script: String, video: String },
+}
+
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Default)]
+pub struct AlignOptions {
+    /// Script variant to align (default: the first one listed)
+    pub script: Option<String>,
+    /// Video format to align against (default: the first one present in the archive)
+    pub video: Option<String>,
+    pub signal: AlignSignal,
+    /// Largest offset considered, either way
+    pub max_offset_ms: u64,
+    /// Only estimate the offset, leave the archive untouched
+    pub dry_run: bool,
+}
+
+#[cfg(feature = "native")]
+#[derive(Debug, Clone)]
+pub struct AlignReport {
+    pub script: String,
+    pub video: String,
+    pub previous_offset: i64,
+    pub estimate: AlignEstimate,
+}
+
+/// Estimate a script variant's `start_offset` against a video format (see `align::estimate_offset`) and store it, unless `dry_run` is set.
+#[cfg(feature = "native")]
+pub fn align_script_variant(path: &Path, options: &AlignOptions) -> Result<AlignReport, FsvAlignError> {
+    let _lock = lock_fsv(path)?;
+    let (mut archive, mut metadata) = open_fsv(path)?;
+    let script_index = match &options.script {
+        Some(script) => metadata.script_variants.iter().position(|variant| &variant.name == script).ok_or_else(|| FsvAlignError::ScriptNotFound(script.clone()))?,
+        None if metadata.script_variants.is_empty() => return Err(FsvAlignError::NoScripts),
+        None => 0,
+    };
+    let script_name = metadata.script_variants[script_index].name.clone();
+
+    let video_name = match &options.video {
+        Some(video) => metadata.video_formats.iter().find(|format| &format.name == video && archive.index_for_name(video).is_some()).ok_or_else(|| FsvAlignError::VideoNotFound(video.clone()))?,
+        None => metadata.video_formats.iter().find(|format| archive.index_for_name(&format.name).is_some()).ok_or(FsvAlignError::NoVideo)?,
+    }.name.clone();
+
+    let funscript = {
+        let mut entry = archive.by_name(&script_name).entry_context("reading", &script_name).in_archive(path)?;
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).entry_context("reading", &script_name).in_archive(path)?;
+        serde_json::from_slice::<Funscript>(&data)?
+    };
+
+    let work_dir = TranscodeWorkDir::new()?;
+    let extension = Path::new(&video_name).extension().and_then(|ext| ext.to_str()).unwrap_or("mp4");
+    let video_path = work_dir.path().join(format!("source.{}", extension));
+    {
+        let mut entry = archive.by_name(&video_name).entry_context("reading", &video_name).in_archive(path)?;
+        let mut file = File::create(&video_path).path_context("creating", &video_path)?;
+        std::io::copy(&mut entry, &mut file)?;
+    }
+
+    let estimate = align::estimate_offset(&video_path, &funscript, options.signal, options.max_offset_ms)?
+        .ok_or_else(|| FsvAlignError::NoMatch { script: script_name.clone(), video: video_name.clone() })?;
+    if estimate.score < align::WEAK_MATCH_SCORE {
+        warn!(operation = "align", archive = %path.display(), item = %script_name, video = %video_name, score = estimate.score, "Weak match; check the offset before relying on it");
+    }
+
+    let variant = &mut metadata.script_variants[script_index];
+    let previous_offset = variant.start_offset;
+    if !options.dry_run && previous_offset != estimate.offset_ms {
+        variant.start_offset = estimate.offset_ms;
+        history::record(&mut metadata, &format!("align script {} to {}", script_name, video_name));
+        rebuild_archive(path, archive, &metadata, vec![], vec![])?;
+    }
+
+    Ok(AlignReport { script: script_name, video: video_name, previous_offset, estimate })
+}
+
+/// Rewrite metadata.json in canonical form (see `FsvMetadata::to_canonical_json`). Returns false if it already was canonical.
+pub fn normalize_fsv_metadata(path: &Path) -> Result<bool, FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let (mut archive, mut metadata) = open_fsv(path)?;
+    let canonical_json = metadata.to_canonical_json()?;
+    let current_json = {
+        let mut metadata_file = archive.by_name("metadata.json").entry_context("reading", "metadata.json").in_archive(path)?;
+        let mut current_json = String::new();
+        metadata_file.read_to_string(&mut current_json).entry_context("reading", "metadata.json").in_archive(path)?;
+
+        current_json
+    };
+
+    if current_json == canonical_json {
+        return Ok(false);
+    }
+
+    history::record(&mut metadata, "normalize metadata");
+    let canonical_json = metadata.to_canonical_json()?;
+    rebuild_archive_with_json(path, archive, &canonical_json, vec![], vec![], None, &NoProgress)?;
+
+    Ok(true)
+}
+
+/// Replace metadata.json with `metadata_json`, e.g. after editing it by hand. The archive is validated with the new metadata first
+/// and left as it is unless that passes, or only finds content missing with `allow_content_incomplete`. Returns the validation report,
+/// whose warnings still apply, or `None` if the metadata is unchanged.
+pub fn put_fsv_metadata(path: &Path, metadata_json: &str, allow_content_incomplete: bool) -> Result<Option<ValidationReport>, FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let file = File::open(path).path_context("opening", path)?;
+    let (mut archive, structure) = ArchiveStructure::read(file).path_context("reading", path)?;
+    let index = EntryIndex::new(archive.file_names());
+    let report = validate_archive_with_json(&mut archive, &structure, &index, metadata_json, &ValidateOptions { parse_mode: ParseMode::Warn, ..ValidateOptions::default() })?;
+    match report.state {
+        FsvState::Valid => (),
+        FsvState::ContentIncomplete(_) if allow_content_incomplete => (),
+        _ => return Err(FsvEditError::MetadataRejected(Box::new(report))),
+    }
+
+    let mut metadata: FsvMetadata = serde_json::from_str(metadata_json)?;
+    if read_archive_metadata(&mut archive)?.to_canonical_json()? == metadata.to_canonical_json()? {
+        return Ok(None);
+    }
+
+    history::record(&mut metadata, "put metadata");
+    rebuild_archive(path, archive, &metadata, vec![], vec![])?;
+
+    Ok(Some(report))
+}
+
+/// Compute BLAKE3 hashes of every entry except metadata.json, streaming them from the archive.
+/// Entries with a digest in `cache` (loaded for BLAKE3) are not read again.
+pub fn hash_fsv(path: &Path, cache: Option<&mut EntryHashCache>) -> Result<ContentHashes, FsvError> {
+    let (mut archive, _) = open_fsv(path)?;
+    compute_content_hashes(&mut archive, cache)
+}
+
+fn compute_content_hashes<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, mut cache: Option<&mut EntryHashCache>) -> Result<ContentHashes, FsvError> {
+    let mut entries = std::collections::BTreeMap::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        if entry.name() == "metadata.json" {
+            continue;
+        }
+
+        let name = entry.name().to_string();
+        let hash = match cache.as_deref_mut() {
+            Some(cache) if cache.algorithm() == HashAlgorithm::Blake3 => cache.digest(&name, entry.crc32(), entry.size(), &mut entry)?,
+            _ => content_hash::hash_reader(&mut entry)?,
+        };
+        entries.insert(name, hash);
+    }
+
+    Ok(ContentHashes::new(entries))
+}
+
+/// Store freshly computed content hashes in metadata and declare the `fsv.content-hashes` extension.
+pub fn store_content_hashes(path: &Path, cache: Option<&mut EntryHashCache>) -> Result<ContentHashes, FsvEditError> {
+    let _lock = lock_fsv(path)?;
+    let (mut archive, mut metadata) = open_fsv(path)?;
+    let hashes = compute_content_hashes(&mut archive, cache)?;
+    metadata.extra.insert(content_hash::CONTENT_HASHES_FIELD.to_string(), serde_json::to_value(&hashes)?);
+    if !metadata.extensions.iter().any(|id| id == extensions::CONTENT_HASHES_EXTENSION) {
+        metadata.extensions.push(extensions::CONTENT_HASHES_EXTENSION.to_string());
+    }
+
+    history::record(&mut metadata, "store content hashes");
+    rebuild_archive(path, archive, &metadata, vec![], vec![])?;
+
+    Ok(hashes)
+}
+
+/// Compare the stored content hashes against the archive. Returns `None` if the FSV has no stored hashes.
+pub fn verify_content_hashes(path: &Path, cache: Option<&mut EntryHashCache>) -> Result<Option<HashVerification>, FsvError> {
+    let (mut archive, metadata) = open_fsv(path)?;
+    let stored = match ContentHashes::from_metadata(&metadata) {
+        Some(stored) => stored?,
+        None => return Ok(None),
+    };
+
+    let computed = compute_content_hashes(&mut archive, cache)?;
+
+    Ok(Some(HashVerification::compare(&stored, &computed)))
+}
+
+#[derive(Debug, Serialize)]
+pub struct FsvInfo {
+    // Define fields to hold information about the FSV file
+    pub title: String,
+    pub performers: Vec<String>,
+    pub studio: String,
+    pub videos: Vec<(String, bool)>, // (filename, is_present)
+    pub scripts: Vec<(String, bool)>, // (filename, is_present)
+    pub subtitles: Vec<(String, bool)>, // (filename, is_present)
+    /// Notes document declared by `fsv.notes`, and whether it is present
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<(String, bool)>,
+    pub extra_files: Vec<String>,
+    pub name_mismatches: Vec<(String, String)>, // (metadata name, archive entry name)
+    pub extensions: Vec<ExtensionReport>,
+    /// Video formats kept outside the archive, see `fsv.external-content`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub external_content: Vec<ExternalContent>,
+    /// Titles in other languages by language tag, see `fsv.localized-titles`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub localized_titles: BTreeMap<String, String>,
+    /// Every creator credited by the metadata, once each
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub creators: Vec<CreatorSocials>,
+    /// Only filled in with `InfoOptions::full`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<FsvDetails>,
+    /// Only filled in with `InfoOptions::sizes`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sizes: Option<ArchiveSizes>,
+}
+
+impl FsvInfo {
+    fn new(title: String, videos: Vec<(String, bool)>, scripts: Vec<(String, bool)>, subtitles: Vec<(String, bool)>, extra_files: Vec<String>, name_mismatches: Vec<(String, String)>, extensions: Vec<ExtensionReport>) -> Self {
+        FsvInfo { title, performers: Vec::new(), studio: String::new(), videos, scripts, subtitles, notes: None, extra_files, name_mismatches, extensions, external_content: Vec::new(), localized_titles: BTreeMap::new(), creators: Vec::new(), details: None, sizes: None }
+    }
+}
+
+/// A creator's name and socials, normalized and labelled with the platform they link to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CreatorSocials {
+    pub name: String,
+    pub socials: Vec<Social>,
+}
+
+impl CreatorSocials {
+    fn from_metadata(metadata: &FsvMetadata) -> Vec<CreatorSocials> {
+        let mut creators: Vec<CreatorSocials> = Vec::new();
+        let works = metadata.creators.videos.iter().chain(&metadata.creators.scripts).chain(&metadata.creators.subtitles);
+        for info in works.map(|work| &work.creator_info) {
+            let socials = socials::normalize_socials(&info.socials).iter().map(|social| Social::parse(social)).collect();
+            let creator = CreatorSocials { name: info.name.trim().to_string(), socials };
+            if !creators.contains(&creator) {
+                creators.push(creator);
+            }
+        }
+
+        creators
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FsvDetails {
+    pub format_version: Version,
+    pub tags: Vec<String>,
+    pub entries: Vec<EntryDetails>,
+}
+
+/// Detailed information about one archive entry. `item_type` is `None` for entries not referenced by the metadata.
+#[derive(Debug, Serialize)]
+pub struct EntryDetails {
+    pub name: String,
+    pub item_type: Option<ItemType>,
+    pub present: bool,
+    pub creators: Vec<String>,
+    pub duration: Option<DurationMs>,
+    pub compressed_size: Option<u64>,
+    pub uncompressed_size: Option<u64>,
+    pub checksum: ChecksumStatus,
+}
+
+/// Stored and uncompressed size of one archive entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EntrySize {
+    pub name: String,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+}
+
+/// Sizes of every archive entry (metadata.json included) and their totals, in archive order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ArchiveSizes {
+    pub entries: Vec<EntrySize>,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+}
+
+/// Compressed size as a percentage of the uncompressed size, 100 for empty entries.
+pub fn compression_ratio(compressed_size: u64, uncompressed_size: u64) -> f64 {
+    match uncompressed_size {
+        0 => 100.0,
+        size => compressed_size as f64 * 100.0 / size as f64,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumStatus {
+    Match,
+    Mismatch,
+    /// The metadata has no checksum for the entry
+    Missing,
+    /// The checksum uses an algorithm this tool cannot verify
+    Unsupported,
+    /// The checksum uses a compromised algorithm (e.g. sha1) and is ignored, per the spec
+    Deprecated,
+    /// The checksum is not in `algorithm:digest` form or the digest has the wrong length
+    Malformed,
+    /// The entry is missing from the archive or could not be read
+    NotChecked,
+}
+
+impl ChecksumStatus {
+    pub fn get_name(&self) -> &str {
+        match self {
+            ChecksumStatus::Match => "OK",
+            ChecksumStatus::Mismatch => "MISMATCH",
+            ChecksumStatus::Missing => "none",
+            ChecksumStatus::Unsupported => "unsupported algorithm",
+            ChecksumStatus::Deprecated => "deprecated algorithm, ignored",
+            ChecksumStatus::Malformed => "malformed",
+            ChecksumStatus::NotChecked => "not checked",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InfoOptions {
+    pub name_matching: NameMatching,
+    /// Include format version, tags, and per-entry creators, durations, sizes, and checksum status.
+    /// Verifying checksums reads every referenced entry in full.
+    pub full: bool,
+    /// Include the compressed and uncompressed size of every entry. Only the central directory is read.
+    pub sizes: bool,
+}
+
+pub fn get_fsv_info(path: &Path) -> Result<FsvInfo, FsvError> {
+    get_fsv_info_with_options(path, &InfoOptions::default())
+}
+
+/// Entries that only match after name normalization are listed in `name_mismatches`, and count as present only with `NameMatching::Normalized`.
+pub fn get_fsv_info_with_options(path: &Path, options: &InfoOptions) -> Result<FsvInfo, FsvError> {
+    let mut container = FsvContainer::open(path)?;
+    let mut info = container.info(options)?;
+    if info.title.trim().is_empty() {
+        info.title = path.file_stem()
+            .and_then(|os_str| os_str.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+    }
+
+    Ok(info)
+}
+
+fn archive_info<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, index: &EntryIndex, metadata: &FsvMetadata, options: &InfoOptions) -> Result<FsvInfo, FsvError> {
+    let name_matching = options.name_matching;
+    let title = metadata.title.to_string();
+
+    let mut seen_files = HashSet::new();
+    let mut name_mismatches = Vec::new();
+    let mut check_presence = |name: &str| {
+        seen_files.insert(name.to_string());
+        match index.lookup(name) {
+            EntryLookup::Exact => true,
+            EntryLookup::Decoded(actual) => {
+                seen_files.insert(actual);
+                true
+            },
+            EntryLookup::Normalized(actual) => {
+                let is_present = name_matching == NameMatching::Normalized;
+                if is_present {
+                    seen_files.insert(actual.clone());
+                }
+                name_mismatches.push((name.to_string(), actual));
+                is_present
+            },
+            EntryLookup::Missing => false,
+        }
+    };
+
+    let mut videos = Vec::new();
+    for video in &metadata.video_formats {
+        videos.push((video.name.to_string(), check_presence(&video.name)));
+    }
+
+    let mut scripts = Vec::new();
+    for variant in &metadata.script_variants {
+        scripts.push((variant.name.to_string(), check_presence(&variant.name)));
+    }
+
+    let mut subtitles = Vec::new();
+    for track in &metadata.subtitle_tracks {
+        subtitles.push((track.name.to_string(), check_presence(&track.name)));
+    }
+
+    let notes = notes::notes_from_metadata(metadata).ok().flatten().map(|name| {
+        let is_present = check_presence(&name);
+        (name, is_present)
+    });
+
+    let mut extra_files = Vec::new();
+    for file_name in archive.file_names() {
+        if !seen_files.contains(file_name) {
+            extra_files.push(file_name.to_string());
+        }
+    }
+    
+    let entry_names: Vec<String> = archive.file_names().map(|name| entry_name::decode_entry_name(name).into_owned()).collect();
+    let entry_names: Vec<&str> = entry_names.iter().map(String::as_str).collect();
+    let extensions = extensions::check_extensions(metadata, &entry_names);
+
+    let details = match options.full {
+        true => Some(archive_details(archive, metadata, index, name_matching, &extra_files)?),
+        false => None,
+    };
+
+    let mut info = FsvInfo::new(title, videos, scripts, subtitles, extra_files, name_mismatches, extensions);
+    info.performers = metadata.performers.clone();
+    info.studio = metadata.studio.clone();
+    info.external_content = external::external_content_from_metadata(metadata).unwrap_or_default();
+    info.localized_titles = titles::localized_titles_from_metadata(metadata).unwrap_or_default();
+    info.creators = CreatorSocials::from_metadata(metadata);
+    info.notes = notes;
+    info.details = details;
+    if options.sizes {
+        info.sizes = Some(archive_sizes(archive)?);
+    }
+
+    Ok(info)
+}
+
+fn archive_sizes<R: Read + Seek>(archive: &mut zip::ZipArchive<R>) -> Result<ArchiveSizes, FsvError> {
+    let mut sizes = ArchiveSizes::default();
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+
+        sizes.compressed_size += entry.compressed_size();
+        sizes.uncompressed_size += entry.size();
+        sizes.entries.push(EntrySize { name: entry.name().to_string(), compressed_size: entry.compressed_size(), uncompressed_size: entry.size() });
+    }
+
+    Ok(sizes)
+}
+
+fn archive_details<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, metadata: &FsvMetadata, index: &EntryIndex, name_matching: NameMatching, extra_files: &[String]) -> Result<FsvDetails, FsvError> {
+    let creators_of = |works: &Vec<WorkCreatorsMetadata>, name: &str| -> Vec<String> {
+        works.iter().filter(|work| work.work_name == name).map(|work| work.creator_info.name.clone()).collect()
+    };
+
+    let item_details = |item_type: ItemType, name: &str, duration: Option<DurationMs>, creators: Vec<String>| EntryDetails {
+        name: name.to_string(),
+        item_type: Some(item_type),
+        present: false,
+        creators,
+        duration,
+        compressed_size: None,
+        uncompressed_size: None,
+        checksum: ChecksumStatus::NotChecked,
+    };
+
+    // (details, checksum from metadata)
+    let mut items = Vec::new();
+    for video in &metadata.video_formats {
+        items.push((item_details(ItemType::Video, &video.name, Some(video.duration), creators_of(&metadata.creators.videos, &video.name)), &video.checksum));
+    }
+
+    for variant in &metadata.script_variants {
+        items.push((item_details(ItemType::Script, &variant.name, Some(variant.duration), creators_of(&metadata.creators.scripts, &variant.name)), &variant.checksum));
+    }
+
+    for track in &metadata.subtitle_tracks {
+        items.push((item_details(ItemType::Subtitle, &track.name, None, creators_of(&metadata.creators.subtitles, &track.name)), &track.checksum));
+    }
+
+    let mut entries = Vec::new();
+    for (mut details, checksum) in items {
+        if let Some(entry_name) = index.resolve(&details.name, name_matching) && let Ok(mut entry) = archive.by_name(&entry_name) {
+            details.present = true;
+            details.compressed_size = Some(entry.compressed_size());
+            details.uncompressed_size = Some(entry.size());
+            details.checksum = checksum_status(checksum, &mut entry);
+        }
+        else if checksum.trim().is_empty() {
+            details.checksum = ChecksumStatus::Missing;
+        }
+
+        entries.push(details);
+    }
+
+    for name in extra_files {
+        let entry = archive.by_name(name)?;
+        entries.push(EntryDetails {
+            name: name.clone(),
+            item_type: None,
+            present: true,
+            creators: Vec::new(),
+            duration: None,
+            compressed_size: Some(entry.compressed_size()),
+            uncompressed_size: Some(entry.size()),
+            checksum: ChecksumStatus::Missing,
+        });
+    }
+
+    Ok(FsvDetails { format_version: metadata.format_version.clone(), tags: metadata.tags.clone(), entries })
+}
+
+fn checksum_status(checksum: &str, reader: &mut impl Read) -> ChecksumStatus {
+    let checksum = checksum.trim();
+    if checksum.is_empty() {
+        return ChecksumStatus::Missing;
+    }
+
+    let checksum = match checksum.parse::<Checksum>() {
+        Ok(checksum) if checksum.algorithm.is_deprecated() => return ChecksumStatus::Deprecated,
+        Ok(checksum) => checksum,
+        Err(ParseChecksumError::UnsupportedAlgorithm(_)) => return ChecksumStatus::Unsupported,
+        Err(_) => return ChecksumStatus::Malformed,
+    };
+
+    let mut data = Vec::new();
+    if reader.read_to_end(&mut data).is_err() {
+        return ChecksumStatus::NotChecked;
+    }
+
+    match checksum.matches(&data) {
+        true => ChecksumStatus::Match,
+        false => ChecksumStatus::Mismatch,
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum FsvError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ZIP archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("JSON deserialization error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("Database client error: {0}")]
+    #[cfg(feature = "native")]
+    DbClient(#[from] db_client::DbClientError),
+    #[error("Metadata file not found in FSV archive")]
+    MetadataFileNotFound,
+    #[error("Creator info not found for key: {0}")]
+    CreatorInfoNotFound(String),
+    #[error("Creator key '{0}' can't be looked up without the creator database; pass the creator's details instead or fix the database")]
+    NoCreatorDatabase(String),
+    #[error("Archive '{0}' is locked by another process")]
+    Locked(PathBuf),
+    #[error("Journal error: {0}")]
+    Journal(#[from] JournalError),
+}
+
+#[derive(Debug)]
+pub struct AddFile<'a> {
+    pub name: &'a str,
+    pub path: &'a Path,
+}
+
+impl<'a> AddFile<'a> {
+    pub fn new(name: &'a str, path: &'a Path) -> Self {
+        AddFile { name, path }
+    }
+}
+
+/// Files compressed into a staging archive next to the FSV while they are hashed, so adding a file reads it only once.
+/// metadata.json comes first in an FSV and holds the checksums, so files can't be hashed while they are written there;
+/// their staged entries are copied over as they are instead, without compressing them again.
+#[cfg_attr(not(feature = "native"), allow(dead_code))]
+struct StagedFiles {
+    path: PathBuf,
+    options: SimpleFileOptions,
+    password: Option<String>,
+    writer: Option<zip::ZipWriter<File>>,
+    archive: Option<zip::ZipArchive<File>>,
+    names: HashSet<String>,
+}
+
+#[cfg_attr(not(feature = "native"), allow(dead_code))]
+impl StagedFiles {
+    /// Stage into `<archive_path>.stage`, compressing with `options` as the entries of the archive being written.
+    fn new(archive_path: &Path, options: SimpleFileOptions) -> Self {
+        StagedFiles { path: archive_path.with_extension("stage"), options, password: None, writer: None, archive: None, names: HashSet::new() }
+    }
+
+    /// Encrypt the staged entries under `password`, as the entries of the archive being written.
+    fn password(mut self, password: Option<String>) -> Self {
+        self.password = password;
+        self
+    }
+
+    /// Compress the file at `path` as entry `name` and return its checksum.
+    fn stage(&mut self, name: &str, path: &Path, algorithm: HashAlgorithm) -> Result<Checksum, FsvError> {
+        debug_assert!(self.archive.is_none(), "files can't be staged once staged entries are copied");
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => {
+                // Read back once the entries are copied, so not File::create
+                let file = File::options().read(true).write(true).create(true).truncate(true).open(&self.path).path_context("creating", &self.path)?;
+                self.writer.insert(zip::ZipWriter::new(file))
+            },
+        };
+
+        let mut reader = HashingReader::new(File::open(path).path_context("opening", path)?, algorithm);
+        writer.start_file(name, encrypted_options(self.options, self.password.as_deref()))?;
+        std::io::copy(&mut reader, writer).path_context("staging", path)?;
+        self.names.insert(name.to_string());
+
+        Ok(reader.finish())
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    /// Copy the staged entry `name` into `zip_writer`, still compressed (and encrypted).
+    fn copy_to<W: Write + Seek>(&mut self, name: &str, zip_writer: &mut zip::ZipWriter<W>) -> Result<(), FsvError> {
+        if let Some(writer) = self.writer.take() {
+            self.archive = Some(zip::ZipArchive::new(writer.finish()?)?);
+        }
+
+        let archive = self.archive.as_mut().ok_or(zip::result::ZipError::FileNotFound)?;
+        let index = archive.index_for_name(name).ok_or(zip::result::ZipError::FileNotFound)?;
+        zip_writer.raw_copy_file(archive.by_index_raw(index)?)?;
+
+        Ok(())
+    }
+}
+
+impl Drop for StagedFiles {
+    fn drop(&mut self) {
+        if self.names.is_empty() {
+            return;
+        }
+
+        // The staging archive has to be closed before it can be removed (on Windows)
+        self.writer = None;
+        self.archive = None;
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            warn!(operation = "stage", item = %self.path.display(), outcome = "cleanup_failed", error = %err, "Unable to remove staging archive");
+        }
+    }
+}
+
+/// Checksum of the file at `path`, added to an archive as `name`: the cached one, or else one computed while the file is compressed
+/// into `staged`, so it is read only once. Without a `db_client` the cache is neither read nor updated.
+#[cfg(feature = "native")]
+async fn checksum_for_add(db_client: Option<&DbClient>, name: &str, path: &Path, algorithm: HashAlgorithm, staged: &mut StagedFiles) -> Result<String, FsvError> {
+    let Some(db_client) = db_client else {
+        return Ok(staged.stage(name, path, algorithm)?.to_string());
+    };
+
+    // Taken before reading, so a file changing while it is staged isn't cached under its new mtime
+    let file_metadata = std::fs::metadata(path).path_context("reading", path)?;
+    if let Some(checksum) = hash_cache::cached_file_checksum(db_client, path, &file_metadata, algorithm).await {
+        return Ok(checksum.to_string());
+    }
+
+    let checksum = staged.stage(name, path, algorithm)?;
+    hash_cache::remember_file_checksum(db_client, path, &file_metadata, &checksum).await;
+
+    Ok(checksum.to_string())
+}
+
+/// Where the content of an entry being written comes from.
+#[cfg_attr(not(feature = "native"), allow(dead_code))]
+enum EntrySource<'a> {
+    Reader(Box<dyn Read + 'a>),
+    /// Already compressed in the staging archive
+    Staged,
+}
+
+#[cfg(feature = "native")]
+fn build_archive(file: File, metadata: &FsvMetadata, add_files: Vec<AddFile>, staged: &mut StagedFiles, reproducible: bool, compression: ArchiveCompression, password: Option<&str>) -> Result<(), FsvError> {
+    let mut entries = Vec::new();
+    for file_path in add_files {
+        let source = match staged.contains(file_path.name) {
+            true => EntrySource::Staged,
+            false => EntrySource::Reader(Box::new(std::fs::File::open(file_path.path).path_context("opening", file_path.path)?)),
+        };
+        entries.push((file_path.name.to_string(), source));
+    }
+
+    write_archive(file, metadata, entries, Some(staged), reproducible, compression, password)?.flush()?;
+
+    Ok(())
+}
+
+/// Options of the entries `write_archive` writes. Files staged for it have to be compressed with these.
+fn entry_options(reproducible: bool, compression: ArchiveCompression) -> SimpleFileOptions {
+    let options = SimpleFileOptions::default().compression_method(compression.method());
+    match reproducible {
+        true => options.last_modified_time(zip::DateTime::default()).unix_permissions(0o644),
+        false => options,
+    }
+}
+
+/// `options` encrypting the entry with AES-256 under `password`, if there is one. Only native builds have the cipher.
+fn encrypted_options(options: SimpleFileOptions, password: Option<&str>) -> zip::write::FileOptions<'_, ()> {
+    match password {
+        #[cfg(feature = "native")]
+        Some(password) => options.with_aes_encryption(zip::AesMode::Aes256, password),
+        _ => options,
+    }
+}
+
+/// With `reproducible`, entry timestamps and permissions are fixed, entries are written in name order,
+/// and metadata keys are sorted, so identical inputs yield identical bytes.
+/// With a `password`, every entry but metadata.json is encrypted.
+fn write_archive<W: Write + Seek>(writer: W, metadata: &FsvMetadata, mut entries: Vec<(String, EntrySource<'_>)>, mut staged: Option<&mut StagedFiles>, reproducible: bool, compression: ArchiveCompression, password: Option<&str>) -> Result<W, FsvError> {
+    let mut zip_writer = zip::ZipWriter::new(writer);
+    zip_writer.set_comment(magic::archive_comment(&metadata.format_version));
+    let options = entry_options(reproducible, compression);
+    let metadata_json = if reproducible {
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        metadata.to_canonical_json()?
+    }
+    else {
+        serde_json::to_string_pretty(metadata)?
+    };
+
+    // Write metadata first
+    zip_writer.start_file("metadata.json", options)?;
+    zip_writer.write_all(metadata_json.as_bytes())?;
+
+    for (name, source) in entries {
+        match (source, staged.as_deref_mut()) {
+            (EntrySource::Reader(mut reader), _) => {
+                zip_writer.start_file(name, encrypted_options(options, password))?;
+                std::io::copy(&mut reader, &mut zip_writer)?;
+            },
+            (EntrySource::Staged, Some(staged)) => staged.copy_to(&name, &mut zip_writer)?,
+            (EntrySource::Staged, None) => return Err(FsvError::Zip(zip::result::ZipError::FileNotFound)),
+        }
+    }
+
+    Ok(zip_writer.finish()?)
+}
+
+/// Assembles an FSV from in-memory data or arbitrary readers and writes it to any `Write + Seek` target,
+/// so FSVs can be produced without touching the filesystem.
+/// Checksums are computed from the given data. Durations are taken as-is since they cannot be probed from raw bytes.
+pub struct FsvBuilder<'a> {
+    metadata: FsvMetadata,
+    entries: Vec<(String, Box<dyn Read + 'a>)>,
+    reproducible: bool,
+    hash_algorithm: HashAlgorithm,
+    compression: ArchiveCompression,
+}
+
+impl<'a> FsvBuilder<'a> {
+    pub fn new(title: &str) -> Self {
+        let mut metadata = FsvMetadata::new(LATEST_FSV_FORMAT_VERSION);
+        metadata.title = title.to_string();
+        FsvBuilder::from_metadata(metadata)
+    }
+
+    /// Start from existing metadata. Entries it references still have to be added with `entry`.
+    pub fn from_metadata(metadata: FsvMetadata) -> Self {
+        FsvBuilder { metadata, entries: Vec::new(), reproducible: false, hash_algorithm: HashAlgorithm::default(), compression: ArchiveCompression::default() }
+    }
+
+    pub fn tags(mut self, tags: Vec<String>) -> Self {
+        self.metadata.tags = tags;
+        self
+    }
+
+    pub fn video(mut self, name: &str, data: &'a [u8], duration_ms: u64) -> Self {
+        self.metadata.add_video_format(VideoFormat::new(name.to_string(), String::new(), DurationMs::from_millis(duration_ms), get_file_checksum(data, self.hash_algorithm)));
+        self.entry(name, data)
+    }
+
+    pub fn script(mut self, name: &str, data: &'a [u8], duration_ms: u64) -> Self {
+        self.metadata.add_script_variant(ScriptVariant::new(name.to_string(), String::new(), vec![], DurationMs::from_millis(duration_ms), 0, get_file_checksum(data, self.hash_algorithm)));
+        self.entry(name, data)
+    }
+
+    pub fn subtitle(mut self, name: &str, language: &str, data: &'a [u8]) -> Self {
+        self.metadata.add_subtitle_track(SubtitleTrack::new(name.to_string(), language.to_string(), String::new(), get_file_checksum(data, self.hash_algorithm)));
+        self.entry(name, data)
+    }
+
+    /// Algorithm for checksums of items added after this call.
+    pub fn hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = hash_algorithm;
+        self
+    }
+
+    /// Add a raw archive entry without touching the metadata.
+    pub fn entry(mut self, name: &str, reader: impl Read + 'a) -> Self {
+        self.entries.push((name.to_string(), Box::new(reader)));
+        self
+    }
+
+    /// Fix entry timestamps, write entries in name order and sort metadata keys, so identical inputs yield identical bytes.
+    pub fn reproducible(mut self, reproducible: bool) -> Self {
+        self.reproducible = reproducible;
+        self
+    }
+
+    pub fn compression(mut self, compression: ArchiveCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut FsvMetadata {
+        &mut self.metadata
+    }
+
+    /// Write the archive and return the writer.
+    pub fn write<W: Write + Seek>(self, writer: W) -> Result<W, FsvError> {
+        let entries = self.entries.into_iter().map(|(name, reader)| (name, EntrySource::Reader(reader))).collect();
+        write_archive(writer, &self.metadata, entries, None, self.reproducible, self.compression, None)
+    }
+
+    pub fn to_bytes(self) -> Result<Vec<u8>, FsvError> {
+        Ok(self.write(std::io::Cursor::new(Vec::new()))?.into_inner())
+    }
+}
+
+/// Rebuild the FSV archive with updated metadata and added/removed files (metadata is assumed to already have added/removed the relevant entries)
+fn rebuild_archive<R: Read + Seek>(archive_path: &Path, archive: zip::ZipArchive<R>, metadata: &FsvMetadata, add_files: Vec<AddFile>, remove_files: Vec<&str>) -> Result<(), FsvError> {
+    let metadata_json = serde_json::to_string_pretty(metadata)?;
+    rebuild_archive_with_json(archive_path, archive, &metadata_json, add_files, remove_files, None, &NoProgress)
+}
+
+/// Same as `rebuild_archive`, but the added files staged in `staged` are copied from there.
+#[cfg(feature = "native")]
+fn rebuild_archive_staged<R: Read + Seek>(archive_path: &Path, archive: zip::ZipArchive<R>, metadata: &FsvMetadata, add_files: Vec<AddFile>, remove_files: Vec<&str>, staged: &mut StagedFiles) -> Result<(), FsvError> {
+    let metadata_json = serde_json::to_string_pretty(metadata)?;
+    rebuild_archive_with_json(archive_path, archive, &metadata_json, add_files, remove_files, Some(staged), &NoProgress)
+}
+
+/// Options of the entries a rebuild adds. Files staged for it have to be compressed with these.
+fn rebuild_entry_options() -> SimpleFileOptions {
+    SimpleFileOptions::default().compression_method(zip::CompressionMethod::Bzip2)
+}
+
+/// Same as `rebuild_archive`, but with metadata.json already serialized (e.g. in canonical form).
+/// Reports a `rebuild` progress event for every entry written. The archive is written to a temporary file that then replaces it,
+/// under a journal so an interrupted rebuild can be cleaned up by `recover`.
+fn rebuild_archive_with_json<R: Read + Seek>(archive_path: &Path, archive: zip::ZipArchive<R>, metadata_json: &str, add_files: Vec<AddFile>, remove_files: Vec<&str>, staged: Option<&mut StagedFiles>, progress: &dyn ProgressListener) -> Result<(), FsvError> {
+    let temp_path = archive_path.with_extension("tmp");
+    let journal = Journal::begin(archive_path, JournalOperation::Rebuild { temp: temp_path.clone() })?;
+    let result = write_rebuilt_archive(archive_path, &temp_path, archive, metadata_json, add_files, remove_files, staged, progress)
+        .and_then(|()| Ok(std::fs::rename(&temp_path, archive_path).path_context("replacing", archive_path)?));
+    if let Err(err) = result {
+        if let Err(abort_err) = journal.abort() {
+            error!(operation = "rebuild", archive = %archive_path.display(), outcome = "cleanup_failed", error = %abort_err, "Error removing incomplete rebuild");
+        }
+
+        return Err(err);
+    }
+
+    journal.finish()?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_rebuilt_archive<R: Read + Seek>(archive_path: &Path, temp_path: &Path, mut archive: zip::ZipArchive<R>, metadata_json: &str, add_files: Vec<AddFile>, remove_files: Vec<&str>, mut staged: Option<&mut StagedFiles>, progress: &dyn ProgressListener) -> Result<(), FsvError> {
+    let target = archive_path.display().to_string();
+    let total = archive.len() + add_files.len();
+    let temp_file = std::fs::File::create(temp_path).path_context("creating", temp_path)?;
+    let mut zip_writer = zip::ZipWriter::new(temp_file);
+    let options = rebuild_entry_options();
+    // The marker follows the metadata's format version; metadata without a readable one gets no marker
+    let format_version = serde_json::from_str::<serde_json::Value>(metadata_json).ok()
+        .and_then(|value| value.get("format_version").and_then(|version| version.as_str()).and_then(|version| Version::parse(version).ok()));
+    if let Some(format_version) = format_version {
+        zip_writer.set_comment(magic::archive_comment(&format_version));
+    }
+    // Write updated metadata.json
+    zip_writer.start_file("metadata.json", options)?;
+    zip_writer.write_all(metadata_json.as_bytes())?;
+    // Copy existing files, skipping removed files
+    for i in 0..archive.len() {
+        progress.on_event(ProgressEvent::progress("rebuild", &target, i, total));
+        let raw_file = archive.by_index_raw(i).path_context("reading", archive_path)?;
+        // Names stored as UTF-8 without the flag are written back with it, so other readers see them right too
+        let file_name = entry_name::decode_entry_name(raw_file.name()).into_owned();
+        if file_name == "metadata.json" || remove_files.contains(&file_name.as_str()) || remove_files.contains(&raw_file.name()) {
+            continue; // skip metadata.json (already written) and removed files
+        }
+        // Encrypted entries can't be read without the password, so they are copied as they are
+        if raw_file.encrypted() {
+            zip_writer.raw_copy_file_rename(raw_file, &file_name)?;
+            continue;
+        }
+        drop(raw_file);
+
+        let mut file = archive.by_index(i).path_context("reading", archive_path)?;
+        // Existing entries keep their compression, so archives created with e.g. `stored` stay that way
+        let compression = file.compression();
+        zip_writer.start_file(file_name.as_str(), options.compression_method(compression))?;
+        std::io::copy(&mut file, &mut zip_writer).entry_context("copying", &file_name).in_archive(archive_path)?;
+    }
+
+    // Add new files
+    let copied = archive.len();
+    for (i, file_path) in add_files.into_iter().enumerate() {
+        progress.on_event(ProgressEvent::progress("rebuild", &target, copied + i, total));
+        if let Some(staged) = staged.as_deref_mut().filter(|staged| staged.contains(file_path.name)) {
+            staged.copy_to(file_path.name, &mut zip_writer)?;
+            continue;
+        }
+
+        let mut file = std::fs::File::open(file_path.path).path_context("opening", file_path.path)?;
+        zip_writer.start_file(file_path.name, options)?;
+        std::io::copy(&mut file, &mut zip_writer)?;
+    }
+
+    progress.on_event(ProgressEvent::progress("rebuild", &target, total, total));
+
+    zip_writer.finish()?.flush()?;
+    // The source archive has to be closed before it can be replaced (on Windows)
+    drop(archive);
+
+    Ok(())
+}
+
+/// Take the advisory lock that every operation rewriting the archive at `path` holds until it is done.
+fn lock_fsv(path: &Path) -> Result<ArchiveLock, FsvError> {
+    ArchiveLock::try_acquire(path)?.ok_or_else(|| FsvError::Locked(path.to_path_buf()))
+}
+
+fn open_fsv(path: &Path) -> Result<(zip::ZipArchive<std::fs::File>, FsvMetadata), FsvError> {
+    let file = std::fs::File::open(path).path_context("opening", path)?;
+    let mut archive = zip::ZipArchive::new(file).path_context("reading", path)?;
+    let metadata = read_archive_metadata(&mut archive)?;
+
+    Ok((archive, metadata))
+}
+
+fn read_archive_metadata<R: Read + Seek>(archive: &mut zip::ZipArchive<R>) -> Result<FsvMetadata, FsvError> {
+    let metadata_json = read_metadata_json(archive, FsvError::MetadataFileNotFound)?;
+    let metadata = serde_json::from_str::<FsvMetadata>(&metadata_json)?;
+
+    Ok(metadata)
+}
+
+/// metadata.json as stored in `archive`, `not_found` if there is none.
+fn read_metadata_json<R: Read + Seek, E: From<std::io::Error> + From<zip::result::ZipError>>(archive: &mut zip::ZipArchive<R>, not_found: E) -> Result<String, E> {
+    let mut metadata_file = match archive.by_name("metadata.json") {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Err(not_found),
+        Err(err) => return Err(err.into()),
+    };
+    let mut metadata_json = String::new();
+    metadata_file.read_to_string(&mut metadata_json).entry_context("reading", "metadata.json")?;
+
+    Ok(metadata_json)
+}
+
+/// metadata.json of `archive`, read into `cache` on first use only.
+fn cached_metadata_json<'a, R: Read + Seek, E: From<std::io::Error> + From<zip::result::ZipError>>(archive: &mut zip::ZipArchive<R>, cache: &'a mut Option<String>, not_found: E) -> Result<&'a str, E> {
+    let metadata_json = match cache.take() {
+        Some(metadata_json) => metadata_json,
+        None => read_metadata_json(archive, not_found)?,
+    };
+
+    Ok(cache.insert(metadata_json))
+}
+
+/// The metadata of `archive`, read and parsed into the caches on first use only.
+fn cached_metadata<'a, R: Read + Seek>(archive: &mut zip::ZipArchive<R>, json_cache: &mut Option<String>, cache: &'a mut Option<FsvMetadata>) -> Result<&'a FsvMetadata, FsvError> {
+    let metadata = match cache.take() {
+        Some(metadata) => metadata,
+        None => serde_json::from_str(cached_metadata_json(archive, json_cache, FsvError::MetadataFileNotFound)?)?,
+    };
+
+    Ok(cache.insert(metadata))
+}
+
+/// Read and parse metadata.json from an FSV without touching the rest of the archive.
+pub fn read_fsv_metadata(path: &Path) -> Result<FsvMetadata, FsvError> {
+    let (_, metadata) = open_fsv(path)?;
+    Ok(metadata)
+}
+
+/// Read the full contents of a single archive entry.
+pub fn read_fsv_entry(path: &Path, entry_name: &str) -> Result<Vec<u8>, FsvError> {
+    let mut container = FsvContainer::open(path)?;
+    container.read_entry(entry_name)
+}
+
+/// Stream a single archive entry into `writer` without unpacking it to disk, matching `entry_name` like the names in metadata.
+/// Returns the number of bytes copied.
+pub fn copy_fsv_entry(path: &Path, entry_name: &str, name_matching: NameMatching, writer: &mut impl Write) -> Result<u64, FsvError> {
+    let mut container = FsvContainer::open(path)?;
+    let entry_name = container.resolve_entry(entry_name, name_matching).unwrap_or_else(|| entry_name.to_string());
+    container.copy_entry(&entry_name, writer)
+}
+
+/// Walk the central directory starting at `directory_start` and return every entry name that appears more than once.
+fn find_duplicate_entry_names<R: Read + Seek>(reader: &mut R, directory_start: u64) -> Result<Vec<String>, std::io::Error> {
+    const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+    const CENTRAL_HEADER_LEN: usize = 46;
+
+    reader.seek(std::io::SeekFrom::Start(directory_start))?;
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut header = [0u8; CENTRAL_HEADER_LEN];
+    loop {
+        if reader.read_exact(&mut header).is_err() {
+            break;
+        }
+
+        if u32::from_le_bytes([header[0], header[1], header[2], header[3]]) != CENTRAL_HEADER_SIGNATURE {
+            break;
+        }
+
+        let name_len = u16::from_le_bytes([header[28], header[29]]) as usize;
+        let extra_len = u16::from_le_bytes([header[30], header[31]]) as i64;
+        let comment_len = u16::from_le_bytes([header[32], header[33]]) as i64;
+        let mut name = vec![0u8; name_len];
+        reader.read_exact(&mut name)?;
+        reader.seek(std::io::SeekFrom::Current(extra_len + comment_len))?;
+        *counts.entry(String::from_utf8_lossy(&name).into_owned()).or_default() += 1;
+    }
+
+    let mut duplicates: Vec<String> = counts.into_iter().filter(|(_, count)| *count > 1).map(|(name, _)| name).collect();
+    duplicates.sort();
+
+    Ok(duplicates)
+}
+
+/// Names of the local entries in `gaps` (byte ranges of the file not covered by any central directory entry).
+/// Only the local headers are read, but the gaps are searched for them in full.
+fn find_stray_local_entries<R: Read + Seek>(reader: &mut R, gaps: &[(u64, u64)]) -> Result<Vec<String>, std::io::Error> {
+    const LOCAL_HEADER_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x03, 0x04];
+    const LOCAL_HEADER_LEN: u64 = 30;
+    const CHUNK_SIZE: u64 = 64 * 1024;
+
+    let mut names = Vec::new();
+    for &(start, end) in gaps {
+        let mut position = start;
+        while position + LOCAL_HEADER_LEN <= end {
+            // Chunks overlap by the signature's length so one split between two chunks is still found
+            let chunk_end = (position + CHUNK_SIZE).min(end);
+            let mut chunk = vec![0u8; (chunk_end - position) as usize];
+            reader.seek(std::io::SeekFrom::Start(position))?;
+            reader.read_exact(&mut chunk)?;
+
+            let found = chunk.windows(LOCAL_HEADER_SIGNATURE.len()).position(|window| window == LOCAL_HEADER_SIGNATURE);
+            let Some(offset) = found else {
+                if chunk_end == end {
+                    break;
                 }
-            },
-        };
-        let mut metadata_json = String::new();
-        metadata_file.read_to_string(&mut metadata_json)?;
 
-        metadata_json
+                position = chunk_end - (LOCAL_HEADER_SIGNATURE.len() as u64 - 1);
+                continue;
+            };
+
+            let header_start = position + offset as u64;
+            if header_start + LOCAL_HEADER_LEN > end {
+                break;
+            }
+
+            let mut header = [0u8; LOCAL_HEADER_LEN as usize];
+            reader.seek(std::io::SeekFrom::Start(header_start))?;
+            reader.read_exact(&mut header)?;
+            let name_len = u16::from_le_bytes([header[26], header[27]]) as u64;
+            let compressed_size = u32::from_le_bytes([header[18], header[19], header[20], header[21]]) as u64;
+            let extra_len = u16::from_le_bytes([header[28], header[29]]) as u64;
+            let name_end = (header_start + LOCAL_HEADER_LEN + name_len).min(end);
+            let mut name = vec![0u8; (name_end - header_start - LOCAL_HEADER_LEN) as usize];
+            reader.read_exact(&mut name)?;
+            names.push(String::from_utf8_lossy(&name).into_owned());
+
+            // Skip the entry's data when its header gives the size, otherwise keep searching right after the signature
+            position = match compressed_size {
+                0 | 0xFFFFFFFF => header_start + LOCAL_HEADER_SIGNATURE.len() as u64,
+                size => (header_start + LOCAL_HEADER_LEN + name_len + extra_len + size).max(header_start + 1),
+            };
+        }
+    }
+
+    Ok(names)
+}
+
+/// Irregularities in how an archive's entries are stored, found by reading the raw ZIP records, as `ZipArchive` hides them.
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveStructure {
+    /// Names stored more than once in the central directory. Readers only see one entry for each.
+    pub duplicate_entries: Vec<String>,
+    /// Names of local entries that no central directory record points to, e.g. files appended by a tool that didn't
+    /// update the directory. Readers never see them, and rebuilding the archive discards them.
+    pub stray_entries: Vec<String>,
+    /// Whether metadata.json is stored before every other entry
+    pub metadata_first: bool,
+}
+
+impl ArchiveStructure {
+    fn read<R: Read + Seek>(reader: R) -> Result<(zip::ZipArchive<R>, ArchiveStructure), zip::result::ZipError> {
+        let mut archive = zip::ZipArchive::new(reader)?;
+        let mut extents = Vec::new();
+        for i in 0..archive.len() {
+            let entry = archive.by_index_raw(i)?;
+            extents.push((entry.header_start(), entry.data_start() + entry.compressed_size(), entry.name().to_string()));
+        }
+        extents.sort();
+        let metadata_first = extents.first().is_none_or(|(_, _, name)| name == "metadata.json");
+
+        let directory_start = archive.central_directory_start();
+        let mut gaps = Vec::new();
+        let mut position = archive.offset();
+        for (start, end, _) in &extents {
+            if *start > position {
+                gaps.push((position, *start));
+            }
+            position = position.max(*end);
+        }
+        if directory_start > position {
+            gaps.push((position, directory_start));
+        }
+
+        // ZipArchive keeps only one entry per name, so duplicates have to be found in the raw central directory
+        let mut reader = archive.into_inner();
+        let duplicate_entries = find_duplicate_entry_names(&mut reader, directory_start)?;
+        // The shadowed copies of duplicates are reported as such, not as stray entries
+        let mut stray_entries = find_stray_local_entries(&mut reader, &gaps)?;
+        stray_entries.retain(|name| !duplicate_entries.contains(name));
+
+        Ok((zip::ZipArchive::new(reader)?, ArchiveStructure { duplicate_entries, stray_entries, metadata_first }))
+    }
+
+    /// Names of the data a rebuild would discard: the shadowed copies of duplicate entries and the stray local entries.
+    pub fn unknown_data(&self) -> Vec<String> {
+        self.duplicate_entries.iter().chain(&self.stray_entries).cloned().collect()
+    }
+}
+
+/// An FSV archive read from any seekable source, e.g. a file, an in-memory buffer or a ranged HTTP reader.
+/// The path-based functions in this module are thin wrappers around it.
+/// Opening reads the central directory only. metadata.json is read and parsed on first use and kept for later queries,
+/// so a command can look up entries, validate, show info and extract through one container without reading anything twice.
+pub struct FsvContainer<R: Read + Seek> {
+    archive: zip::ZipArchive<R>,
+    structure: ArchiveStructure,
+    index: EntryIndex,
+    metadata_json: Option<String>,
+    metadata: Option<FsvMetadata>,
+}
+
+impl FsvContainer<File> {
+    /// Open the FSV at `path`, reading its central directory only.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        FsvContainer::from_reader(File::open(path).path_context("opening", path)?).path_context("reading", path)
+    }
+}
+
+impl<R: Read + Seek> FsvContainer<R> {
+    pub fn from_reader(reader: R) -> Result<Self, zip::result::ZipError> {
+        let (archive, structure) = ArchiveStructure::read(reader)?;
+        let index = EntryIndex::new(archive.file_names());
+        Ok(FsvContainer { archive, structure, index, metadata_json: None, metadata: None })
+    }
+
+    /// Names stored more than once in the archive's central directory.
+    pub fn duplicate_entries(&self) -> &[String] {
+        &self.structure.duplicate_entries
+    }
+
+    pub fn structure(&self) -> &ArchiveStructure {
+        &self.structure
+    }
+
+    pub fn entry_names(&self) -> impl Iterator<Item = &str> {
+        self.archive.file_names()
+    }
+
+    /// Archive entry names indexed for lookups by the names in metadata.
+    pub fn entry_index(&self) -> &EntryIndex {
+        &self.index
+    }
+
+    /// metadata.json as stored in the archive.
+    pub fn metadata_json(&mut self) -> Result<&str, FsvError> {
+        cached_metadata_json(&mut self.archive, &mut self.metadata_json, FsvError::MetadataFileNotFound)
+    }
+
+    /// The parsed metadata, without copying it.
+    pub fn cached_metadata(&mut self) -> Result<&FsvMetadata, FsvError> {
+        cached_metadata(&mut self.archive, &mut self.metadata_json, &mut self.metadata)
+    }
+
+    pub fn metadata(&mut self) -> Result<FsvMetadata, FsvError> {
+        Ok(self.cached_metadata()?.clone())
+    }
+
+    /// Read the full contents of a single archive entry.
+    pub fn read_entry(&mut self, entry_name: &str) -> Result<Vec<u8>, FsvError> {
+        let mut entry = self.archive.by_name(entry_name).entry_context("reading", entry_name)?;
+        let mut buffer = Vec::new();
+        entry.read_to_end(&mut buffer).entry_context("reading", entry_name)?;
+
+        Ok(buffer)
+    }
+
+    /// The name of the entry `name` refers to, matched like the names in metadata are. `None` if there is none.
+    pub fn resolve_entry(&self, name: &str, name_matching: NameMatching) -> Option<String> {
+        self.index.resolve(name, name_matching)
+    }
+
+    /// Uncompressed size of a single archive entry.
+    pub fn entry_size(&mut self, entry_name: &str) -> Result<u64, FsvError> {
+        Ok(self.archive.by_name(entry_name).entry_context("reading", entry_name)?.size())
+    }
+
+    /// Stream a single archive entry into `writer`, without buffering it in memory. Returns the number of bytes copied.
+    pub fn copy_entry(&mut self, entry_name: &str, writer: &mut impl Write) -> Result<u64, FsvError> {
+        let mut entry = self.archive.by_name(entry_name).entry_context("reading", entry_name)?;
+        Ok(std::io::copy(&mut entry, writer).entry_context("reading", entry_name)?)
+    }
+
+    pub fn validate(&mut self, name_matching: NameMatching) -> Result<FsvState, FsvValidationError> {
+        Ok(log_validation_report(self.validate_report(name_matching)?))
+    }
+
+    pub fn validate_report(&mut self, name_matching: NameMatching) -> Result<ValidationReport, FsvValidationError> {
+        self.validate_report_with(&ValidateOptions { name_matching, ..ValidateOptions::default() })
+    }
+
+    pub fn validate_report_with(&mut self, options: &ValidateOptions) -> Result<ValidationReport, FsvValidationError> {
+        let metadata_json = cached_metadata_json(&mut self.archive, &mut self.metadata_json, FsvValidationError::MetadataNotFound)?;
+        validate_archive_with_json(&mut self.archive, &self.structure, &self.index, metadata_json, options)
+    }
+
+    /// Extract into a subdirectory of `output_dir` named after the title, or `fallback_dirname` if the title is unusable.
+    pub fn extract(&mut self, output_dir: &Path, fallback_dirname: &str, options: &ExtractOptions) -> Result<(), FsvExtractError> {
+        let metadata_json = cached_metadata_json(&mut self.archive, &mut self.metadata_json, FsvExtractError::MetadataNotFound)?;
+        extract_archive(&mut self.archive, &self.structure, &self.index, metadata_json, output_dir, fallback_dirname, options)
+    }
+
+    /// Compressed and uncompressed sizes of all entries, read from the central directory only.
+    pub fn sizes(&mut self) -> Result<ArchiveSizes, FsvError> {
+        archive_sizes(&mut self.archive)
+    }
+
+    /// The title is left as-is (possibly empty) since there is no file name to fall back on.
+    pub fn info(&mut self, options: &InfoOptions) -> Result<FsvInfo, FsvError> {
+        let metadata = cached_metadata(&mut self.archive, &mut self.metadata_json, &mut self.metadata)?;
+        archive_info(&mut self.archive, &self.index, metadata, options)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.archive.into_inner()
+    }
+}
+
+/// Database key for a creator known only by name, e.g. `Some Scripter` -> `some-scripter`
+#[cfg(feature = "native")]
+fn creator_key_from_name(name: &str) -> String {
+    let key: String = name.chars().map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' }).collect();
+    key.split('-').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("-")
+}
+
+/// Fill in FSV metadata from a funscript's embedded `metadata` block: its title (only replacing a different title after
+/// confirmation), its tags and performers (merged), and its creator as the script's creator unless one was given. Creators not yet in the
+/// database are saved under a key derived from their name.
+#[cfg(feature = "native")]
+async fn apply_script_metadata(metadata: &mut FsvMetadata, funscript: &Funscript, script_name: &str, has_creator: bool, db_client: &DbClient, interactive: bool) -> Result<(), FsvError> {
+    let Some(script_metadata) = &funscript.metadata else {
+        info!(operation = "script_metadata", item = %script_name, outcome = "skipped", "Script has no embedded metadata");
+        return Ok(());
     };
 
-    let metadata = serde_json::from_str::<FsvMetadata>(&metadata_json)?;
+    let title = script_metadata.title.trim();
+    if !title.is_empty() && title != metadata.title {
+        let replace = metadata.title.is_empty()
+            || (interactive && prompt_input(&format!("Replace title '{}' with '{}' from the script? [y/N]: ", metadata.title, title))?.eq_ignore_ascii_case("y"));
+        if replace {
+            metadata.title = title.to_string();
+        }
+        else {
+            info!(operation = "script_metadata", title = %metadata.title, script_title = %title, outcome = "kept", "Keeping the title, not the one in the script metadata");
+        }
+    }
 
-    Ok((archive, metadata))
+    if !script_metadata.tags.is_empty() {
+        let tags = metadata.tags.iter().chain(&script_metadata.tags).cloned().collect();
+        metadata.tags = normalize_tags(db_client, tags).await?;
+    }
+
+    metadata.performers = dedup_names(metadata.performers.drain(..).chain(script_metadata.performers.iter().cloned()).collect());
+
+    if !has_creator {
+        credit_script_creator(metadata, script_metadata, script_name, db_client, interactive).await?;
+    }
+
+    Ok(())
+}
+
+/// Credit the creator a script's embedded metadata names as the creator of `script_name`, see `import_script_creator`.
+#[cfg(feature = "native")]
+async fn credit_script_creator(metadata: &mut FsvMetadata, script_metadata: &FunscriptMetadata, script_name: &str, db_client: &DbClient, interactive: bool) -> Result<(), FsvError> {
+    if let Some(imported) = import_script_creator(db_client, script_metadata, interactive).await? {
+        metadata.add_script_creator(WorkCreatorsMetadata::new(script_name.to_string(), imported.script_url, imported.creator_info).with_creator_key(imported.key));
+    }
+
+    Ok(())
+}
+
+/// The creator a script's embedded metadata credits, as settled by `import_script_creator`.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone)]
+pub struct ImportedCreator {
+    /// Key of the database record the creator is linked to, `None` if it isn't stored
+    pub key: Option<String>,
+    pub creator_info: CreatorInfo,
+    /// Whether the import created the record
+    pub created: bool,
+    /// The script's `script_url`, where the work was published
+    pub script_url: String,
+}
+
+/// Find the database creator a script's embedded metadata credits: one named or keyed like its `creator`, or with a social
+/// its `script_url` is a page of. Interactive runs list the matches to link one of, or offer to create a record (asking
+/// for a key and socials); other runs link the first match, or save a new creator under a key derived from the name.
+/// `None` if the metadata names no creator and none matches, or the user skips it.
+#[cfg(feature = "native")]
+pub async fn import_script_creator(db_client: &DbClient, script_metadata: &FunscriptMetadata, interactive: bool) -> Result<Option<ImportedCreator>, FsvError> {
+    let name = script_metadata.creator.trim();
+    let script_url = script_metadata.script_url.trim().to_string();
+    if name.is_empty() && script_url.is_empty() {
+        return Ok(None);
+    }
+
+    let derived_key = creator_key_from_name(name);
+    let matches = db_client.match_creators(name, &derived_key, &script_url).await?;
+    let linked = |record: &CreatorRecord| Some(ImportedCreator { key: Some(record.key.clone()), creator_info: record.creator_info.clone(), created: false, script_url: script_url.clone() });
+    if !interactive {
+        if matches.len() > 1 {
+            warn!(operation = "import_creator", creator = %name, outcome = "linked", key = %matches[0].key, "{} creators match the script's creator, linking the first", matches.len());
+        }
+        if let Some(record) = matches.first() {
+            return Ok(linked(record));
+        }
+        if name.is_empty() {
+            info!(operation = "import_creator", script_url = %script_url, outcome = "skipped", "No creator matches the script URL");
+            return Ok(None);
+        }
+
+        let creator_info = CreatorInfo::new(name.to_string(), vec![]);
+        db_client.insert_creator_info(&derived_key, &creator_info).await?;
+        info!(operation = "import_creator", creator = %name, key = %derived_key, outcome = "created", "Creator saved to database");
+        return Ok(Some(ImportedCreator { key: Some(derived_key), creator_info, created: true, script_url }));
+    }
+
+    if !matches.is_empty() {
+        println!("Creators matching the script's creator '{}' ({}):", name, script_url);
+        for (i, record) in matches.iter().enumerate() {
+            println!("  {}) {} [{}] {}", i + 1, record.creator_info.name, record.key, record.creator_info.socials.join(", "));
+        }
+        loop {
+            let answer = prompt_input(&format!("Link creator [1-{}, blank for 1], c to create a new one, s to skip: ", matches.len()))?;
+            match answer.to_lowercase().as_str() {
+                "" => return Ok(linked(&matches[0])),
+                "s" => return Ok(None),
+                "c" => break,
+                answer => match answer.parse::<usize>().ok().and_then(|i| matches.get(i.wrapping_sub(1))) {
+                    Some(record) => return Ok(linked(record)),
+                    None => println!("Please answer 1-{}, c or s.", matches.len()),
+                },
+            }
+        }
+    }
+
+    let name = match name {
+        "" => prompt_input("Enter creator name (blank to skip): ")?,
+        name => name.to_string(),
+    };
+    if name.is_empty() {
+        return Ok(None);
+    }
+    let default_key = creator_key_from_name(&name);
+    let key = match prompt_input(&format!("Create creator '{}' with key [blank for '{}', s to skip]: ", name, default_key))?.as_str() {
+        "" => default_key,
+        "s" => return Ok(None),
+        key => key.to_string(),
+    };
+    let socials = socials::normalize_socials(prompt_input("Enter creator socials (comma-separated, optional): ")?.split(','));
+    let creator_info = CreatorInfo::new(name, socials);
+    db_client.insert_creator_info(&key, &creator_info).await?;
+    info!(operation = "import_creator", creator = %creator_info.name, key = %key, outcome = "created", "Creator saved to database");
+
+    Ok(Some(ImportedCreator { key: Some(key), creator_info, created: true, script_url }))
+}
+
+/// `import_script_creator` for the funscript at `path`. `None` if it has no embedded metadata.
+#[cfg(feature = "native")]
+pub async fn import_creator_from_script(path: &Path, db_client: &DbClient, interactive: bool) -> Result<Option<ImportedCreator>, FsvError> {
+    let file_content = std::fs::read_to_string(path).path_context("reading", path)?;
+    let funscript = serde_json::from_str::<Funscript>(&file_content)?;
+    match &funscript.metadata {
+        Some(script_metadata) => import_script_creator(db_client, script_metadata, interactive).await,
+        None => Ok(None),
+    }
 }
 
 /// Prompt the user and return trimmed input
+#[cfg(feature = "native")]
 fn prompt_input(prompt: &str) -> std::io::Result<String> {
     print!("{}", prompt);
     std::io::stdout().flush()?; // make sure the prompt appears immediately
@@ -1015,17 +4295,24 @@ fn prompt_input(prompt: &str) -> std::io::Result<String> {
     Ok(buf.trim().to_string())
 }
 
+#[cfg(feature = "native")]
 pub async fn get_creator_info_from_key(db_client: &DbClient, creator_key: Option<&str>, interactive: bool) -> Result<Option<CreatorInfo>, FsvError> {
     if let Some(key) = creator_key {
-        let creator_info = db_client.get_creator_info_by_key(&key).await?;
+        let creator_info = db_client.get_creator_info_by_key(key).await?;
         if let Some(creator_info) = creator_info {
             Ok(Some(creator_info))
         }
         else if interactive {
-            warn!("Creator with key '{}' not found in database; entering interactive mode.", key);
-            let creator_info = get_creator_info_from_user(db_client, Some(&key)).await?;
+            match db_client.is_persistent() {
+                true => warn!(operation = "creator_lookup", key = %key, outcome = "not_found", "Creator not found in database; entering interactive mode"),
+                false => warn!(operation = "creator_lookup", key = %key, outcome = "not_found", "No creator database to look up the key in; entering interactive mode"),
+            }
+            let creator_info = get_creator_info_from_user(db_client, Some(key)).await?;
             Ok(Some(creator_info))
         }
+        else if !db_client.is_persistent() {
+            Err(FsvError::NoCreatorDatabase(key.to_string()))
+        }
         else{
             Err(FsvError::CreatorInfoNotFound(key.to_string()))
         }
@@ -1035,6 +4322,64 @@ pub async fn get_creator_info_from_key(db_client: &DbClient, creator_key: Option
     }
 }
 
+/// The tags implied by `tags` (canonical names) that aren't among them yet, in the order they are first implied.
+#[cfg(feature = "native")]
+pub async fn implied_tags(db_client: &DbClient, tags: &[String]) -> Result<Vec<String>, db_client::DbClientError> {
+    let mut seen: HashSet<String> = tags.iter().map(|tag| tag.to_lowercase()).collect();
+    let mut implied = Vec::new();
+    for tag in tags {
+        for implied_tag in db_client.implied_tags(tag).await? {
+            if seen.insert(implied_tag.to_lowercase()) {
+                implied.push(implied_tag);
+            }
+        }
+    }
+
+    Ok(implied)
+}
+
+/// `tags` without `remove_tags` (matched case-insensitively) and with `add_tags`, normalized against the tag vocabulary.
+#[cfg(feature = "native")]
+pub async fn retag(db_client: &DbClient, tags: &[String], add_tags: &[String], remove_tags: &[String]) -> Result<Vec<String>, db_client::DbClientError> {
+    let tags = tags.iter().filter(|tag| !remove_tags.iter().any(|r| r.eq_ignore_ascii_case(tag))).chain(add_tags).cloned().collect();
+    normalize_tags(db_client, tags).await
+}
+
+/// Map tags to their canonical names from the tag vocabulary and drop duplicates.
+/// Unknown tags are kept as-is with a warning. If no vocabulary has been defined, tags are only de-duplicated.
+#[cfg(feature = "native")]
+pub async fn normalize_tags(db_client: &DbClient, tags: Vec<String>) -> Result<Vec<String>, db_client::DbClientError> {
+    let has_vocabulary = db_client.has_tags().await?;
+    let mut seen = HashSet::new();
+    let mut normalized = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            continue;
+        }
+
+        let tag = if has_vocabulary {
+            match db_client.resolve_tag(tag).await? {
+                Some(canonical) => canonical,
+                None => {
+                    warn!(operation = "normalize_tags", item = %tag, outcome = "unknown", "Tag is not in the tag vocabulary");
+                    tag.to_string()
+                }
+            }
+        }
+        else {
+            tag.to_string()
+        };
+
+        if seen.insert(tag.to_lowercase()) {
+            normalized.push(tag);
+        }
+    }
+
+    Ok(normalized)
+}
+
+#[cfg(feature = "native")]
 pub async fn get_creator_info_from_user(db_client: &DbClient, creator_key: Option<&str>) -> Result<CreatorInfo, FsvError> {
     // Name (required)
     let name = loop {
@@ -1048,13 +4393,7 @@ pub async fn get_creator_info_from_user(db_client: &DbClient, creator_key: Optio
 
     // Socials (comma-separated)
     let socials_input = prompt_input("Enter creator socials (comma-separated): ")?;
-    let socials: Vec<String> = socials_input
-        .split(',')
-        .filter_map(|s| {
-            let trimmed = s.trim();
-            if trimmed.is_empty() { None } else { Some(trimmed.to_string()) }
-        })
-        .collect();
+    let socials = socials::normalize_socials(socials_input.split(','));
 
     let creator_info = CreatorInfo::new(name, socials);
 
@@ -1062,7 +4401,7 @@ pub async fn get_creator_info_from_user(db_client: &DbClient, creator_key: Optio
     let input_key;
     // Save to DB if key provided or in interactive mode
     let key = if let Some(key) = creator_key {
-        info!("Saving creator info with key '{}' to database.", key);
+        info!(operation = "creator_lookup", key = %key, "Saving creator info to database");
         key
     }
     else{
@@ -1072,9 +4411,9 @@ pub async fn get_creator_info_from_user(db_client: &DbClient, creator_key: Optio
     };
 
     if !key.is_empty() {
-        match db_client.insert_creator_info(&key, &creator_info).await {
-            Ok(_) => info!("Creator '{}' saved to database.", key),
-            Err(e) => error!("Failed to insert creator info: {}", e),
+        match db_client.insert_creator_info(key, &creator_info).await {
+            Ok(_) => info!(operation = "creator_lookup", key = %key, outcome = "created", "Creator saved to database"),
+            Err(e) => error!(operation = "creator_lookup", key = %key, outcome = "failed", error = %e, "Failed to insert creator info"),
         }
     }
 
@@ -1082,6 +4421,785 @@ pub async fn get_creator_info_from_user(db_client: &DbClient, creator_key: Optio
 }
 
 pub fn get_file_hash(data: &[u8]) -> String {
-    let hash = file_util::get_hash_string(data);
-    format!("sha256:{}", hash)
-}
\ No newline at end of file
+    get_file_checksum(data, HashAlgorithm::default())
+}
+
+/// Checksum string for `data` in `algorithm:digest` form
+pub fn get_file_checksum(data: &[u8], algorithm: HashAlgorithm) -> String {
+    Checksum::compute(algorithm, data).to_string()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::ExtraFields;
+
+    const VIDEO: &[u8] = b"\0\0\0\x18ftypisom";
+    const SCRIPT: &[u8] = br#"{"actions":[{"at":0,"pos":0},{"at":1000,"pos":100}],"inverted":false,"range":100,"version":"1.0"}"#;
+
+    /// Fresh directory for a test's files, removed when dropped, so a failing test doesn't leave them behind.
+    fn test_dir(name: &str) -> tempfile::TempDir {
+        tempfile::Builder::new().prefix(&format!("fsv-{}-", name)).tempdir().unwrap()
+    }
+
+    #[test]
+    fn test_entry_index_lookup() {
+        let index = EntryIndex::new(["Video.MP4", "scripts\\scene.funscript", "metadata.json"].into_iter());
+        assert_eq!(index.lookup("metadata.json"), EntryLookup::Exact);
+        assert_eq!(index.lookup("video.mp4"), EntryLookup::Normalized("Video.MP4".to_string()));
+        assert_eq!(index.lookup("./scripts/scene.funscript"), EntryLookup::Normalized("scripts\\scene.funscript".to_string()));
+        assert_eq!(index.lookup("missing.mp4"), EntryLookup::Missing);
+        assert_eq!(index.resolve("video.mp4", NameMatching::Strict), None);
+        assert_eq!(index.resolve("video.mp4", NameMatching::Normalized), Some("Video.MP4".to_string()));
+        // `動画.mp4` stored without the UTF-8 flag, as the ZIP library reads it
+        let index = EntryIndex::new(["\u{3C3}\u{EF}\u{F2}\u{3C4}\u{F6}\u{2557}.mp4"].into_iter());
+        assert_eq!(index.resolve("動画.mp4", NameMatching::Strict), Some("\u{3C3}\u{EF}\u{F2}\u{3C4}\u{F6}\u{2557}.mp4".to_string()));
+    }
+
+    #[test]
+    fn test_unflagged_utf8_entry_names() {
+        let mut data = FsvBuilder::new("scene").video("動画.mp4", VIDEO, 1000).script("動画.funscript", SCRIPT, 1000).to_bytes().unwrap();
+        // Clear the UTF-8 flag of every local and central header, as tools that store UTF-8 names without it do
+        for i in 0..data.len().saturating_sub(4) {
+            let flags_offset = match u32::from_le_bytes(data[i..i + 4].try_into().unwrap()) {
+                0x04034b50 => i + 6,
+                0x02014b50 => i + 8,
+                _ => continue,
+            };
+            data[flags_offset + 1] &= !0x08;
+        }
+        let temp = test_dir("unflagged-names");
+        let dir = temp.path();
+        let path = dir.join("scene.fsv");
+        std::fs::write(&path, data).unwrap();
+        assert!(FsvContainer::from_reader(File::open(&path).unwrap()).unwrap().entry_names().all(|name| name != "動画.mp4"));
+
+        let report = validate_fsv_report(&path, NameMatching::Strict).unwrap();
+        assert!(matches!(report.state, FsvState::Valid));
+        assert!(report.warnings().any(|issue| issue.item.as_deref() == Some("動画.mp4")));
+        extract_fsv(&path, &dir.join("out"), &ExtractOptions::default()).unwrap();
+        assert!(dir.join("out/scene/動画_動画.mp4").exists() && dir.join("out/scene/動画_動画.funscript").exists());
+
+        // Rebuilding writes the names back with the flag
+        edit_fsv_description(&path, ItemType::Video, "動画.mp4", "1080p").unwrap();
+        let mut names: Vec<_> = FsvContainer::from_reader(File::open(&path).unwrap()).unwrap().entry_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(names, ["metadata.json", "動画.funscript", "動画.mp4"]);
+        assert!(validate_fsv_report(&path, NameMatching::Strict).unwrap().warnings().all(|issue| issue.item.as_deref() != Some("動画.mp4")));
+    }
+
+    #[test]
+    fn test_container_from_reader() {
+        let data = FsvBuilder::new("scene")
+            .video("video.mp4", VIDEO, 1000)
+            .script("video.funscript", SCRIPT, 1000)
+            .to_bytes()
+            .unwrap();
+        let mut container = FsvContainer::from_reader(std::io::Cursor::new(data)).unwrap();
+        assert!(matches!(container.validate(NameMatching::Strict).unwrap(), FsvState::Valid));
+        assert_eq!(container.read_entry("video.funscript").unwrap(), SCRIPT);
+
+        let mut builder = FsvBuilder::new("scene")
+            .script("video.funscript", SCRIPT, 1000)
+            .entry("Video.MP4", VIDEO);
+        builder.metadata_mut().add_video_format(VideoFormat::new("video.mp4".to_string(), String::new(), DurationMs::from_millis(1000), get_file_hash(VIDEO)));
+        let data = builder.to_bytes().unwrap();
+        let mut container = FsvContainer::from_reader(std::io::Cursor::new(data.clone())).unwrap();
+        assert!(matches!(container.validate(NameMatching::Strict).unwrap(), FsvState::ContentIncomplete(ContentIncompleteReason::MismatchedItemName(ItemType::Video, _, _))));
+        assert!(matches!(container.validate(NameMatching::Normalized).unwrap(), FsvState::Valid));
+        assert_eq!(container.resolve_entry("video.mp4", NameMatching::Normalized).as_deref(), Some("Video.MP4"));
+        assert_eq!(container.resolve_entry("video.mp4", NameMatching::Strict), None);
+
+        let temp = test_dir("copy-entry");
+        let dir = temp.path();
+        let path = dir.join("scene.fsv");
+        std::fs::write(&path, data).unwrap();
+        let mut output = Vec::new();
+        assert_eq!(copy_fsv_entry(&path, "video.mp4", NameMatching::Normalized, &mut output).unwrap(), VIDEO.len() as u64);
+        assert_eq!(output, VIDEO);
+        assert!(matches!(copy_fsv_entry(&path, "video.mp4", NameMatching::Strict, &mut Vec::new()), Err(FsvError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn test_container_caches_metadata() {
+        let data = FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000).to_bytes().unwrap();
+        let mut container = FsvContainer::from_reader(std::io::Cursor::new(data)).unwrap();
+        let first: *const FsvMetadata = container.cached_metadata().unwrap();
+        assert!(std::ptr::eq(first, container.cached_metadata().unwrap()));
+        assert!(container.metadata_json().unwrap().contains("\"video.funscript\""));
+
+        // Every query goes through the one container
+        assert_eq!(container.info(&InfoOptions::default()).unwrap().scripts, [("video.funscript".to_string(), true)]);
+        assert!(matches!(container.validate(NameMatching::Strict).unwrap(), FsvState::Valid));
+        let temp = test_dir("container-cache");
+        let dir = temp.path();
+        container.extract(dir, "scene", &ExtractOptions { only: Some(ExtractOnly::Metadata), ..ExtractOptions::default() }).unwrap();
+        let extracted = std::fs::read_to_string(dir.join("scene").join("metadata.json")).unwrap();
+        assert_eq!(extracted, container.metadata_json().unwrap());
+        assert!(std::ptr::eq(first, container.cached_metadata().unwrap()));
+
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer.start_file("video.mp4", SimpleFileOptions::default()).unwrap();
+        writer.write_all(VIDEO).unwrap();
+        let mut container = FsvContainer::from_reader(writer.finish().unwrap()).unwrap();
+        assert!(matches!(container.cached_metadata(), Err(FsvError::MetadataFileNotFound)));
+        assert!(matches!(container.validate(NameMatching::Strict), Err(FsvValidationError::MetadataNotFound)));
+    }
+
+    #[test]
+    fn test_external_video_is_not_missing() {
+        let mut builder = FsvBuilder::new("scene").script("video.funscript", SCRIPT, 1000);
+        builder.metadata_mut().add_video_format(VideoFormat::new("video.mp4".to_string(), String::new(), DurationMs::from_millis(1000), get_file_hash(VIDEO)));
+        let mut container = FsvContainer::from_reader(std::io::Cursor::new(builder.to_bytes().unwrap())).unwrap();
+        assert!(matches!(container.validate(NameMatching::Strict).unwrap(), FsvState::ContentIncomplete(ContentIncompleteReason::MissingItemFile(ItemType::Video))));
+
+        let mut builder = FsvBuilder::new("scene").script("video.funscript", SCRIPT, 1000);
+        builder.metadata_mut().add_video_format(VideoFormat::new("video.mp4".to_string(), String::new(), DurationMs::from_millis(1000), get_file_hash(VIDEO)));
+        let reference = ExternalContent::new("video.mp4".to_string(), "video.mp4".to_string(), None);
+        external::set_external_content(builder.metadata_mut(), &[reference]).unwrap();
+        let mut container = FsvContainer::from_reader(std::io::Cursor::new(builder.to_bytes().unwrap())).unwrap();
+        let report = container.validate_report(NameMatching::Strict).unwrap();
+        assert!(matches!(report.state, FsvState::Valid));
+        assert_eq!(report.external, ["video.mp4"]);
+    }
+
+    #[test]
+    fn test_validation_report_collects_all_issues() {
+        let mut builder = FsvBuilder::new("")
+            .entry("Video.MP4", VIDEO);
+        builder.metadata_mut().add_video_format(VideoFormat::new("video.mp4".to_string(), String::new(), DurationMs::from_millis(1000), get_file_hash(VIDEO)));
+        builder.metadata_mut().add_script_variant(ScriptVariant::new("missing.funscript".to_string(), String::new(), vec![], DurationMs::from_millis(1000), 0, String::new()));
+        let mut container = FsvContainer::from_reader(std::io::Cursor::new(builder.to_bytes().unwrap())).unwrap();
+
+        let report = container.validate_report(NameMatching::Strict).unwrap();
+        assert!(matches!(report.state, FsvState::ContentIncomplete(ContentIncompleteReason::MismatchedItemName(ItemType::Video, _, _))));
+        let errors: Vec<_> = report.errors().map(|issue| issue.item.as_deref()).collect();
+        assert_eq!(errors, [Some("video.mp4"), Some("missing.funscript")]);
+        assert!(report.warnings().any(|issue| issue.message.contains("title is empty")));
+    }
+
+    #[test]
+    fn test_orphan_creators() {
+        let mut builder = FsvBuilder::new("scene")
+            .video("video.mp4", VIDEO, 1000)
+            .script("video.funscript", SCRIPT, 1000);
+        let metadata = builder.metadata_mut();
+        metadata.add_video_creator(WorkCreatorsMetadata::new("video.mp4".to_string(), String::new(), CreatorInfo::new("Studio".to_string(), vec![])));
+        metadata.add_video_creator(WorkCreatorsMetadata::new("old.mp4".to_string(), String::new(), CreatorInfo::new("Studio".to_string(), vec![])));
+        // A script creator can't credit a video, even one that exists
+        metadata.add_script_creator(WorkCreatorsMetadata::new("video.mp4".to_string(), String::new(), CreatorInfo::new("Scripter".to_string(), vec![])));
+        metadata.add_script_creator(WorkCreatorsMetadata::new("video.funscript".to_string(), String::new(), CreatorInfo::new("Scripter".to_string(), vec![])));
+        let orphans: Vec<_> = orphan_creators(builder.metadata_mut()).into_iter().map(|(item_type, work)| (item_type, work.work_name.clone())).collect();
+        assert_eq!(orphans, [(ItemType::Video, "old.mp4".to_string()), (ItemType::Script, "video.mp4".to_string())]);
+
+        let temp = test_dir("orphan-creators");
+        let dir = temp.path();
+        let path = dir.join("scene.fsv");
+        std::fs::write(&path, builder.to_bytes().unwrap()).unwrap();
+        let report = validate_fsv_report(&path, NameMatching::Strict).unwrap();
+        assert!(matches!(report.state, FsvState::Valid));
+        assert_eq!(report.warnings().filter(|issue| issue.message.contains("prune-orphan-creators")).count(), 2);
+
+        assert_eq!(prune_orphan_creators(&path).unwrap(), ["old.mp4", "video.mp4"]);
+        let (_, metadata) = open_fsv(&path).unwrap();
+        assert!(orphan_creators(&metadata).is_empty());
+        assert_eq!((metadata.creators.videos.len(), metadata.creators.scripts.len()), (1, 1));
+        assert!(prune_orphan_creators(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pair_offsets() {
+        let temp = test_dir("pair-offsets");
+        let dir = temp.path();
+        let path = dir.join("scene.fsv");
+        let data = FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000).to_bytes().unwrap();
+        std::fs::write(&path, data).unwrap();
+
+        assert!(matches!(set_pair_offset(&path, "video.funscript", "other.mp4", Some(500)), Err(FsvEditError::ItemNotFound(ItemType::Video, _))));
+        assert_eq!(set_pair_offset(&path, "video.funscript", "video.mp4", Some(500)).unwrap(), None);
+        let metadata = read_fsv_metadata(&path).unwrap();
+        assert_eq!(offsets::start_offset(&metadata, "video.funscript", None), Some(500));
+        assert!(validate_fsv_report(&path, NameMatching::Strict).unwrap().warnings().all(|issue| !issue.message.contains("offset")));
+
+        // The extracted script is shifted into sync with the video, dropping the action before its start
+        let output_dir = dir.join("out");
+        extract_fsv(&path, &output_dir, &ExtractOptions::default()).unwrap();
+        let script: Funscript = serde_json::from_slice(&std::fs::read(output_dir.join("scene/video_video.funscript")).unwrap()).unwrap();
+        assert_eq!(script.actions.iter().map(|action| action.at).collect::<Vec<_>>(), [500]);
+
+        assert_eq!(set_pair_offset(&path, "video.funscript", "video.mp4", None).unwrap(), Some(500));
+        assert!(read_fsv_metadata(&path).unwrap().extensions.is_empty());
+
+        // The variant's own start_offset is only applied when asked to
+        let mut builder = FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000);
+        builder.metadata_mut().script_variants[0].start_offset = -250;
+        std::fs::write(&path, builder.to_bytes().unwrap()).unwrap();
+        let extracted_times = |apply_offset| {
+            std::fs::remove_dir_all(&output_dir).unwrap();
+            extract_fsv(&path, &output_dir, &ExtractOptions { apply_offset, ..ExtractOptions::default() }).unwrap();
+            let script: Funscript = serde_json::from_slice(&std::fs::read(output_dir.join("scene/video_video.funscript")).unwrap()).unwrap();
+            script.actions.iter().map(|action| action.at).collect::<Vec<_>>()
+        };
+        assert_eq!(extracted_times(false), [0, 1000]);
+        assert_eq!(extracted_times(true), [250, 1250]);
+
+        // Subtitles are shifted by their own offset against the pair's video, also only when asked to
+        let srt: &[u8] = b"1\n00:00:01,000 --> 00:00:02,000\nHello\n";
+        std::fs::write(&path, FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000).subtitle("english.srt", "en", srt).to_bytes().unwrap()).unwrap();
+        assert!(matches!(set_subtitle_offset(&path, "french.srt", "video.mp4", Some(500)), Err(FsvEditError::ItemNotFound(ItemType::Subtitle, _))));
+        assert_eq!(set_subtitle_offset(&path, "english.srt", "video.mp4", Some(500)).unwrap(), None);
+        let extracted_subtitle = |apply_offset| {
+            std::fs::remove_dir_all(&output_dir).unwrap();
+            extract_fsv(&path, &output_dir, &ExtractOptions { apply_offset, ..ExtractOptions::default() }).unwrap();
+            std::fs::read_to_string(output_dir.join("scene/video_video.en.srt")).unwrap()
+        };
+        assert_eq!(extracted_subtitle(false).as_bytes(), srt);
+        assert_eq!(extracted_subtitle(true), "1\n00:00:00,500 --> 00:00:01,500\nHello\n");
+    }
+
+    #[test]
+    fn test_put_metadata() {
+        let temp = test_dir("put-metadata");
+        let dir = temp.path();
+        let path = dir.join("scene.fsv");
+        let data = FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000).to_bytes().unwrap();
+        std::fs::write(&path, data).unwrap();
+
+        let mut metadata = read_fsv_metadata(&path).unwrap();
+        metadata.title = "Renamed".to_string();
+        assert!(put_fsv_metadata(&path, &serde_json::to_string(&metadata).unwrap(), false).unwrap().is_some());
+        assert_eq!(read_fsv_metadata(&path).unwrap().title, "Renamed");
+        assert!(put_fsv_metadata(&path, &read_fsv_metadata(&path).unwrap().to_canonical_json().unwrap(), false).unwrap().is_none());
+
+        // Metadata the archive wouldn't validate with is refused, leaving the archive as it was
+        assert!(matches!(put_fsv_metadata(&path, "{", false), Err(FsvEditError::MetadataRejected(report)) if matches!(report.state, FsvState::MetadataInvalid(_))));
+        metadata.script_variants[0].name = "missing.funscript".to_string();
+        let json = serde_json::to_string(&metadata).unwrap();
+        assert!(matches!(put_fsv_metadata(&path, &json, false), Err(FsvEditError::MetadataRejected(report)) if matches!(report.state, FsvState::ContentIncomplete(_))));
+        assert_eq!(read_fsv_metadata(&path).unwrap().script_variants[0].name, "video.funscript");
+        put_fsv_metadata(&path, &json, true).unwrap();
+        assert_eq!(read_fsv_metadata(&path).unwrap().script_variants[0].name, "missing.funscript");
+    }
+
+    #[test]
+    fn test_parse_modes() {
+        let temp = test_dir("parse-modes");
+        let dir = temp.path();
+        let path = dir.join("scene.fsv");
+        let data = FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000).to_bytes().unwrap();
+        std::fs::write(&path, data).unwrap();
+
+        let mut document = serde_json::to_value(read_fsv_metadata(&path).unwrap()).unwrap();
+        document["script_variants"][0]["nmae"] = serde_json::json!("typo");
+        let report = put_fsv_metadata(&path, &document.to_string(), false).unwrap().unwrap();
+        assert!(report.warnings().any(|issue| issue.message == "Unknown field in metadata: script_variants[0].nmae"));
+        // Kept on parse, so lenient readers round-trip it
+        assert!(read_fsv_metadata(&path).unwrap().script_variants[0].extra.contains_key("nmae"));
+
+        let report = validate_fsv_report(&path, NameMatching::Strict).unwrap();
+        assert!(matches!(report.state, FsvState::Valid) && !report.warnings().any(|issue| issue.message.starts_with("Unknown field")));
+        let report = validate_fsv_report_with(&path, &ValidateOptions { parse_mode: ParseMode::Warn, ..ValidateOptions::default() }).unwrap();
+        assert!(matches!(report.state, FsvState::Valid) && report.warnings().any(|issue| issue.message.starts_with("Unknown field")));
+        let report = validate_fsv_report_with(&path, &ValidateOptions { parse_mode: ParseMode::Strict, ..ValidateOptions::default() }).unwrap();
+        assert!(matches!(&report.state, FsvState::MetadataInvalid(MetadataInvalidReason::UnknownField(path)) if path == "script_variants[0].nmae"));
+    }
+
+    #[test]
+    fn test_deep_validation() {
+        let mut builder = FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 5000).script("other.funscript", SCRIPT, 1000);
+        builder.metadata_mut().script_variants[1].checksum = get_file_hash(b"replaced");
+        let data = builder.to_bytes().unwrap();
+        let validate = |options: &ValidateOptions| FsvContainer::from_reader(std::io::Cursor::new(data.clone())).unwrap().validate_report_with(options).unwrap();
+
+        let report = validate(&ValidateOptions::default());
+        assert!(matches!(report.state, FsvState::Valid) && report.warnings().all(|issue| !issue.message.starts_with("Script lasts")));
+
+        let report = validate(&ValidateOptions { deep: true, ..ValidateOptions::default() });
+        assert!(matches!(report.state, FsvState::ContentIncomplete(ContentIncompleteReason::ChecksumMismatch(ItemType::Script))));
+        assert_eq!(report.errors().filter_map(|issue| issue.item.as_deref()).collect::<Vec<_>>(), ["other.funscript"]);
+        let drifted: Vec<_> = report.warnings().filter(|issue| issue.message.starts_with("Script lasts")).collect();
+        assert_eq!(drifted.len(), 1);
+        assert_eq!(drifted[0].message, "Script lasts 1000 ms, but the metadata says 5000 ms");
+        // The test video is only a header, which ffprobe (if there is one) can't measure
+        assert!(report.warnings().any(|issue| issue.message.starts_with("Unable to probe video duration")));
+
+        let report = validate(&ValidateOptions { deep: true, duration_tolerance: DurationMs::from_millis(5000), ..ValidateOptions::default() });
+        assert!(report.warnings().all(|issue| !issue.message.starts_with("Script lasts")));
+    }
+
+    #[test]
+    fn test_unknown_fields_survive_edits() {
+        let temp = test_dir("unknown-fields");
+        let dir = temp.path();
+        let path = dir.join("scene.fsv");
+        let mut builder = FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000)
+            .script("other.funscript", SCRIPT, 1000).subtitle("video.srt", "en", b"1\n00:00:00,000 --> 00:00:01,000\nHi\n");
+        let metadata = builder.metadata_mut();
+        let unknown = serde_json::json!({ "by": "other-tool" });
+        metadata.add_script_creator(WorkCreatorsMetadata::new("video.funscript".to_string(), String::new(), CreatorInfo::new("Scripter".to_string(), vec![])));
+        metadata.set_extra_field("x_rating", &unknown).unwrap();
+        metadata.creators.set_extra_field("x_rating", &unknown).unwrap();
+        metadata.creators.scripts[0].set_extra_field("x_rating", &unknown).unwrap();
+        metadata.creators.scripts[0].creator_info.set_extra_field("x_rating", &unknown).unwrap();
+        metadata.video_formats[0].set_extra_field("x_rating", &unknown).unwrap();
+        metadata.script_variants[0].set_extra_field("x_rating", &unknown).unwrap();
+        metadata.subtitle_tracks[0].set_extra_field("x_rating", &unknown).unwrap();
+        let mut offset = PairOffset::new("video.funscript".to_string(), "video.mp4".to_string(), 100);
+        offset.extra.insert("x_rating".to_string(), unknown.clone());
+        offsets::set_pair_offsets(metadata, &[offset]).unwrap();
+        std::fs::write(&path, builder.to_bytes().unwrap()).unwrap();
+
+        edit_fsv_title(&path, "Renamed").unwrap();
+        edit_fsv_localized_title(&path, "ja", "夏").unwrap();
+        edit_fsv_description(&path, ItemType::Script, "video.funscript", "Slow").unwrap();
+        edit_fsv_description(&path, ItemType::Video, "video.mp4", "1080p").unwrap();
+        set_pair_offset(&path, "video.funscript", "video.mp4", Some(250)).unwrap();
+        remove_from_fsv(&path, EntryType::Script, "other.funscript").unwrap();
+        enable_fsv_history(&path).unwrap();
+        normalize_fsv_metadata(&path).unwrap();
+
+        let metadata = read_fsv_metadata(&path).unwrap();
+        fn kept(item: &impl ExtraFields) -> bool {
+            item.extra_field::<serde_json::Value>("x_rating").unwrap() == Some(serde_json::json!({ "by": "other-tool" }))
+        }
+        assert!(kept(&metadata) && kept(&metadata.creators) && kept(&metadata.creators.scripts[0]) && kept(&metadata.creators.scripts[0].creator_info));
+        assert!(kept(&metadata.video_formats[0]) && kept(&metadata.script_variants[0]) && kept(&metadata.subtitle_tracks[0]));
+        let pair_offsets = offsets::pair_offsets_from_metadata(&metadata).unwrap();
+        assert_eq!(pair_offsets[0].offset_ms, 250);
+        assert_eq!(pair_offsets[0].extra.get("x_rating"), Some(&unknown));
+    }
+
+    #[test]
+    fn test_edit_description() {
+        let temp = test_dir("edit-description");
+        let dir = temp.path();
+        let path = dir.join("scene.fsv");
+        let data = FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000).to_bytes().unwrap();
+        std::fs::write(&path, data).unwrap();
+
+        edit_fsv_description(&path, ItemType::Script, "video.funscript", " Slow build-up ").unwrap();
+        assert_eq!(read_fsv_metadata(&path).unwrap().script_variants[0].description, "Slow build-up");
+        assert!(matches!(edit_fsv_description(&path, ItemType::Video, "video.funscript", "1080p"), Err(FsvEditError::ItemNotFound(ItemType::Video, _))));
+        edit_fsv_description(&path, ItemType::Script, "video.funscript", "").unwrap();
+        assert!(read_fsv_metadata(&path).unwrap().script_variants[0].description.is_empty());
+    }
+
+    #[test]
+    fn test_item_content_checks() {
+        let data = FsvBuilder::new("scene")
+            .video("video.mp4", b"not a video", 1000)
+            .script("video.funscript", b"{}", 1000)
+            .subtitle("video.srt", "en", b"1\n00:00:01,000 --> 00:00:02,000\nHello\n")
+            .to_bytes()
+            .unwrap();
+        let mut container = FsvContainer::from_reader(std::io::Cursor::new(data)).unwrap();
+
+        let report = container.validate_report(NameMatching::Strict).unwrap();
+        assert!(matches!(report.state, FsvState::ContentIncomplete(ContentIncompleteReason::MalformedItem(ItemType::Video, _))));
+        let errors: Vec<_> = report.errors().map(|issue| issue.item.as_deref()).collect();
+        assert_eq!(errors, [Some("video.mp4"), Some("video.funscript")]);
+    }
+
+    #[test]
+    fn test_extract_subtitles() {
+        let srt: &[u8] = b"1\n00:00:01,000 --> 00:00:02,000\nHello\n";
+        let data = FsvBuilder::new("scene")
+            .video("video.mp4", VIDEO, 1000)
+            .script("video.funscript", SCRIPT, 1000)
+            .subtitle("english.srt", "EN_us", srt)
+            .subtitle("japanese.vtt", "ja", b"WEBVTT\n\n00:01.000 --> 00:02.000\nHello\n")
+            .subtitle("french.srt", "fr", srt)
+            .subtitle("english-sdh.srt", "en-us", srt)
+            .subtitle("unknown.SRT", "", srt)
+            .to_bytes()
+            .unwrap();
+        let temp = test_dir("extract-subtitles");
+        let output_dir = temp.path().join("out");
+        let extract = |subtitle_languages: &[&str]| {
+            let mut container = FsvContainer::from_reader(std::io::Cursor::new(data.clone())).unwrap();
+            let options = ExtractOptions { subtitle_languages: subtitle_languages.iter().map(|language| language.to_string()).collect(), ..Default::default() };
+            container.extract(&output_dir, "scene", &options).unwrap();
+            let mut files: Vec<_> = std::fs::read_dir(output_dir.join("scene")).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().to_string()).collect();
+            files.sort();
+            std::fs::remove_dir_all(&output_dir).unwrap();
+            files
+        };
+
+        assert_eq!(extract(&["en", "ja"]), ["video_video.en-US.2.srt", "video_video.en-US.srt", "video_video.funscript", "video_video.ja.vtt", "video_video.mp4"]);
+        assert_eq!(extract(&[]), ["video_video.en-US.2.srt", "video_video.en-US.srt", "video_video.fr.srt", "video_video.funscript", "video_video.ja.vtt", "video_video.mp4", "video_video.srt"]);
+    }
+
+    #[test]
+    fn test_extract_windows_safe_paths() {
+        let long_name = "a".repeat(240);
+        let data = FsvBuilder::new("CON")
+            .video(&format!("{}.mp4", long_name), VIDEO, 1000)
+            .script("scene.funscript", SCRIPT, 1000)
+            .subtitle("scene.srt", "en", b"1\n00:00:01,000 --> 00:00:02,000\nHello\n")
+            .to_bytes()
+            .unwrap();
+        let temp = test_dir("extract-windows");
+        let output_dir = temp.path().join("out");
+        let extract = |path_safety: PathSafety| {
+            let mut container = FsvContainer::from_reader(std::io::Cursor::new(data.clone())).unwrap();
+            container.extract(&output_dir, "scene", &ExtractOptions { path_safety, ..Default::default() })
+        };
+
+        extract(PathSafety::Sanitize).unwrap();
+        let extraction_path = output_dir.join("CON_");
+        let files: Vec<_> = std::fs::read_dir(&extraction_path).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().to_string()).collect();
+        assert_eq!(files.len(), 3);
+        for file in &files {
+            let path = std::path::absolute(extraction_path.join(file)).unwrap();
+            assert!(path.to_string_lossy().encode_utf16().count() <= path_safety::MAX_PATH_LENGTH);
+            assert!(file.starts_with("aaa"));
+        }
+        // The pair shares its shortened stem, so players still match them up
+        let stems: HashSet<_> = files.iter().map(|file| file.split('.').next().unwrap()).collect();
+        assert_eq!(stems.len(), 1);
+        std::fs::remove_dir_all(&output_dir).unwrap();
+
+        assert!(matches!(extract(PathSafety::Fail), Err(FsvExtractError::UnsafePath(PathSafetyError::ReservedName(_)))));
+        assert!(!output_dir.exists());
+    }
+
+    #[test]
+    fn test_notes() {
+        let temp = test_dir("notes");
+        let dir = temp.path();
+        let path = dir.join("scene.fsv");
+        std::fs::write(&path, FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000).to_bytes().unwrap()).unwrap();
+        let notes_path = dir.join("readme.txt");
+        std::fs::write(&notes_path, "# Usage\nStart slow.\n").unwrap();
+        assert_eq!(read_fsv_notes(&path).unwrap(), None);
+
+        add_fsv_notes(&path, &notes_path, notes::DEFAULT_NOTES_NAME).unwrap();
+        assert_eq!(read_fsv_notes(&path).unwrap().as_deref(), Some("# Usage\nStart slow.\n"));
+        let info = get_fsv_info(&path).unwrap();
+        assert_eq!(info.notes, Some((notes::DEFAULT_NOTES_NAME.to_string(), true)));
+        assert!(!info.extra_files.iter().any(|name| name == notes::DEFAULT_NOTES_NAME) && info.extensions.iter().all(|extension| extension.issues.is_empty()));
+
+        // Replacing the notes under another name drops the old entry
+        add_fsv_notes(&path, &notes_path, "readme.txt").unwrap();
+        let names: Vec<String> = FsvContainer::open(&path).unwrap().entry_names().map(str::to_string).collect();
+        assert!(names.contains(&"readme.txt".to_string()) && !names.contains(&notes::DEFAULT_NOTES_NAME.to_string()));
+        assert!(matches!(add_fsv_notes(&path, &notes_path, "video.mp4"), Err(FsvNotesError::NameInUse(_))));
+        std::fs::write(&notes_path, [0xff, 0xfe]).unwrap();
+        assert!(matches!(add_fsv_notes(&path, &notes_path, "readme.txt"), Err(FsvNotesError::NotText(_))));
+
+        let output_dir = dir.join("out");
+        extract_fsv(&path, &output_dir, &ExtractOptions::default()).unwrap();
+        assert_eq!(std::fs::read_to_string(output_dir.join("scene").join("readme.txt")).unwrap(), "# Usage\nStart slow.\n");
+    }
+
+    #[test]
+    fn test_axis_script_bundle() {
+        let build = |axis_script: Option<&'static [u8]>| {
+            let mut builder = FsvBuilder::new("scene")
+                .video("video.mp4", VIDEO, 1000)
+                .script("video.funscript", SCRIPT, 1000);
+            if let Some(data) = axis_script {
+                builder = builder.entry("video.roll.funscript", data);
+            }
+
+            builder.metadata_mut().script_variants[0].additional_axes = vec!["roll".to_string()];
+            FsvContainer::from_reader(std::io::Cursor::new(builder.to_bytes().unwrap())).unwrap().validate_report(NameMatching::Strict).unwrap()
+        };
+
+        assert_eq!(axis_script_names(&ScriptVariant::new("a.b.funscript".to_string(), String::new(), vec!["roll".to_string()], DurationMs::from_millis(0), 0, String::new())), [("roll".to_string(), "a.b.roll.funscript".to_string())]);
+        assert!(matches!(build(Some(SCRIPT)).state, FsvState::Valid));
+        assert!(matches!(build(None).state, FsvState::ContentIncomplete(ContentIncompleteReason::MissingAxisScript(_, _))));
+
+        let longer = br#"{"actions":[{"at":0,"pos":0},{"at":3000,"pos":100}],"inverted":false,"range":100,"version":"1.0"}"#;
+        let report = build(Some(longer));
+        assert!(matches!(report.state, FsvState::Valid));
+        assert!(report.warnings().any(|issue| issue.item.as_deref() == Some("video.roll.funscript")));
+    }
+
+    #[test]
+    fn test_duplicate_metadata_entries() {
+        let mut builder = FsvBuilder::new("scene")
+            .video("video.mp4", VIDEO, 1000)
+            .script("video.funscript", SCRIPT, 1000);
+        builder.metadata_mut().add_video_format(VideoFormat::new("video.mp4".to_string(), String::new(), DurationMs::from_millis(1000), get_file_hash(VIDEO)));
+        let mut container = FsvContainer::from_reader(std::io::Cursor::new(builder.to_bytes().unwrap())).unwrap();
+        assert!(container.duplicate_entries().is_empty());
+
+        let state = container.validate(NameMatching::Strict).unwrap();
+        assert!(matches!(state, FsvState::ContentIncomplete(ContentIncompleteReason::DuplicateItemEntry(ItemType::Video))));
+
+        let mut metadata = container.metadata().unwrap();
+        assert_eq!(dedup_items(&mut metadata.video_formats), ["video.mp4"]);
+        assert_eq!(metadata.video_formats.len(), 1);
+    }
+
+    /// Insert a local entry named `name` right before the central directory, without adding it to the directory.
+    fn with_stray_entry(data: Vec<u8>, name: &str) -> Vec<u8> {
+        let directory_start = zip::ZipArchive::new(std::io::Cursor::new(&data)).unwrap().central_directory_start() as usize;
+        let content = b"stray";
+        let mut stray = vec![0x50, 0x4b, 0x03, 0x04, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        stray.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        stray.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        stray.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        stray.extend_from_slice(&[0, 0]);
+        stray.extend_from_slice(name.as_bytes());
+        stray.extend_from_slice(content);
+
+        let mut patched = data[..directory_start].to_vec();
+        patched.extend_from_slice(&stray);
+        patched.extend_from_slice(&data[directory_start..]);
+        // The end of central directory record (22 bytes, then the comment) stores where the directory starts
+        let comment_len = magic::read_archive_comment(&mut std::io::Cursor::new(&data)).unwrap().unwrap().len();
+        let offset_field = patched.len() - comment_len - 6;
+        patched[offset_field..offset_field + 4].copy_from_slice(&((directory_start + stray.len()) as u32).to_le_bytes());
+        patched
+    }
+
+    #[test]
+    fn test_archive_structure() {
+        let data = FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000).to_bytes().unwrap();
+        let container = FsvContainer::from_reader(std::io::Cursor::new(data.clone())).unwrap();
+        assert!(container.structure().metadata_first && container.structure().unknown_data().is_empty());
+
+        let mut container = FsvContainer::from_reader(std::io::Cursor::new(with_stray_entry(data.clone(), "notes.txt"))).unwrap();
+        assert_eq!(container.structure().stray_entries, ["notes.txt"]);
+        let report = container.validate_report(NameMatching::Strict).unwrap();
+        assert!(matches!(report.state, FsvState::Valid));
+        assert!(report.warnings().any(|issue| issue.item.as_deref() == Some("notes.txt")));
+
+        let mut container = FsvContainer::from_reader(std::io::Cursor::new(with_stray_entry(data.clone(), "metadata.json"))).unwrap();
+        let report = container.validate_report(NameMatching::Strict).unwrap();
+        assert!(matches!(report.state, FsvState::MetadataInvalid(MetadataInvalidReason::MultipleMetadataFiles)));
+
+        let temp = test_dir("structure");
+        let dir = temp.path();
+        let path = dir.join("scene.fsv");
+        std::fs::write(&path, with_stray_entry(data, "notes.txt")).unwrap();
+        let strict = RebuildOptions { strict: true, ..Default::default() };
+        assert!(matches!(rebuild_fsv(&path, &strict), Err(FsvRebuildError::UnknownData(names)) if names == ["notes.txt"]));
+        rebuild_fsv(&path, &RebuildOptions { discard_unknown: true, ..strict }).unwrap();
+        let container = FsvContainer::from_reader(File::open(&path).unwrap()).unwrap();
+        assert!(container.structure().unknown_data().is_empty());
+
+    }
+
+    #[test]
+    fn test_reproducible_build() {
+        let build = |reproducible: bool| FsvBuilder::new("scene")
+            .script("b.funscript", b"script", 1000)
+            .video("a.mp4", b"video", 1000)
+            .reproducible(reproducible)
+            .to_bytes()
+            .unwrap();
+        let first = build(true);
+        assert_eq!(first, build(true));
+
+        let container = FsvContainer::from_reader(std::io::Cursor::new(first)).unwrap();
+        assert_eq!(container.entry_names().collect::<Vec<_>>(), ["metadata.json", "a.mp4", "b.funscript"]);
+    }
+
+    #[test]
+    fn test_is_safe_entry_name() {
+        assert!(is_safe_entry_name("video.mp4"));
+        assert!(is_safe_entry_name("scripts/scene..funscript"));
+        assert!(!is_safe_entry_name("../video.mp4"));
+        assert!(!is_safe_entry_name("scripts\\..\\..\\evil.exe"));
+        assert!(!is_safe_entry_name("/etc/passwd"));
+        assert!(!is_safe_entry_name("C:video.mp4"));
+    }
+
+    #[test]
+    fn test_sanitize_path_component() {
+        assert_eq!(sanitize_path_component("My Scene"), Some("My Scene".to_string()));
+        assert_eq!(sanitize_path_component("../../etc"), Some(".._.._etc".to_string()));
+        assert_eq!(sanitize_path_component("a/b:c"), Some("a_b_c".to_string()));
+        assert_eq!(sanitize_path_component(".."), None);
+        assert_eq!(sanitize_path_component("  "), None);
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_creator_key_from_name() {
+        assert_eq!(creator_key_from_name("Some Scripter"), "some-scripter");
+        assert_eq!(creator_key_from_name("  J. Doe (EU) "), "j-doe-eu");
+    }
+
+    #[test]
+    fn test_checksum_status_dispatch() {
+        for algorithm in [HashAlgorithm::Sha256, HashAlgorithm::Blake3, HashAlgorithm::Xxh3] {
+            let checksum = get_file_checksum(VIDEO, algorithm);
+            assert_eq!(checksum_status(&checksum, &mut &VIDEO[..]), ChecksumStatus::Match);
+            assert_eq!(checksum_status(&checksum, &mut &SCRIPT[..]), ChecksumStatus::Mismatch);
+        }
+
+        assert_eq!(checksum_status(&get_file_checksum(VIDEO, HashAlgorithm::Sha1), &mut &VIDEO[..]), ChecksumStatus::Deprecated);
+        assert_eq!(checksum_status("sha512:abcd", &mut &VIDEO[..]), ChecksumStatus::Unsupported);
+        assert_eq!(checksum_status("sha256:abcd", &mut &VIDEO[..]), ChecksumStatus::Malformed);
+        assert_eq!(checksum_status("", &mut &VIDEO[..]), ChecksumStatus::Missing);
+    }
+
+    #[test]
+    fn test_staged_files() {
+        let temp = test_dir("staged-files");
+        let dir = temp.path();
+        let fsv_path = dir.join("scene.fsv");
+        let mut builder = FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000);
+        builder.metadata_mut().add_script_variant(ScriptVariant::new("video.funscript".to_string(), get_file_hash(SCRIPT), vec![], DurationMs::from_millis(1000), 0, String::new()));
+        std::fs::write(&fsv_path, builder.to_bytes().unwrap()).unwrap();
+        let script_path = dir.join("video.funscript");
+        std::fs::write(&script_path, SCRIPT).unwrap();
+
+        let mut staged = StagedFiles::new(&fsv_path, rebuild_entry_options());
+        let checksum = staged.stage("video.funscript", &script_path, HashAlgorithm::default()).unwrap();
+        assert_eq!(checksum.to_string(), get_file_hash(SCRIPT));
+        assert!(staged.contains("video.funscript") && fsv_path.with_extension("stage").exists());
+
+        let metadata = read_fsv_metadata(&fsv_path).unwrap();
+        let archive = zip::ZipArchive::new(File::open(&fsv_path).unwrap()).unwrap();
+        // The staged entry is copied from the staging archive, not read from the (since changed) file
+        std::fs::write(&script_path, b"changed").unwrap();
+        rebuild_archive_staged(&fsv_path, archive, &metadata, vec![AddFile::new("video.funscript", &script_path)], vec![], &mut staged).unwrap();
+        drop(staged);
+        assert!(!fsv_path.with_extension("stage").exists());
+        assert_eq!(read_fsv_entry(&fsv_path, "video.funscript").unwrap(), SCRIPT);
+        assert!(matches!(validate_fsv_report_with(&fsv_path, &ValidateOptions { deep: true, ..ValidateOptions::default() }).unwrap().state, FsvState::Valid));
+
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "native")]
+    async fn test_add_conflict() {
+        let temp = test_dir("add-conflict");
+        let dir = temp.path();
+        let fsv_path = dir.join("scene.fsv");
+        std::fs::write(&fsv_path, FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000).to_bytes().unwrap()).unwrap();
+        let longer = br#"{"actions":[{"at":0,"pos":0},{"at":3000,"pos":100}],"inverted":false,"range":100,"version":"1.0"}"#;
+        let script_path = dir.join("video.funscript");
+        std::fs::write(&script_path, longer).unwrap();
+        let db_client = DbClient::in_memory().await.unwrap();
+        let add = |on_conflict| AddArgs::new(fsv_path.clone(), ItemType::Script, script_path.clone(), None).on_conflict(on_conflict);
+
+        add_to_fsv(add(None), &db_client, false).await.unwrap();
+        assert_eq!(read_fsv_metadata(&fsv_path).unwrap().script_variants[0].duration, DurationMs::from_millis(1000));
+
+        add_to_fsv(add(Some(AddConflict::Overwrite)), &db_client, false).await.unwrap();
+        let metadata = read_fsv_metadata(&fsv_path).unwrap();
+        assert_eq!(metadata.script_variants.len(), 1);
+        assert_eq!((metadata.script_variants[0].duration, metadata.script_variants[0].checksum.as_str()), (DurationMs::from_millis(3000), get_file_hash(longer).as_str()));
+        assert_eq!(read_fsv_entry(&fsv_path, "video.funscript").unwrap(), longer);
+        assert!(!fsv_path.with_extension("stage").exists());
+
+        add_to_fsv(add(Some(AddConflict::Rename)), &db_client, false).await.unwrap();
+        let names: Vec<_> = read_fsv_metadata(&fsv_path).unwrap().script_variants.into_iter().map(|variant| variant.name).collect();
+        assert_eq!(names, ["video.funscript", "video (2).funscript"]);
+        assert_eq!(free_entry_name("video.roll.funscript", |name| name == "video (2).roll.funscript"), "video (3).roll.funscript");
+
+        db_client.pool.close().await;
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "native")]
+    async fn test_create_encrypted() {
+        let temp = test_dir("encrypted");
+        let dir = temp.path();
+        let fsv_path = dir.join("scene.fsv");
+        let script_path = dir.join("video.funscript");
+        std::fs::write(&script_path, SCRIPT).unwrap();
+        let db_client = DbClient::in_memory().await.unwrap();
+        let args = CreateArgs::new(fsv_path.clone(), "scene".to_string(), vec![], None, Some(script_path.clone()), None, None).encryption_password(Some("secret".to_string()));
+        create_fsv(args, &db_client, false).await.unwrap();
+
+        // metadata.json stays readable, the content needs the password
+        assert_eq!(read_fsv_metadata(&fsv_path).unwrap().title, "scene");
+        let read_script = |name: &str| {
+            let mut archive = zip::ZipArchive::new(File::open(&fsv_path).unwrap()).unwrap();
+            let mut data = Vec::new();
+            archive.by_name_decrypt(name, b"secret").unwrap().read_to_end(&mut data).unwrap();
+            data
+        };
+        assert_eq!(read_script("video.funscript"), SCRIPT);
+        let report = validate_fsv_report(&fsv_path, NameMatching::default()).unwrap();
+        assert!(report.issues.iter().any(|issue| issue.item.as_deref() == Some("video.funscript") && issue.message == "Script file is password protected"));
+
+        // Rebuilding copies the encrypted entries without reading them
+        let other_path = dir.join("other.funscript");
+        std::fs::write(&other_path, SCRIPT).unwrap();
+        add_to_fsv(AddArgs::new(fsv_path.clone(), ItemType::Script, other_path, None), &db_client, false).await.unwrap();
+        assert_eq!(read_script("video.funscript"), SCRIPT);
+        assert_eq!(read_fsv_entry(&fsv_path, "other.funscript").unwrap(), SCRIPT);
+
+        db_client.pool.close().await;
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "native")]
+    async fn test_sync_creators() {
+        let temp = test_dir("sync-creators");
+        let dir = temp.path();
+        let fsv_path = dir.join("scene.fsv");
+        let mut builder = FsvBuilder::new("scene").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000);
+        let creator = |name: &str, key: Option<&str>| WorkCreatorsMetadata::new("video.funscript".to_string(), String::new(), CreatorInfo::new(name.to_string(), vec![])).with_creator_key(key.map(str::to_string));
+        builder.metadata_mut().add_script_creator(creator("Old Name", Some("scripter")));
+        builder.metadata_mut().add_script_creator(creator("Newcomer", Some("newcomer")));
+        builder.metadata_mut().add_script_creator(creator("Anonymous", None));
+        std::fs::write(&fsv_path, builder.to_bytes().unwrap()).unwrap();
+        let db_client = DbClient::in_memory().await.unwrap();
+        db_client.insert_creator_info("scripter", &CreatorInfo::new("Scripter".to_string(), vec!["https://example.com/scripter".to_string()])).await.unwrap();
+
+        let report = sync_creators(&fsv_path, CreatorSyncDirection::Pull, true, &db_client).await.unwrap();
+        assert_eq!((report.unchanged, report.conflicts.len(), report.missing.as_slice(), report.unlinked.as_slice()), (0, 1, ["newcomer".to_string()].as_slice(), ["video.funscript".to_string()].as_slice()));
+        assert_eq!(read_fsv_metadata(&fsv_path).unwrap().creators.scripts[0].creator_info.name, "Old Name");
+
+        sync_creators(&fsv_path, CreatorSyncDirection::Pull, false, &db_client).await.unwrap();
+        let scripts = read_fsv_metadata(&fsv_path).unwrap().creators.scripts;
+        assert_eq!((scripts[0].creator_info.name.as_str(), scripts[0].creator_info.socials.as_slice()), ("Scripter", ["https://example.com/scripter".to_string()].as_slice()));
+        assert!(db_client.get_creator_info_by_key("newcomer").await.unwrap().is_none());
+
+        let report = sync_creators(&fsv_path, CreatorSyncDirection::Push, false, &db_client).await.unwrap();
+        assert_eq!((report.unchanged, report.conflicts.len()), (1, 0));
+        assert_eq!(db_client.get_creator_info_by_key("newcomer").await.unwrap().unwrap().name, "Newcomer");
+
+        // Socials are stored once in canonical form, labelled with their platform
+        assert!(db_client.add_social_to_creator("newcomer", "https://mobile.twitter.com/Newcomer/").await.unwrap());
+        assert!(!db_client.add_social_to_creator("newcomer", "x.com/newcomer").await.unwrap());
+        let records = db_client.search_creators("newcomer", None, 0).await.unwrap();
+        assert_eq!((records[0].creator_info.socials.as_slice(), records[0].social_platforms.as_slice()), (["https://x.com/newcomer".to_string()].as_slice(), ["Twitter/X".to_string()].as_slice()));
+
+        // Merging moves the loser's socials to the winner and relinks its credits in the file
+        assert!(matches!(db_client.merge_creators("scripter", "Scripter").await, Err(db_client::DbClientError::SelfMerge(..))));
+        let merge = db_client.merge_creators("scripter", "Newcomer").await.unwrap().unwrap();
+        assert_eq!((merge.loser_key.as_str(), merge.socials_moved), ("newcomer", 1));
+        assert!(db_client.get_creator_info_by_key("newcomer").await.unwrap().is_none());
+        let report = relink_merged_creator(&fsv_path, &merge.loser_key, &merge.winner_key, &db_client).await.unwrap();
+        assert_eq!((report.relinked.len(), report.conflicts.len()), (1, 2));
+        let scripts = read_fsv_metadata(&fsv_path).unwrap().creators.scripts;
+        assert_eq!(scripts[1].creator_key.as_deref(), Some("scripter"));
+        assert_eq!((scripts[1].creator_info.name.as_str(), scripts[1].creator_info.socials.len()), ("Scripter", 2));
+
+        // Without a persistent database, unknown keys are reported as unsearchable rather than missing
+        assert!(matches!(get_creator_info_from_key(&db_client, Some("nobody"), false).await, Err(FsvError::NoCreatorDatabase(key)) if key == "nobody"));
+
+        db_client.pool.close().await;
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "native")]
+    async fn test_import_script_creator() {
+        let db_client = DbClient::in_memory().await.unwrap();
+        db_client.insert_creator_info("scripter", &CreatorInfo::new("Scripter".to_string(), vec!["https://scripter.fanbox.cc".to_string()])).await.unwrap();
+        let script_metadata = |creator: &str, script_url: &str| FunscriptMetadata { creator: creator.to_string(), script_url: script_url.to_string(), ..Default::default() };
+        let import = |creator, script_url| {
+            let script_metadata = script_metadata(creator, script_url);
+            let db_client = &db_client;
+            async move { import_script_creator(db_client, &script_metadata, false).await.unwrap().map(|imported| (imported.key.unwrap(), imported.created)) }
+        };
+
+        // A social the script URL is a page of links the creator, even by another name
+        assert_eq!(import("", "https://scripter.fanbox.cc/posts/123").await, Some(("scripter".to_string(), false)));
+        assert_eq!(import("SCRIPTER", "").await, Some(("scripter".to_string(), false)));
+        assert_eq!(import("", "https://example.com/scripts/1").await, None);
+
+        // Unknown creators are saved once under a key derived from their name
+        assert_eq!(import("New Person", "https://example.com/scripts/1").await, Some(("new-person".to_string(), true)));
+        assert_eq!(import("New Person", "").await, Some(("new-person".to_string(), false)));
+    }
+}
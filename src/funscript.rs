@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+use crate::duration::DurationMs;
+
+pub mod transform;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Funscript {
     pub actions: Vec<FunscriptAction>,
@@ -10,16 +14,19 @@ pub struct Funscript {
     pub version: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FunscriptAction {
     pub at: u64,
     pub pos: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Optional block written by script editors. Editors fill in different subsets, so every field defaults to empty.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct FunscriptMetadata {
     pub creator: String,
     pub description: String,
+    /// In seconds, unlike every other duration here
     pub duration: u64,
     pub license: String,
     pub notes: String,
@@ -31,4 +38,79 @@ pub struct FunscriptMetadata {
     pub video_url: String,
 }
 
+/// Summary statistics over a funscript's actions. Speeds are in position units per second.
+#[derive(Debug, Clone, Default)]
+pub struct FunscriptStats {
+    pub action_count: usize,
+    pub duration: DurationMs,
+    pub min_position: u64,
+    pub max_position: u64,
+    pub average_speed: f64,
+    pub max_speed: f64,
+}
+
+impl Funscript {
+    fn sorted_actions(&self) -> Vec<&FunscriptAction> {
+        let mut actions: Vec<&FunscriptAction> = self.actions.iter().collect();
+        actions.sort_by_key(|a| a.at);
+        actions
+    }
+
+    pub fn stats(&self) -> FunscriptStats {
+        let actions = self.sorted_actions();
+        let mut stats = FunscriptStats {
+            action_count: actions.len(),
+            duration: actions.last().map(|a| DurationMs::from_millis(a.at)).unwrap_or_default(),
+            min_position: actions.iter().map(|a| a.pos).min().unwrap_or(0),
+            max_position: actions.iter().map(|a| a.pos).max().unwrap_or(0),
+            ..Default::default()
+        };
+
+        let mut total_distance = 0.0;
+        let mut total_time = 0.0;
+        for pair in actions.windows(2) {
+            let (prev, next) = (pair[0], pair[1]);
+            let dt = (next.at - prev.at) as f64 / 1000.0;
+            if dt <= 0.0 {
+                continue;
+            }
+
+            let distance = next.pos.abs_diff(prev.pos) as f64;
+            total_distance += distance;
+            total_time += dt;
+            stats.max_speed = stats.max_speed.max(distance / dt);
+        }
+
+        if total_time > 0.0 {
+            stats.average_speed = total_distance / total_time;
+        }
+
+        stats
+    }
+
+    /// Average speed per time bucket across the script's duration, for intensity heatmaps.
+    pub fn speed_heatmap(&self, buckets: usize) -> Vec<f64> {
+        let actions = self.sorted_actions();
+        let duration = actions.last().map(|a| a.at).unwrap_or(0);
+        if buckets == 0 || duration == 0 {
+            return vec![0.0; buckets];
+        }
+
+        let mut distance = vec![0.0; buckets];
+        let mut time = vec![0.0; buckets];
+        for pair in actions.windows(2) {
+            let (prev, next) = (pair[0], pair[1]);
+            if next.at <= prev.at {
+                continue;
+            }
+
+            let bucket = ((prev.at as u128 * buckets as u128) / duration as u128).min(buckets as u128 - 1) as usize;
+            distance[bucket] += next.pos.abs_diff(prev.pos) as f64;
+            time[bucket] += (next.at - prev.at) as f64 / 1000.0;
+        }
+
+        distance.iter().zip(time.iter()).map(|(d, t)| if *t > 0.0 { d / t } else { 0.0 }).collect()
+    }
+}
+
 // TODO: Double-check the Funscript format specification and implement parsing and validation functions.
\ No newline at end of file
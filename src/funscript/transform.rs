@@ -0,0 +1,115 @@
+use super::Funscript;
+
+/// Position transforms applied to a copy of a script. Speed scaling runs first, then range limiting, then inversion.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TransformOptions {
+    pub invert: bool,
+    /// Keep positions within `min..=max` (0-100)
+    pub range: Option<(u64, u64)>,
+    /// Stretch the script's own position range onto `range` instead of clamping to it
+    pub rescale: bool,
+    /// Multiply every move's distance from the midpoint (50), and with it its speed
+    pub speed: Option<f64>,
+}
+
+impl TransformOptions {
+    pub fn is_empty(&self) -> bool {
+        !self.invert && self.range.is_none() && self.speed.is_none()
+    }
+
+    /// Reason the options can't be applied, if any
+    pub fn check(&self) -> Result<(), String> {
+        if let Some((min, max)) = self.range && (min > max || max > 100) {
+            return Err(format!("Invalid range {}-{}: expected 0 <= min <= max <= 100", min, max));
+        }
+
+        if let Some(speed) = self.speed && !(speed.is_finite() && speed > 0.0) {
+            return Err(format!("Invalid speed factor {}: expected a positive number", speed));
+        }
+
+        Ok(())
+    }
+
+    /// Human readable summary of the transforms, e.g. `speed x0.5, range 20-80, inverted`
+    pub fn describe(&self) -> String {
+        let mut steps = Vec::new();
+        if let Some(speed) = self.speed {
+            steps.push(format!("speed x{}", speed));
+        }
+
+        if let Some((min, max)) = self.range {
+            let mode = if self.rescale { "rescaled to" } else { "range" };
+            steps.push(format!("{} {}-{}", mode, min, max));
+        }
+
+        if self.invert {
+            steps.push("inverted".to_string());
+        }
+
+        steps.join(", ")
+    }
+}
+
+/// Bake the script's `range` and `inverted` flag into its positions, leaving a plain 0-100 script.
+pub fn normalize(funscript: &mut Funscript) {
+    let range = funscript.range.max(1);
+    for action in &mut funscript.actions {
+        let pos = (action.pos * 100 / range).min(100);
+        action.pos = if funscript.inverted { 100 - pos } else { pos };
+    }
+
+    funscript.range = 100;
+    funscript.inverted = false;
+}
+
+/// Apply `options` to the script's positions (after normalizing it). Timing is left untouched, so sync is kept.
+pub fn transform(funscript: &mut Funscript, options: &TransformOptions) {
+    normalize(funscript);
+
+    if let Some(speed) = options.speed {
+        for action in &mut funscript.actions {
+            action.pos = (50.0 + (action.pos as f64 - 50.0) * speed).round().clamp(0.0, 100.0) as u64;
+        }
+    }
+
+    if let Some((min, max)) = options.range {
+        let lowest = funscript.actions.iter().map(|action| action.pos).min().unwrap_or(0);
+        let highest = funscript.actions.iter().map(|action| action.pos).max().unwrap_or(100);
+        for action in &mut funscript.actions {
+            action.pos = match options.rescale && highest > lowest {
+                true => min + ((action.pos - lowest) * (max - min) + (highest - lowest) / 2) / (highest - lowest),
+                false => action.pos.clamp(min, max),
+            };
+        }
+    }
+
+    if options.invert {
+        for action in &mut funscript.actions {
+            action.pos = 100 - action.pos;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform() {
+        let script = || serde_json::from_str::<Funscript>(r#"{ "actions": [{ "at": 0, "pos": 10 }, { "at": 500, "pos": 90 }, { "at": 1000, "pos": 50 }], "inverted": false, "range": 100, "version": "1.0" }"#).unwrap();
+        let positions = |options: TransformOptions| {
+            let mut funscript = script();
+            transform(&mut funscript, &options);
+            funscript.actions.iter().map(|action| action.pos).collect::<Vec<_>>()
+        };
+
+        assert_eq!(positions(TransformOptions { invert: true, ..Default::default() }), [90, 10, 50]);
+        assert_eq!(positions(TransformOptions { range: Some((20, 80)), ..Default::default() }), [20, 80, 50]);
+        assert_eq!(positions(TransformOptions { range: Some((0, 100)), rescale: true, ..Default::default() }), [0, 100, 50]);
+        assert_eq!(positions(TransformOptions { speed: Some(0.5), invert: true, ..Default::default() }), [70, 30, 50]);
+
+        let options = TransformOptions { invert: true, range: Some((20, 80)), rescale: false, speed: Some(0.5) };
+        assert_eq!(options.describe(), "speed x0.5, range 20-80, inverted");
+        assert!(TransformOptions { range: Some((80, 20)), ..Default::default() }.check().is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use std::{collections::HashMap, io::Read, path::Path, time::UNIX_EPOCH};
+#[cfg(feature = "native")]
+use std::{fs::File, io::BufReader};
+
+#[cfg(feature = "native")]
+use tracing::{debug, warn};
+
+use crate::checksum::HashAlgorithm;
+#[cfg(feature = "native")]
+use crate::{checksum::Checksum, db_client::DbClient};
+
+/// A cached digest. `stamp` is the file mtime (ns since the epoch) for files and the CRC-32 for archive entries;
+/// together with `size` it decides whether the cached digest is still valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedHashRecord {
+    pub entry: String,
+    pub size: i64,
+    pub stamp: i64,
+    pub digest: String,
+}
+
+/// Entry name used for plain files in the hash_cache table
+#[cfg(feature = "native")]
+const FILE_ENTRY: &str = "";
+
+/// Cache key for a path. Canonicalized so different spellings of the same file share one cache entry.
+fn source_key(path: &Path) -> String {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()).to_string_lossy().to_string()
+}
+
+/// File mtime in nanoseconds since the epoch, used to notice changed files.
+pub fn mtime_stamp(metadata: &std::fs::Metadata) -> Option<i64> {
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    i64::try_from(modified.as_nanos()).ok()
+}
+
+/// Checksum of the file at `path`, reusing the cached digest while the file's size and mtime are unchanged.
+/// Cache failures are logged and fall back to hashing the file.
+#[cfg(feature = "native")]
+pub async fn file_checksum(db_client: &DbClient, path: &Path, algorithm: HashAlgorithm) -> std::io::Result<Checksum> {
+    let metadata = std::fs::metadata(path)?;
+    if let Some(checksum) = cached_file_checksum(db_client, path, &metadata, algorithm).await {
+        return Ok(checksum);
+    }
+
+    let digest = algorithm.digest_reader(&mut BufReader::new(File::open(path)?))?;
+    let checksum = Checksum { algorithm, digest };
+    remember_file_checksum(db_client, path, &metadata, &checksum).await;
+
+    Ok(checksum)
+}
+
+/// The cached checksum of the file at `path`, whose file system metadata is `metadata`, if the file is unchanged since.
+/// Cache failures are logged and count as a miss.
+#[cfg(feature = "native")]
+pub async fn cached_file_checksum(db_client: &DbClient, path: &Path, metadata: &std::fs::Metadata, algorithm: HashAlgorithm) -> Option<Checksum> {
+    // Without an mtime a cached digest could never be invalidated
+    let stamp = mtime_stamp(metadata)?;
+    let size = metadata.len() as i64;
+    match db_client.get_cached_hashes(&source_key(path), algorithm.get_name()).await {
+        Ok(records) => {
+            let record = records.into_iter().find(|record| record.entry == FILE_ENTRY && record.size == size && record.stamp == stamp)?;
+            debug!(operation = "hash", item = %path.display(), outcome = "cached", "Using cached {} digest", algorithm);
+            Some(Checksum { algorithm, digest: record.digest })
+        },
+        Err(err) => {
+            warn!(operation = "hash", item = %path.display(), outcome = "failed", error = %err, "Unable to read hash cache");
+            None
+        },
+    }
+}
+
+/// Cache `checksum` for the file at `path`, e.g. one computed while copying the file. `metadata` must be taken before the file
+/// was read, so a file changed while it was hashed is not cached under its new mtime. Cache failures are logged.
+#[cfg(feature = "native")]
+pub async fn remember_file_checksum(db_client: &DbClient, path: &Path, metadata: &std::fs::Metadata, checksum: &Checksum) {
+    let Some(stamp) = mtime_stamp(metadata) else {
+        return;
+    };
+
+    let record = CachedHashRecord { entry: FILE_ENTRY.to_string(), size: metadata.len() as i64, stamp, digest: checksum.digest.clone() };
+    if let Err(err) = db_client.store_cached_hashes(&source_key(path), checksum.algorithm.get_name(), &[record]).await {
+        warn!(operation = "hash", item = %path.display(), outcome = "failed", error = %err, "Unable to update hash cache");
+    }
+}
+
+/// Digests of the entries of one archive, valid while an entry's CRC-32 and size are unchanged.
+/// Hashing archives is synchronous, so the cache is loaded up front, handed to the hashing code, and saved afterwards.
+#[derive(Debug)]
+pub struct EntryHashCache {
+    #[cfg_attr(not(feature = "native"), allow(dead_code))]
+    source: String,
+    algorithm: HashAlgorithm,
+    cached: HashMap<String, CachedHashRecord>,
+    computed: Vec<CachedHashRecord>,
+    hits: usize,
+}
+
+impl EntryHashCache {
+    /// A cache with nothing stored yet, e.g. when no database is available.
+    pub fn empty(archive_path: &Path, algorithm: HashAlgorithm) -> Self {
+        EntryHashCache { source: source_key(archive_path), algorithm, cached: HashMap::new(), computed: Vec::new(), hits: 0 }
+    }
+
+    #[cfg(feature = "native")]
+    pub async fn load(db_client: &DbClient, archive_path: &Path, algorithm: HashAlgorithm) -> Self {
+        let mut cache = EntryHashCache::empty(archive_path, algorithm);
+        match db_client.get_cached_hashes(&cache.source, algorithm.get_name()).await {
+            Ok(records) => cache.cached = records.into_iter().map(|record| (record.entry.clone(), record)).collect(),
+            Err(err) => warn!(operation = "hash", archive = %archive_path.display(), outcome = "failed", error = %err, "Unable to read hash cache"),
+        }
+
+        cache
+    }
+
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// Number of digests served from the cache so far
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Digest of an archive entry, read from `reader` only if no valid cached digest exists.
+    pub fn digest(&mut self, name: &str, crc32: u32, size: u64, reader: &mut impl Read) -> std::io::Result<String> {
+        let size = size as i64;
+        let stamp = i64::from(crc32);
+        if let Some(record) = self.cached.get(name) && record.size == size && record.stamp == stamp {
+            self.hits += 1;
+            return Ok(record.digest.clone());
+        }
+
+        let digest = self.algorithm.digest_reader(reader)?;
+        let record = CachedHashRecord { entry: name.to_string(), size, stamp, digest: digest.clone() };
+        self.cached.insert(name.to_string(), record.clone());
+        self.computed.push(record);
+
+        Ok(digest)
+    }
+
+    /// Store newly computed digests. Failures are logged, since the cache is only an optimization.
+    #[cfg(feature = "native")]
+    pub async fn save(self, db_client: &DbClient) {
+        if self.computed.is_empty() {
+            return;
+        }
+
+        if let Err(err) = db_client.store_cached_hashes(&self.source, self.algorithm.get_name(), &self.computed).await {
+            warn!(operation = "hash", archive = %self.source, outcome = "failed", error = %err, "Unable to update hash cache");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_hash_cache_invalidation() {
+        let mut cache = EntryHashCache::empty(Path::new("test.fsv"), HashAlgorithm::Blake3);
+        let digest = cache.digest("a.mp4", 1, 4, &mut &b"data"[..]).unwrap();
+        assert_eq!(digest, HashAlgorithm::Blake3.digest(b"data"));
+
+        // Same fingerprint: the reader is not consulted
+        assert_eq!(cache.digest("a.mp4", 1, 4, &mut &b"other"[..]).unwrap(), digest);
+        assert_eq!(cache.hits(), 1);
+
+        // Changed CRC: rehashed
+        assert_eq!(cache.digest("a.mp4", 2, 5, &mut &b"other"[..]).unwrap(), HashAlgorithm::Blake3.digest(b"other"));
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.computed.len(), 2);
+    }
+}
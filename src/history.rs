@@ -0,0 +1,145 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::{extensions, metadata::FsvMetadata};
+
+/// Metadata field holding the `fsv.history` extension data.
+pub const HISTORY_FIELD: &str = "history";
+
+/// One change made to an archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// UTC, e.g. `2024-05-01T12:30:00Z`
+    pub timestamp: String,
+    /// What was done, e.g. `edit title` or `remove video scene.mp4`
+    pub operation: String,
+    /// Name and version of the program that made the change
+    pub tool: String,
+}
+
+impl HistoryEntry {
+    pub fn now(operation: &str) -> Self {
+        HistoryEntry { timestamp: utc_timestamp(SystemTime::now()), operation: operation.to_string(), tool: tool_version() }
+    }
+}
+
+pub fn tool_version() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+/// `time` as an ISO 8601 UTC timestamp with second precision.
+pub fn utc_timestamp(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+    let (days, rest) = (seconds / 86_400, seconds % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm), shifted to eras starting in March
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, rest / 3600, rest % 3600 / 60, rest % 60)
+}
+
+pub fn is_enabled(metadata: &FsvMetadata) -> bool {
+    metadata.extensions.iter().any(|id| id == extensions::HISTORY_EXTENSION)
+}
+
+/// Declare the `fsv.history` extension, so later changes are recorded. Returns false if it already was.
+pub fn enable(metadata: &mut FsvMetadata) -> bool {
+    if is_enabled(metadata) {
+        return false;
+    }
+
+    metadata.extensions.push(extensions::HISTORY_EXTENSION.to_string());
+    metadata.extra.entry(HISTORY_FIELD.to_string()).or_insert_with(|| Value::Array(Vec::new()));
+    true
+}
+
+/// Keep the history of `current` when its metadata is replaced by `replacement` (e.g. an older snapshot being restored),
+/// so the audit trail isn't rolled back along with the rest.
+pub fn carry_over(current: &FsvMetadata, replacement: &mut FsvMetadata) {
+    if !is_enabled(current) {
+        return;
+    }
+
+    enable(replacement);
+    if let Some(history) = current.extra.get(HISTORY_FIELD) {
+        replacement.extra.insert(HISTORY_FIELD.to_string(), history.clone());
+    }
+}
+
+/// Read the change history declared in metadata. A missing field means no recorded changes.
+pub fn history_from_metadata(metadata: &FsvMetadata) -> Result<Vec<HistoryEntry>, serde_json::Error> {
+    match metadata.extra.get(HISTORY_FIELD) {
+        Some(value) => serde_json::from_value(value.clone()),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Append `operation` to the history, if the archive declares the `fsv.history` extension. A malformed history is left
+/// alone (with a warning) rather than failing the change it would record.
+pub fn record(metadata: &mut FsvMetadata, operation: &str) {
+    if !is_enabled(metadata) {
+        return;
+    }
+
+    let entry = HistoryEntry::now(operation);
+    let entry = serde_json::json!({ "timestamp": entry.timestamp, "operation": entry.operation, "tool": entry.tool });
+    match metadata.extra.entry(HISTORY_FIELD.to_string()).or_insert_with(|| Value::Array(Vec::new())) {
+        Value::Array(entries) => entries.push(entry),
+        _ => warn!(operation = %operation, outcome = "skipped", "Malformed '{}' field, not recording the operation", HISTORY_FIELD),
+    }
+}
+
+pub fn validate_history(metadata: &FsvMetadata, _entry_names: &[&str]) -> Vec<String> {
+    let Some(value) = metadata.extra.get(HISTORY_FIELD) else {
+        return vec![format!("Missing '{}' field", HISTORY_FIELD)];
+    };
+
+    let Value::Array(entries) = value else {
+        return vec![format!("'{}' must be an array", HISTORY_FIELD)];
+    };
+
+    let mut issues = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        for field in ["timestamp", "operation", "tool"] {
+            if entry.get(field).and_then(Value::as_str).is_none() {
+                issues.push(format!("History entry {} has no '{}'", i, field));
+            }
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semver::Version;
+
+    #[test]
+    fn test_history() {
+        assert_eq!(utc_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(utc_timestamp(UNIX_EPOCH + std::time::Duration::from_secs(1_709_210_096)), "2024-02-29T12:34:56Z");
+
+        let mut metadata = FsvMetadata::new(Version::new(1, 0, 0));
+        record(&mut metadata, "edit title");
+        assert!(!metadata.extra.contains_key(HISTORY_FIELD));
+
+        assert!(enable(&mut metadata));
+        record(&mut metadata, "edit title");
+        record(&mut metadata, "remove video scene.mp4");
+        let history = history_from_metadata(&metadata).unwrap();
+        assert_eq!(history.iter().map(|entry| entry.operation.as_str()).collect::<Vec<_>>(), ["edit title", "remove video scene.mp4"]);
+        assert_eq!(history[0].tool, tool_version());
+        assert!(validate_history(&metadata, &[]).is_empty());
+    }
+}
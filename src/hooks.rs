@@ -0,0 +1,207 @@
+use std::{io::Write, path::{Path, PathBuf}, process::{Command, ExitStatus, Stdio}};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+#[derive(Debug, Error)]
+pub enum HookError {
+    #[error("Unable to run hook '{0}': {1}")]
+    Launch(String, std::io::Error),
+    #[error("Hook '{0}' refused the {1} ({2})")]
+    Rejected(String, HookOperation, ExitStatus),
+}
+
+/// Operations hooks run around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookOperation {
+    Create,
+    Add,
+    Remove,
+    Import,
+}
+
+impl HookOperation {
+    pub fn get_name(&self) -> &str {
+        match self {
+            HookOperation::Create => "create",
+            HookOperation::Add => "add",
+            HookOperation::Remove => "remove",
+            HookOperation::Import => "import",
+        }
+    }
+}
+
+impl std::fmt::Display for HookOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.get_name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookStage {
+    Pre,
+    Post,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookOutcome {
+    Succeeded,
+    Failed,
+}
+
+/// An external command run around operations, without a shell.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    /// Executable, looked up on PATH
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Operations the hook runs for; all of them if empty
+    #[serde(default)]
+    pub operations: Vec<HookOperation>,
+}
+
+impl Hook {
+    fn applies_to(&self, operation: HookOperation) -> bool {
+        self.operations.is_empty() || self.operations.contains(&operation)
+    }
+}
+
+/// Commands run before (`pre`) and after (`post`) `create`, `add`, `remove` and `import`, in the order they are listed.
+/// Each gets the operation's context as JSON on stdin. A `pre` hook exiting with a non-zero status refuses the operation,
+/// while `post` hooks, which also run after failed operations, only have their failures logged.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    pub pre: Vec<Hook>,
+    pub post: Vec<Hook>,
+}
+
+/// What a hook is told about the operation it runs for.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HookContext {
+    pub operation: HookOperation,
+    /// The archive created, changed or imported into
+    pub archive: PathBuf,
+    /// Operation specific arguments, such as the files being added
+    pub details: serde_json::Value,
+}
+
+impl HookContext {
+    pub fn new(operation: HookOperation, archive: &Path, details: serde_json::Value) -> Self {
+        HookContext { operation, archive: archive.to_path_buf(), details }
+    }
+}
+
+/// The JSON written to a hook's stdin.
+#[derive(Serialize)]
+struct HookPayload<'a> {
+    #[serde(flatten)]
+    context: &'a HookContext,
+    stage: HookStage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outcome: Option<HookOutcome>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+impl HooksConfig {
+    /// Run the `pre` hooks of the operation, stopping at the first one that fails.
+    pub fn run_pre(&self, context: &HookContext) -> Result<(), HookError> {
+        let payload = HookPayload { context, stage: HookStage::Pre, outcome: None, error: None };
+        for hook in self.pre.iter().filter(|hook| hook.applies_to(context.operation)) {
+            let status = run_hook(hook, &payload)?;
+            if !status.success() {
+                return Err(HookError::Rejected(hook.command.clone(), context.operation, status));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run the `post` hooks of the operation, which failed with `error` if there is one.
+    pub fn run_post(&self, context: &HookContext, error: Option<&str>) {
+        let outcome = match error {
+            Some(_) => HookOutcome::Failed,
+            None => HookOutcome::Succeeded,
+        };
+        let payload = HookPayload { context, stage: HookStage::Post, outcome: Some(outcome), error };
+        for hook in self.post.iter().filter(|hook| hook.applies_to(context.operation)) {
+            match run_hook(hook, &payload) {
+                Ok(status) if status.success() => {},
+                Ok(status) => warn!(operation = %context.operation, archive = %context.archive.display(), hook = %hook.command, "Post hook exited with {}", status),
+                Err(err) => warn!(operation = %context.operation, archive = %context.archive.display(), error = %err, "Post hook failed"),
+            }
+        }
+    }
+}
+
+fn run_hook(hook: &Hook, payload: &HookPayload) -> Result<ExitStatus, HookError> {
+    let launch_error = |err| HookError::Launch(hook.command.clone(), err);
+    let json = serde_json::to_vec(payload).map_err(|err| launch_error(err.into()))?;
+    debug!(operation = %payload.context.operation, hook = %hook.command, "Running {:?} hook", payload.stage);
+    let mut child = Command::new(&hook.command)
+        .args(&hook.args)
+        .env("FSV_HOOK_OPERATION", payload.context.operation.get_name())
+        .env("FSV_HOOK_STAGE", match payload.stage {
+            HookStage::Pre => "pre",
+            HookStage::Post => "post",
+        })
+        .env("FSV_HOOK_ARCHIVE", &payload.context.archive)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(launch_error)?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that doesn't read its stdin closes it early, which is fine
+        match stdin.write_all(&json) {
+            Err(err) if err.kind() != std::io::ErrorKind::BrokenPipe => return Err(launch_error(err)),
+            _ => {},
+        }
+    }
+
+    child.wait().map_err(launch_error)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn hook(script: &str, operations: Vec<HookOperation>) -> Hook {
+        Hook { command: "sh".to_string(), args: vec!["-c".to_string(), script.to_string()], operations }
+    }
+
+    #[test]
+    fn test_hooks() {
+        let dir = std::env::temp_dir().join(format!("fsv-hooks-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("log");
+        let record = format!("cat >> '{}'; echo >> '{}'", log.display(), log.display());
+        let hooks = HooksConfig {
+            pre: vec![hook(&record, vec![]), hook("test \"$FSV_HOOK_OPERATION\" != remove", vec![HookOperation::Remove])],
+            post: vec![hook(&record, vec![HookOperation::Add]), hook("exit 3", vec![])],
+        };
+        let context = HookContext::new(HookOperation::Add, Path::new("scene.fsv"), serde_json::json!({ "item_type": "script" }));
+
+        hooks.run_pre(&context).unwrap();
+        hooks.run_post(&context, Some("disk full"));
+        let remove = HookContext::new(HookOperation::Remove, Path::new("scene.fsv"), serde_json::Value::Null);
+        assert!(matches!(hooks.run_pre(&remove), Err(HookError::Rejected(_, HookOperation::Remove, _))));
+        let missing = HooksConfig { pre: vec![Hook { command: "fsv-missing-hook".to_string(), args: vec![], operations: vec![] }], post: vec![] };
+        assert!(matches!(missing.run_pre(&context), Err(HookError::Launch(_, _))));
+
+        let payloads: Vec<serde_json::Value> = std::fs::read_to_string(&log).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(payloads, [
+            serde_json::json!({ "operation": "add", "archive": "scene.fsv", "details": { "item_type": "script" }, "stage": "pre" }),
+            serde_json::json!({ "operation": "add", "archive": "scene.fsv", "details": { "item_type": "script" }, "stage": "post", "outcome": "failed", "error": "disk full" }),
+            serde_json::json!({ "operation": "remove", "archive": "scene.fsv", "details": null, "stage": "pre" }),
+        ]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use thiserror::Error;
+#[cfg(feature = "native")]
+use tracing::{error, info};
+
+use crate::{entry_name, fsv::{FsvAddError, FsvCreateError, AXES}, journal::JournalError};
+#[cfg(feature = "native")]
+use crate::{db_client::DbClient, file_util, fsv::{self, AddArgs, CreateArgs, ItemType}, journal::{Journal, JournalOperation}, policy::ContentPolicy};
+
+pub const VIDEO_EXTENSIONS: [&str; 8] = ["mp4", "mkv", "webm", "avi", "mov", "m4v", "wmv", "flv"];
+pub const SCRIPT_EXTENSION: &str = "funscript";
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("FSV create error: {0}")]
+    Create(#[from] FsvCreateError),
+    #[error("FSV add error: {0}")]
+    Add(#[from] FsvAddError),
+    #[error("Journal error: {0}")]
+    Journal(#[from] JournalError),
+}
+
+/// A video and its matching script(s) found in a directory, paired by filestem.
+#[derive(Debug, Clone)]
+pub struct ImportCandidate {
+    pub stem: String,
+    pub video: PathBuf,
+    pub script: PathBuf,
+    pub axis_scripts: Vec<PathBuf>,
+}
+
+impl ImportCandidate {
+    pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.video).chain(std::iter::once(&self.script)).chain(self.axis_scripts.iter())
+    }
+}
+
+pub fn is_video_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| VIDEO_EXTENSIONS.iter().any(|v| v.eq_ignore_ascii_case(ext)))
+}
+
+/// Split a script filename into (stem, axis), e.g. `scene.roll.funscript` -> (`scene`, Some(`roll`)).
+/// Returns `None` if the file is not a funscript.
+pub fn split_script_name(file_name: &str) -> Option<(&str, Option<&str>)> {
+    let base = file_name.strip_suffix(&format!(".{}", SCRIPT_EXTENSION))?;
+    if let Some((stem, axis)) = base.rsplit_once('.') && AXES.contains(&axis) {
+        return Some((stem, Some(axis)));
+    }
+
+    Some((base, None))
+}
+
+/// Pair videos and scripts in `dir` by filestem. A candidate needs a video and a main (non-axis) script;
+/// axis scripts with the same stem are attached to it. Unpaired files are left alone.
+pub fn find_import_candidates(dir: &Path) -> Result<Vec<ImportCandidate>, std::io::Error> {
+    let mut videos: HashMap<String, PathBuf> = HashMap::new();
+    let mut scripts: HashMap<String, PathBuf> = HashMap::new();
+    let mut axis_scripts: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+
+        let path = entry.path();
+        let Some(file_name) = path.file_name().map(entry_name::os_str_entry_name) else {
+            continue;
+        };
+
+        if is_video_file(&path) {
+            if let Some(stem) = path.file_stem().map(entry_name::os_str_entry_name) {
+                videos.insert(stem, path.clone());
+            }
+        }
+        else if let Some((stem, axis)) = split_script_name(&file_name) {
+            match axis {
+                Some(_) => axis_scripts.entry(stem.to_string()).or_default().push(path.clone()),
+                None => { scripts.insert(stem.to_string(), path.clone()); },
+            }
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for (stem, video) in videos {
+        let Some(script) = scripts.remove(&stem) else {
+            continue;
+        };
+
+        let mut axis = axis_scripts.remove(&stem).unwrap_or_default();
+        axis.sort();
+        candidates.push(ImportCandidate { stem, video, script, axis_scripts: axis });
+    }
+
+    candidates.sort_by(|a, b| a.stem.cmp(&b.stem));
+
+    Ok(candidates)
+}
+
+/// Create `<output_dir>/<stem>.fsv` from a candidate. Returns the path of the new FSV.
+#[cfg(feature = "native")]
+pub async fn import_candidate(candidate: &ImportCandidate, output_dir: &Path, db_client: &DbClient) -> Result<PathBuf, ImportError> {
+    import_candidate_with_archive(candidate, output_dir, None, None, db_client).await
+}
+
+/// Same as `import_candidate`, screening the metadata with `content_policy` (if any), then moves the candidate's files into
+/// `archive_dir` (if any). The import runs under a journal: a failed import removes the incomplete FSV, and one interrupted by
+/// a crash is completed or rolled back by `recover`. Files that can't be moved are logged and left in place.
+#[cfg(feature = "native")]
+pub async fn import_candidate_with_archive(candidate: &ImportCandidate, output_dir: &Path, archive_dir: Option<&Path>, content_policy: Option<&ContentPolicy>, db_client: &DbClient) -> Result<PathBuf, ImportError> {
+    let fsv_path = output_dir.join(format!("{}.fsv", candidate.stem));
+    // Checked before journaling, so rolling back never removes an FSV this import didn't create
+    if fsv_path.exists() {
+        return Err(FsvCreateError::FsvAlreadyExists(fsv_path).into());
+    }
+
+    let sources = candidate.files().cloned().collect();
+    let mut journal = Journal::begin(&fsv_path, JournalOperation::Import { sources, archive_dir: archive_dir.map(Path::to_path_buf), imported: false })?;
+    if let Err(err) = build_fsv(candidate, &fsv_path, content_policy, db_client).await {
+        if let Err(abort_err) = journal.abort() {
+            error!(operation = "import", archive = %fsv_path.display(), outcome = "cleanup_failed", error = %abort_err, "Failed to remove incomplete FSV");
+        }
+
+        return Err(err);
+    }
+
+    journal.update(|operation| if let JournalOperation::Import { imported, .. } = operation {
+        *imported = true;
+    })?;
+
+    info!(operation = "import", archive = %fsv_path.display(), item = %candidate.stem, outcome = "completed", video = %candidate.video.display(), script = %candidate.script.display(), axis_scripts = candidate.axis_scripts.len(), "Imported FSV");
+
+    if let Some(archive_dir) = archive_dir {
+        for file in candidate.files() {
+            if let Err(err) = file_util::move_into_dir(file, archive_dir) {
+                error!(operation = "archive_original", item = %file.display(), outcome = "failed", error = %err, "Failed to move original to archive folder");
+            }
+            else {
+                info!(operation = "archive_original", item = %file.display(), outcome = "completed", archive_dir = %archive_dir.display(), "Moved original to archive folder");
+            }
+        }
+    }
+
+    journal.finish()?;
+
+    Ok(fsv_path)
+}
+
+#[cfg(feature = "native")]
+async fn build_fsv(candidate: &ImportCandidate, fsv_path: &Path, content_policy: Option<&ContentPolicy>, db_client: &DbClient) -> Result<(), ImportError> {
+    let args = CreateArgs::new(
+        fsv_path.to_path_buf(),
+        candidate.stem.clone(),
+        vec![],
+        Some(candidate.video.clone()),
+        Some(candidate.script.clone()),
+        None,
+        None,
+    ).content_policy(content_policy.cloned());
+    fsv::create_fsv(args, db_client, false).await?;
+
+    for axis_script in &candidate.axis_scripts {
+        let args = AddArgs::new(fsv_path.to_path_buf(), ItemType::Script, axis_script.clone(), None);
+        fsv::add_to_fsv(args, db_client, false).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_script_name() {
+        assert_eq!(split_script_name("scene.funscript"), Some(("scene", None)));
+        assert_eq!(split_script_name("scene.roll.funscript"), Some(("scene", Some("roll"))));
+        assert_eq!(split_script_name("scene.v2.funscript"), Some(("scene.v2", None)));
+        assert_eq!(split_script_name("scene.mp4"), None);
+    }
+}
@@ -0,0 +1,188 @@
+use std::{fmt::Display, path::{Path, PathBuf}, sync::{Mutex, atomic::{AtomicUsize, Ordering}}, time::{Duration, Instant}};
+
+use tracing::{debug, warn};
+
+use crate::progress::{ProgressEvent, ProgressListener};
+
+/// How a failed job is retried. Every error is retried, so it is meant for operations with transient failures
+/// (files still being written, archives locked by another process).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per job, including the first one
+    pub attempts: u32,
+    /// Wait between attempts
+    pub delay: Duration,
+}
+
+impl RetryPolicy {
+    pub const NEVER: RetryPolicy = RetryPolicy { attempts: 1, delay: Duration::ZERO };
+
+    /// Retry a failed job up to `retries` times, waiting `delay` before each retry.
+    pub fn retries(retries: u32, delay: Duration) -> Self {
+        RetryPolicy { attempts: retries.saturating_add(1), delay }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::NEVER
+    }
+}
+
+/// Result of one job and the attempts it took.
+#[derive(Debug)]
+pub struct JobOutcome<T, E> {
+    pub path: PathBuf,
+    pub result: Result<T, E>,
+    pub attempts: u32,
+}
+
+/// Runs a per-file operation over many files (a library scan, verification, ...) on a pool of worker threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobScheduler {
+    workers: usize,
+    retry: RetryPolicy,
+    /// Least time between the starts of two jobs, across all workers
+    interval: Duration,
+}
+
+impl Default for JobScheduler {
+    /// One worker per CPU, no retries.
+    fn default() -> Self {
+        JobScheduler::new(std::thread::available_parallelism().map_or(1, |workers| workers.get()))
+    }
+}
+
+impl JobScheduler {
+    /// A scheduler with `workers` threads (at least one) and no retries.
+    pub fn new(workers: usize) -> Self {
+        JobScheduler { workers: workers.max(1), retry: RetryPolicy::NEVER, interval: Duration::ZERO }
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Start at most `files_per_second` jobs a second across all workers, to keep the load on slow storage such as network
+    /// shares down. Unlimited if it isn't positive.
+    pub fn rate_limit(mut self, files_per_second: f64) -> Self {
+        self.interval = match files_per_second > 0.0 {
+            true => Duration::from_secs_f64(1.0 / files_per_second),
+            false => Duration::ZERO,
+        };
+        self
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Run `job` on every file in `files` and return the outcomes in the order of `files`.
+    /// Progress is consolidated across workers into `operation` events on `target`: a `progress` event as each file finishes,
+    /// and a `failed` event for each file whose job still failed after its last attempt.
+    pub fn run<T: Send, E: Send + Display>(&self, operation: &str, target: &str, files: &[PathBuf], job: impl Fn(&Path) -> Result<T, E> + Sync, progress: &dyn ProgressListener) -> Vec<JobOutcome<T, E>> {
+        let next = AtomicUsize::new(0);
+        let done = AtomicUsize::new(0);
+        let outcomes = Mutex::new(Vec::with_capacity(files.len()));
+        let next_start = Mutex::new(Instant::now());
+        progress.on_event(ProgressEvent::progress(operation, target, 0, files.len()));
+        std::thread::scope(|scope| {
+            for _ in 0..self.workers.min(files.len()) {
+                scope.spawn(|| {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = files.get(index) else {
+                            break;
+                        };
+
+                        self.wait_turn(&next_start);
+                        let outcome = self.run_job(operation, path, &job);
+                        if let Err(err) = &outcome.result {
+                            progress.on_event(ProgressEvent::failed(operation, path.display(), err));
+                        }
+
+                        outcomes.lock().unwrap_or_else(|err| err.into_inner()).push((index, outcome));
+                        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                        progress.on_event(ProgressEvent::progress(operation, target, done, files.len()));
+                    }
+                });
+            }
+        });
+
+        let mut outcomes = outcomes.into_inner().unwrap_or_else(|err| err.into_inner());
+        outcomes.sort_by_key(|(index, _)| *index);
+        outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+    }
+
+    /// Wait until a job may start under the rate limit, and reserve the next start for the job after it.
+    fn wait_turn(&self, next_start: &Mutex<Instant>) {
+        if self.interval.is_zero() {
+            return;
+        }
+
+        let wait = {
+            let mut next_start = next_start.lock().unwrap_or_else(|err| err.into_inner());
+            let now = Instant::now();
+            let start = (*next_start).max(now);
+            *next_start = start + self.interval;
+            start - now
+        };
+        std::thread::sleep(wait);
+    }
+
+    fn run_job<T, E: Display>(&self, operation: &str, path: &Path, job: &impl Fn(&Path) -> Result<T, E>) -> JobOutcome<T, E> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let result = job(path);
+            match &result {
+                Err(err) if attempts < self.retry.attempts => {
+                    warn!(operation, item = %path.display(), attempt = attempts, outcome = "retrying", error = %err, "Job failed, retrying");
+                    std::thread::sleep(self.retry.delay);
+                },
+                _ => {
+                    let outcome = if result.is_ok() { "completed" } else { "failed" };
+                    debug!(operation, item = %path.display(), attempts, outcome, "Finished job");
+                    return JobOutcome { path: path.to_path_buf(), result, attempts };
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::EventBroadcaster;
+
+    #[test]
+    fn test_job_scheduler() {
+        let files: Vec<PathBuf> = (0..20).map(|i| PathBuf::from(format!("{}.fsv", i))).collect();
+        let calls = AtomicUsize::new(0);
+        let broadcaster = EventBroadcaster::new();
+        let events = broadcaster.subscribe();
+        let scheduler = JobScheduler::new(4).retry(RetryPolicy::retries(2, Duration::ZERO));
+        let outcomes = scheduler.run("test", "library", &files, |path| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            match path == Path::new("7.fsv") {
+                true => Err("broken"),
+                false => Ok(path.display().to_string()),
+            }
+        }, &broadcaster);
+
+        assert_eq!(outcomes.iter().map(|outcome| outcome.path.clone()).collect::<Vec<_>>(), files);
+        assert_eq!(outcomes[0].result, Ok("0.fsv".to_string()));
+        assert_eq!((&outcomes[7].result, outcomes[7].attempts), (&Err("broken"), 3));
+        assert_eq!(calls.load(Ordering::Relaxed), 22);
+
+        let events: Vec<_> = events.try_iter().collect();
+        assert!(events.contains(&ProgressEvent::failed("test", "7.fsv", "broken")));
+        assert!(events.contains(&ProgressEvent::progress("test", "library", 20, 20)));
+
+        // Jobs start no faster than the rate limit, however many workers there are
+        let started = Instant::now();
+        JobScheduler::new(4).rate_limit(100.0).run("test", "library", &files[..5], |_| Ok::<_, &str>(()), &crate::progress::NoProgress);
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+}
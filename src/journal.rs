@@ -0,0 +1,298 @@
+use std::{ffi::OsString, fs::{File, OpenOptions, TryLockError}, io::{Read, Seek, Write}, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use crate::file_util;
+
+pub const JOURNAL_EXTENSION: &str = "journal";
+
+#[derive(Debug, Error)]
+pub enum JournalError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serde json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("Journal '{0}' is in use by a running operation")]
+    Busy(PathBuf),
+    #[error("Database changes for '{0}' are still pending; run `recover` with the database available to apply them")]
+    Pending(PathBuf),
+}
+
+/// A database change that goes with a change to an archive, made once the archive has been replaced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "write", rename_all = "snake_case")]
+pub enum DbWrite {
+    /// Store the creator under `key`, replacing the name and socials of one already stored under it
+    SaveCreator { key: String, name: String, socials: Vec<String> },
+    /// Re-read the archive into the library index
+    IndexWork,
+}
+
+/// What an interrupted operation was doing, and with that what it takes to complete it or roll it back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum JournalOperation {
+    /// The archive is rewritten into `temp`, which then replaces it. Rolled back by removing `temp`.
+    Rebuild { temp: PathBuf },
+    /// An FSV is created from `sources`. Until `imported` the new FSV is incomplete and is rolled back by removing it;
+    /// afterwards the sources still in place are moved to `archive_dir` (if any) to complete the import.
+    Import { sources: Vec<PathBuf>, archive_dir: Option<PathBuf>, imported: bool },
+    /// `writes` go with a change to the archive. Rolled back by dropping them until the archive is `replaced`;
+    /// afterwards they are pending until `recover` applies them to the database.
+    DbWrites { writes: Vec<DbWrite>, replaced: bool },
+}
+
+impl JournalOperation {
+    pub fn get_name(&self) -> &str {
+        match self {
+            JournalOperation::Rebuild { .. } => "rebuild",
+            JournalOperation::Import { .. } => "import",
+            JournalOperation::DbWrites { .. } => "db",
+        }
+    }
+}
+
+/// Contents of a journal file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalRecord {
+    pub target: PathBuf,
+    #[serde(flatten)]
+    pub operation: JournalOperation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryOutcome {
+    RolledBack,
+    Completed,
+    /// Completing it takes the database; the journal stays until `archive_tx::apply_pending` applies it
+    Pending,
+}
+
+/// An interrupted operation found and resolved by `recover_dir`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovery {
+    pub record: JournalRecord,
+    pub outcome: RecoveryOutcome,
+}
+
+/// Path of the journal for `operation` on `target`, e.g. `scene.fsv.rebuild.journal`.
+pub fn journal_path(target: &Path, operation: &str) -> PathBuf {
+    let mut journal_path = OsString::from(target.as_os_str());
+    journal_path.push(format!(".{}.{}", operation, JOURNAL_EXTENSION));
+    PathBuf::from(journal_path)
+}
+
+/// Intent of a multi-step operation, written next to its target before the first step and removed once the operation
+/// is done (or rolled back after a failure). A journal left behind means the process died mid-way; `recover_dir`
+/// (or the next `begin` of the same operation) then completes or rolls it back, as happens when a `Journal` is dropped
+/// without `finish` or `abort`. The journal file stays locked while the operation runs, so recovery never touches a live operation.
+#[derive(Debug)]
+pub struct Journal {
+    file: File,
+    path: PathBuf,
+    record: JournalRecord,
+}
+
+/// Open and lock an existing or new journal file. `None` if another handle holds its lock.
+fn open_locked(path: &Path, create: bool) -> std::io::Result<Option<File>> {
+    let file = OpenOptions::new().read(true).write(true).create(create).truncate(false).open(path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(err)) => Err(err),
+    }
+}
+
+/// The record in a locked journal file, `None` if the file is empty (created but never written).
+fn read_record(file: &mut File) -> Result<Option<JournalRecord>, JournalError> {
+    let mut json = String::new();
+    file.read_to_string(&mut json)?;
+    if json.trim().is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_str(&json)?))
+}
+
+/// Complete or roll back the operation `record` describes.
+fn resolve(record: &JournalRecord) -> std::io::Result<RecoveryOutcome> {
+    match &record.operation {
+        JournalOperation::Rebuild { temp } => {
+            // The archive is only replaced by a rename, so it is either untouched or fully rewritten
+            if temp.exists() {
+                std::fs::remove_file(temp)?;
+                return Ok(RecoveryOutcome::RolledBack);
+            }
+
+            Ok(RecoveryOutcome::Completed)
+        },
+        JournalOperation::Import { imported: false, .. } => {
+            if record.target.exists() {
+                std::fs::remove_file(&record.target)?;
+            }
+
+            Ok(RecoveryOutcome::RolledBack)
+        },
+        JournalOperation::Import { sources, archive_dir, imported: true } => {
+            if let Some(archive_dir) = archive_dir {
+                for source in sources.iter().filter(|source| source.exists()) {
+                    file_util::move_into_dir(source, archive_dir)?;
+                }
+            }
+
+            Ok(RecoveryOutcome::Completed)
+        },
+        // The archive wasn't replaced, so the database stays as it is too
+        JournalOperation::DbWrites { replaced: false, .. } => Ok(RecoveryOutcome::RolledBack),
+        JournalOperation::DbWrites { replaced: true, .. } => Ok(RecoveryOutcome::Pending),
+    }
+}
+
+impl Journal {
+    /// Record that `operation` on `target` is starting. A journal left behind by an interrupted run of the same operation is
+    /// resolved first.
+    pub fn begin(target: &Path, operation: JournalOperation) -> Result<Journal, JournalError> {
+        let path = journal_path(target, operation.get_name());
+        let mut file = open_locked(&path, true)?.ok_or_else(|| JournalError::Busy(path.clone()))?;
+        if let Some(stale) = read_record(&mut file)? {
+            let outcome = resolve(&stale)?;
+            if outcome == RecoveryOutcome::Pending {
+                return Err(JournalError::Pending(stale.target));
+            }
+            warn!(operation = stale.operation.get_name(), archive = %stale.target.display(), outcome = ?outcome, "Resolved interrupted operation");
+        }
+
+        let mut journal = Journal { file, path, record: JournalRecord { target: target.to_path_buf(), operation } };
+        journal.write()?;
+
+        Ok(journal)
+    }
+
+    fn write(&mut self) -> Result<(), JournalError> {
+        let json = serde_json::to_string_pretty(&self.record)?;
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        self.file.write_all(json.as_bytes())?;
+        self.file.sync_data()?;
+
+        Ok(())
+    }
+
+    /// Record progress, e.g. a step that changes how the operation is recovered.
+    pub fn update(&mut self, update: impl FnOnce(&mut JournalOperation)) -> Result<(), JournalError> {
+        update(&mut self.record.operation);
+        self.write()
+    }
+
+    /// The operation is done; remove the journal.
+    pub fn finish(self) -> Result<(), JournalError> {
+        // Remove the file while still holding the lock, so recovery can't pick up a finished journal
+        std::fs::remove_file(&self.path)?;
+        Ok(())
+    }
+
+    /// The operation failed; complete or roll back what it did, then remove the journal. A pending journal is kept.
+    pub fn abort(self) -> Result<RecoveryOutcome, JournalError> {
+        let outcome = resolve(&self.record)?;
+        if outcome != RecoveryOutcome::Pending {
+            self.finish()?;
+        }
+
+        Ok(outcome)
+    }
+
+    /// Lock the journal at `path` left by an interrupted operation, to complete it. `None` if its operation is still
+    /// running, or the journal is gone or empty.
+    pub fn reopen(path: &Path) -> Result<Option<Journal>, JournalError> {
+        let mut file = match open_locked(path, false) {
+            Ok(Some(file)) => file,
+            Ok(None) => return Ok(None),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let Some(record) = read_record(&mut file)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Journal { file, path: path.to_path_buf(), record }))
+    }
+
+    pub fn record(&self) -> &JournalRecord {
+        &self.record
+    }
+}
+
+/// Resolve the journal at `path`. `None` if its operation is still running (or the journal is empty).
+pub fn recover_journal(path: &Path) -> Result<Option<Recovery>, JournalError> {
+    let Some(mut file) = open_locked(path, false)? else {
+        return Ok(None);
+    };
+
+    let Some(record) = read_record(&mut file)? else {
+        std::fs::remove_file(path)?;
+        return Ok(None);
+    };
+
+    let outcome = resolve(&record)?;
+    if outcome != RecoveryOutcome::Pending {
+        std::fs::remove_file(path)?;
+    }
+    debug!(operation = "recover", archive = %record.target.display(), item = record.operation.get_name(), outcome = ?outcome, "Recovered interrupted operation");
+
+    Ok(Some(Recovery { record, outcome }))
+}
+
+/// Complete or roll back every interrupted operation with a journal in `dir` or its subdirectories.
+/// Journals of operations that are still running are left alone, as are pending database changes.
+pub fn recover_dir(dir: &Path) -> Result<Vec<Recovery>, JournalError> {
+    let journals = file_util::find_files(dir, |path| path.extension().is_some_and(|ext| ext == JOURNAL_EXTENSION))?;
+    let mut recoveries = Vec::new();
+    for path in journals {
+        if let Some(recovery) = recover_journal(&path)? {
+            recoveries.push(recovery);
+        }
+    }
+
+    Ok(recoveries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_recovery() {
+        let dir = std::env::temp_dir().join(format!("fsv-journal-test-{}", std::process::id()));
+        let archive_dir = dir.join("archive");
+        std::fs::create_dir_all(&archive_dir).unwrap();
+        let target = dir.join("scene.fsv");
+        let temp = dir.join("scene.tmp");
+        let source = dir.join("scene.mp4");
+        std::fs::write(&target, b"partial").unwrap();
+        std::fs::write(&temp, b"partial").unwrap();
+        std::fs::write(&source, b"video").unwrap();
+
+        // A live journal is left alone. Dropping one without finishing it is what a crash leaves behind
+        let rebuild = Journal::begin(&target, JournalOperation::Rebuild { temp: temp.clone() }).unwrap();
+        assert!(recover_dir(&dir).unwrap().is_empty());
+        drop(rebuild);
+        let import = Journal::begin(&target, JournalOperation::Import { sources: vec![source.clone()], archive_dir: Some(archive_dir.clone()), imported: false }).unwrap();
+        assert!(matches!(Journal::begin(&target, JournalOperation::Import { sources: vec![], archive_dir: None, imported: false }), Err(JournalError::Busy(_))));
+        drop(import);
+
+        let mut outcomes: Vec<_> = recover_dir(&dir).unwrap().into_iter().map(|r| (r.record.operation.get_name().to_string(), r.outcome)).collect();
+        outcomes.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(outcomes, [("import".to_string(), RecoveryOutcome::RolledBack), ("rebuild".to_string(), RecoveryOutcome::RolledBack)]);
+        assert!(!temp.exists() && !target.exists() && source.exists());
+
+        let mut import = Journal::begin(&target, JournalOperation::Import { sources: vec![source.clone()], archive_dir: Some(archive_dir.clone()), imported: false }).unwrap();
+        import.update(|operation| if let JournalOperation::Import { imported, .. } = operation { *imported = true }).unwrap();
+        assert_eq!(import.abort().unwrap(), RecoveryOutcome::Completed);
+        assert!(archive_dir.join("scene.mp4").exists() && !source.exists());
+        assert!(recover_dir(&dir).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
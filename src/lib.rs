@@ -1,6 +1,82 @@
+#![allow(non_snake_case)]
+
 pub mod metadata;
+pub mod schema;
 pub mod fsv;
+#[cfg(feature = "native")]
 pub mod db_client;
 pub mod semver;
+pub mod duration;
 pub mod funscript;
+pub mod convert;
+pub mod simplify;
 pub mod file_util;
+pub mod checksum;
+pub mod hash_cache;
+pub mod lock;
+pub mod journal;
+#[cfg(feature = "native")]
+pub mod archive_tx;
+pub mod trash;
+pub mod history;
+pub mod external;
+pub mod offsets;
+pub mod subtitle_offsets;
+pub mod socials;
+pub mod titles;
+pub mod notes;
+#[cfg(feature = "native")]
+pub mod config;
+#[cfg(feature = "native")]
+pub mod hooks;
+#[cfg(feature = "native")]
+pub mod template;
+#[cfg(feature = "native")]
+pub mod naming;
+pub mod policy;
+pub mod transcode;
+pub mod preview;
+#[cfg(feature = "native")]
+pub mod align;
+#[cfg(feature = "native")]
+pub mod library;
+#[cfg(feature = "native")]
+pub mod snapshot;
+pub mod playback;
+#[cfg(feature = "native")]
+pub mod open;
+pub mod tcode;
+pub mod import;
+#[cfg(feature = "native")]
+pub mod watch;
+pub mod progress;
+pub mod notify;
+pub mod jobs;
+pub mod exit_code;
+pub mod error_context;
+pub mod entry_name;
+pub mod path_safety;
+pub mod content;
+pub mod content_hash;
+pub mod magic;
+pub mod extensions;
+pub mod storage;
+pub mod package;
+#[cfg(feature = "native")]
+pub mod register;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "play")]
+pub mod play;
+#[cfg(feature = "serve")]
+pub mod serve;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "http")]
+pub mod fetch;
+#[cfg(feature = "http")]
+pub mod remote;
+#[cfg(feature = "s3")]
+pub mod s3;
+#[cfg(feature = "capi")]
+pub mod capi;
@@ -0,0 +1,989 @@
+use std::{collections::HashSet, path::{Path, PathBuf}};
+
+use serde::Serialize;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+use crate::{archive_tx::{ArchiveTransaction, ArchiveTxError}, db_client::{DbClient, DbClientError, HistoryRecord, LibraryEntry, LibraryFilter, LibraryScript, LibraryStats, LibraryVideo, LibraryWork, UsageGrouping, UsageRecord}, file_util, fsv::{self, ArchiveSizes, FsvContainer, FsvError, FsvState, FsvValidationError, NameMatching}, jobs::JobScheduler, metadata::{FsvMetadata, VideoFormat}, hash_cache::mtime_stamp, journal::DbWrite, magic, progress::{NoProgress, ProgressEvent, ProgressListener}, storage::Storage, titles};
+
+pub const MAX_RATING: u8 = 5;
+
+/// Axis the library index records script variants themselves on, their axis scripts are on the axis they are named after.
+pub const STROKE_AXIS: &str = "stroke";
+
+/// Archives read and indexed by a scan between two checkpoints
+const SCAN_BATCH_SIZE: usize = 100;
+
+/// Title similarity from which a new archive is warned to be a likely duplicate of an indexed work, see `title_similarity`
+pub const SIMILAR_TITLE_THRESHOLD: f64 = 0.85;
+
+#[derive(Debug, Error)]
+pub enum LibraryError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Database client error: {0}")]
+    DbClient(#[from] DbClientError),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+    #[error("Archive transaction error: {0}")]
+    ArchiveTx(#[from] ArchiveTxError),
+    #[error("Invalid rating {0}: expected 1-{MAX_RATING}")]
+    InvalidRating(u8),
+}
+
+/// What a library scan did.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ScanSummary {
+    pub indexed: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+    pub removed: usize,
+    /// Files with an FSV extension that turned out to be something else, e.g. plain ZIP archives
+    pub skipped: usize,
+    /// Archives an interrupted scan already got through, passed over when resuming it
+    pub resumed: usize,
+    /// New archives recognized as indexed works whose files are gone, moved over with their ratings and history
+    pub moved: usize,
+}
+
+/// How a library scan runs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// Continue an interrupted scan of the directory after the last archive it got through, instead of starting over
+    pub resume: bool,
+}
+
+/// Reports the `progress` events of one batch of a scan as progress through all of it.
+struct BatchProgress<'a> {
+    progress: &'a dyn ProgressListener,
+    offset: usize,
+    total: usize,
+}
+
+impl ProgressListener for BatchProgress<'_> {
+    fn on_event(&self, event: ProgressEvent) {
+        let event = match event {
+            ProgressEvent::Progress { operation, target, done, .. } => ProgressEvent::Progress { operation, target, done: self.offset + done, total: self.total },
+            event => event,
+        };
+        self.progress.on_event(event);
+    }
+}
+
+/// Index key of a work: its canonical path, so different spellings of the same file share one entry.
+pub(crate) fn library_key(path: &Path) -> std::io::Result<String> {
+    Ok(std::fs::canonicalize(path)?.to_string_lossy().to_string())
+}
+
+fn is_fsv_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("fsv"))
+}
+
+/// FSV files in `dir` and its subdirectories, sorted. Unreadable subdirectories are skipped with a warning.
+pub fn find_fsv_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    file_util::find_files(dir, is_fsv_file)
+}
+
+/// Index key, size and mtime of the FSV at `path`.
+fn file_stamp(path: &Path) -> std::io::Result<(String, i64, i64)> {
+    let key = library_key(path)?;
+    let file_metadata = std::fs::metadata(path)?;
+    Ok((key, file_metadata.len() as i64, mtime_stamp(&file_metadata).unwrap_or(0)))
+}
+
+/// Index key, size and mtime of the FSV at `path`. `None` if it is indexed with the same size and mtime (unless `force` is set).
+async fn changed_file(db_client: &DbClient, path: &Path, force: bool) -> Result<Option<(String, i64, i64)>, LibraryError> {
+    let (key, size, stamp) = file_stamp(path)?;
+    if !force && db_client.get_library_stamp(&key).await? == Some((size, stamp)) {
+        debug!(operation = "scan", archive = %path.display(), outcome = "unchanged", "Index entry is up to date");
+        return Ok(None);
+    }
+
+    Ok(Some((key, size, stamp)))
+}
+
+/// Metadata and entry sizes of the FSV at `path`, as stored in its index entry.
+fn read_work(path: &Path) -> Result<(FsvMetadata, ArchiveSizes), FsvError> {
+    let mut container = FsvContainer::from_reader(std::fs::File::open(path)?)?;
+    Ok((container.metadata()?, container.sizes()?))
+}
+
+async fn upsert_work(db_client: &DbClient, file: (String, i64, i64), work: (FsvMetadata, ArchiveSizes)) -> Result<(), LibraryError> {
+    db_client.upsert_library_work(&work_record(file, work)).await?;
+    Ok(())
+}
+
+/// The index entry of the FSV at `path`, as `index_work` stores it.
+pub(crate) fn library_work(path: &Path) -> Result<LibraryWork, FsvError> {
+    let file = file_stamp(path)?;
+    Ok(work_record(file, read_work(path)?))
+}
+
+fn work_record((key, size, stamp): (String, i64, i64), (metadata, sizes): (FsvMetadata, ArchiveSizes)) -> LibraryWork {
+    let mut creators: Vec<String> = Vec::new();
+    for work in metadata.creators.videos.iter().chain(&metadata.creators.scripts).chain(&metadata.creators.subtitles) {
+        if !creators.iter().any(|name| name.eq_ignore_ascii_case(&work.creator_info.name)) {
+            creators.push(work.creator_info.name.clone());
+        }
+    }
+
+    let localized_titles = titles::localized_titles_from_metadata(&metadata).unwrap_or_else(|err| {
+        warn!(operation = "scan", archive = %key, outcome = "skipped", error = %err, "Ignoring malformed localized titles");
+        Default::default()
+    });
+    let entry_size = |name: &str| sizes.entries.iter().find(|entry| entry.name == name).map_or(0, |entry| entry.compressed_size);
+    let extra = |format: &VideoFormat, field: &str| format.extra.get(field).and_then(|value| value.as_str()).unwrap_or_default().to_string();
+    let videos = metadata.video_formats.iter().map(|format| LibraryVideo {
+        name: format.name.clone(),
+        duration_ms: format.duration.as_millis(),
+        checksum: format.checksum.clone(),
+        codec: extra(format, "codec"),
+        resolution: extra(format, "resolution"),
+        size: entry_size(&format.name),
+    }).collect();
+    let scripts = metadata.script_variants.iter().flat_map(|variant| {
+        let axes = fsv::axis_script_names(variant).into_iter().map(|(axis, name)| LibraryScript { name, axis });
+        std::iter::once(LibraryScript { name: variant.name.clone(), axis: STROKE_AXIS.to_string() }).chain(axes)
+    }).collect();
+    LibraryWork {
+        path: key,
+        title: metadata.title,
+        localized_titles,
+        studio: metadata.studio,
+        tags: metadata.tags,
+        performers: metadata.performers,
+        creators,
+        size,
+        stamp,
+        compressed_size: sizes.compressed_size,
+        uncompressed_size: sizes.uncompressed_size,
+        videos,
+        scripts,
+    }
+}
+
+/// Add the FSV at `path` to the library index, or refresh its entry.
+pub async fn index_work(db_client: &DbClient, path: &Path) -> Result<(), LibraryError> {
+    if let Some(file) = changed_file(db_client, path, true).await? {
+        upsert_work(db_client, file, read_work(path)?).await?;
+    }
+
+    Ok(())
+}
+
+/// Index every FSV file under `dir`, skipping files unchanged since the last scan, and drop index entries under `dir`
+/// whose files are gone. Files that can't be read are logged and counted as failed.
+pub async fn scan_library(db_client: &DbClient, dir: &Path) -> Result<ScanSummary, LibraryError> {
+    scan_library_with_progress(db_client, dir, &JobScheduler::default(), &NoProgress).await
+}
+
+/// Same as `scan_library`, reading the changed files on the scheduler's workers and reporting `scan` progress events to `progress`.
+pub async fn scan_library_with_progress(db_client: &DbClient, dir: &Path, scheduler: &JobScheduler, progress: &dyn ProgressListener) -> Result<ScanSummary, LibraryError> {
+    scan_library_with_options(db_client, dir, &ScanOptions::default(), scheduler, progress).await
+}
+
+/// Same as `scan_library_with_progress`. Changed files are read and indexed in batches, after each of which the scan records
+/// in the database how far it got, so an interrupted scan can be resumed with `ScanOptions::resume`. Files up to there are
+/// passed over without being looked at, including ones that failed; vanished files are dropped once a scan completes.
+pub async fn scan_library_with_options(db_client: &DbClient, dir: &Path, options: &ScanOptions, scheduler: &JobScheduler, progress: &dyn ProgressListener) -> Result<ScanSummary, LibraryError> {
+    let root = std::fs::canonicalize(dir)?;
+    let root_key = root.to_string_lossy().to_string();
+    let checkpoint = match options.resume {
+        true => db_client.get_scan_checkpoint(&root_key).await?,
+        false => None,
+    };
+    match &checkpoint {
+        Some(last_path) => info!(operation = "scan", archive = %root_key, outcome = "resumed", "Resuming scan after '{}'", last_path),
+        None if options.resume => info!(operation = "scan", archive = %root_key, "No interrupted scan to resume, scanning everything"),
+        None => {},
+    }
+
+    let mut summary = ScanSummary::default();
+    let mut changed = Vec::new();
+    let mut changed_paths = Vec::new();
+    for path in find_fsv_files(&root)? {
+        // Files are found in path order, so everything up to the checkpoint was scanned
+        if checkpoint.as_ref().is_some_and(|last_path| path <= Path::new(last_path)) {
+            summary.resumed += 1;
+            continue;
+        }
+
+        match changed_file(db_client, &path, false).await {
+            // Sniffing only reads the archive comment, so other files are set aside before the workers parse anything
+            Ok(Some((key, _, _))) if !magic::is_fsv_file(&path).unwrap_or(true) => {
+                info!(operation = "scan", archive = %path.display(), outcome = "skipped", "Not an FSV archive, skipping");
+                if db_client.remove_library_work(&key).await? {
+                    summary.removed += 1;
+                }
+                summary.skipped += 1;
+            },
+            Ok(Some(file)) => {
+                changed.push(file);
+                changed_paths.push(path);
+            },
+            Ok(None) => summary.unchanged += 1,
+            Err(LibraryError::DbClient(err)) => return Err(err.into()),
+            Err(err) => {
+                warn!(operation = "scan", archive = %path.display(), outcome = "failed", error = %err, "Unable to index archive");
+                summary.failed += 1;
+            },
+        }
+    }
+
+    // Only reading the archives is spread over the workers, the index is written from here
+    for (batch, paths) in changed_paths.chunks(SCAN_BATCH_SIZE).enumerate() {
+        let batch_progress = BatchProgress { progress, offset: batch * SCAN_BATCH_SIZE, total: changed_paths.len() };
+        let outcomes = scheduler.run("scan", &root.display().to_string(), paths, read_work, &batch_progress);
+        for (file, outcome) in changed[batch * SCAN_BATCH_SIZE..].iter().cloned().zip(outcomes) {
+            match outcome.result {
+                Ok(work) => {
+                    let work = work_record(file, work);
+                    match moved_from(db_client, &work).await? {
+                        Some(old_path) => {
+                            info!(operation = "scan", archive = %work.path, outcome = "moved", "Archive moved from '{}'", old_path);
+                            db_client.move_paths(&[(old_path, work.path.clone())]).await?;
+                            summary.moved += 1;
+                        },
+                        None => summary.indexed += 1,
+                    }
+                    db_client.upsert_library_work(&work).await?;
+                },
+                Err(err) => {
+                    warn!(operation = "scan", archive = %outcome.path.display(), outcome = "failed", error = %err, "Unable to index archive");
+                    summary.failed += 1;
+                },
+            }
+        }
+
+        if let Some(last_path) = paths.last() {
+            db_client.set_scan_checkpoint(&root_key, &last_path.to_string_lossy()).await?;
+        }
+    }
+
+    for key in db_client.list_library_paths().await? {
+        let path = Path::new(&key);
+        if path.starts_with(&root) && !path.is_file() && db_client.remove_library_work(&key).await? {
+            info!(operation = "scan", archive = %key, outcome = "removed", "Removed archive from the library");
+            summary.removed += 1;
+        }
+    }
+
+    db_client.clear_scan_checkpoint(&root_key).await?;
+    Ok(summary)
+}
+
+/// Where the archive of `work` was indexed before, if it is new to the index and has the same videos (by checksum) as exactly
+/// one indexed work whose file is gone.
+async fn moved_from(db_client: &DbClient, work: &LibraryWork) -> Result<Option<String>, LibraryError> {
+    let mut checksums: Vec<&str> = work.videos.iter().map(|video| video.checksum.as_str()).collect();
+    checksums.sort_unstable();
+    if checksums.is_empty() || checksums.contains(&"") || db_client.get_library_stamp(&work.path).await?.is_some() {
+        return Ok(None);
+    }
+
+    let candidates = db_client.find_works_by_video_checksum(checksums[0]).await?;
+    let mut vanished = candidates.into_iter().filter(|(path, others)| {
+        let mut others: Vec<&str> = others.iter().map(String::as_str).collect();
+        others.sort_unstable();
+        // Works in object storage are only dropped by scans of their bucket
+        others == checksums && !path.contains("://") && !Path::new(path).exists()
+    });
+    match (vanished.next(), vanished.next()) {
+        (Some((path, _)), None) => Ok(Some(path)),
+        (Some(_), Some(_)) => {
+            warn!(operation = "scan", archive = %work.path, "Several missing archives hold the same videos, indexing it as a new one");
+            Ok(None)
+        },
+        (None, _) => Ok(None),
+    }
+}
+
+/// What `relocate_library` changed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RelocateSummary {
+    /// Recorded archive paths and the paths they were moved to
+    pub moves: Vec<(String, String)>,
+    /// Archives that aren't at their new path
+    pub missing: Vec<String>,
+}
+
+/// Rewrite the paths under `from` recorded in the index, the snapshots and the hash cache to the same paths under `to`, after
+/// moving a collection to another drive. Indexed works keep their ratings and history. `from` doesn't have to exist anymore.
+pub async fn relocate_library(db_client: &DbClient, from: &Path, to: &Path, dry_run: bool) -> Result<RelocateSummary, LibraryError> {
+    let to = std::fs::canonicalize(to).unwrap_or_else(|_| to.to_path_buf());
+    let mut summary = RelocateSummary::default();
+    for path in db_client.list_recorded_paths().await? {
+        let Ok(relative) = Path::new(&path).strip_prefix(from) else {
+            continue;
+        };
+
+        let new_path = match relative.as_os_str().is_empty() {
+            true => to.clone(),
+            false => to.join(relative),
+        };
+        if !new_path.exists() {
+            summary.missing.push(new_path.to_string_lossy().to_string());
+        }
+        summary.moves.push((path, new_path.to_string_lossy().to_string()));
+    }
+
+    if !dry_run {
+        db_client.move_paths(&summary.moves).await?;
+    }
+
+    Ok(summary)
+}
+
+/// An indexed work with a title close to another one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimilarWork {
+    pub path: String,
+    /// The work's title closest to the other one, in whichever language
+    pub title: String,
+    /// See `title_similarity`
+    pub similarity: f64,
+}
+
+/// Title in lowercase with punctuation and separators dropped, so `Scene_01 (HD)` and `scene 01 hd` are the same title.
+pub fn normalize_title(title: &str) -> String {
+    let spaced: String = title.chars().map(|c| if c.is_alphanumeric() { c } else { ' ' }).collect();
+    spaced.to_lowercase().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// How close two titles are, from 0 to 1: one minus the Levenshtein distance between their normalized forms relative to
+/// the longer of them. Spaces are left out, as file names often run words together.
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let letters = |title: &str| normalize_title(title).chars().filter(|c| *c != ' ').collect::<Vec<_>>();
+    let (a, b) = (letters(a), letters(b));
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a_char != b_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    1.0 - row[b.len()] as f64 / longest as f64
+}
+
+/// Indexed works with a title (in any language) at least `threshold` similar to `title`, most similar first. The work
+/// indexed under `exclude`, if any, is left out.
+pub async fn find_similar_works(db_client: &DbClient, title: &str, threshold: f64, exclude: Option<&str>) -> Result<Vec<SimilarWork>, LibraryError> {
+    let mut works: Vec<SimilarWork> = Vec::new();
+    for (path, work_title) in db_client.list_work_titles().await? {
+        if exclude == Some(path.as_str()) {
+            continue;
+        }
+
+        let similarity = title_similarity(title, &work_title);
+        if similarity < threshold {
+            continue;
+        }
+
+        match works.last_mut() {
+            Some(last) if last.path == path => if similarity > last.similarity {
+                *last = SimilarWork { path, title: work_title, similarity };
+            },
+            _ => works.push(SimilarWork { path, title: work_title, similarity }),
+        }
+    }
+
+    works.sort_by(|a, b| b.similarity.total_cmp(&a.similarity).then_with(|| a.path.cmp(&b.path)));
+
+    Ok(works)
+}
+
+/// Warn about indexed works with a title close to that of the archive being made at `path`, which are likely the same work
+/// under a slightly different file name. Only logs, as an index that can't be read shouldn't stop the archive being made.
+pub async fn warn_similar_works(db_client: &DbClient, title: &str, path: &Path) {
+    if normalize_title(title).is_empty() {
+        return;
+    }
+
+    let exclude = library_key(path).ok();
+    match find_similar_works(db_client, title, SIMILAR_TITLE_THRESHOLD, exclude.as_deref()).await {
+        Ok(works) => for work in works {
+            warn!(archive = %path.display(), existing = %work.path, existing_title = %work.title, "A similar work already exists: '{}' at {}", work.title, work.path);
+        },
+        Err(err) => debug!(archive = %path.display(), error = %err, "Unable to look for works with a similar title"),
+    }
+}
+
+/// Index every FSV in `storage` (e.g. an object storage bucket) under its location, skipping archives whose size and stamp
+/// are unchanged, and drop index entries under the storage's root that are no longer listed. Archives are read one at a time,
+/// fetching only their central directory and metadata.
+pub async fn scan_storage(db_client: &DbClient, storage: &impl Storage) -> Result<ScanSummary, LibraryError> {
+    let mut summary = ScanSummary::default();
+    let archives = storage.list()?;
+    for archive in &archives {
+        let key = storage.location(&archive.key);
+        let (size, stamp) = (archive.size as i64, archive.stamp);
+        if db_client.get_library_stamp(&key).await? == Some((size, stamp)) {
+            summary.unchanged += 1;
+            continue;
+        }
+
+        let mut reader = match storage.open(&archive.key) {
+            Ok(reader) => reader,
+            Err(err) => {
+                warn!(operation = "scan", archive = %key, outcome = "failed", error = %err, "Unable to index archive");
+                summary.failed += 1;
+                continue;
+            },
+        };
+
+        if !magic::is_fsv(&mut reader).unwrap_or(true) {
+            info!(operation = "scan", archive = %key, outcome = "skipped", "Not an FSV archive, skipping");
+            if db_client.remove_library_work(&key).await? {
+                summary.removed += 1;
+            }
+            summary.skipped += 1;
+            continue;
+        }
+
+        let result = FsvContainer::from_reader(reader).map_err(FsvError::from)
+            .and_then(|mut container| Ok((container.metadata()?, container.sizes()?)));
+        match result {
+            Ok(work) => {
+                upsert_work(db_client, (key, size, stamp), work).await?;
+                summary.indexed += 1;
+            },
+            Err(err) => {
+                warn!(operation = "scan", archive = %key, outcome = "failed", error = %err, "Unable to index archive");
+                summary.failed += 1;
+            },
+        }
+    }
+
+    let root = storage.root();
+    let listed: HashSet<String> = archives.iter().map(|archive| storage.location(&archive.key)).collect();
+    for key in db_client.list_library_paths().await? {
+        if key.starts_with(&root) && !listed.contains(&key) && db_client.remove_library_work(&key).await? {
+            info!(operation = "scan", archive = %key, outcome = "removed", "Removed archive from the library");
+            summary.removed += 1;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Index key of `path`, indexing the file first if it isn't in the library yet.
+async fn ensure_indexed(db_client: &DbClient, path: &Path) -> Result<String, LibraryError> {
+    let key = library_key(path)?;
+    if db_client.get_library_stamp(&key).await?.is_none() {
+        index_work(db_client, path).await?;
+    }
+
+    Ok(key)
+}
+
+/// Rate a work 1-5, or clear its rating with `None`. Unindexed files are added to the library.
+pub async fn rate_work(db_client: &DbClient, path: &Path, rating: Option<u8>) -> Result<(), LibraryError> {
+    if let Some(rating) = rating && !(1..=MAX_RATING).contains(&rating) {
+        return Err(LibraryError::InvalidRating(rating));
+    }
+
+    let key = ensure_indexed(db_client, path).await?;
+    db_client.set_work_rating(&key, rating).await?;
+
+    Ok(())
+}
+
+/// Mark or unmark a work as a favorite. Unindexed files are added to the library.
+pub async fn set_favorite(db_client: &DbClient, path: &Path, favorite: bool) -> Result<(), LibraryError> {
+    let key = ensure_indexed(db_client, path).await?;
+    db_client.set_work_favorite(&key, favorite).await?;
+
+    Ok(())
+}
+
+/// Record playback progress of a work: it was played up to `position_ms` just now. `new_session` counts it as another play;
+/// players reporting progress periodically only set it on their first update. Unindexed files are added to the library.
+pub async fn record_progress(db_client: &DbClient, path: &Path, position_ms: u64, new_session: bool) -> Result<(), LibraryError> {
+    let key = ensure_indexed(db_client, path).await?;
+    db_client.record_playback(&key, position_ms, new_session).await?;
+
+    Ok(())
+}
+
+/// Watch history of a work, `None` if it was never played (or isn't indexed).
+pub async fn get_history(db_client: &DbClient, path: &Path) -> Result<Option<HistoryRecord>, LibraryError> {
+    let key = library_key(path)?;
+    Ok(db_client.get_history(&key).await?)
+}
+
+/// Indexed works matching `filter`. The tag filter is resolved against the tag vocabulary, so aliases match too.
+pub async fn list_works(db_client: &DbClient, mut filter: LibraryFilter) -> Result<Vec<LibraryEntry>, LibraryError> {
+    if let Some(tag) = &filter.tag && let Some(canonical) = db_client.resolve_tag(tag).await? {
+        filter.tag = Some(canonical);
+    }
+
+    Ok(db_client.list_library_works(&filter).await?)
+}
+
+/// Disk usage of the indexed works under `dir` (the whole library if `None`), per work or per tag or creator, largest first.
+/// Works not scanned since entry sizes were recorded have a compressed and uncompressed size of 0 until the next scan.
+pub async fn library_usage(db_client: &DbClient, grouping: UsageGrouping, dir: Option<&Path>) -> Result<Vec<UsageRecord>, LibraryError> {
+    let prefix = dir.map(dir_prefix).transpose()?;
+    Ok(db_client.library_usage(grouping, prefix.as_deref()).await?)
+}
+
+/// Statistics over the indexed works under `dir` (the whole library if `None`), with the `top` most used creators and tags.
+pub async fn library_stats(db_client: &DbClient, dir: Option<&Path>, top: u32) -> Result<LibraryStats, LibraryError> {
+    let prefix = dir.map(dir_prefix).transpose()?;
+    Ok(db_client.library_stats(prefix.as_deref(), top).await?)
+}
+
+/// Index key prefix shared by the works in `dir` and its subdirectories.
+pub(crate) fn dir_prefix(dir: &Path) -> std::io::Result<String> {
+    let mut prefix = std::fs::canonicalize(dir)?.to_string_lossy().to_string();
+    if !prefix.ends_with(std::path::MAIN_SEPARATOR) {
+        prefix.push(std::path::MAIN_SEPARATOR);
+    }
+
+    Ok(prefix)
+}
+
+/// What a bulk retag did.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RetagSummary {
+    pub changed: usize,
+    /// Matching works whose tags were already as requested, left untouched
+    pub unchanged: usize,
+    pub failed: usize,
+}
+
+/// Add `add_tags` to and remove `remove_tags` from every indexed work matching `filter`, e.g. when a community renames a tag.
+/// With `dir`, only works in it and its subdirectories are retagged, and it is scanned first so the index is current.
+/// Only archives whose tags actually change are rebuilt; they are re-indexed afterwards. Files that can't be read or
+/// rewritten are logged and counted as failed.
+pub async fn retag_library(db_client: &DbClient, dir: Option<&Path>, filter: LibraryFilter, add_tags: &[String], remove_tags: &[String]) -> Result<RetagSummary, LibraryError> {
+    let prefix = match dir {
+        Some(dir) => {
+            scan_library(db_client, dir).await?;
+            Some(dir_prefix(dir)?)
+        },
+        None => None,
+    };
+
+    let mut summary = RetagSummary::default();
+    for entry in list_works(db_client, filter).await? {
+        if prefix.as_ref().is_some_and(|prefix| !entry.work.path.starts_with(prefix.as_str())) {
+            continue;
+        }
+
+        let path = Path::new(&entry.work.path);
+        let tags = match read_work(path) {
+            Ok((metadata, _)) => metadata.tags,
+            Err(err) => {
+                warn!(operation = "retag", archive = %path.display(), outcome = "failed", error = %err, "Unable to read archive");
+                summary.failed += 1;
+                continue;
+            },
+        };
+
+        if fsv::retag(db_client, &tags, add_tags, remove_tags).await? == tags {
+            debug!(archive = %path.display(), "Tags are unchanged");
+            summary.unchanged += 1;
+            continue;
+        }
+
+        // The index entry is refreshed only once the archive is rewritten
+        let tx = ArchiveTransaction::begin(path, vec![DbWrite::IndexWork]).map_err(ArchiveTxError::from)?;
+        match fsv::edit_fsv_tags(path, add_tags.to_vec(), remove_tags.to_vec(), false, db_client).await {
+            Ok(_) => {
+                info!(operation = "retag", archive = %path.display(), outcome = "changed", "Retagged archive");
+                tx.commit(db_client).await?;
+                summary.changed += 1;
+            },
+            Err(err) => {
+                tx.abort().map_err(ArchiveTxError::from)?;
+                warn!(operation = "retag", archive = %path.display(), outcome = "failed", error = %err, "Unable to retag archive");
+                summary.failed += 1;
+            },
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Condition of one FSV checked by `verify_library`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifyStatus {
+    Valid,
+    /// The metadata is valid but referenced content is missing or unreadable
+    Incomplete,
+    /// The metadata is invalid, or the file isn't a readable FSV at all
+    Invalid,
+}
+
+impl VerifyStatus {
+    pub fn get_name(&self) -> &str {
+        match self {
+            VerifyStatus::Valid => "valid",
+            VerifyStatus::Incomplete => "incomplete",
+            VerifyStatus::Invalid => "invalid",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyResult {
+    pub path: PathBuf,
+    pub status: VerifyStatus,
+    /// Validation errors, empty for valid files
+    pub reasons: Vec<String>,
+}
+
+/// Outcome of `verify_library`, one result per FSV file in path order.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerifyReport {
+    pub valid: usize,
+    pub incomplete: usize,
+    pub invalid: usize,
+    pub results: Vec<VerifyResult>,
+}
+
+impl VerifyReport {
+    /// The worst status found, `None` if there were no files.
+    pub fn worst_status(&self) -> Option<VerifyStatus> {
+        self.results.iter().map(|result| result.status).max()
+    }
+
+    /// The results as CSV with a `path,status,reasons` header. Reasons are joined with `; `.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("path,status,reasons\n");
+        for result in &self.results {
+            let fields = [result.path.display().to_string(), result.status.get_name().to_string(), result.reasons.join("; ")];
+            csv.push_str(&fields.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(","));
+            csv.push('\n');
+        }
+
+        csv
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+fn verify_result(path: &Path, result: Result<fsv::ValidationReport, FsvValidationError>) -> VerifyResult {
+    let (status, reasons) = match result {
+        Ok(report) => {
+            let status = match report.state {
+                FsvState::Valid => VerifyStatus::Valid,
+                FsvState::ContentIncomplete(_) => VerifyStatus::Incomplete,
+                FsvState::MetadataInvalid(_) => VerifyStatus::Invalid,
+            };
+            (status, report.errors().map(|issue| issue.to_string()).collect())
+        },
+        Err(err) => (VerifyStatus::Invalid, vec![err.to_string()]),
+    };
+
+    VerifyResult { path: path.to_path_buf(), status, reasons }
+}
+
+/// Validate every FSV file under `dir` on the scheduler's workers, reporting `verify` progress events.
+/// Files that can't be opened or parsed (after the scheduler's retries) count as invalid.
+pub fn verify_library(dir: &Path, name_matching: NameMatching, scheduler: &JobScheduler, progress: &dyn ProgressListener) -> Result<VerifyReport, LibraryError> {
+    let files = find_fsv_files(dir)?;
+    let outcomes = scheduler.run("verify", &dir.display().to_string(), &files, |path| fsv::validate_fsv_report(path, name_matching), progress);
+    let results: Vec<_> = outcomes.into_iter().map(|outcome| verify_result(&outcome.path, outcome.result)).collect();
+    let count = |status| results.iter().filter(|result| result.status == status).count();
+    Ok(VerifyReport { valid: count(VerifyStatus::Valid), incomplete: count(VerifyStatus::Incomplete), invalid: count(VerifyStatus::Invalid), results })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db_client::{StatsCount, StatsSize}, duration::DurationMs, metadata::ScriptVariant};
+
+    #[tokio::test]
+    async fn test_library_ratings_and_filters() {
+        let dir = std::env::temp_dir().join(format!("fsv-library-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_client = DbClient::new(dir.join("library.db")).await.unwrap();
+
+        let work = |path: &str, title: &str, tags: &[&str], performers: &[&str]| LibraryWork {
+            path: path.to_string(),
+            title: title.to_string(),
+            localized_titles: [("ja".to_string(), format!("{} (ja)", title))].into(),
+            studio: String::new(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            performers: performers.iter().map(|p| p.to_string()).collect(),
+            creators: performers.iter().map(|p| p.to_string()).collect(),
+            size: 10,
+            stamp: 1,
+            compressed_size: 8,
+            uncompressed_size: 20,
+            // /b.fsv and /c.fsv hold the same video
+            videos: vec![LibraryVideo {
+                name: "scene.mp4".to_string(),
+                duration_ms: 60_000,
+                checksum: if path == "/a.fsv" { "aaa" } else { "bbb" }.to_string(),
+                codec: "h264".to_string(),
+                resolution: "1920x1080".to_string(),
+                size: 5,
+            }],
+            scripts: vec![
+                LibraryScript { name: "scene.funscript".to_string(), axis: STROKE_AXIS.to_string() },
+                LibraryScript { name: "scene.roll.funscript".to_string(), axis: "roll".to_string() },
+            ],
+        };
+        db_client.upsert_library_work(&work("/a.fsv", "Alpha", &["pov"], &["Jane Doe"])).await.unwrap();
+        db_client.upsert_library_work(&work("/b.fsv", "Beta", &["vr"], &[])).await.unwrap();
+        assert!(db_client.set_work_rating("/a.fsv", Some(4)).await.unwrap());
+        assert!(db_client.set_work_favorite("/a.fsv", true).await.unwrap());
+        assert!(db_client.set_work_rating("/b.fsv", Some(2)).await.unwrap());
+        assert!(!db_client.set_work_rating("/missing.fsv", Some(2)).await.unwrap());
+
+        // Re-indexing keeps the rating
+        db_client.upsert_library_work(&work("/a.fsv", "Alpha", &["POV", "solo"], &["Jane Doe"])).await.unwrap();
+
+        let titles = |filter: LibraryFilter| {
+            let db_client = &db_client;
+            async move { list_works(db_client, filter).await.unwrap().into_iter().map(|e| e.work.title).collect::<Vec<_>>() }
+        };
+        assert_eq!(titles(LibraryFilter::default()).await, ["Alpha", "Beta"]);
+        assert_eq!(titles(LibraryFilter { min_rating: Some(3), ..Default::default() }).await, ["Alpha"]);
+        assert_eq!(titles(LibraryFilter { favorites: true, ..Default::default() }).await, ["Alpha"]);
+        assert_eq!(titles(LibraryFilter { tag: Some("pov".to_string()), ..Default::default() }).await, ["Alpha"]);
+        assert_eq!(titles(LibraryFilter { performer: Some("jane doe".to_string()), ..Default::default() }).await, ["Alpha"]);
+
+        // Filtering by a tag also finds works tagged with a tag implying it
+        db_client.insert_tag("video", &[]).await.unwrap();
+        db_client.insert_tag("vr", &["virtual reality".to_string()]).await.unwrap();
+        assert!(db_client.add_tag_implication("vr", "video").await.unwrap());
+        assert!(!db_client.add_tag_implication("vr", "missing").await.unwrap());
+        assert!(matches!(db_client.add_tag_implication("video", "vr").await, Err(DbClientError::TagCycle(..))));
+        assert_eq!(db_client.implied_tags("vr").await.unwrap(), ["video"]);
+        assert_eq!(titles(LibraryFilter { tag: Some("video".to_string()), ..Default::default() }).await, ["Beta"]);
+        assert_eq!(titles(LibraryFilter { tag: Some("virtual reality".to_string()), ..Default::default() }).await, ["Beta"]);
+        assert_eq!(titles(LibraryFilter { search: Some("bet".to_string()), ..Default::default() }).await, ["Beta"]);
+        assert_eq!(titles(LibraryFilter { search: Some("Alpha (ja)".to_string()), ..Default::default() }).await, ["Alpha"]);
+
+        let entries = list_works(&db_client, LibraryFilter::default()).await.unwrap();
+        assert_eq!((entries[0].rating, entries[0].favorite), (Some(4), true));
+        assert_eq!(entries[0].work.tags, ["POV", "solo"]);
+
+        assert!(db_client.record_playback("/b.fsv", 1000, true).await.unwrap());
+        assert!(db_client.record_playback("/b.fsv", 2500, false).await.unwrap());
+        let history = db_client.get_history("/b.fsv").await.unwrap().unwrap();
+        assert_eq!((history.position_ms, history.play_count), (2500, 1));
+        assert_eq!(titles(LibraryFilter { unwatched: true, ..Default::default() }).await, ["Alpha"]);
+
+        let usage = db_client.library_usage(UsageGrouping::Tag, None).await.unwrap();
+        let usage: Vec<_> = usage.iter().map(|record| (record.name.as_str(), record.works, record.compressed_size, record.uncompressed_size)).collect();
+        assert_eq!(usage, [("POV", 1, 8, 20), ("solo", 1, 8, 20), ("vr", 1, 8, 20)]);
+        db_client.upsert_library_work(&work("/c.fsv", "Gamma", &["vr"], &["Jane Doe"])).await.unwrap();
+        let usage = db_client.library_usage(UsageGrouping::Creator, None).await.unwrap();
+        assert_eq!((usage[0].name.as_str(), usage[0].works, usage[0].file_size), ("Jane Doe", 2, 20));
+        assert_eq!(db_client.library_usage(UsageGrouping::Work, Some("/b")).await.unwrap().len(), 1);
+
+        let stats = db_client.library_stats(None, 1).await.unwrap();
+        assert_eq!((stats.works, stats.size, stats.video_formats, stats.total_video_ms, stats.unique_video_ms), (3, 30, 3, 180_000, 120_000));
+        assert_eq!(stats.scripts_by_axis, [StatsCount { name: "roll".to_string(), count: 3 }, StatsCount { name: "stroke".to_string(), count: 3 }]);
+        assert_eq!(stats.top_creators, [StatsCount { name: "Jane Doe".to_string(), count: 2 }]);
+        assert_eq!(stats.top_tags, [StatsCount { name: "vr".to_string(), count: 2 }]);
+        assert_eq!(stats.size_by_codec, [StatsSize { name: "h264".to_string(), videos: 3, size: 15 }]);
+        assert_eq!(entries[0].work.videos.len(), 1);
+
+        db_client.pool.close().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scan_library_resume() {
+        let dir = std::env::temp_dir().join(format!("fsv-scan-resume-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = br#"{"actions":[{"at":0,"pos":0},{"at":1000,"pos":100}],"inverted":false,"range":100,"version":"1.0"}"#;
+        for name in ["a", "b", "c"] {
+            std::fs::write(dir.join(format!("{}.fsv", name)), crate::fsv::FsvBuilder::new(name).script("a.funscript", script, 1000).to_bytes().unwrap()).unwrap();
+        }
+        let db_client = DbClient::in_memory().await.unwrap();
+        let root = std::fs::canonicalize(&dir).unwrap().to_string_lossy().to_string();
+        let scan = |resume: bool| {
+            let (db_client, dir) = (&db_client, &dir);
+            async move { scan_library_with_options(db_client, dir, &ScanOptions { resume }, &JobScheduler::new(2), &NoProgress).await }
+        };
+
+        // A completed scan leaves no checkpoint behind
+        assert_eq!(scan(true).await.unwrap(), ScanSummary { indexed: 3, ..Default::default() });
+        assert_eq!(db_client.get_scan_checkpoint(&root).await.unwrap(), None);
+
+        // Resuming passes over the archives up to the checkpoint, a new scan looks at everything
+        std::fs::remove_file(dir.join("a.fsv")).unwrap();
+        db_client.set_scan_checkpoint(&root, &Path::new(&root).join("b.fsv").to_string_lossy()).await.unwrap();
+        assert_eq!(scan(true).await.unwrap(), ScanSummary { unchanged: 1, removed: 1, resumed: 1, ..Default::default() });
+        db_client.set_scan_checkpoint(&root, &Path::new(&root).join("b.fsv").to_string_lossy()).await.unwrap();
+        assert_eq!(scan(false).await.unwrap(), ScanSummary { unchanged: 2, ..Default::default() });
+
+        db_client.pool.close().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_relocate_library() {
+        let dir = std::env::temp_dir().join(format!("fsv-relocate-test-{}", std::process::id()));
+        let (old_dir, new_dir) = (dir.join("old"), dir.join("new"));
+        std::fs::create_dir_all(&old_dir).unwrap();
+        std::fs::create_dir_all(&new_dir).unwrap();
+        let script = br#"{"actions":[{"at":0,"pos":0},{"at":1000,"pos":100}],"inverted":false,"range":100,"version":"1.0"}"#;
+        let archive = crate::fsv::FsvBuilder::new("Alpha").video("a.mp4", b"video", 1000).script("a.funscript", script, 1000).to_bytes().unwrap();
+        std::fs::write(old_dir.join("a.fsv"), &archive).unwrap();
+        let db_client = DbClient::in_memory().await.unwrap();
+        let old_dir = std::fs::canonicalize(&old_dir).unwrap();
+        let rating = |path: PathBuf| {
+            let db_client = &db_client;
+            async move {
+                let works = list_works(db_client, LibraryFilter::default()).await.unwrap();
+                assert_eq!(works.iter().map(|entry| Path::new(&entry.work.path)).collect::<Vec<_>>(), [std::fs::canonicalize(&path).unwrap()]);
+                works[0].rating
+            }
+        };
+        scan_library(&db_client, &dir).await.unwrap();
+        rate_work(&db_client, &old_dir.join("a.fsv"), Some(4)).await.unwrap();
+
+        // An archive that turns up elsewhere is recognized by its videos and keeps its rating
+        std::fs::rename(old_dir.join("a.fsv"), new_dir.join("a.fsv")).unwrap();
+        assert_eq!(scan_library(&db_client, &dir).await.unwrap(), ScanSummary { moved: 1, ..Default::default() });
+        assert_eq!(rating(new_dir.join("a.fsv")).await, Some(4));
+
+        // Relocating rewrites the paths under the old directory, which doesn't exist anymore
+        let new_dir = std::fs::canonicalize(&new_dir).unwrap();
+        std::fs::rename(&new_dir, &old_dir).unwrap();
+        let summary = relocate_library(&db_client, &new_dir, &old_dir, true).await.unwrap();
+        assert_eq!(summary.moves, [(new_dir.join("a.fsv").to_string_lossy().to_string(), old_dir.join("a.fsv").to_string_lossy().to_string())]);
+        assert!(summary.missing.is_empty());
+        relocate_library(&db_client, &new_dir, &old_dir, false).await.unwrap();
+        assert_eq!(rating(old_dir.join("a.fsv")).await, Some(4));
+        assert_eq!(scan_library(&db_client, &dir).await.unwrap(), ScanSummary { unchanged: 1, ..Default::default() });
+
+        db_client.pool.close().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_find_similar_works() {
+        assert_eq!(normalize_title("  Scene_01 (HD)! "), "scene 01 hd");
+        assert_eq!(title_similarity("Scene_01 (HD)", "scene 01 hd"), 1.0);
+        assert_eq!(title_similarity("kitten", "sitting"), 1.0 - 3.0 / 7.0);
+        assert_eq!(title_similarity("", "a"), 0.0);
+
+        let db_client = DbClient::in_memory().await.unwrap();
+        let work = |path: &str, title: &str, localized: &str| LibraryWork {
+            path: path.to_string(),
+            title: title.to_string(),
+            localized_titles: [("ja".to_string(), localized.to_string())].into(),
+            studio: String::new(),
+            tags: vec![],
+            performers: vec![],
+            creators: vec![],
+            size: 1,
+            stamp: 1,
+            compressed_size: 1,
+            uncompressed_size: 1,
+            videos: vec![],
+            scripts: vec![],
+        };
+        db_client.upsert_library_work(&work("/a.fsv", "Summer Vacation Part 1", "夏休み 1")).await.unwrap();
+        db_client.upsert_library_work(&work("/b.fsv", "Winter Holiday", "冬休み")).await.unwrap();
+
+        let similar = find_similar_works(&db_client, "summer_vacation_part1", SIMILAR_TITLE_THRESHOLD, None).await.unwrap();
+        assert_eq!(similar.iter().map(|work| (work.path.as_str(), work.title.as_str())).collect::<Vec<_>>(), [("/a.fsv", "Summer Vacation Part 1")]);
+        let similar = find_similar_works(&db_client, "夏休み1", SIMILAR_TITLE_THRESHOLD, None).await.unwrap();
+        assert_eq!(similar.iter().map(|work| work.path.as_str()).collect::<Vec<_>>(), ["/a.fsv"]);
+        assert!(find_similar_works(&db_client, "Summer Vacation Part 1", SIMILAR_TITLE_THRESHOLD, Some("/a.fsv")).await.unwrap().is_empty());
+        assert!(find_similar_works(&db_client, "Autumn Trip", SIMILAR_TITLE_THRESHOLD, None).await.unwrap().is_empty());
+        let all = find_similar_works(&db_client, "Winter Holiday", 0.0, None).await.unwrap();
+        assert_eq!(all.iter().map(|work| work.path.as_str()).collect::<Vec<_>>(), ["/b.fsv", "/a.fsv"]);
+    }
+
+    #[tokio::test]
+    async fn test_scan_storage() {
+        let dir = std::env::temp_dir().join(format!("fsv-scan-storage-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = br#"{"actions":[{"at":0,"pos":0},{"at":1000,"pos":100}],"inverted":false,"range":100,"version":"1.0"}"#;
+        std::fs::write(dir.join("a.fsv"), crate::fsv::FsvBuilder::new("Alpha").script("a.funscript", script, 1000).to_bytes().unwrap()).unwrap();
+        // A plain ZIP archive with an FSV extension
+        let mut zip_writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip_writer.start_file("readme.txt", zip::write::SimpleFileOptions::default()).unwrap();
+        std::fs::write(dir.join("b.fsv"), zip_writer.finish().unwrap().into_inner()).unwrap();
+        let db_client = DbClient::in_memory().await.unwrap();
+        let storage = crate::storage::LocalStorage::new(&dir).unwrap();
+
+        assert_eq!(scan_storage(&db_client, &storage).await.unwrap(), ScanSummary { indexed: 1, skipped: 1, ..Default::default() });
+        assert_eq!(scan_storage(&db_client, &storage).await.unwrap(), ScanSummary { unchanged: 1, skipped: 1, ..Default::default() });
+        assert_eq!(db_client.list_library_paths().await.unwrap(), [storage.location("a.fsv")]);
+
+        std::fs::remove_file(dir.join("a.fsv")).unwrap();
+        assert_eq!(scan_storage(&db_client, &storage).await.unwrap(), ScanSummary { removed: 1, skipped: 1, ..Default::default() });
+
+        db_client.pool.close().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retag_library() {
+        let dir = std::env::temp_dir().join(format!("fsv-retag-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = br#"{"actions":[{"at":0,"pos":0},{"at":1000,"pos":100}],"inverted":false,"range":100,"version":"1.0"}"#;
+        let fsv = |title: &str, tags: &[&str]| fsv::FsvBuilder::new(title).tags(tags.iter().map(|tag| tag.to_string()).collect()).script("scene.funscript", script, 1000).to_bytes().unwrap();
+        std::fs::write(dir.join("a.fsv"), fsv("Alpha", &["cowgirl", "pov"])).unwrap();
+        std::fs::write(dir.join("b.fsv"), fsv("Beta", &["pov"])).unwrap();
+        std::fs::write(dir.join("c.fsv"), fsv("Gamma", &["riding", "cowgirl"])).unwrap();
+        let db_client = DbClient::in_memory().await.unwrap();
+
+        let filter = LibraryFilter { tag: Some("cowgirl".to_string()), ..Default::default() };
+        let (add, remove) = (["riding".to_string()], ["cowgirl".to_string()]);
+        let summary = retag_library(&db_client, Some(&dir), filter.clone(), &add, &remove).await.unwrap();
+        assert_eq!(summary, RetagSummary { changed: 2, ..Default::default() });
+        let tags = |name: &str| read_work(&dir.join(name)).unwrap().0.tags;
+        assert_eq!((tags("a.fsv"), tags("b.fsv"), tags("c.fsv")), (vec!["pov".to_string(), "riding".to_string()], vec!["pov".to_string()], vec!["riding".to_string()]));
+        // The index was updated, so nothing matches any more
+        assert!(list_works(&db_client, filter).await.unwrap().is_empty());
+
+        let filter = LibraryFilter { tag: Some("pov".to_string()), ..Default::default() };
+        let summary = retag_library(&db_client, None, filter, &["pov".to_string()], &[]).await.unwrap();
+        assert_eq!(summary, RetagSummary { unchanged: 2, ..Default::default() });
+
+        db_client.pool.close().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_library() {
+        let dir = std::env::temp_dir().join(format!("fsv-verify-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        let script = br#"{"actions":[{"at":0,"pos":0},{"at":1000,"pos":100}],"inverted":false,"range":100,"version":"1.0"}"#;
+        let valid = fsv::FsvBuilder::new("scene").video("scene.mp4", b"\0\0\0\x18ftypisom", 1000).script("scene.funscript", script, 1000).to_bytes().unwrap();
+        let mut incomplete = fsv::FsvBuilder::new("scene").video("scene.mp4", b"\0\0\0\x18ftypisom", 1000);
+        incomplete.metadata_mut().add_script_variant(ScriptVariant::new("missing.funscript".to_string(), String::new(), vec![], DurationMs::from_millis(1000), 0, String::new()));
+        let incomplete = incomplete.to_bytes().unwrap();
+        std::fs::write(dir.join("a.fsv"), valid).unwrap();
+        std::fs::write(dir.join("nested").join("b.fsv"), incomplete).unwrap();
+        std::fs::write(dir.join("c.fsv"), b"not a zip").unwrap();
+
+        let report = verify_library(&dir, NameMatching::Strict, &JobScheduler::new(2), &NoProgress).unwrap();
+        let statuses: Vec<_> = report.results.iter().map(|result| result.status).collect();
+        assert_eq!(statuses, [VerifyStatus::Valid, VerifyStatus::Invalid, VerifyStatus::Incomplete]);
+        assert_eq!((report.valid, report.incomplete, report.invalid), (1, 1, 1));
+        assert_eq!(report.worst_status(), Some(VerifyStatus::Invalid));
+        assert!(report.to_csv().starts_with("path,status,reasons\n"));
+        assert_eq!(csv_field("a, \"b\""), "\"a, \"\"b\"\"\"");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
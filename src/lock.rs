@@ -0,0 +1,69 @@
+use std::{ffi::OsString, fs::{File, OpenOptions, TryLockError}, path::{Path, PathBuf}};
+
+use tracing::{debug, warn};
+
+/// Advisory lock on an archive, held while it is rewritten so concurrent writers (a second CLI invocation, the watch daemon)
+/// can't interleave their rebuilds. The lock is taken on a `<archive>.lock` sidecar rather than the archive itself, since
+/// rebuilds replace the archive file and Windows locks would also block plain readers.
+/// The sidecar is left in place when the lock is dropped: removing it would let a writer that opened it just before lock the
+/// orphaned file while another one locks a fresh sidecar, and both would hold the lock.
+#[derive(Debug)]
+pub struct ArchiveLock {
+    file: File,
+    lock_path: PathBuf,
+}
+
+/// Path of the sidecar lock file for `archive_path`, e.g. `scene.fsv.lock`.
+pub fn lock_path(archive_path: &Path) -> PathBuf {
+    let mut lock_path = OsString::from(archive_path.as_os_str());
+    lock_path.push(".lock");
+    PathBuf::from(lock_path)
+}
+
+impl ArchiveLock {
+    /// Lock `archive_path` without waiting. `None` if another process (or another handle in this one) holds the lock.
+    pub fn try_acquire(archive_path: &Path) -> std::io::Result<Option<ArchiveLock>> {
+        let lock_path = lock_path(archive_path);
+        let file = OpenOptions::new().write(true).create(true).truncate(false).open(&lock_path)?;
+        match file.try_lock() {
+            Ok(()) => {
+                debug!(operation = "lock", archive = %archive_path.display(), outcome = "locked", "Locked archive");
+                Ok(Some(ArchiveLock { file, lock_path }))
+            },
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(err)) => Err(err),
+        }
+    }
+}
+
+impl Drop for ArchiveLock {
+    fn drop(&mut self) {
+        if let Err(err) = self.file.unlock() {
+            warn!(operation = "unlock", item = %self.lock_path.display(), outcome = "failed", error = %err, "Unable to release lock file");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_lock() {
+        let archive_path = std::env::temp_dir().join(format!("fsv-lock-test-{}.fsv", std::process::id()));
+        let first = ArchiveLock::try_acquire(&archive_path).unwrap().unwrap();
+        assert!(lock_path(&archive_path).is_file());
+        assert!(ArchiveLock::try_acquire(&archive_path).unwrap().is_none());
+
+        // A writer that opened the sidecar before the lock was released, and one that opens it after, contend for one file
+        let waiting = OpenOptions::new().write(true).open(lock_path(&archive_path)).unwrap();
+        drop(first);
+        assert!(lock_path(&archive_path).is_file());
+        waiting.try_lock().unwrap();
+        assert!(ArchiveLock::try_acquire(&archive_path).unwrap().is_none());
+
+        waiting.unlock().unwrap();
+        assert!(ArchiveLock::try_acquire(&archive_path).unwrap().is_some());
+        std::fs::remove_file(lock_path(&archive_path)).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+use std::{fs::File, io::{Read, Seek, SeekFrom}, path::Path};
+
+use crate::semver::Version;
+
+/// MIME type of FSV files, as registered by the `register` command.
+pub const MIME_TYPE: &str = "application/vnd.funscriptvideo+zip";
+
+/// Start of the ZIP archive comment written on create and rebuild, followed by the format version (e.g. `FunscriptVideo/1.0.0`).
+pub const ARCHIVE_COMMENT_PREFIX: &str = "FunscriptVideo/";
+
+const END_OF_DIRECTORY_SIGNATURE: [u8; 4] = [0x50, 0x4b, 0x05, 0x06];
+const END_OF_DIRECTORY_LEN: u64 = 22;
+const MAX_COMMENT_LEN: u64 = u16::MAX as u64;
+
+/// What sniffing a file found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FsvSniff {
+    /// A ZIP archive whose comment marks it as an FSV of this format version
+    Marked(Version),
+    /// A ZIP archive without the marker but with a `metadata.json` in its central directory, e.g. one written before the marker existed
+    Unmarked,
+    /// Not a ZIP archive, or a ZIP archive without `metadata.json`
+    NotFsv,
+}
+
+/// The archive comment identifying an FSV of `format_version`.
+pub fn archive_comment(format_version: &Version) -> String {
+    format!("{}{}", ARCHIVE_COMMENT_PREFIX, format_version)
+}
+
+/// The format version in an archive comment written by `archive_comment`.
+pub fn comment_format_version(comment: &[u8]) -> Option<Version> {
+    let comment = std::str::from_utf8(comment).ok()?;
+    Version::parse(comment.trim().strip_prefix(ARCHIVE_COMMENT_PREFIX)?).ok()
+}
+
+/// The comment of the ZIP archive in `reader`, read from its end of central directory record. `None` if there is no such record.
+pub fn read_archive_comment<R: Read + Seek>(reader: &mut R) -> std::io::Result<Option<Vec<u8>>> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    if len < END_OF_DIRECTORY_LEN {
+        return Ok(None);
+    }
+
+    // The record is the last thing in the file, followed only by the comment
+    let tail_len = len.min(END_OF_DIRECTORY_LEN + MAX_COMMENT_LEN);
+    let mut tail = vec![0; tail_len as usize];
+    reader.seek(SeekFrom::Start(len - tail_len))?;
+    reader.read_exact(&mut tail)?;
+
+    for start in (0..=tail.len() - END_OF_DIRECTORY_LEN as usize).rev() {
+        if tail[start..start + 4] != END_OF_DIRECTORY_SIGNATURE {
+            continue;
+        }
+
+        let comment_len = u16::from_le_bytes([tail[start + 20], tail[start + 21]]) as usize;
+        let comment_start = start + END_OF_DIRECTORY_LEN as usize;
+        if comment_start + comment_len == tail.len() {
+            return Ok(Some(tail[comment_start..].to_vec()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Tell whether `reader` holds an FSV without parsing its metadata. Only the archive comment is read for marked archives;
+/// for unmarked ones the central directory is searched for `metadata.json`.
+pub fn sniff<R: Read + Seek>(reader: &mut R) -> std::io::Result<FsvSniff> {
+    let Some(comment) = read_archive_comment(reader)? else {
+        return Ok(FsvSniff::NotFsv);
+    };
+
+    if let Some(version) = comment_format_version(&comment) {
+        return Ok(FsvSniff::Marked(version));
+    }
+
+    match zip::ZipArchive::new(reader) {
+        Ok(archive) if archive.index_for_name("metadata.json").is_some() => Ok(FsvSniff::Unmarked),
+        Ok(_) | Err(zip::result::ZipError::InvalidArchive(_) | zip::result::ZipError::UnsupportedArchive(_)) => Ok(FsvSniff::NotFsv),
+        Err(err) => Err(std::io::Error::other(err)),
+    }
+}
+
+/// Whether `reader` holds an FSV, marked or not (see `sniff`).
+pub fn is_fsv<R: Read + Seek>(reader: &mut R) -> std::io::Result<bool> {
+    Ok(sniff(reader)? != FsvSniff::NotFsv)
+}
+
+/// Whether the file at `path` is an FSV, whatever its extension.
+pub fn is_fsv_file(path: &Path) -> std::io::Result<bool> {
+    is_fsv(&mut File::open(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use super::*;
+    use crate::fsv::FsvBuilder;
+
+    #[test]
+    fn test_sniff() {
+        let data = FsvBuilder::new("scene").script("scene.funscript", b"{}", 1000).to_bytes().unwrap();
+        assert!(matches!(sniff(&mut Cursor::new(&data)).unwrap(), FsvSniff::Marked(_)));
+
+        let mut zip_writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip_writer.start_file("metadata.json", zip::write::SimpleFileOptions::default()).unwrap();
+        zip_writer.write_all(b"{}").unwrap();
+        zip_writer.set_comment("made elsewhere");
+        let unmarked = zip_writer.finish().unwrap().into_inner();
+        assert_eq!(sniff(&mut Cursor::new(&unmarked)).unwrap(), FsvSniff::Unmarked);
+
+        let mut zip_writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip_writer.start_file("readme.txt", zip::write::SimpleFileOptions::default()).unwrap();
+        let plain = zip_writer.finish().unwrap().into_inner();
+        assert!(!is_fsv(&mut Cursor::new(&plain)).unwrap());
+        assert!(!is_fsv(&mut Cursor::new(b"not a zip")).unwrap());
+
+        assert_eq!(comment_format_version(b"FunscriptVideo/1.2.3"), Some(Version::new(1, 2, 3)));
+        assert_eq!(comment_format_version(b"FunscriptVideo/"), None);
+    }
+}
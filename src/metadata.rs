@@ -1,11 +1,15 @@
-use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap};
 
-use crate::semver::Version;
+use crate::{duration::DurationMs, semver::Version};
+
+/// Array fields whose order carries no meaning. These are sorted in canonical JSON.
+const UNORDERED_ARRAY_FIELDS: [&str; 5] = ["extensions", "tags", "performers", "additional_axes", "socials"];
 
 /// The root FSV metadata object.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FsvMetadata {
     pub format_version: Version,
     #[serde(default)]
@@ -15,6 +19,11 @@ pub struct FsvMetadata {
     // Optional in spec, but MUST NOT be null -> use empty string as "missing"
     #[serde(default)]
     pub title: String,
+    // Not in the spec; left out when empty so existing archives serialize unchanged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub performers: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub studio: String,
     #[serde(default)]
     pub creators: CreatorsMetadata,
     pub video_formats: Vec<VideoFormat>,
@@ -33,6 +42,8 @@ impl FsvMetadata {
             extensions: Vec::new(),
             tags: Vec::new(),
             title: String::new(),
+            performers: Vec::new(),
+            studio: String::new(),
             creators: CreatorsMetadata::new(),
             video_formats: Vec::new(),
             script_variants: Vec::new(),
@@ -64,9 +75,69 @@ impl FsvMetadata {
     pub fn add_subtitle_track(&mut self, subtitle_track: SubtitleTrack) {
         self.subtitle_tracks.push(subtitle_track);
     }
+
+    /// The data of an extension kept in `field`, its default (e.g. an empty list) if the field isn't set.
+    pub fn extension_data<T: DeserializeOwned + Default>(&self, field: &str) -> serde_json::Result<T> {
+        Ok(self.extra_field(field)?.unwrap_or_default())
+    }
+
+    /// Store the data of extension `id` in `field` and declare the extension, or with `None` drop both field and declaration.
+    pub fn set_extension_data<T: Serialize + ?Sized>(&mut self, id: &str, field: &str, data: Option<&T>) -> serde_json::Result<()> {
+        let Some(data) = data else {
+            self.extra.remove(field);
+            self.extensions.retain(|declared| declared != id);
+            return Ok(());
+        };
+
+        self.set_extra_field(field, data)?;
+        if !self.extensions.iter().any(|declared| declared == id) {
+            self.extensions.push(id.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Serialize into canonical JSON: object keys sorted, unordered lists (tags, extensions, axes, socials) sorted,
+    /// and integral floats written as integers. Ordered lists such as video formats keep their order.
+    /// Identical metadata always yields identical bytes, which signing, diffing and reproducible builds rely on.
+    pub fn to_canonical_json(&self) -> serde_json::Result<String> {
+        let value = canonicalize_value(serde_json::to_value(self)?, None);
+        serde_json::to_string_pretty(&value)
+    }
+}
+
+fn canonicalize_value(value: Value, key: Option<&str>) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter()
+                .map(|(key, value)| {
+                    let value = canonicalize_value(value, Some(&key));
+                    (key, value)
+                })
+                .collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(entries.into_iter().collect())
+        },
+        Value::Array(items) => {
+            let mut items: Vec<Value> = items.into_iter().map(|item| canonicalize_value(item, None)).collect();
+            if key.is_some_and(|key| UNORDERED_ARRAY_FIELDS.contains(&key)) {
+                items.sort_by_key(|item| item.to_string());
+            }
+            Value::Array(items)
+        },
+        Value::Number(number) => {
+            // Largest integer an f64 represents exactly
+            const MAX_EXACT_INT: f64 = 9_007_199_254_740_992.0;
+            match number.as_f64() {
+                Some(float) if number.is_f64() && float.fract() == 0.0 && float.abs() <= MAX_EXACT_INT => Value::from(float as i64),
+                _ => Value::Number(number),
+            }
+        },
+        other => other,
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreatorsMetadata {
     #[serde(default)]
     pub videos: Vec<WorkCreatorsMetadata>,
@@ -117,11 +188,15 @@ impl Default for CreatorsMetadata {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WorkCreatorsMetadata {
     pub work_name: String,
     pub source_url: String,
     pub creator_info: CreatorInfo,
+    /// Key of the creator's database record `creator_info` was copied from, which `sync-creators` refreshes it from.
+    /// Not in the spec; left out when unset so existing archives serialize unchanged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creator_key: Option<String>,
     #[serde(flatten)]
     pub extra: HashMap<String, Value>,
 }
@@ -132,12 +207,18 @@ impl WorkCreatorsMetadata {
             work_name,
             source_url,
             creator_info,
+            creator_key: None,
             extra: HashMap::new(),
         }
     }
+
+    pub fn with_creator_key(mut self, creator_key: Option<String>) -> Self {
+        self.creator_key = creator_key;
+        self
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreatorInfo {
     pub name: String,
     #[serde(default)]
@@ -152,17 +233,88 @@ impl CreatorInfo {
     }
 }
 
+/// Typed access to the fields a metadata object keeps in `extra`: extension data, and fields of other tools or newer
+/// versions of the format. Every change this tool makes writes them back as they were read.
+pub trait ExtraFields {
+    fn extra(&self) -> &HashMap<String, Value>;
+
+    fn extra_mut(&mut self) -> &mut HashMap<String, Value>;
+
+    /// `field` as a `T`, `None` if it isn't set.
+    fn extra_field<T: DeserializeOwned>(&self, field: &str) -> serde_json::Result<Option<T>> {
+        self.extra().get(field).map(T::deserialize).transpose()
+    }
+
+    /// Replace `field` with `value`.
+    fn set_extra_field<T: Serialize + ?Sized>(&mut self, field: &str, value: &T) -> serde_json::Result<()> {
+        let value = serde_json::to_value(value)?;
+        self.extra_mut().insert(field.to_string(), value);
+        Ok(())
+    }
+
+    /// Merge `value` into `field`, keeping the keys of an object already there that `value` doesn't set (see `merge_value`).
+    fn merge_extra_field<T: Serialize + ?Sized>(&mut self, field: &str, value: &T) -> serde_json::Result<()> {
+        let value = serde_json::to_value(value)?;
+        match self.extra_mut().entry(field.to_string()) {
+            Entry::Occupied(mut existing) => merge_value(existing.get_mut(), value),
+            Entry::Vacant(vacant) => {
+                vacant.insert(value);
+            },
+        }
+
+        Ok(())
+    }
+
+    fn remove_extra_field(&mut self, field: &str) -> Option<Value> {
+        self.extra_mut().remove(field)
+    }
+}
+
+macro_rules! impl_extra_fields {
+    ($($type:ty),*) => {
+        $(impl ExtraFields for $type {
+            fn extra(&self) -> &HashMap<String, Value> {
+                &self.extra
+            }
+
+            fn extra_mut(&mut self) -> &mut HashMap<String, Value> {
+                &mut self.extra
+            }
+        })*
+    };
+}
+
+impl_extra_fields!(FsvMetadata, CreatorsMetadata, WorkCreatorsMetadata, CreatorInfo, VideoFormat, ScriptVariant, SubtitleTrack);
+
+/// Merge `value` into `target` recursively: where both are objects, keys only `target` has are kept, so fields another tool
+/// added to an object survive this tool rewriting it. Anything else in `target` is replaced.
+pub fn merge_value(target: &mut Value, value: Value) {
+    match (target, value) {
+        (Value::Object(target), Value::Object(value)) => {
+            for (key, value) in value {
+                match target.get_mut(&key) {
+                    Some(existing) => merge_value(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    },
+                }
+            }
+        },
+        (target, value) => *target = value,
+    }
+}
+
 pub trait WorkItem {
     fn get_name(&self) -> &str;
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct VideoFormat {
     pub name: String,
     #[serde(default)]
     pub description: String,
     #[serde(default)]
-    pub duration: u64,
+    pub duration: DurationMs,
     #[serde(default)]
     pub checksum: String,
     #[serde(flatten)]
@@ -170,11 +322,11 @@ pub struct VideoFormat {
 }
 
 impl VideoFormat {
-    pub fn new(name: String, description: String, duration_ms: u64, checksum: String) -> Self {
+    pub fn new(name: String, description: String, duration: DurationMs, checksum: String) -> Self {
         VideoFormat {
             name,
             description,
-            duration: duration_ms,
+            duration,
             checksum,
             extra: HashMap::new(),
         }
@@ -187,7 +339,7 @@ impl WorkItem for VideoFormat {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ScriptVariant {
     pub name: String,
     #[serde(default)]
@@ -195,7 +347,7 @@ pub struct ScriptVariant {
     #[serde(default)]
     pub additional_axes: Vec<String>,
     #[serde(default)]
-    pub duration: u64,
+    pub duration: DurationMs,
     #[serde(default)]
     pub start_offset: i64,
     #[serde(default)]
@@ -205,7 +357,7 @@ pub struct ScriptVariant {
 }
 
 impl ScriptVariant {
-    pub fn new(name: String, description: String, additional_axes: Vec<String>, duration: u64, start_offset: i64, checksum: String) -> Self {
+    pub fn new(name: String, description: String, additional_axes: Vec<String>, duration: DurationMs, start_offset: i64, checksum: String) -> Self {
         ScriptVariant {
             name,
             description,
@@ -224,7 +376,7 @@ impl WorkItem for ScriptVariant {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SubtitleTrack {
     pub name: String,
     pub language: String,
@@ -246,6 +398,49 @@ impl SubtitleTrack {
             extra: HashMap::new(),
         }
     }
+
+    /// The track's language as a normalized BCP-47 tag, e.g. `en-US` for `EN_us`. Empty if the metadata doesn't give one.
+    pub fn language_tag(&self) -> String {
+        normalize_language_tag(&self.language)
+    }
+
+    /// Whether the track's language falls under any of `ranges`, matched as in RFC 4647 basic filtering:
+    /// `en` matches `en` and `en-US`, but not `eng`. `*` matches every track.
+    pub fn matches_language(&self, ranges: &[String]) -> bool {
+        let tag = self.language_tag().to_ascii_lowercase();
+        ranges.iter().any(|range| {
+            let range = normalize_language_tag(range).to_ascii_lowercase();
+            range == "*" || (!range.is_empty() && (tag == range || tag.strip_prefix(&range).is_some_and(|rest| rest.starts_with('-'))))
+        })
+    }
+}
+
+/// Normalize the case and separators of a BCP-47 language tag: `_` becomes `-`, the language is lowercased,
+/// a four letter script titlecased and a two letter region uppercased (`ZH_hant_tw` becomes `zh-Hant-TW`).
+pub fn normalize_language_tag(tag: &str) -> String {
+    let mut subtags = Vec::new();
+    let mut private = false;
+    for (i, subtag) in tag.trim().split(['-', '_']).filter(|subtag| !subtag.is_empty()).enumerate() {
+        let alphabetic = subtag.chars().all(|c| c.is_ascii_alphabetic());
+        // Subtags after a singleton (e.g. the `x` of private use tags) have no case conventions
+        let normalized = if i == 0 || private {
+            subtag.to_ascii_lowercase()
+        }
+        else if alphabetic && subtag.len() == 4 {
+            subtag[..1].to_ascii_uppercase() + &subtag[1..].to_ascii_lowercase()
+        }
+        else if alphabetic && subtag.len() == 2 {
+            subtag.to_ascii_uppercase()
+        }
+        else {
+            subtag.to_ascii_lowercase()
+        };
+
+        private |= subtag.len() == 1;
+        subtags.push(normalized);
+    }
+
+    subtags.join("-")
 }
 
 impl WorkItem for SubtitleTrack {
@@ -253,3 +448,71 @@ impl WorkItem for SubtitleTrack {
         &self.name
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_json() {
+        let mut metadata = FsvMetadata::new(Version::new(1, 0, 0));
+        metadata.tags = vec!["b".to_string(), "a".to_string()];
+        metadata.extra.insert("z_extra".to_string(), serde_json::json!({ "y": 2.0, "x": 1.5 }));
+        metadata.add_video_format(VideoFormat::new("2.mp4".to_string(), String::new(), DurationMs::from_millis(0), String::new()));
+        metadata.add_video_format(VideoFormat::new("1.mp4".to_string(), String::new(), DurationMs::from_millis(0), String::new()));
+
+        let json = metadata.to_canonical_json().unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["tags"], serde_json::json!(["a", "b"]));
+        assert_eq!(value["video_formats"][0]["name"], "2.mp4");
+        assert!(json.contains("\"y\": 2\n"));
+        assert!(json.find("\"x\"").unwrap() < json.find("\"y\"").unwrap());
+        assert!(json.find("\"creators\"").unwrap() < json.find("\"format_version\"").unwrap());
+        // Empty optional fields stay out of the document
+        assert!(value.get("performers").is_none() && value.get("studio").is_none());
+
+        metadata.performers = vec!["Zoe".to_string(), "Ann".to_string()];
+        let value: Value = serde_json::from_str(&metadata.to_canonical_json().unwrap()).unwrap();
+        assert_eq!(value["performers"], serde_json::json!(["Ann", "Zoe"]));
+    }
+
+    #[test]
+    fn test_extra_fields() {
+        let json = r#"{"format_version":"1.0.0","video_formats":[{"name":"a.mp4","x_color":"hdr"}],"script_variants":[],"x_app":{"id":1,"keep":true}}"#;
+        let mut metadata: FsvMetadata = serde_json::from_str(json).unwrap();
+        assert_eq!(metadata.video_formats[0].extra_field::<String>("x_color").unwrap().as_deref(), Some("hdr"));
+        assert!(metadata.extra_field::<String>("x_app").is_err());
+        assert_eq!(metadata.extra_field::<u64>("missing").unwrap(), None);
+
+        // Merging keeps the keys only the stored object has, setting replaces it
+        metadata.merge_extra_field("x_app", &serde_json::json!({ "id": 2 })).unwrap();
+        assert_eq!(metadata.extra["x_app"], serde_json::json!({ "id": 2, "keep": true }));
+        metadata.set_extra_field("x_app", &serde_json::json!({ "id": 3 })).unwrap();
+        assert_eq!(metadata.extra["x_app"], serde_json::json!({ "id": 3 }));
+        assert!(metadata.remove_extra_field("x_app").is_some());
+
+        metadata.set_extension_data("x.app", "x_list", Some(&[1, 2])).unwrap();
+        assert_eq!(metadata.extension_data::<Vec<u32>>("x_list").unwrap(), [1, 2]);
+        assert_eq!(metadata.extensions, ["x.app"]);
+        metadata.set_extension_data::<[u32]>("x.app", "x_list", None).unwrap();
+        assert!(metadata.extension_data::<Vec<u32>>("x_list").unwrap().is_empty() && metadata.extensions.is_empty());
+
+        let value: Value = serde_json::from_str(&serde_json::to_string(&metadata).unwrap()).unwrap();
+        assert_eq!(value["video_formats"][0]["x_color"], "hdr");
+    }
+
+    #[test]
+    fn test_subtitle_language() {
+        assert_eq!(normalize_language_tag(" ZH_hant_tw "), "zh-Hant-TW");
+        assert_eq!(normalize_language_tag("en-x-ab"), "en-x-ab");
+        assert_eq!(normalize_language_tag("es-419"), "es-419");
+
+        let track = SubtitleTrack::new("video.srt".to_string(), "EN_us".to_string(), String::new(), String::new());
+        assert_eq!(track.language_tag(), "en-US");
+        assert!(track.matches_language(&["ja".to_string(), "en".to_string()]));
+        assert!(track.matches_language(&["en-us".to_string()]));
+        assert!(track.matches_language(&["*".to_string()]));
+        assert!(!track.matches_language(&["en-GB".to_string(), "e".to_string()]));
+        assert!(!track.matches_language(&[]));
+    }
+}
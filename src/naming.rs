@@ -0,0 +1,137 @@
+use std::path::Path;
+
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::info;
+
+use crate::{fsv::{self, ItemType}, import, transcode::{self, TranscodeError}};
+
+#[derive(Debug, Error)]
+pub enum NamingError {
+    #[error("Invalid naming pattern '{0}': {1}")]
+    InvalidPattern(String, String),
+    #[error("'{0}' does not follow the naming policy, it should be named '{1}'")]
+    Violation(String, String),
+    #[error("Transcode error: {0}")]
+    Transcode(#[from] TranscodeError),
+}
+
+/// What happens to a file whose name doesn't follow the naming policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NamingAction {
+    /// Store it in the archive under the name the policy expects
+    #[default]
+    Rename,
+    /// Refuse to add it
+    Reject,
+}
+
+/// Entry name patterns enforced when files are added, e.g. `{resolution}_{codec}.mp4` for videos or `{title}.{axis}.funscript`
+/// for scripts. Item types without a pattern keep their file names.
+///
+/// Placeholders are `{title}` (the FSV's title), `{stem}` and `{ext}` (of the added file), `{axis}` (scripts, empty for main scripts),
+/// and `{resolution}` (e.g. `1920x1080`) and `{codec}` (videos, probed with ffprobe).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NamingPolicy {
+    pub video: Option<String>,
+    pub script: Option<String>,
+    pub subtitle: Option<String>,
+    pub on_violation: NamingAction,
+}
+
+/// Replace the `{placeholder}`s in `pattern` with the values from `lookup`, which returns `None` for unknown placeholders.
+/// An empty value takes a neighbouring `.` with it, so `{title}.{axis}.funscript` names a main script `Title.funscript`.
+/// The result is sanitized into a single path component.
+pub fn render(pattern: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, NamingError> {
+    let invalid = |reason: String| NamingError::InvalidPattern(pattern.to_string(), reason);
+    let mut name = String::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or_else(|| invalid("unclosed '{'".to_string()))? + start;
+        let placeholder = &rest[start + 1..end];
+        let value = lookup(placeholder).ok_or_else(|| invalid(format!("unsupported placeholder '{{{}}}'", placeholder)))?;
+        let value = value.trim();
+        name.push_str(value);
+        rest = &rest[end + 1..];
+        if value.is_empty() && name.ends_with('.') && rest.starts_with('.') {
+            rest = &rest[1..];
+        }
+    }
+
+    name.push_str(rest);
+    fsv::sanitize_path_component(&name).ok_or_else(|| invalid("the name is empty".to_string()))
+}
+
+impl NamingPolicy {
+    pub fn pattern(&self, item_type: ItemType) -> Option<&str> {
+        match item_type {
+            ItemType::Video => self.video.as_deref(),
+            ItemType::Script => self.script.as_deref(),
+            ItemType::Subtitle => self.subtitle.as_deref(),
+        }
+    }
+
+    /// Archive entry name for the file `file_name` (at `file_path`) added as `item_type` to an FSV titled `title`.
+    /// A name that doesn't follow the policy is replaced by the one it expects, or rejected if the policy says so.
+    pub fn entry_name(&self, item_type: ItemType, file_name: &str, file_path: &Path, title: &str) -> Result<String, NamingError> {
+        let Some(pattern) = self.pattern(item_type) else {
+            return Ok(file_name.to_string());
+        };
+
+        let (mut stem, ext) = file_name.rsplit_once('.').unwrap_or((file_name, ""));
+        let mut axis = None;
+        if matches!(item_type, ItemType::Script) && let Some((script_stem, script_axis)) = import::split_script_name(file_name) {
+            (stem, axis) = (script_stem, script_axis);
+        }
+
+        // Probing is only worth it if the pattern uses the stream properties
+        let stream = match matches!(item_type, ItemType::Video) && (pattern.contains("{resolution}") || pattern.contains("{codec}")) {
+            true => Some(transcode::probe_video_stream(file_path)?),
+            false => None,
+        };
+        let expected = render(pattern, |placeholder| match placeholder {
+            "title" => Some(title.to_string()),
+            "stem" => Some(stem.to_string()),
+            "ext" => Some(ext.to_string()),
+            "axis" if matches!(item_type, ItemType::Script) => Some(axis.unwrap_or_default().to_string()),
+            "resolution" => stream.as_ref().map(|stream| format!("{}x{}", stream.width, stream.height)),
+            "codec" => stream.as_ref().map(|stream| stream.codec_name.clone()),
+            _ => None,
+        })?;
+        if expected == file_name {
+            return Ok(expected);
+        }
+
+        match self.on_violation {
+            NamingAction::Rename => {
+                info!(operation = "add", item = %file_name, outcome = "renamed", "Adding as '{}' to follow the naming policy", expected);
+                Ok(expected)
+            },
+            NamingAction::Reject => Err(NamingError::Violation(file_name.to_string(), expected)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_naming_policy() {
+        let mut policy = NamingPolicy { script: Some("{title}.{axis}.funscript".to_string()), ..Default::default() };
+        let entry_name = |policy: &NamingPolicy, name: &str| policy.entry_name(ItemType::Script, name, Path::new(name), "My Scene");
+        assert_eq!(entry_name(&policy, "scene.funscript").unwrap(), "My Scene.funscript");
+        assert_eq!(entry_name(&policy, "scene.roll.funscript").unwrap(), "My Scene.roll.funscript");
+        assert_eq!(policy.entry_name(ItemType::Subtitle, "scene.srt", Path::new("scene.srt"), "My Scene").unwrap(), "scene.srt");
+
+        policy.on_violation = NamingAction::Reject;
+        assert_eq!(entry_name(&policy, "My Scene.funscript").unwrap(), "My Scene.funscript");
+        assert!(matches!(entry_name(&policy, "scene.funscript"), Err(NamingError::Violation(_, _))));
+
+        policy.script = Some("{resolution}.funscript".to_string());
+        assert!(matches!(entry_name(&policy, "scene.funscript"), Err(NamingError::InvalidPattern(_, _))));
+    }
+}
@@ -0,0 +1,65 @@
+use serde_json::Value;
+
+use crate::{extensions, metadata::FsvMetadata};
+
+/// Metadata field holding the `fsv.notes` extension data: the name of the notes entry.
+pub const NOTES_FIELD: &str = "notes";
+pub const DEFAULT_NOTES_NAME: &str = "notes.md";
+
+const NOTES_FILE_EXTENSIONS: [&str; 3] = ["md", "markdown", "txt"];
+
+/// Name of the notes entry declared in metadata. A missing field means there are no notes.
+pub fn notes_from_metadata(metadata: &FsvMetadata) -> Result<Option<String>, serde_json::Error> {
+    metadata.extension_data(NOTES_FIELD)
+}
+
+/// Point the notes at the entry `name`, declaring the extension, or with `None` drop both field and declaration.
+pub fn set_notes(metadata: &mut FsvMetadata, name: Option<&str>) -> serde_json::Result<()> {
+    metadata.set_extension_data(extensions::NOTES_EXTENSION, NOTES_FIELD, name)
+}
+
+pub fn validate_notes(metadata: &FsvMetadata, entry_names: &[&str]) -> Vec<String> {
+    let Some(value) = metadata.extra.get(NOTES_FIELD) else {
+        return vec![format!("Missing '{}' field", NOTES_FIELD)];
+    };
+
+    let Value::String(notes) = value else {
+        return vec![format!("'{}' must be the name of an archive entry", NOTES_FIELD)];
+    };
+
+    let mut issues = Vec::new();
+    if !entry_names.contains(&notes.as_str()) {
+        issues.push(format!("Notes '{}' not found in archive", notes));
+    }
+
+    let is_text = notes.rsplit_once('.')
+        .is_some_and(|(_, ext)| NOTES_FILE_EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)));
+    if !is_text {
+        issues.push(format!("Notes '{}' are not a supported document type ({})", notes, NOTES_FILE_EXTENSIONS.join(", ")));
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semver::Version;
+
+    #[test]
+    fn test_notes() {
+        let mut metadata = FsvMetadata::new(Version::new(1, 0, 0));
+        assert_eq!(notes_from_metadata(&metadata).unwrap(), None);
+
+        set_notes(&mut metadata, Some(DEFAULT_NOTES_NAME)).unwrap();
+        assert_eq!(notes_from_metadata(&metadata).unwrap().as_deref(), Some(DEFAULT_NOTES_NAME));
+        assert_eq!(metadata.extensions, [extensions::NOTES_EXTENSION]);
+        assert!(validate_notes(&metadata, &["metadata.json", DEFAULT_NOTES_NAME]).is_empty());
+        assert_eq!(validate_notes(&metadata, &["metadata.json"]), ["Notes 'notes.md' not found in archive"]);
+        set_notes(&mut metadata, Some("notes.pdf")).unwrap();
+        assert_eq!(validate_notes(&metadata, &["notes.pdf"]).len(), 1);
+
+        set_notes(&mut metadata, None).unwrap();
+        assert!(metadata.extensions.is_empty() && !metadata.extra.contains_key(NOTES_FIELD));
+    }
+}
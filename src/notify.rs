@@ -0,0 +1,206 @@
+use serde::Deserialize;
+#[cfg(feature = "http")]
+use std::{sync::{Arc, atomic::{AtomicBool, Ordering}}, thread::JoinHandle, time::Duration};
+#[cfg(feature = "http")]
+use tracing::debug;
+use tracing::warn;
+
+use crate::progress::{EventBroadcaster, ProgressEvent};
+
+/// How long the notifier waits for an event before checking whether it was asked to finish
+#[cfg(feature = "http")]
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Events notifications are sent for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// An import made a new archive
+    Created,
+    /// An import, or an operation requested through the API, failed
+    Failed,
+    /// Validation requested through the API found errors
+    ValidationFailed,
+}
+
+/// Where notifications are sent.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum NotificationTarget {
+    /// The event as JSON (as on the event feed), with a `message` added
+    Webhook { url: String },
+    /// A Discord channel webhook
+    Discord { url: String },
+    /// An ntfy topic, e.g. `https://ntfy.sh/my-library`, with an access token for protected topics
+    Ntfy {
+        url: String,
+        #[serde(default)]
+        token: Option<String>,
+    },
+}
+
+impl NotificationTarget {
+    pub fn url(&self) -> &str {
+        match self {
+            NotificationTarget::Webhook { url } | NotificationTarget::Discord { url } | NotificationTarget::Ntfy { url, .. } => url,
+        }
+    }
+
+    /// Headers and body of the request notifying of `event`.
+    pub fn request(&self, kind: NotificationEvent, event: &ProgressEvent, message: &str) -> (Vec<(&'static str, String)>, String) {
+        let json = "application/json".to_string();
+        match self {
+            NotificationTarget::Webhook { .. } => {
+                let mut body = serde_json::to_value(event).unwrap_or_default();
+                body["message"] = message.into();
+                (vec![("Content-Type", json)], body.to_string())
+            },
+            NotificationTarget::Discord { .. } => (vec![("Content-Type", json)], serde_json::json!({ "content": message }).to_string()),
+            NotificationTarget::Ntfy { token, .. } => {
+                let mut headers = vec![("Title", "FunscriptVideo".to_string())];
+                if kind != NotificationEvent::Created {
+                    headers.push(("Priority", "high".to_string()));
+                    headers.push(("Tags", "warning".to_string()));
+                }
+                if let Some(token) = token {
+                    headers.push(("Authorization", format!("Bearer {}", token)));
+                }
+                (headers, message.to_string())
+            },
+        }
+    }
+}
+
+/// Notifications sent by the `watch` and `serve` daemons, so unattended ingestion doesn't fail silently.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    pub targets: Vec<NotificationTarget>,
+    /// Events notified of; all of them if empty
+    pub events: Vec<NotificationEvent>,
+}
+
+impl NotificationsConfig {
+    /// The kind of notification `event` calls for and its message, if it is one notified of.
+    pub fn notification(&self, event: &ProgressEvent) -> Option<(NotificationEvent, String)> {
+        let (kind, message) = match event {
+            ProgressEvent::Completed { operation, target } if operation == "import" => (NotificationEvent::Created, format!("New FSV created: {}", target)),
+            ProgressEvent::Failed { operation, target, error } => (NotificationEvent::Failed, format!("{} failed for {}: {}", operation, target, error)),
+            ProgressEvent::ValidationFailed { target, errors } => (NotificationEvent::ValidationFailed, format!("{} failed validation: {}", target, errors.join("; "))),
+            _ => return None,
+        };
+
+        (self.events.is_empty() || self.events.contains(&kind)).then_some((kind, message))
+    }
+}
+
+/// Sends notifications for the events published to a broadcaster, on a thread of its own so the work publishing them
+/// isn't held up by slow targets. Failed notifications are logged and dropped.
+#[derive(Debug)]
+pub struct Notifier {
+    #[cfg(feature = "http")]
+    finish: Arc<AtomicBool>,
+    #[cfg(feature = "http")]
+    thread: JoinHandle<()>,
+}
+
+impl Notifier {
+    /// Notify of the events published to `events` from now on. None without targets, or without the `http` feature
+    /// needed to send them.
+    pub fn spawn(config: NotificationsConfig, events: &EventBroadcaster) -> Option<Notifier> {
+        if config.targets.is_empty() {
+            return None;
+        }
+
+        #[cfg(feature = "http")]
+        {
+            let receiver = events.subscribe();
+            let finish = Arc::new(AtomicBool::new(false));
+            let finished = finish.clone();
+            let thread = std::thread::spawn(move || {
+                let agent = ureq::Agent::new_with_defaults();
+                loop {
+                    match receiver.recv_timeout(POLL_INTERVAL) {
+                        Ok(event) => notify(&agent, &config, &event),
+                        Err(std::sync::mpsc::RecvTimeoutError::Timeout) if !finished.load(Ordering::Relaxed) => {},
+                        Err(_) => break,
+                    }
+                }
+            });
+
+            Some(Notifier { finish, thread })
+        }
+
+        #[cfg(not(feature = "http"))]
+        {
+            let _ = events;
+            warn!(operation = "notify", "Notifications are configured, but sending them needs the `http` feature");
+            None
+        }
+    }
+
+    /// Send the notifications of the events published so far, then stop.
+    pub fn finish(self) {
+        #[cfg(feature = "http")]
+        {
+            self.finish.store(true, Ordering::Relaxed);
+            let _ = self.thread.join();
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+fn notify(agent: &ureq::Agent, config: &NotificationsConfig, event: &ProgressEvent) {
+    let Some((kind, message)) = config.notification(event) else {
+        return;
+    };
+
+    for target in &config.targets {
+        let (headers, body) = target.request(kind, event, &message);
+        let mut request = agent.post(target.url());
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        match request.send(body) {
+            Ok(_) => debug!(operation = "notify", url = %target.url(), "Sent notification"),
+            Err(err) => warn!(operation = "notify", url = %target.url(), error = %err, "Failed to send notification"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notifications() {
+        let config: NotificationsConfig = serde_json::from_value(serde_json::json!({
+            "targets": [
+                { "type": "webhook", "url": "https://example.com/hook" },
+                { "type": "ntfy", "url": "https://ntfy.sh/library", "token": "secret" },
+            ],
+            "events": ["failed", "validation_failed"],
+        })).unwrap();
+
+        // Only the configured events are notified of
+        assert_eq!(config.notification(&ProgressEvent::completed("import", "a.fsv")), None);
+        assert_eq!(config.notification(&ProgressEvent::started("import", "a.fsv")), None);
+        let failed = ProgressEvent::failed("import", "a.fsv", "disk full");
+        let (kind, message) = config.notification(&failed).unwrap();
+        assert_eq!((kind, message.as_str()), (NotificationEvent::Failed, "import failed for a.fsv: disk full"));
+        let all = NotificationsConfig { events: vec![], ..config.clone() };
+        assert_eq!(all.notification(&ProgressEvent::completed("import", "a.fsv")).unwrap().1, "New FSV created: a.fsv");
+        assert_eq!(all.notification(&ProgressEvent::completed("scan", "library")), None);
+
+        let (headers, body) = config.targets[0].request(kind, &failed, &message);
+        assert_eq!(headers, [("Content-Type", "application/json".to_string())]);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap(), serde_json::json!({
+            "event": "failed", "operation": "import", "target": "a.fsv", "error": "disk full", "message": message,
+        }));
+        let (headers, body) = config.targets[1].request(kind, &failed, &message);
+        assert!(headers.contains(&("Authorization", "Bearer secret".to_string())) && headers.contains(&("Priority", "high".to_string())));
+        assert_eq!(body, message);
+        let discord = NotificationTarget::Discord { url: "https://discord.com/api/webhooks/1/x".to_string() };
+        assert_eq!(discord.request(kind, &failed, &message).1, serde_json::json!({ "content": message }).to_string());
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{extensions, metadata::FsvMetadata};
+
+/// Metadata field holding the `fsv.pair-offsets` extension data.
+pub const PAIR_OFFSETS_FIELD: &str = "pair_offsets";
+
+/// The `start_offset` (script time minus video time, in ms) of a script variant when played against one video format.
+/// Overrides the variant's own `start_offset` for that pair, as different encodes often have different intros.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PairOffset {
+    /// Name of the script variant
+    pub script: String,
+    /// Name of the video format
+    pub video: String,
+    pub offset_ms: i64,
+    /// Fields of other tools, kept when the offset is changed
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl PairOffset {
+    pub fn new(script: String, video: String, offset_ms: i64) -> Self {
+        PairOffset { script, video, offset_ms, extra: HashMap::new() }
+    }
+}
+
+/// Read the per-pair offsets declared in metadata. A missing field means every pair uses the variant's `start_offset`.
+pub fn pair_offsets_from_metadata(metadata: &FsvMetadata) -> Result<Vec<PairOffset>, serde_json::Error> {
+    metadata.extension_data(PAIR_OFFSETS_FIELD)
+}
+
+/// Store `offsets`, declaring the extension while there are any and dropping both field and declaration once there are none.
+pub fn set_pair_offsets(metadata: &mut FsvMetadata, offsets: &[PairOffset]) -> serde_json::Result<()> {
+    metadata.set_extension_data(extensions::PAIR_OFFSETS_EXTENSION, PAIR_OFFSETS_FIELD, (!offsets.is_empty()).then_some(offsets))
+}
+
+/// The offset set for `script` against `video`, if any. Malformed extension data counts as none.
+pub fn pair_offset(metadata: &FsvMetadata, script: &str, video: &str) -> Option<i64> {
+    pair_offsets_from_metadata(metadata).ok()?.into_iter().find(|offset| offset.script == script && offset.video == video).map(|offset| offset.offset_ms)
+}
+
+/// The offset to play the script variant `script` with against `video` (the first video format listed if `None`):
+/// its pair offset, or else the variant's `start_offset`. `None` if `script` is not a script variant.
+pub fn start_offset(metadata: &FsvMetadata, script: &str, video: Option<&str>) -> Option<i64> {
+    let variant = metadata.script_variants.iter().find(|variant| variant.name == script)?;
+    let video = video.or_else(|| metadata.video_formats.first().map(|format| format.name.as_str()));
+    Some(video.and_then(|video| pair_offset(metadata, script, video)).unwrap_or(variant.start_offset))
+}
+
+/// Shift the actions of a funscript so it plays in sync with a video it has `offset_ms` of start offset against.
+/// Actions that would land before the start of the video are dropped, everything else in the document is kept as is.
+pub fn shift_funscript(data: &[u8], offset_ms: i64) -> Result<Vec<u8>, serde_json::Error> {
+    let mut funscript: Value = serde_json::from_slice(data)?;
+    if let Some(Value::Array(actions)) = funscript.get_mut("actions") {
+        actions.retain_mut(|action| {
+            let Some(at) = action.get("at").and_then(Value::as_i64) else {
+                return true;
+            };
+
+            match at - offset_ms {
+                at if at < 0 => false,
+                at => {
+                    action["at"] = Value::from(at);
+                    true
+                },
+            }
+        });
+    }
+
+    serde_json::to_vec(&funscript)
+}
+
+pub fn validate_pair_offsets(metadata: &FsvMetadata, _entry_names: &[&str]) -> Vec<String> {
+    let Some(value) = metadata.extra.get(PAIR_OFFSETS_FIELD) else {
+        return vec![format!("Missing '{}' field", PAIR_OFFSETS_FIELD)];
+    };
+
+    let Value::Array(items) = value else {
+        return vec![format!("'{}' must be an array", PAIR_OFFSETS_FIELD)];
+    };
+
+    let mut issues = Vec::new();
+    let mut seen = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        let offset = match serde_json::from_value::<PairOffset>(item.clone()) {
+            Ok(offset) => offset,
+            Err(err) => {
+                issues.push(format!("Pair offset {} is malformed: {}", i, err));
+                continue;
+            },
+        };
+
+        if !metadata.script_variants.iter().any(|variant| variant.name == offset.script) {
+            issues.push(format!("Pair offset script '{}' is not a script variant", offset.script));
+        }
+
+        if !metadata.video_formats.iter().any(|format| format.name == offset.video) {
+            issues.push(format!("Pair offset video '{}' is not a video format", offset.video));
+        }
+
+        if seen.contains(&(offset.script.clone(), offset.video.clone())) {
+            issues.push(format!("Pair offset for '{}' against '{}' is set more than once", offset.script, offset.video));
+        } else {
+            seen.push((offset.script, offset.video));
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{duration::DurationMs, metadata::{ScriptVariant, VideoFormat}, semver::Version};
+
+    #[test]
+    fn test_pair_offsets() {
+        let mut metadata = FsvMetadata::new(Version::new(1, 0, 0));
+        metadata.add_video_format(VideoFormat::new("scene.mp4".to_string(), String::new(), DurationMs::from_millis(1000), String::new()));
+        metadata.add_video_format(VideoFormat::new("scene_4k.mp4".to_string(), String::new(), DurationMs::from_millis(1000), String::new()));
+        metadata.add_script_variant(ScriptVariant::new("scene.funscript".to_string(), String::new(), vec![], DurationMs::from_millis(1000), 50, String::new()));
+        let offset = PairOffset::new("scene.funscript".to_string(), "scene_4k.mp4".to_string(), 1500);
+        set_pair_offsets(&mut metadata, std::slice::from_ref(&offset)).unwrap();
+        assert_eq!(metadata.extensions, [extensions::PAIR_OFFSETS_EXTENSION]);
+        assert_eq!(pair_offsets_from_metadata(&metadata).unwrap(), std::slice::from_ref(&offset));
+        assert!(validate_pair_offsets(&metadata, &[]).is_empty());
+
+        assert_eq!(start_offset(&metadata, "scene.funscript", Some("scene_4k.mp4")), Some(1500));
+        assert_eq!(start_offset(&metadata, "scene.funscript", None), Some(50));
+        assert_eq!(start_offset(&metadata, "other.funscript", None), None);
+
+        set_pair_offsets(&mut metadata, &[offset.clone(), PairOffset { video: "missing.mp4".to_string(), ..offset }]).unwrap();
+        assert_eq!(validate_pair_offsets(&metadata, &[]).len(), 1);
+
+        let shifted = shift_funscript(br#"{"actions":[{"at":500,"pos":0},{"at":2000,"pos":100}],"version":"1.0"}"#, 1000).unwrap();
+        assert_eq!(serde_json::from_slice::<Value>(&shifted).unwrap(), serde_json::json!({ "actions": [{ "at": 1000, "pos": 100 }], "version": "1.0" }));
+
+        set_pair_offsets(&mut metadata, &[]).unwrap();
+        assert!(metadata.extensions.is_empty() && !metadata.extra.contains_key(PAIR_OFFSETS_FIELD));
+    }
+}
@@ -0,0 +1,291 @@
+use std::{fs::File, path::{Path, PathBuf}, process::{Command, ExitStatus}, time::SystemTime};
+
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::{fsv::{self, FsvContainer, FsvError}, import, offsets, playback::PlaybackError};
+
+/// Marks a cache directory as a complete extraction. Its modification time is when the pair was last opened.
+const CACHE_MARKER: &str = ".fsv-open";
+
+#[derive(Debug, Error)]
+pub enum OpenError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ZIP archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+    #[error("Playback error: {0}")]
+    Playback(#[from] PlaybackError),
+    #[error("Unable to launch player '{0}': {1}")]
+    Launch(String, std::io::Error),
+    #[error("Player '{0}' exited with {1}")]
+    PlayerFailed(String, ExitStatus),
+}
+
+/// The external player `open` launches, and where it extracts to.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlayerConfig {
+    /// Player executable, looked up on PATH
+    pub command: String,
+    /// Player arguments. `{video}` and `{script}` are replaced by the extracted files; the video is appended if no argument uses `{video}`.
+    pub args: Vec<String>,
+    /// Keep extracted pairs here so reopening an archive is instant. Without one, each `open` extracts to a temporary directory
+    /// that is removed once the player exits.
+    pub cache_dir: Option<PathBuf>,
+    /// Size the cache is kept under, in MiB. Least recently opened pairs are removed first.
+    pub cache_size_mb: u64,
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        PlayerConfig { command: "mpv".to_string(), args: Vec::new(), cache_dir: None, cache_size_mb: 4096 }
+    }
+}
+
+impl PlayerConfig {
+    fn cache_size(&self) -> u64 {
+        self.cache_size_mb.saturating_mul(1024 * 1024)
+    }
+
+    fn command_args(&self, video: &Path, script: Option<&Path>) -> Vec<String> {
+        let video = video.to_string_lossy();
+        let script = script.map(|script| script.to_string_lossy()).unwrap_or_default();
+        let mut args: Vec<String> = self.args.iter().map(|arg| arg.replace("{video}", &video).replace("{script}", &script)).collect();
+        if !self.args.iter().any(|arg| arg.contains("{video}")) {
+            args.push(video.to_string());
+        }
+
+        args
+    }
+}
+
+/// The video and script chosen from an FSV, and the names they are extracted under.
+/// The script and subtitles take the video's stem, so players that look for them next to the video pick them up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OpenSelection {
+    /// (entry name, file name)
+    files: Vec<(String, String)>,
+    video: String,
+    script: Option<String>,
+}
+
+fn select<R: std::io::Read + std::io::Seek>(container: &mut FsvContainer<R>, video_name: Option<&str>, script_name: Option<&str>) -> Result<OpenSelection, OpenError> {
+    let metadata = container.metadata()?;
+    let present: Vec<String> = container.entry_names().map(str::to_string).collect();
+    let is_present = |name: &str| present.iter().any(|entry| entry == name);
+
+    let video = match video_name {
+        Some(name) => metadata.video_formats.iter().find(|format| format.name == name && is_present(name)).ok_or_else(|| PlaybackError::VideoNotFound(name.to_string()))?,
+        None => metadata.video_formats.iter().find(|format| is_present(&format.name)).ok_or(PlaybackError::NoVideo)?,
+    };
+    let variant = match script_name {
+        Some(name) => Some(metadata.script_variants.iter().find(|variant| variant.name == name && is_present(name)).ok_or_else(|| PlaybackError::ScriptNotFound(name.to_string()))?),
+        None => metadata.script_variants.iter().find(|variant| is_present(&variant.name)),
+    };
+
+    let video_file = Path::new(&video.name).file_name().map_or_else(|| video.name.clone(), |name| name.to_string_lossy().to_string());
+    let stem = video_file.rsplit_once('.').map_or(video_file.as_str(), |(stem, _)| stem).to_string();
+    let mut files = vec![(video.name.clone(), video_file.clone())];
+    let mut script = None;
+    if let Some(variant) = variant {
+        let start_offset = offsets::start_offset(&metadata, &variant.name, Some(&video.name)).unwrap_or(variant.start_offset);
+        if start_offset != 0 {
+            warn!(operation = "open", item = %variant.name, video = %video.name, start_offset_ms = start_offset, "Script variant has a start offset, which external players don't apply");
+        }
+
+        let script_file = match variant.name.rsplit_once('.') {
+            Some((_, ext)) => format!("{}.{}", stem, ext),
+            None => stem.clone(),
+        };
+        files.push((variant.name.clone(), script_file.clone()));
+        for (axis, name) in fsv::axis_script_names(variant).into_iter().filter(|(_, name)| is_present(name)) {
+            files.push((name, format!("{}.{}.{}", stem, axis, import::SCRIPT_EXTENSION)));
+        }
+        script = Some(script_file);
+    } else {
+        warn!(operation = "open", item = %video.name, outcome = "video_only", "FSV has no script, opening the video only");
+    }
+
+    for (track, suffix) in fsv::subtitle_suffixes(metadata.subtitle_tracks.iter().filter(|track| is_present(&track.name))) {
+        files.push((track.name.clone(), format!("{}{}", stem, suffix)));
+    }
+
+    Ok(OpenSelection { files, video: video_file, script })
+}
+
+/// Where a selection is extracted to. A temporary directory is removed when dropped.
+struct OpenDir {
+    path: PathBuf,
+    temporary: bool,
+}
+
+impl Drop for OpenDir {
+    fn drop(&mut self) {
+        if self.temporary && let Err(err) = std::fs::remove_dir_all(&self.path) {
+            warn!(operation = "open", item = %self.path.display(), outcome = "cleanup_failed", error = %err, "Unable to remove extraction directory");
+        }
+    }
+}
+
+/// Cache directory name for the archive at `path`. It changes whenever the archive does, so a stale extraction is never reused.
+fn cache_key(path: &Path) -> Result<String, std::io::Error> {
+    let path = std::path::absolute(path)?;
+    let file_metadata = std::fs::metadata(&path)?;
+    let modified = file_metadata.modified()?.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let stem = fsv::sanitize_path_component(&stem).unwrap_or_default();
+    let hash = xxh3_64(format!("{}\0{}\0{}", path.display(), file_metadata.len(), modified).as_bytes());
+
+    Ok(format!("{}-{:016x}", stem, hash))
+}
+
+fn dir_size(dir: &Path) -> Result<u64, std::io::Error> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        size += match file_type.is_dir() {
+            true => dir_size(&entry.path())?,
+            false => entry.metadata()?.len(),
+        };
+    }
+
+    Ok(size)
+}
+
+/// Remove the least recently opened pairs from `cache_dir` (never `keep`) until `needed` more bytes fit in `max_size`.
+/// Only directories with the cache marker are touched, so a cache directory shared with other files is safe.
+fn evict(cache_dir: &Path, keep: &Path, needed: u64, max_size: u64) -> Result<(), std::io::Error> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(cache_dir)? {
+        let path = entry?.path();
+        let Ok(marker) = std::fs::metadata(path.join(CACHE_MARKER)) else {
+            continue;
+        };
+
+        if path != keep {
+            entries.push((marker.modified()?, dir_size(&path)?, path));
+        }
+    }
+
+    entries.sort_by_key(|(last_used, _, _)| *last_used);
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    for (_, size, path) in entries {
+        if total + needed <= max_size {
+            break;
+        }
+
+        info!(operation = "open", item = %path.display(), outcome = "evicted", "Removing '{}' from the player cache", path.display());
+        std::fs::remove_dir_all(&path)?;
+        total -= size;
+    }
+
+    Ok(())
+}
+
+/// The directory to extract into: a cached one (already extracted if `.1` is true) or, without a cache
+/// or for a pair larger than the whole cache, a temporary one.
+fn open_dir(path: &Path, player: &PlayerConfig, needed: u64) -> Result<(OpenDir, bool), std::io::Error> {
+    let Some(cache_dir) = player.cache_dir.as_ref().filter(|_| needed <= player.cache_size()) else {
+        if player.cache_dir.is_some() {
+            info!(operation = "open", archive = %path.display(), outcome = "uncached", "The selected files don't fit in the player cache, extracting them to a temporary directory");
+        }
+
+        let path = std::env::temp_dir().join(format!("fsv-open-{}", std::process::id()));
+        std::fs::create_dir_all(&path)?;
+        return Ok((OpenDir { path, temporary: true }, false));
+    };
+
+    std::fs::create_dir_all(cache_dir)?;
+    let dir = cache_dir.join(cache_key(path)?);
+    let marker = dir.join(CACHE_MARKER);
+    if marker.is_file() {
+        File::options().write(true).open(&marker)?.set_modified(SystemTime::now())?;
+        return Ok((OpenDir { path: dir, temporary: false }, true));
+    }
+
+    // An unmarked directory is left over from an interrupted extraction
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+
+    evict(cache_dir, &dir, needed, player.cache_size())?;
+    std::fs::create_dir_all(&dir)?;
+    Ok((OpenDir { path: dir, temporary: false }, false))
+}
+
+/// Extract a video format (`video_name`, or the first one present) and a script variant (`script_name`, or the first one present)
+/// with its axis scripts and the subtitle tracks, and play them with the configured player. Returns once the player exits; temporary files are removed then.
+pub fn open_fsv(path: &Path, video_name: Option<&str>, script_name: Option<&str>, player: &PlayerConfig) -> Result<(), OpenError> {
+    let mut container = FsvContainer::from_reader(File::open(path)?)?;
+    let selection = select(&mut container, video_name, script_name)?;
+    let mut needed = 0;
+    for (entry, _) in &selection.files {
+        needed += container.entry_size(entry)?;
+    }
+
+    let (dir, cached) = open_dir(path, player, needed)?;
+    if cached {
+        debug!(operation = "open", archive = %path.display(), outcome = "cached", "Using cached files in '{}'", dir.path.display());
+    } else {
+        info!(operation = "open", archive = %path.display(), item = %dir.path.display(), outcome = "started", "Extracting for the player");
+        for (entry, file_name) in &selection.files {
+            container.copy_entry(entry, &mut File::create(dir.path.join(file_name))?)?;
+        }
+
+        if !dir.temporary {
+            File::create(dir.path.join(CACHE_MARKER))?;
+        }
+    }
+
+    let video = dir.path.join(&selection.video);
+    let script = selection.script.as_ref().map(|script| dir.path.join(script));
+    let args = player.command_args(&video, script.as_deref());
+    info!(operation = "open", archive = %path.display(), "Launching {}", player.command);
+    let status = Command::new(&player.command).args(&args).status().map_err(|err| OpenError::Launch(player.command.clone(), err))?;
+    if !status.success() {
+        return Err(OpenError::PlayerFailed(player.command.clone(), status));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsv::FsvBuilder;
+
+    #[test]
+    fn test_open_selection() {
+        let data = FsvBuilder::new("scene").video("videos/scene 4k.mp4", b"video", 1000).script("main.funscript", b"{\"actions\":[]}", 1000)
+            .script("alt.funscript", b"{\"actions\":[]}", 1000).subtitle("subs/english.srt", "en", b"").to_bytes().unwrap();
+        let mut container = FsvContainer::from_reader(std::io::Cursor::new(data)).unwrap();
+        let selection = select(&mut container, None, Some("alt.funscript")).unwrap();
+        assert_eq!(selection.files, [
+            ("videos/scene 4k.mp4".to_string(), "scene 4k.mp4".to_string()),
+            ("alt.funscript".to_string(), "scene 4k.funscript".to_string()),
+            ("subs/english.srt".to_string(), "scene 4k.en.srt".to_string()),
+        ]);
+        assert!(matches!(select(&mut container, Some("missing.mp4"), None), Err(OpenError::Playback(PlaybackError::VideoNotFound(_)))));
+
+        let player = PlayerConfig { args: vec!["--script={script}".to_string()], ..Default::default() };
+        assert_eq!(player.command_args(Path::new("a.mp4"), Some(Path::new("a.funscript"))), ["--script=a.funscript", "a.mp4"]);
+
+        let cache_dir = std::env::temp_dir().join(format!("open_cache_test_{}", std::process::id()));
+        for (name, size) in [("old", 600), ("new", 300)] {
+            std::fs::create_dir_all(cache_dir.join(name)).unwrap();
+            std::fs::write(cache_dir.join(name).join("video.mp4"), vec![0; size]).unwrap();
+            File::create(cache_dir.join(name).join(CACHE_MARKER)).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        std::fs::create_dir_all(cache_dir.join("other")).unwrap();
+        evict(&cache_dir, &cache_dir.join("current"), 200, 1000).unwrap();
+        assert!(!cache_dir.join("old").exists() && cache_dir.join("new").exists() && cache_dir.join("other").exists());
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+}
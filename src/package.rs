@@ -0,0 +1,284 @@
+use std::{collections::BTreeMap, fs::File, io::{BufReader, Read}, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{fsv::{FsvContainer, FsvError}, history::{tool_version, utc_timestamp}};
+
+/// Smallest piece size clients reliably accept
+pub const MIN_PIECE_SIZE: u64 = 16 * 1024;
+/// Largest piece size picked automatically
+const MAX_AUTO_PIECE_SIZE: u64 = 16 * 1024 * 1024;
+/// Number of pieces the automatic piece size aims to stay under
+const TARGET_PIECE_COUNT: u64 = 1500;
+
+#[derive(Debug, Error)]
+pub enum PackageError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+    #[error("Serde json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("Invalid piece size {0}: expected a power of two of at least {MIN_PIECE_SIZE} bytes")]
+    InvalidPieceSize(u64),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PackageOptions {
+    /// Announce URLs, each in its own tier. The first one is also the torrent's `announce`.
+    pub trackers: Vec<String>,
+    /// Bytes per piece, picked from the file size if not set
+    pub piece_size: Option<u64>,
+    /// Set the private flag, so clients only use the trackers to find peers
+    pub private: bool,
+    /// Where to write the torrent and manifest (defaults to the directory holding the FSV)
+    pub output_dir: Option<PathBuf>,
+}
+
+/// Describes a release for publishing alongside its torrent.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReleaseManifest {
+    pub title: String,
+    pub tags: Vec<String>,
+    pub performers: Vec<String>,
+    pub creators: Vec<ManifestCreator>,
+    pub file_name: String,
+    pub size: u64,
+    /// `sha256:<hex>` of the whole FSV file
+    pub checksum: String,
+    /// Hex encoded BitTorrent info hash
+    pub info_hash: String,
+    pub piece_size: u64,
+    pub trackers: Vec<String>,
+    pub entries: Vec<ManifestEntry>,
+    pub created: String,
+    pub tool: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManifestCreator {
+    pub name: String,
+    /// `video`, `script` or `subtitle`
+    pub role: String,
+    pub work: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub size: u64,
+    /// Checksum from the metadata, empty for entries it doesn't list
+    pub checksum: String,
+}
+
+/// Files written by `package_fsv`.
+#[derive(Debug, Clone)]
+pub struct PackageOutput {
+    pub torrent_path: PathBuf,
+    pub manifest_path: PathBuf,
+    pub manifest: ReleaseManifest,
+}
+
+/// A bencoded value, the encoding of torrent files.
+enum Bencode {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Bencode>),
+    /// Keys are kept sorted, as the format requires
+    Dict(BTreeMap<&'static str, Bencode>),
+}
+
+impl Bencode {
+    fn string(value: &str) -> Self {
+        Bencode::Bytes(value.as_bytes().to_vec())
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Bencode::Int(value) => out.extend_from_slice(format!("i{}e", value).as_bytes()),
+            Bencode::Bytes(bytes) => {
+                out.extend_from_slice(format!("{}:", bytes.len()).as_bytes());
+                out.extend_from_slice(bytes);
+            },
+            Bencode::List(items) => {
+                out.push(b'l');
+                items.iter().for_each(|item| item.encode(out));
+                out.push(b'e');
+            },
+            Bencode::Dict(entries) => {
+                out.push(b'd');
+                for (key, value) in entries {
+                    Bencode::string(key).encode(out);
+                    value.encode(out);
+                }
+                out.push(b'e');
+            },
+        }
+    }
+}
+
+/// A power of two piece size keeping a file of `size` bytes under about `TARGET_PIECE_COUNT` pieces.
+pub fn auto_piece_size(size: u64) -> u64 {
+    let mut piece_size = MIN_PIECE_SIZE;
+    while piece_size < MAX_AUTO_PIECE_SIZE && size.div_ceil(piece_size) > TARGET_PIECE_COUNT {
+        piece_size *= 2;
+    }
+
+    piece_size
+}
+
+/// Write `<stem>.torrent` (single file, BitTorrent v1) and `<stem>.release.json` for the FSV at `path`.
+pub fn package_fsv(path: &Path, options: &PackageOptions) -> Result<PackageOutput, PackageError> {
+    let size = std::fs::metadata(path)?.len();
+    let piece_size = match options.piece_size {
+        Some(piece_size) if piece_size < MIN_PIECE_SIZE || !piece_size.is_power_of_two() => return Err(PackageError::InvalidPieceSize(piece_size)),
+        Some(piece_size) => piece_size,
+        None => auto_piece_size(size),
+    };
+
+    let mut container = FsvContainer::from_reader(File::open(path)?).map_err(FsvError::from)?;
+    let metadata = container.metadata()?;
+    let sizes = container.sizes()?;
+
+    // Piece hashes and the whole file checksum in one pass
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut file_hasher = Sha256::new();
+    let mut pieces = Vec::new();
+    let mut piece = vec![0; piece_size as usize];
+    loop {
+        let read = read_full(&mut reader, &mut piece)?;
+        if read == 0 {
+            break;
+        }
+
+        file_hasher.update(&piece[..read]);
+        pieces.extend_from_slice(&Sha1::digest(&piece[..read]));
+        if read < piece.len() {
+            break;
+        }
+    }
+
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let mut info = BTreeMap::new();
+    info.insert("length", Bencode::Int(size as i64));
+    info.insert("name", Bencode::string(&file_name));
+    info.insert("piece length", Bencode::Int(piece_size as i64));
+    info.insert("pieces", Bencode::Bytes(pieces));
+    if options.private {
+        info.insert("private", Bencode::Int(1));
+    }
+    let info = Bencode::Dict(info);
+    let mut info_bytes = Vec::new();
+    info.encode(&mut info_bytes);
+    let info_hash = format!("{:x}", Sha1::digest(&info_bytes));
+
+    let now = SystemTime::now();
+    let mut torrent = BTreeMap::new();
+    if let Some(tracker) = options.trackers.first() {
+        torrent.insert("announce", Bencode::string(tracker));
+    }
+    if options.trackers.len() > 1 {
+        torrent.insert("announce-list", Bencode::List(options.trackers.iter().map(|tracker| Bencode::List(vec![Bencode::string(tracker)])).collect()));
+    }
+    if !metadata.title.trim().is_empty() {
+        torrent.insert("comment", Bencode::string(&metadata.title));
+    }
+    torrent.insert("created by", Bencode::string(&tool_version()));
+    torrent.insert("creation date", Bencode::Int(now.duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs() as i64)));
+    torrent.insert("info", info);
+    let mut torrent_bytes = Vec::new();
+    Bencode::Dict(torrent).encode(&mut torrent_bytes);
+
+    let checksums: Vec<(&str, &str)> = metadata.video_formats.iter().map(|format| (format.name.as_str(), format.checksum.as_str()))
+        .chain(metadata.script_variants.iter().map(|variant| (variant.name.as_str(), variant.checksum.as_str())))
+        .chain(metadata.subtitle_tracks.iter().map(|track| (track.name.as_str(), track.checksum.as_str())))
+        .collect();
+    let entries = sizes.entries.iter().map(|entry| ManifestEntry {
+        name: entry.name.clone(),
+        size: entry.uncompressed_size,
+        checksum: checksums.iter().find(|(name, _)| *name == entry.name).map(|(_, checksum)| checksum.to_string()).unwrap_or_default(),
+    }).collect();
+    let roles = [("video", &metadata.creators.videos), ("script", &metadata.creators.scripts), ("subtitle", &metadata.creators.subtitles)];
+    let creators = roles.iter()
+        .flat_map(|(role, works)| works.iter().map(move |work| ManifestCreator { name: work.creator_info.name.clone(), role: role.to_string(), work: work.work_name.clone() }))
+        .collect();
+
+    let manifest = ReleaseManifest {
+        title: metadata.title.clone(),
+        tags: metadata.tags.clone(),
+        performers: metadata.performers.clone(),
+        creators,
+        file_name,
+        size,
+        checksum: format!("sha256:{:x}", file_hasher.finalize()),
+        info_hash,
+        piece_size,
+        trackers: options.trackers.clone(),
+        entries,
+        created: utc_timestamp(now),
+        tool: tool_version(),
+    };
+
+    let output_dir = match &options.output_dir {
+        Some(dir) => dir.clone(),
+        None => path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+    };
+    std::fs::create_dir_all(&output_dir)?;
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_else(|| "release".to_string());
+    let torrent_path = output_dir.join(format!("{}.torrent", stem));
+    let manifest_path = output_dir.join(format!("{}.release.json", stem));
+    std::fs::write(&torrent_path, torrent_bytes)?;
+    std::fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+
+    Ok(PackageOutput { torrent_path, manifest_path, manifest })
+}
+
+/// Fill `buf` as far as the reader allows. Returns the number of bytes read, less than `buf.len()` only at the end.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsv::FsvBuilder;
+
+    #[test]
+    fn test_package_fsv() {
+        let dir = std::env::temp_dir().join(format!("fsv-package-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fsv_path = dir.join("scene.fsv");
+        let script = br#"{"actions":[{"at":0,"pos":0},{"at":1000,"pos":100}],"inverted":false,"range":100,"version":"1.0"}"#;
+        std::fs::write(&fsv_path, FsvBuilder::new("Scene").tags(vec!["pov".to_string()]).script("scene.funscript", script, 1000).to_bytes().unwrap()).unwrap();
+
+        assert!(matches!(package_fsv(&fsv_path, &PackageOptions { piece_size: Some(1000), ..Default::default() }), Err(PackageError::InvalidPieceSize(1000))));
+
+        let options = PackageOptions { trackers: vec!["udp://a.example:80".to_string(), "udp://b.example:80".to_string()], private: true, ..Default::default() };
+        let output = package_fsv(&fsv_path, &options).unwrap();
+        let torrent = std::fs::read(&output.torrent_path).unwrap();
+        assert!(torrent.starts_with(b"d8:announce18:udp://a.example:8013:announce-listll18:udp://a.example:80el18:udp://b.example:80ee"));
+        assert!(torrent.windows(10).any(|window| window == b"7:privatei"));
+        assert_eq!(output.manifest.piece_size, MIN_PIECE_SIZE);
+        assert_eq!(output.manifest.info_hash.len(), 40);
+        assert_eq!(output.manifest.tags, ["pov"]);
+        let script_entry = output.manifest.entries.iter().find(|entry| entry.name == "scene.funscript").unwrap();
+        assert_eq!(script_entry.size, script.len() as u64);
+        assert!(!script_entry.checksum.is_empty());
+        assert!(output.manifest_path.is_file());
+
+        assert_eq!(auto_piece_size(10 * 1024 * 1024 * 1024), 8 * 1024 * 1024);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use thiserror::Error;
+
+use crate::fsv;
+
+/// Longest path Windows opens without the `\\?\` prefix: `MAX_PATH` less the terminating null, in UTF-16 code units.
+pub const MAX_PATH_LENGTH: usize = 259;
+/// Longest file or directory name most Windows file systems store, in UTF-16 code units.
+pub const MAX_COMPONENT_LENGTH: usize = 255;
+/// Longest name a sanitized extraction directory gets, so long titles leave room for the file names in it.
+pub const MAX_DIR_NAME_LENGTH: usize = 100;
+
+/// Device names Windows reserves in every directory, also with an extension (`con.txt` is as reserved as `CON`).
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// What extraction does with names Windows can't store. Applies on every platform, so extracted folders can be copied anywhere.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum PathSafety {
+    /// Replace invalid characters, trim trailing dots and spaces, suffix reserved device names with `_` and shorten names so paths fit
+    #[default]
+    Sanitize,
+    /// Refuse to extract instead of writing a name other than the one in the archive
+    Fail,
+}
+
+#[derive(Debug, Error)]
+pub enum PathSafetyError {
+    #[error("'{0}' is not a valid Windows file name")]
+    InvalidName(String),
+    #[error("'{0}' is a reserved Windows device name")]
+    ReservedName(String),
+    #[error("Path '{}' is longer than Windows allows ({} characters)", .0.display(), MAX_PATH_LENGTH)]
+    TooLong(PathBuf),
+}
+
+/// Whether Windows reserves `name` for a device, whatever its extension.
+pub fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+fn windows_length(name: &str) -> usize {
+    name.encode_utf16().count()
+}
+
+/// The longest start of `name` that is at most `max_length` UTF-16 code units long.
+fn truncate(name: &str, max_length: usize) -> &str {
+    let mut length = 0;
+    for (i, c) in name.char_indices() {
+        length += c.len_utf16();
+        if length > max_length {
+            return &name[..i];
+        }
+    }
+
+    name
+}
+
+/// `name` as a single path component Windows can store, `fallback` if nothing of it is left.
+/// Directory separators are replaced under either strategy, as extracted files never go into subdirectories.
+pub fn safe_component(name: &str, fallback: &str, safety: PathSafety) -> Result<String, PathSafetyError> {
+    let flattened = name.trim().replace(['/', '\\'], "_");
+    let sanitized = fsv::sanitize_path_component(&flattened);
+    match safety {
+        PathSafety::Fail if sanitized.as_deref() != Some(flattened.as_str()) => Err(PathSafetyError::InvalidName(name.to_string())),
+        PathSafety::Fail if is_reserved_name(&flattened) => Err(PathSafetyError::ReservedName(name.to_string())),
+        PathSafety::Fail => Ok(flattened),
+        PathSafety::Sanitize => {
+            let mut sanitized = sanitized.unwrap_or_else(|| fallback.to_string());
+            if is_reserved_name(&sanitized) {
+                let stem_end = sanitized.find('.').unwrap_or(sanitized.len());
+                sanitized.insert(stem_end, '_');
+            }
+
+            Ok(sanitized)
+        },
+    }
+}
+
+/// `stem`, shortened if need be so every name made of it and one of `suffixes` fits in `dir` on Windows.
+/// Paths are measured absolute, as Windows resolves them. Fails if nothing of `stem` would be left, or under `PathSafety::Fail`
+/// if it would have to be shortened at all.
+pub fn fit_stem(dir: &Path, stem: &str, suffixes: &[&str], safety: PathSafety) -> Result<String, PathSafetyError> {
+    let absolute_dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let dir_length = windows_length(&absolute_dir.to_string_lossy()) + 1;
+    let longest_suffix = suffixes.iter().copied().max_by_key(|suffix| windows_length(suffix)).unwrap_or("");
+    let budget = MAX_PATH_LENGTH.saturating_sub(dir_length).min(MAX_COMPONENT_LENGTH).saturating_sub(windows_length(longest_suffix));
+    if windows_length(stem) <= budget {
+        return Ok(stem.to_string());
+    }
+
+    let too_long = || PathSafetyError::TooLong(absolute_dir.join(format!("{}{}", stem, longest_suffix)));
+    if safety == PathSafety::Fail {
+        return Err(too_long());
+    }
+
+    match truncate(stem, budget).trim_end_matches(['.', ' ']) {
+        "" => Err(too_long()),
+        shortened => Ok(shortened.to_string()),
+    }
+}
+
+/// A name for the directory `name` is extracted into in `output_dir`, `fallback` if nothing of it is left.
+/// Sanitized names are kept to `MAX_DIR_NAME_LENGTH`, so the files in it can keep theirs.
+pub fn safe_dir_name(output_dir: &Path, name: &str, fallback: &str, safety: PathSafety) -> Result<String, PathSafetyError> {
+    let name = safe_component(name, fallback, safety)?;
+    let name = match safety {
+        PathSafety::Sanitize => truncate(&name, MAX_DIR_NAME_LENGTH).trim_end_matches(['.', ' ']).to_string(),
+        PathSafety::Fail => name,
+    };
+
+    fit_stem(output_dir, &name, &[""], safety)
+}
+
+/// A name for the file `name` is extracted as in `dir`, `fallback` if nothing of it is left. Shortening keeps the extension.
+pub fn safe_file_name(dir: &Path, name: &str, fallback: &str, safety: PathSafety) -> Result<String, PathSafetyError> {
+    let name = safe_component(name, fallback, safety)?;
+    let (stem, extension) = name.split_at(name.rfind('.').unwrap_or(name.len()));
+    Ok(format!("{}{}", fit_stem(dir, stem, &[extension], safety)?, extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_safety() {
+        let dir = Path::new("out");
+        assert_eq!(safe_file_name(dir, "con.txt", "file", PathSafety::Sanitize).unwrap(), "con_.txt");
+        assert_eq!(safe_file_name(dir, "LPT1", "file", PathSafety::Sanitize).unwrap(), "LPT1_");
+        assert_eq!(safe_file_name(dir, "console.mp4", "file", PathSafety::Sanitize).unwrap(), "console.mp4");
+        assert_eq!(safe_file_name(dir, "scene. ", "file", PathSafety::Sanitize).unwrap(), "scene");
+        assert_eq!(safe_file_name(dir, "...", "file", PathSafety::Sanitize).unwrap(), "file");
+        assert!(matches!(safe_file_name(dir, "Aux.srt", "file", PathSafety::Fail), Err(PathSafetyError::ReservedName(_))));
+        assert!(matches!(safe_file_name(dir, "scene.", "file", PathSafety::Fail), Err(PathSafetyError::InvalidName(_))));
+        assert!(matches!(safe_file_name(dir, "a:b.mp4", "file", PathSafety::Fail), Err(PathSafetyError::InvalidName(_))));
+        assert_eq!(safe_file_name(dir, "scripts/scene.funscript", "file", PathSafety::Fail).unwrap(), "scripts_scene.funscript");
+
+        // Shortened to fit, keeping the extension
+        let long = format!("{}. {}", "a".repeat(100), "b".repeat(300));
+        let name = safe_file_name(dir, &format!("{}.mp4", long), "file", PathSafety::Sanitize).unwrap();
+        assert!(name.starts_with(&"a".repeat(100)) && name.ends_with("b.mp4"));
+        let path = std::path::absolute(dir.join(&name)).unwrap();
+        assert!(windows_length(&path.to_string_lossy()) <= MAX_PATH_LENGTH && windows_length(&name) <= MAX_COMPONENT_LENGTH);
+        assert!(matches!(safe_file_name(dir, &format!("{}.mp4", long), "file", PathSafety::Fail), Err(PathSafetyError::TooLong(_))));
+
+        // Nothing of the stem fits
+        assert!(matches!(fit_stem(dir, "scene", &[&".x".repeat(200)], PathSafety::Sanitize), Err(PathSafetyError::TooLong(_))));
+
+        let title = "夏".repeat(300);
+        assert_eq!(safe_dir_name(dir, &title, "scene", PathSafety::Sanitize).unwrap(), "夏".repeat(MAX_DIR_NAME_LENGTH));
+        assert!(matches!(safe_dir_name(dir, &title, "scene", PathSafety::Fail), Err(PathSafetyError::TooLong(_))));
+        assert_eq!(safe_dir_name(dir, "CON", "scene", PathSafety::Sanitize).unwrap(), "CON_");
+    }
+}
@@ -0,0 +1,713 @@
+use std::{io::Write, path::{Path, PathBuf}, process::{Child, Command, Stdio}, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use thiserror::Error;
+use tokio::{io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader}, net::TcpStream};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite::Message};
+use tracing::{debug, info, warn};
+
+use crate::{playback::{self, PlaybackError}, tcode::{self, AxisMove, AxisScheduler, TCodeAxis}, transcode::TranscodeWorkDir};
+
+pub const DEFAULT_SERVER_URL: &str = "ws://127.0.0.1:12345";
+/// Baud rate used by OSR firmware
+pub const DEFAULT_BAUD_RATE: u32 = 115_200;
+const CLIENT_NAME: &str = "FunscriptVideo";
+/// Buttplug message spec version this client speaks
+const MESSAGE_VERSION: u32 = 3;
+const SCAN_TIMEOUT: Duration = Duration::from_secs(10);
+/// Upper bound on how long playback sleeps between clock checks, so seeks and pauses are picked up quickly
+const TICK: Duration = Duration::from_millis(50);
+/// How long to wait for a freshly launched mpv to open its IPC socket
+const MPV_LAUNCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[cfg(unix)]
+type MpvStream = tokio::net::UnixStream;
+#[cfg(windows)]
+type MpvStream = tokio::net::windows::named_pipe::NamedPipeClient;
+
+#[derive(Debug, Error)]
+pub enum PlayError {
+    #[error("Playback error: {0}")]
+    Playback(#[from] PlaybackError),
+    #[error("WebSocket error: {0}")]
+    WebSocket(#[from] tokio_tungstenite::tungstenite::Error),
+    #[error("Serde JSON error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("Buttplug server error: {0}")]
+    Server(String),
+    #[error("Unexpected Buttplug message: {0}")]
+    Protocol(String),
+    #[error("No device with linear or vibration support found")]
+    NoDevice,
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serial port error: {0}")]
+    Serial(#[from] serialport::Error),
+    #[error("mpv error: {0}")]
+    Mpv(String),
+}
+
+/// Where the current video time comes from.
+#[derive(Debug, Clone)]
+pub enum TimecodeSource {
+    /// Start playback immediately, at this video time (ms)
+    Manual { start_at: u64 },
+    /// A WebSocket that sends the player's position, either as a bare number of milliseconds
+    /// or as `{ "position_ms": <u64>, "playing": <bool> }`
+    WebSocket(String),
+    /// Follow mpv through its JSON IPC socket, attaching to a running instance or launching one.
+    /// mpv is given `media` (a path or URL) if set, otherwise the video format being played and the subtitles extracted from the FSV.
+    Mpv { socket: PathBuf, media: Option<String> },
+}
+
+/// Where script positions are sent.
+#[derive(Debug, Clone)]
+pub enum PlayOutput {
+    /// A device connected to an Intiface/Buttplug server
+    Buttplug { server_url: String },
+    /// An OSR-style stroker driven with TCode over a serial port
+    Serial { port: String, baud_rate: u32 },
+}
+
+#[derive(Debug)]
+pub struct PlayArgs {
+    pub path: PathBuf,
+    pub script: Option<String>,
+    /// Video format the script is timed against (its pair offset applies) and that mpv opens, the first one listed if `None`
+    pub video: Option<String>,
+    pub output: PlayOutput,
+    pub timecode: TimecodeSource,
+}
+
+/// Where playback stopped, for resume tracking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaySummary {
+    /// Video time (ms) playback stopped at
+    pub position_ms: u64,
+    /// No script actions were left after `position_ms`
+    pub finished: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeviceKind {
+    Linear,
+    Vibrate,
+}
+
+#[derive(Debug, Clone)]
+struct Device {
+    index: u64,
+    name: String,
+    kind: DeviceKind,
+}
+
+impl Device {
+    /// Strokers get linear moves; vibrators get the target position as intensity.
+    fn from_message(device: &Value) -> Option<Self> {
+        let index = device.get("DeviceIndex")?.as_u64()?;
+        let name = device.get("DeviceName").and_then(Value::as_str).unwrap_or("unknown device").to_string();
+        let messages = device.get("DeviceMessages")?;
+        let vibrates = messages.get("ScalarCmd").and_then(Value::as_array)
+            .is_some_and(|actuators| actuators.iter().any(|a| a.get("ActuatorType").and_then(Value::as_str) == Some("Vibrate")));
+        let kind = match (messages.get("LinearCmd").is_some(), vibrates) {
+            (true, _) => DeviceKind::Linear,
+            (false, true) => DeviceKind::Vibrate,
+            (false, false) => return None,
+        };
+
+        Some(Device { index, name, kind })
+    }
+}
+
+/// Minimal Buttplug (Intiface) client over its JSON WebSocket protocol.
+struct ButtplugClient {
+    socket: Socket,
+    next_id: u64,
+    ping_interval: Option<Duration>,
+    devices: Vec<Device>,
+}
+
+impl ButtplugClient {
+    async fn connect(url: &str) -> Result<Self, PlayError> {
+        let (socket, _) = tokio_tungstenite::connect_async(url).await?;
+        let mut client = ButtplugClient { socket, next_id: 1, ping_interval: None, devices: Vec::new() };
+        let server_info = client.request("RequestServerInfo", json!({ "ClientName": CLIENT_NAME, "MessageVersion": MESSAGE_VERSION })).await?;
+        let max_ping = server_info.get("ServerInfo").and_then(|info| info.get("MaxPingTime")).and_then(Value::as_u64).unwrap_or(0);
+        if max_ping > 0 {
+            client.ping_interval = Some(Duration::from_millis(max_ping / 2));
+        }
+
+        Ok(client)
+    }
+
+    /// Send a message and wait for the reply carrying the same Id. Device events arriving meanwhile are recorded.
+    async fn request(&mut self, kind: &str, mut fields: Value) -> Result<Value, PlayError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        fields["Id"] = json!(id);
+        self.socket.send(Message::text(json!([{ kind: fields }]).to_string())).await?;
+
+        loop {
+            for message in self.receive().await? {
+                if message_id(&message) == Some(id) {
+                    if let Some(error) = message.get("Error") {
+                        let text = error.get("ErrorMessage").and_then(Value::as_str).unwrap_or("unknown error");
+                        return Err(PlayError::Server(text.to_string()));
+                    }
+
+                    return Ok(message);
+                }
+            }
+        }
+    }
+
+    /// Read one batch of server messages, keeping track of added devices.
+    async fn receive(&mut self) -> Result<Vec<Value>, PlayError> {
+        loop {
+            let message = match self.socket.next().await {
+                Some(message) => message?,
+                None => return Err(PlayError::Protocol("connection closed".to_string())),
+            };
+
+            let Message::Text(text) = message else {
+                continue;
+            };
+
+            let messages = match serde_json::from_str::<Value>(&text)? {
+                Value::Array(messages) => messages,
+                other => return Err(PlayError::Protocol(other.to_string())),
+            };
+
+            for message in &messages {
+                if let Some(device) = message.get("DeviceAdded").and_then(Device::from_message) {
+                    debug!(device = %device.name, "Device added");
+                    self.devices.push(device);
+                }
+            }
+
+            return Ok(messages);
+        }
+    }
+
+    async fn find_device(&mut self) -> Result<Device, PlayError> {
+        let list = self.request("RequestDeviceList", json!({})).await?;
+        let listed = list.get("DeviceList").and_then(|l| l.get("Devices")).and_then(Value::as_array).cloned().unwrap_or_default();
+        self.devices.extend(listed.iter().filter_map(Device::from_message));
+        if let Some(device) = self.best_device() {
+            return Ok(device);
+        }
+
+        info!(operation = "play", outcome = "scanning", "Scanning for devices");
+        self.request("StartScanning", json!({})).await?;
+        let deadline = tokio::time::Instant::now() + SCAN_TIMEOUT;
+        while self.best_device().is_none() {
+            match tokio::time::timeout_at(deadline, self.receive()).await {
+                Ok(result) => { result?; },
+                Err(_) => break,
+            }
+        }
+
+        self.request("StopScanning", json!({})).await?;
+        self.best_device().ok_or(PlayError::NoDevice)
+    }
+
+    fn best_device(&self) -> Option<Device> {
+        self.devices.iter().find(|d| d.kind == DeviceKind::Linear).or_else(|| self.devices.first()).cloned()
+    }
+
+    async fn move_to(&mut self, device: &Device, duration_ms: u64, position: f64) -> Result<(), PlayError> {
+        match device.kind {
+            DeviceKind::Linear => {
+                let vectors = json!([{ "Index": 0, "Duration": duration_ms, "Position": position }]);
+                self.request("LinearCmd", json!({ "DeviceIndex": device.index, "Vectors": vectors })).await?;
+            },
+            DeviceKind::Vibrate => {
+                let scalars = json!([{ "Index": 0, "Scalar": position, "ActuatorType": "Vibrate" }]);
+                self.request("ScalarCmd", json!({ "DeviceIndex": device.index, "Scalars": scalars })).await?;
+            },
+        }
+
+        Ok(())
+    }
+
+    async fn stop(&mut self, device: &Device) -> Result<(), PlayError> {
+        self.request("StopDeviceCmd", json!({ "DeviceIndex": device.index })).await?;
+        Ok(())
+    }
+
+    async fn ping(&mut self) -> Result<(), PlayError> {
+        self.request("Ping", json!({})).await?;
+        Ok(())
+    }
+}
+
+fn message_id(message: &Value) -> Option<u64> {
+    message.as_object()?.values().next()?.get("Id")?.as_u64()
+}
+
+enum Output {
+    Buttplug { client: Box<ButtplugClient>, device: Device },
+    Serial(Box<dyn serialport::SerialPort>),
+}
+
+impl Output {
+    async fn open(output: &PlayOutput) -> Result<Self, PlayError> {
+        match output {
+            PlayOutput::Buttplug { server_url } => {
+                let mut client = ButtplugClient::connect(server_url).await?;
+                let device = client.find_device().await?;
+                info!(operation = "play", item = %device.name, outcome = "connected", "Using device");
+                Ok(Output::Buttplug { client: Box::new(client), device })
+            },
+            PlayOutput::Serial { port, baud_rate } => {
+                let port = serialport::new(port, *baud_rate).timeout(Duration::from_secs(1)).open()?;
+                info!(operation = "play", item = port.name().unwrap_or_default(), outcome = "connected", "Using serial port");
+                Ok(Output::Serial(port))
+            },
+        }
+    }
+
+    /// Buttplug devices only follow the stroke axis; serial devices get every axis in one TCode line.
+    async fn send(&mut self, moves: &[AxisMove]) -> Result<(), PlayError> {
+        match self {
+            Output::Buttplug { client, device } => match moves.iter().find(|m| m.axis == TCodeAxis::Stroke) {
+                Some(stroke) => client.move_to(device, stroke.duration_ms, stroke.position).await,
+                None => Ok(()),
+            },
+            Output::Serial(port) => Ok(port.write_all(tcode::format_command(moves).as_bytes())?),
+        }
+    }
+
+    async fn stop(&mut self) -> Result<(), PlayError> {
+        match self {
+            Output::Buttplug { client, device } => client.stop(device).await,
+            Output::Serial(port) => Ok(port.write_all(tcode::STOP_COMMAND.as_bytes())?),
+        }
+    }
+
+    fn ping_interval(&self) -> Option<Duration> {
+        match self {
+            Output::Buttplug { client, .. } => client.ping_interval,
+            Output::Serial(_) => None,
+        }
+    }
+
+    async fn ping(&mut self) -> Result<(), PlayError> {
+        match self {
+            Output::Buttplug { client, .. } => client.ping().await,
+            Output::Serial(_) => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ClockState {
+    position: u64,
+    updated: Instant,
+    playing: bool,
+    closed: bool,
+}
+
+/// The current video time, advanced locally between timecode updates.
+#[derive(Debug, Clone)]
+struct PlaybackClock {
+    state: Arc<Mutex<ClockState>>,
+    external: bool,
+}
+
+impl PlaybackClock {
+    fn manual(start_at: u64) -> Self {
+        let state = ClockState { position: start_at, updated: Instant::now(), playing: true, closed: false };
+        PlaybackClock { state: Arc::new(Mutex::new(state)), external: false }
+    }
+
+    async fn websocket(url: &str) -> Result<Self, PlayError> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await?;
+        let state = ClockState { position: 0, updated: Instant::now(), playing: false, closed: false };
+        let clock = PlaybackClock { state: Arc::new(Mutex::new(state)), external: true };
+        let shared = clock.state.clone();
+        tokio::spawn(async move {
+            while let Some(Ok(message)) = socket.next().await {
+                let Message::Text(text) = message else {
+                    continue;
+                };
+
+                match parse_timecode(&text) {
+                    Some((position, playing)) => {
+                        let mut state = shared.lock().unwrap();
+                        state.position = position;
+                        state.playing = playing;
+                        state.updated = Instant::now();
+                    },
+                    None => warn!(operation = "play", item = text.as_str(), outcome = "skipped", "Ignoring unrecognized timecode message"),
+                }
+            }
+
+            shared.lock().unwrap().closed = true;
+        });
+
+        Ok(clock)
+    }
+
+    /// Current video time, or `None` while paused.
+    fn now(&self) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        state.playing.then(|| state.position + state.updated.elapsed().as_millis() as u64)
+    }
+
+    /// Current video time, paused or not.
+    fn position(&self) -> u64 {
+        let state = self.state.lock().unwrap();
+        match state.playing {
+            true => state.position + state.updated.elapsed().as_millis() as u64,
+            false => state.position,
+        }
+    }
+
+    /// Follow mpv over an IPC connection: load `media` (plus `subtitles` once it is open) and track its position, pauses and seeks.
+    fn mpv<S: AsyncRead + AsyncWrite + Send + 'static>(stream: S, media: String, subtitles: Vec<String>) -> Self {
+        let state = ClockState { position: 0, updated: Instant::now(), playing: false, closed: false };
+        let clock = PlaybackClock { state: Arc::new(Mutex::new(state)), external: true };
+        let shared = clock.state.clone();
+        tokio::spawn(async move {
+            let (reader, mut writer) = tokio::io::split(stream);
+            let mut lines = BufReader::new(reader).lines();
+            let commands = [json!(["observe_property", 1, "time-pos"]), json!(["observe_property", 2, "pause"]), json!(["loadfile", media])];
+            for command in commands {
+                if let Err(err) = send_mpv_command(&mut writer, command).await {
+                    warn!(operation = "play", outcome = "failed", error = %err, "Unable to send command to mpv");
+                }
+            }
+
+            let mut status = MpvStatus::default();
+            let mut loaded = false;
+            while let Ok(Some(line)) = lines.next_line().await {
+                let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                    warn!(operation = "play", item = %line, outcome = "skipped", "Ignoring unrecognized mpv message");
+                    continue;
+                };
+
+                if let Some(error) = message.get("error").and_then(Value::as_str) && error != "success" {
+                    warn!(operation = "play", outcome = "failed", error = %error, "mpv command failed");
+                }
+
+                let before = (status.time_pos, status.playing());
+                match status.handle(&message) {
+                    MpvEvent::FileLoaded => {
+                        loaded = true;
+                        for subtitle in &subtitles {
+                            if let Err(err) = send_mpv_command(&mut writer, json!(["sub-add", subtitle, "auto"])).await {
+                                warn!(operation = "play", item = %subtitle, outcome = "failed", error = %err, "Unable to add subtitle");
+                            }
+                        }
+                    },
+                    // Loading our file ends whatever an attached mpv was playing before
+                    MpvEvent::Ended if loaded => break,
+                    _ => {},
+                }
+
+                if (status.time_pos, status.playing()) != before {
+                    let mut state = shared.lock().unwrap();
+                    state.position = status.time_pos.unwrap_or(0);
+                    state.playing = status.playing();
+                    state.updated = Instant::now();
+                }
+            }
+
+            shared.lock().unwrap().closed = true;
+        });
+
+        clock
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+}
+
+async fn send_mpv_command(writer: &mut (impl AsyncWrite + Unpin), command: Value) -> std::io::Result<()> {
+    let mut line = json!({ "command": command }).to_string();
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum MpvEvent {
+    FileLoaded,
+    Ended,
+    Other,
+}
+
+/// mpv's playback state, as reported by the events on its JSON IPC.
+#[derive(Debug, Default)]
+struct MpvStatus {
+    /// Video time in ms, `None` while no file is playing
+    time_pos: Option<u64>,
+    paused: bool,
+    /// Between a seek and the playback restarting; the device is held still meanwhile
+    seeking: bool,
+}
+
+impl MpvStatus {
+    fn handle(&mut self, message: &Value) -> MpvEvent {
+        let data = message.get("data");
+        match message.get("event").and_then(Value::as_str) {
+            Some("property-change") => match message.get("name").and_then(Value::as_str) {
+                Some("time-pos") => self.time_pos = data.and_then(Value::as_f64).map(|seconds| (seconds.max(0.0) * 1000.0).round() as u64),
+                Some("pause") => self.paused = data.and_then(Value::as_bool).unwrap_or(false),
+                _ => {},
+            },
+            Some("seek") => self.seeking = true,
+            Some("playback-restart") => self.seeking = false,
+            Some("file-loaded") => return MpvEvent::FileLoaded,
+            Some("end-file") | Some("shutdown") => return MpvEvent::Ended,
+            _ => {},
+        }
+
+        MpvEvent::Other
+    }
+
+    fn playing(&self) -> bool {
+        self.time_pos.is_some() && !self.paused && !self.seeking
+    }
+}
+
+#[cfg(unix)]
+async fn connect_mpv(socket: &Path) -> std::io::Result<MpvStream> {
+    tokio::net::UnixStream::connect(socket).await
+}
+
+#[cfg(windows)]
+async fn connect_mpv(socket: &Path) -> std::io::Result<MpvStream> {
+    tokio::net::windows::named_pipe::ClientOptions::new().open(socket)
+}
+
+/// An mpv instance started for playback, and the extracted media it plays. The process is stopped when dropped.
+struct MpvSession {
+    process: Option<Child>,
+    _work_dir: Option<TranscodeWorkDir>,
+}
+
+impl Drop for MpvSession {
+    fn drop(&mut self) {
+        if let Some(process) = &mut self.process {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+}
+
+async fn attach_or_launch_mpv(socket: &Path) -> Result<(MpvStream, Option<Child>), PlayError> {
+    if let Ok(stream) = connect_mpv(socket).await {
+        info!(operation = "play", item = %socket.display(), outcome = "attached", "Attached to mpv");
+        return Ok((stream, None));
+    }
+
+    info!(operation = "play", item = %socket.display(), outcome = "launching", "Launching mpv");
+    let process = Command::new("mpv")
+        .args(["--idle=once", "--force-window"])
+        .arg(format!("--input-ipc-server={}", socket.display()))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|err| PlayError::Mpv(format!("unable to launch mpv: {}", err)))?;
+
+    let deadline = Instant::now() + MPV_LAUNCH_TIMEOUT;
+    loop {
+        match connect_mpv(socket).await {
+            Ok(stream) => return Ok((stream, Some(process))),
+            Err(err) if Instant::now() >= deadline => return Err(PlayError::Mpv(format!("unable to connect to '{}': {}", socket.display(), err))),
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+}
+
+async fn mpv_clock(path: &Path, socket: &Path, video: Option<&str>, media: Option<&str>) -> Result<(PlaybackClock, MpvSession), PlayError> {
+    let mut work_dir = None;
+    let (media, subtitles) = match media {
+        Some(media) => (media.to_string(), Vec::new()),
+        None => {
+            let dir = TranscodeWorkDir::new()?;
+            info!(operation = "play", archive = %path.display(), outcome = "extracting", "Extracting video for mpv");
+            let extracted = playback::extract_media(path, video, dir.path())?;
+            work_dir = Some(dir);
+            let subtitles = extracted.subtitles.iter().map(|path| path.to_string_lossy().to_string()).collect();
+            (extracted.video.to_string_lossy().to_string(), subtitles)
+        },
+    };
+
+    let (stream, process) = attach_or_launch_mpv(socket).await?;
+    Ok((PlaybackClock::mpv(stream, media, subtitles), MpvSession { process, _work_dir: work_dir }))
+}
+
+/// `1234` (ms, playing) or `{ "position_ms": 1234, "playing": true }`
+fn parse_timecode(text: &str) -> Option<(u64, bool)> {
+    match serde_json::from_str::<Value>(text).ok()? {
+        Value::Number(number) => Some((number.as_u64()?, true)),
+        Value::Object(object) => {
+            let position = object.get("position_ms")?.as_u64()?;
+            let playing = object.get("playing").and_then(Value::as_bool).unwrap_or(true);
+            Some((position, playing))
+        },
+        _ => None,
+    }
+}
+
+/// Stream a script variant of an FSV to the first suitable Buttplug device or a serial stroker, following the given timecode source.
+/// Serial devices also get the variant's axis scripts (see `tcode::load_axis_timelines`).
+/// Runs until the script ends (manual start), the timecode connection or mpv closes, or Ctrl-C is pressed.
+pub async fn play(args: PlayArgs) -> Result<PlaySummary, PlayError> {
+    let axes = match args.output {
+        PlayOutput::Buttplug { .. } => vec![(TCodeAxis::Stroke, playback::load_timeline(&args.path, args.script.as_deref(), args.video.as_deref())?)],
+        PlayOutput::Serial { .. } => tcode::load_axis_timelines(&args.path, args.script.as_deref(), args.video.as_deref())?,
+    };
+    for (axis, timeline) in &axes {
+        info!(operation = "play", archive = %args.path.display(), item = %timeline.name, axis = %axis, actions = timeline.actions().len(), outcome = "loaded", "Loaded script");
+    }
+
+    let mut scheduler = AxisScheduler::new(axes);
+    let mut output = Output::open(&args.output).await?;
+
+    let mut _mpv_session = None;
+    let clock = match &args.timecode {
+        TimecodeSource::Manual { start_at } => PlaybackClock::manual(*start_at),
+        TimecodeSource::WebSocket(url) => PlaybackClock::websocket(url).await?,
+        TimecodeSource::Mpv { socket, media } => {
+            let (clock, session) = mpv_clock(&args.path, socket, args.video.as_deref(), media.as_deref()).await?;
+            _mpv_session = Some(session);
+            clock
+        },
+    };
+
+    let result = tokio::select! {
+        result = run(&mut output, &mut scheduler, &clock) => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    };
+
+    output.stop().await?;
+    result?;
+
+    let position_ms = clock.position();
+    Ok(PlaySummary { position_ms, finished: scheduler.time_to_next(position_ms).is_none() })
+}
+
+async fn run(output: &mut Output, scheduler: &mut AxisScheduler, clock: &PlaybackClock) -> Result<(), PlayError> {
+    let mut last_ping = Instant::now();
+    loop {
+        if clock.is_closed() {
+            info!(operation = "play", outcome = "stopped", "Timecode source closed, stopping playback");
+            return Ok(());
+        }
+
+        if let Some(interval) = output.ping_interval() && last_ping.elapsed() >= interval {
+            output.ping().await?;
+            last_ping = Instant::now();
+        }
+
+        let Some(now) = clock.now() else {
+            if scheduler.reset() {
+                output.stop().await?;
+            }
+
+            tokio::time::sleep(TICK).await;
+            continue;
+        };
+
+        let Some(remaining) = scheduler.time_to_next(now) else {
+            if !clock.external {
+                info!(operation = "play", outcome = "completed", "Script finished");
+                return Ok(());
+            }
+
+            // The player may still seek back
+            tokio::time::sleep(TICK).await;
+            continue;
+        };
+
+        let moves = scheduler.update(now);
+        if !moves.is_empty() {
+            output.send(&moves).await?;
+        }
+
+        tokio::time::sleep(Duration::from_millis(remaining).min(TICK)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsv::FsvBuilder;
+
+    /// Answers every request with Ok, except the handshake and device list. Returns the number of LinearCmd messages.
+    async fn fake_server(listener: tokio::net::TcpListener) -> usize {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let mut linear_commands = 0;
+        while let Some(Ok(Message::Text(text))) = socket.next().await {
+            let request: Value = serde_json::from_str(&text).unwrap();
+            let (kind, fields) = request[0].as_object().unwrap().iter().next().unwrap();
+            let id = fields["Id"].clone();
+            let reply = match kind.as_str() {
+                "RequestServerInfo" => json!({ "ServerInfo": { "Id": id, "ServerName": "fake", "MessageVersion": 3, "MaxPingTime": 0 } }),
+                "RequestDeviceList" => json!({ "DeviceList": { "Id": id, "Devices": [{ "DeviceName": "Stroker", "DeviceIndex": 0, "DeviceMessages": { "LinearCmd": [{ "ActuatorType": "Position" }] } }] } }),
+                _ => json!({ "Ok": { "Id": id } }),
+            };
+
+            linear_commands += usize::from(kind == "LinearCmd");
+            let done = kind == "StopDeviceCmd";
+            socket.send(Message::text(json!([reply]).to_string())).await.unwrap();
+            if done {
+                break;
+            }
+        }
+
+        linear_commands
+    }
+
+    #[tokio::test]
+    async fn test_play_manual_start() {
+        let script = br#"{ "actions": [{ "at": 0, "pos": 0 }, { "at": 60, "pos": 100 }, { "at": 120, "pos": 0 }], "inverted": false, "range": 100, "version": "1.0" }"#;
+        let bytes = FsvBuilder::new("Test").script("a.funscript", script, 120).to_bytes().unwrap();
+        let path = std::env::temp_dir().join(format!("fsv-play-test-{}.fsv", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(fake_server(listener));
+        let args = PlayArgs { path: path.clone(), script: None, video: None, output: PlayOutput::Buttplug { server_url }, timecode: TimecodeSource::Manual { start_at: 0 } };
+        let result = play(args).await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.unwrap().finished);
+        // The action at 0 is already in the past when playback starts
+        assert_eq!(server.await.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_mpv_status() {
+        let mut status = MpvStatus::default();
+        assert_eq!(status.handle(&json!({ "event": "property-change", "id": 1, "name": "time-pos", "data": 12.3456 })), MpvEvent::Other);
+        assert_eq!(status.time_pos, Some(12_346));
+        assert!(status.playing());
+
+        status.handle(&json!({ "event": "seek" }));
+        assert!(!status.playing());
+        status.handle(&json!({ "event": "playback-restart" }));
+        status.handle(&json!({ "event": "property-change", "id": 2, "name": "pause", "data": true }));
+        assert!(!status.playing());
+
+        status.handle(&json!({ "event": "property-change", "id": 1, "name": "time-pos", "data": null }));
+        assert_eq!(status.time_pos, None);
+        assert_eq!(status.handle(&json!({ "event": "end-file", "reason": "eof" })), MpvEvent::Ended);
+    }
+
+    #[test]
+    fn test_parse_timecode() {
+        assert_eq!(parse_timecode("1500"), Some((1500, true)));
+        assert_eq!(parse_timecode(r#"{ "position_ms": 42, "playing": false }"#), Some((42, false)));
+        assert_eq!(parse_timecode("pause"), None);
+    }
+}
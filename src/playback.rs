@@ -0,0 +1,154 @@
+use std::{fs::File, io::{Read, Seek}, path::{Path, PathBuf}};
+
+use thiserror::Error;
+
+use crate::{fsv::{FsvContainer, FsvError}, funscript::Funscript, offsets};
+
+#[derive(Debug, Error)]
+pub enum PlaybackError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ZIP archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Serde JSON error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("FSV error: {0}")]
+    Fsv(#[from] FsvError),
+    #[error("Script variant '{0}' not found in FSV")]
+    ScriptNotFound(String),
+    #[error("FSV has no script variants")]
+    NoScripts,
+    #[error("Video format '{0}' not found in FSV")]
+    VideoNotFound(String),
+    #[error("FSV contains no video")]
+    NoVideo,
+}
+
+/// A script action placed on the video timeline, with the position normalized to 0.0..=1.0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimelineAction {
+    pub video_time: u64,
+    pub position: f64,
+}
+
+/// Funscript actions mapped onto video time, honoring the variant's `start_offset`, `inverted` and `range`.
+/// Script time is video time plus the start offset (spec 4.4), so actions that would fall before the video starts are dropped.
+#[derive(Debug, Clone)]
+pub struct ScriptTimeline {
+    pub name: String,
+    actions: Vec<TimelineAction>,
+}
+
+impl ScriptTimeline {
+    pub fn new(name: &str, funscript: &Funscript, start_offset: i64) -> Self {
+        let range = match funscript.range {
+            0 => 100.0,
+            range => range as f64,
+        };
+
+        let mut actions: Vec<TimelineAction> = funscript.actions.iter()
+            .filter_map(|action| {
+                let video_time = u64::try_from(action.at as i64 - start_offset).ok()?;
+                let position = (action.pos as f64 / range).clamp(0.0, 1.0);
+                let position = if funscript.inverted { 1.0 - position } else { position };
+                Some(TimelineAction { video_time, position })
+            })
+            .collect();
+        actions.sort_by_key(|action| action.video_time);
+
+        ScriptTimeline { name: name.to_string(), actions }
+    }
+
+    pub fn actions(&self) -> &[TimelineAction] {
+        &self.actions
+    }
+
+    /// The first action strictly after `video_time`: the one a device should be moving towards.
+    pub fn next_action(&self, video_time: u64) -> Option<(usize, TimelineAction)> {
+        let index = self.actions.partition_point(|action| action.video_time <= video_time);
+        self.actions.get(index).map(|action| (index, *action))
+    }
+
+    /// Video time of the last action
+    pub fn duration(&self) -> u64 {
+        self.actions.last().map(|action| action.video_time).unwrap_or(0)
+    }
+}
+
+/// Load a script variant from an FSV (`script_name`, or the first one listed) as a playback timeline,
+/// timed against the video format `video_name` (the first one listed if `None`, see `offsets::start_offset`).
+pub fn load_timeline(path: &Path, script_name: Option<&str>, video_name: Option<&str>) -> Result<ScriptTimeline, PlaybackError> {
+    let mut container = FsvContainer::from_reader(std::fs::File::open(path)?)?;
+    let metadata = container.metadata()?;
+    let variant = match script_name {
+        Some(name) => metadata.script_variants.iter().find(|variant| variant.name == name).ok_or_else(|| PlaybackError::ScriptNotFound(name.to_string()))?,
+        None => metadata.script_variants.first().ok_or(PlaybackError::NoScripts)?,
+    };
+
+    let start_offset = offsets::start_offset(&metadata, &variant.name, video_name).unwrap_or(variant.start_offset);
+    read_timeline(&mut container, &variant.name, start_offset)
+}
+
+/// Read the funscript stored as `name` as a playback timeline.
+pub fn read_timeline<R: Read + Seek>(container: &mut FsvContainer<R>, name: &str, start_offset: i64) -> Result<ScriptTimeline, PlaybackError> {
+    let data = container.read_entry(name)?;
+    let funscript = serde_json::from_slice::<Funscript>(&data)?;
+
+    Ok(ScriptTimeline::new(name, &funscript, start_offset))
+}
+
+/// Video and subtitle files extracted from an FSV so an external player can open them.
+#[derive(Debug)]
+pub struct PlaybackMedia {
+    pub video: PathBuf,
+    pub subtitles: Vec<PathBuf>,
+}
+
+/// Extract a video format (`video_name`, or the first one present in the archive) and all subtitle tracks into `output_dir`.
+/// Subtitle tracks missing from the archive are skipped.
+pub fn extract_media(path: &Path, video_name: Option<&str>, output_dir: &Path) -> Result<PlaybackMedia, PlaybackError> {
+    let mut container = FsvContainer::from_reader(File::open(path)?)?;
+    let metadata = container.metadata()?;
+    let present: Vec<String> = container.entry_names().map(str::to_string).collect();
+    let is_present = |name: &str| present.iter().any(|entry| entry == name);
+
+    let video_name = match video_name {
+        Some(name) => metadata.video_formats.iter().find(|format| format.name == name && is_present(name)).ok_or_else(|| PlaybackError::VideoNotFound(name.to_string()))?,
+        None => metadata.video_formats.iter().find(|format| is_present(&format.name)).ok_or(PlaybackError::NoVideo)?,
+    }.name.clone();
+
+    let mut extract = |name: &str| -> Result<PathBuf, PlaybackError> {
+        let file_name = Path::new(name).file_name().map(PathBuf::from).unwrap_or_else(|| PathBuf::from(name));
+        let output_path = output_dir.join(file_name);
+        container.copy_entry(name, &mut File::create(&output_path)?)?;
+        Ok(output_path)
+    };
+
+    let video = extract(&video_name)?;
+    let mut subtitles = Vec::new();
+    for track in metadata.subtitle_tracks.iter().filter(|track| is_present(&track.name)) {
+        subtitles.push(extract(&track.name)?);
+    }
+
+    Ok(PlaybackMedia { video, subtitles })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_timeline() {
+        let funscript: Funscript = serde_json::from_str(r#"{ "actions": [{ "at": 500, "pos": 0 }, { "at": 1000, "pos": 100 }, { "at": 2000, "pos": 50 }], "inverted": false, "range": 100, "version": "1.0" }"#).unwrap();
+        let timeline = ScriptTimeline::new("a.funscript", &funscript, 800);
+        // The action at 500 falls before the video start and is dropped
+        assert_eq!(timeline.actions(), [TimelineAction { video_time: 200, position: 1.0 }, TimelineAction { video_time: 1200, position: 0.5 }]);
+        assert_eq!(timeline.next_action(0).map(|(i, _)| i), Some(0));
+        assert_eq!(timeline.next_action(200).map(|(i, _)| i), Some(1));
+        assert_eq!(timeline.next_action(1200), None);
+
+        let delayed = ScriptTimeline::new("a.funscript", &funscript, -1000);
+        assert_eq!(delayed.actions()[0].video_time, 1500);
+        assert_eq!(delayed.duration(), 3000);
+    }
+}
@@ -0,0 +1,163 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::warn;
+
+use crate::metadata::FsvMetadata;
+#[cfg(feature = "native")]
+use crate::config::Config;
+
+/// Tag added to archives that violate a policy set to flag them, unless it names another one.
+pub const DEFAULT_FLAG_TAG: &str = "flagged";
+
+#[derive(Debug, Error)]
+pub enum PolicyError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid content policy '{0}': {1}")]
+    Invalid(PathBuf, serde_json::Error),
+    #[error("Refused by the content policy: {}", .0.join("; "))]
+    Rejected(Vec<String>),
+}
+
+/// What happens to an archive that violates the content policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    /// Refuse to create it
+    #[default]
+    Reject,
+    /// Create it with the policy's `flag_tag` added, for review
+    Flag,
+}
+
+/// Screening of the metadata of archives made by `create`, `import` and `watch`, read from a user-provided JSON file.
+/// All comparisons ignore case.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContentPolicy {
+    /// If not empty, every tag has to be one of these
+    pub allowed_tags: Vec<String>,
+    pub blocked_tags: Vec<String>,
+    pub blocked_performers: Vec<String>,
+    pub blocked_studios: Vec<String>,
+    /// Words or phrases not allowed anywhere in the title, studio, performers or tags
+    pub blocked_terms: Vec<String>,
+    pub on_violation: PolicyAction,
+    pub flag_tag: String,
+}
+
+impl Default for ContentPolicy {
+    fn default() -> Self {
+        ContentPolicy {
+            allowed_tags: Vec::new(),
+            blocked_tags: Vec::new(),
+            blocked_performers: Vec::new(),
+            blocked_studios: Vec::new(),
+            blocked_terms: Vec::new(),
+            on_violation: PolicyAction::default(),
+            flag_tag: DEFAULT_FLAG_TAG.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl Config {
+    /// The content policy the config points to, if any. A relative path is relative to the directory holding the config.
+    pub fn content_policy(&self, config_path: &Path) -> Result<Option<ContentPolicy>, PolicyError> {
+        let Some(policy_path) = &self.content_policy else {
+            return Ok(None);
+        };
+
+        let policy_path = config_path.parent().unwrap_or(Path::new(".")).join(policy_path);
+        ContentPolicy::load(&policy_path).map(Some)
+    }
+}
+
+impl ContentPolicy {
+    pub fn load(path: &Path) -> Result<ContentPolicy, PolicyError> {
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(|err| PolicyError::Invalid(path.to_path_buf(), err))
+    }
+
+    /// Every way `metadata` breaks the policy, empty if it doesn't.
+    pub fn violations(&self, metadata: &FsvMetadata) -> Vec<String> {
+        let contains = |list: &[String], value: &str| list.iter().any(|item| item.trim().eq_ignore_ascii_case(value.trim()));
+        let mut violations = Vec::new();
+        for tag in &metadata.tags {
+            if contains(&self.blocked_tags, tag) {
+                violations.push(format!("Tag '{}' is blocked", tag));
+            }
+            else if !self.allowed_tags.is_empty() && !contains(&self.allowed_tags, tag) && !tag.eq_ignore_ascii_case(&self.flag_tag) {
+                violations.push(format!("Tag '{}' is not allowed", tag));
+            }
+        }
+
+        for performer in metadata.performers.iter().filter(|performer| contains(&self.blocked_performers, performer)) {
+            violations.push(format!("Performer '{}' is blocked", performer));
+        }
+
+        if !metadata.studio.trim().is_empty() && contains(&self.blocked_studios, &metadata.studio) {
+            violations.push(format!("Studio '{}' is blocked", metadata.studio));
+        }
+
+        let fields = [("title", &metadata.title), ("studio", &metadata.studio)].into_iter()
+            .chain(metadata.performers.iter().map(|performer| ("performer", performer)))
+            .chain(metadata.tags.iter().map(|tag| ("tag", tag)));
+        for (field, value) in fields {
+            let value = value.to_lowercase();
+            for term in self.blocked_terms.iter().filter(|term| !term.trim().is_empty() && value.contains(&term.trim().to_lowercase())) {
+                violations.push(format!("The {} contains blocked term '{}'", field, term.trim()));
+            }
+        }
+
+        violations
+    }
+
+    /// Screen `metadata` before its archive is written: refuse it, or add the flag tag, if it violates the policy.
+    pub fn screen(&self, metadata: &mut FsvMetadata) -> Result<(), PolicyError> {
+        let violations = self.violations(metadata);
+        if violations.is_empty() {
+            return Ok(());
+        }
+
+        match self.on_violation {
+            PolicyAction::Reject => Err(PolicyError::Rejected(violations)),
+            PolicyAction::Flag => {
+                warn!(outcome = "flagged", "Flagging '{}' with '{}': {}", metadata.title, self.flag_tag, violations.join("; "));
+                if !metadata.tags.iter().any(|tag| tag.eq_ignore_ascii_case(&self.flag_tag)) {
+                    metadata.tags.push(self.flag_tag.clone());
+                }
+
+                Ok(())
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semver::Version;
+
+    #[test]
+    fn test_content_policy() {
+        let policy: ContentPolicy = serde_json::from_str(r#"{ "allowed_tags": ["pov", "vr", "solo"], "blocked_tags": ["vr"], "blocked_terms": ["leak"], "on_violation": "flag" }"#).unwrap();
+        let mut metadata = FsvMetadata::new(Version::new(1, 0, 0));
+        metadata.title = "Scene".to_string();
+        metadata.tags = vec!["POV".to_string()];
+        assert!(policy.violations(&metadata).is_empty());
+
+        metadata.title = "Leaked Scene".to_string();
+        metadata.tags = vec!["pov".to_string(), "VR".to_string(), "other".to_string()];
+        assert_eq!(policy.violations(&metadata), ["Tag 'VR' is blocked", "Tag 'other' is not allowed", "The title contains blocked term 'leak'"]);
+
+        policy.screen(&mut metadata).unwrap();
+        assert_eq!(metadata.tags.last().map(String::as_str), Some(DEFAULT_FLAG_TAG));
+        assert_eq!(policy.violations(&metadata).len(), 3);
+
+        let reject = ContentPolicy { on_violation: PolicyAction::Reject, ..policy };
+        assert!(matches!(reject.screen(&mut metadata), Err(PolicyError::Rejected(violations)) if violations.len() == 3));
+    }
+}
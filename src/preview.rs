@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+#[cfg(feature = "native")]
+use std::{ffi::OsString, path::Path, process::Command};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+#[cfg(feature = "native")]
+use tracing::info;
+
+use crate::metadata::FsvMetadata;
+#[cfg(feature = "native")]
+use crate::transcode::TranscodeError;
+
+/// Metadata field holding the `fsv.previews` extension data.
+pub const PREVIEWS_FIELD: &str = "previews";
+pub const DEFAULT_PREVIEW_NAME: &str = "preview.mp4";
+
+/// Previews are meant for gallery pages, so they are capped at 720p and carry no audio.
+#[cfg(feature = "native")]
+const PREVIEW_MAX_HEIGHT: u32 = 720;
+/// Portion of the video skipped at each end when spreading montage segments (intros and credits)
+const MONTAGE_MARGIN_PERCENT: u64 = 5;
+
+/// A cut from the source video, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreviewSegment {
+    pub start: u64,
+    pub duration: u64,
+}
+
+/// A preview clip stored in the archive, and the segments of `source` it was cut from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preview {
+    pub name: String,
+    pub source: String,
+    pub segments: Vec<PreviewSegment>,
+    /// Fields of other tools, kept when the preview is recut
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl Preview {
+    pub fn new(name: String, source: String, segments: Vec<PreviewSegment>) -> Self {
+        Preview { name, source, segments, extra: HashMap::new() }
+    }
+}
+
+/// Read the previews declared in metadata. A missing field means no previews.
+pub fn previews_from_metadata(metadata: &FsvMetadata) -> Result<Vec<Preview>, serde_json::Error> {
+    metadata.extension_data(PREVIEWS_FIELD)
+}
+
+/// `count` segments of `segment_ms` spread evenly over the video, skipping the first and last few percent.
+/// Falls back to a single segment from the start if the video is too short.
+pub fn montage_segments(video_duration: u64, count: u32, segment_ms: u64) -> Vec<PreviewSegment> {
+    let margin = video_duration * MONTAGE_MARGIN_PERCENT / 100;
+    let first = margin;
+    let last = video_duration.saturating_sub(margin).saturating_sub(segment_ms);
+    let count = u64::from(count.max(1));
+    if last <= first || segment_ms * count > video_duration {
+        return vec![PreviewSegment { start: 0, duration: segment_ms.min(video_duration) }];
+    }
+
+    if count == 1 {
+        return vec![PreviewSegment { start: first + (last - first) / 2, duration: segment_ms }];
+    }
+
+    (0..count)
+        .map(|i| PreviewSegment { start: first + (last - first) * i / (count - 1), duration: segment_ms })
+        .collect()
+}
+
+#[cfg(feature = "native")]
+fn seconds(ms: u64) -> String {
+    format!("{}.{:03}", ms / 1000, ms % 1000)
+}
+
+#[cfg(feature = "native")]
+fn ffmpeg_args(input: &Path, output: &Path, segments: &[PreviewSegment]) -> Vec<OsString> {
+    let mut filters = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        filters.push(format!(
+            "[0:v]trim=start={}:duration={},setpts=PTS-STARTPTS,scale=-2:'min({},ih)'[v{}]",
+            seconds(segment.start), seconds(segment.duration), PREVIEW_MAX_HEIGHT, i
+        ));
+    }
+
+    let inputs: String = (0..segments.len()).map(|i| format!("[v{}]", i)).collect();
+    filters.push(format!("{}concat=n={}:v=1:a=0[out]", inputs, segments.len()));
+
+    let mut args: Vec<OsString> = ["-hide_banner", "-loglevel", "error", "-y", "-i"].iter().map(OsString::from).collect();
+    args.push(input.into());
+    args.push("-filter_complex".into());
+    args.push(filters.join(";").into());
+    args.extend(["-map", "[out]", "-an", "-c:v", "libx264", "-crf", "23", "-preset", "fast", "-pix_fmt", "yuv420p", "-movflags", "+faststart"].iter().map(OsString::from));
+    args.push(output.into());
+    args
+}
+
+/// Cut `segments` out of `input` and join them into a single clip at `output` using `ffmpeg`.
+/// Requires ffmpeg (with libx264) to be installed and on PATH.
+#[cfg(feature = "native")]
+pub fn cut_preview(input: &Path, output: &Path, segments: &[PreviewSegment]) -> Result<(), TranscodeError> {
+    info!(operation = "preview", item = %input.display(), segments = segments.len(), outcome = "started", "Cutting preview segments");
+    let output = Command::new("ffmpeg").args(ffmpeg_args(input, output, segments)).output()?;
+    if !output.status.success() {
+        return Err(TranscodeError::Ffmpeg(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+
+    Ok(())
+}
+
+/// Validation hook for the extension registry.
+pub fn validate_previews(metadata: &FsvMetadata, entry_names: &[&str]) -> Vec<String> {
+    if !metadata.extra.contains_key(PREVIEWS_FIELD) {
+        return vec![format!("Missing '{}' field", PREVIEWS_FIELD)];
+    }
+
+    let previews = match previews_from_metadata(metadata) {
+        Ok(previews) => previews,
+        Err(err) => return vec![format!("Malformed '{}' field: {}", PREVIEWS_FIELD, err)],
+    };
+
+    let mut issues = Vec::new();
+    for preview in &previews {
+        if !entry_names.contains(&preview.name.as_str()) {
+            issues.push(format!("Preview '{}' not found in archive", preview.name));
+        }
+
+        if preview.segments.is_empty() {
+            issues.push(format!("Preview '{}' has no segments", preview.name));
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_montage_segments() {
+        let segments = montage_segments(100_000, 3, 2000);
+        assert_eq!(segments, [
+            PreviewSegment { start: 5000, duration: 2000 },
+            PreviewSegment { start: 49_000, duration: 2000 },
+            PreviewSegment { start: 93_000, duration: 2000 },
+        ]);
+
+        assert_eq!(montage_segments(3000, 4, 2000), [PreviewSegment { start: 0, duration: 2000 }]);
+        assert_eq!(montage_segments(1000, 1, 2000), [PreviewSegment { start: 0, duration: 1000 }]);
+    }
+}
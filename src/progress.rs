@@ -0,0 +1,128 @@
+use std::{collections::HashMap, sync::{Mutex, mpsc}};
+
+use serde::Serialize;
+use tracing::info;
+
+/// A step of a long-running operation (import, scan, rebuild, ...), as reported to UIs. `target` is the file or
+/// directory the operation works on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    Started { operation: String, target: String },
+    /// `done` of `total` steps (entries, files) are finished
+    Progress { operation: String, target: String, done: usize, total: usize },
+    Completed { operation: String, target: String },
+    Failed { operation: String, target: String, error: String },
+    ValidationFailed { target: String, errors: Vec<String> },
+}
+
+impl ProgressEvent {
+    pub fn started(operation: &str, target: impl ToString) -> Self {
+        ProgressEvent::Started { operation: operation.to_string(), target: target.to_string() }
+    }
+
+    pub fn progress(operation: &str, target: impl ToString, done: usize, total: usize) -> Self {
+        ProgressEvent::Progress { operation: operation.to_string(), target: target.to_string(), done, total }
+    }
+
+    pub fn completed(operation: &str, target: impl ToString) -> Self {
+        ProgressEvent::Completed { operation: operation.to_string(), target: target.to_string() }
+    }
+
+    pub fn failed(operation: &str, target: impl ToString, error: impl ToString) -> Self {
+        ProgressEvent::Failed { operation: operation.to_string(), target: target.to_string(), error: error.to_string() }
+    }
+}
+
+/// Receives progress events. Called on the thread doing the work, so implementations should return quickly.
+pub trait ProgressListener: Send + Sync {
+    fn on_event(&self, event: ProgressEvent);
+}
+
+/// Discards every event, for callers that don't track progress.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoProgress;
+
+impl ProgressListener for NoProgress {
+    fn on_event(&self, _event: ProgressEvent) {}
+}
+
+/// Hands every event to all current subscribers (e.g. WebSocket clients). Subscribers that went away are dropped on the next event.
+#[derive(Debug, Default)]
+pub struct EventBroadcaster {
+    subscribers: Mutex<Vec<mpsc::Sender<ProgressEvent>>>,
+}
+
+impl EventBroadcaster {
+    pub fn new() -> Self {
+        EventBroadcaster::default()
+    }
+
+    /// Receive every event sent from now on.
+    pub fn subscribe(&self) -> mpsc::Receiver<ProgressEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+}
+
+impl ProgressListener for EventBroadcaster {
+    fn on_event(&self, event: ProgressEvent) {
+        self.subscribers.lock().unwrap().retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
+
+/// Logs `progress` events in steps of 10 percent per operation and target, as a plain progress view for the CLI.
+#[derive(Debug, Default)]
+pub struct ProgressLog {
+    reported: Mutex<HashMap<(String, String), usize>>,
+}
+
+impl ProgressLog {
+    pub fn new() -> Self {
+        ProgressLog::default()
+    }
+}
+
+impl ProgressListener for ProgressLog {
+    fn on_event(&self, event: ProgressEvent) {
+        let ProgressEvent::Progress { operation, target, done, total } = event else {
+            return;
+        };
+
+        let step = (done * 10).checked_div(total).unwrap_or(0);
+        let mut reported = self.reported.lock().unwrap();
+        let last = reported.entry((operation.clone(), target.clone())).or_insert(0);
+        // Workers finish out of order, so counts can arrive slightly shuffled
+        if step > *last {
+            *last = step;
+            info!(operation = %operation, target = %target, done, total, outcome = "progress", "{}% done", done * 100 / total);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_broadcaster() {
+        let broadcaster = EventBroadcaster::new();
+        let first = broadcaster.subscribe();
+        let second = broadcaster.subscribe();
+        broadcaster.on_event(ProgressEvent::progress("rebuild", "a.fsv", 3, 7));
+        assert_eq!(first.recv().unwrap(), ProgressEvent::progress("rebuild", "a.fsv", 3, 7));
+        assert_eq!(second.recv().unwrap(), ProgressEvent::progress("rebuild", "a.fsv", 3, 7));
+
+        drop(second);
+        broadcaster.on_event(ProgressEvent::completed("rebuild", "a.fsv"));
+        assert_eq!(broadcaster.subscriber_count(), 1);
+
+        let json = serde_json::to_value(first.recv().unwrap()).unwrap();
+        assert_eq!(json, serde_json::json!({ "event": "completed", "operation": "rebuild", "target": "a.fsv" }));
+    }
+}
@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use pyo3::{create_exception, exceptions::{PyException, PyValueError}, prelude::*, types::PyDict};
+use serde::Serialize;
+
+use crate::{db_client::{DbClient, DbClientError}, exit_code::ToExitCode, fsv::{self, AddArgs, CreateArgs, EntryType, ExtractOnly, ExtractOptions, FsvState, InfoOptions, ItemType, NameMatching}};
+
+create_exception!(funscriptvideo, FsvException, PyException, "An FSV operation failed. `exit_code` holds the code the CLI would exit with.");
+
+/// Raise `FsvException` with the error message and its CLI exit code.
+fn fsv_err<E: std::fmt::Display + ToExitCode>(err: E) -> PyErr {
+    let exception = FsvException::new_err(err.to_string());
+    Python::attach(|py| {
+        if let Err(attr_err) = exception.value(py).setattr("exit_code", err.exit_code().code()) {
+            attr_err.print(py);
+        }
+    });
+    exception
+}
+
+/// Parse a CLI-style value such as `"strict"` or `"video"`.
+fn parse_value<T: ValueEnum>(value: &str, what: &str) -> PyResult<T> {
+    T::from_str(value, true).map_err(|_| PyValueError::new_err(format!("Invalid {} '{}'", what, value)))
+}
+
+/// Convert anything serializable to plain Python objects by way of the `json` module.
+fn to_python<'py>(py: Python<'py>, value: &impl Serialize) -> PyResult<Bound<'py, PyAny>> {
+    let json = serde_json::to_string(value).map_err(|err| PyValueError::new_err(err.to_string()))?;
+    py.import("json")?.call_method1("loads", (json,))
+}
+
+/// Run an async library call on a runtime of its own, with a database at `db_path` or an in-memory one.
+fn block_on_with_db<T>(db_path: Option<&Path>, run: impl AsyncFnOnce(&DbClient) -> PyResult<T>) -> PyResult<T> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().map_err(|err| FsvException::new_err(err.to_string()))?;
+    runtime.block_on(async {
+        let db_client = match db_path {
+            Some(db_path) => DbClient::new(db_path).await,
+            None => DbClient::in_memory().await,
+        };
+        let db_client = db_client.map_err(|err: DbClientError| fsv_err(err))?;
+        let result = run(&db_client).await;
+        db_client.pool.close().await;
+        result
+    })
+}
+
+/// Metadata of the FSV at `path` as a dict.
+#[pyfunction]
+fn open<'py>(py: Python<'py>, path: PathBuf) -> PyResult<Bound<'py, PyAny>> {
+    let metadata = py.detach(|| fsv::read_fsv_metadata(&path)).map_err(fsv_err)?;
+    to_python(py, &metadata)
+}
+
+/// Validate the FSV at `path`. Returns a dict with `state` (`valid`, `content_incomplete` or `metadata_invalid`),
+/// `errors` and `warnings`.
+#[pyfunction]
+#[pyo3(signature = (path, name_matching = "strict"))]
+fn validate<'py>(py: Python<'py>, path: PathBuf, name_matching: &str) -> PyResult<Bound<'py, PyDict>> {
+    let name_matching: NameMatching = parse_value(name_matching, "name matching")?;
+    let report = py.detach(|| fsv::validate_fsv_report(&path, name_matching)).map_err(fsv_err)?;
+    let state = match report.state {
+        FsvState::Valid => "valid",
+        FsvState::ContentIncomplete(_) => "content_incomplete",
+        FsvState::MetadataInvalid(_) => "metadata_invalid",
+    };
+    let result = PyDict::new(py);
+    result.set_item("state", state)?;
+    result.set_item("errors", report.errors().map(|issue| issue.to_string()).collect::<Vec<_>>())?;
+    result.set_item("warnings", report.warnings().map(|issue| issue.to_string()).collect::<Vec<_>>())?;
+    Ok(result)
+}
+
+/// Summary of the FSV at `path`, as printed by `info --json`. `full` adds per-entry details and verifies checksums,
+/// `sizes` adds the compressed and uncompressed size of every entry.
+#[pyfunction]
+#[pyo3(signature = (path, full = false, name_matching = "strict", sizes = false))]
+fn info<'py>(py: Python<'py>, path: PathBuf, full: bool, name_matching: &str, sizes: bool) -> PyResult<Bound<'py, PyAny>> {
+    let options = InfoOptions { name_matching: parse_value(name_matching, "name matching")?, full, sizes };
+    let info = py.detach(|| fsv::get_fsv_info_with_options(&path, &options)).map_err(fsv_err)?;
+    to_python(py, &info)
+}
+
+/// Extract the FSV at `path` into `output_dir`. `only` limits extraction to part of the content, e.g. `"previews"`.
+#[pyfunction]
+#[pyo3(signature = (path, output_dir, only = None))]
+fn extract(py: Python<'_>, path: PathBuf, output_dir: PathBuf, only: Option<&str>) -> PyResult<()> {
+    let only = only.map(|only| parse_value::<ExtractOnly>(only, "content")).transpose()?;
+    let options = ExtractOptions { only, ..Default::default() };
+    py.detach(|| fsv::extract_fsv(&path, &output_dir, &options)).map_err(fsv_err)
+}
+
+/// Create an FSV at `path`. Creator keys are looked up in the database at `db_path`; without one, no creators are known.
+#[pyfunction]
+#[pyo3(signature = (path, title, tags = Vec::new(), video = None, script = None, video_creator_key = None, script_creator_key = None, db_path = None))]
+#[allow(clippy::too_many_arguments)]
+fn create(py: Python<'_>, path: PathBuf, title: String, tags: Vec<String>, video: Option<PathBuf>, script: Option<PathBuf>, video_creator_key: Option<String>, script_creator_key: Option<String>, db_path: Option<PathBuf>) -> PyResult<()> {
+    let args = CreateArgs::new(path, title, tags, video, script, video_creator_key, script_creator_key);
+    py.detach(|| block_on_with_db(db_path.as_deref(), async |db_client| fsv::create_fsv(args, db_client, false).await.map_err(fsv_err)))
+}
+
+/// Add a `"video"`, `"script"` or `"subtitle"` file to the FSV at `path`.
+#[pyfunction]
+#[pyo3(signature = (path, item_type, file, creator_key = None, db_path = None))]
+fn add(py: Python<'_>, path: PathBuf, item_type: &str, file: PathBuf, creator_key: Option<String>, db_path: Option<PathBuf>) -> PyResult<()> {
+    let args = AddArgs::new(path, parse_value::<ItemType>(item_type, "item type")?, file, creator_key);
+    py.detach(|| block_on_with_db(db_path.as_deref(), async |db_client| fsv::add_to_fsv(args, db_client, false).await.map_err(fsv_err)))
+}
+
+/// Remove an entry (`"creator"`, `"video"`, `"script"` or `"subtitle"`) from the FSV at `path`. It can be restored with the CLI's `undo`.
+#[pyfunction]
+fn remove(py: Python<'_>, path: PathBuf, entry_type: &str, entry_id: &str) -> PyResult<()> {
+    let entry_type: EntryType = parse_value(entry_type, "entry type")?;
+    py.detach(|| fsv::remove_from_fsv(&path, entry_type, entry_id)).map_err(fsv_err)
+}
+
+#[pymodule]
+fn funscriptvideo(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("FsvException", m.py().get_type::<FsvException>())?;
+    m.add_function(wrap_pyfunction!(open, m)?)?;
+    m.add_function(wrap_pyfunction!(validate, m)?)?;
+    m.add_function(wrap_pyfunction!(info, m)?)?;
+    m.add_function(wrap_pyfunction!(extract, m)?)?;
+    m.add_function(wrap_pyfunction!(create, m)?)?;
+    m.add_function(wrap_pyfunction!(add, m)?)?;
+    m.add_function(wrap_pyfunction!(remove, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_python_module() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = pyo3::wrap_pymodule!(funscriptvideo)(py);
+            let module = module.bind(py);
+            let missing = std::env::temp_dir().join("missing_python_test.fsv");
+            let err = module.call_method1("open", (missing,)).unwrap_err();
+            assert!(err.is_instance_of::<FsvException>(py));
+            assert!(err.value(py).getattr("exit_code").is_ok());
+
+            let err = module.call_method1("validate", ("scene.fsv", "fuzzy")).unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+
+            let path = std::env::temp_dir().join("python_test.fsv");
+            let _ = std::fs::remove_file(&path);
+            module.call_method1("create", (&path, "Scene")).unwrap();
+            let metadata = module.call_method1("open", (&path,)).unwrap();
+            assert_eq!(metadata.get_item("title").unwrap().extract::<String>().unwrap(), "Scene");
+            std::fs::remove_file(&path).unwrap();
+        });
+    }
+}
@@ -0,0 +1,214 @@
+use std::{path::{Path, PathBuf}, process::{Command, ExitStatus}};
+
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::magic::MIME_TYPE;
+
+/// Name of the desktop entry written to `<data dir>/applications`, the default application for FSVs
+pub const DESKTOP_FILE_NAME: &str = "funscriptvideo.desktop";
+/// Name of the desktop entry showing `info` in a terminal, offered under "Open With"
+pub const INFO_DESKTOP_FILE_NAME: &str = "funscriptvideo-info.desktop";
+/// Name of the shared-mime-info package written to `<data dir>/mime/packages`
+pub const MIME_PACKAGE_FILE_NAME: &str = "funscriptvideo.xml";
+/// Windows file type (ProgID) the `.fsv` extension is associated with
+pub const PROG_ID: &str = "FunscriptVideo.Archive";
+
+#[derive(Debug, Error)]
+pub enum RegisterError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("No data directory to register in: neither XDG_DATA_HOME nor HOME is set")]
+    NoDataDir,
+    #[error("'{0}' exited with {1}")]
+    ToolFailed(String, ExitStatus),
+    #[error("Registering file types is not supported on this platform")]
+    Unsupported,
+}
+
+/// A file written or a command run by `register`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegisterStep {
+    WriteFile(PathBuf, String),
+    Run(Vec<String>),
+}
+
+impl std::fmt::Display for RegisterStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegisterStep::WriteFile(path, _) => write!(f, "write {}", path.display()),
+            RegisterStep::Run(command) => write!(f, "run {}", command.join(" ")),
+        }
+    }
+}
+
+/// The shared-mime-info package declaring the FSV mime type, recognized by extension and by the archive comment.
+pub fn mime_package_xml() -> String {
+    format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<mime-info xmlns="http://www.freedesktop.org/standards/shared-mime-info">
+  <mime-type type="{MIME_TYPE}">
+    <comment>FunscriptVideo archive</comment>
+    <sub-class-of type="application/zip"/>
+    <glob pattern="*.fsv"/>
+  </mime-type>
+</mime-info>
+"#)
+}
+
+/// Quote an argument of a desktop entry's `Exec` key, as the Desktop Entry Specification requires for reserved characters.
+fn quote_exec_arg(arg: &str) -> String {
+    if !arg.chars().any(|c| c.is_whitespace() || "\"'\\><~|&;$*?#()`".contains(c)) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    // The whole key value is unescaped once more, so backslashes are doubled again
+    quoted.replace('\\', "\\\\")
+}
+
+/// The desktop entry opening FSVs with `exe open`.
+pub fn desktop_entry(exe: &Path) -> String {
+    format!("[Desktop Entry]
+Type=Application
+Name=FunscriptVideo
+Comment=Play FunscriptVideo archives
+Exec={} open %f
+Terminal=false
+NoDisplay=true
+MimeType={MIME_TYPE};
+", quote_exec_arg(&exe.to_string_lossy()))
+}
+
+/// The desktop entry showing `exe info` in a terminal, kept open until Enter is pressed.
+pub fn info_desktop_entry(exe: &Path) -> String {
+    let shell_exe = format!("'{}'", exe.to_string_lossy().replace('\'', r"'\''"));
+    let script = format!(r#"{} info "$1"; read -r _"#, shell_exe);
+    format!("[Desktop Entry]
+Type=Application
+Name=FunscriptVideo Info
+Comment=Show the contents of a FunscriptVideo archive
+Exec=sh -c {} sh %f
+Terminal=true
+NoDisplay=true
+MimeType={MIME_TYPE};
+", quote_exec_arg(&script))
+}
+
+/// `reg add` commands associating `.fsv` with `exe` for the current user: `open` as the default verb and `info` in a console.
+pub fn registry_commands(exe: &Path) -> Vec<Vec<String>> {
+    let exe = exe.to_string_lossy();
+    let classes = r"HKCU\Software\Classes";
+    let values = [
+        (format!(r"{classes}\.fsv"), None, PROG_ID.to_string()),
+        (format!(r"{classes}\.fsv"), Some("Content Type"), MIME_TYPE.to_string()),
+        (format!(r"{classes}\{PROG_ID}"), None, "FunscriptVideo archive".to_string()),
+        (format!(r"{classes}\{PROG_ID}\shell\open\command"), None, format!(r#""{exe}" open "%1""#)),
+        (format!(r"{classes}\{PROG_ID}\shell\info"), None, "Show FSV Info".to_string()),
+        (format!(r#"{classes}\{PROG_ID}\shell\info\command"#), None, format!(r#"cmd /k ""{exe}" info "%1"""#)),
+        (format!(r"{classes}\MIME\Database\Content Type\{MIME_TYPE}"), Some("Extension"), ".fsv".to_string()),
+    ];
+
+    values.into_iter().map(|(key, name, data)| {
+        let mut command = vec!["reg".to_string(), "add".to_string(), key];
+        match name {
+            Some(name) => command.extend(["/v".to_string(), name.to_string()]),
+            None => command.push("/ve".to_string()),
+        }
+        command.extend(["/t".to_string(), "REG_SZ".to_string(), "/d".to_string(), data, "/f".to_string()]);
+        command
+    }).collect()
+}
+
+/// `$XDG_DATA_HOME`, or `~/.local/share`.
+fn xdg_data_home() -> Result<PathBuf, RegisterError> {
+    match std::env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => std::env::var_os("HOME").filter(|dir| !dir.is_empty()).map(|home| PathBuf::from(home).join(".local/share")).ok_or(RegisterError::NoDataDir),
+    }
+}
+
+/// What associating `.fsv` files with `exe` takes on this platform: the registry on Windows,
+/// a shared-mime-info package and a desktop entry under the XDG data directory elsewhere.
+pub fn registration_steps(exe: &Path) -> Result<Vec<RegisterStep>, RegisterError> {
+    if cfg!(windows) {
+        return Ok(registry_commands(exe).into_iter().map(RegisterStep::Run).collect());
+    }
+
+    if cfg!(target_os = "macos") || !cfg!(unix) {
+        return Err(RegisterError::Unsupported);
+    }
+
+    let data_home = xdg_data_home()?;
+    let mime_dir = data_home.join("mime");
+    let applications_dir = data_home.join("applications");
+    let to_arg = |path: &Path| path.to_string_lossy().to_string();
+    Ok(vec![
+        RegisterStep::WriteFile(mime_dir.join("packages").join(MIME_PACKAGE_FILE_NAME), mime_package_xml()),
+        RegisterStep::WriteFile(applications_dir.join(DESKTOP_FILE_NAME), desktop_entry(exe)),
+        RegisterStep::WriteFile(applications_dir.join(INFO_DESKTOP_FILE_NAME), info_desktop_entry(exe)),
+        RegisterStep::Run(vec!["update-mime-database".to_string(), to_arg(&mime_dir)]),
+        RegisterStep::Run(vec!["update-desktop-database".to_string(), to_arg(&applications_dir)]),
+        RegisterStep::Run(vec!["xdg-mime".to_string(), "default".to_string(), DESKTOP_FILE_NAME.to_string(), MIME_TYPE.to_string()]),
+    ])
+}
+
+/// Associate `.fsv` files with `exe` for the current user, performing `registration_steps`.
+/// Missing desktop database tools are only warned about, as the session picks the files up on its next start.
+pub fn register(exe: &Path) -> Result<Vec<RegisterStep>, RegisterError> {
+    let steps = registration_steps(exe)?;
+    for step in &steps {
+        info!(operation = "register", "Registering: {}", step);
+        match step {
+            RegisterStep::WriteFile(path, content) => {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(path, content)?;
+            },
+            RegisterStep::Run(command) => {
+                let status = match Command::new(&command[0]).args(&command[1..]).status() {
+                    Ok(status) => status,
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound && !cfg!(windows) => {
+                        warn!(operation = "register", item = %command[0], outcome = "skipped", "Command is not installed, the association applies once the desktop session refreshes its caches");
+                        continue;
+                    },
+                    Err(err) => return Err(err.into()),
+                };
+
+                if !status.success() {
+                    return Err(RegisterError::ToolFailed(command.join(" "), status));
+                }
+            },
+        }
+    }
+
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registration_files() {
+        let entry = desktop_entry(Path::new("/opt/my apps/fsv-cli"));
+        assert!(entry.contains(r#"Exec="/opt/my apps/fsv-cli" open %f"#));
+        assert!(entry.contains(&format!("MimeType={};", MIME_TYPE)));
+        let info_entry = info_desktop_entry(Path::new("/opt/fsv-cli"));
+        assert!(info_entry.contains(r#"Exec=sh -c "'/opt/fsv-cli' info \\"\\$1\\"; read -r _" sh %f"#));
+        assert_eq!(quote_exec_arg("/usr/bin/fsv"), "/usr/bin/fsv");
+        assert_eq!(quote_exec_arg("C:\\a b"), r#""C:\\\\a b""#);
+        assert!(mime_package_xml().contains(r#"<glob pattern="*.fsv"/>"#));
+
+        let commands = registry_commands(Path::new(r"C:\Tools\fsv.exe"));
+        assert_eq!(commands[0], ["reg", "add", r"HKCU\Software\Classes\.fsv", "/ve", "/t", "REG_SZ", "/d", PROG_ID, "/f"]);
+        assert!(commands.iter().any(|command| command.contains(&r#""C:\Tools\fsv.exe" open "%1""#.to_string())));
+    }
+}
@@ -0,0 +1,257 @@
+use std::{collections::{HashMap, VecDeque}, io::{Read, Seek, SeekFrom}, path::Path};
+
+use tracing::{debug, info};
+
+use crate::fsv::{FsvContainer, FsvError, FsvInfo, FsvValidationError, InfoOptions, ValidateOptions, ValidationReport};
+
+/// Size of the aligned blocks fetched with one range request
+const BLOCK_SIZE: u64 = 64 * 1024;
+/// Most blocks fetched by a single request, bounding the memory used for one large read
+const MAX_BLOCKS_PER_REQUEST: u64 = 64;
+/// Blocks kept around, so the central directory and metadata.json are only fetched once
+const CACHED_BLOCKS: usize = 64;
+
+/// The `http(s)` URL `path` stands for, if it is one rather than a local path.
+pub fn as_url(path: &Path) -> Option<&str> {
+    let path = path.to_str()?;
+    let lower = path.to_ascii_lowercase();
+    (lower.starts_with("http://") || lower.starts_with("https://")).then_some(path)
+}
+
+/// Builds the headers of a request for the byte range `range` (e.g. `bytes=0-0`), including the `Range` header itself.
+pub type RangeHeaders = Box<dyn Fn(&str) -> Vec<(String, String)>>;
+
+/// A seekable reader over a file served over HTTP(S), fetching only the parts that are read with range requests.
+/// Reads are served from aligned blocks, the most recently used of which are cached.
+pub struct HttpRangeReader {
+    agent: ureq::Agent,
+    url: String,
+    headers: RangeHeaders,
+    len: u64,
+    position: u64,
+    blocks: HashMap<u64, Vec<u8>>,
+    recent: VecDeque<u64>,
+    bytes_fetched: u64,
+    requests: usize,
+}
+
+impl HttpRangeReader {
+    /// Find the size of the file at `url` with a one byte range request. Fails if the server doesn't support ranges.
+    pub fn open(url: &str) -> std::io::Result<Self> {
+        HttpRangeReader::open_with_headers(url, Box::new(|range| vec![("Range".to_string(), range.to_string())]))
+    }
+
+    /// Same as `open`, sending the headers built by `headers` with every request, e.g. to sign them.
+    pub fn open_with_headers(url: &str, headers: RangeHeaders) -> std::io::Result<Self> {
+        let agent = ureq::Agent::new_with_defaults();
+        let response = get_range(&agent, url, &headers, "bytes=0-0")?;
+        if response.status() != 206 {
+            return Err(std::io::Error::other(format!("'{}' does not support range requests (status {})", url, response.status())));
+        }
+
+        let len = response.headers().get("Content-Range")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit_once('/'))
+            .and_then(|(_, total)| total.trim().parse().ok())
+            .ok_or_else(|| std::io::Error::other(format!("'{}' did not report its size", url)))?;
+
+        Ok(HttpRangeReader { agent, url: url.to_string(), headers, len, position: 0, blocks: HashMap::new(), recent: VecDeque::new(), bytes_fetched: 0, requests: 1 })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Bytes downloaded so far, excluding the size probe.
+    pub fn bytes_fetched(&self) -> u64 {
+        self.bytes_fetched
+    }
+
+    /// Range requests made so far, including the size probe.
+    pub fn requests(&self) -> usize {
+        self.requests
+    }
+
+    /// Make sure `first` is cached, fetching it along with the uncached blocks after it up to `last` in one request.
+    fn fetch_blocks(&mut self, first: u64, last: u64) -> std::io::Result<()> {
+        if self.blocks.contains_key(&first) {
+            return Ok(());
+        }
+
+        let last = (first..=last.min(first + MAX_BLOCKS_PER_REQUEST - 1))
+            .take_while(|block| !self.blocks.contains_key(block))
+            .last()
+            .unwrap_or(first);
+        let start = first * BLOCK_SIZE;
+        let end = ((last + 1) * BLOCK_SIZE).min(self.len) - 1;
+        debug!(operation = "fetch", archive = %self.url, start, end, "Fetching byte range");
+        let mut response = get_range(&self.agent, &self.url, &self.headers, &format!("bytes={}-{}", start, end))?;
+        if response.status() != 206 {
+            return Err(std::io::Error::other(format!("'{}' ignored the range request (status {})", self.url, response.status())));
+        }
+
+        let mut data = Vec::with_capacity((end - start + 1) as usize);
+        response.body_mut().as_reader().read_to_end(&mut data)?;
+        if data.len() as u64 != end - start + 1 {
+            return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, format!("'{}' returned {} bytes for a {} byte range", self.url, data.len(), end - start + 1)));
+        }
+
+        self.requests += 1;
+        self.bytes_fetched += data.len() as u64;
+        for (block, chunk) in (first..).zip(data.chunks(BLOCK_SIZE as usize)) {
+            self.blocks.insert(block, chunk.to_vec());
+            self.recent.push_back(block);
+        }
+
+        while self.recent.len() > CACHED_BLOCKS {
+            if let Some(block) = self.recent.pop_front() {
+                self.blocks.remove(&block);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn get_range(agent: &ureq::Agent, url: &str, headers: &RangeHeaders, range: &str) -> std::io::Result<ureq::http::Response<ureq::Body>> {
+    let mut request = agent.get(url);
+    for (name, value) in headers(range) {
+        request = request.header(name, value);
+    }
+
+    request.call().map_err(std::io::Error::other)
+}
+
+impl Read for HttpRangeReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() || self.position >= self.len {
+            return Ok(0);
+        }
+
+        let block = self.position / BLOCK_SIZE;
+        let last = (self.position + buf.len() as u64 - 1).min(self.len - 1) / BLOCK_SIZE;
+        self.fetch_blocks(block, last)?;
+
+        let data = &self.blocks[&block];
+        let offset = (self.position - block * BLOCK_SIZE) as usize;
+        let count = buf.len().min(data.len() - offset);
+        buf[..count].copy_from_slice(&data[offset..offset + count]);
+        self.position += count as u64;
+
+        Ok(count)
+    }
+}
+
+impl Seek for HttpRangeReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        self.position = position.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Seek before the start of the file"))?;
+        Ok(self.position)
+    }
+}
+
+/// Validate the FSV at `url`, downloading only the central directory, metadata.json, scripts, subtitles and video headers.
+pub fn validate_url_report(url: &str, options: &ValidateOptions) -> Result<ValidationReport, FsvValidationError> {
+    let mut container = FsvContainer::from_reader(HttpRangeReader::open(url)?)?;
+    let report = container.validate_report_with(options)?;
+    log_transfer(&container.into_inner());
+
+    Ok(report)
+}
+
+/// Get the info of the FSV at `url`. Only `options.full` reads whole entries, including the videos.
+pub fn get_url_info(url: &str, options: &InfoOptions) -> Result<FsvInfo, FsvError> {
+    let mut container = FsvContainer::from_reader(HttpRangeReader::open(url)?)?;
+    let mut info = container.info(options)?;
+    if info.title.trim().is_empty() {
+        let file_name = url.split(['?', '#']).next().unwrap_or(url).rsplit('/').next().unwrap_or_default();
+        info.title = Path::new(file_name).file_stem()
+            .and_then(|os_str| os_str.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+    }
+
+    log_transfer(&container.into_inner());
+    Ok(info)
+}
+
+fn log_transfer(reader: &HttpRangeReader) {
+    info!(operation = "remote", archive = %reader.url, outcome = "completed", "Read {} of {} bytes in {} requests", reader.bytes_fetched(), reader.len(), reader.requests());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::{BufRead, BufReader, Write}, net::TcpListener};
+
+    use super::*;
+    use crate::fsv::{ArchiveCompression, FsvBuilder, FsvState};
+
+    const VIDEO: &[u8] = b"\0\0\0\x18ftypisom";
+    const SCRIPT: &[u8] = br#"{"actions":[{"at":0,"pos":0},{"at":1000,"pos":100}],"inverted":false,"range":100,"version":"1.0"}"#;
+
+    /// Serve `data` on localhost, answering `Range: bytes=<start>-<end>` requests only.
+    fn serve(data: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut range = None;
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap() == 0 || line.trim().is_empty() {
+                        break;
+                    }
+
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                        let (start, end) = value.trim().split_once('-').unwrap();
+                        range = Some((start.parse::<usize>().unwrap(), end.parse::<usize>().unwrap()));
+                    }
+                }
+
+                let (start, end) = range.unwrap();
+                let body = &data[start..=end];
+                let header = format!("HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n", body.len(), start, end, data.len());
+                stream.write_all(header.as_bytes()).unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+
+        format!("http://{}/scene.fsv", address)
+    }
+
+    #[test]
+    fn test_remote_validate_and_info() {
+        let padding = vec![0; 3 * BLOCK_SIZE as usize];
+        let data = FsvBuilder::new("").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000).entry("padding.bin", &padding[..])
+            .compression(ArchiveCompression::Stored).to_bytes().unwrap();
+        let url = serve(data.clone());
+
+        assert_eq!(as_url(Path::new(&url)), Some(url.as_str()));
+        assert_eq!(as_url(Path::new("scene.fsv")), None);
+
+        let report = validate_url_report(&url, &ValidateOptions::default()).unwrap();
+        assert!(matches!(report.state, FsvState::Valid));
+
+        let info = get_url_info(&url, &InfoOptions::default()).unwrap();
+        assert_eq!(info.title, "scene");
+
+        let mut reader = HttpRangeReader::open(&url).unwrap();
+        assert_eq!(reader.len(), data.len() as u64);
+        reader.seek(SeekFrom::End(-22)).unwrap();
+        let mut tail = Vec::new();
+        reader.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, data[data.len() - 22..]);
+        assert!(reader.bytes_fetched() < data.len() as u64);
+    }
+}
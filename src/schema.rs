@@ -0,0 +1,201 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{extensions::{KNOWN_EXTENSIONS, REQUIRED_EXTENSIONS_FIELD}, metadata::FsvMetadata};
+
+/// Fields this tool stores on video formats besides the ones in the spec.
+const VIDEO_FORMAT_FIELDS: [(&str, &str); 2] = [
+    ("codec", "Video codec as ffprobe names it, e.g. h264"),
+    ("resolution", "Width and height in pixels, e.g. 1920x1080"),
+];
+
+/// JSON Schema (draft 2020-12) of metadata.json, generated from `FsvMetadata`. The fields of the known extensions are listed
+/// but not constrained, their extensions' own validation checks them.
+pub fn metadata_schema() -> Value {
+    let mut schema = schemars::schema_for!(FsvMetadata).to_value();
+    if let Some(Value::Object(properties)) = schema.get_mut("properties") {
+        properties.insert(REQUIRED_EXTENSIONS_FIELD.to_string(), json!({
+            "type": "array",
+            "items": { "type": "string" },
+            "description": "Extensions a reader must understand to interpret the container correctly",
+        }));
+        for spec in &KNOWN_EXTENSIONS {
+            properties.insert(spec.field.to_string(), json!({ "description": format!("{} ({} extension)", spec.description, spec.id) }));
+        }
+    }
+
+    if let Some(Value::Object(properties)) = schema.pointer_mut("/$defs/VideoFormat/properties") {
+        for (field, description) in VIDEO_FORMAT_FIELDS {
+            properties.insert(field.to_string(), json!({ "type": "string", "description": description }));
+        }
+    }
+
+    schema
+}
+
+/// Problems found checking a metadata document against `metadata_schema`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SchemaReport {
+    pub errors: Vec<String>,
+    /// Fields the schema doesn't describe, and fields of known extensions the document doesn't declare.
+    /// Readers keep and ignore them, but they may be typos or another implementation's additions.
+    pub warnings: Vec<String>,
+    /// Paths of the fields the schema doesn't describe, also listed in `warnings`.
+    #[serde(skip)]
+    pub unknown_fields: Vec<String>,
+}
+
+impl SchemaReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Check a standalone metadata document against `metadata_schema`, without an archive to check its entries against.
+/// Supports the keywords the generated schema uses: `$ref`, `type`, `minimum`, `required`, `properties`, `additionalProperties`
+/// and `items`. Version patterns are checked by parsing the document, which also catches numbers out of range.
+pub fn validate_metadata_document(document: &Value) -> SchemaReport {
+    let schema = metadata_schema();
+    let mut report = SchemaReport::default();
+    check_value(&schema, &schema, document, "$", &mut report);
+    if report.is_valid() && let Err(err) = serde_json::from_value::<FsvMetadata>(document.clone()) {
+        report.errors.push(format!("$: {}", err));
+    }
+
+    let unknown_warnings: Vec<String> = report.unknown_fields.iter().map(|path| format!("{}: unknown field", path)).collect();
+    report.warnings.extend(unknown_warnings);
+
+    let declared = document.get("extensions").and_then(Value::as_array);
+    for spec in &KNOWN_EXTENSIONS {
+        let is_declared = declared.is_some_and(|ids| ids.iter().any(|id| id.as_str() == Some(spec.id)));
+        if document.get(spec.field).is_some() && !is_declared {
+            report.warnings.push(format!("$.{}: field of the {} extension, which is not declared in 'extensions'", spec.field, spec.id));
+        }
+    }
+
+    report
+}
+
+/// Paths (`$.script_variants[0].nmae`) of the fields in `document` that `metadata_schema` doesn't describe. The flattened `extra`
+/// maps keep them on parse, so this is the only place typos in field names show up.
+pub fn unknown_fields(document: &Value) -> Vec<String> {
+    let schema = metadata_schema();
+    let mut report = SchemaReport::default();
+    check_value(&schema, &schema, document, "$", &mut report);
+    report.unknown_fields
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_i64() || number.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match (expected, type_name(value)) {
+        ("number", "integer") => true,
+        // 3.0 is an integer in JSON Schema
+        ("integer", "number") => value.as_f64().is_some_and(|number| number.fract() == 0.0),
+        (expected, actual) => expected == actual,
+    }
+}
+
+fn check_value(root: &Value, schema: &Value, value: &Value, path: &str, report: &mut SchemaReport) {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        match reference.strip_prefix('#').and_then(|pointer| root.pointer(pointer)) {
+            Some(schema) => check_value(root, schema, value, path, report),
+            None => report.errors.push(format!("{}: unresolvable schema reference '{}'", path, reference)),
+        }
+        return;
+    }
+
+    if let Some(types) = schema.get("type") {
+        let matches = |expected: &Value| expected.as_str().is_some_and(|expected| type_matches(expected, value));
+        let matched = match types {
+            Value::Array(types) => types.iter().any(matches),
+            expected => matches(expected),
+        };
+        if !matched {
+            report.errors.push(format!("{}: expected {}, found {}", path, types, type_name(value)));
+            return;
+        }
+    }
+
+    if let (Some(minimum), Some(number)) = (schema.get("minimum").and_then(Value::as_f64), value.as_f64()) && number < minimum {
+        report.errors.push(format!("{}: {} is less than the minimum of {}", path, number, minimum));
+    }
+
+    match value {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for field in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+                if !object.contains_key(field) {
+                    report.errors.push(format!("{}: missing required field '{}'", path, field));
+                }
+            }
+
+            // Objects the schema doesn't describe the fields of (extension data) can hold anything
+            let Some(properties) = properties else {
+                return;
+            };
+
+            for (key, field) in object {
+                let field_path = format!("{}.{}", path, key);
+                match properties.get(key) {
+                    Some(field_schema) => check_value(root, field_schema, field, &field_path, report),
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => report.errors.push(format!("{}: field not allowed", field_path)),
+                    None => report.unknown_fields.push(field_path),
+                }
+            }
+        },
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check_value(root, item_schema, item, &format!("{}[{}]", path, i), report);
+                }
+            }
+        },
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{duration::DurationMs, extensions, metadata::{ScriptVariant, VideoFormat}, semver::Version};
+
+    #[test]
+    fn test_validate_metadata_document() {
+        let schema = metadata_schema();
+        assert!(schema.pointer("/properties/format_version").is_some() && schema.pointer("/$defs/ScriptVariant").is_some());
+        assert_eq!(schema.get("required").unwrap(), &json!(["format_version", "video_formats", "script_variants"]));
+
+        let mut metadata = FsvMetadata::new(Version::new(1, 0, 0));
+        metadata.add_video_format(VideoFormat::new("video.mp4".to_string(), String::new(), DurationMs::from_millis(1000), String::new()));
+        metadata.video_formats[0].extra.insert("codec".to_string(), json!("h264"));
+        metadata.add_script_variant(ScriptVariant::new("video.funscript".to_string(), String::new(), vec![], DurationMs::from_millis(1000), -50, String::new()));
+        metadata.extra.insert("titles".to_string(), json!({ "ja": "夏" }));
+        metadata.extensions.push(extensions::LOCALIZED_TITLES_EXTENSION.to_string());
+        let report = validate_metadata_document(&serde_json::to_value(&metadata).unwrap());
+        assert!(report.is_valid() && report.warnings.is_empty(), "{:?}", report);
+
+        let mut document = serde_json::to_value(&metadata).unwrap();
+        document["video_formats"][0]["duration"] = json!(-1);
+        document["script_variants"][0]["nmae"] = json!("typo");
+        document["extensions"] = json!([]);
+        document.as_object_mut().unwrap().remove("format_version");
+        let report = validate_metadata_document(&document);
+        assert_eq!(report.errors, ["$: missing required field 'format_version'", "$.video_formats[0].duration: -1 is less than the minimum of 0"]);
+        assert_eq!(report.warnings, ["$.script_variants[0].nmae: unknown field", "$.titles: field of the fsv.localized-titles extension, which is not declared in 'extensions'"]);
+
+        // Only parsing catches malformed versions
+        let report = validate_metadata_document(&json!({ "format_version": "1.0", "video_formats": [], "script_variants": [] }));
+        assert_eq!(report.errors.len(), 1);
+    }
+}
@@ -1,3 +1,6 @@
+use std::borrow::Cow;
+
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -52,13 +55,13 @@ impl PartialOrd for Version {
 impl Ord for Version {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         if self.major != other.major {
-            return self.major.cmp(&other.major);
+            self.major.cmp(&other.major)
         }
         else if self.minor != other.minor {
-            return self.minor.cmp(&other.minor);
+            self.minor.cmp(&other.minor)
         }
         else {
-            return self.patch.cmp(&other.patch);
+            self.patch.cmp(&other.patch)
         }
     }
 }
@@ -87,6 +90,20 @@ impl<'de> Deserialize<'de> for Version {
     }
 }
 
+impl JsonSchema for Version {
+    fn schema_name() -> Cow<'static, str> {
+        "Version".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "pattern": "^[0-9]+\\.[0-9]+\\.[0-9]+$",
+            "description": "Semantic version as MAJOR.MINOR.PATCH"
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
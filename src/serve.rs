@@ -0,0 +1,515 @@
+use std::{io::Read, path::{Component, Path, PathBuf}, sync::Arc};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use thiserror::Error;
+use tiny_http::{Header, Method, Request, Response, Server};
+use tokio::runtime::Runtime;
+use tracing::{debug, info, info_span, warn};
+use tungstenite::{Message, WebSocket, handshake::derive_accept_key, protocol::Role};
+
+use crate::{db_client::{DbClient, LibraryEntry, LibraryFilter}, exit_code::{FsvExitCode, ToExitCode}, fsv::{self, AddArgs, EntryType, ExtractOnly, ExtractOptions, FsvState, InfoOptions, ItemType, NameMatching, RebuildOptions}, jobs::JobScheduler, library, progress::{EventBroadcaster, ProgressEvent, ProgressListener}};
+
+pub const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1:8731";
+/// Largest request body accepted; requests only carry small JSON documents
+const MAX_BODY_SIZE: u64 = 1 << 20;
+
+#[derive(Debug, Error)]
+pub enum ServeError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Unable to start server: {0}")]
+    Bind(String),
+}
+
+#[derive(Debug)]
+pub struct ServeArgs {
+    /// Address to listen on, e.g. `127.0.0.1:8731`
+    pub bind: String,
+    /// Library directory. Every path in a request is relative to it and may not leave it.
+    pub root: PathBuf,
+    /// Clients have to send `Authorization: Bearer <token>` (or `?token=` for the event feed, which browsers can't send headers to)
+    pub token: String,
+    /// Events of the API's own operations are published here, and sent to `/api/events` subscribers
+    pub events: Arc<EventBroadcaster>,
+}
+
+/// A token for a session: 32 bytes from the OS random number generator, hex-encoded.
+pub fn generate_token() -> Result<String, ServeError> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(std::io::Error::from)?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// An API failure, sent to the client as `{ "error": <message> }` with `status`.
+#[derive(Debug)]
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        ApiError { status, message: message.into() }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        ApiError::new(400, message)
+    }
+}
+
+impl<E: ToExitCode + std::fmt::Display> From<E> for ApiError {
+    fn from(err: E) -> Self {
+        let status = match err.exit_code() {
+            FsvExitCode::NotFound => 404,
+            FsvExitCode::AlreadyExists | FsvExitCode::Locked => 409,
+            FsvExitCode::Usage | FsvExitCode::ValidationFailed | FsvExitCode::ContentIncomplete | FsvExitCode::Metadata | FsvExitCode::Archive => 400,
+            _ => 500,
+        };
+        ApiError::new(status, err.to_string())
+    }
+}
+
+type ApiResult = Result<Value, ApiError>;
+
+#[derive(Debug, Deserialize)]
+struct RatingRequest {
+    path: String,
+    rating: Option<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FavoriteRequest {
+    path: String,
+    favorite: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProgressRequest {
+    path: String,
+    position_ms: u64,
+    #[serde(default)]
+    new_session: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddEntryRequest {
+    path: String,
+    #[serde(rename = "type")]
+    item_type: String,
+    file: String,
+    creator_key: Option<String>,
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtractRequest {
+    path: String,
+    output_dir: String,
+    only: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ScanRequest {
+    dir: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RebuildRequest {
+    path: String,
+    #[serde(default)]
+    fix_duplicates: bool,
+    #[serde(default)]
+    strict: bool,
+    #[serde(default)]
+    discard_unknown: bool,
+}
+
+/// Decode `%XX` escapes and `+` in a query string component. Invalid escapes are kept as they are.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if let Some(byte) = text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) => {
+                decoded.push(byte);
+                i += 2;
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Split a request URL into its path and decoded query parameters.
+fn parse_url(url: &str) -> (&str, Vec<(String, String)>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params = query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+
+    (path, params)
+}
+
+/// Compare without returning early, so response times don't reveal how much of a guessed token was right.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len() && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Whether `request` carries `token`, as a bearer token or (when `allow_query` is set) a `token` query parameter.
+fn authorized(request: &Request, token: &str, allow_query: bool) -> bool {
+    let header = request.headers().iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        .is_some_and(|given| tokens_match(given.trim(), token));
+    if header || !allow_query {
+        return header;
+    }
+
+    let (_, params) = parse_url(request.url());
+    params.iter().any(|(key, value)| key == "token" && tokens_match(value, token))
+}
+
+fn is_event_feed(request: &Request) -> bool {
+    *request.method() == Method::Get && parse_url(request.url()).0 == "/api/events"
+}
+
+/// Upgrade `request` to a WebSocket and forward every event published to `events` as a JSON text message, on a thread of its own.
+/// The thread ends when the client goes away.
+fn open_event_feed(request: Request, token: &str, events: &EventBroadcaster) {
+    if !authorized(&request, token, true) {
+        let _ = request.respond(json_response(401, &json!({ "error": "Missing or invalid API token" })));
+        return;
+    }
+
+    let key = request.headers().iter().find(|header| header.field.equiv("Sec-WebSocket-Key")).map(|header| header.value.to_string());
+    let Some(key) = key else {
+        let _ = request.respond(json_response(400, &json!({ "error": "Expected a WebSocket upgrade request" })));
+        return;
+    };
+
+    let accept = Header::from_bytes("Sec-WebSocket-Accept", derive_accept_key(key.trim().as_bytes())).expect("accept key is a valid header value");
+    let receiver = events.subscribe();
+    let stream = request.upgrade("websocket", Response::empty(101).with_header(accept));
+    std::thread::spawn(move || {
+        let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+        for event in receiver {
+            let Ok(text) = serde_json::to_string(&event) else {
+                continue;
+            };
+
+            if socket.send(Message::text(text)).is_err() {
+                break;
+            }
+        }
+
+        debug!(operation = "events", outcome = "disconnected", "Event feed client disconnected");
+    });
+}
+
+/// Serve only the `/api/events` WebSocket feed of `events` on `bind`, on a background thread. Used by modes that aren't
+/// an API server themselves (e.g. watch) to let UIs follow their progress.
+pub fn spawn_event_server(bind: &str, token: String, events: Arc<EventBroadcaster>) -> Result<(), ServeError> {
+    let server = Server::http(bind).map_err(|err| ServeError::Bind(err.to_string()))?;
+    info!(operation = "events", outcome = "started", url = %format!("ws://{}/api/events", bind), "Publishing events");
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            match is_event_feed(&request) {
+                true => open_event_feed(request, &token, &events),
+                false => {
+                    let _ = request.respond(json_response(404, &json!({ "error": "Not found" })));
+                },
+            }
+        }
+    });
+
+    Ok(())
+}
+
+struct Api<'a> {
+    root: PathBuf,
+    token: String,
+    events: Arc<EventBroadcaster>,
+    db_client: &'a DbClient,
+    runtime: &'a Runtime,
+}
+
+impl Api<'_> {
+    /// Resolve a client supplied path against the library root. Absolute paths and `..` are rejected.
+    fn resolve(&self, relative: &str) -> Result<PathBuf, ApiError> {
+        let path = Path::new(relative);
+        if relative.is_empty() || !path.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) {
+            return Err(ApiError::bad_request(format!("Invalid path '{}': expected a path relative to the library root", relative)));
+        }
+
+        Ok(self.root.join(path))
+    }
+
+    /// Path as the client knows it (relative to the root), or the full path for works outside it.
+    fn display_path(&self, path: &str) -> String {
+        Path::new(path).strip_prefix(&self.root).map_or_else(|_| path.to_string(), |relative| relative.to_string_lossy().to_string())
+    }
+
+    fn entry_json(&self, entry: LibraryEntry) -> Value {
+        json!({
+            "path": self.display_path(&entry.work.path),
+            "title": entry.work.title,
+            "titles": entry.work.localized_titles,
+            "studio": entry.work.studio,
+            "tags": entry.work.tags,
+            "performers": entry.work.performers,
+            "rating": entry.rating,
+            "favorite": entry.favorite,
+            "history": entry.history.map(|history| json!({
+                "last_played": history.last_played,
+                "position_ms": history.position_ms,
+                "play_count": history.play_count,
+            })),
+        })
+    }
+
+    /// Run an operation, publishing its start and outcome.
+    fn tracked<T, E: std::fmt::Display>(&self, operation: &str, target: &Path, run: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        let target = target.display().to_string();
+        self.events.on_event(ProgressEvent::started(operation, &target));
+        let result = run();
+        match &result {
+            Ok(_) => self.events.on_event(ProgressEvent::completed(operation, &target)),
+            Err(err) => self.events.on_event(ProgressEvent::failed(operation, &target, err)),
+        }
+
+        result
+    }
+
+    fn handle(&self, request: &mut Request) -> ApiResult {
+        if !authorized(request, &self.token, false) {
+            return Err(ApiError::new(401, "Missing or invalid API token"));
+        }
+
+        let url = request.url().to_string();
+        let (route, params) = parse_url(&url);
+        let param = |name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+        let required = |name: &str| param(name).ok_or_else(|| ApiError::bad_request(format!("Missing query parameter '{}'", name)));
+        let flag = |name: &str| param(name).is_some_and(|value| value.is_empty() || value == "true" || value == "1");
+
+        match (request.method(), route) {
+            (Method::Get, "/api/works") => {
+                let min_rating = param("min_rating").map(|value| value.parse::<u8>()).transpose().map_err(|_| ApiError::bad_request("Invalid min_rating"))?;
+                let filter = LibraryFilter {
+                    tag: param("tag").map(str::to_string),
+                    performer: param("performer").map(str::to_string),
+                    search: param("search").map(str::to_string),
+                    min_rating,
+                    favorites: flag("favorites"),
+                    unwatched: flag("unwatched"),
+                };
+                let entries = self.runtime.block_on(library::list_works(self.db_client, filter))?;
+                let works: Vec<Value> = entries.into_iter()
+                    .filter(|entry| Path::new(&entry.work.path).starts_with(&self.root))
+                    .map(|entry| self.entry_json(entry))
+                    .collect();
+                Ok(json!({ "works": works }))
+            },
+            (Method::Delete, "/api/works") => {
+                let path = self.resolve(required("path")?)?;
+                let key = std::fs::canonicalize(&path).unwrap_or(path).to_string_lossy().to_string();
+                let removed = self.runtime.block_on(self.db_client.remove_library_work(&key)).map_err(ApiError::from)?;
+                match removed {
+                    true => Ok(json!({ "removed": true })),
+                    false => Err(ApiError::new(404, "Work is not in the library")),
+                }
+            },
+            (Method::Post, "/api/works/scan") => {
+                let body: ScanRequest = read_json_or_default(request)?;
+                let dir = match body.dir {
+                    Some(dir) => self.resolve(&dir)?,
+                    None => self.root.clone(),
+                };
+                let summary = self.tracked("scan", &dir, || self.runtime.block_on(library::scan_library_with_progress(self.db_client, &dir, &JobScheduler::default(), &*self.events)))?;
+                Ok(json!({ "indexed": summary.indexed, "unchanged": summary.unchanged, "removed": summary.removed, "failed": summary.failed, "skipped": summary.skipped }))
+            },
+            (Method::Get, "/api/works/info") => {
+                let path = self.resolve(required("path")?)?;
+                let info = fsv::get_fsv_info_with_options(&path, &InfoOptions { full: flag("full"), sizes: flag("sizes"), ..Default::default() })?;
+                Ok(serde_json::to_value(info).map_err(|err| ApiError::new(500, err.to_string()))?)
+            },
+            (Method::Get, "/api/works/validate") => {
+                let path = self.resolve(required("path")?)?;
+                let report = fsv::validate_fsv_report(&path, NameMatching::Strict)?;
+                let state = match report.state {
+                    FsvState::Valid => "valid",
+                    FsvState::ContentIncomplete(_) => "content-incomplete",
+                    FsvState::MetadataInvalid(_) => "metadata-invalid",
+                };
+                let errors: Vec<String> = report.errors().map(|issue| issue.to_string()).collect();
+                if !errors.is_empty() {
+                    self.events.on_event(ProgressEvent::ValidationFailed { target: path.display().to_string(), errors: errors.clone() });
+                }
+                let warnings: Vec<String> = report.warnings().map(|issue| issue.to_string()).collect();
+                Ok(json!({ "state": state, "errors": errors, "warnings": warnings }))
+            },
+            (Method::Put, "/api/works/rating") => {
+                let body: RatingRequest = read_json(request)?;
+                self.runtime.block_on(library::rate_work(self.db_client, &self.resolve(&body.path)?, body.rating))?;
+                Ok(json!({ "rating": body.rating }))
+            },
+            (Method::Put, "/api/works/favorite") => {
+                let body: FavoriteRequest = read_json(request)?;
+                self.runtime.block_on(library::set_favorite(self.db_client, &self.resolve(&body.path)?, body.favorite))?;
+                Ok(json!({ "favorite": body.favorite }))
+            },
+            (Method::Post, "/api/works/progress") => {
+                let body: ProgressRequest = read_json(request)?;
+                self.runtime.block_on(library::record_progress(self.db_client, &self.resolve(&body.path)?, body.position_ms, body.new_session))?;
+                Ok(json!({ "position_ms": body.position_ms }))
+            },
+            (Method::Post, "/api/works/entries") => {
+                let body: AddEntryRequest = read_json(request)?;
+                let item_type = ItemType::from_str(&body.item_type, true).map_err(ApiError::bad_request)?;
+                let path = self.resolve(&body.path)?;
+                let args = AddArgs::new(path.clone(), item_type, self.resolve(&body.file)?, body.creator_key).description(body.description);
+                self.tracked("add", &path, || self.runtime.block_on(fsv::add_to_fsv(args, self.db_client, false)))?;
+                self.refresh_index(&body.path);
+                Ok(json!({ "added": body.file }))
+            },
+            (Method::Delete, "/api/works/entries") => {
+                let path = self.resolve(required("path")?)?;
+                let entry_type = EntryType::from_str(required("type")?, true).map_err(ApiError::bad_request)?;
+                let entry_id = required("id")?;
+                self.tracked("remove", &path, || fsv::remove_from_fsv(&path, entry_type, entry_id))?;
+                self.refresh_index(required("path")?);
+                Ok(json!({ "removed": entry_id }))
+            },
+            (Method::Post, "/api/works/extract") => {
+                let body: ExtractRequest = read_json(request)?;
+                let only = body.only.as_deref().map(|only| ExtractOnly::from_str(only, true)).transpose().map_err(ApiError::bad_request)?;
+                let output_dir = self.resolve(&body.output_dir)?;
+                let path = self.resolve(&body.path)?;
+                self.tracked("extract", &path, || fsv::extract_fsv(&path, &output_dir, &ExtractOptions { only, ..Default::default() }))?;
+                Ok(json!({ "output_dir": body.output_dir }))
+            },
+            (Method::Post, "/api/works/rebuild") => {
+                let body: RebuildRequest = read_json(request)?;
+                let path = self.resolve(&body.path)?;
+                let options = RebuildOptions { fix_duplicates: body.fix_duplicates, strict: body.strict, discard_unknown: body.discard_unknown };
+                let removed = self.tracked("rebuild", &path, || fsv::rebuild_fsv_with_progress(&path, &options, &*self.events))?;
+                self.refresh_index(&body.path);
+                Ok(json!({ "removed_duplicates": removed }))
+            },
+            (_, route) if route.starts_with("/api/") => Err(ApiError::new(404, format!("No endpoint {} {}", request.method(), route))),
+            _ => Err(ApiError::new(404, "Not found")),
+        }
+    }
+
+    /// Keep the index in step with an archive the API just modified. Failures only cost freshness, so they are logged.
+    fn refresh_index(&self, relative: &str) {
+        let Ok(path) = self.resolve(relative) else {
+            return;
+        };
+
+        if let Err(err) = self.runtime.block_on(library::index_work(self.db_client, &path)) {
+            warn!(operation = "index", archive = %path.display(), outcome = "failed", error = %err, "Unable to refresh library index");
+        }
+    }
+}
+
+fn read_body(request: &mut Request) -> Result<String, ApiError> {
+    let mut body = String::new();
+    request.as_reader().take(MAX_BODY_SIZE).read_to_string(&mut body).map_err(|err| ApiError::bad_request(format!("Unable to read request body: {}", err)))?;
+    Ok(body)
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(request: &mut Request) -> Result<T, ApiError> {
+    serde_json::from_str(&read_body(request)?).map_err(|err| ApiError::bad_request(format!("Invalid request body: {}", err)))
+}
+
+fn read_json_or_default<T: for<'de> Deserialize<'de> + Default>(request: &mut Request) -> Result<T, ApiError> {
+    let body = read_body(request)?;
+    match body.trim().is_empty() {
+        true => Ok(T::default()),
+        false => serde_json::from_str(&body).map_err(|err| ApiError::bad_request(format!("Invalid request body: {}", err))),
+    }
+}
+
+fn json_response(status: u16, body: &Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes("Content-Type", "application/json").expect("static header is valid");
+    Response::from_string(body.to_string()).with_status_code(status).with_header(header)
+}
+
+/// Serve the library index and FSV operations over HTTP until the process is stopped. Requests are handled one at a time,
+/// with database work run on `runtime`. All endpoints take and return JSON and require the bearer token:
+///
+/// - `GET /api/works` (`tag`, `performer`, `search`, `min_rating`, `favorites`, `unwatched`), `DELETE /api/works?path=`
+/// - `POST /api/works/scan` (`{ "dir"? }`)
+/// - `GET /api/works/info?path=[&full][&sizes]`, `GET /api/works/validate?path=`
+/// - `PUT /api/works/rating` (`{ "path", "rating" }`), `PUT /api/works/favorite` (`{ "path", "favorite" }`)
+/// - `POST /api/works/progress` (`{ "path", "position_ms", "new_session"? }`)
+/// - `POST /api/works/entries` (`{ "path", "type", "file", "creator_key"?, "description"? }`), `DELETE /api/works/entries?path=&type=&id=`
+/// - `POST /api/works/extract` (`{ "path", "output_dir", "only"? }`), `POST /api/works/rebuild` (`{ "path", "fix_duplicates"? }`)
+/// - `GET /api/events`: WebSocket feed of `ProgressEvent`s as JSON (the token may be passed as `?token=`)
+pub fn serve(args: ServeArgs, db_client: &DbClient, runtime: &Runtime) -> Result<(), ServeError> {
+    let root = std::fs::canonicalize(&args.root)?;
+    let server = Server::http(&args.bind).map_err(|err| ServeError::Bind(err.to_string()))?;
+    info!(operation = "serve", outcome = "started", root = %root.display(), url = %format!("http://{}", args.bind), "Serving library");
+
+    let api = Api { root, token: args.token, events: args.events, db_client, runtime };
+    for mut request in server.incoming_requests() {
+        let span = info_span!("request", method = %request.method(), url = %request.url());
+        let _span = span.enter();
+        debug!(operation = "request", outcome = "started", "Handling request");
+        if is_event_feed(&request) {
+            open_event_feed(request, &api.token, &api.events);
+            continue;
+        }
+
+        let (status, body) = match api.handle(&mut request) {
+            Ok(body) => (200, body),
+            Err(err) => {
+                if err.status >= 500 {
+                    warn!(operation = "request", outcome = "failed", status = err.status, error = %err.message, "Request failed");
+                }
+                (err.status, json!({ "error": err.message }))
+            },
+        };
+
+        if let Err(err) = request.respond(json_response(status, &body)) {
+            warn!(operation = "request", outcome = "failed", error = %err, "Unable to send response");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_parsing() {
+        let (route, params) = parse_url("/api/works?tag=solo%20play&search=a+b&favorites");
+        assert_eq!(route, "/api/works");
+        assert_eq!(params, [("tag".to_string(), "solo play".to_string()), ("search".to_string(), "a b".to_string()), ("favorites".to_string(), String::new())]);
+        assert_eq!(percent_decode("100%"), "100%");
+
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secreT", "secret"));
+        assert!(!tokens_match("secret2", "secret"));
+    }
+
+    #[test]
+    fn test_generate_token() {
+        let token = generate_token().unwrap();
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, generate_token().unwrap());
+    }
+}
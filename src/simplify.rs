@@ -0,0 +1,130 @@
+use crate::funscript::FunscriptAction;
+
+/// How a script is thinned out for devices with low command rates. Simplification runs before resampling when both are set.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SimplifyOptions {
+    /// Ramer-Douglas-Peucker tolerance: the largest position error (0-100) a dropped action may leave behind
+    pub epsilon: Option<f64>,
+    /// Minimum time between actions, in milliseconds
+    pub min_interval_ms: Option<u64>,
+}
+
+impl SimplifyOptions {
+    pub fn is_empty(&self) -> bool {
+        self.epsilon.is_none() && self.min_interval_ms.is_none()
+    }
+
+    /// Apply the configured steps to actions sorted by time.
+    pub fn apply(&self, actions: &[FunscriptAction]) -> Vec<FunscriptAction> {
+        let mut actions = actions.to_vec();
+        if let Some(epsilon) = self.epsilon {
+            actions = simplify_actions(&actions, epsilon);
+        }
+
+        if let Some(min_interval_ms) = self.min_interval_ms {
+            actions = resample_actions(&actions, min_interval_ms);
+        }
+
+        actions
+    }
+
+    /// Human readable summary of the steps, e.g. `RDP epsilon 5, minimum interval 100 ms`
+    pub fn describe(&self) -> String {
+        let mut steps = Vec::new();
+        if let Some(epsilon) = self.epsilon {
+            steps.push(format!("RDP epsilon {}", epsilon));
+        }
+
+        if let Some(min_interval_ms) = self.min_interval_ms {
+            steps.push(format!("minimum interval {} ms", min_interval_ms));
+        }
+
+        steps.join(", ")
+    }
+}
+
+/// Position error of `action` against the straight move from `start` to `end`.
+/// Distances are measured along the position axis only, since time and position don't share a unit.
+fn position_error(start: &FunscriptAction, end: &FunscriptAction, action: &FunscriptAction) -> f64 {
+    if end.at == start.at {
+        return action.pos.abs_diff(start.pos) as f64;
+    }
+
+    let progress = (action.at - start.at) as f64 / (end.at - start.at) as f64;
+    let expected = start.pos as f64 + (end.pos as f64 - start.pos as f64) * progress;
+    (action.pos as f64 - expected).abs()
+}
+
+/// Ramer-Douglas-Peucker simplification: drop actions that lie within `epsilon` positions of the line between their kept neighbours.
+pub fn simplify_actions(actions: &[FunscriptAction], epsilon: f64) -> Vec<FunscriptAction> {
+    if actions.len() < 3 {
+        return actions.to_vec();
+    }
+
+    let mut keep = vec![false; actions.len()];
+    keep[0] = true;
+    keep[actions.len() - 1] = true;
+    let mut segments = vec![(0, actions.len() - 1)];
+    while let Some((start, end)) = segments.pop() {
+        let farthest = (start + 1..end)
+            .map(|i| (i, position_error(&actions[start], &actions[end], &actions[i])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((index, error)) = farthest && error > epsilon {
+            keep[index] = true;
+            segments.push((start, index));
+            segments.push((index, end));
+        }
+    }
+
+    actions.iter().zip(keep).filter(|(_, keep)| *keep).map(|(action, _)| *action).collect()
+}
+
+/// Drop actions closer than `min_interval_ms` to the previously kept one. When a dropped action moves further than the
+/// kept one it follows, it takes that one's place (if spacing allows), so stroke peaks survive.
+pub fn resample_actions(actions: &[FunscriptAction], min_interval_ms: u64) -> Vec<FunscriptAction> {
+    let mut kept: Vec<FunscriptAction> = Vec::with_capacity(actions.len());
+    for action in actions {
+        let Some(last) = kept.last() else {
+            kept.push(*action);
+            continue;
+        };
+
+        if action.at - last.at >= min_interval_ms {
+            kept.push(*action);
+            continue;
+        }
+
+        if let [.., previous, last] = kept.as_mut_slice()
+            && action.at - previous.at >= min_interval_ms
+            && action.pos.abs_diff(previous.pos) > last.pos.abs_diff(previous.pos)
+        {
+            *last = *action;
+        }
+    }
+
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn actions(points: &[(u64, u64)]) -> Vec<FunscriptAction> {
+        points.iter().map(|&(at, pos)| FunscriptAction { at, pos }).collect()
+    }
+
+    #[test]
+    fn test_simplify_and_resample() {
+        // Midpoints of straight moves go, the turning point stays
+        let ramp = actions(&[(0, 0), (100, 25), (200, 50), (300, 75), (400, 100), (500, 50), (600, 1)]);
+        assert_eq!(simplify_actions(&ramp, 2.0), actions(&[(0, 0), (400, 100), (600, 1)]));
+        assert_eq!(simplify_actions(&ramp, 0.0).len(), 4);
+
+        let dense = actions(&[(0, 0), (100, 50), (150, 100), (250, 0), (300, 10), (400, 90)]);
+        assert_eq!(resample_actions(&dense, 100), actions(&[(0, 0), (150, 100), (250, 0), (400, 90)]));
+
+        let options = SimplifyOptions { epsilon: Some(2.0), min_interval_ms: Some(250) };
+        assert_eq!(options.apply(&ramp), actions(&[(0, 0), (400, 100)]));
+        assert_eq!(options.describe(), "RDP epsilon 2, minimum interval 250 ms");
+    }
+}
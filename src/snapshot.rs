@@ -0,0 +1,294 @@
+use std::{collections::HashMap, fs::File, io::BufReader, path::{Path, PathBuf}};
+
+use serde::Serialize;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::{checksum::HashAlgorithm, db_client::{DbClient, DbClientError, SnapshotRecord}, hash_cache::mtime_stamp, jobs::JobScheduler, library, progress::ProgressListener};
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Database client error: {0}")]
+    DbClient(#[from] DbClientError),
+}
+
+/// What taking a snapshot did.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotSummary {
+    pub recorded: usize,
+    /// Files recorded before whose size and mtime are unchanged, so they keep their earlier record
+    pub unchanged: usize,
+    pub failed: usize,
+    /// Records of vanished files dropped with `prune`
+    pub pruned: usize,
+}
+
+/// Entry names and digests of their contents, `None` for entries that can't be read.
+type EntryDigests = Vec<(String, Option<String>)>;
+
+/// Digests of an archive as a whole and of every entry's uncompressed contents. Entries that can't be read have no digest.
+/// Nothing comes from the hash cache: its digests are only invalidated by changed sizes and stamps, which bit-rot leaves alone.
+fn hash_archive(path: &Path, algorithm: HashAlgorithm) -> std::io::Result<(SnapshotRecord, EntryDigests)> {
+    let file_metadata = std::fs::metadata(path)?;
+    let digest = algorithm.digest_reader(&mut BufReader::new(File::open(path)?))?;
+    let mut archive = zip::ZipArchive::new(BufReader::new(File::open(path)?)).map_err(std::io::Error::other)?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(std::io::Error::other)?;
+        // Reading an entry to the end checks its CRC-32, so damaged entries fail here
+        let digest = algorithm.digest_reader(&mut entry).ok();
+        entries.push((entry.name().to_string(), digest));
+    }
+
+    let record = SnapshotRecord {
+        path: library::library_key(path)?,
+        algorithm: algorithm.get_name().to_string(),
+        size: file_metadata.len() as i64,
+        stamp: mtime_stamp(&file_metadata).unwrap_or(0),
+        digest,
+        entries: Vec::new(),
+    };
+    Ok((record, entries))
+}
+
+/// Record the digest of every FSV under `dir` and of each of its entries, hashing on the scheduler's workers and reporting
+/// `snapshot` progress events. Files already recorded with the same size and mtime are skipped, so taking another snapshot
+/// never accepts silent corruption as the new baseline. With `prune`, records of files under `dir` that are gone are dropped.
+pub async fn take_snapshot(db_client: &DbClient, dir: &Path, algorithm: HashAlgorithm, prune: bool, scheduler: &JobScheduler, progress: &dyn ProgressListener) -> Result<SnapshotSummary, SnapshotError> {
+    let prefix = library::dir_prefix(dir)?;
+    let records: HashMap<String, SnapshotRecord> = db_client.get_snapshots(Some(&prefix)).await?.into_iter().map(|record| (record.path.clone(), record)).collect();
+    let mut summary = SnapshotSummary::default();
+    let mut files = Vec::new();
+    for path in library::find_fsv_files(dir)? {
+        let file_metadata = std::fs::metadata(&path)?;
+        let recorded = records.get(&library::library_key(&path)?)
+            .is_some_and(|record| record.size == file_metadata.len() as i64 && Some(record.stamp) == mtime_stamp(&file_metadata));
+        match recorded {
+            true => summary.unchanged += 1,
+            false => files.push(path),
+        }
+    }
+
+    let outcomes = scheduler.run("snapshot", &dir.display().to_string(), &files, |path| hash_archive(path, algorithm), progress);
+    for outcome in outcomes {
+        let (mut record, entries) = match outcome.result {
+            Ok(hashes) => hashes,
+            Err(err) => {
+                warn!(operation = "snapshot", archive = %outcome.path.display(), outcome = "failed", error = %err, "Unable to hash archive");
+                summary.failed += 1;
+                continue;
+            },
+        };
+
+        if let Some((name, _)) = entries.iter().find(|(_, digest)| digest.is_none()) {
+            warn!(operation = "snapshot", archive = %outcome.path.display(), outcome = "failed", entry = %name, "Unable to read entry, not recording archive");
+            summary.failed += 1;
+            continue;
+        }
+
+        record.entries = entries.into_iter().map(|(name, digest)| (name, digest.unwrap_or_default())).collect();
+        db_client.store_snapshot(&record).await?;
+        summary.recorded += 1;
+    }
+
+    if prune {
+        for path in records.keys().filter(|path| !Path::new(path).is_file()) {
+            if db_client.remove_snapshot(path).await? {
+                info!(operation = "snapshot", archive = %path, outcome = "pruned", "Dropped snapshot of vanished archive");
+                summary.pruned += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// How an archive compares to its snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotStatus {
+    Intact,
+    /// Not in the snapshot, e.g. added since it was taken
+    New,
+    /// The contents changed along with the file's size or mtime, e.g. by an edit (or by tampering)
+    Modified,
+    /// The file is gone
+    Missing,
+    /// The contents changed while size and mtime stayed the same, or the file can no longer be read: bit-rot or tampering
+    Corrupted,
+}
+
+impl SnapshotStatus {
+    pub fn get_name(&self) -> &str {
+        match self {
+            SnapshotStatus::Intact => "intact",
+            SnapshotStatus::New => "new",
+            SnapshotStatus::Modified => "modified",
+            SnapshotStatus::Missing => "missing",
+            SnapshotStatus::Corrupted => "corrupted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotResult {
+    pub path: PathBuf,
+    pub status: SnapshotStatus,
+    /// What differs, e.g. which entries changed; empty for intact and new files
+    pub reasons: Vec<String>,
+}
+
+/// Outcome of `verify_snapshot`, one result per archive in path order.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SnapshotReport {
+    pub intact: usize,
+    pub new: usize,
+    pub modified: usize,
+    pub missing: usize,
+    pub corrupted: usize,
+    pub results: Vec<SnapshotResult>,
+}
+
+impl SnapshotReport {
+    /// Whether any recorded archive changed, vanished or became unreadable.
+    pub fn has_changes(&self) -> bool {
+        self.modified + self.missing + self.corrupted > 0
+    }
+}
+
+/// Compare an archive with its snapshot. The entries are only hashed if the archive as a whole changed.
+fn compare_archive(path: &Path, record: &SnapshotRecord) -> SnapshotResult {
+    let result = |status, reasons| SnapshotResult { path: path.to_path_buf(), status, reasons };
+    let Some(algorithm) = HashAlgorithm::from_name(&record.algorithm) else {
+        return result(SnapshotStatus::Corrupted, vec![format!("Snapshot uses unknown hash algorithm '{}'", record.algorithm)]);
+    };
+
+    let file_metadata = match std::fs::metadata(path) {
+        Ok(file_metadata) => file_metadata,
+        Err(err) => return result(SnapshotStatus::Corrupted, vec![format!("Unable to read file: {}", err)]),
+    };
+    let status = match file_metadata.len() as i64 == record.size && mtime_stamp(&file_metadata) == Some(record.stamp) {
+        true => SnapshotStatus::Corrupted,
+        false => SnapshotStatus::Modified,
+    };
+
+    match File::open(path).and_then(|file| algorithm.digest_reader(&mut BufReader::new(file))) {
+        Ok(digest) if digest == record.digest => return result(SnapshotStatus::Intact, Vec::new()),
+        Ok(_) => (),
+        Err(err) => return result(SnapshotStatus::Corrupted, vec![format!("Unable to read file: {}", err)]),
+    }
+
+    let entries = match hash_archive(path, algorithm) {
+        Ok((_, entries)) => entries,
+        Err(err) => return result(status, vec![format!("Archive is unreadable: {}", err)]),
+    };
+
+    let mut reasons = Vec::new();
+    for (name, digest) in &entries {
+        match (record.entries.iter().find(|(recorded, _)| recorded == name), digest) {
+            (_, None) => reasons.push(format!("Entry '{}' is unreadable", name)),
+            (None, Some(_)) => reasons.push(format!("Entry '{}' was added", name)),
+            (Some((_, recorded)), Some(digest)) if recorded != digest => reasons.push(format!("Entry '{}' changed", name)),
+            (Some(_), Some(_)) => (),
+        }
+    }
+    for (name, _) in &record.entries {
+        if !entries.iter().any(|(entry, _)| entry == name) {
+            reasons.push(format!("Entry '{}' was removed", name));
+        }
+    }
+    if reasons.is_empty() {
+        reasons.push("Archive changed outside its entries (e.g. its directory or comment)".to_string());
+    }
+
+    result(status, reasons)
+}
+
+/// Check every FSV under `dir` against its snapshot on the scheduler's workers, reporting `verify-snapshot` progress events.
+/// Archives recorded under `dir` that are gone are reported as missing.
+pub async fn verify_snapshot(db_client: &DbClient, dir: &Path, scheduler: &JobScheduler, progress: &dyn ProgressListener) -> Result<SnapshotReport, SnapshotError> {
+    let prefix = library::dir_prefix(dir)?;
+    let records: HashMap<String, SnapshotRecord> = db_client.get_snapshots(Some(&prefix)).await?.into_iter().map(|record| (record.path.clone(), record)).collect();
+    let files = library::find_fsv_files(dir)?;
+    let keys = files.iter().map(|path| library::library_key(path)).collect::<Result<Vec<_>, _>>()?;
+    let outcomes = scheduler.run("verify-snapshot", &dir.display().to_string(), &files, |path| {
+        let key = library::library_key(path)?;
+        Ok::<_, std::io::Error>(match records.get(&key) {
+            Some(record) => compare_archive(path, record),
+            None => SnapshotResult { path: path.to_path_buf(), status: SnapshotStatus::New, reasons: Vec::new() },
+        })
+    }, progress);
+
+    let mut results: Vec<SnapshotResult> = outcomes.into_iter().map(|outcome| match outcome.result {
+        Ok(result) => result,
+        Err(err) => SnapshotResult { path: outcome.path, status: SnapshotStatus::Corrupted, reasons: vec![format!("Unable to read file: {}", err)] },
+    }).collect();
+    for path in records.keys().filter(|path| !keys.contains(path)) {
+        results.push(SnapshotResult { path: PathBuf::from(path), status: SnapshotStatus::Missing, reasons: Vec::new() });
+    }
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let count = |status| results.iter().filter(|result| result.status == status).count();
+    Ok(SnapshotReport {
+        intact: count(SnapshotStatus::Intact),
+        new: count(SnapshotStatus::New),
+        modified: count(SnapshotStatus::Modified),
+        missing: count(SnapshotStatus::Missing),
+        corrupted: count(SnapshotStatus::Corrupted),
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fsv::FsvBuilder, progress::NoProgress};
+
+    #[tokio::test]
+    async fn test_snapshot() {
+        let dir = std::env::temp_dir().join(format!("fsv-snapshot-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = br#"{"actions":[{"at":0,"pos":0},{"at":1000,"pos":100}],"inverted":false,"range":100,"version":"1.0"}"#;
+        let fsv = |title: &str| FsvBuilder::new(title).script("scene.funscript", script, 1000).compression(crate::fsv::ArchiveCompression::Stored).to_bytes().unwrap();
+        for name in ["a.fsv", "b.fsv", "c.fsv"] {
+            std::fs::write(dir.join(name), fsv(name)).unwrap();
+        }
+        let db_client = DbClient::in_memory().await.unwrap();
+        let scheduler = JobScheduler::new(2);
+
+        let summary = take_snapshot(&db_client, &dir, HashAlgorithm::Xxh3, false, &scheduler, &NoProgress).await.unwrap();
+        assert_eq!(summary, SnapshotSummary { recorded: 3, ..Default::default() });
+        assert_eq!(db_client.get_snapshots(None).await.unwrap()[0].entries.len(), 2);
+
+        // Flip a byte of the stored script, keeping size and mtime as bit-rot would
+        let path = dir.join("a.fsv");
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        let mut data = std::fs::read(&path).unwrap();
+        let offset = data.windows(script.len()).position(|window| window == script).unwrap() + 20;
+        data[offset] ^= 1;
+        std::fs::write(&path, &data).unwrap();
+        File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        // Edit another one and drop the third
+        std::fs::write(dir.join("b.fsv"), fsv("Other title")).unwrap();
+        std::fs::remove_file(dir.join("c.fsv")).unwrap();
+        std::fs::write(dir.join("d.fsv"), fsv("d.fsv")).unwrap();
+
+        let report = verify_snapshot(&db_client, &dir, &scheduler, &NoProgress).await.unwrap();
+        let statuses: Vec<_> = report.results.iter().map(|result| result.status).collect();
+        assert_eq!(statuses, [SnapshotStatus::Corrupted, SnapshotStatus::Modified, SnapshotStatus::Missing, SnapshotStatus::New]);
+        assert_eq!(report.results[0].reasons, ["Entry 'scene.funscript' is unreadable"]);
+        assert_eq!(report.results[1].reasons, ["Entry 'metadata.json' changed"]);
+        assert!(report.has_changes());
+
+        // The corrupted file keeps its record, the edited one is recorded again
+        let summary = take_snapshot(&db_client, &dir, HashAlgorithm::Xxh3, true, &scheduler, &NoProgress).await.unwrap();
+        assert_eq!(summary, SnapshotSummary { recorded: 2, unchanged: 1, pruned: 1, ..Default::default() });
+        let report = verify_snapshot(&db_client, &dir, &scheduler, &NoProgress).await.unwrap();
+        assert_eq!((report.intact, report.corrupted), (2, 1));
+
+        db_client.pool.close().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,200 @@
+use serde::Serialize;
+
+/// A site creators link to, recognized from the host of a social URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SocialPlatform {
+    Patreon,
+    Twitter,
+    Pixiv,
+    Fanbox,
+    Fantia,
+    OnlyFans,
+    Fansly,
+    YouTube,
+    Reddit,
+    EroScripts,
+    GitHub,
+    Bluesky,
+    Instagram,
+    KoFi,
+    SubscribeStar,
+    Boosty,
+}
+
+impl SocialPlatform {
+    /// The name shown next to a social, and stored with it in the database.
+    pub fn label(&self) -> &'static str {
+        match self {
+            SocialPlatform::Patreon => "Patreon",
+            SocialPlatform::Twitter => "Twitter/X",
+            SocialPlatform::Pixiv => "Pixiv",
+            SocialPlatform::Fanbox => "Fanbox",
+            SocialPlatform::Fantia => "Fantia",
+            SocialPlatform::OnlyFans => "OnlyFans",
+            SocialPlatform::Fansly => "Fansly",
+            SocialPlatform::YouTube => "YouTube",
+            SocialPlatform::Reddit => "Reddit",
+            SocialPlatform::EroScripts => "EroScripts",
+            SocialPlatform::GitHub => "GitHub",
+            SocialPlatform::Bluesky => "Bluesky",
+            SocialPlatform::Instagram => "Instagram",
+            SocialPlatform::KoFi => "Ko-fi",
+            SocialPlatform::SubscribeStar => "SubscribeStar",
+            SocialPlatform::Boosty => "Boosty",
+        }
+    }
+
+    /// The platform of a (lowercase, port-less) host and the host its URLs are normalized to.
+    fn from_host(host: &str) -> Option<(SocialPlatform, String)> {
+        let host = host.strip_prefix("www.").unwrap_or(host);
+        let canonical = |platform: SocialPlatform, canonical: &str| Some((platform, canonical.to_string()));
+        match host {
+            "patreon.com" => canonical(SocialPlatform::Patreon, "patreon.com"),
+            "twitter.com" | "mobile.twitter.com" | "x.com" | "mobile.x.com" | "fxtwitter.com" | "vxtwitter.com" | "fixupx.com" => canonical(SocialPlatform::Twitter, "x.com"),
+            "pixiv.net" | "touch.pixiv.net" => canonical(SocialPlatform::Pixiv, "www.pixiv.net"),
+            "fantia.jp" => canonical(SocialPlatform::Fantia, "fantia.jp"),
+            "onlyfans.com" => canonical(SocialPlatform::OnlyFans, "onlyfans.com"),
+            "fansly.com" => canonical(SocialPlatform::Fansly, "fansly.com"),
+            "youtube.com" | "m.youtube.com" => canonical(SocialPlatform::YouTube, "www.youtube.com"),
+            "youtu.be" => canonical(SocialPlatform::YouTube, "youtu.be"),
+            "reddit.com" | "old.reddit.com" | "new.reddit.com" | "m.reddit.com" => canonical(SocialPlatform::Reddit, "www.reddit.com"),
+            "discuss.eroscripts.com" => canonical(SocialPlatform::EroScripts, "discuss.eroscripts.com"),
+            "github.com" => canonical(SocialPlatform::GitHub, "github.com"),
+            "bsky.app" => canonical(SocialPlatform::Bluesky, "bsky.app"),
+            "instagram.com" => canonical(SocialPlatform::Instagram, "www.instagram.com"),
+            "ko-fi.com" => canonical(SocialPlatform::KoFi, "ko-fi.com"),
+            "subscribestar.com" | "subscribestar.adult" => canonical(SocialPlatform::SubscribeStar, host),
+            "boosty.to" => canonical(SocialPlatform::Boosty, "boosty.to"),
+            // Every creator gets their own subdomain
+            host if host.ends_with(".fanbox.cc") => canonical(SocialPlatform::Fanbox, host),
+            _ => None,
+        }
+    }
+
+    /// Whether the handles in this platform's URLs ignore case, so `x.com/Name` and `x.com/name` are the same page.
+    fn case_insensitive_paths(&self) -> bool {
+        matches!(self, SocialPlatform::Twitter | SocialPlatform::Reddit | SocialPlatform::GitHub | SocialPlatform::Instagram)
+    }
+}
+
+/// A social URL in canonical form, together with the platform it links to if it is a known one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Social {
+    pub url: String,
+    pub platform: Option<SocialPlatform>,
+}
+
+impl Social {
+    /// Normalize `url`: known platforms get `https`, their canonical host (`x.com` for `mobile.twitter.com`), no query
+    /// or fragment and lowercase handles where the platform ignores case. Any URL loses its trailing slash and fragment
+    /// and gets a lowercase host, and a scheme if it has none. Text that isn't a web URL (e.g. `@name`) is only trimmed.
+    pub fn parse(url: &str) -> Social {
+        let url = url.trim();
+        let (scheme, rest) = match url.split_once("://") {
+            Some((scheme, rest)) => (scheme.to_ascii_lowercase(), rest),
+            None => ("https".to_string(), url),
+        };
+        let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        let (authority, rest) = rest.split_at(authority_end);
+        // Only web URLs, or bare `host/path` text that reads like one, are taken apart
+        if !matches!(scheme.as_str(), "http" | "https") || !authority.contains('.') || authority.contains('@') {
+            return Social { url: url.to_string(), platform: None };
+        }
+
+        let host = authority.trim_end_matches('.').to_ascii_lowercase();
+        let host = host.strip_suffix(":443").or_else(|| host.strip_suffix(":80")).unwrap_or(&host);
+        let (rest, _fragment) = rest.split_once('#').unwrap_or((rest, ""));
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let mut segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+
+        let Some((platform, host)) = SocialPlatform::from_host(host) else {
+            let mut url = format!("{}://{}", scheme, host);
+            segments.iter().for_each(|segment| url.push_str(&format!("/{}", segment)));
+            if !query.is_empty() {
+                url.push_str(&format!("?{}", query));
+            }
+            return Social { url, platform: None };
+        };
+
+        let mut query = None;
+        match platform {
+            // Profile pages are the same in every language, and the legacy member page is a profile too
+            SocialPlatform::Pixiv => {
+                if segments.len() > 1 && segments[0].len() == 2 && matches!(segments[1], "users" | "artworks") {
+                    segments.remove(0);
+                }
+                if segments == ["member.php"] && let Some(id) = query_param(rest, "id") {
+                    segments = vec!["users", id];
+                }
+            },
+            SocialPlatform::Reddit if segments.first() == Some(&"u") => segments[0] = "user",
+            SocialPlatform::YouTube if segments == ["watch"] => query = query_param(rest, "v").map(|video| format!("v={}", video)),
+            _ => {},
+        }
+
+        let mut url = format!("https://{}", host);
+        for segment in segments {
+            match platform.case_insensitive_paths() {
+                true => url.push_str(&format!("/{}", segment.to_lowercase())),
+                false => url.push_str(&format!("/{}", segment)),
+            }
+        }
+        if let Some(query) = query {
+            url.push_str(&format!("?{}", query));
+        }
+
+        Social { url, platform: Some(platform) }
+    }
+}
+
+fn query_param<'a>(rest: &'a str, name: &str) -> Option<&'a str> {
+    let (_, query) = rest.split_once('?')?;
+    query.split('&').find_map(|pair| pair.strip_prefix(name)?.strip_prefix('=')).filter(|value| !value.is_empty())
+}
+
+/// The canonical form of `url`, see [`Social::parse`].
+pub fn normalize_social(url: &str) -> String {
+    Social::parse(url).url
+}
+
+/// Normalize `socials`, dropping empty ones and those that turn out to be the same page as an earlier one.
+pub fn normalize_socials<S: AsRef<str>>(socials: impl IntoIterator<Item = S>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for social in socials {
+        let social = normalize_social(social.as_ref());
+        if !social.is_empty() && !normalized.contains(&social) {
+            normalized.push(social);
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_socials() {
+        let parse = |url: &str| {
+            let social = Social::parse(url);
+            (social.url, social.platform)
+        };
+
+        assert_eq!(parse("https://mobile.twitter.com/SomeCreator/"), ("https://x.com/somecreator".to_string(), Some(SocialPlatform::Twitter)));
+        assert_eq!(parse("x.com/somecreator?s=20"), ("https://x.com/somecreator".to_string(), Some(SocialPlatform::Twitter)));
+        assert_eq!(parse("http://www.patreon.com/SomeCreator#posts"), ("https://patreon.com/SomeCreator".to_string(), Some(SocialPlatform::Patreon)));
+        assert_eq!(parse("https://www.pixiv.net/en/users/123"), ("https://www.pixiv.net/users/123".to_string(), Some(SocialPlatform::Pixiv)));
+        assert_eq!(parse("https://pixiv.net/member.php?id=123"), ("https://www.pixiv.net/users/123".to_string(), Some(SocialPlatform::Pixiv)));
+        assert_eq!(parse("https://creator.fanbox.cc/"), ("https://creator.fanbox.cc".to_string(), Some(SocialPlatform::Fanbox)));
+        assert_eq!(parse("https://old.reddit.com/u/Someone"), ("https://www.reddit.com/user/someone".to_string(), Some(SocialPlatform::Reddit)));
+        assert_eq!(parse("https://m.youtube.com/watch?v=abc&t=10"), ("https://www.youtube.com/watch?v=abc".to_string(), Some(SocialPlatform::YouTube)));
+        assert_eq!(parse("HTTP://Example.COM:80/Page/?a=1"), ("http://example.com/Page?a=1".to_string(), None));
+        assert_eq!(parse(" @somecreator "), ("@somecreator".to_string(), None));
+        assert_eq!(parse("mailto:someone@example.com"), ("mailto:someone@example.com".to_string(), None));
+
+        let socials = normalize_socials(["twitter.com/SomeCreator", "https://x.com/somecreator/", "", "https://patreon.com/somecreator"]);
+        assert_eq!(socials, ["https://x.com/somecreator", "https://patreon.com/somecreator"]);
+    }
+}
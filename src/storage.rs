@@ -0,0 +1,143 @@
+use std::{fs::File, io::{Read, Seek, Write}, path::{Path, PathBuf}};
+
+use crate::{file_util, fsv::{ExtractOptions, FsvContainer, FsvError, FsvExtractError, FsvInfo, FsvValidationError, InfoOptions, NameMatching, ValidateOptions, ValidationReport}, hash_cache::mtime_stamp};
+
+/// An FSV found in a storage backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredFsv {
+    /// Key of the archive within the storage, as passed to `Storage::open`
+    pub key: String,
+    pub size: u64,
+    /// Changes whenever the archive does (the mtime for files, a hash of the ETag for objects)
+    pub stamp: i64,
+}
+
+/// Where FSV archives are kept: a local directory or, with the `s3` feature, an object storage bucket.
+/// Readers only fetch what is read, so validating or inspecting a stored archive doesn't download its videos.
+pub trait Storage {
+    type Reader: Read + Seek;
+
+    /// Every FSV in the storage, sorted by key.
+    fn list(&self) -> std::io::Result<Vec<StoredFsv>>;
+
+    fn open(&self, key: &str) -> std::io::Result<Self::Reader>;
+
+    /// Where `key` lives, e.g. `/srv/fsv/a.fsv` or `s3://bucket/a.fsv`. Used as the library index key of the archive.
+    fn location(&self, key: &str) -> String;
+
+    /// Common start of the locations of everything `list` returns.
+    fn root(&self) -> String;
+}
+
+/// FSV files in a directory and its subdirectories, keyed by their `/` separated path relative to it.
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: &Path) -> std::io::Result<Self> {
+        Ok(LocalStorage { root: std::fs::canonicalize(root)? })
+    }
+}
+
+impl Storage for LocalStorage {
+    type Reader = File;
+
+    fn list(&self) -> std::io::Result<Vec<StoredFsv>> {
+        let mut archives = Vec::new();
+        for path in file_util::find_files(&self.root, |path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("fsv")))? {
+            let file_metadata = std::fs::metadata(&path)?;
+            let key = path.strip_prefix(&self.root).unwrap_or(&path).components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            archives.push(StoredFsv { key, size: file_metadata.len(), stamp: mtime_stamp(&file_metadata).unwrap_or(0) });
+        }
+
+        archives.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(archives)
+    }
+
+    fn open(&self, key: &str) -> std::io::Result<File> {
+        File::open(self.root.join(key))
+    }
+
+    fn location(&self, key: &str) -> String {
+        self.root.join(key).to_string_lossy().to_string()
+    }
+
+    fn root(&self) -> String {
+        self.root.to_string_lossy().to_string()
+    }
+}
+
+/// Validate a stored FSV and collect every problem, like `fsv::validate_fsv_report`.
+pub fn validate_stored(storage: &impl Storage, key: &str, options: &ValidateOptions) -> Result<ValidationReport, FsvValidationError> {
+    let mut container = FsvContainer::from_reader(storage.open(key)?)?;
+    container.validate_report_with(options)
+}
+
+/// Info of a stored FSV, like `fsv::get_fsv_info_with_options`. An empty title falls back to the key's file stem.
+pub fn stored_info(storage: &impl Storage, key: &str, options: &InfoOptions) -> Result<FsvInfo, FsvError> {
+    let mut container = FsvContainer::from_reader(storage.open(key)?)?;
+    let mut info = container.info(options)?;
+    if info.title.trim().is_empty() {
+        info.title = key_stem(key).to_string();
+    }
+
+    Ok(info)
+}
+
+/// Extract a stored FSV into a subdirectory of `output_dir`, like `fsv::extract_fsv`.
+pub fn extract_stored(storage: &impl Storage, key: &str, output_dir: &Path, options: &ExtractOptions) -> Result<(), FsvExtractError> {
+    let mut container = FsvContainer::from_reader(storage.open(key)?)?;
+    container.extract(output_dir, key_stem(key), options)
+}
+
+/// Stream a single entry of a stored FSV into `writer`, like `fsv::copy_fsv_entry`.
+pub fn copy_stored_entry(storage: &impl Storage, key: &str, entry_name: &str, name_matching: NameMatching, writer: &mut impl Write) -> Result<u64, FsvError> {
+    let mut container = FsvContainer::from_reader(storage.open(key)?)?;
+    let entry_name = container.resolve_entry(entry_name, name_matching).unwrap_or_else(|| entry_name.to_string());
+    container.copy_entry(&entry_name, writer)
+}
+
+fn key_stem(key: &str) -> &str {
+    let file_name = key.rsplit('/').next().unwrap_or(key);
+    match file_name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => file_name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsv::{ExtractOnly, FsvBuilder, FsvState};
+
+    const VIDEO: &[u8] = b"\0\0\0\x18ftypisom";
+    const SCRIPT: &[u8] = br#"{"actions":[{"at":0,"pos":0},{"at":1000,"pos":100}],"inverted":false,"range":100,"version":"1.0"}"#;
+
+    #[test]
+    fn test_local_storage() {
+        let dir = std::env::temp_dir().join(format!("fsv-storage-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/scene.fsv"), FsvBuilder::new("").video("video.mp4", VIDEO, 1000).script("video.funscript", SCRIPT, 1000).to_bytes().unwrap()).unwrap();
+        std::fs::write(dir.join("notes.txt"), b"").unwrap();
+
+        let storage = LocalStorage::new(&dir).unwrap();
+        let archives = storage.list().unwrap();
+        assert_eq!(archives.iter().map(|archive| archive.key.as_str()).collect::<Vec<_>>(), ["sub/scene.fsv"]);
+        assert!(storage.location("sub/scene.fsv").starts_with(&storage.root()));
+
+        assert!(matches!(validate_stored(&storage, "sub/scene.fsv", &ValidateOptions::default()).unwrap().state, FsvState::Valid));
+        assert_eq!(stored_info(&storage, "sub/scene.fsv", &InfoOptions::default()).unwrap().title, "scene");
+
+        let output_dir = dir.join("out");
+        extract_stored(&storage, "sub/scene.fsv", &output_dir, &ExtractOptions { only: Some(ExtractOnly::Metadata), ..Default::default() }).unwrap();
+        let entries: Vec<_> = std::fs::read_dir(output_dir.join("scene")).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(entries, ["metadata.json"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
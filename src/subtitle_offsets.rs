@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{content::{self, SubtitleFormat}, extensions, metadata::FsvMetadata};
+
+/// Metadata field holding the `fsv.subtitle-offsets` extension data.
+pub const SUBTITLE_OFFSETS_FIELD: &str = "subtitle_offsets";
+
+/// The offset (subtitle time minus video time, in ms) of a subtitle track when shown over one video format,
+/// for tracks timed against another encode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubtitleOffset {
+    /// Name of the subtitle track
+    pub subtitle: String,
+    /// Name of the video format
+    pub video: String,
+    pub offset_ms: i64,
+    /// Fields of other tools, kept when the offset is changed
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl SubtitleOffset {
+    pub fn new(subtitle: String, video: String, offset_ms: i64) -> Self {
+        SubtitleOffset { subtitle, video, offset_ms, extra: HashMap::new() }
+    }
+}
+
+/// Read the subtitle offsets declared in metadata. A missing field means every track is timed against every video format.
+pub fn subtitle_offsets_from_metadata(metadata: &FsvMetadata) -> Result<Vec<SubtitleOffset>, serde_json::Error> {
+    metadata.extension_data(SUBTITLE_OFFSETS_FIELD)
+}
+
+/// Store `offsets`, declaring the extension while there are any and dropping both field and declaration once there are none.
+pub fn set_subtitle_offsets(metadata: &mut FsvMetadata, offsets: &[SubtitleOffset]) -> serde_json::Result<()> {
+    metadata.set_extension_data(extensions::SUBTITLE_OFFSETS_EXTENSION, SUBTITLE_OFFSETS_FIELD, (!offsets.is_empty()).then_some(offsets))
+}
+
+/// The offset set for `subtitle` against `video`, if any. Malformed extension data counts as none.
+pub fn subtitle_offset(metadata: &FsvMetadata, subtitle: &str, video: &str) -> Option<i64> {
+    subtitle_offsets_from_metadata(metadata).ok()?.into_iter().find(|offset| offset.subtitle == subtitle && offset.video == video).map(|offset| offset.offset_ms)
+}
+
+/// Shift the cues of an SRT, WebVTT or ASS/SSA subtitle file so it shows in sync with a video it has `offset_ms` of offset against.
+/// Cues ending before the start of the video are dropped and those starting before it start with it; everything else is kept as is.
+pub fn shift_subtitle(data: &[u8], offset_ms: i64) -> Result<Vec<u8>, String> {
+    let format = content::validate_subtitle(data)?;
+    let text = std::str::from_utf8(data).map_err(|_| "Subtitle is not valid UTF-8".to_string())?;
+    let shifted = match format {
+        SubtitleFormat::Srt | SubtitleFormat::Vtt => shift_cue_blocks(text, format, offset_ms),
+        SubtitleFormat::Ass => shift_ass_events(text, offset_ms),
+    };
+
+    Ok(shifted.into_bytes())
+}
+
+/// SRT and WebVTT cues are blocks separated by blank lines, timed by a `start --> end` line.
+fn shift_cue_blocks(text: &str, format: SubtitleFormat, offset_ms: i64) -> String {
+    let mut shifted = String::with_capacity(text.len());
+    let mut block = Vec::new();
+    for line in text.split_inclusive('\n') {
+        block.push(line);
+        if line.trim().is_empty() {
+            shift_cue_block(&block, format, offset_ms, &mut shifted);
+            block.clear();
+        }
+    }
+    shift_cue_block(&block, format, offset_ms, &mut shifted);
+
+    shifted
+}
+
+fn shift_cue_block(block: &[&str], format: SubtitleFormat, offset_ms: i64, shifted: &mut String) {
+    let mut lines = Vec::with_capacity(block.len());
+    for line in block {
+        let Some((start, rest)) = line.split_once("-->") else {
+            lines.push(line.to_string());
+            continue;
+        };
+
+        let end_len = rest.trim_start().find(char::is_whitespace).unwrap_or(rest.trim_start().len());
+        let (end, settings) = rest.trim_start().split_at(end_len);
+        let (Some(start_ms), Some(end_ms)) = (parse_cue_time(start.trim()), parse_cue_time(end)) else {
+            lines.push(line.to_string());
+            continue;
+        };
+
+        let end_ms = end_ms - offset_ms;
+        if end_ms < 0 {
+            // A cue before the video starts, leave out the whole block
+            return;
+        }
+
+        let start_ms = (start_ms - offset_ms).max(0);
+        lines.push(format!("{} --> {}{}", format_cue_time(start_ms, format), format_cue_time(end_ms, format), settings));
+    }
+
+    lines.iter().for_each(|line| shifted.push_str(line));
+}
+
+/// `HH:MM:SS,mmm` (SRT) or `[HH:]MM:SS.mmm` (WebVTT) in ms.
+fn parse_cue_time(time: &str) -> Option<i64> {
+    let (clock, millis) = time.rsplit_once([',', '.'])?;
+    let mut ms = millis.parse::<i64>().ok()?;
+    for (part, unit) in clock.rsplit(':').zip([1000, 60_000, 3_600_000]) {
+        ms += part.parse::<i64>().ok()? * unit;
+    }
+
+    Some(ms)
+}
+
+fn format_cue_time(ms: i64, format: SubtitleFormat) -> String {
+    let separator = if format == SubtitleFormat::Srt { ',' } else { '.' };
+    format!("{:02}:{:02}:{:02}{}{:03}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, separator, ms % 1000)
+}
+
+/// ASS/SSA events are `Dialogue:` lines whose start and end are at the positions the `[Events]` section's `Format:` line gives.
+fn shift_ass_events(text: &str, offset_ms: i64) -> String {
+    // The order every ASS/SSA writer uses, for files without a format line
+    let (mut start_field, mut end_field) = (1, 2);
+    let mut shifted = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        if let Some(fields) = line.trim().strip_prefix("Format:") {
+            let fields: Vec<&str> = fields.split(',').map(str::trim).collect();
+            if let (Some(start), Some(end)) = (fields.iter().position(|field| *field == "Start"), fields.iter().position(|field| *field == "End")) {
+                (start_field, end_field) = (start, end);
+            }
+        }
+
+        let Some(event) = line.strip_prefix("Dialogue:") else {
+            shifted.push_str(line);
+            continue;
+        };
+
+        // The text is the last field and may contain commas
+        let mut fields: Vec<String> = event.splitn(start_field.max(end_field) + 2, ',').map(str::to_string).collect();
+        let (Some(start_ms), Some(end_ms)) = (fields.get(start_field).and_then(|time| parse_ass_time(time)), fields.get(end_field).and_then(|time| parse_ass_time(time))) else {
+            shifted.push_str(line);
+            continue;
+        };
+
+        let end_ms = end_ms - offset_ms;
+        if end_ms < 0 {
+            continue;
+        }
+
+        fields[start_field] = format_ass_time((start_ms - offset_ms).max(0));
+        fields[end_field] = format_ass_time(end_ms);
+        shifted.push_str("Dialogue:");
+        shifted.push_str(&fields.join(","));
+    }
+
+    shifted
+}
+
+/// `H:MM:SS.cc` in ms.
+fn parse_ass_time(time: &str) -> Option<i64> {
+    let (clock, centis) = time.trim().rsplit_once('.')?;
+    let mut ms = centis.parse::<i64>().ok()? * 10;
+    for (part, unit) in clock.rsplit(':').zip([1000, 60_000, 3_600_000]) {
+        ms += part.parse::<i64>().ok()? * unit;
+    }
+
+    Some(ms)
+}
+
+fn format_ass_time(ms: i64) -> String {
+    format!("{}:{:02}:{:02}.{:02}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000 / 10)
+}
+
+pub fn validate_subtitle_offsets(metadata: &FsvMetadata, _entry_names: &[&str]) -> Vec<String> {
+    let Some(value) = metadata.extra.get(SUBTITLE_OFFSETS_FIELD) else {
+        return vec![format!("Missing '{}' field", SUBTITLE_OFFSETS_FIELD)];
+    };
+
+    let Value::Array(items) = value else {
+        return vec![format!("'{}' must be an array", SUBTITLE_OFFSETS_FIELD)];
+    };
+
+    let mut issues = Vec::new();
+    let mut seen = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        let offset = match serde_json::from_value::<SubtitleOffset>(item.clone()) {
+            Ok(offset) => offset,
+            Err(err) => {
+                issues.push(format!("Subtitle offset {} is malformed: {}", i, err));
+                continue;
+            },
+        };
+
+        if !metadata.subtitle_tracks.iter().any(|track| track.name == offset.subtitle) {
+            issues.push(format!("Subtitle offset track '{}' is not a subtitle track", offset.subtitle));
+        }
+
+        if !metadata.video_formats.iter().any(|format| format.name == offset.video) {
+            issues.push(format!("Subtitle offset video '{}' is not a video format", offset.video));
+        }
+
+        if seen.contains(&(offset.subtitle.clone(), offset.video.clone())) {
+            issues.push(format!("Subtitle offset for '{}' against '{}' is set more than once", offset.subtitle, offset.video));
+        } else {
+            seen.push((offset.subtitle, offset.video));
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{duration::DurationMs, metadata::{SubtitleTrack, VideoFormat}, semver::Version};
+
+    #[test]
+    fn test_subtitle_offsets() {
+        let mut metadata = FsvMetadata::new(Version::new(1, 0, 0));
+        metadata.add_video_format(VideoFormat::new("scene.mp4".to_string(), String::new(), DurationMs::from_millis(1000), String::new()));
+        metadata.add_subtitle_track(SubtitleTrack::new("scene.en.srt".to_string(), "en".to_string(), String::new(), String::new()));
+        let offset = SubtitleOffset::new("scene.en.srt".to_string(), "scene.mp4".to_string(), 1500);
+        set_subtitle_offsets(&mut metadata, std::slice::from_ref(&offset)).unwrap();
+        assert_eq!(metadata.extensions, [extensions::SUBTITLE_OFFSETS_EXTENSION]);
+        assert_eq!(subtitle_offset(&metadata, "scene.en.srt", "scene.mp4"), Some(1500));
+        assert!(validate_subtitle_offsets(&metadata, &[]).is_empty());
+        set_subtitle_offsets(&mut metadata, &[offset.clone(), SubtitleOffset { subtitle: "missing.srt".to_string(), ..offset }]).unwrap();
+        assert_eq!(validate_subtitle_offsets(&metadata, &[]).len(), 1);
+        set_subtitle_offsets(&mut metadata, &[]).unwrap();
+        assert!(metadata.extensions.is_empty() && !metadata.extra.contains_key(SUBTITLE_OFFSETS_FIELD));
+
+        let srt = "1\r\n00:00:00,500 --> 00:00:00,900\r\nGone\r\n\r\n2\r\n00:00:00,800 --> 00:00:02,000\r\nClipped\r\n\r\n3\r\n01:00:01,000 --> 01:00:02,250\r\nLate\r\n";
+        let shifted = String::from_utf8(shift_subtitle(srt.as_bytes(), 1000).unwrap()).unwrap();
+        assert_eq!(shifted, "2\r\n00:00:00,000 --> 00:00:01,000\r\nClipped\r\n\r\n3\r\n01:00:00,000 --> 01:00:01,250\r\nLate\r\n");
+
+        let vtt = "WEBVTT\n\n00:01.000 --> 00:02.000 align:start\nHello\n";
+        let shifted = String::from_utf8(shift_subtitle(vtt.as_bytes(), -500).unwrap()).unwrap();
+        assert_eq!(shifted, "WEBVTT\n\n00:00:01.500 --> 00:00:02.500 align:start\nHello\n");
+
+        let ass = "[Script Info]\nTitle: x\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,0:00:00.50,0:00:00.90,Default,,0,0,0,,Gone\nDialogue: 0,0:00:03.00,0:00:04.50,Default,,0,0,0,,Hello, there\n";
+        let shifted = String::from_utf8(shift_subtitle(ass.as_bytes(), 1000).unwrap()).unwrap();
+        assert!(shifted.ends_with("[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,0:00:02.00,0:00:03.50,Default,,0,0,0,,Hello, there\n"));
+
+        assert!(shift_subtitle(b"just some text", 1000).is_err());
+    }
+}
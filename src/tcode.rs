@@ -0,0 +1,195 @@
+use std::{fs::File, path::Path};
+
+use tracing::warn;
+
+use crate::{fsv::FsvContainer, import, offsets, playback::{self, PlaybackError, ScriptTimeline}};
+
+/// TCode axes that funscript axis files map onto (TCode v0.3 naming).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TCodeAxis {
+    Stroke,
+    Surge,
+    Sway,
+    Twist,
+    Roll,
+    Pitch,
+    Vibrate,
+    Valve,
+    Suck,
+    Lube,
+}
+
+impl TCodeAxis {
+    pub fn code(&self) -> &str {
+        match self {
+            TCodeAxis::Stroke => "L0",
+            TCodeAxis::Surge => "L1",
+            TCodeAxis::Sway => "L2",
+            TCodeAxis::Twist => "R0",
+            TCodeAxis::Roll => "R1",
+            TCodeAxis::Pitch => "R2",
+            TCodeAxis::Vibrate => "V0",
+            TCodeAxis::Valve => "A0",
+            TCodeAxis::Suck => "A1",
+            TCodeAxis::Lube => "A2",
+        }
+    }
+
+    /// Axis for a funscript axis file suffix (see `fsv::AXES`), e.g. `roll` for `scene.roll.funscript`.
+    pub fn from_script_axis(axis: &str) -> Option<Self> {
+        match axis {
+            "surge" => Some(TCodeAxis::Surge),
+            "sway" => Some(TCodeAxis::Sway),
+            "twist" => Some(TCodeAxis::Twist),
+            "roll" => Some(TCodeAxis::Roll),
+            "pitch" => Some(TCodeAxis::Pitch),
+            "vib" => Some(TCodeAxis::Vibrate),
+            "valve" => Some(TCodeAxis::Valve),
+            "suck" | "suckManual" => Some(TCodeAxis::Suck),
+            "lube" => Some(TCodeAxis::Lube),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for TCodeAxis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// A move towards `position` (0.0..=1.0) over `duration_ms`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisMove {
+    pub axis: TCodeAxis,
+    pub position: f64,
+    pub duration_ms: u64,
+}
+
+/// One TCode line moving all given axes at once, e.g. `L0500I250 R1999I100`
+pub fn format_command(moves: &[AxisMove]) -> String {
+    let mut commands: Vec<String> = moves.iter()
+        .map(|m| format!("{}{:03}I{}", m.axis.code(), (m.position.clamp(0.0, 1.0) * 999.0).round() as u32, m.duration_ms))
+        .collect();
+    commands.sort();
+
+    format!("{}\n", commands.join(" "))
+}
+
+/// Stops all motion (TCode device command)
+pub const STOP_COMMAND: &str = "DSTOP\n";
+
+struct AxisState {
+    axis: TCodeAxis,
+    timeline: ScriptTimeline,
+    last_sent: Option<usize>,
+}
+
+/// Follows several axis timelines against a playback clock, emitting a move for an axis whenever its next action changes.
+pub struct AxisScheduler {
+    axes: Vec<AxisState>,
+}
+
+impl AxisScheduler {
+    pub fn new(axes: Vec<(TCodeAxis, ScriptTimeline)>) -> Self {
+        AxisScheduler { axes: axes.into_iter().map(|(axis, timeline)| AxisState { axis, timeline, last_sent: None }).collect() }
+    }
+
+    /// Moves that have to be sent at video time `now`: one per axis whose target action is new.
+    pub fn update(&mut self, now: u64) -> Vec<AxisMove> {
+        let mut moves = Vec::new();
+        for state in &mut self.axes {
+            if let Some((index, action)) = state.timeline.next_action(now) && state.last_sent != Some(index) {
+                moves.push(AxisMove { axis: state.axis, position: action.position, duration_ms: action.video_time - now });
+                state.last_sent = Some(index);
+            }
+        }
+
+        moves
+    }
+
+    /// Forget what was sent, e.g. after a pause or seek, so the current targets are sent again. Returns true if anything had been sent.
+    pub fn reset(&mut self) -> bool {
+        let sent = self.axes.iter().any(|state| state.last_sent.is_some());
+        for state in &mut self.axes {
+            state.last_sent = None;
+        }
+
+        sent
+    }
+
+    /// Time until the nearest upcoming action on any axis, or `None` once every axis has finished.
+    pub fn time_to_next(&self, now: u64) -> Option<u64> {
+        self.axes.iter().filter_map(|state| state.timeline.next_action(now)).map(|(_, action)| action.video_time - now).min()
+    }
+}
+
+/// Load a script variant as the stroke axis, plus its axis scripts (`<stem>.<axis>.funscript`) present in the archive.
+/// Axis scripts listed as script variants use their own offset against `video_name`, others share the main script's (see `offsets::start_offset`).
+pub fn load_axis_timelines(path: &Path, script_name: Option<&str>, video_name: Option<&str>) -> Result<Vec<(TCodeAxis, ScriptTimeline)>, PlaybackError> {
+    let mut container = FsvContainer::from_reader(File::open(path)?)?;
+    let metadata = container.metadata()?;
+    let main = match script_name {
+        Some(name) => metadata.script_variants.iter().find(|variant| variant.name == name).ok_or_else(|| PlaybackError::ScriptNotFound(name.to_string()))?,
+        None => metadata.script_variants.first().ok_or(PlaybackError::NoScripts)?,
+    };
+
+    let main_offset = offsets::start_offset(&metadata, &main.name, video_name).unwrap_or(main.start_offset);
+    let mut axes = vec![(TCodeAxis::Stroke, playback::read_timeline(&mut container, &main.name, main_offset)?)];
+    let Some((stem, None)) = import::split_script_name(&main.name) else {
+        return Ok(axes);
+    };
+
+    let mut axis_files: Vec<(TCodeAxis, String)> = container.entry_names()
+        .filter_map(|name| match import::split_script_name(name) {
+            Some((entry_stem, Some(axis))) if entry_stem == stem => TCodeAxis::from_script_axis(axis).map(|axis| (axis, name.to_string())),
+            _ => None,
+        })
+        .collect();
+    axis_files.sort_by(|a, b| a.1.cmp(&b.1));
+
+    for axis in &main.additional_axes {
+        if !axis_files.iter().any(|(_, name)| import::split_script_name(name).and_then(|(_, a)| a) == Some(axis.as_str())) {
+            warn!(operation = "play", archive = %path.display(), item = %main.name, axis = %axis, outcome = "skipped", "Axis script listed in the script variant not found in archive");
+        }
+    }
+
+    for (axis, name) in axis_files {
+        if axes.iter().any(|(existing, _)| *existing == axis) {
+            warn!(operation = "play", archive = %path.display(), item = %name, axis = %axis, outcome = "skipped", "Axis already has a script");
+            continue;
+        }
+
+        let start_offset = offsets::start_offset(&metadata, &name, video_name).unwrap_or(main_offset);
+        axes.push((axis, playback::read_timeline(&mut container, &name, start_offset)?));
+    }
+
+    Ok(axes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::funscript::Funscript;
+
+    #[test]
+    fn test_axis_scheduler() {
+        let stroke: Funscript = serde_json::from_str(r#"{ "actions": [{ "at": 100, "pos": 100 }, { "at": 300, "pos": 0 }], "inverted": false, "range": 100, "version": "1.0" }"#).unwrap();
+        let roll: Funscript = serde_json::from_str(r#"{ "actions": [{ "at": 200, "pos": 50 }], "inverted": false, "range": 100, "version": "1.0" }"#).unwrap();
+        let mut scheduler = AxisScheduler::new(vec![
+            (TCodeAxis::Stroke, ScriptTimeline::new("a.funscript", &stroke, 0)),
+            (TCodeAxis::Roll, ScriptTimeline::new("a.roll.funscript", &roll, 0)),
+        ]);
+
+        let moves = scheduler.update(0);
+        assert_eq!(format_command(&moves), "L0999I100 R1500I200\n");
+        assert!(scheduler.update(50).is_empty());
+        assert_eq!(format_command(&scheduler.update(150)), "L0000I150\n");
+        assert_eq!(scheduler.time_to_next(150), Some(50));
+
+        assert!(scheduler.reset());
+        assert_eq!(scheduler.update(150).len(), 2);
+        assert_eq!(scheduler.time_to_next(300), None);
+        assert_eq!(TCodeAxis::from_script_axis("suckManual"), Some(TCodeAxis::Suck));
+    }
+}
@@ -0,0 +1,175 @@
+use std::{io::Write, path::{Path, PathBuf}};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{checksum::HashAlgorithm, config::{Config, ConfigError}, fsv::{ArchiveCompression, CreateArgs}, naming::{self, NamingError}, transcode::TranscodeProfile};
+
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("Config error: {0}")]
+    Config(#[from] ConfigError),
+    #[error("No template named '{0}' in '{1}'")]
+    NotFound(String, PathBuf),
+    #[error("Naming error: {0}")]
+    Naming(#[from] NamingError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Template '{0}' encrypts archives, but no password was given ({1})")]
+    MissingPassword(String, String),
+}
+
+/// Defaults for `create`, e.g. for a series of similarly structured releases. Values given on the command line take precedence,
+/// except tags, which are added to the template's.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CreateTemplate {
+    pub tags: Vec<String>,
+    pub video_creator_key: Option<String>,
+    pub script_creator_key: Option<String>,
+    pub studio: Option<String>,
+    pub performers: Vec<String>,
+    pub compression: Option<ArchiveCompression>,
+    pub hash_algorithm: Option<HashAlgorithm>,
+    pub reproducible: bool,
+    pub transcode: Vec<TranscodeProfile>,
+    /// File name used when `create` is given a directory, e.g. `{studio} - {title}.fsv`.
+    /// Placeholders are `{title}`, `{studio}` and `{performers}` (comma separated).
+    pub naming: Option<String>,
+    pub encryption: Option<TemplateEncryption>,
+}
+
+/// AES-256 encryption of every entry but metadata.json. The password is never stored in the config.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemplateEncryption {
+    /// Environment variable holding the password. When it isn't set (or none is named), the password is asked for.
+    pub password_env: Option<String>,
+}
+
+impl TemplateEncryption {
+    /// The password from the environment, or else from a prompt unless `interactive` is off. `template` names the template in errors.
+    pub fn password(&self, template: &str, interactive: bool) -> Result<String, TemplateError> {
+        if let Some(password) = self.password_env.as_ref().and_then(|var| std::env::var(var).ok()).filter(|password| !password.is_empty()) {
+            return Ok(password);
+        }
+
+        let source = match &self.password_env {
+            Some(var) => format!("set ${}", var),
+            None => "name a password_env".to_string(),
+        };
+        if !interactive {
+            return Err(TemplateError::MissingPassword(template.to_string(), format!("{} or run interactively", source)));
+        }
+
+        eprint!("Password for archives made with template '{}': ", template);
+        std::io::stderr().flush()?;
+        let mut password = String::new();
+        std::io::stdin().read_line(&mut password)?;
+        match password.trim_end_matches(['\r', '\n']) {
+            "" => Err(TemplateError::MissingPassword(template.to_string(), "the password entered was empty".to_string())),
+            password => Ok(password.to_string()),
+        }
+    }
+}
+
+impl Config {
+    /// The template `name`, from the config read from `config_path`.
+    pub fn template(&self, config_path: &Path, name: &str) -> Result<&CreateTemplate, TemplateError> {
+        self.templates.get(name).ok_or_else(|| TemplateError::NotFound(name.to_string(), config_path.to_path_buf()))
+    }
+}
+
+impl CreateTemplate {
+    /// Fill in what `args` leaves unset from the template. Hash algorithm and compression always come from the template
+    /// (when it sets them), so explicit command line choices have to be applied afterwards.
+    /// The encryption password isn't filled in; it comes from `TemplateEncryption::password`.
+    /// If `args.path` is a directory and the template has a naming pattern, the archive is named by it inside that directory.
+    pub fn apply(&self, mut args: CreateArgs) -> Result<CreateArgs, TemplateError> {
+        args.tags = self.tags.iter().cloned().chain(args.tags).collect();
+        args.video_creator_key = args.video_creator_key.or_else(|| self.video_creator_key.clone());
+        args.script_creator_key = args.script_creator_key.or_else(|| self.script_creator_key.clone());
+        if args.studio.trim().is_empty() && let Some(studio) = &self.studio {
+            args.studio = studio.clone();
+        }
+
+        if args.performers.is_empty() {
+            args.performers = self.performers.clone();
+        }
+
+        if args.transcode.is_empty() {
+            args.transcode = self.transcode.clone();
+        }
+
+        args.reproducible |= self.reproducible;
+        if let Some(hash_algorithm) = self.hash_algorithm {
+            args.hash_algorithm = hash_algorithm;
+        }
+
+        if let Some(compression) = self.compression {
+            args.compression = compression;
+        }
+
+        if let Some(naming) = &self.naming && args.path.is_dir() {
+            let name = render_name(naming, &args.title, &args.studio, &args.performers)?;
+            args.path = args.path.join(name);
+        }
+
+        Ok(args)
+    }
+}
+
+/// File name from a naming pattern. The result is a single path component ending in `.fsv`.
+pub fn render_name(pattern: &str, title: &str, studio: &str, performers: &[String]) -> Result<String, NamingError> {
+    let mut name = naming::render(pattern, |placeholder| match placeholder {
+        "title" => Some(title.to_string()),
+        "studio" => Some(studio.to_string()),
+        "performers" => Some(performers.join(", ")),
+        _ => None,
+    })?;
+    if !name.to_lowercase().ends_with(".fsv") {
+        name.push_str(".fsv");
+    }
+
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_template() {
+        let config: Config = serde_json::from_str(r#"{
+            "templates": {
+                "vr-release": {
+                    "tags": ["vr"],
+                    "script_creator_key": "me",
+                    "studio": "Studio",
+                    "compression": "stored",
+                    "hash_algorithm": "xxh3",
+                    "naming": "{studio} - {title}"
+                }
+            }
+        }"#).unwrap();
+        let template = &config.templates["vr-release"];
+        let dir = std::env::temp_dir();
+        let args = CreateArgs::new(dir.clone(), "Scene: One".to_string(), vec!["pov".to_string()], None, None, None, Some("other".to_string()));
+        let args = template.apply(args).unwrap();
+        assert_eq!(args.path, dir.join("Studio - Scene_ One.fsv"));
+        assert_eq!(args.tags, ["vr", "pov"]);
+        assert_eq!(args.script_creator_key.as_deref(), Some("other"));
+        assert_eq!((args.compression, args.hash_algorithm), (ArchiveCompression::Stored, HashAlgorithm::Xxh3));
+
+        assert!(matches!(render_name("{date}", "", "", &[]), Err(NamingError::InvalidPattern(_, _))));
+        assert_eq!(template.encryption, None);
+
+        let config: Config = serde_json::from_str(r#"{ "templates": { "locked": { "encryption": { "password_env": "FSV_TEMPLATE_TEST_PASSWORD" } } } }"#).unwrap();
+        let encryption = config.templates["locked"].encryption.clone().unwrap();
+        assert!(matches!(encryption.password("locked", false), Err(TemplateError::MissingPassword(_, _))));
+        // SAFETY: no other test reads or writes this variable
+        unsafe { std::env::set_var("FSV_TEMPLATE_TEST_PASSWORD", "secret") };
+        assert_eq!(encryption.password("locked", false).unwrap(), "secret");
+        assert!(serde_json::from_str::<Config>(r#"{ "templates": { "locked": { "encryption": { "password": "secret" } } } }"#).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::{extensions, metadata::{FsvMetadata, normalize_language_tag}};
+
+/// Metadata field holding the `fsv.localized-titles` extension data.
+pub const LOCALIZED_TITLES_FIELD: &str = "titles";
+
+/// Read the localized titles declared in metadata, keyed by BCP-47 language tag. A missing field means there are none.
+pub fn localized_titles_from_metadata(metadata: &FsvMetadata) -> Result<BTreeMap<String, String>, serde_json::Error> {
+    metadata.extension_data(LOCALIZED_TITLES_FIELD)
+}
+
+/// Store `titles`, declaring the extension while there are any and dropping both field and declaration once there are none.
+pub fn set_localized_titles(metadata: &mut FsvMetadata, titles: &BTreeMap<String, String>) -> serde_json::Result<()> {
+    metadata.set_extension_data(extensions::LOCALIZED_TITLES_EXTENSION, LOCALIZED_TITLES_FIELD, (!titles.is_empty()).then_some(titles))
+}
+
+/// Set the title in `language` (normalized, see `metadata::normalize_language_tag`), or remove it if `title` is empty.
+/// Returns the normalized language tag.
+pub fn set_localized_title(metadata: &mut FsvMetadata, language: &str, title: &str) -> serde_json::Result<String> {
+    let language = normalize_language_tag(language);
+    let mut titles = localized_titles_from_metadata(metadata)?;
+    match title.trim() {
+        "" => titles.remove(&language),
+        title => titles.insert(language.clone(), title.to_string()),
+    };
+    set_localized_titles(metadata, &titles)?;
+
+    Ok(language)
+}
+
+/// The title to show for `language`: the one in exactly that language, else the first one in a more specific language
+/// (`ja` gets a `ja-JP` title), else the primary `title`.
+pub fn title_for<'a>(metadata: &'a FsvMetadata, titles: &'a BTreeMap<String, String>, language: &str) -> &'a str {
+    let language = normalize_language_tag(language).to_ascii_lowercase();
+    let matching = |exact: bool| titles.iter().find(|(tag, _)| {
+        let tag = tag.to_ascii_lowercase();
+        tag == language || (!exact && tag.strip_prefix(&language).is_some_and(|rest| rest.starts_with('-')))
+    });
+
+    matching(true).or_else(|| matching(false)).map_or(metadata.title.as_str(), |(_, title)| title.as_str())
+}
+
+pub fn validate_localized_titles(metadata: &FsvMetadata, _entry_names: &[&str]) -> Vec<String> {
+    let Some(value) = metadata.extra.get(LOCALIZED_TITLES_FIELD) else {
+        return vec![format!("Missing '{}' field", LOCALIZED_TITLES_FIELD)];
+    };
+
+    let Value::Object(titles) = value else {
+        return vec![format!("'{}' must be an object of language tags to titles", LOCALIZED_TITLES_FIELD)];
+    };
+
+    let mut issues = Vec::new();
+    for (language, title) in titles {
+        if normalize_language_tag(language).is_empty() {
+            issues.push("Localized title has an empty language tag".to_string());
+        }
+
+        match title {
+            Value::String(title) if title.trim().is_empty() => issues.push(format!("Title for '{}' is empty", language)),
+            Value::String(_) => (),
+            _ => issues.push(format!("Title for '{}' must be a string", language)),
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semver::Version;
+
+    #[test]
+    fn test_localized_titles() {
+        let mut metadata = FsvMetadata::new(Version::new(1, 0, 0));
+        metadata.title = "Summer".to_string();
+        assert_eq!(set_localized_title(&mut metadata, "JA_jp", " 夏 ").unwrap(), "ja-JP");
+        assert_eq!(metadata.extensions, [extensions::LOCALIZED_TITLES_EXTENSION]);
+        assert!(validate_localized_titles(&metadata, &[]).is_empty());
+
+        let titles = localized_titles_from_metadata(&metadata).unwrap();
+        assert_eq!(title_for(&metadata, &titles, "ja-JP"), "夏");
+        assert_eq!(title_for(&metadata, &titles, "ja"), "夏");
+        assert_eq!(title_for(&metadata, &titles, "en"), "Summer");
+
+        set_localized_title(&mut metadata, "ja-jp", "").unwrap();
+        assert!(metadata.extensions.is_empty() && !metadata.extra.contains_key(LOCALIZED_TITLES_FIELD));
+    }
+}
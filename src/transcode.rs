@@ -0,0 +1,228 @@
+use std::path::{Path, PathBuf};
+#[cfg(feature = "native")]
+use std::{ffi::OsString, process::Command};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use thiserror::Error;
+#[cfg(feature = "native")]
+use tracing::info;
+use tracing::warn;
+
+use crate::file_util::GetDurationError;
+
+#[derive(Debug, Error)]
+pub enum TranscodeError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Serde JSON error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+    #[error("FFmpeg error: {0}")]
+    Ffmpeg(String),
+    #[error("FFprobe error: {0}")]
+    Ffprobe(String),
+    #[error("No video stream found in: {0}")]
+    NoVideoStream(PathBuf),
+    #[error("Get duration error: {0}")]
+    GetDuration(#[from] GetDurationError),
+}
+
+/// Predefined ffmpeg encoding profiles for generating additional video formats.
+/// Profiles with a target height never upscale smaller sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+pub enum TranscodeProfile {
+    #[value(name = "h265-4k")]
+    #[serde(rename = "h265-4k")]
+    H265_4k,
+    #[value(name = "h264-1080p")]
+    #[serde(rename = "h264-1080p")]
+    H264_1080p,
+    #[value(name = "av1")]
+    #[serde(rename = "av1")]
+    Av1,
+}
+
+impl TranscodeProfile {
+    pub fn get_name(&self) -> &str {
+        match self {
+            TranscodeProfile::H265_4k => "h265-4k",
+            TranscodeProfile::H264_1080p => "h264-1080p",
+            TranscodeProfile::Av1 => "av1",
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            TranscodeProfile::H265_4k => "H.265 4K",
+            TranscodeProfile::H264_1080p => "H.264 1080p",
+            TranscodeProfile::Av1 => "AV1",
+        }
+    }
+
+    #[cfg(feature = "native")]
+    fn max_height(&self) -> Option<u32> {
+        match self {
+            TranscodeProfile::H265_4k => Some(2160),
+            TranscodeProfile::H264_1080p => Some(1080),
+            TranscodeProfile::Av1 => None,
+        }
+    }
+
+    fn extension(&self) -> &str {
+        match self {
+            TranscodeProfile::H265_4k | TranscodeProfile::H264_1080p => "mp4",
+            TranscodeProfile::Av1 => "mkv",
+        }
+    }
+
+    #[cfg(feature = "native")]
+    fn codec_args(&self) -> &[&str] {
+        match self {
+            TranscodeProfile::H265_4k => &["-c:v", "libx265", "-crf", "22", "-preset", "medium", "-tag:v", "hvc1", "-c:a", "aac", "-b:a", "192k"],
+            TranscodeProfile::H264_1080p => &["-c:v", "libx264", "-crf", "20", "-preset", "medium", "-pix_fmt", "yuv420p", "-c:a", "aac", "-b:a", "160k"],
+            TranscodeProfile::Av1 => &["-c:v", "libsvtav1", "-crf", "30", "-preset", "6", "-c:a", "libopus", "-b:a", "160k"],
+        }
+    }
+
+    /// Archive entry name for a transcode of `source_name`, e.g. `scene.h264-1080p.mp4`
+    pub fn output_name(&self, source_name: &str) -> String {
+        let stem = Path::new(source_name).file_stem().and_then(|s| s.to_str()).unwrap_or(source_name);
+        format!("{}.{}.{}", stem, self.get_name(), self.extension())
+    }
+
+    /// Paths are passed as they are, so files with names that aren't valid Unicode are found.
+    #[cfg(feature = "native")]
+    fn ffmpeg_args(&self, input: &Path, output: &Path) -> Vec<OsString> {
+        let mut args: Vec<OsString> = ["-hide_banner", "-loglevel", "error", "-y", "-i"].iter().map(OsString::from).collect();
+        args.push(input.into());
+        if let Some(height) = self.max_height() {
+            args.push("-vf".into());
+            args.push(format!("scale=-2:'min({},ih)'", height).into());
+        }
+
+        args.extend(self.codec_args().iter().map(OsString::from));
+        args.push(output.into());
+        args
+    }
+}
+
+impl std::fmt::Display for TranscodeProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.get_name())
+    }
+}
+
+/// A transcoded video and the stream properties ffprobe reported for it.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone)]
+pub struct TranscodedVideo {
+    pub profile: TranscodeProfile,
+    pub name: String,
+    pub path: PathBuf,
+    pub codec: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[cfg(feature = "native")]
+impl TranscodedVideo {
+    pub fn resolution(&self) -> String {
+        format!("{}x{}", self.width, self.height)
+    }
+}
+
+/// Temporary directory for transcoder output, removed when dropped.
+#[derive(Debug)]
+pub struct TranscodeWorkDir {
+    path: PathBuf,
+}
+
+impl TranscodeWorkDir {
+    pub fn new() -> Result<Self, std::io::Error> {
+        let path = std::env::temp_dir().join(format!("fsv-transcode-{}", std::process::id()));
+        std::fs::create_dir_all(&path)?;
+        Ok(TranscodeWorkDir { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TranscodeWorkDir {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_dir_all(&self.path) {
+            warn!(operation = "transcode", item = %self.path.display(), outcome = "cleanup_failed", error = %err, "Unable to remove transcode directory");
+        }
+    }
+}
+
+/// Transcode `input` with `profile` into `output_dir` using `ffmpeg`.
+/// Requires ffmpeg (with the profile's encoder) and ffprobe to be installed and on PATH.
+#[cfg(feature = "native")]
+pub fn transcode_video(input: &Path, source_name: &str, output_dir: &Path, profile: TranscodeProfile) -> Result<TranscodedVideo, TranscodeError> {
+    let name = profile.output_name(source_name);
+    let path = output_dir.join(&name);
+    info!(operation = "transcode", item = %input.display(), profile = %profile, outcome = "started", "Transcoding video");
+    let output = Command::new("ffmpeg").args(profile.ffmpeg_args(input, &path)).output()?;
+    if !output.status.success() {
+        return Err(TranscodeError::Ffmpeg(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+
+    let stream = probe_video_stream(&path)?;
+
+    Ok(TranscodedVideo { profile, name, path, codec: stream.codec_name, width: stream.width, height: stream.height })
+}
+
+#[cfg(feature = "native")]
+#[derive(Debug, Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<VideoStream>,
+}
+
+/// Properties of a video's first video stream.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Deserialize)]
+pub struct VideoStream {
+    pub codec_name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Probe the first video stream of `path` with ffprobe.
+#[cfg(feature = "native")]
+pub fn probe_video_stream(path: &Path) -> Result<VideoStream, TranscodeError> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v", "error",
+            "-select_streams", "v:0",
+            "-show_entries", "stream=codec_name,width,height",
+            "-of", "json",
+        ])
+        .arg(path)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(TranscodeError::Ffprobe(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+
+    let probe = serde_json::from_slice::<ProbeOutput>(&output.stdout)?;
+    probe.streams.into_iter().next().ok_or_else(|| TranscodeError::NoVideoStream(path.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcode_profile_args() {
+        assert_eq!(TranscodeProfile::H264_1080p.output_name("scene.mov"), "scene.h264-1080p.mp4");
+        assert_eq!(TranscodeProfile::Av1.output_name("scene"), "scene.av1.mkv");
+
+        let args = TranscodeProfile::H265_4k.ffmpeg_args(Path::new("in.mp4"), Path::new("out.mp4"));
+        assert!(args.windows(2).any(|w| w[0] == "-vf" && w[1] == "scale=-2:'min(2160,ih)'"));
+        assert_eq!(args.last().and_then(|arg| arg.to_str()), Some("out.mp4"));
+        assert!(!TranscodeProfile::Av1.ffmpeg_args(Path::new("in.mp4"), Path::new("out.mkv")).contains(&OsString::from("-vf")));
+    }
+}
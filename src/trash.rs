@@ -0,0 +1,185 @@
+use std::{ffi::OsString, fs::File, io::{Read, Seek, Write}, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zip::write::SimpleFileOptions;
+
+/// Removals kept per archive; older ones are dropped from the trash.
+pub const MAX_TRASH_SNAPSHOTS: usize = 10;
+const INDEX_ENTRY: &str = "trash.json";
+
+#[derive(Debug, Error)]
+pub enum TrashError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ZIP archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Serde json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+}
+
+/// One removal: metadata.json before and after it, and the archive entries it deleted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashSnapshot {
+    pub id: u64,
+    /// What was removed, e.g. `video scene.mp4`
+    pub description: String,
+    pub metadata_before: serde_json::Value,
+    pub metadata_after: serde_json::Value,
+    pub files: Vec<String>,
+}
+
+impl TrashSnapshot {
+    /// Name of a removed entry inside the trash archive.
+    fn entry_name(&self, file: &str) -> String {
+        format!("{}/{}", self.id, file)
+    }
+}
+
+/// Path of the trash for `archive_path`, e.g. `scene.fsv.trash`. The trash is a ZIP archive holding a `trash.json` index
+/// of snapshots and the removed entries under `<snapshot id>/<entry name>`.
+pub fn trash_path(archive_path: &Path) -> PathBuf {
+    let mut trash_path = OsString::from(archive_path.as_os_str());
+    trash_path.push(".trash");
+    PathBuf::from(trash_path)
+}
+
+fn open_trash(trash_path: &Path) -> Result<Option<zip::ZipArchive<File>>, TrashError> {
+    match File::open(trash_path) {
+        Ok(file) => Ok(Some(zip::ZipArchive::new(file)?)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn read_index<R: Read + Seek>(trash: &mut zip::ZipArchive<R>) -> Result<Vec<TrashSnapshot>, TrashError> {
+    let mut json = String::new();
+    trash.by_name(INDEX_ENTRY)?.read_to_string(&mut json)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Snapshots in the trash of `archive_path`, oldest first. Empty if there is no trash.
+pub fn list_snapshots(archive_path: &Path) -> Result<Vec<TrashSnapshot>, TrashError> {
+    match open_trash(&trash_path(archive_path))? {
+        Some(mut trash) => read_index(&mut trash),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Rewrite the trash with `snapshots`, copying their entries from the current trash and, for `added`, from `source`.
+/// The trash file is removed once no snapshots are left.
+fn write_trash<R: Read + Seek>(archive_path: &Path, snapshots: &[TrashSnapshot], mut added: Option<(&TrashSnapshot, &mut zip::ZipArchive<R>)>) -> Result<(), TrashError> {
+    let trash_path = trash_path(archive_path);
+    if snapshots.is_empty() {
+        if trash_path.exists() {
+            std::fs::remove_file(&trash_path)?;
+        }
+
+        return Ok(());
+    }
+
+    let mut current = open_trash(&trash_path)?;
+    let temp_path = trash_path.with_extension("trash.tmp");
+    let mut zip_writer = zip::ZipWriter::new(File::create(&temp_path)?);
+    zip_writer.start_file(INDEX_ENTRY, SimpleFileOptions::default())?;
+    zip_writer.write_all(serde_json::to_string_pretty(snapshots)?.as_bytes())?;
+    // Entries are copied raw, so removed videos aren't recompressed
+    for snapshot in snapshots {
+        for file in &snapshot.files {
+            let name = snapshot.entry_name(file);
+            match &mut added {
+                Some((new, source)) if new.id == snapshot.id => zip_writer.raw_copy_file_rename(source.by_name(file)?, name)?,
+                _ => match &mut current {
+                    Some(current) => zip_writer.raw_copy_file(current.by_name(&name)?)?,
+                    None => return Err(zip::result::ZipError::FileNotFound.into()),
+                },
+            }
+        }
+    }
+
+    zip_writer.finish()?.flush()?;
+    drop(current);
+    std::fs::rename(temp_path, trash_path)?;
+
+    Ok(())
+}
+
+/// Keep the entries `files` of `source` (the archive at `archive_path`, before the removal) and the metadata before and after
+/// the removal in the trash. Only the newest `MAX_TRASH_SNAPSHOTS` removals are kept. Names not in `source` are skipped.
+pub fn push_snapshot<R: Read + Seek>(archive_path: &Path, source: &mut zip::ZipArchive<R>, description: &str, metadata_before: serde_json::Value, metadata_after: serde_json::Value, files: &[&str]) -> Result<TrashSnapshot, TrashError> {
+    let mut snapshots = list_snapshots(archive_path)?;
+    let id = snapshots.last().map_or(1, |snapshot| snapshot.id + 1);
+    let files = files.iter().filter(|file| source.index_for_name(file).is_some()).map(|file| file.to_string()).collect();
+    let snapshot = TrashSnapshot { id, description: description.to_string(), metadata_before, metadata_after, files };
+    snapshots.push(snapshot.clone());
+    let evicted = snapshots.len().saturating_sub(MAX_TRASH_SNAPSHOTS);
+    snapshots.drain(..evicted);
+    write_trash(archive_path, &snapshots, Some((&snapshot, source)))?;
+
+    Ok(snapshot)
+}
+
+/// Write the entries removed in `snapshot` into `dir`. Returns (entry name, file path) pairs.
+pub fn extract_snapshot_files(archive_path: &Path, snapshot: &TrashSnapshot, dir: &Path) -> Result<Vec<(String, PathBuf)>, TrashError> {
+    let Some(mut trash) = open_trash(&trash_path(archive_path))? else {
+        return Err(zip::result::ZipError::FileNotFound.into());
+    };
+
+    let mut files = Vec::new();
+    for (i, file) in snapshot.files.iter().enumerate() {
+        // Named by position, entry names aren't necessarily valid file names
+        let path = dir.join(format!("trash-{}-{}", snapshot.id, i));
+        std::io::copy(&mut trash.by_name(&snapshot.entry_name(file))?, &mut File::create(&path)?)?;
+        files.push((file.clone(), path));
+    }
+
+    Ok(files)
+}
+
+/// Drop the newest snapshot from the trash, e.g. once it has been restored.
+pub fn pop_snapshot(archive_path: &Path) -> Result<Option<TrashSnapshot>, TrashError> {
+    let mut snapshots = list_snapshots(archive_path)?;
+    let popped = snapshots.pop();
+    if popped.is_some() {
+        write_trash::<File>(archive_path, &snapshots, None)?;
+    }
+
+    Ok(popped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trash_snapshots() {
+        let dir = std::env::temp_dir().join(format!("fsv-trash-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let archive_path = dir.join("scene.fsv");
+
+        let mut zip_writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip_writer.start_file("scene.srt", SimpleFileOptions::default()).unwrap();
+        zip_writer.write_all(b"1\n00:00:01,000 --> 00:00:02,000\nHi\n").unwrap();
+        let mut source = zip::ZipArchive::new(zip_writer.finish().unwrap()).unwrap();
+
+        for i in 0..MAX_TRASH_SNAPSHOTS + 2 {
+            let files: &[&str] = if i == 0 { &["scene.srt", "missing.srt"] } else { &[] };
+            push_snapshot(&archive_path, &mut source, &format!("removal {}", i), serde_json::json!({ "n": i }), serde_json::json!({ "n": i + 1 }), files).unwrap();
+        }
+
+        let snapshots = list_snapshots(&archive_path).unwrap();
+        assert_eq!(snapshots.len(), MAX_TRASH_SNAPSHOTS);
+        assert_eq!(snapshots[0].description, "removal 2");
+
+        let latest = push_snapshot(&archive_path, &mut source, "subtitle scene.srt", serde_json::json!({}), serde_json::json!({}), &["scene.srt", "missing.srt"]).unwrap();
+        assert_eq!(latest.files, ["scene.srt"]);
+        let files = extract_snapshot_files(&archive_path, &latest, &dir).unwrap();
+        assert_eq!(std::fs::read(&files[0].1).unwrap(), b"1\n00:00:01,000 --> 00:00:02,000\nHi\n");
+
+        assert_eq!(pop_snapshot(&archive_path).unwrap().unwrap().id, latest.id);
+        while pop_snapshot(&archive_path).unwrap().is_some() {}
+        assert!(!trash_path(&archive_path).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
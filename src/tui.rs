@@ -0,0 +1,389 @@
+use std::path::{Path, PathBuf};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Sparkline, Wrap},
+    DefaultTerminal, Frame,
+};
+use thiserror::Error;
+
+use crate::{duration::DurationMs, fsv::{self, EntryType, ItemType}, funscript::{Funscript, FunscriptStats}, metadata::FsvMetadata};
+
+const HEATMAP_BUCKETS: usize = 80;
+
+#[derive(Debug, Error)]
+pub enum TuiError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("No FSV files found at: {0}")]
+    NoFsvFiles(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Files,
+    Entries,
+}
+
+#[derive(Debug)]
+struct Entry {
+    item_type: ItemType,
+    name: String,
+    present: bool,
+}
+
+#[derive(Debug)]
+struct ScriptPreview {
+    stats: FunscriptStats,
+    heatmap: Vec<u64>,
+}
+
+struct App {
+    files: Vec<PathBuf>,
+    file_state: ListState,
+    entries: Vec<Entry>,
+    entry_state: ListState,
+    metadata: Option<FsvMetadata>,
+    preview: Option<ScriptPreview>,
+    focus: Focus,
+    status: String,
+    pending_remove: bool,
+    output_dir: PathBuf,
+}
+
+/// Open an interactive browser over a single FSV or every `.fsv` file in a directory.
+/// Extracted files are written to `output_dir`.
+pub fn browse(path: &Path, output_dir: &Path) -> Result<(), TuiError> {
+    let files = collect_fsv_files(path)?;
+    if files.is_empty() {
+        return Err(TuiError::NoFsvFiles(path.to_path_buf()));
+    }
+
+    let mut app = App {
+        files,
+        file_state: ListState::default(),
+        entries: Vec::new(),
+        entry_state: ListState::default(),
+        metadata: None,
+        preview: None,
+        focus: Focus::Files,
+        status: "q: quit | tab: switch pane | e: extract | d: remove entry | r: reload".to_string(),
+        pending_remove: false,
+        output_dir: output_dir.to_path_buf(),
+    };
+    app.file_state.select(Some(0));
+    app.load_selected_file();
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+
+    result
+}
+
+fn collect_fsv_files(path: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("fsv")) {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> Result<(), TuiError> {
+    loop {
+        terminal.draw(|frame| draw(frame, app))?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        if app.pending_remove {
+            app.pending_remove = false;
+            if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                app.remove_selected_entry();
+            }
+            else {
+                app.status = "Removal cancelled".to_string();
+            }
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Tab | KeyCode::Left | KeyCode::Right => app.toggle_focus(),
+            KeyCode::Up | KeyCode::Char('k') => app.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => app.move_selection(1),
+            KeyCode::Char('r') => app.load_selected_file(),
+            KeyCode::Char('e') => app.extract_selected_file(),
+            KeyCode::Char('d') => {
+                if let Some(entry) = app.selected_entry() {
+                    app.status = format!("Remove {} '{}' from the archive? (y/n)", entry.item_type.get_name_lower(), entry.name);
+                    app.pending_remove = true;
+                }
+            },
+            _ => (),
+        }
+    }
+}
+
+impl App {
+    fn selected_file(&self) -> Option<&PathBuf> {
+        self.file_state.selected().and_then(|i| self.files.get(i))
+    }
+
+    fn selected_entry(&self) -> Option<&Entry> {
+        if self.focus != Focus::Entries {
+            return None;
+        }
+
+        self.entry_state.selected().and_then(|i| self.entries.get(i))
+    }
+
+    fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Focus::Files if !self.entries.is_empty() => Focus::Entries,
+            _ => Focus::Files,
+        };
+        self.load_preview();
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let (state, len) = match self.focus {
+            Focus::Files => (&mut self.file_state, self.files.len()),
+            Focus::Entries => (&mut self.entry_state, self.entries.len()),
+        };
+
+        if len == 0 {
+            return;
+        }
+
+        let current = state.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, len as isize - 1) as usize;
+        state.select(Some(next));
+
+        match self.focus {
+            Focus::Files => self.load_selected_file(),
+            Focus::Entries => self.load_preview(),
+        }
+    }
+
+    fn load_selected_file(&mut self) {
+        self.entries.clear();
+        self.metadata = None;
+        self.preview = None;
+        let Some(path) = self.selected_file().cloned() else {
+            return;
+        };
+
+        match fsv::get_fsv_info(&path) {
+            Ok(info) => {
+                let items = [(ItemType::Video, info.videos), (ItemType::Script, info.scripts), (ItemType::Subtitle, info.subtitles)];
+                for (item_type, list) in items {
+                    for (name, present) in list {
+                        self.entries.push(Entry { item_type, name, present });
+                    }
+                }
+            },
+            Err(err) => self.status = format!("Error reading '{}': {}", path.display(), err),
+        }
+
+        match fsv::read_fsv_metadata(&path) {
+            Ok(metadata) => self.metadata = Some(metadata),
+            Err(err) => self.status = format!("Error reading metadata of '{}': {}", path.display(), err),
+        }
+
+        self.entry_state.select(if self.entries.is_empty() { None } else { Some(0) });
+        if self.entries.is_empty() {
+            self.focus = Focus::Files;
+        }
+    }
+
+    fn load_preview(&mut self) {
+        self.preview = None;
+        let Some(entry) = self.selected_entry() else {
+            return;
+        };
+
+        if !matches!(entry.item_type, ItemType::Script) || !entry.present {
+            return;
+        }
+
+        let name = entry.name.clone();
+        let Some(path) = self.selected_file() else {
+            return;
+        };
+
+        let result = fsv::read_fsv_entry(path, &name)
+            .map_err(|err| err.to_string())
+            .and_then(|data| serde_json::from_slice::<Funscript>(&data).map_err(|err| err.to_string()));
+        match result {
+            Ok(funscript) => {
+                let heatmap = funscript.speed_heatmap(HEATMAP_BUCKETS).iter().map(|speed| speed.round() as u64).collect();
+                self.preview = Some(ScriptPreview { stats: funscript.stats(), heatmap });
+            },
+            Err(err) => self.status = format!("Unable to parse script '{}': {}", name, err),
+        }
+    }
+
+    fn extract_selected_file(&mut self) {
+        let Some(path) = self.selected_file() else {
+            return;
+        };
+
+        self.status = match fsv::extract_fsv(path, &self.output_dir, &fsv::ExtractOptions::default()) {
+            Ok(_) => format!("Extracted '{}' to '{}'", path.display(), self.output_dir.display()),
+            Err(err) => format!("Error extracting '{}': {}", path.display(), err),
+        };
+    }
+
+    fn remove_selected_entry(&mut self) {
+        let (Some(path), Some(entry)) = (self.selected_file().cloned(), self.selected_entry()) else {
+            return;
+        };
+
+        let entry_type = match entry.item_type {
+            ItemType::Video => EntryType::Video,
+            ItemType::Script => EntryType::Script,
+            ItemType::Subtitle => EntryType::Subtitle,
+        };
+
+        let name = entry.name.clone();
+        let result = fsv::remove_from_fsv(&path, entry_type, &name);
+        self.load_selected_file();
+        self.status = match result {
+            Ok(_) => format!("Removed '{}'", name),
+            Err(err) => format!("Error removing '{}': {}", name, err),
+        };
+    }
+}
+
+fn pane_block(title: &str, focused: bool) -> Block<'_> {
+    let style = if focused { Style::default().fg(Color::Yellow) } else { Style::default() };
+    Block::default().borders(Borders::ALL).title(title).border_style(style)
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1)])
+        .split(frame.area());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(30), Constraint::Percentage(40)])
+        .split(rows[0]);
+
+    let highlight = Style::default().add_modifier(Modifier::REVERSED);
+
+    let files: Vec<ListItem> = app.files.iter()
+        .map(|f| ListItem::new(f.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()))
+        .collect();
+    let files = List::new(files).block(pane_block("Files", app.focus == Focus::Files)).highlight_style(highlight);
+    frame.render_stateful_widget(files, columns[0], &mut app.file_state);
+
+    let entries: Vec<ListItem> = app.entries.iter()
+        .map(|e| {
+            let style = if e.present { Style::default() } else { Style::default().fg(Color::Red) };
+            ListItem::new(format!("[{}] {}{}", e.item_type.get_name_lower(), e.name, if e.present { "" } else { " (missing)" })).style(style)
+        })
+        .collect();
+    let entries = List::new(entries).block(pane_block("Entries", app.focus == Focus::Entries)).highlight_style(highlight);
+    frame.render_stateful_widget(entries, columns[1], &mut app.entry_state);
+
+    let details_constraints = if app.preview.is_some() {
+        vec![Constraint::Min(3), Constraint::Length(8)]
+    }
+    else {
+        vec![Constraint::Min(3)]
+    };
+    let details = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(details_constraints)
+        .split(columns[2]);
+
+    frame.render_widget(Paragraph::new(metadata_lines(app)).wrap(Wrap { trim: false }).block(pane_block("Metadata", false)), details[0]);
+
+    if let Some(preview) = &app.preview {
+        let title = format!(
+            "Script: {} actions, {:.1}s, avg {:.0}/s, max {:.0}/s",
+            preview.stats.action_count,
+            preview.stats.duration.as_secs_f64(),
+            preview.stats.average_speed,
+            preview.stats.max_speed,
+        );
+        let sparkline = Sparkline::default()
+            .block(pane_block(&title, false))
+            .data(&preview.heatmap)
+            .style(Style::default().fg(Color::Magenta));
+        frame.render_widget(sparkline, details[1]);
+    }
+
+    frame.render_widget(Paragraph::new(app.status.as_str()), rows[1]);
+}
+
+fn metadata_lines(app: &App) -> Vec<Line<'static>> {
+    let Some(metadata) = &app.metadata else {
+        return vec![Line::from("No metadata")];
+    };
+
+    let mut lines = vec![
+        Line::from(format!("Title: {}", metadata.title)),
+        Line::from(format!("Format version: {}", metadata.format_version)),
+        Line::from(format!("Tags: {}", metadata.tags.join(", "))),
+    ];
+    if !metadata.performers.is_empty() {
+        lines.push(Line::from(format!("Performers: {}", metadata.performers.join(", "))));
+    }
+    if !metadata.studio.is_empty() {
+        lines.push(Line::from(format!("Studio: {}", metadata.studio)));
+    }
+
+    let creator_groups = [("Video", &metadata.creators.videos), ("Script", &metadata.creators.scripts), ("Subtitle", &metadata.creators.subtitles)];
+    for (label, creators) in creator_groups {
+        for creator in creators {
+            lines.push(Line::from(format!("{} creator: {} ({})", label, creator.creator_info.name, creator.work_name)));
+        }
+    }
+
+    if let Some(entry) = app.selected_entry() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(format!("Selected {}: {}", entry.item_type.get_name_lower(), entry.name)));
+        let description = match entry.item_type {
+            ItemType::Video => metadata.video_formats.iter().find(|v| v.name == entry.name).map(|v| (v.description.clone(), v.duration, v.checksum.clone())),
+            ItemType::Script => metadata.script_variants.iter().find(|s| s.name == entry.name).map(|s| (s.description.clone(), s.duration, s.checksum.clone())),
+            ItemType::Subtitle => metadata.subtitle_tracks.iter().find(|s| s.name == entry.name).map(|s| (s.description.clone(), DurationMs::ZERO, s.checksum.clone())),
+        };
+
+        if let Some((description, duration, checksum)) = description {
+            if !description.is_empty() {
+                lines.push(Line::from(format!("Description: {}", description)));
+            }
+
+            if !duration.is_zero() {
+                lines.push(Line::from(format!("Duration: {:.1}s", duration.as_secs_f64())));
+            }
+
+            if !checksum.is_empty() {
+                lines.push(Line::from(format!("Checksum: {}", checksum)));
+            }
+        }
+    }
+
+    lines
+}
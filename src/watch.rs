@@ -0,0 +1,157 @@
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use thiserror::Error;
+use tracing::{Instrument, error, info, info_span, warn};
+
+use crate::{db_client::DbClient, file_util, fsv::FsvCreateError, hooks::{HookContext, HookOperation, HooksConfig}, import::{self, ImportCandidate, ImportError}, journal::{self, JournalError}, policy::{ContentPolicy, PolicyError}, progress::{NoProgress, ProgressEvent, ProgressListener}};
+
+/// Subdirectory of the archive folder receiving the originals of imports refused by the content policy
+pub const REJECTED_DIR_NAME: &str = "rejected";
+
+#[derive(Debug, Error)]
+pub enum WatchError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Journal error: {0}")]
+    Journal(#[from] JournalError),
+    #[error("Watch folder does not exist: {0}")]
+    DropDirNotFound(PathBuf),
+}
+
+#[derive(Debug)]
+pub struct WatchArgs {
+    pub drop_dir: PathBuf,
+    pub output_dir: PathBuf,
+    pub archive_dir: PathBuf,
+    pub interval: Duration,
+    pub once: bool,
+    /// Screening of every import; originals of refused imports are moved into `<archive_dir>/rejected`
+    pub content_policy: Option<ContentPolicy>,
+    /// Run around every import; an import a `pre` hook refuses is retried on the next poll
+    pub hooks: HooksConfig,
+}
+
+impl WatchArgs {
+    pub fn new(drop_dir: PathBuf, output_dir: PathBuf, archive_dir: PathBuf, interval: Duration, once: bool) -> Self {
+        WatchArgs { drop_dir, output_dir, archive_dir, interval, once, content_policy: None, hooks: HooksConfig::default() }
+    }
+
+    pub fn content_policy(mut self, content_policy: Option<ContentPolicy>) -> Self {
+        self.content_policy = content_policy;
+        self
+    }
+
+    pub fn hooks(mut self, hooks: HooksConfig) -> Self {
+        self.hooks = hooks;
+        self
+    }
+}
+
+/// Poll `drop_dir` for video+script pairs, import each pair into an FSV in `output_dir`, and move the originals into `archive_dir`.
+/// Files are only imported once their sizes are unchanged between two polls, so partially copied files are not picked up.
+/// With `once`, a single pass is made over the folder without the stability check. Otherwise runs until Ctrl+C.
+pub async fn watch_folder(args: WatchArgs, db_client: &DbClient) -> Result<(), WatchError> {
+    watch_folder_with_progress(args, db_client, &NoProgress).await
+}
+
+/// Same as `watch_folder`, reporting an `import` started/completed/failed event per candidate to `progress`.
+pub async fn watch_folder_with_progress(args: WatchArgs, db_client: &DbClient, progress: &dyn ProgressListener) -> Result<(), WatchError> {
+    if !args.drop_dir.is_dir() {
+        return Err(WatchError::DropDirNotFound(args.drop_dir));
+    }
+
+    std::fs::create_dir_all(&args.output_dir)?;
+    std::fs::create_dir_all(&args.archive_dir)?;
+    // Finish whatever a previous run was in the middle of before picking up new files
+    for recovery in journal::recover_dir(&args.output_dir)? {
+        info!(operation = recovery.record.operation.get_name(), archive = %recovery.record.target.display(), outcome = ?recovery.outcome, "Recovered interrupted operation");
+    }
+
+    info!(operation = "watch", outcome = "started", drop_dir = %args.drop_dir.display(), output_dir = %args.output_dir.display(), archive_dir = %args.archive_dir.display(), "Watching folder");
+
+    let mut last_sizes: HashMap<PathBuf, u64> = HashMap::new();
+    loop {
+        let candidates = import::find_import_candidates(&args.drop_dir)?;
+        let mut sizes = HashMap::new();
+        for candidate in candidates {
+            if !args.once && !is_stable(&candidate, &last_sizes, &mut sizes) {
+                continue;
+            }
+
+            process_candidate(&candidate, &args, db_client, progress).await;
+        }
+
+        if args.once {
+            break;
+        }
+
+        last_sizes = sizes;
+        tokio::select! {
+            _ = tokio::time::sleep(args.interval) => (),
+            _ = tokio::signal::ctrl_c() => {
+                info!(operation = "watch", outcome = "stopped", "Stopping watch");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn is_stable(candidate: &ImportCandidate, last_sizes: &HashMap<PathBuf, u64>, sizes: &mut HashMap<PathBuf, u64>) -> bool {
+    let mut stable = true;
+    for file in candidate.files() {
+        let size = match std::fs::metadata(file) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return false,
+        };
+
+        if last_sizes.get(file) != Some(&size) {
+            stable = false;
+        }
+
+        sizes.insert(file.clone(), size);
+    }
+
+    stable
+}
+
+async fn process_candidate(candidate: &ImportCandidate, args: &WatchArgs, db_client: &DbClient, progress: &dyn ProgressListener) {
+    let fsv_path = args.output_dir.join(format!("{}.fsv", candidate.stem));
+    if fsv_path.exists() {
+        warn!(operation = "import", archive = %fsv_path.display(), item = %candidate.stem, outcome = "skipped", "FSV already exists, leaving originals in place");
+        return;
+    }
+
+    let target = fsv_path.display().to_string();
+    progress.on_event(ProgressEvent::started("import", &target));
+    let details = serde_json::json!({ "video": candidate.video, "script": candidate.script, "axis_scripts": candidate.axis_scripts });
+    let context = HookContext::new(HookOperation::Import, &fsv_path, details);
+    if let Err(err) = args.hooks.run_pre(&context) {
+        warn!(operation = "import", archive = %target, item = %candidate.stem, outcome = "refused", error = %err, "Import refused by a hook");
+        progress.on_event(ProgressEvent::failed("import", &target, &err));
+        return;
+    }
+
+    let span = info_span!("import", archive = %target, item = %candidate.stem);
+    let result = import::import_candidate_with_archive(candidate, &args.output_dir, Some(&args.archive_dir), args.content_policy.as_ref(), db_client).instrument(span).await;
+    args.hooks.run_post(&context, result.as_ref().err().map(ToString::to_string).as_deref());
+    match result {
+        Ok(_) => progress.on_event(ProgressEvent::completed("import", &target)),
+        Err(err @ ImportError::Create(FsvCreateError::Policy(PolicyError::Rejected(_)))) => {
+            warn!(operation = "import", archive = %target, item = %candidate.stem, outcome = "rejected", error = %err, "Import refused by the content policy");
+            // Otherwise the same files would be refused again on every poll
+            let rejected_dir = args.archive_dir.join(REJECTED_DIR_NAME);
+            for file in candidate.files() {
+                if let Err(err) = std::fs::create_dir_all(&rejected_dir).and_then(|_| file_util::move_into_dir(file, &rejected_dir)) {
+                    error!(operation = "archive_original", item = %file.display(), outcome = "failed", error = %err, "Failed to move rejected original");
+                }
+            }
+            progress.on_event(ProgressEvent::failed("import", &target, &err));
+        },
+        Err(err) => {
+            error!(operation = "import", archive = %target, item = %candidate.stem, outcome = "failed", error = %err, "Failed to import");
+            progress.on_event(ProgressEvent::failed("import", &target, &err));
+        },
+    }
+}